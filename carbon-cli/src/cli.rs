@@ -0,0 +1,213 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Command line client for Carbon Cache servers
+#[derive(Debug, Parser)]
+#[command(name = "carbon", version)]
+pub struct Cli {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
+    /// How results are printed
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Where to connect, each flag overrides the selected profile
+#[derive(Debug, Args)]
+pub struct ConnectionArgs {
+    /// Profile from the profiles file (CARBON_CLI_CONFIG, default ~/.config/carbon/profiles.toml)
+    #[arg(long, global = true, env = "CARBON_PROFILE", default_value = "default")]
+    pub profile: String,
+
+    /// HTTP endpoint, e.g. http://localhost:8080
+    #[arg(long, global = true, env = "CARBON_URL")]
+    pub url: Option<String>,
+
+    /// TCP endpoint, e.g. localhost:5500
+    #[arg(long, global = true, env = "CARBON_TCP_ADDR")]
+    pub tcp_addr: Option<String>,
+
+    /// Protocol for data commands, admin commands always use HTTP
+    #[arg(long, global = true, value_enum)]
+    pub transport: Option<Transport>,
+
+    #[arg(long, global = true, env = "CARBON_USERNAME")]
+    pub username: Option<String>,
+
+    #[arg(long, global = true, env = "CARBON_PASSWORD", hide_env_values = true)]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Http,
+    Tcp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Create, inspect and drop caches
+    Caches {
+        #[command(subcommand)]
+        command: CachesCommand,
+    },
+    /// Manage users
+    Users {
+        #[command(subcommand)]
+        command: UsersCommand,
+    },
+    /// Store a value
+    Put {
+        cache: String,
+        key: String,
+        value: String,
+    },
+    /// Read a value
+    Get { cache: String, key: String },
+    /// Delete a value
+    Delete { cache: String, key: String },
+    /// Write entries to a JSON file, keys must be listed as the server cannot enumerate them
+    Export {
+        cache: String,
+        #[arg(required = true)]
+        keys: Vec<String>,
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Store the entries of a file written by export
+    Import { cache: String, file: PathBuf },
+    /// Generate Rust structs and a typed client from the schema of a cache's indexes
+    Codegen {
+        cache: String,
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CachesCommand {
+    List {
+        /// Only caches whose name starts with this
+        #[arg(long)]
+        prefix: Option<String>,
+    },
+    Describe {
+        name: String,
+    },
+    Create {
+        name: String,
+        /// Storage backend: ttl, size, storage or redis
+        #[arg(long, default_value = "ttl")]
+        eviction: String,
+        /// redis:// URL of the server holding a redis cache
+        #[arg(long)]
+        remote_url: Option<String>,
+        #[arg(long)]
+        mem_bytes: Option<u64>,
+        /// Eviction policy: lru, tinylfu, sieve, lfu or arc
+        #[arg(long)]
+        policy: Option<String>,
+        #[arg(long)]
+        default_ttl_ms: Option<u64>,
+        /// Shorten TTLs by a random share of up to this percentage, ttl and redis caches only
+        #[arg(long)]
+        ttl_jitter_pct: Option<u8>,
+        /// Keep up to this many hot entries in process in front of the backend, not for ttl caches
+        #[arg(long)]
+        l1_entries: Option<u64>,
+        /// How long the in-process tier serves an entry before asking the backend again
+        #[arg(long, requires = "l1_entries")]
+        l1_ttl_ms: Option<u64>,
+        /// Drop time series samples this much older than the newest sample of their series
+        #[arg(long)]
+        timeseries_retention_ms: Option<u64>,
+        /// Keep at most this many samples per time series, dropping the oldest
+        #[arg(long)]
+        timeseries_max_samples: Option<u64>,
+        /// Tag as key=value, repeatable
+        #[arg(long = "tag", value_parser = parse_tag)]
+        tags: Vec<(String, String)>,
+    },
+    Drop {
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum UsersCommand {
+    List,
+    Create {
+        username: String,
+        #[arg(long)]
+        password: String,
+        /// Role to assign, repeatable
+        #[arg(long = "role")]
+        roles: Vec<String>,
+    },
+    Delete {
+        username: String,
+    },
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), String> {
+    tag.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("tag '{}' is not key=value", tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_caches_create() {
+        let cli = Cli::parse_from([
+            "carbon",
+            "caches",
+            "create",
+            "users",
+            "--mem-bytes",
+            "1024",
+            "--tag",
+            "team=a",
+        ]);
+        match cli.command {
+            Command::Caches {
+                command:
+                    CachesCommand::Create {
+                        name,
+                        eviction,
+                        mem_bytes,
+                        tags,
+                        ..
+                    },
+            } => {
+                assert_eq!(name, "users");
+                assert_eq!(eviction, "ttl");
+                assert_eq!(mem_bytes, Some(1024));
+                assert_eq!(tags, vec![("team".to_string(), "a".to_string())]);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::cli::Transport;
+use crate::error::{CliError, Result};
+use crate::http::HttpClient;
+use crate::profile::Profile;
+use crate::tcp::TcpClient;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use server_tcp::{Request, Response};
+
+/// Client for the data commands, over the profile's transport
+pub enum DataClient {
+    Http(HttpClient),
+    Tcp(TcpClient),
+}
+
+impl DataClient {
+    pub async fn connect(profile: &Profile) -> Result<Self> {
+        match profile.transport() {
+            Transport::Http => Ok(DataClient::Http(HttpClient::new(
+                profile.http_url(),
+                profile.username.clone(),
+                profile.password.clone(),
+            )?)),
+            Transport::Tcp => Ok(DataClient::Tcp(
+                TcpClient::connect(profile.tcp_addr()).await?,
+            )),
+        }
+    }
+
+    pub async fn put(&mut self, cache_name: &str, key: &str, value: Bytes) -> Result<()> {
+        match self {
+            DataClient::Http(client) => {
+                let entry = ExportedEntry::new(key, &value);
+                let body = json!({ "value": entry.value, "encoding": entry.encoding });
+                client.put(&["cache", cache_name, key], &body).await?;
+                Ok(())
+            }
+            DataClient::Tcp(client) => {
+                let request = Request::Put {
+                    cache_name: cache_name.to_string(),
+                    key: Bytes::copy_from_slice(key.as_bytes()),
+                    value,
+                };
+                match client.call(request).await? {
+                    Response::Ok => Ok(()),
+                    other => Err(unexpected(other)),
+                }
+            }
+        }
+    }
+
+    pub async fn get(&mut self, cache_name: &str, key: &str) -> Result<Option<Bytes>> {
+        match self {
+            DataClient::Http(client) => {
+                let response = client.get(&["cache", cache_name, key]).await?;
+                if response["found"] != Value::Bool(true) {
+                    return Ok(None);
+                }
+                // Empty values are left out of the response
+                let value = response["value"].as_str().unwrap_or_default().to_string();
+                let encoding = match response["encoding"].as_str() {
+                    Some("base64") => Encoding::Base64,
+                    _ => Encoding::Utf8,
+                };
+                let entry = ExportedEntry {
+                    key: key.to_string(),
+                    value,
+                    encoding,
+                };
+                entry.into_value().map(Some)
+            }
+            DataClient::Tcp(client) => {
+                let request = Request::Get {
+                    cache_name: cache_name.to_string(),
+                    key: Bytes::copy_from_slice(key.as_bytes()),
+                };
+                match client.call(request).await? {
+                    Response::Value { value } => Ok(Some(value)),
+                    Response::NotFound => Ok(None),
+                    other => Err(unexpected(other)),
+                }
+            }
+        }
+    }
+
+    pub async fn delete(&mut self, cache_name: &str, key: &str) -> Result<bool> {
+        match self {
+            DataClient::Http(client) => {
+                let response = client.delete(&["cache", cache_name, key]).await?;
+                Ok(response["deleted"] == Value::Bool(true))
+            }
+            DataClient::Tcp(client) => {
+                let request = Request::Delete {
+                    cache_name: cache_name.to_string(),
+                    key: Bytes::copy_from_slice(key.as_bytes()),
+                };
+                match client.call(request).await? {
+                    Response::Ok => Ok(true),
+                    Response::NotFound => Ok(false),
+                    other => Err(unexpected(other)),
+                }
+            }
+        }
+    }
+}
+
+fn unexpected(response: Response) -> CliError {
+    CliError::Protocol(format!("unexpected response {:?}", response))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Utf8,
+    Base64,
+}
+
+/// One entry of an export file, values that are not UTF-8 are base64 encoded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedEntry {
+    pub key: String,
+    pub value: String,
+    pub encoding: Encoding,
+}
+
+impl ExportedEntry {
+    pub fn new(key: &str, value: &[u8]) -> Self {
+        let (value, encoding) = match std::str::from_utf8(value) {
+            Ok(text) => (text.to_string(), Encoding::Utf8),
+            Err(_) => (STANDARD.encode(value), Encoding::Base64),
+        };
+        Self {
+            key: key.to_string(),
+            value,
+            encoding,
+        }
+    }
+
+    pub fn into_value(self) -> Result<Bytes> {
+        match self.encoding {
+            Encoding::Utf8 => Ok(Bytes::from(self.value)),
+            Encoding::Base64 => STANDARD.decode(self.value).map(Bytes::from).map_err(|e| {
+                CliError::InvalidArgument(format!("value of '{}' is not base64: {}", self.key, e))
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exported_entry_round_trip() {
+        let text = ExportedEntry::new("greeting", b"hello");
+        assert_eq!(text.encoding, Encoding::Utf8);
+        assert_eq!(text.clone().into_value().unwrap(), Bytes::from("hello"));
+
+        let binary = ExportedEntry::new("blob", &[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(binary.encoding, Encoding::Base64);
+        assert_eq!(binary.value, "3q2+7w==");
+        assert_eq!(
+            binary.into_value().unwrap(),
+            Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef])
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("profile '{name}' not found in {path}")]
+    ProfileNotFound { name: String, path: String },
+
+    #[error("invalid profiles file {path}: {message}")]
+    InvalidProfiles { path: String, message: String },
+
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("server answered {status}: {body}")]
+    Server { status: u16, body: String },
+
+    #[error("command failed: {0}")]
+    Command(String),
+
+    #[error("protocol error: {0}")]
+    Protocol(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, CliError>;
@@ -0,0 +1,89 @@
+use crate::error::{CliError, Result};
+use reqwest::{Client, Method, RequestBuilder, Url};
+use serde_json::Value;
+
+/// JSON client for the HTTP API, authenticating every request with Basic auth when a
+/// username is configured
+pub struct HttpClient {
+    client: Client,
+    base_url: Url,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl HttpClient {
+    pub fn new(base_url: &str, username: Option<String>, password: Option<String>) -> Result<Self> {
+        let base_url = Url::parse(base_url)
+            .map_err(|e| CliError::InvalidArgument(format!("URL '{}': {}", base_url, e)))?;
+        Ok(Self {
+            client: Client::new(),
+            base_url,
+            username,
+            password,
+        })
+    }
+
+    pub async fn get(&self, path: &[&str]) -> Result<Value> {
+        self.send(self.request(Method::GET, path, &[])?).await
+    }
+
+    pub async fn get_with_query(&self, path: &[&str], query: &[(&str, &str)]) -> Result<Value> {
+        self.send(self.request(Method::GET, path, query)?).await
+    }
+
+    pub async fn post(&self, path: &[&str], body: &Value) -> Result<Value> {
+        self.send(self.request(Method::POST, path, &[])?.json(body))
+            .await
+    }
+
+    pub async fn put(&self, path: &[&str], body: &Value) -> Result<Value> {
+        self.send(self.request(Method::PUT, path, &[])?.json(body))
+            .await
+    }
+
+    pub async fn delete(&self, path: &[&str]) -> Result<Value> {
+        self.send(self.request(Method::DELETE, path, &[])?).await
+    }
+
+    /// Segments are percent-encoded, so keys may contain '/' and other reserved characters
+    fn request(
+        &self,
+        method: Method,
+        path: &[&str],
+        query: &[(&str, &str)],
+    ) -> Result<RequestBuilder> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| {
+                CliError::InvalidArgument(format!("URL '{}' cannot have a path", self.base_url))
+            })?
+            .pop_if_empty()
+            .extend(path);
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+
+        let request = self.client.request(method, url);
+        Ok(match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_ref()),
+            None => request,
+        })
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Value> {
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            return Err(CliError::Server {
+                status: status.as_u16(),
+                body,
+            });
+        }
+        if body.is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+}
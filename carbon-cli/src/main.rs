@@ -0,0 +1,227 @@
+mod cli;
+mod data;
+mod error;
+mod http;
+mod output;
+mod profile;
+mod tcp;
+
+use bytes::Bytes;
+use carbon_query::{CacheSchema, codegen};
+use clap::Parser;
+use cli::{CachesCommand, Cli, Command, OutputFormat, UsersCommand};
+use data::{DataClient, ExportedEntry};
+use error::Result;
+use http::HttpClient;
+use profile::Profile;
+use serde_json::{Value, json};
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    if let Err(e) = run(cli).await {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let profile = Profile::resolve(&cli.connection)?;
+    let output = cli.output;
+
+    match cli.command {
+        Command::Caches { command } => caches(&admin_client(&profile)?, command, output).await,
+        Command::Users { command } => users(&admin_client(&profile)?, command, output).await,
+        Command::Put { cache, key, value } => {
+            let mut client = DataClient::connect(&profile).await?;
+            client.put(&cache, &key, Bytes::from(value)).await?;
+            output::print_fields(&json!({ "ok": true }), output);
+            Ok(())
+        }
+        Command::Get { cache, key } => {
+            let mut client = DataClient::connect(&profile).await?;
+            let result = match client.get(&cache, &key).await? {
+                Some(value) => json!(ExportedEntry::new(&key, &value)),
+                None => json!({ "key": key, "found": false }),
+            };
+            output::print_fields(&result, output);
+            Ok(())
+        }
+        Command::Delete { cache, key } => {
+            let mut client = DataClient::connect(&profile).await?;
+            let deleted = client.delete(&cache, &key).await?;
+            output::print_fields(&json!({ "deleted": deleted }), output);
+            Ok(())
+        }
+        Command::Export { cache, keys, file } => {
+            let mut client = DataClient::connect(&profile).await?;
+            let mut entries = Vec::new();
+            for key in &keys {
+                if let Some(value) = client.get(&cache, key).await? {
+                    entries.push(ExportedEntry::new(key, &value));
+                }
+            }
+
+            let contents = serde_json::to_string_pretty(&entries)?;
+            match file {
+                Some(path) => {
+                    std::fs::write(&path, contents)?;
+                    eprintln!(
+                        "exported {} of {} keys to {}",
+                        entries.len(),
+                        keys.len(),
+                        path.display()
+                    );
+                }
+                None => println!("{}", contents),
+            }
+            Ok(())
+        }
+        Command::Import { cache, file } => {
+            let entries: Vec<ExportedEntry> =
+                serde_json::from_str(&std::fs::read_to_string(&file)?)?;
+            let mut client = DataClient::connect(&profile).await?;
+            let imported = entries.len();
+            for entry in entries {
+                let key = entry.key.clone();
+                client.put(&cache, &key, entry.into_value()?).await?;
+            }
+            output::print_fields(&json!({ "imported": imported }), output);
+            Ok(())
+        }
+        Command::Codegen { cache, file } => {
+            let schema: CacheSchema = serde_json::from_value(
+                admin_client(&profile)?
+                    .get(&["admin", "caches", &cache, "schema"])
+                    .await?,
+            )?;
+            let code = codegen::rust_client(&schema);
+            match file {
+                Some(path) => {
+                    std::fs::write(&path, code)?;
+                    eprintln!(
+                        "wrote {} fields of cache '{}' to {}",
+                        schema.fields.len(),
+                        cache,
+                        path.display()
+                    );
+                }
+                None => print!("{}", code),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The admin API is only served over HTTP, whatever the profile's transport
+fn admin_client(profile: &Profile) -> Result<HttpClient> {
+    HttpClient::new(
+        profile.http_url(),
+        profile.username.clone(),
+        profile.password.clone(),
+    )
+}
+
+async fn caches(client: &HttpClient, command: CachesCommand, output: OutputFormat) -> Result<()> {
+    match command {
+        CachesCommand::List { prefix } => {
+            let query: Vec<(&str, &str)> = prefix
+                .as_deref()
+                .map(|prefix| ("name_prefix", prefix))
+                .into_iter()
+                .collect();
+            let caches = client.get_with_query(&["admin", "caches"], &query).await?;
+            output::print_rows(
+                &caches,
+                output,
+                "caches",
+                &[
+                    "config.name",
+                    "config.eviction",
+                    "config.policy",
+                    "keys_estimate",
+                    "size_estimate",
+                ],
+            );
+        }
+        CachesCommand::Describe { name } => {
+            let cache = client.get(&["admin", "caches", &name]).await?;
+            output::print_fields(&cache, output);
+        }
+        CachesCommand::Create {
+            name,
+            eviction,
+            mem_bytes,
+            policy,
+            default_ttl_ms,
+            ttl_jitter_pct,
+            l1_entries,
+            l1_ttl_ms,
+            timeseries_retention_ms,
+            timeseries_max_samples,
+            remote_url,
+            tags,
+        } => {
+            let mut body = json!({
+                "name": name,
+                "eviction": eviction,
+                "mem_bytes": mem_bytes,
+                "default_ttl_ms": default_ttl_ms,
+                "ttl_jitter_pct": ttl_jitter_pct,
+                "l1_entries": l1_entries,
+                "l1_ttl_ms": l1_ttl_ms,
+                "timeseries_retention_ms": timeseries_retention_ms,
+                "timeseries_max_samples": timeseries_max_samples,
+                "policy": policy.unwrap_or_default(),
+                "remote_url": remote_url,
+            });
+            if !tags.is_empty() {
+                body["tags"] = Value::Object(
+                    tags.into_iter()
+                        .map(|(key, value)| (key, Value::String(value)))
+                        .collect(),
+                );
+            }
+            let created = client.post(&["admin", "caches"], &body).await?;
+            output::print_fields(&created, output);
+        }
+        CachesCommand::Drop { name } => {
+            let dropped = client.delete(&["admin", "caches", &name]).await?;
+            output::print_fields(&dropped, output);
+        }
+    }
+    Ok(())
+}
+
+async fn users(client: &HttpClient, command: UsersCommand, output: OutputFormat) -> Result<()> {
+    match command {
+        UsersCommand::List => {
+            let users = client.get(&["admin", "users"]).await?;
+            output::print_rows(
+                &users,
+                output,
+                "users",
+                &["username", "role_ids", "created_at"],
+            );
+        }
+        UsersCommand::Create {
+            username,
+            password,
+            roles,
+        } => {
+            let body = json!({
+                "username": username,
+                "password": password,
+                "role_ids": roles,
+            });
+            let created = client.post(&["admin", "users"], &body).await?;
+            output::print_fields(&created, output);
+        }
+        UsersCommand::Delete { username } => {
+            let deleted = client.delete(&["admin", "users", &username]).await?;
+            output::print_fields(&deleted, output);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use crate::cli::OutputFormat;
+use serde_json::Value;
+
+/// Print the array at `rows_path` (dotted, empty for `value` itself) with one column per path
+pub fn print_rows(value: &Value, format: OutputFormat, rows_path: &str, columns: &[&str]) {
+    match format {
+        OutputFormat::Json => print_json(value),
+        OutputFormat::Table => {
+            let rows = lookup(value, rows_path)
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            print!("{}", render_table(rows, columns));
+        }
+    }
+}
+
+/// Print an object one field per line, nested values as compact JSON
+pub fn print_fields(value: &Value, format: OutputFormat) {
+    match format {
+        OutputFormat::Json => print_json(value),
+        OutputFormat::Table => match value.as_object() {
+            Some(fields) => {
+                let width = fields.keys().map(String::len).max().unwrap_or_default();
+                for (name, field) in fields {
+                    println!("{:width$}  {}", name, cell(Some(field)), width = width);
+                }
+            }
+            None => println!("{}", cell(Some(value))),
+        },
+    }
+}
+
+fn print_json(value: &Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).unwrap_or_default()
+    );
+}
+
+fn render_table(rows: &[Value], columns: &[&str]) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            columns
+                .iter()
+                .map(|column| cell(lookup(row, column)))
+                .collect()
+        })
+        .collect();
+
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([column.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let header: Vec<String> = columns.iter().map(|column| column.to_uppercase()).collect();
+    let mut table = String::new();
+    for row in [header].iter().chain(cells.iter()) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        table.push_str(line.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |value, segment| value.get(segment))
+}
+
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => "-".to_string(),
+        Some(Value::String(text)) => text.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_table() {
+        let caches = json!({
+            "caches": [
+                { "config": { "name": "users", "eviction": "TimeBound" }, "keys_estimate": 12 },
+                { "config": { "name": "sessions" }, "keys_estimate": 3 }
+            ]
+        });
+        let rows = lookup(&caches, "caches").unwrap().as_array().unwrap();
+
+        let table = render_table(rows, &["config.name", "config.eviction", "keys_estimate"]);
+        assert_eq!(
+            table,
+            "CONFIG.NAME  CONFIG.EVICTION  KEYS_ESTIMATE\n\
+             users        TimeBound        12\n\
+             sessions     -                3\n"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::cli::{ConnectionArgs, Transport};
+use crate::error::{CliError, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_PROFILE: &str = "default";
+const DEFAULT_HTTP_URL: &str = "http://localhost:8080";
+const DEFAULT_TCP_ADDR: &str = "localhost:5500";
+
+/// Connection settings for one server, as kept under `[profiles.<name>]` in the profiles file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Profile {
+    pub http_url: Option<String>,
+    pub tcp_addr: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub transport: Option<Transport>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: HashMap<String, Profile>,
+}
+
+impl Profile {
+    /// Profile named on the command line, with command line flags taking precedence
+    pub fn resolve(args: &ConnectionArgs) -> Result<Self> {
+        let profile = load(&profiles_path(), &args.profile)?;
+        Ok(profile.with_overrides(args))
+    }
+
+    fn with_overrides(self, args: &ConnectionArgs) -> Self {
+        Self {
+            http_url: args.url.clone().or(self.http_url),
+            tcp_addr: args.tcp_addr.clone().or(self.tcp_addr),
+            username: args.username.clone().or(self.username),
+            password: args.password.clone().or(self.password),
+            transport: args.transport.or(self.transport),
+        }
+    }
+
+    pub fn http_url(&self) -> &str {
+        self.http_url.as_deref().unwrap_or(DEFAULT_HTTP_URL)
+    }
+
+    pub fn tcp_addr(&self) -> &str {
+        self.tcp_addr.as_deref().unwrap_or(DEFAULT_TCP_ADDR)
+    }
+
+    pub fn transport(&self) -> Transport {
+        self.transport.unwrap_or(Transport::Http)
+    }
+}
+
+fn profiles_path() -> PathBuf {
+    if let Ok(path) = std::env::var("CARBON_CLI_CONFIG") {
+        return PathBuf::from(path);
+    }
+    let home = std::env::var("HOME").unwrap_or_default();
+    Path::new(&home).join(".config/carbon/profiles.toml")
+}
+
+/// Read a profile, without a profiles file only the default profile exists
+fn load(path: &Path, name: &str) -> Result<Profile> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && name == DEFAULT_PROFILE => {
+            return Ok(Profile::default());
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(CliError::ProfileNotFound {
+                name: name.to_string(),
+                path: path.display().to_string(),
+            });
+        }
+        Err(e) => return Err(e.into()),
+    };
+    parse(&contents, name, path)
+}
+
+fn parse(contents: &str, name: &str, path: &Path) -> Result<Profile> {
+    let mut file: ProfilesFile =
+        toml::from_str(contents).map_err(|e| CliError::InvalidProfiles {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+
+    match file.profiles.remove(name) {
+        Some(profile) => Ok(profile),
+        None if name == DEFAULT_PROFILE => Ok(Profile::default()),
+        None => Err(CliError::ProfileNotFound {
+            name: name.to_string(),
+            path: path.display().to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: &str = r#"
+        [profiles.default]
+        http_url = "http://localhost:8080"
+        username = "admin"
+        password = "secret"
+
+        [profiles.staging]
+        tcp_addr = "cache.staging:5500"
+        transport = "tcp"
+    "#;
+
+    #[test]
+    fn test_parse_profiles() {
+        let path = Path::new("profiles.toml");
+
+        let default = parse(PROFILES, "default", path).unwrap();
+        assert_eq!(default.username.as_deref(), Some("admin"));
+        assert_eq!(default.transport(), Transport::Http);
+
+        let staging = parse(PROFILES, "staging", path).unwrap();
+        assert_eq!(staging.tcp_addr(), "cache.staging:5500");
+        assert_eq!(staging.http_url(), DEFAULT_HTTP_URL);
+        assert_eq!(staging.transport(), Transport::Tcp);
+
+        assert!(matches!(
+            parse(PROFILES, "prod", path),
+            Err(CliError::ProfileNotFound { .. })
+        ));
+        assert_eq!(parse("", "default", path).unwrap(), Profile::default());
+    }
+}
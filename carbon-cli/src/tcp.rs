@@ -0,0 +1,109 @@
+use crate::error::{CliError, Result};
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use server_tcp::protocol::{
+    FEATURE_AUTH_REQUIRED, FEATURE_CHECKSUM, FEATURE_COMPRESSION, Negotiated, PROTOCOL_VERSION,
+    RESP_ERROR, checksum, compression,
+};
+use server_tcp::server::MAX_FRAME_LENGTH;
+use server_tcp::{Request, Response};
+use tokio::net::TcpStream;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+/// Optional features this client offers in HELLO
+const CLIENT_FEATURES: u32 = FEATURE_COMPRESSION | FEATURE_CHECKSUM;
+
+/// Client for the binary TCP protocol, one request in flight at a time
+pub struct TcpClient {
+    framed: Framed<TcpStream, LengthDelimitedCodec>,
+    /// Set once the server agreed to compress frames
+    compressed: bool,
+    /// Set once the server agreed to checksum frames
+    checksummed: bool,
+}
+
+impl TcpClient {
+    pub async fn connect(addr: &str) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        // Same framing as the server: 4-byte big-endian length prefix, 8 MB frames
+        let codec = LengthDelimitedCodec::builder()
+            .length_field_length(4)
+            .max_frame_length(MAX_FRAME_LENGTH)
+            .new_codec();
+        let mut client = Self {
+            framed: Framed::new(stream, codec),
+            compressed: false,
+            checksummed: false,
+        };
+        let negotiated = client.hello().await?;
+        if negotiated.supports(FEATURE_AUTH_REQUIRED) {
+            return Err(CliError::Command(
+                "the server requires authentication over TCP, use the http transport".to_string(),
+            ));
+        }
+        client.compressed = negotiated.supports(FEATURE_COMPRESSION);
+        client.checksummed = negotiated.supports(FEATURE_CHECKSUM);
+        Ok(client)
+    }
+
+    /// Exchange versions and features, servers from before HELLO reject it and are spoken to
+    /// as version 0
+    async fn hello(&mut self) -> Result<Negotiated> {
+        let hello = Request::Hello {
+            version: PROTOCOL_VERSION,
+            features: CLIENT_FEATURES,
+        };
+        let frame = self.exchange(hello).await?;
+
+        // Any error will do, whichever ERROR layout the server writes
+        if frame.first() == Some(&RESP_ERROR) {
+            return Ok(Negotiated::LEGACY);
+        }
+        match Response::decode(frame).map_err(CliError::Protocol)? {
+            Response::Hello { version, features } => {
+                Ok(Negotiated::new(CLIENT_FEATURES, version, features))
+            }
+            _ => Ok(Negotiated::LEGACY),
+        }
+    }
+
+    /// Send a request and wait for its response, ERROR and THROTTLED become errors
+    pub async fn call(&mut self, request: Request) -> Result<Response> {
+        let frame = self.exchange(request).await?;
+        match Response::decode(frame).map_err(CliError::Protocol)? {
+            Response::Error { code, msg } => Err(CliError::Command(format!("{}: {}", code, msg))),
+            Response::Throttled { msg } => Err(CliError::Command(msg)),
+            response => Ok(response),
+        }
+    }
+
+    /// Send a request and wait for the message of its response frame
+    async fn exchange(&mut self, request: Request) -> Result<Bytes> {
+        let request = request.encode();
+        let request = if self.compressed {
+            compression::compress(&request)
+        } else {
+            request
+        };
+        let request = if self.checksummed {
+            checksum::seal(&request)
+        } else {
+            request
+        };
+        self.framed.send(request).await?;
+
+        let frame = self.framed.next().await.ok_or_else(|| {
+            CliError::Protocol("connection closed before a response arrived".to_string())
+        })??;
+        let frame = if self.checksummed {
+            checksum::open(frame.freeze()).map_err(CliError::Protocol)?
+        } else {
+            frame.freeze()
+        };
+        if self.compressed {
+            compression::decompress(frame, MAX_FRAME_LENGTH).map_err(CliError::Protocol)
+        } else {
+            Ok(frame)
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use crate::definition::FieldType;
+use crate::schema::CacheSchema;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+/// Rust source for the documents of a cache: a serde struct per object of the schema and a
+/// client putting and getting them over the HTTP API
+/// The code needs serde with derive, serde_json and reqwest with json where it is compiled
+/// A field holding an object that other fields are nested in, such as `/address` next to
+/// `/address/zip`, is left out and listed in the header
+pub fn rust_client(schema: &CacheSchema) -> String {
+    // Nested fields go first, so they win over a field holding the whole object
+    let mut fields: Vec<_> = schema.fields.iter().collect();
+    fields.sort_by_key(|field| Reverse(pointer_segments(&field.path).len()));
+
+    let mut root = Object::default();
+    let mut skipped = Vec::new();
+    let mut has_point = false;
+    for field in fields {
+        let segments = pointer_segments(&field.path);
+        if segments.is_empty() || !root.insert(&segments, field.field_type) {
+            skipped.push(field.path.as_str());
+        } else {
+            has_point |= field.field_type == FieldType::GeoPoint;
+        }
+    }
+
+    let document = type_name(&schema.cache);
+    let mut out = format!(
+        "// Generated by `carbon codegen` from the schema of cache '{}', regenerate it rather than\n\
+         // editing it\n\
+         // Needs serde with derive, serde_json and reqwest with json\n",
+        schema.cache
+    );
+    if !skipped.is_empty() {
+        let _ = writeln!(
+            out,
+            "// Left out as other fields are nested in them: {}",
+            skipped.join(", ")
+        );
+    }
+    out.push_str("\nuse serde::{Deserialize, Serialize};\n");
+
+    let doc = format!("A document of cache '{}'", schema.cache);
+    emit_struct(&mut out, &document, &doc, &root);
+    if has_point {
+        out.push_str(GEO_POINT_TEMPLATE);
+    }
+
+    out.push_str(
+        &CLIENT_TEMPLATE
+            .replace("{cache}", &schema.cache)
+            .replace("{cache_literal}", &format!("{:?}", schema.cache))
+            .replace("{document}", &document),
+    );
+    out
+}
+
+/// Fields of a JSON object, by key
+#[derive(Default)]
+struct Object {
+    fields: BTreeMap<String, Node>,
+}
+
+enum Node {
+    Value(FieldType),
+    Object(Object),
+}
+
+impl Object {
+    /// Add the field at `segments`, false when a field is already there or on the way
+    fn insert(&mut self, segments: &[String], field_type: FieldType) -> bool {
+        let Some((first, rest)) = segments.split_first() else {
+            return false;
+        };
+        match (self.fields.get_mut(first), rest.is_empty()) {
+            (None, true) => {
+                self.fields.insert(first.clone(), Node::Value(field_type));
+                true
+            }
+            (None, false) => {
+                let mut child = Object::default();
+                let inserted = child.insert(rest, field_type);
+                if inserted {
+                    self.fields.insert(first.clone(), Node::Object(child));
+                }
+                inserted
+            }
+            (Some(Node::Object(child)), false) => child.insert(rest, field_type),
+            (Some(_), _) => false,
+        }
+    }
+}
+
+/// Write the struct `name` for `object`, followed by the structs of its nested objects
+fn emit_struct(out: &mut String, name: &str, doc: &str, object: &Object) {
+    let _ = writeln!(out, "\n/// {}", doc);
+    out.push_str("#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]\n");
+    let _ = writeln!(out, "pub struct {} {{", name);
+
+    // `extra` holds the fields outside the schema
+    let mut used = HashSet::from(["extra".to_string()]);
+    let mut nested = Vec::new();
+    for (key, node) in &object.fields {
+        let mut ident = field_ident(key);
+        let base = ident.clone();
+        let mut suffix = 2;
+        while !used.insert(ident.clone()) {
+            ident = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+
+        let rust_type = match node {
+            Node::Value(FieldType::String) => "String".to_string(),
+            Node::Value(FieldType::Number) => "f64".to_string(),
+            Node::Value(FieldType::Bool) => "bool".to_string(),
+            Node::Value(FieldType::GeoPoint) => "GeoPoint".to_string(),
+            Node::Object(child) => {
+                let child_name = format!("{}{}", name, type_name(key));
+                nested.push((child_name.clone(), key, child));
+                child_name
+            }
+        };
+
+        let rename = if ident == *key {
+            String::new()
+        } else {
+            format!("rename = {:?}, ", key)
+        };
+        let _ = writeln!(
+            out,
+            "    #[serde({}default, skip_serializing_if = \"Option::is_none\")]",
+            rename
+        );
+        let _ = writeln!(out, "    pub {}: Option<{}>,", ident, rust_type);
+    }
+    out.push_str(
+        "    /// Fields outside the schema, kept so a read-modify-write does not drop them\n",
+    );
+    out.push_str("    #[serde(flatten)]\n");
+    out.push_str("    pub extra: serde_json::Map<String, serde_json::Value>,\n}\n");
+
+    for (child_name, key, child) in nested {
+        let doc = format!("The `{}` object of {}", key, name);
+        emit_struct(out, &child_name, &doc, child);
+    }
+}
+
+/// Keys along an RFC 6901 pointer, unescaped
+fn pointer_segments(pointer: &str) -> Vec<String> {
+    match pointer.strip_prefix('/') {
+        Some(rest) => rest
+            .split('/')
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// snake_case field name for a document key, `userName` becoming `user_name`
+fn field_ident(key: &str) -> String {
+    let mut ident = String::new();
+    let mut after_lower = false;
+    for c in key.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase() && after_lower {
+                ident.push('_');
+            }
+            after_lower = !c.is_ascii_uppercase();
+            ident.push(c.to_ascii_lowercase());
+        } else {
+            if !ident.ends_with('_') {
+                ident.push('_');
+            }
+            after_lower = false;
+        }
+    }
+
+    let ident = ident.trim_matches('_');
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("field_{}", ident)
+    } else if KEYWORDS.contains(&ident) {
+        format!("{}_", ident)
+    } else {
+        ident.to_string()
+    }
+}
+
+/// PascalCase type name for a cache name or document key, `user-profiles` becoming
+/// `UserProfiles`
+fn type_name(name: &str) -> String {
+    let name: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("Field{}", name)
+    } else {
+        name
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+const GEO_POINT_TEMPLATE: &str = r#"
+/// A location in degrees
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+"#;
+
+const CLIENT_TEMPLATE: &str = r#"
+/// Errors of the client, from the transport, the server or the documents
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Reads and writes documents of cache '{cache}' over the HTTP API
+#[derive(Clone, Debug)]
+pub struct {document}Client {
+    http: reqwest::Client,
+    base_url: reqwest::Url,
+    credentials: Option<(String, String)>,
+}
+
+impl {document}Client {
+    pub const CACHE: &'static str = {cache_literal};
+
+    /// Client for the server at `base_url`, such as http://localhost:8080
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base_url: reqwest::Url::parse(base_url)?,
+            credentials: None,
+        })
+    }
+
+    /// Authenticate every request with Basic auth
+    pub fn with_basic_auth(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Store `value` under `key`
+    pub async fn put(&self, key: &str, value: &{document}) -> Result<(), Error> {
+        let body = serde_json::json!({ "value": serde_json::to_string(value)? });
+        self.request(reqwest::Method::PUT, key)?
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// The document under `key`, None when there is none
+    pub async fn get(&self, key: &str) -> Result<Option<{document}>, Error> {
+        let response: serde_json::Value = self
+            .request(reqwest::Method::GET, key)?
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if response["found"] != serde_json::Value::Bool(true) {
+            return Ok(None);
+        }
+        match response["value"].as_str() {
+            Some(value) => Ok(Some(serde_json::from_str(value)?)),
+            None => Err("the server sent no value".into()),
+        }
+    }
+
+    /// Delete the document under `key`, false when there was none
+    pub async fn delete(&self, key: &str) -> Result<bool, Error> {
+        let response: serde_json::Value = self
+            .request(reqwest::Method::DELETE, key)?
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response["deleted"] == serde_json::Value::Bool(true))
+    }
+
+    fn request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+    ) -> Result<reqwest::RequestBuilder, Error> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| "the base URL cannot have a path")?
+            .pop_if_empty()
+            .extend(["cache", Self::CACHE, key]);
+        let request = self.http.request(method, url);
+        Ok(match &self.credentials {
+            Some((username, password)) => request.basic_auth(username, Some(password)),
+            None => request,
+        })
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{FieldDefinition, IndexDefinition, IndexKind};
+    use carbon::domain::{ValueFormat, ValueType};
+
+    #[test]
+    fn test_rust_client_from_schema() {
+        let indexes = vec![
+            IndexDefinition::new(
+                "by_name",
+                FieldDefinition::new("userName", FieldType::String),
+                IndexKind::Hash,
+            ),
+            IndexDefinition::composite(
+                "by_place",
+                vec![
+                    FieldDefinition::new("zip", FieldType::Number).with_path("/address/zip"),
+                    FieldDefinition::new("type", FieldType::Bool),
+                ],
+                IndexKind::Range,
+            ),
+            // Holds the object /address/zip is in
+            IndexDefinition::new(
+                "by_address",
+                FieldDefinition::new("address", FieldType::String),
+                IndexKind::Hash,
+            ),
+        ];
+        let schema =
+            CacheSchema::from_indexes("user-profiles", ValueType::Json, ValueFormat::Raw, &indexes);
+        assert_eq!(schema.fields.len(), 4);
+
+        let code = rust_client(&schema);
+        assert!(code.contains("pub struct UserProfiles {"));
+        assert!(code.contains("pub address: Option<UserProfilesAddress>,"));
+        assert!(code.contains("pub struct UserProfilesAddress {"));
+        assert!(code.contains("pub zip: Option<f64>,"));
+        assert!(code.contains("#[serde(rename = \"userName\", default"));
+        assert!(code.contains("pub user_name: Option<String>,"));
+        assert!(code.contains("#[serde(rename = \"type\", default"));
+        assert!(code.contains("pub type_: Option<bool>,"));
+        assert!(code.contains("// Left out as other fields are nested in them: /address\n"));
+        assert!(code.contains("pub struct UserProfilesClient {"));
+        assert!(code.contains("pub const CACHE: &'static str = \"user-profiles\";"));
+        assert!(!code.contains("{document}") && !code.contains("{cache"));
+        assert!(!code.contains("pub struct GeoPoint"));
+    }
+
+    #[test]
+    fn test_rust_client_geo_point() {
+        let indexes = vec![IndexDefinition::new(
+            "place_geo",
+            FieldDefinition::new("place", FieldType::GeoPoint),
+            IndexKind::Geo,
+        )];
+        let schema =
+            CacheSchema::from_indexes("stores", ValueType::Json, ValueFormat::Raw, &indexes);
+
+        let code = rust_client(&schema);
+        assert!(code.contains("pub place: Option<GeoPoint>,"));
+        assert!(code.contains("pub struct GeoPoint {"));
+    }
+}
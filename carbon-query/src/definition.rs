@@ -0,0 +1,395 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shared::{Error, Result};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+/// Type a document field must have to be indexed
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    String,
+    Number,
+    Bool,
+    /// `{"lat": 48.8566, "lon": 2.3522}` in degrees
+    GeoPoint,
+}
+
+/// A field extracted from the JSON documents of a cache
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct FieldDefinition {
+    pub name: String,
+    /// RFC 6901 pointer into the document, defaults to `/{name}`
+    #[serde(default)]
+    pub path: Option<String>,
+    pub field_type: FieldType,
+}
+
+impl FieldDefinition {
+    pub fn new(name: impl Into<String>, field_type: FieldType) -> Self {
+        Self {
+            name: name.into(),
+            path: None,
+            field_type,
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// JSON pointer used to locate the field in a document
+    pub fn pointer(&self) -> String {
+        self.path
+            .clone()
+            .unwrap_or_else(|| format!("/{}", self.name))
+    }
+
+    /// Extract the field from `document`, None when it is missing or has another type
+    pub fn extract(&self, document: &Value) -> Option<FieldValue> {
+        FieldValue::from_json(document.pointer(&self.pointer())?, self.field_type)
+    }
+}
+
+/// How an index organises its values
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexKind {
+    /// Equality lookups only
+    Hash,
+    /// Ordered, supports equality and range lookups
+    Range,
+    /// Inverted index over the terms of a single string field
+    Text,
+    /// Geohash cells of a single geopoint field, answering radius queries
+    Geo,
+}
+
+/// An index over one or more document fields
+/// Composite indexes order entries by their first field, then the second, and so on
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct IndexDefinition {
+    pub name: String,
+    pub fields: Vec<FieldDefinition>,
+    pub kind: IndexKind,
+}
+
+impl IndexDefinition {
+    pub fn new(name: impl Into<String>, field: FieldDefinition, kind: IndexKind) -> Self {
+        Self::composite(name, vec![field], kind)
+    }
+
+    pub fn composite(
+        name: impl Into<String>,
+        fields: Vec<FieldDefinition>,
+        kind: IndexKind,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            fields,
+            kind,
+        }
+    }
+
+    /// Reject definitions no index can be built from
+    pub fn validate(&self) -> Result<()> {
+        if self.fields.is_empty() {
+            return Err(Error::InvalidValue(format!(
+                "index '{}' needs at least one field",
+                self.name
+            )));
+        }
+
+        let single_string =
+            matches!(self.fields.as_slice(), [field] if field.field_type == FieldType::String);
+        if self.kind == IndexKind::Text && !single_string {
+            return Err(Error::InvalidValue(format!(
+                "text index '{}' must cover exactly one string field",
+                self.name
+            )));
+        }
+
+        let single_point =
+            matches!(self.fields.as_slice(), [field] if field.field_type == FieldType::GeoPoint);
+        if self.kind == IndexKind::Geo && !single_point {
+            return Err(Error::InvalidValue(format!(
+                "geo index '{}' must cover exactly one geopoint field",
+                self.name
+            )));
+        }
+
+        // Points have no order worth scanning and no terms
+        let has_point = self
+            .fields
+            .iter()
+            .any(|field| field.field_type == FieldType::GeoPoint);
+        if has_point && matches!(self.kind, IndexKind::Range | IndexKind::Text) {
+            return Err(Error::InvalidValue(format!(
+                "index '{}' cannot cover geopoint fields, use a hash or geo index",
+                self.name
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Build the index key of `document`, None unless every field is present
+    pub fn extract(&self, document: &Value) -> Option<IndexKey> {
+        self.fields
+            .iter()
+            .map(|field| field.extract(document))
+            .collect::<Option<Vec<_>>>()
+            .map(IndexKey)
+    }
+}
+
+/// Composite key stored in an index, one value per indexed field
+/// Keys compare field by field, so every key sharing a prefix sorts directly after
+/// that prefix and a prefix scan is a contiguous range of a Range index
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub struct IndexKey(pub Vec<FieldValue>);
+
+impl IndexKey {
+    pub fn starts_with(&self, prefix: &[FieldValue]) -> bool {
+        self.0.starts_with(prefix)
+    }
+}
+
+impl From<FieldValue> for IndexKey {
+    fn from(value: FieldValue) -> Self {
+        IndexKey(vec![value])
+    }
+}
+
+/// A value stored in an index
+/// Numbers compare with `f64::total_cmp` so every value has a total order and can key a BTreeMap
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+    GeoPoint { lat: f64, lon: f64 },
+}
+
+impl FieldValue {
+    /// Convert a JSON value, None when it does not match `field_type`
+    pub fn from_json(value: &Value, field_type: FieldType) -> Option<Self> {
+        match (field_type, value) {
+            (FieldType::String, Value::String(s)) => Some(FieldValue::String(s.clone())),
+            (FieldType::Number, Value::Number(n)) => n.as_f64().map(FieldValue::number),
+            (FieldType::Bool, Value::Bool(b)) => Some(FieldValue::Bool(*b)),
+            (FieldType::GeoPoint, Value::Object(point)) => {
+                let lat = point.get("lat")?.as_f64()?;
+                let lon = point.get("lon")?.as_f64()?;
+                FieldValue::geo_point(lat, lon)
+            }
+            _ => None,
+        }
+    }
+
+    /// Point value, None when the coordinates are off the globe
+    pub fn geo_point(lat: f64, lon: f64) -> Option<Self> {
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return None;
+        }
+        Some(FieldValue::GeoPoint {
+            lat: if lat == 0.0 { 0.0 } else { lat },
+            lon: if lon == 0.0 { 0.0 } else { lon },
+        })
+    }
+
+    /// Number value with -0.0 folded into 0.0 so both index under the same key
+    pub fn number(value: f64) -> Self {
+        FieldValue::Number(if value == 0.0 { 0.0 } else { value })
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            FieldValue::Bool(_) => 0,
+            FieldValue::Number(_) => 1,
+            FieldValue::String(_) => 2,
+            FieldValue::GeoPoint { .. } => 3,
+        }
+    }
+}
+
+impl PartialEq for FieldValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FieldValue {}
+
+impl PartialOrd for FieldValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FieldValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (FieldValue::Bool(a), FieldValue::Bool(b)) => a.cmp(b),
+            (FieldValue::Number(a), FieldValue::Number(b)) => a.total_cmp(b),
+            (FieldValue::String(a), FieldValue::String(b)) => a.cmp(b),
+            (
+                FieldValue::GeoPoint { lat, lon },
+                FieldValue::GeoPoint {
+                    lat: other_lat,
+                    lon: other_lon,
+                },
+            ) => lat.total_cmp(other_lat).then(lon.total_cmp(other_lon)),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl Hash for FieldValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            FieldValue::Bool(b) => b.hash(state),
+            FieldValue::Number(n) => n.to_bits().hash(state),
+            FieldValue::String(s) => s.hash(state),
+            FieldValue::GeoPoint { lat, lon } => {
+                lat.to_bits().hash(state);
+                lon.to_bits().hash(state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_by_name_and_path() {
+        let document = json!({"status": "active", "address": {"zip": 75001}});
+
+        let status = FieldDefinition::new("status", FieldType::String);
+        assert_eq!(
+            status.extract(&document),
+            Some(FieldValue::String("active".to_string()))
+        );
+
+        let zip = FieldDefinition::new("zip", FieldType::Number).with_path("/address/zip");
+        assert_eq!(zip.extract(&document), Some(FieldValue::Number(75001.0)));
+    }
+
+    #[test]
+    fn test_extract_skips_missing_and_mistyped_fields() {
+        let document = json!({"age": "forty"});
+
+        let age = FieldDefinition::new("age", FieldType::Number);
+        assert_eq!(age.extract(&document), None);
+
+        let name = FieldDefinition::new("name", FieldType::String);
+        assert_eq!(name.extract(&document), None);
+    }
+
+    #[test]
+    fn test_extract_geo_point() {
+        let place = FieldDefinition::new("place", FieldType::GeoPoint);
+        assert_eq!(
+            place.extract(&json!({"place": {"lat": 48.8566, "lon": 2.3522}})),
+            Some(FieldValue::GeoPoint {
+                lat: 48.8566,
+                lon: 2.3522
+            })
+        );
+        assert_eq!(place.extract(&json!({"place": {"lat": 48.8566}})), None);
+        assert_eq!(
+            place.extract(&json!({"place": {"lat": 91, "lon": 0}})),
+            None
+        );
+        assert_eq!(place.extract(&json!({"place": [2.3522, 48.8566]})), None);
+    }
+
+    #[test]
+    fn test_number_ordering() {
+        assert!(FieldValue::number(-1.5) < FieldValue::number(2.0));
+        assert_eq!(FieldValue::number(-0.0), FieldValue::number(0.0));
+    }
+
+    #[test]
+    fn test_composite_extract_requires_every_field() {
+        let index = IndexDefinition::composite(
+            "by_country_city",
+            vec![
+                FieldDefinition::new("country", FieldType::String),
+                FieldDefinition::new("city", FieldType::String),
+            ],
+            IndexKind::Range,
+        );
+
+        let key = index.extract(&json!({"country": "FR", "city": "Paris"}));
+        assert_eq!(
+            key,
+            Some(IndexKey(vec![
+                FieldValue::String("FR".to_string()),
+                FieldValue::String("Paris".to_string()),
+            ]))
+        );
+        assert_eq!(index.extract(&json!({"country": "FR"})), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let bio = FieldDefinition::new("bio", FieldType::String);
+        let age = FieldDefinition::new("age", FieldType::Number);
+
+        assert!(
+            IndexDefinition::new("bio_text", bio.clone(), IndexKind::Text)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            IndexDefinition::new("age_text", age.clone(), IndexKind::Text)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            IndexDefinition::composite("both", vec![bio.clone(), age], IndexKind::Text)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            IndexDefinition::composite("none", vec![], IndexKind::Hash)
+                .validate()
+                .is_err()
+        );
+
+        let place = FieldDefinition::new("place", FieldType::GeoPoint);
+        assert!(
+            IndexDefinition::new("place_geo", place.clone(), IndexKind::Geo)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            IndexDefinition::new("bio_geo", bio, IndexKind::Geo)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            IndexDefinition::new("place_range", place, IndexKind::Range)
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_index_key_prefix_ordering() {
+        let fr = FieldValue::String("FR".to_string());
+        let prefix = IndexKey(vec![fr.clone()]);
+        let lyon = IndexKey(vec![fr.clone(), FieldValue::String("Lyon".to_string())]);
+        let uk = IndexKey(vec![FieldValue::String("UK".to_string())]);
+
+        assert!(prefix < lyon && lyon < uk);
+        assert!(lyon.starts_with(&[fr]));
+    }
+}
@@ -0,0 +1,285 @@
+use crate::definition::{FieldValue, IndexDefinition, IndexKey};
+use crate::indexes::SecondaryIndex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::RwLock;
+
+/// Characters of a geohash, five bits each
+const BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Geohash length points are bucketed at, cells of about 4.9 m by 4.9 m at the equator
+const CELL_PRECISION: usize = 9;
+
+/// Most cells a radius query probes, coarser cells are used for larger radii
+const MAX_QUERY_CELLS: usize = 32;
+
+/// Mean radius of the Earth used for distances
+pub const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// Geohash of a point with `precision` characters
+pub fn geohash(lat: f64, lon: f64, precision: usize) -> String {
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut hash = String::with_capacity(precision);
+    let mut even = true;
+    while hash.len() < precision {
+        let mut index = 0;
+        for _ in 0..5 {
+            // Bits alternate between longitude and latitude, longitude first
+            let (range, value) = if even {
+                (&mut lon_range, lon)
+            } else {
+                (&mut lat_range, lat)
+            };
+            let middle = (range.0 + range.1) / 2.0;
+            index <<= 1;
+            if value >= middle {
+                index |= 1;
+                range.0 = middle;
+            } else {
+                range.1 = middle;
+            }
+            even = !even;
+        }
+        hash.push(BASE32[index] as char);
+    }
+    hash
+}
+
+/// Great-circle distance in metres between two points
+pub fn haversine_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().min(1.0).asin()
+}
+
+/// Height and width in degrees of the cells of a geohash `precision`
+fn cell_size(precision: usize) -> (f64, f64) {
+    let bits = 5 * precision as i32;
+    let lat_bits = bits / 2;
+    let lon_bits = bits - lat_bits;
+    (180.0 / 2f64.powi(lat_bits), 360.0 / 2f64.powi(lon_bits))
+}
+
+/// Geohash prefixes covering every point within `radius_m` of the centre
+/// The finest precision whose cover stays under MAX_QUERY_CELLS is used
+fn covering_cells(lat: f64, lon: f64, radius_m: f64) -> BTreeSet<String> {
+    let delta_lat = (radius_m / EARTH_RADIUS_M).to_degrees();
+    let min_lat = (lat - delta_lat).max(-90.0);
+    let max_lat = (lat + delta_lat).min(90.0);
+
+    // Near the poles the circle can span every longitude
+    let cos_lat = min_lat.to_radians().cos().min(max_lat.to_radians().cos());
+    let delta_lon = if cos_lat > 0.0 {
+        delta_lat / cos_lat
+    } else {
+        180.0
+    };
+    let lon_ranges = if delta_lon >= 180.0 {
+        vec![(-180.0, 180.0)]
+    } else if lon - delta_lon < -180.0 {
+        vec![(lon - delta_lon + 360.0, 180.0), (-180.0, lon + delta_lon)]
+    } else if lon + delta_lon > 180.0 {
+        vec![(lon - delta_lon, 180.0), (-180.0, lon + delta_lon - 360.0)]
+    } else {
+        vec![(lon - delta_lon, lon + delta_lon)]
+    };
+
+    for precision in (1..=CELL_PRECISION).rev() {
+        let (height, width) = cell_size(precision);
+        let rows = ((max_lat - min_lat) / height).ceil() as usize + 1;
+        let columns: usize = lon_ranges
+            .iter()
+            .map(|(west, east)| ((east - west) / width).ceil() as usize + 1)
+            .sum();
+        if rows * columns > MAX_QUERY_CELLS && precision > 1 {
+            continue;
+        }
+
+        // Stepping by the cell size lands in every row and column the box touches
+        let mut cells = BTreeSet::new();
+        for row in 0..=rows {
+            let cell_lat = (min_lat + row as f64 * height).min(max_lat);
+            for (west, east) in &lon_ranges {
+                let mut cell_lon = *west;
+                loop {
+                    cells.insert(geohash(cell_lat, cell_lon.min(*east), precision));
+                    if cell_lon >= *east {
+                        break;
+                    }
+                    cell_lon += width;
+                }
+            }
+        }
+        return cells;
+    }
+    unreachable!("precision 1 always covers the box")
+}
+
+/// Keys in one cell, with the point each was indexed at
+type CellPoints = BTreeMap<Vec<u8>, (f64, f64)>;
+
+/// Index from geohash cells to the keys whose points lie in them
+/// Cells are kept ordered, so the points under a coarser cell are a contiguous scan
+pub struct GeoIndex {
+    definition: IndexDefinition,
+    cells: RwLock<BTreeMap<String, CellPoints>>,
+}
+
+impl GeoIndex {
+    pub fn new(definition: IndexDefinition) -> Self {
+        Self {
+            definition,
+            cells: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    fn point(index_key: &IndexKey) -> Option<(f64, f64)> {
+        match index_key.0.first() {
+            Some(FieldValue::GeoPoint { lat, lon }) => Some((*lat, *lon)),
+            _ => None,
+        }
+    }
+}
+
+impl SecondaryIndex for GeoIndex {
+    fn definition(&self) -> &IndexDefinition {
+        &self.definition
+    }
+
+    fn insert(&self, index_key: IndexKey, key: &[u8]) {
+        let Some((lat, lon)) = Self::point(&index_key) else {
+            return;
+        };
+        self.cells
+            .write()
+            .unwrap()
+            .entry(geohash(lat, lon, CELL_PRECISION))
+            .or_default()
+            .insert(key.to_vec(), (lat, lon));
+    }
+
+    fn remove(&self, index_key: &IndexKey, key: &[u8]) {
+        let Some((lat, lon)) = Self::point(index_key) else {
+            return;
+        };
+        let cell = geohash(lat, lon, CELL_PRECISION);
+        let mut cells = self.cells.write().unwrap();
+        if let Some(keys) = cells.get_mut(&cell) {
+            keys.remove(key);
+            if keys.is_empty() {
+                cells.remove(&cell);
+            }
+        }
+    }
+
+    /// Keys stored at exactly the point
+    fn lookup(&self, index_key: &IndexKey) -> Vec<Vec<u8>> {
+        let Some(point) = Self::point(index_key) else {
+            return Vec::new();
+        };
+        self.cells
+            .read()
+            .unwrap()
+            .get(&geohash(point.0, point.1, CELL_PRECISION))
+            .map(|keys| {
+                keys.iter()
+                    .filter(|(_, stored)| **stored == point)
+                    .map(|(key, _)| key.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn within(&self, lat: f64, lon: f64, radius_m: f64) -> Option<Vec<Vec<u8>>> {
+        let cells = self.cells.read().unwrap();
+
+        let mut keys = BTreeSet::new();
+        for prefix in covering_cells(lat, lon, radius_m) {
+            let points = cells
+                .range(prefix.clone()..)
+                .take_while(|(cell, _)| cell.starts_with(prefix.as_str()))
+                .flat_map(|(_, points)| points);
+            for (key, (point_lat, point_lon)) in points {
+                if haversine_m(lat, lon, *point_lat, *point_lon) <= radius_m {
+                    keys.insert(key.clone());
+                }
+            }
+        }
+
+        Some(keys.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{FieldDefinition, FieldType, IndexKind};
+
+    fn place(lat: f64, lon: f64) -> IndexKey {
+        IndexKey::from(FieldValue::geo_point(lat, lon).unwrap())
+    }
+
+    fn places_index() -> GeoIndex {
+        let index = GeoIndex::new(IndexDefinition::new(
+            "place_geo",
+            FieldDefinition::new("place", FieldType::GeoPoint),
+            IndexKind::Geo,
+        ));
+        for (key, lat, lon) in [
+            ("louvre", 48.8606, 2.3376),
+            ("notre-dame", 48.8530, 2.3499),
+            ("versailles", 48.8049, 2.1204),
+            ("london", 51.5074, -0.1278),
+            ("fiji", -17.7134, 179.9990),
+            ("samoa", -17.7134, -179.9990),
+        ] {
+            index.insert(place(lat, lon), key.as_bytes());
+        }
+        index
+    }
+
+    #[test]
+    fn test_geohash_and_distance() {
+        assert_eq!(geohash(57.64911, 10.40744, 11), "u4pruydqqvj");
+        assert_eq!(geohash(48.8606, 2.3376, 5), "u09tv");
+
+        let paris_london = haversine_m(48.8566, 2.3522, 51.5074, -0.1278);
+        assert!(
+            (paris_london - 343_500.0).abs() < 1_000.0,
+            "{}",
+            paris_london
+        );
+    }
+
+    #[test]
+    fn test_within_radius() {
+        let index = places_index();
+
+        let central = index.within(48.8566, 2.3522, 2_000.0).unwrap();
+        assert_eq!(central, vec![b"louvre".to_vec(), b"notre-dame".to_vec()]);
+
+        let region = index.within(48.8566, 2.3522, 25_000.0).unwrap();
+        assert_eq!(region.len(), 3);
+
+        let europe = index.within(48.8566, 2.3522, 500_000.0).unwrap();
+        assert_eq!(europe.len(), 4);
+
+        // The antimeridian lies between these two
+        let pacific = index.within(-17.7134, 179.9995, 1_000.0).unwrap();
+        assert_eq!(pacific, vec![b"fiji".to_vec(), b"samoa".to_vec()]);
+    }
+
+    #[test]
+    fn test_lookup_and_remove() {
+        let index = places_index();
+        assert_eq!(
+            index.lookup(&place(48.8606, 2.3376)),
+            vec![b"louvre".to_vec()]
+        );
+
+        index.remove(&place(48.8606, 2.3376), b"louvre");
+        assert!(index.lookup(&place(48.8606, 2.3376)).is_empty());
+        assert_eq!(index.cells.read().unwrap().len(), 5);
+    }
+}
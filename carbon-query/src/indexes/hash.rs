@@ -0,0 +1,92 @@
+use crate::definition::{IndexDefinition, IndexKey};
+use crate::indexes::SecondaryIndex;
+use dashmap::DashMap;
+use std::collections::BTreeSet;
+
+/// Equality index backed by a concurrent hash map
+/// Composite keys can only be looked up with a value for every field
+pub struct HashIndex {
+    definition: IndexDefinition,
+    entries: DashMap<IndexKey, BTreeSet<Vec<u8>>>,
+}
+
+impl HashIndex {
+    pub fn new(definition: IndexDefinition) -> Self {
+        Self {
+            definition,
+            entries: DashMap::new(),
+        }
+    }
+}
+
+impl SecondaryIndex for HashIndex {
+    fn definition(&self) -> &IndexDefinition {
+        &self.definition
+    }
+
+    fn insert(&self, index_key: IndexKey, key: &[u8]) {
+        self.entries
+            .entry(index_key)
+            .or_default()
+            .insert(key.to_vec());
+    }
+
+    fn remove(&self, index_key: &IndexKey, key: &[u8]) {
+        if let Some(mut keys) = self.entries.get_mut(index_key) {
+            keys.remove(key);
+        }
+        self.entries.remove_if(index_key, |_, keys| keys.is_empty());
+    }
+
+    fn lookup(&self, index_key: &IndexKey) -> Vec<Vec<u8>> {
+        self.entries
+            .get(index_key)
+            .map(|keys| keys.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{FieldDefinition, FieldType, FieldValue, IndexKind};
+    use std::ops::Bound;
+
+    fn status_index() -> HashIndex {
+        HashIndex::new(IndexDefinition::new(
+            "by_status",
+            FieldDefinition::new("status", FieldType::String),
+            IndexKind::Hash,
+        ))
+    }
+
+    #[test]
+    fn test_insert_lookup_remove() {
+        let index = status_index();
+        let active = IndexKey::from(FieldValue::String("active".to_string()));
+
+        index.insert(active.clone(), b"user:1");
+        index.insert(active.clone(), b"user:2");
+        assert_eq!(
+            index.lookup(&active),
+            vec![b"user:1".to_vec(), b"user:2".to_vec()]
+        );
+
+        index.remove(&active, b"user:1");
+        assert_eq!(index.lookup(&active), vec![b"user:2".to_vec()]);
+
+        index.remove(&active, b"user:2");
+        assert!(index.lookup(&active).is_empty());
+        assert!(index.entries.is_empty());
+    }
+
+    #[test]
+    fn test_no_scan_support() {
+        let index = status_index();
+        assert!(
+            index
+                .scan(&[], Bound::Unbounded, Bound::Unbounded)
+                .is_none()
+        );
+    }
+}
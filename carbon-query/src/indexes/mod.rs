@@ -0,0 +1,58 @@
+mod geo;
+mod hash;
+mod range;
+mod text;
+
+pub use geo::{GeoIndex, geohash, haversine_m};
+pub use hash::HashIndex;
+pub use range::RangeIndex;
+pub use text::{TextIndex, tokenize};
+
+use crate::definition::{FieldValue, IndexDefinition, IndexKey, IndexKind};
+use std::ops::Bound;
+use std::sync::Arc;
+
+/// A secondary index mapping index keys to the cache keys whose documents hold them
+pub trait SecondaryIndex: Send + Sync {
+    fn definition(&self) -> &IndexDefinition;
+
+    fn insert(&self, index_key: IndexKey, key: &[u8]);
+
+    fn remove(&self, index_key: &IndexKey, key: &[u8]);
+
+    /// Keys stored under exactly `index_key`
+    fn lookup(&self, index_key: &IndexKey) -> Vec<Vec<u8>>;
+
+    /// Keys whose index key starts with `prefix` and whose next field lies within the bounds
+    /// None when the index is unordered
+    fn scan(
+        &self,
+        _prefix: &[FieldValue],
+        _lower: Bound<&FieldValue>,
+        _upper: Bound<&FieldValue>,
+    ) -> Option<Vec<Vec<u8>>> {
+        None
+    }
+
+    /// Keys whose text contains every term, each term matching as a prefix when `prefix` is set
+    /// None unless the index is a Text index
+    fn search(&self, _terms: &[String], _prefix: bool) -> Option<Vec<Vec<u8>>> {
+        None
+    }
+
+    /// Keys whose point lies within `radius_m` metres of the centre
+    /// None unless the index is a Geo index
+    fn within(&self, _lat: f64, _lon: f64, _radius_m: f64) -> Option<Vec<Vec<u8>>> {
+        None
+    }
+}
+
+/// Create an empty index for `definition`
+pub fn build_index(definition: IndexDefinition) -> Arc<dyn SecondaryIndex> {
+    match definition.kind {
+        IndexKind::Hash => Arc::new(HashIndex::new(definition)),
+        IndexKind::Range => Arc::new(RangeIndex::new(definition)),
+        IndexKind::Text => Arc::new(TextIndex::new(definition)),
+        IndexKind::Geo => Arc::new(GeoIndex::new(definition)),
+    }
+}
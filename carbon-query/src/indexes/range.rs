@@ -0,0 +1,200 @@
+use crate::definition::{FieldValue, IndexDefinition, IndexKey};
+use crate::indexes::SecondaryIndex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+use std::sync::RwLock;
+
+/// Ordered index supporting exact lookups and prefix/range scans
+pub struct RangeIndex {
+    definition: IndexDefinition,
+    entries: RwLock<BTreeMap<IndexKey, BTreeSet<Vec<u8>>>>,
+}
+
+impl RangeIndex {
+    pub fn new(definition: IndexDefinition) -> Self {
+        Self {
+            definition,
+            entries: RwLock::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl SecondaryIndex for RangeIndex {
+    fn definition(&self) -> &IndexDefinition {
+        &self.definition
+    }
+
+    fn insert(&self, index_key: IndexKey, key: &[u8]) {
+        let mut entries = self.entries.write().unwrap();
+        entries.entry(index_key).or_default().insert(key.to_vec());
+    }
+
+    fn remove(&self, index_key: &IndexKey, key: &[u8]) {
+        let mut entries = self.entries.write().unwrap();
+        if let Some(keys) = entries.get_mut(index_key) {
+            keys.remove(key);
+            if keys.is_empty() {
+                entries.remove(index_key);
+            }
+        }
+    }
+
+    fn lookup(&self, index_key: &IndexKey) -> Vec<Vec<u8>> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(index_key)
+            .map(|keys| keys.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn scan(
+        &self,
+        prefix: &[FieldValue],
+        lower: Bound<&FieldValue>,
+        upper: Bound<&FieldValue>,
+    ) -> Option<Vec<Vec<u8>>> {
+        // Seek to the first key that can satisfy the lower bound, everything with the
+        // prefix follows contiguously
+        let mut start = prefix.to_vec();
+        if let Bound::Included(value) | Bound::Excluded(value) = lower {
+            start.push(value.clone());
+        }
+        let position = prefix.len();
+
+        let entries = self.entries.read().unwrap();
+        let mut keys = Vec::new();
+        for (index_key, matching) in entries.range(IndexKey(start)..) {
+            if !index_key.starts_with(prefix) {
+                break;
+            }
+
+            if let Some(value) = index_key.0.get(position) {
+                if matches!(lower, Bound::Excluded(low) if value == low) {
+                    continue;
+                }
+                let past_upper = match upper {
+                    Bound::Included(high) => value > high,
+                    Bound::Excluded(high) => value >= high,
+                    Bound::Unbounded => false,
+                };
+                if past_upper {
+                    break;
+                }
+            }
+
+            keys.extend(matching.iter().cloned());
+        }
+
+        Some(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{FieldDefinition, FieldType, IndexKind};
+
+    fn age_index() -> RangeIndex {
+        let index = RangeIndex::new(IndexDefinition::new(
+            "by_age",
+            FieldDefinition::new("age", FieldType::Number),
+            IndexKind::Range,
+        ));
+        index.insert(IndexKey::from(FieldValue::number(25.0)), b"alice");
+        index.insert(IndexKey::from(FieldValue::number(31.0)), b"bob");
+        index.insert(IndexKey::from(FieldValue::number(42.0)), b"carol");
+        index
+    }
+
+    fn city_index() -> RangeIndex {
+        let index = RangeIndex::new(IndexDefinition::composite(
+            "by_country_city",
+            vec![
+                FieldDefinition::new("country", FieldType::String),
+                FieldDefinition::new("city", FieldType::String),
+            ],
+            IndexKind::Range,
+        ));
+        for (key, country, city) in [
+            ("a", "DE", "Berlin"),
+            ("b", "FR", "Lyon"),
+            ("c", "FR", "Nice"),
+            ("d", "FR", "Paris"),
+            ("e", "UK", "Leeds"),
+        ] {
+            let index_key = IndexKey(vec![
+                FieldValue::String(country.to_string()),
+                FieldValue::String(city.to_string()),
+            ]);
+            index.insert(index_key, key.as_bytes());
+        }
+        index
+    }
+
+    fn text(value: &str) -> FieldValue {
+        FieldValue::String(value.to_string())
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        let index = age_index();
+        let (low, high) = (FieldValue::number(25.0), FieldValue::number(42.0));
+
+        let inclusive = index.scan(&[], Bound::Included(&low), Bound::Included(&high));
+        assert_eq!(inclusive.unwrap().len(), 3);
+
+        let exclusive = index.scan(&[], Bound::Excluded(&low), Bound::Excluded(&high));
+        assert_eq!(exclusive.unwrap(), vec![b"bob".to_vec()]);
+
+        let open = index.scan(
+            &[],
+            Bound::Included(&FieldValue::number(30.0)),
+            Bound::Unbounded,
+        );
+        assert_eq!(open.unwrap(), vec![b"bob".to_vec(), b"carol".to_vec()]);
+    }
+
+    #[test]
+    fn test_inverted_range_is_empty() {
+        let index = age_index();
+        let (low, high) = (FieldValue::number(40.0), FieldValue::number(30.0));
+        let result = index.scan(&[], Bound::Included(&low), Bound::Included(&high));
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prefix_scan() {
+        let index = city_index();
+
+        let france = index.scan(&[text("FR")], Bound::Unbounded, Bound::Unbounded);
+        assert_eq!(
+            france.unwrap(),
+            vec![b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]
+        );
+
+        let after_lyon = index.scan(
+            &[text("FR")],
+            Bound::Excluded(&text("Lyon")),
+            Bound::Excluded(&text("Paris")),
+        );
+        assert_eq!(after_lyon.unwrap(), vec![b"c".to_vec()]);
+
+        let missing = index.scan(&[text("ES")], Bound::Unbounded, Bound::Unbounded);
+        assert!(missing.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_remove() {
+        let index = age_index();
+        index.remove(&IndexKey::from(FieldValue::number(31.0)), b"bob");
+        assert!(
+            index
+                .lookup(&IndexKey::from(FieldValue::number(31.0)))
+                .is_empty()
+        );
+        assert_eq!(
+            index.lookup(&IndexKey::from(FieldValue::number(25.0))),
+            vec![b"alice".to_vec()]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use crate::definition::{FieldValue, IndexDefinition, IndexKey};
+use crate::indexes::SecondaryIndex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::RwLock;
+
+/// Split `text` into lowercase alphanumeric terms
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Inverted index from the terms of a string field to the keys containing them
+/// Terms are kept ordered so a prefix query is a contiguous scan
+pub struct TextIndex {
+    definition: IndexDefinition,
+    terms: RwLock<BTreeMap<String, BTreeSet<Vec<u8>>>>,
+}
+
+impl TextIndex {
+    pub fn new(definition: IndexDefinition) -> Self {
+        Self {
+            definition,
+            terms: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    fn text_terms(index_key: &IndexKey) -> BTreeSet<String> {
+        match index_key.0.first() {
+            Some(FieldValue::String(text)) => tokenize(text).into_iter().collect(),
+            _ => BTreeSet::new(),
+        }
+    }
+}
+
+impl SecondaryIndex for TextIndex {
+    fn definition(&self) -> &IndexDefinition {
+        &self.definition
+    }
+
+    fn insert(&self, index_key: IndexKey, key: &[u8]) {
+        let mut terms = self.terms.write().unwrap();
+        for term in Self::text_terms(&index_key) {
+            terms.entry(term).or_default().insert(key.to_vec());
+        }
+    }
+
+    fn remove(&self, index_key: &IndexKey, key: &[u8]) {
+        let mut terms = self.terms.write().unwrap();
+        for term in Self::text_terms(index_key) {
+            if let Some(keys) = terms.get_mut(&term) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    terms.remove(&term);
+                }
+            }
+        }
+    }
+
+    /// Keys containing every term of the indexed text
+    fn lookup(&self, index_key: &IndexKey) -> Vec<Vec<u8>> {
+        let terms: Vec<_> = Self::text_terms(index_key).into_iter().collect();
+        self.search(&terms, false).unwrap_or_default()
+    }
+
+    fn search(&self, terms: &[String], prefix: bool) -> Option<Vec<Vec<u8>>> {
+        let index = self.terms.read().unwrap();
+
+        let mut result: Option<BTreeSet<Vec<u8>>> = None;
+        for term in terms {
+            let keys: BTreeSet<Vec<u8>> = if prefix {
+                index
+                    .range(term.clone()..)
+                    .take_while(|(candidate, _)| candidate.starts_with(term.as_str()))
+                    .flat_map(|(_, keys)| keys.iter().cloned())
+                    .collect()
+            } else {
+                index.get(term).cloned().unwrap_or_default()
+            };
+
+            let matched: BTreeSet<_> = match result {
+                Some(current) => current.intersection(&keys).cloned().collect(),
+                None => keys,
+            };
+            if matched.is_empty() {
+                return Some(Vec::new());
+            }
+            result = Some(matched);
+        }
+
+        Some(result.unwrap_or_default().into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{FieldDefinition, FieldType, IndexKind};
+
+    fn bio_index() -> TextIndex {
+        let index = TextIndex::new(IndexDefinition::new(
+            "bio_text",
+            FieldDefinition::new("bio", FieldType::String),
+            IndexKind::Text,
+        ));
+        for (key, bio) in [
+            ("alice", "Rust developer, Paris"),
+            ("bob", "Go developer in Berlin"),
+            ("carol", "Parisian baker"),
+        ] {
+            index.insert(
+                IndexKey::from(FieldValue::String(bio.to_string())),
+                key.as_bytes(),
+            );
+        }
+        index
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Hello, World! rust-lang 2024"),
+            vec!["hello", "world", "rust", "lang", "2024"]
+        );
+        assert!(tokenize(" -- ").is_empty());
+    }
+
+    #[test]
+    fn test_term_and_prefix_search() {
+        let index = bio_index();
+
+        let developers = index.search(&tokenize("DEVELOPER"), false).unwrap();
+        assert_eq!(developers, vec![b"alice".to_vec(), b"bob".to_vec()]);
+
+        let rust_in_paris = index.search(&tokenize("paris rust"), false).unwrap();
+        assert_eq!(rust_in_paris, vec![b"alice".to_vec()]);
+
+        let paris_prefix = index.search(&tokenize("pari"), true).unwrap();
+        assert_eq!(paris_prefix, vec![b"alice".to_vec(), b"carol".to_vec()]);
+
+        assert!(index.search(&tokenize("pari"), false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_remove_drops_empty_terms() {
+        let index = bio_index();
+        let bio = IndexKey::from(FieldValue::String("Parisian baker".to_string()));
+        index.remove(&bio, b"carol");
+
+        assert!(index.search(&tokenize("baker"), false).unwrap().is_empty());
+        assert!(!index.terms.read().unwrap().contains_key("parisian"));
+    }
+}
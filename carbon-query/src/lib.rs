@@ -0,0 +1,15 @@
+pub mod codegen;
+pub mod definition;
+pub mod indexes;
+pub mod query;
+pub mod queryable;
+pub mod registry;
+pub mod schema;
+
+pub use definition::{
+    FieldDefinition, FieldType, FieldValue, IndexDefinition, IndexKey, IndexKind,
+};
+pub use query::{GeoRadius, Operator, Predicate, Query, QueryResult};
+pub use queryable::QueryableCache;
+pub use registry::{CacheIndexes, IndexRegistry};
+pub use schema::{CacheSchema, SchemaField};
@@ -0,0 +1,683 @@
+use crate::definition::{FieldDefinition, FieldValue, IndexDefinition, IndexKey, IndexKind};
+use crate::indexes::{SecondaryIndex, build_index, tokenize};
+use crate::registry::CacheIndexes;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shared::{Error, Result};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::ops::Bound;
+use std::sync::Arc;
+
+/// Page size used when a query does not set a limit
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// Upper bound on the page size, larger limits are clamped
+pub const MAX_QUERY_LIMIT: usize = 1000;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operator {
+    Eq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// Every term of the value appears in the field
+    Contains,
+    /// Every term of the value is a prefix of a term in the field
+    Matches,
+    /// The point lies within `radius_m` metres of the `lat` and `lon` of the value
+    Near,
+}
+
+impl Operator {
+    /// Range operators can only be answered by Range indexes
+    pub fn is_range(&self) -> bool {
+        matches!(
+            self,
+            Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte
+        )
+    }
+
+    /// Text operators can only be answered by Text indexes
+    pub fn is_text(&self) -> bool {
+        matches!(self, Operator::Contains | Operator::Matches)
+    }
+
+    /// Geo operators can only be answered by Geo indexes
+    pub fn is_geo(&self) -> bool {
+        *self == Operator::Near
+    }
+}
+
+/// `{"field": "status", "op": "eq", "value": "active"}`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Predicate {
+    pub field: String,
+    pub op: Operator,
+    pub value: Value,
+}
+
+/// Value of a `near` predicate, distances in metres
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GeoRadius {
+    pub lat: f64,
+    pub lon: f64,
+    pub radius_m: f64,
+}
+
+/// Predicates combined with AND, paginated by offset into the matching keys
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Query {
+    pub predicates: Vec<Predicate>,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    /// Page size after applying the default and the guardrail
+    pub fn effective_limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct QueryResult {
+    /// Matching keys in key order
+    pub keys: Vec<Vec<u8>>,
+    /// Offset of the next page, None on the last page
+    pub next_offset: Option<usize>,
+}
+
+/// Index chosen to answer some of a query's predicates
+/// Eq predicates on a leading run of the index fields form the prefix, a Range index
+/// can additionally bound the field after the prefix, a Text index searches for terms
+/// and a Geo index for points around a centre
+struct Plan {
+    definition: IndexDefinition,
+    prefix: Vec<FieldValue>,
+    lower: Bound<FieldValue>,
+    upper: Bound<FieldValue>,
+    terms: Vec<String>,
+    match_prefix: bool,
+    near: Option<GeoRadius>,
+    /// Positions of the predicates the index answers
+    consumed: Vec<usize>,
+}
+
+impl Plan {
+    /// Whether this plan should replace `other`: more predicates answered wins,
+    /// then Hash over Range, otherwise the first index by name is kept
+    fn beats(&self, other: &Plan) -> bool {
+        match self.consumed.len().cmp(&other.consumed.len()) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => {
+                self.definition.kind == IndexKind::Hash && other.definition.kind == IndexKind::Range
+            }
+        }
+    }
+}
+
+/// Indexes chosen to answer every predicate of a query
+pub struct QueryPlan {
+    plans: Vec<Plan>,
+}
+
+impl QueryPlan {
+    /// Whether `document` satisfies every predicate
+    /// Indexes can lag behind the store, so candidates are checked against their current document
+    pub fn matches(&self, document: &Value) -> bool {
+        self.plans.iter().all(|plan| {
+            let Some(index_key) = plan.definition.extract(document) else {
+                return false;
+            };
+            let index = build_index(plan.definition.clone());
+            index.insert(index_key, &[]);
+            run(plan, index.as_ref()).is_ok_and(|keys| !keys.is_empty())
+        })
+    }
+}
+
+impl CacheIndexes {
+    /// Keys matching every predicate of `query`, before pagination
+    /// Keys may include entries the store has since expired, evicted or changed
+    pub fn candidates(&self, query: &Query) -> Result<BTreeSet<Vec<u8>>> {
+        let plan = self.plan_query(query)?;
+        self.candidates_for(&plan)
+    }
+
+    /// Split `query` into the index lookups answering it
+    pub fn plan_query(&self, query: &Query) -> Result<QueryPlan> {
+        if query.predicates.is_empty() {
+            return Err(Error::InvalidValue(
+                "query needs at least one predicate".to_string(),
+            ));
+        }
+
+        let mut remaining: Vec<usize> = (0..query.predicates.len()).collect();
+        let mut plans = Vec::new();
+        while !remaining.is_empty() {
+            let plan = self.plan(&query.predicates, &remaining)?;
+            remaining.retain(|position| !plan.consumed.contains(position));
+            plans.push(plan);
+        }
+
+        Ok(QueryPlan { plans })
+    }
+
+    /// Keys found by every lookup of `plan`
+    pub fn candidates_for(&self, plan: &QueryPlan) -> Result<BTreeSet<Vec<u8>>> {
+        let mut result: Option<BTreeSet<Vec<u8>>> = None;
+        for plan in &plan.plans {
+            let keys = self.execute(plan)?;
+            let matched = match result {
+                Some(current) => current.intersection(&keys).cloned().collect(),
+                None => keys,
+            };
+
+            if matched.is_empty() {
+                return Ok(BTreeSet::new());
+            }
+            result = Some(matched);
+        }
+
+        Ok(result.unwrap_or_default())
+    }
+
+    /// Pick the index answering the most of the `remaining` predicates
+    fn plan(&self, predicates: &[Predicate], remaining: &[usize]) -> Result<Plan> {
+        let mut best: Option<Plan> = None;
+        for definition in self.definitions() {
+            let Some(plan) = plan_for(definition, predicates, remaining)? else {
+                continue;
+            };
+            if best.as_ref().is_none_or(|current| plan.beats(current)) {
+                best = Some(plan);
+            }
+        }
+
+        best.ok_or_else(|| {
+            let predicate = &predicates[remaining[0]];
+            if predicate.op.is_range() {
+                Error::InvalidValue(format!("no range index on field '{}'", predicate.field))
+            } else if predicate.op.is_text() {
+                Error::InvalidValue(format!("no text index on field '{}'", predicate.field))
+            } else if predicate.op.is_geo() {
+                Error::InvalidValue(format!("no geo index on field '{}'", predicate.field))
+            } else {
+                Error::InvalidValue(format!("no index on field '{}'", predicate.field))
+            }
+        })
+    }
+
+    fn execute(&self, plan: &Plan) -> Result<BTreeSet<Vec<u8>>> {
+        let index = self.require_index(&plan.definition.name)?;
+        run(plan, index.as_ref())
+    }
+
+    fn require_index(&self, name: &str) -> Result<Arc<dyn SecondaryIndex>> {
+        self.index(name)
+            .ok_or_else(|| Error::InvalidValue(format!("unknown index '{}'", name)))
+    }
+}
+
+/// Keys of `index` matching `plan`
+fn run(plan: &Plan, index: &dyn SecondaryIndex) -> Result<BTreeSet<Vec<u8>>> {
+    let keys = match plan.definition.kind {
+        IndexKind::Hash => index.lookup(&IndexKey(plan.prefix.clone())),
+        IndexKind::Range => index
+            .scan(&plan.prefix, plan.lower.as_ref(), plan.upper.as_ref())
+            .ok_or_else(|| {
+                Error::InvalidValue(format!(
+                    "index '{}' does not support range queries",
+                    plan.definition.name
+                ))
+            })?,
+        IndexKind::Text => index
+            .search(&plan.terms, plan.match_prefix)
+            .ok_or_else(|| {
+                Error::InvalidValue(format!(
+                    "index '{}' does not support text queries",
+                    plan.definition.name
+                ))
+            })?,
+        IndexKind::Geo => {
+            let near = plan.near.expect("geo plans have a centre");
+            index
+                .within(near.lat, near.lon, near.radius_m)
+                .ok_or_else(|| {
+                    Error::InvalidValue(format!(
+                        "index '{}' does not support geo queries",
+                        plan.definition.name
+                    ))
+                })?
+        }
+    };
+
+    Ok(keys.into_iter().collect())
+}
+
+/// Match the `remaining` predicates against `definition`, None when the index cannot help
+fn plan_for(
+    definition: IndexDefinition,
+    predicates: &[Predicate],
+    remaining: &[usize],
+) -> Result<Option<Plan>> {
+    let find = |field: &str, accepts: fn(Operator) -> bool, consumed: &[usize]| {
+        remaining.iter().copied().find(|position| {
+            let predicate = &predicates[*position];
+            predicate.field == field && accepts(predicate.op) && !consumed.contains(position)
+        })
+    };
+
+    if definition.kind == IndexKind::Text {
+        return text_plan(definition, predicates, remaining);
+    }
+    if definition.kind == IndexKind::Geo {
+        return geo_plan(definition, predicates, remaining);
+    }
+
+    let mut prefix = Vec::new();
+    let mut consumed = Vec::new();
+    for field in &definition.fields {
+        let Some(position) = find(&field.name, |op| op == Operator::Eq, &consumed) else {
+            break;
+        };
+        prefix.push(field_value(&predicates[position], field)?);
+        consumed.push(position);
+    }
+
+    let (mut lower, mut upper) = (Bound::Unbounded, Bound::Unbounded);
+    match definition.kind {
+        // Hashed keys can only be probed with a value for every field
+        IndexKind::Hash if prefix.len() < definition.fields.len() => return Ok(None),
+        IndexKind::Hash => {}
+        IndexKind::Text | IndexKind::Geo => {}
+        IndexKind::Range => {
+            if let Some(field) = definition.fields.get(prefix.len()) {
+                let is_lower = |op: Operator| matches!(op, Operator::Gt | Operator::Gte);
+                if let Some(position) = find(&field.name, is_lower, &consumed) {
+                    let value = field_value(&predicates[position], field)?;
+                    lower = match predicates[position].op {
+                        Operator::Gt => Bound::Excluded(value),
+                        _ => Bound::Included(value),
+                    };
+                    consumed.push(position);
+                }
+
+                let is_upper = |op: Operator| matches!(op, Operator::Lt | Operator::Lte);
+                if let Some(position) = find(&field.name, is_upper, &consumed) {
+                    let value = field_value(&predicates[position], field)?;
+                    upper = match predicates[position].op {
+                        Operator::Lt => Bound::Excluded(value),
+                        _ => Bound::Included(value),
+                    };
+                    consumed.push(position);
+                }
+            }
+        }
+    }
+
+    if consumed.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(Plan {
+        definition,
+        prefix,
+        lower,
+        upper,
+        terms: Vec::new(),
+        match_prefix: false,
+        near: None,
+        consumed,
+    }))
+}
+
+/// Answer one `contains`/`matches` predicate on the field of a Text index
+fn text_plan(
+    definition: IndexDefinition,
+    predicates: &[Predicate],
+    remaining: &[usize],
+) -> Result<Option<Plan>> {
+    let Some(position) = remaining.iter().copied().find(|position| {
+        let predicate = &predicates[*position];
+        predicate.op.is_text() && predicate.field == definition.fields[0].name
+    }) else {
+        return Ok(None);
+    };
+
+    let predicate = &predicates[position];
+    let FieldValue::String(text) = field_value(predicate, &definition.fields[0])? else {
+        unreachable!("text indexes only cover string fields");
+    };
+    let terms = tokenize(&text);
+    if terms.is_empty() {
+        return Err(Error::InvalidValue(format!(
+            "text query on '{}' has no terms",
+            predicate.field
+        )));
+    }
+
+    Ok(Some(Plan {
+        definition,
+        prefix: Vec::new(),
+        lower: Bound::Unbounded,
+        upper: Bound::Unbounded,
+        terms,
+        match_prefix: predicate.op == Operator::Matches,
+        near: None,
+        consumed: vec![position],
+    }))
+}
+
+/// Answer one `near` predicate on the field of a Geo index
+fn geo_plan(
+    definition: IndexDefinition,
+    predicates: &[Predicate],
+    remaining: &[usize],
+) -> Result<Option<Plan>> {
+    let Some(position) = remaining.iter().copied().find(|position| {
+        let predicate = &predicates[*position];
+        predicate.op.is_geo() && predicate.field == definition.fields[0].name
+    }) else {
+        return Ok(None);
+    };
+
+    let predicate = &predicates[position];
+    let invalid = || {
+        Error::InvalidValue(format!(
+            "value for '{}' must be {{\"lat\", \"lon\", \"radius_m\"}} with a finite, \
+             non-negative radius",
+            predicate.field
+        ))
+    };
+    let near: GeoRadius = serde_json::from_value(predicate.value.clone()).map_err(|_| invalid())?;
+    if FieldValue::geo_point(near.lat, near.lon).is_none()
+        || !near.radius_m.is_finite()
+        || near.radius_m < 0.0
+    {
+        return Err(invalid());
+    }
+
+    Ok(Some(Plan {
+        definition,
+        prefix: Vec::new(),
+        lower: Bound::Unbounded,
+        upper: Bound::Unbounded,
+        terms: Vec::new(),
+        match_prefix: false,
+        near: Some(near),
+        consumed: vec![position],
+    }))
+}
+
+fn field_value(predicate: &Predicate, field: &FieldDefinition) -> Result<FieldValue> {
+    FieldValue::from_json(&predicate.value, field.field_type).ok_or_else(|| {
+        Error::InvalidValue(format!(
+            "value for '{}' must be a {:?}",
+            predicate.field, field.field_type
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::FieldType;
+    use serde_json::json;
+
+    fn indexes() -> CacheIndexes {
+        let indexes = CacheIndexes::new();
+        indexes
+            .create_index(IndexDefinition::new(
+                "by_status",
+                FieldDefinition::new("status", FieldType::String),
+                IndexKind::Hash,
+            ))
+            .unwrap();
+        indexes
+            .create_index(IndexDefinition::new(
+                "by_age",
+                FieldDefinition::new("age", FieldType::Number),
+                IndexKind::Range,
+            ))
+            .unwrap();
+
+        indexes.index_document(b"alice", &json!({"status": "active", "age": 25}));
+        indexes.index_document(b"bob", &json!({"status": "active", "age": 31}));
+        indexes.index_document(b"carol", &json!({"status": "disabled", "age": 42}));
+        indexes
+    }
+
+    fn query(predicates: Value) -> Query {
+        serde_json::from_value(json!({ "predicates": predicates })).unwrap()
+    }
+
+    #[test]
+    fn test_and_of_eq_and_range() {
+        let q = query(json!([
+            {"field": "status", "op": "eq", "value": "active"},
+            {"field": "age", "op": "gt", "value": 30}
+        ]));
+        let keys = indexes().candidates(&q).unwrap();
+        assert_eq!(keys.into_iter().collect::<Vec<_>>(), vec![b"bob".to_vec()]);
+    }
+
+    #[test]
+    fn test_range_operators() {
+        let indexes = indexes();
+        let count = |op: &str, value: i64| {
+            let q = query(json!([{"field": "age", "op": op, "value": value}]));
+            indexes.candidates(&q).unwrap().len()
+        };
+
+        assert_eq!(count("gte", 31), 2);
+        assert_eq!(count("gt", 31), 1);
+        assert_eq!(count("lt", 31), 1);
+        assert_eq!(count("lte", 31), 2);
+        assert_eq!(count("eq", 42), 1);
+    }
+
+    #[test]
+    fn test_rejects_unindexed_fields_and_bad_values() {
+        let indexes = indexes();
+
+        let unindexed = query(json!([{"field": "name", "op": "eq", "value": "x"}]));
+        assert!(indexes.candidates(&unindexed).is_err());
+
+        let range_on_hash = query(json!([{"field": "status", "op": "gt", "value": "a"}]));
+        assert!(indexes.candidates(&range_on_hash).is_err());
+
+        let wrong_type = query(json!([{"field": "age", "op": "eq", "value": "old"}]));
+        assert!(indexes.candidates(&wrong_type).is_err());
+
+        assert!(indexes.candidates(&Query::default()).is_err());
+    }
+
+    fn location_indexes() -> CacheIndexes {
+        let indexes = CacheIndexes::new();
+        let country = FieldDefinition::new("country", FieldType::String);
+        let city = FieldDefinition::new("city", FieldType::String);
+        let age = FieldDefinition::new("age", FieldType::Number);
+        for definition in [
+            IndexDefinition::new("by_country", country.clone(), IndexKind::Hash),
+            IndexDefinition::composite(
+                "by_country_city",
+                vec![country.clone(), city.clone()],
+                IndexKind::Hash,
+            ),
+            IndexDefinition::composite("by_country_age", vec![country, age], IndexKind::Range),
+        ] {
+            indexes.create_index(definition).unwrap();
+        }
+
+        indexes.index_document(b"a", &json!({"country": "FR", "city": "Lyon", "age": 25}));
+        indexes.index_document(b"b", &json!({"country": "FR", "city": "Paris", "age": 31}));
+        indexes.index_document(b"c", &json!({"country": "FR", "city": "Paris", "age": 42}));
+        indexes.index_document(b"d", &json!({"country": "UK", "city": "Leeds", "age": 37}));
+        indexes
+    }
+
+    fn chosen_index(indexes: &CacheIndexes, predicates: Value) -> String {
+        let q = query(predicates);
+        let remaining: Vec<usize> = (0..q.predicates.len()).collect();
+        indexes
+            .plan(&q.predicates, &remaining)
+            .unwrap()
+            .definition
+            .name
+    }
+
+    #[test]
+    fn test_planner_prefers_index_covering_most_predicates() {
+        let indexes = location_indexes();
+
+        let country_only = json!([{"field": "country", "op": "eq", "value": "FR"}]);
+        assert_eq!(chosen_index(&indexes, country_only), "by_country");
+
+        let country_city = json!([
+            {"field": "city", "op": "eq", "value": "Paris"},
+            {"field": "country", "op": "eq", "value": "FR"}
+        ]);
+        assert_eq!(chosen_index(&indexes, country_city), "by_country_city");
+
+        let country_age = json!([
+            {"field": "country", "op": "eq", "value": "FR"},
+            {"field": "age", "op": "gte", "value": 30},
+            {"field": "age", "op": "lt", "value": 40}
+        ]);
+        assert_eq!(chosen_index(&indexes, country_age), "by_country_age");
+    }
+
+    #[test]
+    fn test_composite_prefix_queries() {
+        let indexes = location_indexes();
+        let keys = |predicates: Value| {
+            let keys = indexes.candidates(&query(predicates)).unwrap();
+            keys.into_iter().collect::<Vec<_>>()
+        };
+
+        let paris = keys(json!([
+            {"field": "country", "op": "eq", "value": "FR"},
+            {"field": "city", "op": "eq", "value": "Paris"}
+        ]));
+        assert_eq!(paris, vec![b"b".to_vec(), b"c".to_vec()]);
+
+        let french_thirties = keys(json!([
+            {"field": "country", "op": "eq", "value": "FR"},
+            {"field": "age", "op": "gt", "value": 25},
+            {"field": "age", "op": "lte", "value": 40}
+        ]));
+        assert_eq!(french_thirties, vec![b"b".to_vec()]);
+
+        // City alone is not a prefix of any index
+        let city_only = query(json!([{"field": "city", "op": "eq", "value": "Leeds"}]));
+        assert!(indexes.candidates(&city_only).is_err());
+    }
+
+    #[test]
+    fn test_text_operators() {
+        let indexes = indexes();
+        indexes
+            .create_index(IndexDefinition::new(
+                "bio_text",
+                FieldDefinition::new("bio", FieldType::String),
+                IndexKind::Text,
+            ))
+            .unwrap();
+        indexes.index_document(
+            b"alice",
+            &json!({"status": "active", "bio": "Rust developer"}),
+        );
+        indexes.index_document(
+            b"bob",
+            &json!({"status": "active", "bio": "Rusty bike mechanic"}),
+        );
+        indexes.index_document(
+            b"carol",
+            &json!({"status": "disabled", "bio": "rust trainer"}),
+        );
+
+        let keys = |predicates: Value| {
+            let keys = indexes.candidates(&query(predicates)).unwrap();
+            keys.into_iter().collect::<Vec<_>>()
+        };
+
+        let contains = keys(json!([{"field": "bio", "op": "contains", "value": "RUST"}]));
+        assert_eq!(contains, vec![b"alice".to_vec(), b"carol".to_vec()]);
+
+        let active_rust = keys(json!([
+            {"field": "bio", "op": "matches", "value": "rus"},
+            {"field": "status", "op": "eq", "value": "active"}
+        ]));
+        assert_eq!(active_rust, vec![b"alice".to_vec(), b"bob".to_vec()]);
+
+        let no_terms = query(json!([{"field": "bio", "op": "contains", "value": "!?"}]));
+        assert!(indexes.candidates(&no_terms).is_err());
+
+        let unindexed = query(json!([{"field": "status", "op": "contains", "value": "act"}]));
+        assert!(indexes.candidates(&unindexed).is_err());
+    }
+
+    #[test]
+    fn test_near_operator() {
+        let indexes = indexes();
+        indexes
+            .create_index(IndexDefinition::new(
+                "place_geo",
+                FieldDefinition::new("place", FieldType::GeoPoint),
+                IndexKind::Geo,
+            ))
+            .unwrap();
+        indexes.index_document(
+            b"alice",
+            &json!({"status": "active", "place": {"lat": 48.8606, "lon": 2.3376}}),
+        );
+        indexes.index_document(
+            b"bob",
+            &json!({"status": "active", "place": {"lat": 51.5074, "lon": -0.1278}}),
+        );
+        indexes.index_document(
+            b"carol",
+            &json!({"status": "disabled", "place": {"lat": 48.8530, "lon": 2.3499}}),
+        );
+
+        let keys = |predicates: Value| {
+            let keys = indexes.candidates(&query(predicates)).unwrap();
+            keys.into_iter().collect::<Vec<_>>()
+        };
+
+        let paris = json!({"lat": 48.8566, "lon": 2.3522, "radius_m": 2000});
+        let near_paris = keys(json!([{"field": "place", "op": "near", "value": paris}]));
+        assert_eq!(near_paris, vec![b"alice".to_vec(), b"carol".to_vec()]);
+
+        let active_near_paris = keys(json!([
+            {"field": "place", "op": "near", "value": paris},
+            {"field": "status", "op": "eq", "value": "active"}
+        ]));
+        assert_eq!(active_near_paris, vec![b"alice".to_vec()]);
+
+        let negative = json!({"lat": 48.8566, "lon": 2.3522, "radius_m": -1});
+        let bad_radius = query(json!([{"field": "place", "op": "near", "value": negative}]));
+        assert!(indexes.candidates(&bad_radius).is_err());
+
+        let unindexed = query(json!([{"field": "status", "op": "near", "value": paris}]));
+        assert!(indexes.candidates(&unindexed).is_err());
+    }
+
+    #[test]
+    fn test_limit_guardrail() {
+        let mut q = Query::default();
+        assert_eq!(q.effective_limit(), DEFAULT_QUERY_LIMIT);
+
+        q.limit = Some(MAX_QUERY_LIMIT * 10);
+        assert_eq!(q.effective_limit(), MAX_QUERY_LIMIT);
+    }
+}
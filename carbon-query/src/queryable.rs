@@ -0,0 +1,343 @@
+use crate::definition::{FieldValue, IndexKey};
+use crate::indexes::SecondaryIndex;
+use crate::query::{Query, QueryResult};
+use crate::registry::CacheIndexes;
+use async_trait::async_trait;
+use bytes::Bytes;
+use carbon::domain::response::{DeleteResponse, ExistsResponse, GetResponse, PutResponse};
+use carbon::domain::{EntryMetadata, EvictionAlgorithm, ValueFormat};
+use carbon::encoding::decode_document;
+use carbon::ports::CacheStore;
+use serde_json::Value;
+use shared::{Error, Result};
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Cache store wrapper that keeps secondary indexes consistent with the JSON documents it stores
+pub struct QueryableCache {
+    store: Arc<dyn CacheStore<Vec<u8>, Bytes>>,
+    indexes: Arc<CacheIndexes>,
+    value_format: ValueFormat,
+}
+
+impl QueryableCache {
+    pub fn new(store: Arc<dyn CacheStore<Vec<u8>, Bytes>>, indexes: Arc<CacheIndexes>) -> Self {
+        Self {
+            store,
+            indexes,
+            value_format: ValueFormat::Json,
+        }
+    }
+
+    /// Encoding the cache stores its documents in, JSON by default
+    pub fn with_value_format(mut self, value_format: ValueFormat) -> Self {
+        self.value_format = value_format;
+        self
+    }
+
+    pub fn indexes(&self) -> &Arc<CacheIndexes> {
+        &self.indexes
+    }
+
+    /// Keys whose indexed fields equal `index_key`
+    pub async fn find_eq(&self, index_name: &str, index_key: &IndexKey) -> Result<Vec<Vec<u8>>> {
+        let keys = self.index(index_name)?.lookup(index_key);
+        self.live_keys(keys).await
+    }
+
+    /// Keys whose leading indexed fields equal `prefix` and whose next field lies
+    /// within the bounds (Range indexes only)
+    pub async fn find_range(
+        &self,
+        index_name: &str,
+        prefix: &[FieldValue],
+        lower: Bound<&FieldValue>,
+        upper: Bound<&FieldValue>,
+    ) -> Result<Vec<Vec<u8>>> {
+        let index = self.index(index_name)?;
+        let keys = index.scan(prefix, lower, upper).ok_or_else(|| {
+            Error::InvalidValue(format!(
+                "index '{}' does not support range queries",
+                index_name
+            ))
+        })?;
+        self.live_keys(keys).await
+    }
+
+    /// Run `query` and return one page of matching keys
+    /// Each candidate is checked against its current document, so a write racing the index
+    /// update cannot return a key whose document no longer matches
+    pub async fn query(&self, query: &Query) -> Result<QueryResult> {
+        let limit = query.effective_limit();
+        let plan = self.indexes.plan_query(query)?;
+        let candidates = self.indexes.candidates_for(&plan)?;
+
+        let mut keys = Vec::new();
+        let mut next_offset = None;
+        for (position, key) in candidates.into_iter().enumerate().skip(query.offset) {
+            if keys.len() == limit {
+                next_offset = Some(position);
+                break;
+            }
+            match self.document(&key).await? {
+                Some(document) if plan.matches(&document) => keys.push(key),
+                Some(_) => {}
+                None => self.indexes.remove_key(&key),
+            }
+        }
+
+        Ok(QueryResult { keys, next_offset })
+    }
+
+    fn index(&self, index_name: &str) -> Result<Arc<dyn SecondaryIndex>> {
+        self.indexes
+            .index(index_name)
+            .ok_or_else(|| Error::InvalidValue(format!("unknown index '{}'", index_name)))
+    }
+
+    /// Current document of `key`, None once the store no longer holds it
+    /// Values that no longer decode are treated as not matching rather than failing the query
+    async fn document(&self, key: &Vec<u8>) -> Result<Option<Value>> {
+        match self.store.get(key).await {
+            Ok(response) if response.found => Ok(Some(
+                decode_document(&response.message, self.value_format).unwrap_or(Value::Null),
+            )),
+            Ok(_) | Err(Error::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Drop keys the store expired or evicted since they were indexed
+    async fn live_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+        let mut live = Vec::with_capacity(keys.len());
+        for key in keys {
+            if self.store.exists(&key).await?.exists {
+                live.push(key);
+            } else {
+                self.indexes.remove_key(&key);
+            }
+        }
+        Ok(live)
+    }
+}
+
+#[async_trait]
+impl CacheStore<Vec<u8>, Bytes> for QueryableCache {
+    async fn exists(&self, key: &Vec<u8>) -> Result<ExistsResponse> {
+        self.store.exists(key).await
+    }
+
+    async fn put(&self, key: Vec<u8>, val: Bytes) -> Result<PutResponse> {
+        let document = serde_json::from_slice(&val)
+            .map_err(|e| Error::InvalidValue(format!("value is not valid JSON: {}", e)))?;
+
+        let result = self.store.put(key.clone(), val).await?;
+        self.indexes.index_document(&key, &document);
+        Ok(result)
+    }
+
+    async fn get(&self, key: &Vec<u8>) -> Result<GetResponse<Bytes>> {
+        self.store.get(key).await
+    }
+
+    async fn delete(&self, key: &Vec<u8>) -> Result<DeleteResponse> {
+        let result = self.store.delete(key).await?;
+        self.indexes.remove_key(key);
+        Ok(result)
+    }
+
+    async fn get_and_delete(&self, key: &Vec<u8>) -> Result<GetResponse<Bytes>> {
+        let result = self.store.get_and_delete(key).await?;
+        self.indexes.remove_key(key);
+        Ok(result)
+    }
+
+    async fn get_and_expire(
+        &self,
+        key: &Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<GetResponse<Bytes>> {
+        self.store.get_and_expire(key, ttl).await
+    }
+
+    fn resize(&self, capacity: u64) -> Result<bool> {
+        self.store.resize(capacity)
+    }
+
+    fn eviction_policy(&self) -> Option<EvictionAlgorithm> {
+        self.store.eviction_policy()
+    }
+
+    async fn metadata(&self, key: &Vec<u8>) -> Result<Option<EntryMetadata>> {
+        self.store.metadata(key).await
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.store.ping().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{FieldDefinition, FieldType, IndexDefinition, IndexKind};
+    use dashmap::DashMap;
+
+    /// Minimal in-memory store (storage-engine cannot be used from here)
+    #[derive(Default)]
+    struct MemoryStore {
+        entries: DashMap<Vec<u8>, Bytes>,
+    }
+
+    #[async_trait]
+    impl CacheStore<Vec<u8>, Bytes> for MemoryStore {
+        async fn exists(&self, key: &Vec<u8>) -> Result<ExistsResponse> {
+            Ok(ExistsResponse::new(self.entries.contains_key(key)))
+        }
+
+        async fn put(&self, key: Vec<u8>, val: Bytes) -> Result<PutResponse> {
+            let created = self.entries.insert(key, val).is_none();
+            Ok(PutResponse::new(created, "ok"))
+        }
+
+        async fn get(&self, key: &Vec<u8>) -> Result<GetResponse<Bytes>> {
+            self.entries
+                .get(key)
+                .map(|value| GetResponse::new(true, value.clone()))
+                .ok_or(Error::NotFound)
+        }
+
+        async fn delete(&self, key: &Vec<u8>) -> Result<DeleteResponse> {
+            Ok(DeleteResponse::new(self.entries.remove(key).is_some()))
+        }
+
+        async fn get_and_delete(&self, key: &Vec<u8>) -> Result<GetResponse<Bytes>> {
+            self.entries
+                .remove(key)
+                .map(|(_, value)| GetResponse::new(true, value))
+                .ok_or(Error::NotFound)
+        }
+    }
+
+    fn queryable() -> (QueryableCache, Arc<MemoryStore>) {
+        let store = Arc::new(MemoryStore::default());
+        let indexes = Arc::new(CacheIndexes::new());
+        indexes
+            .create_index(IndexDefinition::new(
+                "by_age",
+                FieldDefinition::new("age", FieldType::Number),
+                IndexKind::Range,
+            ))
+            .unwrap();
+        indexes
+            .create_index(IndexDefinition::new(
+                "by_status",
+                FieldDefinition::new("status", FieldType::String),
+                IndexKind::Hash,
+            ))
+            .unwrap();
+        (QueryableCache::new(store.clone(), indexes), store)
+    }
+
+    async fn put(cache: &QueryableCache, key: &str, document: &str) {
+        cache
+            .put(key.as_bytes().to_vec(), Bytes::from(document.to_string()))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_eq_and_range() {
+        let (cache, _) = queryable();
+        put(&cache, "alice", r#"{"age": 25, "status": "active"}"#).await;
+        put(&cache, "bob", r#"{"age": 31, "status": "active"}"#).await;
+        put(&cache, "carol", r#"{"age": 42, "status": "disabled"}"#).await;
+
+        let active = IndexKey::from(FieldValue::String("active".to_string()));
+        let keys = cache.find_eq("by_status", &active).await.unwrap();
+        assert_eq!(keys, vec![b"alice".to_vec(), b"bob".to_vec()]);
+
+        let thirty = FieldValue::number(30.0);
+        let keys = cache
+            .find_range("by_age", &[], Bound::Included(&thirty), Bound::Unbounded)
+            .await
+            .unwrap();
+        assert_eq!(keys, vec![b"bob".to_vec(), b"carol".to_vec()]);
+
+        let result = cache
+            .find_range("by_status", &[], Bound::Unbounded, Bound::Unbounded)
+            .await;
+        assert!(matches!(result, Err(Error::InvalidValue(_))));
+    }
+
+    #[tokio::test]
+    async fn test_deleted_and_expired_keys_are_not_returned() {
+        let (cache, store) = queryable();
+        put(&cache, "alice", r#"{"status": "active"}"#).await;
+        put(&cache, "bob", r#"{"status": "active"}"#).await;
+
+        cache.delete(&b"alice".to_vec()).await.unwrap();
+
+        // Simulate the underlying store evicting an entry behind the wrapper's back
+        store.entries.remove(&b"bob".to_vec());
+
+        let active = IndexKey::from(FieldValue::String("active".to_string()));
+        assert!(
+            cache
+                .find_eq("by_status", &active)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_rechecks_current_documents() {
+        let (cache, store) = queryable();
+        put(&cache, "alice", r#"{"age": 25, "status": "active"}"#).await;
+        put(&cache, "bob", r#"{"age": 31, "status": "active"}"#).await;
+
+        // A write that reached the store without (yet) updating the indexes
+        store.entries.insert(
+            b"bob".to_vec(),
+            Bytes::from(r#"{"age": 31, "status": "disabled"}"#),
+        );
+
+        let query: Query = serde_json::from_str(
+            r#"{"predicates": [{"field": "status", "op": "eq", "value": "active"}]}"#,
+        )
+        .unwrap();
+        let result = cache.query(&query).await.unwrap();
+        assert_eq!(result.keys, vec![b"alice".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_json() {
+        let (cache, _) = queryable();
+        let result = cache.put(b"bad".to_vec(), Bytes::from("not json")).await;
+        assert!(matches!(result, Err(Error::InvalidValue(_))));
+    }
+
+    #[tokio::test]
+    async fn test_query_pagination() {
+        let (cache, _) = queryable();
+        for name in ["a", "b", "c", "d", "e"] {
+            put(&cache, name, r#"{"status": "active"}"#).await;
+        }
+
+        let mut query: Query = serde_json::from_str(
+            r#"{"predicates": [{"field": "status", "op": "eq", "value": "active"}], "limit": 2}"#,
+        )
+        .unwrap();
+
+        let first = cache.query(&query).await.unwrap();
+        assert_eq!(first.keys, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(first.next_offset, Some(2));
+
+        query.offset = 4;
+        let last = cache.query(&query).await.unwrap();
+        assert_eq!(last.keys, vec![b"e".to_vec()]);
+        assert_eq!(last.next_offset, None);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::definition::{IndexDefinition, IndexKey};
+use crate::indexes::{SecondaryIndex, build_index};
+use carbon::ports::IndexMaintainer;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use serde_json::Value;
+use shared::{Error, Result};
+use std::sync::Arc;
+
+/// Secondary indexes of a single cache
+/// Remembers the index keys each key was indexed under so updates and deletes
+/// can unindex a key without reading its previous document
+#[derive(Default)]
+pub struct CacheIndexes {
+    indexes: DashMap<String, Arc<dyn SecondaryIndex>>,
+    entries: DashMap<Vec<u8>, Vec<(String, IndexKey)>>,
+}
+
+impl CacheIndexes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new index, documents written from now on are indexed by it
+    /// Existing entries are added with backfill
+    pub fn create_index(&self, definition: IndexDefinition) -> Result<()> {
+        definition.validate()?;
+
+        match self.indexes.entry(definition.name.clone()) {
+            Entry::Occupied(_) => Err(Error::InvalidValue(format!(
+                "index '{}' already exists",
+                definition.name
+            ))),
+            Entry::Vacant(slot) => {
+                slot.insert(build_index(definition));
+                Ok(())
+            }
+        }
+    }
+
+    /// Index existing documents under the index `name`, returning how many were added
+    /// Keys a write has indexed since the index was created are skipped, their
+    /// documents are newer than the ones being back-filled
+    pub fn backfill(
+        &self,
+        name: &str,
+        documents: impl IntoIterator<Item = (Vec<u8>, Value)>,
+    ) -> usize {
+        let Some(index) = self.index(name) else {
+            return 0;
+        };
+
+        let mut added = 0;
+        for (key, document) in documents {
+            let Some(index_key) = index.definition().extract(&document) else {
+                continue;
+            };
+            let mut indexed = self.entries.entry(key.clone()).or_default();
+            if indexed.iter().any(|(indexed_name, _)| indexed_name == name) {
+                continue;
+            }
+            index.insert(index_key.clone(), &key);
+            indexed.push((name.to_string(), index_key));
+            added += 1;
+        }
+        added
+    }
+
+    pub fn drop_index(&self, name: &str) -> bool {
+        self.indexes.remove(name).is_some()
+    }
+
+    pub fn index(&self, name: &str) -> Option<Arc<dyn SecondaryIndex>> {
+        self.indexes.get(name).map(|index| index.clone())
+    }
+
+    pub fn definitions(&self) -> Vec<IndexDefinition> {
+        let mut definitions: Vec<_> = self
+            .indexes
+            .iter()
+            .map(|index| index.definition().clone())
+            .collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
+    /// (Re)index `key` from the fields of `document`
+    pub fn index_document(&self, key: &[u8], document: &Value) {
+        // The entry guard serialises concurrent updates of the same key
+        let mut indexed = self.entries.entry(key.to_vec()).or_default();
+
+        for (name, index_key) in indexed.drain(..) {
+            if let Some(index) = self.indexes.get(&name) {
+                index.remove(&index_key, key);
+            }
+        }
+
+        for index in self.indexes.iter() {
+            if let Some(index_key) = index.definition().extract(document) {
+                index.insert(index_key.clone(), key);
+                indexed.push((index.key().clone(), index_key));
+            }
+        }
+
+        drop(indexed);
+        self.entries.remove_if(key, |_, indexed| indexed.is_empty());
+    }
+
+    /// Remove `key` from every index
+    pub fn remove_key(&self, key: &[u8]) {
+        if let Some((_, indexed)) = self.entries.remove(key) {
+            for (name, index_key) in indexed {
+                if let Some(index) = self.indexes.get(&name) {
+                    index.remove(&index_key, key);
+                }
+            }
+        }
+    }
+}
+
+/// Indexes of every cache, plugged into CacheOperationsService as its IndexMaintainer
+#[derive(Default)]
+pub struct IndexRegistry {
+    caches: DashMap<String, Arc<CacheIndexes>>,
+}
+
+impl IndexRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indexes of `cache_name`, created empty on first use
+    pub fn cache(&self, cache_name: &str) -> Arc<CacheIndexes> {
+        self.caches
+            .entry(cache_name.to_string())
+            .or_insert_with(|| Arc::new(CacheIndexes::new()))
+            .clone()
+    }
+
+    pub fn get(&self, cache_name: &str) -> Option<Arc<CacheIndexes>> {
+        self.caches.get(cache_name).map(|indexes| indexes.clone())
+    }
+
+    /// Forget all indexes of a dropped cache
+    pub fn drop_cache(&self, cache_name: &str) -> bool {
+        self.caches.remove(cache_name).is_some()
+    }
+
+    /// File the indexes of a renamed cache under its new name
+    pub fn rename_cache(&self, from: &str, to: &str) -> bool {
+        match self.caches.remove(from) {
+            Some((_, indexes)) => {
+                self.caches.insert(to.to_string(), indexes);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl IndexMaintainer for IndexRegistry {
+    fn on_put(&self, cache_name: &str, key: &[u8], document: &Value) {
+        if let Some(indexes) = self.get(cache_name) {
+            indexes.index_document(key, document);
+        }
+    }
+
+    fn on_delete(&self, cache_name: &str, key: &[u8]) {
+        if let Some(indexes) = self.get(cache_name) {
+            indexes.remove_key(key);
+        }
+    }
+
+    fn on_drop(&self, cache_name: &str) {
+        self.drop_cache(cache_name);
+    }
+
+    fn on_rename(&self, from: &str, to: &str) {
+        self.rename_cache(from, to);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{FieldDefinition, FieldType, FieldValue, IndexKind};
+    use serde_json::json;
+
+    fn status(value: &str) -> IndexKey {
+        IndexKey::from(FieldValue::String(value.to_string()))
+    }
+
+    fn registry_with_status_index() -> IndexRegistry {
+        let registry = IndexRegistry::new();
+        registry
+            .cache("users")
+            .create_index(IndexDefinition::new(
+                "by_status",
+                FieldDefinition::new("status", FieldType::String),
+                IndexKind::Hash,
+            ))
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_duplicate_index_is_rejected() {
+        let registry = registry_with_status_index();
+        let duplicate = IndexDefinition::new(
+            "by_status",
+            FieldDefinition::new("status", FieldType::String),
+            IndexKind::Range,
+        );
+        assert!(registry.cache("users").create_index(duplicate).is_err());
+    }
+
+    #[test]
+    fn test_put_update_delete_keep_index_consistent() {
+        let registry = registry_with_status_index();
+        let index = registry.cache("users").index("by_status").unwrap();
+
+        registry.on_put("users", b"user:1", &json!({"status": "active"}));
+        assert_eq!(index.lookup(&status("active")), vec![b"user:1".to_vec()]);
+
+        // Updating the document moves the key to its new value
+        registry.on_put("users", b"user:1", &json!({"status": "disabled"}));
+        assert!(index.lookup(&status("active")).is_empty());
+
+        registry.on_delete("users", b"user:1");
+        assert!(index.lookup(&status("disabled")).is_empty());
+    }
+
+    #[test]
+    fn test_backfill_keeps_newer_writes() {
+        let registry = registry_with_status_index();
+        let indexes = registry.cache("users");
+        let index = indexes.index("by_status").unwrap();
+
+        // Written after the index was created, while the back-fill was reading entries
+        registry.on_put("users", b"user:2", &json!({"status": "disabled"}));
+
+        let existing = vec![
+            (b"user:1".to_vec(), json!({"status": "active"})),
+            (b"user:2".to_vec(), json!({"status": "active"})),
+            (b"user:3".to_vec(), json!({"name": "no status"})),
+        ];
+        assert_eq!(indexes.backfill("by_status", existing), 1);
+        assert_eq!(index.lookup(&status("active")), vec![b"user:1".to_vec()]);
+        assert_eq!(index.lookup(&status("disabled")), vec![b"user:2".to_vec()]);
+
+        registry.on_delete("users", b"user:1");
+        assert!(index.lookup(&status("active")).is_empty());
+    }
+
+    #[test]
+    fn test_caches_without_indexes_are_ignored() {
+        let registry = registry_with_status_index();
+        registry.on_put("orders", b"order:1", &json!({"status": "active"}));
+        assert!(registry.get("orders").is_none());
+    }
+}
@@ -0,0 +1,54 @@
+use crate::definition::{FieldType, IndexDefinition};
+use carbon::domain::{ValueFormat, ValueType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Shape of the documents of a cache as far as its indexes know it, served by
+/// `GET /admin/caches/{name}/schema` and read by `carbon codegen`
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct CacheSchema {
+    pub cache: String,
+    pub value_type: ValueType,
+    #[serde(default)]
+    pub value_format: ValueFormat,
+    /// Sorted by path, one per JSON pointer
+    pub fields: Vec<SchemaField>,
+}
+
+/// A document field and the type indexes expect it to have
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct SchemaField {
+    pub name: String,
+    /// RFC 6901 pointer into the document
+    pub path: String,
+    pub field_type: FieldType,
+}
+
+impl CacheSchema {
+    /// Collect the fields of `indexes`, a field indexed more than once is listed once with the
+    /// type of the first index naming it
+    pub fn from_indexes(
+        cache: impl Into<String>,
+        value_type: ValueType,
+        value_format: ValueFormat,
+        indexes: &[IndexDefinition],
+    ) -> Self {
+        let mut fields = BTreeMap::new();
+        for field in indexes.iter().flat_map(|index| &index.fields) {
+            fields
+                .entry(field.pointer())
+                .or_insert_with(|| SchemaField {
+                    name: field.name.clone(),
+                    path: field.pointer(),
+                    field_type: field.field_type,
+                });
+        }
+
+        Self {
+            cache: cache.into(),
+            value_type,
+            value_format,
+            fields: fields.into_values().collect(),
+        }
+    }
+}
@@ -1,28 +1,178 @@
+use bytes::Bytes;
 use carbon::auth::{
-    defaults::create_default_admin, AuthService, MokaSessionRepository, RoleService, SessionStore,
-    SledRoleRepository, SledUserRepository, UserRepository, UserService,
+    defaults::create_default_admin, AuthManifest, AuthService, MokaSessionRepository,
+    RoleRepository, RoleService, SecretBox, SessionStore, SledRoleRepository, SledUserRepository,
+    UserRepository, UserService,
 };
+#[cfg(feature = "ldap")]
+use carbon::auth::LdapProvider;
+#[cfg(feature = "postgres")]
+use carbon::auth::{postgres_repository, PostgresRoleRepository, PostgresUserRepository};
+use carbon::event_sink::EventBridge;
+#[cfg(feature = "kafka")]
+use carbon::event_sink::KafkaPublisher;
+#[cfg(feature = "nats")]
+use carbon::event_sink::NatsPublisher;
+use carbon::persistence::SledPersistence;
 use carbon::planes::data::cache_operations::CacheOperationsService;
-use shared::config::Config;
+use carbon::planes::data::{
+    ChangeLog, ConnectionRegistry, KeyTracking, LatencyTracker, Maintenance, OpsLimiter,
+    QuotaTracker, SlowLog, DEFAULT_MAX_TRACKED_BYTES,
+};
+use carbon::ports::EventPublisher;
+use carbon_query::IndexRegistry;
+use shared::config::{
+    AuthStore, Config, ConfigSource, EventSinkConfig, EventSinkKind, HttpServerConfig, LdapConfig,
+    ListenerConfig, ListenerKind,
+};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::TcpListener;
-use tracing::{info, warn, Level};
+use tokio::runtime::{Handle, Runtime};
+use server_http::reload::{self, ConfigReloader};
+use server_http::schedules::ScheduleRunner;
+use tracing::{info, warn};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Load environment variables, before tracing so it picks up the log level
+    let dotenv = dotenvy::dotenv();
+
+    // `--migrate` reports the stored cache configurations an upgrade would rewrite and exits,
+    // `--migrate --apply` rewrites them
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--migrate") {
+        return migrate(args.iter().any(|arg| arg == "--apply"));
+    }
+
+    // `--bootstrap <file>` applies a manifest of users and roles once the auth store is open
+    let bootstrap = args
+        .iter()
+        .position(|arg| arg == "--bootstrap")
+        .map(|i| {
+            args.get(i + 1)
+                .map(std::path::PathBuf::from)
+                .ok_or("--bootstrap needs a value")
+        })
+        .transpose()?;
+
+    // `--config <file>` and `--set key=value` layer a TOML or YAML file and overrides around the
+    // environment
+    let source = ConfigSource::from_args(&args)?;
+    let config = Arc::new(Config::from_source(&source));
+
+    // Built before anything runs, a runtime cannot be dropped from async code
+    let runtime = build_runtime(
+        "carbon-worker",
+        config.runtime.worker_threads,
+        config.runtime.max_blocking_threads,
+    )?;
+    let tcp_runtime = config
+        .runtime
+        .tcp_worker_threads
+        .map(|threads| build_runtime("carbon-tcp", Some(threads), None))
+        .transpose()?;
+    let storage_runtime = config
+        .runtime
+        .storage_threads
+        .map(|threads| build_runtime("carbon-storage", Some(1), Some(threads)))
+        .transpose()?;
+
+    runtime.block_on(run(
+        dotenv,
+        config,
+        source,
+        bootstrap,
+        tcp_runtime.as_ref().map(|runtime| runtime.handle().clone()),
+        storage_runtime
+            .as_ref()
+            .map(|runtime| runtime.handle().clone()),
+    ))
+}
+
+/// Migrate the stored cache configurations to the current schema, or only report what would
+/// change unless `apply` is set
+fn migrate(apply: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let path = server_http::AppState::persistence_path();
+    let report = SledPersistence::new(&path)?.migrate(apply)?;
+
+    println!("{} (schema version {})", path.display(), report.to_version);
+    if report.records.is_empty() {
+        println!("All cache configurations are up to date");
+        return Ok(());
+    }
+    for record in &report.records {
+        let kind = if record.dropped {
+            "dropped cache"
+        } else {
+            "cache"
+        };
+        match &record.error {
+            Some(error) => println!("  {} '{}': cannot be read, {}", kind, record.name, error),
+            None => println!(
+                "  {} '{}': version {}, changes {}",
+                kind,
+                record.name,
+                record.from_version,
+                if record.changed_fields.is_empty() {
+                    "no fields".to_string()
+                } else {
+                    record.changed_fields.join(", ")
+                }
+            ),
+        }
+    }
+    if report.applied {
+        println!("Migrated {} records", report.records.len());
+    } else {
+        println!("Dry run, nothing was written. Run with --migrate --apply to rewrite them");
+    }
+    Ok(())
+}
+
+/// Multi-threaded runtime with named threads, unset sizes keep Tokio's defaults
+fn build_runtime(
+    name: &str,
+    worker_threads: Option<usize>,
+    max_blocking_threads: Option<usize>,
+) -> std::io::Result<Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.enable_all().thread_name(name);
+    if let Some(worker_threads) = worker_threads {
+        builder.worker_threads(worker_threads);
+    }
+    if let Some(max_blocking_threads) = max_blocking_threads {
+        builder.max_blocking_threads(max_blocking_threads);
+    }
+    builder.build()
+}
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn run(
+    dotenv: dotenvy::Result<std::path::PathBuf>,
+    config: Arc<Config>,
+    source: ConfigSource,
+    bootstrap: Option<std::path::PathBuf>,
+    tcp_runtime: Option<Handle>,
+    storage_runtime: Option<Handle>,
+) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing
-    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+    let log_level = reload::init_logging(
+        &config.log_level,
+        config.log_format,
+        config.otlp_endpoint.as_deref(),
+    );
 
     info!("Starting Carbon Server");
 
-    // Load environment variables
-    match dotenvy::dotenv() {
+    match dotenv {
         Ok(_) => info!("Loaded environment variables from .env file"),
         Err(_) => info!("No .env file found, using system environment variables"),
     }
-
-    let config = Arc::new(Config::from_env());
+    if let Some(path) = source.file_path() {
+        info!("Loaded configuration from {}", path.display());
+    }
+    for key in source.unused_keys() {
+        warn!("Configuration setting {} has no effect", key);
+    }
 
     // ============================================
     // STEP 1: Initialize shared CacheManager
@@ -42,27 +192,118 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             carbon::planes::control::CacheManager::new()
         }
     };
+    let cache_manager = cache_manager
+        .with_admin_response_max_age(Duration::from_millis(config.admin_response_max_age_ms))
+        .with_drop_retention(Duration::from_millis(config.drop_retention_ms));
+    let cache_manager = match storage_runtime {
+        Some(storage_runtime) => {
+            info!("Configuration store writes run on a dedicated storage runtime");
+            cache_manager.with_storage_runtime(storage_runtime)
+        }
+        None => cache_manager,
+    };
 
-    let cache_ops = Arc::new(CacheOperationsService::new(cache_manager.clone()));
+    // Shared by both servers so TCP and HTTP writes keep the same indexes up to date
+    let index_registry = Arc::new(IndexRegistry::new());
 
-    // ============================================
-    // STEP 2: Initialize Auth System
-    // ============================================
-    info!("Initializing authentication system...");
-    let (auth_service, user_service, role_service) = init_auth_system(
-        &config.data_dir,
-        &config.admin_username,
-        &config.admin_password,
-    )
-    .await;
+    // One slow log for both servers, so GET /admin/slowlog shows TCP operations too
+    let slow_log = config.slow_log.map(|slow_log| Arc::new(SlowLog::new(slow_log)));
+
+    // One limiter for both servers, so the ops/sec ceilings cover TCP and HTTP together
+    let ops_limiter = Arc::new(OpsLimiter::new(config.max_ops_per_sec));
+
+    // One tracker for both servers, so /metrics reports TCP and HTTP latencies together
+    let latencies = Arc::new(LatencyTracker::new());
+
+    // One switch for both servers, so POST /admin/maintenance refuses TCP writes too
+    let maintenance = Arc::new(Maintenance::new());
+
+    // One table for both servers, so HTTP writes invalidate the keys TCP clients track
+    let key_tracking = Arc::new(KeyTracking::new(
+        config
+            .tcp_limits
+            .max_tracked_bytes
+            .unwrap_or(DEFAULT_MAX_TRACKED_BYTES),
+    ));
+
+    // Loaded before the auth system, the auth key also keys the hashes of event keys so nodes
+    // sharing it hash a key alike
+    let auth_base_path = std::path::Path::new(&config.data_dir).join(".carbon");
+    if let Err(e) = std::fs::create_dir_all(&auth_base_path) {
+        warn!("Failed to create .carbon directory: {}", e);
+    }
+    let secrets = Arc::new(auth_secrets(&config, &auth_base_path));
+    let event_key_secret = secrets.derive_key("event-keys");
+
+    let cache_ops = CacheOperationsService::new(cache_manager.clone())
+        .with_index_maintainer(index_registry.clone())
+        .with_event_key_secret(event_key_secret.to_vec())
+        .with_ops_limiter(ops_limiter.clone())
+        .with_latency_tracker(latencies.clone())
+        .with_maintenance(maintenance.clone())
+        .with_key_tracking(key_tracking.clone());
+    let cache_ops = match &slow_log {
+        Some(slow_log) => cache_ops.with_slow_log(slow_log.clone()),
+        None => cache_ops,
+    };
+
+    // Filled by the TCP server, listed by GET /admin/connections
+    let connections = Arc::new(ConnectionRegistry::new().with_limits(config.tcp_limits));
+
+    // One tracker for both servers, so deletes over TCP release what HTTP writes charged
+    let quotas = config.quotas_enabled.then(|| Arc::new(QuotaTracker::new()));
+    let cache_ops = match &quotas {
+        Some(quotas) => cache_ops.with_quota_tracker(quotas.clone()),
+        None => cache_ops,
+    };
 
-    // Initialize session store (1 hour TTL)
+    // One log for both servers, so GET /cache/{name}/changes replays TCP writes too
+    let change_log = config.change_log.map(|change_log_config| {
+        let path = std::path::Path::new(&config.data_dir)
+            .join(".carbon")
+            .join("changes.sled");
+        info!("Changes to caches are logged in {}", path.display());
+        Arc::new(ChangeLog::open(path, change_log_config).expect("Failed to open the change log"))
+    });
+    let cache_ops = Arc::new(match &change_log {
+        Some(change_log) => cache_ops.with_change_log(change_log.clone()),
+        None => cache_ops,
+    });
+
+    // Initialize session store (TTL from CARBON_SESSION_TTL_MS, 1 hour by default, renewed on
+    // access for up to CARBON_SESSION_MAX_LIFETIME_MS)
     info!("Initializing session store...");
     let session_repository = Arc::new(MokaSessionRepository::new(
         None,                            // No max sessions limit
-        Some(Duration::from_secs(3600)), // 1 hour TTL
+        None,                            // Sessions expire individually
     ));
-    let session_store = Arc::new(SessionStore::new(session_repository));
+    let session_store = Arc::new(
+        SessionStore::new(session_repository.clone())
+            .with_ttl_ms(config.session_ttl_ms)
+            .with_max_lifetime_ms(config.session_max_lifetime_ms)
+            .with_refresh_ttl_ms(config.session_refresh_ttl_ms),
+    );
+
+    // ============================================
+    // STEP 2: Initialize Auth System
+    // ============================================
+    info!("Initializing authentication system...");
+    let (auth_service, user_service, role_service) =
+        init_auth_system(&config, session_repository, secrets).await;
+
+    // Applying the same manifest on every start only changes what drifted from it
+    if let Some(path) = &bootstrap {
+        let manifest = AuthManifest::parse(&std::fs::read_to_string(path)?)?;
+        let report = manifest.apply(&user_service, &role_service).await?;
+        info!(
+            "Applied {}: created roles [{}] and users [{}], updated roles [{}] and users [{}]",
+            path.display(),
+            report.roles_created.join(", "),
+            report.users_created.join(", "),
+            report.roles_updated.join(", "),
+            report.users_updated.join(", ")
+        );
+    }
 
     // ============================================
     // STEP 3: Initialize HTTP Server State
@@ -71,136 +312,233 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let app_state = server_http::AppState::new_with_cache_manager(
         cache_manager,
+        index_registry,
         auth_service,
         user_service,
         role_service,
         session_store,
     )
-    .await;
-
-    let http_router = server_http::build_router(app_state);
+    .await
+    .with_rate_limits(config.rate_limit)
+    .with_cache_defaults(config.cache_defaults)
+    .with_http_limits(config.http_limits)
+    .with_schedules(&config.schedules)
+    .with_trusted_proxies(&config.trusted_proxies)
+    .with_ops_limiter(ops_limiter)
+    .with_latency_tracker(latencies)
+    .with_maintenance(maintenance)
+    .with_event_key_secret(event_key_secret.to_vec())
+    .with_key_tracking(key_tracking)
+    .with_connections(connections.clone());
+
+    let app_state = match slow_log {
+        Some(slow_log) => app_state.with_slow_log(slow_log),
+        None => app_state,
+    };
 
-    // ============================================
-    // STEP 4: Spawn HTTP Server Task
-    // ============================================
-    let config_http_server = Arc::clone(&config);
+    let app_state = match quotas {
+        Some(quotas) => app_state.with_quotas(quotas),
+        None => app_state,
+    };
 
-    let http_handle = tokio::spawn(async move {
-        info!(
-            "Starting HTTP server on {}://{}:{}",
-            config_http_server.http.http_protcol(),
-            config_http_server.host,
-            config_http_server.http.port()
-        );
+    let app_state = match change_log {
+        Some(change_log) => app_state.with_change_log(change_log),
+        None => app_state,
+    };
 
-        let listener = TcpListener::bind(format!(
-            "{}:{}",
-            config_http_server.host,
-            config_http_server.http.port()
-        ))
-        .await
-        .expect("Failed to bind HTTP server");
+    // Single sign-on stays off if the provider cannot be reached, password login still works
+    let app_state = match config.oidc.clone() {
+        Some(oidc_config) => match server_http::oidc::OidcClient::discover(oidc_config).await {
+            Ok(oidc) => app_state.with_oidc(Arc::new(oidc)),
+            Err(e) => {
+                warn!("OIDC sign-in disabled: {}", e);
+                app_state
+            }
+        },
+        None => app_state,
+    };
 
-        info!(
-            "HTTP Server listening on {}://{}:{}",
-            config_http_server.http.http_protcol(),
-            config_http_server.host,
-            config_http_server.http.port()
-        );
+    // Apply configuration changes on SIGHUP or POST /admin/config/reload
+    let config_reloader = Arc::new(
+        ConfigReloader::new(
+            Config::clone(&config),
+            log_level,
+            app_state.rate_limits.clone(),
+            app_state.session_store.clone(),
+        )
+        .with_source(source),
+    );
+    config_reloader.clone().spawn_sighup_listener();
+    let app_state = app_state.with_config_reloader(config_reloader);
+
+    // Subscribe before serving so the sink sees every event
+    let app_state = match &config.event_sink {
+        Some(sink) => {
+            let bridge = Arc::new(
+                EventBridge::new(event_publisher(sink).await, sink.default_topic.clone())
+                    .with_cache_topics(sink.cache_topics.clone()),
+            );
+            tokio::spawn(bridge.clone().run(app_state.event_channel.subscribe()));
+            app_state.with_event_sink(bridge)
+        }
+        None => app_state,
+    };
 
+    // Caches declared in the config, so deployments need no provisioning step after start
+    let created = server_http::provision::create_declared_caches(
+        &app_state.cache_manager,
+        &config.caches,
+        &config.cache_defaults,
+    )
+    .await?;
+    if !created.is_empty() {
         info!(
-            "Try: curl -u admin:admin123 {}://{}:{}/health",
-            config_http_server.http.http_protcol(),
-            config_http_server.host,
-            config_http_server.http.port()
+            "Created caches declared in the config: {}",
+            created.join(", ")
         );
+    }
 
-        axum::serve(listener, http_router)
-            .with_graceful_shutdown(shutdown_signal())
-            .await
-            .expect("HTTP server error");
-    });
-
-    // ============================================
-    // STEP 5: Spawn TCP Server Task
-    // ============================================
-    info!("Initializing TCP server components");
-
-    let config_tcp_server = Arc::clone(&config);
-
-    let tcp_cache_ops = cache_ops.clone();
+    // Reap through the HTTP service so removals reach SSE subscribers as Expired events
+    app_state.cache_operations.clone().spawn_ttl_reaper();
 
-    let tcp_handle = tokio::spawn(async move {
-        info!(
-            "Starting TCP server on {}://{}:{}",
-            config_tcp_server.tcp.tcp_protcol(),
-            config_tcp_server.host,
-            config_tcp_server.tcp.port()
-        );
+    // Keep materialized views in step with the HTTP writes to their sources
+    app_state
+        .cache_operations
+        .clone()
+        .spawn_view_maintenance(app_state.event_channel.subscribe());
 
-        let listener = TcpListener::bind(format!(
-            "{}:{}",
-            config_tcp_server.host,
-            config_tcp_server.tcp.port()
-        ))
-        .await
-        .expect("Failed to bind TCP server");
+    // Run scheduled clears, snapshots and warm-ups of caches
+    app_state
+        .cache_manager
+        .spawn_scheduler(Arc::new(ScheduleRunner::new(&app_state)));
 
-        info!(
-            "TCP Server listening on {}://{}:{}",
-            config_tcp_server.tcp.tcp_protcol(),
-            config_tcp_server.host,
-            config_tcp_server.tcp.port()
-        );
+    let http_router = server_http::build_router(app_state);
 
-        loop {
-            match listener.accept().await {
-                Ok((socket, addr)) => {
-                    tracing::info!("TCP connection from {addr}");
-                    let cache_ops_clone = tcp_cache_ops.clone();
-
-                    tokio::spawn(async move {
-                        if let Err(err) =
-                            server_tcp::process_connection(socket, cache_ops_clone).await
-                        {
-                            tracing::warn!("TCP connection {addr} error: {err:?}");
-                        }
-                    });
-                }
-                Err(e) => {
-                    tracing::error!("TCP accept error: {}", e);
-                }
+    // ============================================
+    // STEP 4: Spawn a task per listener
+    // ============================================
+    let listeners = config.listeners();
+    let mut handles = Vec::new();
+    for listener in &listeners {
+        match listener.kind {
+            ListenerKind::Http => {
+                let router = server_http::routes::listener_router(http_router.clone(), listener);
+                handles.push(tokio::spawn(serve_http(
+                    listener.clone(),
+                    router,
+                    config.http_server,
+                )));
             }
+            ListenerKind::Tcp => {
+                let acceptors = config
+                    .tcp_acceptors
+                    .unwrap_or_else(server_tcp::listener::default_acceptors);
+                let tcp_server = serve_tcp(
+                    listener.clone(),
+                    acceptors,
+                    cache_ops.clone(),
+                    connections.clone(),
+                );
+                // A dedicated TCP runtime keeps cache operations clear of HTTP and admin load
+                handles.push(match &tcp_runtime {
+                    Some(tcp_runtime) => {
+                        info!(
+                            "TCP listener '{}' runs on the dedicated runtime",
+                            listener.name
+                        );
+                        tcp_runtime.spawn(tcp_server)
+                    }
+                    None => tokio::spawn(tcp_server),
+                });
+            }
+            ListenerKind::Grpc => warn!(
+                "Listener '{}' on port {} is skipped, gRPC is not supported by this build",
+                listener.name, listener.port
+            ),
         }
-    });
+    }
 
     // ============================================
-    // STEP 6: Wait for shutdown signal
+    // STEP 5: Wait for shutdown signal
     // ============================================
     info!("Carbon server started successfully");
-    info!(
-        "  - HTTP: {}://{}:{}",
-        config.http.http_protcol(),
-        config.host,
-        config.http.port()
-    );
-    info!(
-        "  - TCP:  {}://{}:{}",
-        config.tcp.tcp_protcol(),
-        config.host,
-        config.tcp.port()
-    );
+    for listener in listeners.iter().filter(|l| l.kind != ListenerKind::Grpc) {
+        info!(
+            "  - {}: {}://{}:{}",
+            listener.name,
+            listener.scheme(),
+            listener.host,
+            listener.port
+        );
+    }
 
+    let listener_stopped = async {
+        if handles.is_empty() {
+            std::future::pending::<()>().await;
+        }
+        futures::future::select_all(handles).await;
+    };
     tokio::select! {
-        _ = http_handle => info!("HTTP server task completed"),
-        _ = tcp_handle => info!("TCP server task completed"),
+        _ = listener_stopped => info!("Listener task completed"),
         _ = shutdown_signal() => info!("Shutdown signal received"),
     }
 
     info!("Carbon server shutting down");
+    server_http::telemetry::shutdown();
     Ok(())
 }
 
+/// Serve the HTTP routes `listener` is scoped to until shutdown
+async fn serve_http(listener: ListenerConfig, router: axum::Router, http_server: HttpServerConfig) {
+    let tcp_listener = TcpListener::bind(format!("{}:{}", listener.host, listener.port))
+        .await
+        .unwrap_or_else(|e| panic!("Failed to bind HTTP listener '{}': {}", listener.name, e));
+
+    info!(
+        "HTTP listener '{}' on {}://{}:{}",
+        listener.name,
+        listener.scheme(),
+        listener.host,
+        listener.port
+    );
+    info!(
+        "Try: curl -u admin:admin123 {}://{}:{}/health",
+        listener.scheme(),
+        listener.host,
+        listener.port
+    );
+
+    // Connection info gives the rate limiter a client IP when no proxy header is set
+    server_http::serve(tcp_listener, router, http_server, shutdown_signal()).await;
+}
+
+/// Serve the TCP protocol on `listener`
+async fn serve_tcp(
+    listener: ListenerConfig,
+    acceptors: usize,
+    cache_ops: Arc<CacheOperationsService<Vec<u8>, Bytes>>,
+    connections: Arc<ConnectionRegistry>,
+) {
+    info!(
+        "TCP listener '{}' on {}://{}:{}",
+        listener.name,
+        listener.scheme(),
+        listener.host,
+        listener.port
+    );
+
+    // The host may be a name such as localhost, bind to the first address it resolves to
+    let addr = tokio::net::lookup_host((listener.host.as_str(), listener.port))
+        .await
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .unwrap_or_else(|| panic!("Failed to resolve TCP listener '{}'", listener.name));
+
+    server_tcp::listener::run(addr, acceptors, cache_ops, connections)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to bind TCP listener '{}': {}", listener.name, e));
+}
+
 // Graceful shutdown handler
 async fn shutdown_signal() {
     use tokio::signal;
@@ -236,29 +574,37 @@ async fn shutdown_signal() {
 
 // Initialize authentication system
 async fn init_auth_system(
-    data_dir: &str,
-    admin_username: &str,
-    admin_password: &str,
+    config: &Config,
+    session_repository: Arc<MokaSessionRepository>,
+    secrets: Arc<SecretBox>,
 ) -> (Arc<AuthService>, Arc<UserService>, Arc<RoleService>) {
-    let auth_base_path = std::path::Path::new(&data_dir).join(".carbon");
-    // Create .carbon directory if it doesn't exist
-    if let Err(e) = std::fs::create_dir_all(&auth_base_path) {
-        warn!("Failed to create .carbon directory: {}", e);
+    let auth_base_path = std::path::Path::new(&config.data_dir).join(".carbon");
+
+    // Records encrypted with a key stored beside them are only as safe as the data volume
+    if config.auth_encrypt_at_rest && config.auth_key.is_none() && config.auth_key_file.is_none() {
+        warn!(
+            "The auth store is encrypted with .carbon/auth.key in the same data directory, set \
+             CARBON_AUTH_KEY or CARBON_AUTH_KEY_FILE to keep the key elsewhere"
+        );
     }
 
     // Initialize repositories
-    let user_repo = Arc::new(
-        SledUserRepository::new(auth_base_path.join("users.sled"))
-            .expect("Failed to initialize user repository"),
-    );
-    let role_repo = Arc::new(
-        SledRoleRepository::new(auth_base_path.join("roles.sled"))
-            .expect("Failed to initialize role repository"),
-    );
+    let sealed_store = config.auth_encrypt_at_rest.then(|| secrets.clone());
+    let (user_repo, role_repo) =
+        auth_repositories(&config.auth_store, &auth_base_path, sealed_store).await;
 
     // Initialize services
-    let auth_service = Arc::new(AuthService::new(user_repo.clone(), role_repo.clone()));
-    let user_service = Arc::new(UserService::new(user_repo.clone(), role_repo.clone()));
+    let auth_service = AuthService::new(user_repo.clone(), role_repo.clone())
+        .with_local_accounts(config.auth_local_accounts)
+        .with_secret_box(secrets);
+    let auth_service = Arc::new(match &config.ldap {
+        Some(ldap) => with_ldap(auth_service, ldap),
+        None => auth_service,
+    });
+    // Password changes end the user's sessions
+    let user_service = Arc::new(
+        UserService::new(user_repo.clone(), role_repo.clone()).with_sessions(session_repository),
+    );
     let role_service = Arc::new(RoleService::new(role_repo.clone()));
 
     // Initialize default roles
@@ -278,6 +624,7 @@ async fn init_auth_system(
 
     // Check if default admin exists
 
+    let (admin_username, admin_password) = (&config.admin_username, &config.admin_password);
     let admin_exists = user_repo
         .username_exists(admin_username)
         .await
@@ -304,3 +651,104 @@ async fn init_auth_system(
 
     (auth_service, user_service, role_service)
 }
+
+/// Verify users against the configured directory before the local accounts
+#[cfg(feature = "ldap")]
+fn with_ldap(auth_service: AuthService, ldap: &LdapConfig) -> AuthService {
+    info!("Users are verified against LDAP at {}", ldap.url);
+    auth_service.with_provider(Arc::new(LdapProvider::new(ldap.clone())))
+}
+
+#[cfg(not(feature = "ldap"))]
+fn with_ldap(_auth_service: AuthService, _ldap: &LdapConfig) -> AuthService {
+    panic!("CARBON_LDAP_URL requires carbon-server built with the ldap feature")
+}
+
+/// Publisher of the configured event sink
+async fn event_publisher(sink: &EventSinkConfig) -> Arc<dyn EventPublisher> {
+    match &sink.kind {
+        #[cfg(feature = "kafka")]
+        EventSinkKind::Kafka { brokers } => {
+            info!("Cache events are published to Kafka at {}", brokers);
+            Arc::new(KafkaPublisher::new(brokers).expect("Failed to create the Kafka producer"))
+        }
+        #[cfg(not(feature = "kafka"))]
+        EventSinkKind::Kafka { .. } => {
+            panic!("CARBON_EVENT_SINK=kafka requires carbon-server built with the kafka feature")
+        }
+        #[cfg(feature = "nats")]
+        EventSinkKind::Nats { url } => {
+            info!("Cache events are published to NATS at {}", url);
+            Arc::new(
+                NatsPublisher::connect(url)
+                    .await
+                    .expect("Failed to connect to the event sink"),
+            )
+        }
+        #[cfg(not(feature = "nats"))]
+        EventSinkKind::Nats { .. } => {
+            panic!("CARBON_EVENT_SINK=nats requires carbon-server built with the nats feature")
+        }
+    }
+}
+
+/// Key sealing secrets in the auth store, from CARBON_AUTH_KEY or the key file
+fn auth_secrets(config: &Config, auth_base_path: &std::path::Path) -> SecretBox {
+    if let Some(key) = &config.auth_key {
+        return SecretBox::from_base64(key).expect("Invalid CARBON_AUTH_KEY");
+    }
+    let key_file = match &config.auth_key_file {
+        Some(path) => std::path::PathBuf::from(path),
+        None => auth_base_path.join("auth.key"),
+    };
+    SecretBox::load_or_create(&key_file).expect("Failed to load auth key")
+}
+
+/// User and role repositories of the configured auth store
+/// With `sealed`, Sled records are encrypted and plaintext ones left from before are rewritten
+async fn auth_repositories(
+    auth_store: &AuthStore,
+    auth_base_path: &std::path::Path,
+    sealed: Option<Arc<SecretBox>>,
+) -> (Arc<dyn UserRepository>, Arc<dyn RoleRepository>) {
+    match auth_store {
+        AuthStore::Sled => {
+            let mut users = SledUserRepository::new(auth_base_path.join("users.sled"))
+                .expect("Failed to initialize user repository");
+            let mut roles = SledRoleRepository::new(auth_base_path.join("roles.sled"))
+                .expect("Failed to initialize role repository");
+            if let Some(secrets) = sealed {
+                users = users.with_secret_box(secrets.clone());
+                roles = roles.with_secret_box(secrets);
+                let sealed_users = users
+                    .seal_existing()
+                    .expect("Failed to encrypt user records");
+                let sealed_roles = roles
+                    .seal_existing()
+                    .expect("Failed to encrypt role records");
+                info!(
+                    "Auth store encrypted at rest ({} users and {} roles migrated)",
+                    sealed_users, sealed_roles
+                );
+            }
+            (Arc::new(users), Arc::new(roles))
+        }
+        #[cfg(feature = "postgres")]
+        AuthStore::Postgres { url } => {
+            info!("Users and roles are stored in PostgreSQL");
+            let pool = postgres_repository::connect(url)
+                .await
+                .expect("Failed to connect to the auth database");
+            (
+                Arc::new(PostgresUserRepository::new(pool.clone())),
+                Arc::new(PostgresRoleRepository::new(pool)),
+            )
+        }
+        #[cfg(not(feature = "postgres"))]
+        AuthStore::Postgres { .. } => {
+            panic!(
+                "CARBON_AUTH_STORE=postgres requires carbon-server built with the postgres feature"
+            )
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use super::models::ApiKey;
+use super::session::generate_session_token;
+use sha2::{Digest, Sha256};
+
+/// Start of every API key, tells them apart from session tokens
+pub const API_KEY_PREFIX: &str = "ck.";
+
+/// Generate the key clients send, `ck.<username>.<key id>.<secret>`, and the digest of its
+/// secret that is stored
+pub fn generate_api_key(username: &str, key_id: &str) -> (String, String) {
+    let secret = generate_session_token();
+    let key = format!("{}{}.{}.{}", API_KEY_PREFIX, username, key_id, secret);
+    (key, hash_secret(&secret))
+}
+
+/// Split an API key into its username, key id and secret
+pub fn parse_api_key(key: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = key.strip_prefix(API_KEY_PREFIX)?.rsplitn(3, '.');
+    let secret = parts.next()?;
+    let key_id = parts.next()?;
+    let username = parts.next().filter(|username| !username.is_empty())?;
+    Some((username, key_id, secret))
+}
+
+/// Whether `secret` is the one `key` was issued with
+pub fn verify_secret(key: &ApiKey, secret: &str) -> bool {
+    key.secret_hash == hash_secret(secret)
+}
+
+/// Secrets are random, so a fast digest is enough to keep them out of the store
+fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_keys_parse_back() {
+        let (key, secret_hash) = generate_api_key("ci.deploy", "k1");
+        let (username, key_id, secret) = parse_api_key(&key).unwrap();
+
+        // Usernames may contain dots, the id and secret never do
+        assert_eq!(username, "ci.deploy");
+        assert_eq!(key_id, "k1");
+        assert_eq!(hash_secret(secret), secret_hash);
+
+        assert!(parse_api_key("0123abcd").is_none());
+        assert!(parse_api_key("ck.k1.secret").is_none());
+    }
+}
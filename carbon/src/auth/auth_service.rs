@@ -1,12 +1,44 @@
+use super::api_key;
 use super::error::AuthError;
-use super::models::{Permission, User};
+use super::mfa;
+use super::models::{ApiKey, MfaSettings, Permission, User};
 use super::password::verify_password;
+use super::provider::{AuthProvider, ExternalIdentity};
 use super::repository::{RoleRepository, UserRepository};
+use super::secret_box::SecretBox;
+use crate::domain::Quota;
+use chrono::Utc;
 use std::sync::Arc;
+use tracing::warn;
 
 pub struct AuthService {
     user_repo: Arc<dyn UserRepository>,
     role_repo: Arc<dyn RoleRepository>,
+    // Consulted in order before the local accounts
+    providers: Vec<Arc<dyn AuthProvider>>,
+    // Whether users may still sign in with a local password hash
+    local_accounts: bool,
+    // Seals the TOTP secrets of MFA enrollments
+    secrets: Option<Arc<SecretBox>>,
+    // Wrong MFA codes per user, locks them out after too many
+    failed_codes: mfa::FailedCodes,
+}
+
+/// Secret of a new TOTP enrollment, to be loaded into an authenticator app
+#[derive(Debug, Clone)]
+pub struct MfaEnrollment {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// `otpauth://` URI, usually shown as a QR code
+    pub provisioning_uri: String,
+}
+
+/// Seconds since the Unix epoch
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
 }
 
 impl AuthService {
@@ -14,17 +46,95 @@ impl AuthService {
         Self {
             user_repo,
             role_repo,
+            providers: Vec::new(),
+            local_accounts: true,
+            secrets: None,
+            failed_codes: mfa::FailedCodes::new(),
         }
     }
 
+    /// Verify credentials against `provider` before the local accounts
+    pub fn with_provider(mut self, provider: Arc<dyn AuthProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    /// Key TOTP secrets are sealed with, MFA enrollment fails without one
+    pub fn with_secret_box(mut self, secrets: Arc<SecretBox>) -> Self {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    /// Turn local password hashes off, so only the providers can authenticate users
+    pub fn with_local_accounts(mut self, enabled: bool) -> Self {
+        self.local_accounts = enabled;
+        self
+    }
+
     /// Authenticate a user by username and password
+    /// Providers are tried first, an unreachable provider is skipped so local admins can still
+    /// sign in during a directory outage
     pub async fn authenticate(&self, username: &str, password: &str) -> Result<User, AuthError> {
+        self.authenticate_with_code(username, password, None).await
+    }
+
+    /// Like `authenticate`, with the TOTP or recovery code local users enrolled in MFA need
+    /// Users verified by a provider are left to the provider's own second factor
+    pub async fn authenticate_with_code(
+        &self,
+        username: &str,
+        password: &str,
+        code: Option<&str>,
+    ) -> Result<User, AuthError> {
+        for provider in &self.providers {
+            match provider.authenticate(username, password).await {
+                Ok(Some(identity)) => return self.external_user(provider.name(), identity).await,
+                Ok(None) => {}
+                Err(e) => warn!("Auth provider {} failed: {}", provider.name(), e),
+            }
+        }
+
+        let mut user = self.verify_local(username, password).await?;
+
+        let Some(settings) = user.mfa.as_mut().filter(|settings| settings.confirmed) else {
+            if self.mfa_required(&user).await? {
+                return Err(AuthError::MfaEnrollmentRequired);
+            }
+            return Ok(user);
+        };
+        let code = code.ok_or(AuthError::MfaRequired)?;
+        self.failed_codes.attempt(&user.id)?;
+        let current = settings.clone();
+        self.verify_mfa_code(settings, code)?;
+
+        // Records the used step or recovery code, unless a sign-in that read the same
+        // settings already spent it
+        if !self
+            .user_repo
+            .update_mfa_if_unchanged(user.clone(), &current)
+            .await?
+        {
+            return Err(AuthError::InvalidMfaCode);
+        }
+        self.failed_codes.succeeded(&user.id);
+        Ok(user)
+    }
+
+    /// Local user for `username` if `password` matches its hash
+    async fn verify_local(&self, username: &str, password: &str) -> Result<User, AuthError> {
+        if !self.local_accounts {
+            return Err(AuthError::InvalidCredentials);
+        }
+
         // Find user by username
         let user = self
             .user_repo
             .find_by_username(username)
             .await?
             .ok_or(AuthError::InvalidCredentials)?;
+        if user.is_service_account() {
+            return Err(AuthError::InvalidCredentials);
+        }
 
         // Verify password
         let is_valid = verify_password(password, &user.password_hash)?;
@@ -36,6 +146,189 @@ impl AuthService {
         Ok(user)
     }
 
+    /// Service account holding the API key `key`, along with the key to scope its requests by
+    pub async fn authenticate_api_key(&self, key: &str) -> Result<(User, ApiKey), AuthError> {
+        let (username, key_id, secret) =
+            api_key::parse_api_key(key).ok_or(AuthError::InvalidCredentials)?;
+        let user = self
+            .user_repo
+            .find_by_username(username)
+            .await?
+            .filter(User::is_service_account)
+            .ok_or(AuthError::InvalidCredentials)?;
+
+        let api_key = user
+            .api_keys
+            .iter()
+            .find(|api_key| api_key.id == key_id && api_key::verify_secret(api_key, secret))
+            .cloned()
+            .ok_or(AuthError::InvalidCredentials)?;
+        Ok((user, api_key))
+    }
+
+    /// Whether `user` or one of its roles was flagged as needing a second factor
+    async fn mfa_required(&self, user: &User) -> Result<bool, AuthError> {
+        if user.mfa_required {
+            return Ok(true);
+        }
+        let roles = self.role_repo.find_by_ids(&user.role_ids).await?;
+        Ok(roles.iter().any(|role| role.requires_mfa))
+    }
+
+    /// Accept a current TOTP code or an unused recovery code, which is then spent
+    fn verify_mfa_code(&self, settings: &mut MfaSettings, code: &str) -> Result<(), AuthError> {
+        let secret = self.secrets()?.open(&settings.sealed_secret)?;
+        if let Some(step) = mfa::verify_code(&secret, code, now_secs(), settings.last_step) {
+            settings.last_step = step;
+            return Ok(());
+        }
+
+        let hash = mfa::hash_recovery_code(code);
+        let unused = &mut settings.recovery_code_hashes;
+        match unused.iter().position(|h| *h == hash) {
+            Some(index) => {
+                unused.remove(index);
+                Ok(())
+            }
+            None => Err(AuthError::InvalidMfaCode),
+        }
+    }
+
+    fn secrets(&self) -> Result<&SecretBox, AuthError> {
+        self.secrets
+            .as_deref()
+            .ok_or_else(|| AuthError::EncryptionError("no secret key configured".to_string()))
+    }
+
+    /// Start TOTP enrollment for a local user, returns the secret for their authenticator app
+    /// A confirmed enrollment must be reset by an admin before the user can enroll again
+    pub async fn enroll_mfa(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<MfaEnrollment, AuthError> {
+        let mut user = self.verify_local(username, password).await?;
+        if user.mfa_enrolled() {
+            return Err(AuthError::MfaAlreadyEnrolled);
+        }
+
+        let secret = mfa::generate_secret();
+        user.mfa = Some(MfaSettings {
+            sealed_secret: self.secrets()?.seal(&secret)?,
+            confirmed: false,
+            recovery_code_hashes: Vec::new(),
+            last_step: 0,
+        });
+        let user = self.user_repo.update(user).await?;
+
+        Ok(MfaEnrollment {
+            secret: mfa::base32_encode(&secret),
+            provisioning_uri: mfa::provisioning_uri(&user.username, &secret),
+        })
+    }
+
+    /// Finish enrollment with a code from the authenticator app
+    /// Returns the recovery codes, which are only ever shown this once
+    pub async fn confirm_mfa(
+        &self,
+        username: &str,
+        password: &str,
+        code: &str,
+    ) -> Result<Vec<String>, AuthError> {
+        let mut user = self.verify_local(username, password).await?;
+        let settings = match user.mfa.as_mut() {
+            Some(settings) if settings.confirmed => return Err(AuthError::MfaAlreadyEnrolled),
+            Some(settings) => settings,
+            None => return Err(AuthError::MfaNotEnrolled),
+        };
+
+        self.failed_codes.attempt(&user.id)?;
+        let current = settings.clone();
+        let secret = self.secrets()?.open(&settings.sealed_secret)?;
+        settings.last_step = mfa::verify_code(&secret, code, now_secs(), settings.last_step)
+            .ok_or(AuthError::InvalidMfaCode)?;
+
+        let recovery_codes = mfa::generate_recovery_codes();
+        settings.recovery_code_hashes = recovery_codes
+            .iter()
+            .map(|code| mfa::hash_recovery_code(code))
+            .collect();
+        settings.confirmed = true;
+        user.updated_at = Utc::now();
+        let user_id = user.id.clone();
+        if !self
+            .user_repo
+            .update_mfa_if_unchanged(user, &current)
+            .await?
+        {
+            return Err(AuthError::InvalidMfaCode);
+        }
+        self.failed_codes.succeeded(&user_id);
+
+        Ok(recovery_codes)
+    }
+
+    /// Session user for an identity verified by a provider, with its mapped roles
+    /// Such users are not stored, their roles are resolved again on every sign-in
+    pub async fn external_user(
+        &self,
+        provider: &str,
+        identity: ExternalIdentity,
+    ) -> Result<User, AuthError> {
+        let mut role_ids = Vec::with_capacity(identity.roles.len());
+        for name in &identity.roles {
+            match self.role_repo.find_by_name(name).await? {
+                Some(role) => role_ids.push(role.id),
+                None => warn!("Auth provider {} mapped to unknown role {}", provider, name),
+            }
+        }
+
+        let mut user = User::new(identity.username, String::new(), role_ids);
+        user.id = format!("{}:{}", provider, user.username);
+        Ok(user)
+    }
+
+    /// `signed_in` as stored now, for trading a refresh token issued to them
+    /// Fails when the user is gone or their second factor changed since they signed in, so a
+    /// refresh never skips a factor signing in again would ask for
+    /// Users from a provider are not stored and sign in through it again instead
+    pub async fn refreshed_user(&self, signed_in: &User) -> Result<User, AuthError> {
+        let user = self
+            .user_repo
+            .find_by_id(&signed_in.id)
+            .await?
+            .ok_or(AuthError::UserNotFound)?;
+        if user.mfa_enrolled() != signed_in.mfa_enrolled() {
+            return Err(AuthError::MfaRequired);
+        }
+        if !user.mfa_enrolled() && self.mfa_required(&user).await? {
+            return Err(AuthError::MfaEnrollmentRequired);
+        }
+        Ok(user)
+    }
+
+    /// Quota a user's writes are held to: their own if set, otherwise the most generous of
+    /// their roles', where a role without a quota grants no limits
+    /// Read from the store so quotas changed since sign-in apply straight away
+    pub async fn effective_quota(&self, user: &User) -> Result<Quota, AuthError> {
+        let stored = self.user_repo.find_by_id(&user.id).await?;
+        let user = stored.as_ref().unwrap_or(user);
+        if let Some(quota) = user.quota {
+            return Ok(quota);
+        }
+
+        let roles = self.role_repo.find_by_ids(&user.role_ids).await?;
+        let mut quotas = roles.iter().map(|role| role.quota);
+        let Some(first) = quotas.next() else {
+            return Ok(Quota::default());
+        };
+        Ok(quotas
+            .fold(first, |effective, quota| {
+                Some(effective?.most_generous(quota?))
+            })
+            .unwrap_or_default())
+    }
+
     /// Check if a user has a specific permission
     pub async fn authorize(&self, user: &User, permission: Permission) -> Result<(), AuthError> {
         // Load all roles for the user
@@ -190,4 +483,256 @@ mod tests {
         let result = auth_service.authorize(&user, Permission::WriteCache).await;
         assert!(matches!(result, Err(AuthError::PermissionDenied)));
     }
+
+    #[tokio::test]
+    async fn test_effective_quota() {
+        let temp_dir = TempDir::new().unwrap();
+        let user_repo =
+            Arc::new(SledUserRepository::new(temp_dir.path().join("users.sled")).unwrap())
+                as Arc<dyn UserRepository>;
+        let role_repo =
+            Arc::new(SledRoleRepository::new(temp_dir.path().join("roles.sled")).unwrap())
+                as Arc<dyn RoleRepository>;
+        let auth_service = AuthService::new(user_repo.clone(), role_repo.clone());
+
+        let mut small = Role::new("small".to_string(), HashSet::new(), false);
+        small.quota = Some(Quota {
+            max_bytes: Some(100),
+            max_keys: Some(10),
+            max_ops_per_day: None,
+        });
+        let mut large = Role::new("large".to_string(), HashSet::new(), false);
+        large.quota = Some(Quota {
+            max_bytes: Some(1000),
+            max_keys: Some(5),
+            max_ops_per_day: Some(50),
+        });
+        let small = role_repo.create(small).await.unwrap();
+        let large = role_repo.create(large).await.unwrap();
+        let unlimited = role_repo
+            .create(Role::new("unlimited".to_string(), HashSet::new(), false))
+            .await
+            .unwrap();
+
+        // The most generous of each limit, None meaning no limit
+        let user = User::new(
+            "alice".to_string(),
+            "hash".to_string(),
+            vec![small.id.clone(), large.id.clone()],
+        );
+        let user = user_repo.create(user).await.unwrap();
+        let quota = auth_service.effective_quota(&user).await.unwrap();
+        assert_eq!(
+            quota,
+            Quota {
+                max_bytes: Some(1000),
+                max_keys: Some(10),
+                max_ops_per_day: None,
+            }
+        );
+
+        // A role without a quota lifts every limit
+        let mut user = user;
+        user.role_ids.push(unlimited.id);
+        let user = user_repo.update(user).await.unwrap();
+        let quota = auth_service.effective_quota(&user).await.unwrap();
+        assert_eq!(quota, Quota::default());
+
+        // The user's own quota wins, even over a stale session copy
+        let mut stored = user.clone();
+        stored.quota = Some(Quota {
+            max_keys: Some(1),
+            ..Default::default()
+        });
+        user_repo.update(stored).await.unwrap();
+        let quota = auth_service.effective_quota(&user).await.unwrap();
+        assert_eq!(quota.max_keys, Some(1));
+    }
+
+    /// Directory that knows one user and puts it in the "reader" role
+    struct StaticProvider;
+
+    #[async_trait::async_trait]
+    impl AuthProvider for StaticProvider {
+        fn name(&self) -> &'static str {
+            "static"
+        }
+
+        async fn authenticate(
+            &self,
+            username: &str,
+            password: &str,
+        ) -> Result<Option<ExternalIdentity>, AuthError> {
+            Ok(
+                (username == "alice" && password == "directory-pass").then(|| ExternalIdentity {
+                    username: username.to_string(),
+                    roles: vec!["reader".to_string(), "missing".to_string()],
+                }),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_with_provider() {
+        let temp_dir = TempDir::new().unwrap();
+        let user_repo =
+            Arc::new(SledUserRepository::new(temp_dir.path().join("users.sled")).unwrap())
+                as Arc<dyn UserRepository>;
+        let role_repo =
+            Arc::new(SledRoleRepository::new(temp_dir.path().join("roles.sled")).unwrap())
+                as Arc<dyn RoleRepository>;
+
+        let mut permissions = HashSet::new();
+        permissions.insert(Permission::ReadCache);
+        let reader = role_repo
+            .create(Role::new("reader".to_string(), permissions, false))
+            .await
+            .unwrap();
+        let password_hash = hash_password("local-pa55").unwrap();
+        user_repo
+            .create(User::new("bob".to_string(), password_hash, Vec::new()))
+            .await
+            .unwrap();
+
+        let auth_service = AuthService::new(user_repo.clone(), role_repo.clone())
+            .with_provider(Arc::new(StaticProvider));
+
+        // Unknown role names in the mapping are skipped
+        let user = auth_service
+            .authenticate("alice", "directory-pass")
+            .await
+            .unwrap();
+        assert_eq!(user.id, "static:alice");
+        assert_eq!(user.role_ids, vec![reader.id]);
+        assert!(
+            auth_service
+                .authorize(&user, Permission::ReadCache)
+                .await
+                .is_ok()
+        );
+
+        // Users the provider rejects fall through to the local accounts
+        assert!(auth_service.authenticate("bob", "local-pa55").await.is_ok());
+
+        let auth_service = AuthService::new(user_repo, role_repo)
+            .with_provider(Arc::new(StaticProvider))
+            .with_local_accounts(false);
+        assert!(matches!(
+            auth_service.authenticate("bob", "local-pa55").await,
+            Err(AuthError::InvalidCredentials)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mfa_enrollment_and_sign_in() {
+        let temp_dir = TempDir::new().unwrap();
+        let user_repo =
+            Arc::new(SledUserRepository::new(temp_dir.path().join("users.sled")).unwrap())
+                as Arc<dyn UserRepository>;
+        let role_repo =
+            Arc::new(SledRoleRepository::new(temp_dir.path().join("roles.sled")).unwrap())
+                as Arc<dyn RoleRepository>;
+
+        let mut admins = Role::new("admins".to_string(), HashSet::new(), false);
+        admins.requires_mfa = true;
+        let admins = role_repo.create(admins).await.unwrap();
+        let password_hash = hash_password("testpass123").unwrap();
+        user_repo
+            .create(User::new(
+                "admin".to_string(),
+                password_hash,
+                vec![admins.id],
+            ))
+            .await
+            .unwrap();
+
+        let secrets = Arc::new(SecretBox::new(&[1u8; 32]));
+        let auth_service =
+            AuthService::new(user_repo.clone(), role_repo).with_secret_box(secrets.clone());
+
+        // The role demands MFA, so the password alone is refused until the user enrolls
+        assert!(matches!(
+            auth_service.authenticate("admin", "testpass123").await,
+            Err(AuthError::MfaEnrollmentRequired)
+        ));
+
+        auth_service
+            .enroll_mfa("admin", "testpass123")
+            .await
+            .unwrap();
+        let stored = user_repo.find_by_username("admin").await.unwrap().unwrap();
+        let secret = secrets.open(&stored.mfa.unwrap().sealed_secret).unwrap();
+        let step = now_secs() / mfa::STEP_SECS;
+        let code = |step| format!("{:06}", mfa::code_at(&secret, step));
+
+        let recovery_codes = auth_service
+            .confirm_mfa("admin", "testpass123", &code(step))
+            .await
+            .unwrap();
+        assert!(matches!(
+            auth_service.authenticate("admin", "testpass123").await,
+            Err(AuthError::MfaRequired)
+        ));
+
+        // A code is accepted once, the next step's code still works
+        assert!(
+            auth_service
+                .authenticate_with_code("admin", "testpass123", Some(&code(step)))
+                .await
+                .is_err()
+        );
+        assert!(
+            auth_service
+                .authenticate_with_code("admin", "testpass123", Some(&code(step + 1)))
+                .await
+                .is_ok()
+        );
+
+        // Recovery codes are spent on use
+        let recovery = Some(recovery_codes[0].as_str());
+        assert!(
+            auth_service
+                .authenticate_with_code("admin", "testpass123", recovery)
+                .await
+                .is_ok()
+        );
+        assert!(
+            auth_service
+                .authenticate_with_code("admin", "testpass123", recovery)
+                .await
+                .is_err()
+        );
+
+        // Settings read before a sign-in spent a code can no longer be written back
+        let stale = user_repo.find_by_username("admin").await.unwrap().unwrap();
+        let stale_mfa = stale.mfa.clone().unwrap();
+        assert!(
+            auth_service
+                .authenticate_with_code("admin", "testpass123", Some(&recovery_codes[1]))
+                .await
+                .is_ok()
+        );
+        assert!(
+            !user_repo
+                .update_mfa_if_unchanged(stale, &stale_mfa)
+                .await
+                .unwrap()
+        );
+
+        // Too many wrong codes lock the user out, even of a right one
+        for _ in 0..mfa::MAX_FAILED_CODES {
+            assert!(matches!(
+                auth_service
+                    .authenticate_with_code("admin", "testpass123", Some("000000x"))
+                    .await,
+                Err(AuthError::InvalidMfaCode)
+            ));
+        }
+        assert!(matches!(
+            auth_service
+                .authenticate_with_code("admin", "testpass123", Some(&recovery_codes[2]))
+                .await,
+            Err(AuthError::MfaLockedOut)
+        ));
+    }
 }
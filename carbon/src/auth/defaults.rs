@@ -21,6 +21,13 @@ pub fn create_admin_role() -> Role {
     permissions.insert(Permission::AdminRead);
     permissions.insert(Permission::AdminWrite);
     permissions.insert(Permission::AdminDelete);
+    permissions.insert(Permission::CreateCache);
+    permissions.insert(Permission::DropCache);
+    permissions.insert(Permission::ExportData);
+    permissions.insert(Permission::SubscribeEvents);
+    permissions.insert(Permission::ReadMetrics);
+    permissions.insert(Permission::ReadSlowLog);
+    permissions.insert(Permission::ClusterAdmin);
     permissions.insert(Permission::ManageUsers);
     permissions.insert(Permission::ManageRoles);
 
@@ -34,6 +41,10 @@ pub fn create_user_role() -> Role {
     permissions.insert(Permission::WriteCache);
     permissions.insert(Permission::DeleteCache);
     permissions.insert(Permission::AdminRead);
+    permissions.insert(Permission::ExportData);
+    permissions.insert(Permission::SubscribeEvents);
+    permissions.insert(Permission::ReadMetrics);
+    permissions.insert(Permission::ReadSlowLog);
 
     Role::new("user".to_string(), permissions, true)
 }
@@ -43,6 +54,9 @@ pub fn create_readonly_role() -> Role {
     let mut permissions = HashSet::new();
     permissions.insert(Permission::ReadCache);
     permissions.insert(Permission::AdminRead);
+    permissions.insert(Permission::SubscribeEvents);
+    permissions.insert(Permission::ReadMetrics);
+    permissions.insert(Permission::ReadSlowLog);
 
     Role::new("read-only".to_string(), permissions, true)
 }
@@ -72,6 +86,13 @@ mod tests {
         assert!(admin.has_permission(&Permission::AdminRead));
         assert!(admin.has_permission(&Permission::AdminWrite));
         assert!(admin.has_permission(&Permission::AdminDelete));
+        assert!(admin.has_permission(&Permission::CreateCache));
+        assert!(admin.has_permission(&Permission::DropCache));
+        assert!(admin.has_permission(&Permission::ExportData));
+        assert!(admin.has_permission(&Permission::SubscribeEvents));
+        assert!(admin.has_permission(&Permission::ReadMetrics));
+        assert!(admin.has_permission(&Permission::ReadSlowLog));
+        assert!(admin.has_permission(&Permission::ClusterAdmin));
         assert!(admin.has_permission(&Permission::ManageUsers));
         assert!(admin.has_permission(&Permission::ManageRoles));
     }
@@ -85,7 +106,12 @@ mod tests {
         assert!(user.has_permission(&Permission::WriteCache));
         assert!(user.has_permission(&Permission::DeleteCache));
         assert!(user.has_permission(&Permission::AdminRead));
+        assert!(user.has_permission(&Permission::ExportData));
+        assert!(user.has_permission(&Permission::SubscribeEvents));
         assert!(!user.has_permission(&Permission::AdminWrite));
+        assert!(!user.has_permission(&Permission::CreateCache));
+        assert!(!user.has_permission(&Permission::DropCache));
+        assert!(!user.has_permission(&Permission::ClusterAdmin));
         assert!(!user.has_permission(&Permission::ManageUsers));
     }
 
@@ -99,6 +125,8 @@ mod tests {
         assert!(!readonly.has_permission(&Permission::WriteCache));
         assert!(!readonly.has_permission(&Permission::DeleteCache));
         assert!(!readonly.has_permission(&Permission::AdminWrite));
+        assert!(!readonly.has_permission(&Permission::ExportData));
+        assert!(readonly.has_permission(&Permission::ReadSlowLog));
     }
 
     #[test]
@@ -1,3 +1,4 @@
+use shared::ErrorCode;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -40,6 +41,63 @@ pub enum AuthError {
 
     #[error("Password hashing error: {0}")]
     PasswordHashError(String),
+
+    #[error("MFA code required")]
+    MfaRequired,
+
+    #[error("MFA enrollment required")]
+    MfaEnrollmentRequired,
+
+    #[error("MFA already enrolled")]
+    MfaAlreadyEnrolled,
+
+    #[error("MFA enrollment not started")]
+    MfaNotEnrolled,
+
+    #[error("Invalid MFA code")]
+    InvalidMfaCode,
+
+    #[error("Too many invalid MFA codes, try again later")]
+    MfaLockedOut,
+
+    #[error("Encryption error: {0}")]
+    EncryptionError(String),
+
+    #[error("Only service accounts have API keys")]
+    NotServiceAccount,
+
+    #[error("API key not found")]
+    ApiKeyNotFound,
+}
+
+impl AuthError {
+    /// Code reported for this error by every frontend
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AuthError::InvalidCredentials | AuthError::MfaRequired | AuthError::InvalidMfaCode => {
+                ErrorCode::Unauthorized
+            }
+            AuthError::MfaLockedOut => ErrorCode::Throttled,
+            AuthError::PermissionDenied
+            | AuthError::CannotDeleteSystemRole
+            | AuthError::CannotDeleteSelf
+            | AuthError::MfaEnrollmentRequired => ErrorCode::Forbidden,
+            AuthError::UserNotFound | AuthError::RoleNotFound | AuthError::ApiKeyNotFound => {
+                ErrorCode::NotFound
+            }
+            AuthError::UserAlreadyExists
+            | AuthError::RoleAlreadyExists
+            | AuthError::MfaAlreadyEnrolled => ErrorCode::AlreadyExists,
+            AuthError::WeakPassword
+            | AuthError::InvalidRoleAssignment
+            | AuthError::MfaNotEnrolled
+            | AuthError::NotServiceAccount => ErrorCode::InvalidValue,
+            AuthError::StorageError(_)
+            | AuthError::SerializationError(_)
+            | AuthError::PasswordHashError(_)
+            | AuthError::EncryptionError(_) => ErrorCode::Internal,
+        }
+    }
 }
 
 impl From<sled::Error> for AuthError {
@@ -48,6 +106,20 @@ impl From<sled::Error> for AuthError {
     }
 }
 
+#[cfg(feature = "postgres")]
+impl From<sqlx::Error> for AuthError {
+    fn from(err: sqlx::Error) -> Self {
+        AuthError::StorageError(err.to_string())
+    }
+}
+
+#[cfg(feature = "ldap")]
+impl From<ldap3::LdapError> for AuthError {
+    fn from(err: ldap3::LdapError) -> Self {
+        AuthError::StorageError(format!("LDAP: {}", err))
+    }
+}
+
 impl From<serde_json::Error> for AuthError {
     fn from(err: serde_json::Error) -> Self {
         AuthError::SerializationError(err.to_string())
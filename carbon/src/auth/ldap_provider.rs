@@ -0,0 +1,174 @@
+use super::error::AuthError;
+use super::provider::{AuthProvider, ExternalIdentity};
+use async_trait::async_trait;
+use ldap3::{LdapConnAsync, LdapConnSettings, Scope, SearchEntry, dn_escape, ldap_escape};
+use shared::config::LdapConfig;
+use std::time::Duration;
+
+/// LDAP result code for a failed bind, whether the user or the password is wrong
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Time allowed to reach the directory before the provider is skipped
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Verifies users by binding to the directory as them, then maps their groups to roles
+pub struct LdapProvider {
+    config: LdapConfig,
+}
+
+impl LdapProvider {
+    pub fn new(config: LdapConfig) -> Self {
+        Self { config }
+    }
+
+    /// Carbon roles for a user in `groups`, the default role first
+    /// Groups are matched by their whole DN, a group named like a mapped one elsewhere in the
+    /// directory does not count
+    fn roles_for(&self, groups: &[String]) -> Vec<String> {
+        let mut roles: Vec<String> = self.config.default_role.iter().cloned().collect();
+        for group in groups {
+            let group = normalize_dn(group);
+            for (key, role) in &self.config.group_roles {
+                if normalize_dn(key) == group && !roles.contains(role) {
+                    roles.push(role.clone());
+                }
+            }
+        }
+        roles
+    }
+}
+
+/// DN compared without case or the spaces around its separators,
+/// `CN=Carbon Admins, OU=Groups` and `cn=carbon admins,ou=groups` are the same group
+fn normalize_dn(dn: &str) -> String {
+    let mut rdns = Vec::new();
+    let mut rdn = String::new();
+    let mut escaped = false;
+    for c in dn.chars() {
+        match c {
+            _ if escaped => {
+                rdn.push(c);
+                escaped = false;
+            }
+            '\\' => {
+                rdn.push(c);
+                escaped = true;
+            }
+            ',' => rdns.push(std::mem::take(&mut rdn)),
+            _ => rdn.push(c),
+        }
+    }
+    rdns.push(rdn);
+
+    rdns.iter()
+        .map(|rdn| match rdn.split_once('=') {
+            Some((attribute, value)) => format!("{}={}", attribute.trim(), value.trim()),
+            None => rdn.trim().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+        .to_lowercase()
+}
+
+#[async_trait]
+impl AuthProvider for LdapProvider {
+    fn name(&self) -> &'static str {
+        "ldap"
+    }
+
+    async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<ExternalIdentity>, AuthError> {
+        // An empty password makes an unauthenticated bind, which most servers accept
+        if username.is_empty() || password.is_empty() {
+            return Ok(None);
+        }
+
+        let settings = LdapConnSettings::new().set_conn_timeout(CONNECT_TIMEOUT);
+        let (connection, mut ldap) =
+            LdapConnAsync::with_settings(settings, &self.config.url).await?;
+        ldap3::drive!(connection);
+
+        let bind_dn = self
+            .config
+            .bind_dn
+            .replace("{username}", &dn_escape(username));
+        let bound = ldap.simple_bind(&bind_dn, password).await?;
+        if bound.rc == INVALID_CREDENTIALS {
+            return Ok(None);
+        }
+        bound.success()?;
+
+        let filter = self
+            .config
+            .user_filter
+            .replace("{username}", &ldap_escape(username));
+        let (entries, _) = ldap
+            .search(
+                &self.config.search_base,
+                Scope::Subtree,
+                &filter,
+                vec![self.config.group_attribute.as_str()],
+            )
+            .await?
+            .success()?;
+        ldap.unbind().await?;
+
+        let groups: Vec<String> = entries
+            .into_iter()
+            .map(SearchEntry::construct)
+            .flat_map(|mut entry| {
+                entry
+                    .attrs
+                    .remove(&self.config.group_attribute)
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        Ok(Some(ExternalIdentity {
+            username: username.to_string(),
+            roles: self.roles_for(&groups),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles_for_groups() {
+        let provider = LdapProvider::new(LdapConfig {
+            url: "ldap://localhost".to_string(),
+            bind_dn: "uid={username},ou=people,dc=example,dc=com".to_string(),
+            search_base: "dc=example,dc=com".to_string(),
+            user_filter: "(uid={username})".to_string(),
+            group_attribute: "memberOf".to_string(),
+            group_roles: vec![
+                (
+                    "cn=carbon-admins,ou=groups,dc=example,dc=com".to_string(),
+                    "admin".to_string(),
+                ),
+                (
+                    "cn=ops,ou=groups,dc=example,dc=com".to_string(),
+                    "user".to_string(),
+                ),
+            ],
+            default_role: Some("read-only".to_string()),
+        });
+
+        let groups = vec![
+            "CN=Carbon-Admins, OU=Groups, DC=example, DC=com".to_string(),
+            "cn=ops,ou=groups,dc=example,dc=com".to_string(),
+            "cn=unrelated,ou=groups,dc=example,dc=com".to_string(),
+        ];
+        assert_eq!(provider.roles_for(&groups), ["read-only", "admin", "user"]);
+
+        // Same CN in another branch of the directory
+        let elsewhere = vec!["cn=carbon-admins,ou=guests,dc=example,dc=com".to_string()];
+        assert_eq!(provider.roles_for(&elsewhere), ["read-only"]);
+        assert_eq!(provider.roles_for(&[]), ["read-only"]);
+    }
+}
@@ -0,0 +1,278 @@
+use super::error::AuthError;
+use super::models::Permission;
+use super::role_service::RoleService;
+use super::user_service::UserService;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Users and roles an environment should have, applied with `carbon-server --bootstrap` or
+/// `POST /admin/auth/import` and produced by `GET /admin/auth/export`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthManifest {
+    #[serde(default)]
+    pub roles: Vec<DeclaredRole>,
+    #[serde(default)]
+    pub users: Vec<DeclaredUser>,
+}
+
+/// A custom role and the permissions it grants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclaredRole {
+    pub name: String,
+    #[serde(default)]
+    pub permissions: HashSet<Permission>,
+    #[serde(default)]
+    pub requires_mfa: bool,
+}
+
+/// A user and the names of their roles
+/// New users need a `password` or, as exported, a `password_hash`, existing users keep theirs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclaredUser {
+    pub username: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+    pub roles: Vec<String>,
+}
+
+/// What applying a manifest changed, names of users and roles left as they were are not listed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ManifestReport {
+    pub roles_created: Vec<String>,
+    pub roles_updated: Vec<String>,
+    pub users_created: Vec<String>,
+    pub users_updated: Vec<String>,
+}
+
+impl AuthManifest {
+    /// Parse a YAML manifest, JSON being valid YAML works as well
+    pub fn parse(contents: &str) -> Result<Self, AuthError> {
+        serde_yaml::from_str(contents).map_err(|e| AuthError::SerializationError(e.to_string()))
+    }
+
+    /// The custom roles and all people's accounts as they are now, with password hashes so the
+    /// users can sign in the same way wherever the manifest is applied
+    pub async fn export(
+        user_service: &UserService,
+        role_service: &RoleService,
+    ) -> Result<Self, AuthError> {
+        let roles = role_service.list_roles().await?;
+        let role_names: HashMap<_, _> = roles
+            .iter()
+            .map(|role| (role.id.clone(), role.name.clone()))
+            .collect();
+
+        let users = user_service
+            .list_users()
+            .await?
+            .into_iter()
+            // Service accounts have no password to carry over
+            .filter(|user| !user.is_service_account())
+            .map(|user| DeclaredUser {
+                username: user.username,
+                password: None,
+                password_hash: Some(user.password_hash),
+                roles: user
+                    .role_ids
+                    .iter()
+                    .filter_map(|id| role_names.get(id).cloned())
+                    .collect(),
+            })
+            .collect();
+        let roles = roles
+            .into_iter()
+            .filter(|role| !role.is_system_role)
+            .map(|role| DeclaredRole {
+                name: role.name,
+                permissions: role.permissions,
+                requires_mfa: role.requires_mfa,
+            })
+            .collect();
+
+        Ok(Self { roles, users })
+    }
+
+    /// Create what is missing and bring roles' permissions and users' roles in line with the
+    /// manifest, so applying it again changes nothing
+    /// Checked before anything is written, so a manifest naming an unknown role or a new user
+    /// without a password changes nothing
+    pub async fn apply(
+        &self,
+        user_service: &UserService,
+        role_service: &RoleService,
+    ) -> Result<ManifestReport, AuthError> {
+        self.validate(user_service, role_service).await?;
+        let mut report = ManifestReport::default();
+
+        for declared in &self.roles {
+            let role = match role_service.find_role_by_name(&declared.name).await? {
+                Some(role) => role,
+                None => {
+                    let role = role_service
+                        .create_role(declared.name.clone(), declared.permissions.clone())
+                        .await?;
+                    report.roles_created.push(declared.name.clone());
+                    role
+                }
+            };
+            // System roles keep their permissions, only their MFA requirement can change
+            let mut updated = false;
+            if !role.is_system_role && role.permissions != declared.permissions {
+                role_service
+                    .update_role(&role.id, declared.permissions.clone())
+                    .await?;
+                updated = true;
+            }
+            if role.requires_mfa != declared.requires_mfa {
+                role_service
+                    .set_requires_mfa(&role.id, declared.requires_mfa)
+                    .await?;
+                updated = true;
+            }
+            if updated && !report.roles_created.contains(&declared.name) {
+                report.roles_updated.push(declared.name.clone());
+            }
+        }
+
+        for declared in &self.users {
+            let mut role_ids = Vec::with_capacity(declared.roles.len());
+            for name in &declared.roles {
+                role_ids.push(role_service.get_role(name).await?.id);
+            }
+
+            match user_service.get_user(&declared.username).await {
+                Ok(user) => {
+                    let current: HashSet<_> = user.role_ids.iter().collect();
+                    let declared_ids: HashSet<_> = role_ids.iter().collect();
+                    if current != declared_ids {
+                        user_service.assign_roles(&user.id, role_ids).await?;
+                        report.users_updated.push(declared.username.clone());
+                    }
+                }
+                Err(AuthError::UserNotFound) => {
+                    let username = declared.username.clone();
+                    match (&declared.password, &declared.password_hash) {
+                        (Some(password), _) => {
+                            user_service
+                                .create_user(username, password.clone(), role_ids)
+                                .await?
+                        }
+                        (None, Some(hash)) => {
+                            user_service
+                                .create_user_with_hash(username, hash.clone(), role_ids)
+                                .await?
+                        }
+                        (None, None) => return Err(missing_password(&declared.username)),
+                    };
+                    report.users_created.push(declared.username.clone());
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn validate(
+        &self,
+        user_service: &UserService,
+        role_service: &RoleService,
+    ) -> Result<(), AuthError> {
+        let declared_roles: HashSet<_> = self.roles.iter().map(|role| &role.name).collect();
+        for user in &self.users {
+            for name in &user.roles {
+                if !declared_roles.contains(name)
+                    && role_service.find_role_by_name(name).await?.is_none()
+                {
+                    return Err(AuthError::RoleNotFound);
+                }
+            }
+            if user.password.is_none()
+                && user.password_hash.is_none()
+                && matches!(
+                    user_service.get_user(&user.username).await,
+                    Err(AuthError::UserNotFound)
+                )
+            {
+                return Err(missing_password(&user.username));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn missing_password(username: &str) -> AuthError {
+    AuthError::SerializationError(format!(
+        "New user '{}' needs a password or password_hash",
+        username
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::repository::{RoleRepository, UserRepository};
+    use crate::auth::sled_repository::{SledRoleRepository, SledUserRepository};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    const MANIFEST: &str = r#"
+roles:
+  - name: reporting
+    permissions: [ReadCache, ReadMetrics]
+users:
+  - username: alice
+    password: alicepass123
+    roles: [reporting]
+  - username: bob
+    password: bobpass123
+    roles: [user, reporting]
+"#;
+
+    #[tokio::test]
+    async fn test_manifest_applies_idempotently() {
+        let temp_dir = TempDir::new().unwrap();
+        let user_repo =
+            Arc::new(SledUserRepository::new(temp_dir.path().join("users.sled")).unwrap())
+                as Arc<dyn UserRepository>;
+        let role_repo =
+            Arc::new(SledRoleRepository::new(temp_dir.path().join("roles.sled")).unwrap())
+                as Arc<dyn RoleRepository>;
+        let user_service = UserService::new(user_repo, role_repo.clone());
+        let role_service = RoleService::new(role_repo);
+        role_service.initialize_default_roles().await.unwrap();
+
+        let manifest = AuthManifest::parse(MANIFEST).unwrap();
+        let report = manifest.apply(&user_service, &role_service).await.unwrap();
+        assert_eq!(report.roles_created, ["reporting"]);
+        assert_eq!(report.users_created, ["alice", "bob"]);
+
+        // Applying it again finds everything in place
+        let report = manifest.apply(&user_service, &role_service).await.unwrap();
+        assert!(report.roles_created.is_empty() && report.users_created.is_empty());
+        assert!(report.roles_updated.is_empty() && report.users_updated.is_empty());
+
+        // An export recreates the same users and roles
+        let exported = AuthManifest::export(&user_service, &role_service)
+            .await
+            .unwrap();
+        assert_eq!(exported.roles.len(), 1);
+        assert_eq!(exported.users.len(), 2);
+        assert!(
+            exported
+                .users
+                .iter()
+                .all(|user| user.password_hash.is_some())
+        );
+
+        // Unknown roles are refused before anything is written
+        let invalid = AuthManifest::parse(
+            "users:\n  - username: carol\n    password: carolpass123\n    roles: [missing]\n",
+        )
+        .unwrap();
+        assert!(invalid.apply(&user_service, &role_service).await.is_err());
+        assert!(user_service.get_user("carol").await.is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Time-based one-time passwords (RFC 6238) and recovery codes for two-factor sign-in
+
+use super::error::AuthError;
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+
+/// Seconds each code stays valid
+pub const STEP_SECS: u64 = 30;
+
+/// Digits in a code
+pub const DIGITS: u32 = 6;
+
+/// Steps accepted either side of the current one, for clocks that drift apart
+const SKEW_STEPS: u64 = 1;
+
+/// Bytes in a secret, the 160 bits RFC 4226 recommends
+const SECRET_LEN: usize = 20;
+
+/// Recovery codes handed out when enrollment is confirmed
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Codes a user may get wrong in a row before their sign-ins with MFA are refused
+pub const MAX_FAILED_CODES: u32 = 5;
+
+/// How long sign-ins stay refused after MAX_FAILED_CODES wrong codes
+pub const LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Name authenticator apps show next to the account
+const ISSUER: &str = "Carbon";
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Secret for a new enrollment, to be loaded into the user's authenticator app
+pub fn generate_secret() -> Vec<u8> {
+    let mut rng = rand::rng();
+    (0..SECRET_LEN).map(|_| rng.random()).collect()
+}
+
+/// HOTP value of `secret` for time step `step`
+pub fn code_at(secret: &[u8], step: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation, RFC 4226 section 5.3
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset],
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]) & 0x7fff_ffff;
+    binary % 10u32.pow(DIGITS)
+}
+
+/// Time step `code` is valid for around `now_secs`, None when it does not match
+/// Only steps after `after_step` are accepted, so a code cannot be used twice
+pub fn verify_code(secret: &[u8], code: &str, now_secs: u64, after_step: u64) -> Option<u64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+
+    let current = now_secs / STEP_SECS;
+    (current.saturating_sub(SKEW_STEPS)..=current + SKEW_STEPS)
+        .filter(|step| *step > after_step)
+        .find(|step| code_at(secret, *step) == code)
+}
+
+/// `otpauth://` URI authenticator apps read from a QR code
+pub fn provisioning_uri(username: &str, secret: &[u8]) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        ISSUER,
+        percent_encode(username),
+        base32_encode(secret),
+        ISSUER,
+        DIGITS,
+        STEP_SECS
+    )
+}
+
+/// RFC 4648 base32 without padding, the form authenticator apps expect secrets in
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// One-time codes for signing in without the authenticator app, like `3f9a1-c07d2`
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let digits: String = (0..10)
+                .map(|_| format!("{:x}", rng.random_range(0..16u8)))
+                .collect();
+            format!("{}-{}", &digits[..5], &digits[5..])
+        })
+        .collect()
+}
+
+/// Digest a recovery code is stored as, ignoring case and separators
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    Sha256::digest(normalized.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Codes tried per user since their last accepted one, so the million six-digit codes cannot
+/// be tried in turn once the password is known
+/// Counted per node, each node of a shared auth store locks out on its own
+#[derive(Default)]
+pub struct FailedCodes {
+    users: DashMap<String, (u32, Instant)>,
+}
+
+impl FailedCodes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count an attempt by `user_id` before its code is checked, MfaLockedOut when it has
+    /// used up its attempts
+    /// Counting first keeps concurrent attempts from getting past the limit together
+    pub fn attempt(&self, user_id: &str) -> Result<(), AuthError> {
+        let mut entry = self
+            .users
+            .entry(user_id.to_string())
+            .or_insert((0, Instant::now()));
+        let (attempts, last) = entry.value_mut();
+        if *attempts >= MAX_FAILED_CODES {
+            if last.elapsed() < LOCKOUT {
+                return Err(AuthError::MfaLockedOut);
+            }
+            // The lockout ran out, the count starts over
+            *attempts = 0;
+        }
+        *attempts += 1;
+        *last = Instant::now();
+        Ok(())
+    }
+
+    /// Forget the attempts of a user whose code was accepted
+    pub fn succeeded(&self, user_id: &str) {
+        self.users.remove(user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B, SHA1 secret, truncated to six digits
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        assert_eq!(code_at(RFC_SECRET, 59 / STEP_SECS), 287082);
+        assert_eq!(code_at(RFC_SECRET, 1111111109 / STEP_SECS), 81804);
+        assert_eq!(code_at(RFC_SECRET, 1234567890 / STEP_SECS), 5924);
+    }
+
+    #[test]
+    fn test_verify_code() {
+        let now = 1111111109;
+        let step = now / STEP_SECS;
+
+        assert_eq!(verify_code(RFC_SECRET, "081804", now, 0), Some(step));
+        // Accepted one step late, for clock drift
+        assert_eq!(
+            verify_code(RFC_SECRET, "081804", now + STEP_SECS, 0),
+            Some(step)
+        );
+        // Not after the step it was used in, and not with the leading zero missing
+        assert_eq!(verify_code(RFC_SECRET, "081804", now, step), None);
+        assert_eq!(verify_code(RFC_SECRET, "81804", now, 0), None);
+    }
+
+    #[test]
+    fn test_base32_encode() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(
+            base32_encode(RFC_SECRET),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+        );
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(codes[0].len(), 11);
+        assert_eq!(
+            hash_recovery_code(&codes[0]),
+            hash_recovery_code(&codes[0].replace('-', "").to_uppercase())
+        );
+    }
+
+    #[test]
+    fn test_failed_codes_lock_out() {
+        let failed = FailedCodes::new();
+        for _ in 0..MAX_FAILED_CODES {
+            assert!(failed.attempt("alice").is_ok());
+        }
+        assert!(matches!(
+            failed.attempt("alice"),
+            Err(AuthError::MfaLockedOut)
+        ));
+        assert!(failed.attempt("bob").is_ok());
+
+        // Attempts only add up between accepted codes
+        failed.succeeded("bob");
+        for _ in 0..MAX_FAILED_CODES {
+            assert!(failed.attempt("bob").is_ok());
+        }
+
+        // An expired lockout starts the count over
+        failed.users.get_mut("alice").unwrap().1 = Instant::now().checked_sub(LOCKOUT).unwrap();
+        assert!(failed.attempt("alice").is_ok());
+    }
+}
@@ -1,12 +1,21 @@
 // Public API
+pub mod api_key;
 pub mod auth_service;
 pub mod defaults;
 pub mod error;
+#[cfg(feature = "ldap")]
+pub mod ldap_provider;
+pub mod manifest;
+pub mod mfa;
 pub mod moka_session_repository;
 pub mod models;
 pub mod password;
+#[cfg(feature = "postgres")]
+pub mod postgres_repository;
+pub mod provider;
 pub mod repository;
 pub mod role_service;
+pub mod secret_box;
 pub mod session;
 pub mod session_store;
 pub mod sled_repository;
@@ -15,11 +24,21 @@ pub mod user_service;
 // Re-export commonly used types
 pub use auth_service::AuthService;
 pub use error::AuthError;
+#[cfg(feature = "ldap")]
+pub use ldap_provider::LdapProvider;
+pub use manifest::{AuthManifest, ManifestReport};
 pub use moka_session_repository::MokaSessionRepository;
-pub use models::{Permission, Role, User};
+pub use models::{AccountKind, ApiKey, MfaSettings, Permission, Role, User};
+#[cfg(feature = "postgres")]
+pub use postgres_repository::{PostgresRoleRepository, PostgresUserRepository};
+pub use provider::{AuthProvider, ExternalIdentity};
 pub use repository::{RoleRepository, UserRepository};
 pub use role_service::RoleService;
-pub use session::{current_timestamp_ms, format_utc_time, generate_session_token, Session, SessionToken};
+pub use secret_box::SecretBox;
+pub use session::{
+    RefreshToken, Session, SessionToken, current_timestamp_ms, format_utc_time,
+    generate_session_token,
+};
 pub use session_store::{SessionRepository, SessionStore};
 pub use sled_repository::{SledRoleRepository, SledUserRepository};
 pub use user_service::UserService;
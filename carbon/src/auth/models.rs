@@ -1,3 +1,4 @@
+use crate::domain::Quota;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -14,6 +15,21 @@ pub enum Permission {
     AdminRead,
     AdminWrite,
     AdminDelete,
+    CreateCache,
+    DropCache,
+
+    // Enumerating a cache's contents, as queries do
+    ExportData,
+
+    // Streaming cache events
+    SubscribeEvents,
+
+    // Operational data
+    ReadMetrics,
+    ReadSlowLog,
+
+    // Node-wide settings such as configuration reload
+    ClusterAdmin,
 
     // User management
     ManageUsers,
@@ -28,6 +44,12 @@ pub struct Role {
     pub name: String,
     pub permissions: HashSet<Permission>,
     pub is_system_role: bool,
+    /// Members must sign in with a second factor
+    #[serde(default)]
+    pub requires_mfa: bool,
+    /// Limits on what members write, None for no limits
+    #[serde(default)]
+    pub quota: Option<Quota>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -38,6 +60,8 @@ impl Role {
             name,
             permissions,
             is_system_role,
+            requires_mfa: false,
+            quota: None,
             created_at: Utc::now(),
         }
     }
@@ -57,6 +81,20 @@ pub struct User {
     pub username: String,
     pub password_hash: String,
     pub role_ids: Vec<String>,
+    /// TOTP enrollment, None until the user starts one
+    #[serde(default)]
+    pub mfa: Option<MfaSettings>,
+    /// Set by an admin to make this user sign in with a second factor
+    #[serde(default)]
+    pub mfa_required: bool,
+    /// Limits on what this user writes, taking the place of their roles' quotas
+    #[serde(default)]
+    pub quota: Option<Quota>,
+    #[serde(default)]
+    pub kind: AccountKind,
+    /// Credentials of a service account, always empty for people
+    #[serde(default)]
+    pub api_keys: Vec<ApiKey>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -69,10 +107,80 @@ impl User {
             username,
             password_hash,
             role_ids,
+            mfa: None,
+            mfa_required: false,
+            quota: None,
+            kind: AccountKind::Human,
+            api_keys: Vec::new(),
             created_at: now,
             updated_at: now,
         }
     }
+
+    /// Automation identity signing in with API keys instead of a password
+    pub fn service_account(username: String, role_ids: Vec<String>) -> Self {
+        Self {
+            kind: AccountKind::Service,
+            ..Self::new(username, String::new(), role_ids)
+        }
+    }
+
+    pub fn is_service_account(&self) -> bool {
+        self.kind == AccountKind::Service
+    }
+
+    /// Whether the user has a confirmed TOTP enrollment
+    pub fn mfa_enrolled(&self) -> bool {
+        self.mfa.as_ref().is_some_and(|mfa| mfa.confirmed)
+    }
+}
+
+/// Whether an account belongs to a person or to automation
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    #[default]
+    Human,
+    /// Cannot sign in with a password or open sessions, only API keys
+    Service,
+}
+
+/// Non-expiring credential of a service account, holding some of its permissions on some caches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    /// SHA-256 digest of the key's secret
+    pub secret_hash: String,
+    /// Caches the key can be used on, empty for all of them
+    pub caches: Vec<String>,
+    /// Permissions the key grants, the account's roles must grant them as well
+    pub permissions: HashSet<Permission>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiKey {
+    /// Whether the key allows `permission` on `cache`, None for requests naming no cache
+    pub fn allows(&self, permission: &Permission, cache: Option<&str>) -> bool {
+        let cache_allowed = match cache {
+            Some(cache) => self.caches.is_empty() || self.caches.iter().any(|c| c == cache),
+            None => self.caches.is_empty(),
+        };
+        cache_allowed && self.permissions.contains(permission)
+    }
+}
+
+/// A user's TOTP enrollment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MfaSettings {
+    /// TOTP secret, sealed with the auth store's key
+    pub sealed_secret: String,
+    /// Set once the user proved their authenticator works, sign-in asks for codes from then on
+    pub confirmed: bool,
+    /// SHA-256 digests of the recovery codes not used yet
+    pub recovery_code_hashes: Vec<String>,
+    /// Time step of the last accepted code, so a code cannot be replayed
+    pub last_step: u64,
 }
 
 #[cfg(test)]
@@ -102,4 +210,21 @@ mod tests {
         assert!(role.has_any_permission(&[Permission::ReadCache, Permission::WriteCache]));
         assert!(!role.has_any_permission(&[Permission::WriteCache, Permission::DeleteCache]));
     }
+
+    #[test]
+    fn test_api_key_scope() {
+        let key = ApiKey {
+            id: "key".to_string(),
+            name: "ci".to_string(),
+            secret_hash: String::new(),
+            caches: vec!["orders".to_string()],
+            permissions: HashSet::from([Permission::ReadCache]),
+            created_at: Utc::now(),
+        };
+
+        assert!(key.allows(&Permission::ReadCache, Some("orders")));
+        assert!(!key.allows(&Permission::WriteCache, Some("orders")));
+        assert!(!key.allows(&Permission::ReadCache, Some("users")));
+        assert!(!key.allows(&Permission::ReadCache, None));
+    }
 }
@@ -1,11 +1,12 @@
 use super::models::User;
-use super::session::{Session, SessionToken, generate_session_token};
+use super::session::{RefreshToken, Session, SessionToken, generate_session_token};
 use super::session_store::SessionRepository;
 use async_trait::async_trait;
+use moka::Expiry;
 use moka::future::Cache;
 use shared::Result;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 /// Username type alias
@@ -17,28 +18,35 @@ pub struct MokaSessionRepository {
     sessions: Cache<SessionToken, Session>,
     // Secondary index: username -> list of session tokens
     user_sessions: Cache<Username, Arc<RwLock<Vec<SessionToken>>>>,
+    // Refresh token -> the user and session it was issued for
+    refresh_tokens: Cache<SessionToken, RefreshToken>,
 }
 
 impl MokaSessionRepository {
     /// Create a new Moka session repository with specified capacity and default TTL
+    /// Sessions are evicted at their own expiry, `default_ttl` bounds the username index
     pub fn new(max_sessions: Option<u64>, default_ttl: Option<Duration>) -> Self {
         let mut sessions_builder = Cache::builder();
         let mut user_sessions_builder = Cache::builder();
+        let mut refresh_tokens_builder = Cache::builder();
 
         if let Some(capacity) = max_sessions {
             sessions_builder = sessions_builder.max_capacity(capacity);
+            refresh_tokens_builder = refresh_tokens_builder.max_capacity(capacity);
             // User sessions cache can be smaller (assume 10 sessions per user on average)
             user_sessions_builder = user_sessions_builder.max_capacity(capacity / 10);
         }
 
+        sessions_builder = sessions_builder.expire_after(SessionExpiry);
+
         if let Some(ttl) = default_ttl {
-            sessions_builder = sessions_builder.time_to_live(ttl);
             user_sessions_builder = user_sessions_builder.time_to_live(ttl);
         }
 
         Self {
             sessions: sessions_builder.build(),
             user_sessions: user_sessions_builder.build(),
+            refresh_tokens: refresh_tokens_builder.expire_after(RefreshExpiry).build(),
         }
     }
 
@@ -48,6 +56,46 @@ impl MokaSessionRepository {
     }
 }
 
+/// Evicts each session when it expires, so the session TTL can change at runtime
+/// without being capped by a TTL fixed when the cache was built
+struct SessionExpiry;
+
+impl Expiry<SessionToken, Session> for SessionExpiry {
+    fn expire_after_create(
+        &self,
+        _token: &SessionToken,
+        session: &Session,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        Some(Duration::from_millis(session.remaining_ttl_ms()))
+    }
+
+    // Accesses re-insert the session with its renewed expires_at
+    fn expire_after_update(
+        &self,
+        _token: &SessionToken,
+        session: &Session,
+        _updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(Duration::from_millis(session.remaining_ttl_ms()))
+    }
+}
+
+/// Evicts each refresh token when it expires
+struct RefreshExpiry;
+
+impl Expiry<SessionToken, RefreshToken> for RefreshExpiry {
+    fn expire_after_create(
+        &self,
+        _token: &SessionToken,
+        refresh: &RefreshToken,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        Some(Duration::from_millis(refresh.remaining_ttl_ms()))
+    }
+}
+
 #[async_trait]
 impl SessionRepository for MokaSessionRepository {
     async fn create_session(
@@ -72,6 +120,8 @@ impl SessionRepository for MokaSessionRepository {
 
         {
             let mut tokens = tokens_lock.write().await;
+            // Drop tokens of sessions that have since expired
+            tokens.retain(|t| self.sessions.contains_key(t));
             tokens.push(token.clone());
         }
 
@@ -81,6 +131,10 @@ impl SessionRepository for MokaSessionRepository {
     }
 
     async fn get_session(&self, token: &SessionToken) -> Result<User> {
+        self.touch_session(token).await.map(|session| session.user)
+    }
+
+    async fn touch_session(&self, token: &SessionToken) -> Result<Session> {
         let session = self
             .sessions
             .get(token)
@@ -96,9 +150,9 @@ impl SessionRepository for MokaSessionRepository {
         // Update last_accessed timestamp
         let mut updated = session.clone();
         updated.update_last_accessed();
-        self.sessions.insert(token.clone(), updated).await;
+        self.sessions.insert(token.clone(), updated.clone()).await;
 
-        Ok(session.user)
+        Ok(updated)
     }
 
     async fn delete_session(&self, token: &SessionToken) -> Result<bool> {
@@ -112,6 +166,17 @@ impl SessionRepository for MokaSessionRepository {
             }
         }
 
+        // Refresh tokens issued with the session would otherwise start a new one
+        let refresh_tokens: Vec<_> = self
+            .refresh_tokens
+            .iter()
+            .filter(|(_, refresh)| &refresh.session_token == token)
+            .map(|(refresh_token, _)| refresh_token)
+            .collect();
+        for refresh_token in &refresh_tokens {
+            self.refresh_tokens.invalidate(refresh_token.as_ref()).await;
+        }
+
         Ok(session.is_some())
     }
 
@@ -219,6 +284,9 @@ impl SessionRepository for MokaSessionRepository {
             self.user_sessions.invalidate(username).await;
         }
 
+        // Refresh tokens would otherwise sign the user back in
+        self.delete_user_refresh_tokens(username).await?;
+
         Ok(count)
     }
 
@@ -257,6 +325,41 @@ impl SessionRepository for MokaSessionRepository {
             .await;
         Ok(())
     }
+
+    async fn create_refresh_token(&self, session: &Session, ttl_ms: u64) -> Result<RefreshToken> {
+        let refresh = RefreshToken::new(session.user.clone(), session.token.clone(), ttl_ms);
+        self.refresh_tokens
+            .insert(refresh.token.clone(), refresh.clone())
+            .await;
+        Ok(refresh)
+    }
+
+    async fn insert_refresh_token(&self, refresh: &RefreshToken) -> Result<()> {
+        self.refresh_tokens
+            .insert(refresh.token.clone(), refresh.clone())
+            .await;
+        Ok(())
+    }
+
+    async fn take_refresh_token(&self, token: &SessionToken) -> Result<RefreshToken> {
+        match self.refresh_tokens.remove(token).await {
+            Some(refresh) if !refresh.is_expired() => Ok(refresh),
+            _ => Err(shared::Error::NotFound),
+        }
+    }
+
+    async fn delete_user_refresh_tokens(&self, username: &str) -> Result<usize> {
+        let refresh_tokens: Vec<_> = self
+            .refresh_tokens
+            .iter()
+            .filter(|(_, refresh)| refresh.user.username == username)
+            .map(|(token, _)| token)
+            .collect();
+        for token in &refresh_tokens {
+            self.refresh_tokens.invalidate(token.as_ref()).await;
+        }
+        Ok(refresh_tokens.len())
+    }
 }
 
 #[cfg(test)]
@@ -456,4 +559,30 @@ mod tests {
         assert_eq!(user1_sessions.len(), 1);
         assert_eq!(user2_sessions.len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_refresh_tokens_are_single_use() {
+        let repo = MokaSessionRepository::with_defaults();
+        let user = User::new("testuser".to_string(), "hash".to_string(), vec![]);
+        let session = repo.create_session(user, 3600000, None).await.unwrap();
+
+        let refresh = repo.create_refresh_token(&session, 3600000).await.unwrap();
+        let taken = repo.take_refresh_token(&refresh.token).await.unwrap();
+        assert_eq!(taken.session_token, session.token);
+        assert!(repo.take_refresh_token(&refresh.token).await.is_err());
+
+        // Logging the user out everywhere drops their refresh tokens too
+        let refresh = repo.create_refresh_token(&session, 3600000).await.unwrap();
+        repo.delete_user_sessions("testuser").await.unwrap();
+        assert!(repo.take_refresh_token(&refresh.token).await.is_err());
+
+        // So does logging out of the session they were issued with
+        let session = repo
+            .create_session(session.user, 3600000, None)
+            .await
+            .unwrap();
+        let refresh = repo.create_refresh_token(&session, 3600000).await.unwrap();
+        repo.delete_session(&session.token).await.unwrap();
+        assert!(repo.take_refresh_token(&refresh.token).await.is_err());
+    }
 }
@@ -0,0 +1,256 @@
+use super::error::AuthError;
+use super::models::{MfaSettings, Role, User};
+use super::repository::{RoleRepository, UserRepository};
+use async_trait::async_trait;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::types::Json;
+
+/// Records are kept as JSON documents, with the lookup columns alongside them, so model
+/// fields can be added without a schema migration
+const CREATE_USERS_TABLE: &str = "CREATE TABLE IF NOT EXISTS carbon_users (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    data JSONB NOT NULL
+)";
+const CREATE_ROLES_TABLE: &str = "CREATE TABLE IF NOT EXISTS carbon_roles (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    data JSONB NOT NULL
+)";
+
+/// Connections each Carbon node keeps open to the auth database
+const MAX_CONNECTIONS: u32 = 5;
+
+/// Connect to `database_url` and create the auth tables if they are missing
+/// The pool can back both repositories, several nodes may share the same database
+pub async fn connect(database_url: &str) -> Result<PgPool, AuthError> {
+    let pool = PgPoolOptions::new()
+        .max_connections(MAX_CONNECTIONS)
+        .connect(database_url)
+        .await?;
+
+    sqlx::query(CREATE_USERS_TABLE).execute(&pool).await?;
+    sqlx::query(CREATE_ROLES_TABLE).execute(&pool).await?;
+
+    Ok(pool)
+}
+
+/// Whether `err` is a unique constraint violation, as when another node created the same name
+fn is_unique_violation(err: &sqlx::Error) -> bool {
+    err.as_database_error()
+        .is_some_and(|err| err.is_unique_violation())
+}
+
+#[derive(Clone)]
+pub struct PostgresUserRepository {
+    pool: PgPool,
+}
+
+impl PostgresUserRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for PostgresUserRepository {
+    async fn create(&self, user: User) -> Result<User, AuthError> {
+        let result =
+            sqlx::query("INSERT INTO carbon_users (id, username, data) VALUES ($1, $2, $3)")
+                .bind(&user.id)
+                .bind(&user.username)
+                .bind(Json(&user))
+                .execute(&self.pool)
+                .await;
+
+        match result {
+            Ok(_) => Ok(user),
+            Err(err) if is_unique_violation(&err) => Err(AuthError::UserAlreadyExists),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, AuthError> {
+        let user: Option<(Json<User>,)> =
+            sqlx::query_as("SELECT data FROM carbon_users WHERE username = $1")
+                .bind(username)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(user.map(|(Json(user),)| user))
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<User>, AuthError> {
+        let user: Option<(Json<User>,)> =
+            sqlx::query_as("SELECT data FROM carbon_users WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(user.map(|(Json(user),)| user))
+    }
+
+    async fn list_all(&self) -> Result<Vec<User>, AuthError> {
+        let users: Vec<(Json<User>,)> = sqlx::query_as("SELECT data FROM carbon_users")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(users.into_iter().map(|(Json(user),)| user).collect())
+    }
+
+    async fn update(&self, user: User) -> Result<User, AuthError> {
+        let result = sqlx::query("UPDATE carbon_users SET username = $2, data = $3 WHERE id = $1")
+            .bind(&user.id)
+            .bind(&user.username)
+            .bind(Json(&user))
+            .execute(&self.pool)
+            .await;
+
+        match result {
+            Ok(done) if done.rows_affected() == 0 => Err(AuthError::UserNotFound),
+            Ok(_) => Ok(user),
+            Err(err) if is_unique_violation(&err) => Err(AuthError::UserAlreadyExists),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn update_mfa_if_unchanged(
+        &self,
+        user: User,
+        current: &MfaSettings,
+    ) -> Result<bool, AuthError> {
+        // The comparison and the write are one statement, so two nodes cannot both pass it
+        let done =
+            sqlx::query("UPDATE carbon_users SET data = $2 WHERE id = $1 AND data->'mfa' = $3")
+                .bind(&user.id)
+                .bind(Json(&user))
+                .bind(Json(current))
+                .execute(&self.pool)
+                .await?;
+        Ok(done.rows_affected() == 1)
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AuthError> {
+        let done = sqlx::query("DELETE FROM carbon_users WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if done.rows_affected() == 0 {
+            return Err(AuthError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    async fn username_exists(&self, username: &str) -> Result<bool, AuthError> {
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT EXISTS (SELECT 1 FROM carbon_users WHERE username = $1)")
+                .bind(username)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(exists)
+    }
+}
+
+#[derive(Clone)]
+pub struct PostgresRoleRepository {
+    pool: PgPool,
+}
+
+impl PostgresRoleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RoleRepository for PostgresRoleRepository {
+    async fn create(&self, role: Role) -> Result<Role, AuthError> {
+        let result = sqlx::query("INSERT INTO carbon_roles (id, name, data) VALUES ($1, $2, $3)")
+            .bind(&role.id)
+            .bind(&role.name)
+            .bind(Json(&role))
+            .execute(&self.pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(role),
+            Err(err) if is_unique_violation(&err) => Err(AuthError::RoleAlreadyExists),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn find_by_name(&self, name: &str) -> Result<Option<Role>, AuthError> {
+        let role: Option<(Json<Role>,)> =
+            sqlx::query_as("SELECT data FROM carbon_roles WHERE name = $1")
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(role.map(|(Json(role),)| role))
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Role>, AuthError> {
+        let role: Option<(Json<Role>,)> =
+            sqlx::query_as("SELECT data FROM carbon_roles WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(role.map(|(Json(role),)| role))
+    }
+
+    async fn find_by_ids(&self, ids: &[String]) -> Result<Vec<Role>, AuthError> {
+        let roles: Vec<(Json<Role>,)> =
+            sqlx::query_as("SELECT data FROM carbon_roles WHERE id = ANY($1)")
+                .bind(ids)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(roles.into_iter().map(|(Json(role),)| role).collect())
+    }
+
+    async fn list_all(&self) -> Result<Vec<Role>, AuthError> {
+        let roles: Vec<(Json<Role>,)> = sqlx::query_as("SELECT data FROM carbon_roles")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(roles.into_iter().map(|(Json(role),)| role).collect())
+    }
+
+    async fn update(&self, role: Role) -> Result<Role, AuthError> {
+        let result = sqlx::query("UPDATE carbon_roles SET name = $2, data = $3 WHERE id = $1")
+            .bind(&role.id)
+            .bind(&role.name)
+            .bind(Json(&role))
+            .execute(&self.pool)
+            .await;
+
+        match result {
+            Ok(done) if done.rows_affected() == 0 => Err(AuthError::RoleNotFound),
+            Ok(_) => Ok(role),
+            Err(err) if is_unique_violation(&err) => Err(AuthError::RoleAlreadyExists),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AuthError> {
+        // System roles are refused in the same statement, so a concurrent update cannot race it
+        let done = sqlx::query(
+            "DELETE FROM carbon_roles WHERE id = $1 AND NOT (data->>'is_system_role')::boolean",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if done.rows_affected() > 0 {
+            return Ok(());
+        }
+        match self.find_by_id(id).await? {
+            Some(_) => Err(AuthError::CannotDeleteSystemRole),
+            None => Err(AuthError::RoleNotFound),
+        }
+    }
+
+    async fn name_exists(&self, name: &str) -> Result<bool, AuthError> {
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT EXISTS (SELECT 1 FROM carbon_roles WHERE name = $1)")
+                .bind(name)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(exists)
+    }
+}
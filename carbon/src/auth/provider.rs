@@ -0,0 +1,26 @@
+use super::error::AuthError;
+use async_trait::async_trait;
+
+/// A user verified by an external identity provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalIdentity {
+    pub username: String,
+    /// Carbon role names granted through the provider's group mapping
+    pub roles: Vec<String>,
+}
+
+/// Source of identities AuthService consults before local password hashes
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// Name used in logs and as the prefix of the session user's id
+    fn name(&self) -> &'static str;
+
+    /// Verify `username` and `password`, None when the provider does not accept them
+    /// so the next provider or the local accounts can be tried
+    /// Errors are for an unreachable or misconfigured provider, not for bad credentials
+    async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<ExternalIdentity>, AuthError>;
+}
@@ -1,5 +1,5 @@
 use super::error::AuthError;
-use super::models::{Role, User};
+use super::models::{MfaSettings, Role, User};
 use async_trait::async_trait;
 
 #[async_trait]
@@ -19,6 +19,14 @@ pub trait UserRepository: Send + Sync {
     /// Update a user
     async fn update(&self, user: User) -> Result<User, AuthError>;
 
+    /// Update a user whose stored MFA settings are still `current`, false when another
+    /// sign-in changed them first, so a code or recovery code is only ever spent once
+    async fn update_mfa_if_unchanged(
+        &self,
+        user: User,
+        current: &MfaSettings,
+    ) -> Result<bool, AuthError>;
+
     /// Delete a user by ID
     async fn delete(&self, id: &str) -> Result<(), AuthError>;
 
@@ -1,6 +1,7 @@
 use super::error::AuthError;
 use super::models::{Permission, Role};
 use super::repository::RoleRepository;
+use crate::domain::Quota;
 use std::collections::HashSet;
 use std::sync::Arc;
 
@@ -78,6 +79,7 @@ impl RoleService {
     }
 
     /// Initialize default system roles if they don't exist
+    /// System roles stored by an older version are given the permissions added since
     pub async fn initialize_default_roles(&self) -> Result<Vec<Role>, AuthError> {
         use super::defaults::create_default_roles;
 
@@ -88,7 +90,11 @@ impl RoleService {
             // Check if role already exists
             if self.role_repo.name_exists(&role.name).await? {
                 // If exists, get it
-                let existing = self.get_role(&role.name).await?;
+                let mut existing = self.get_role(&role.name).await?;
+                if existing.is_system_role && !existing.permissions.is_superset(&role.permissions) {
+                    existing.permissions.extend(role.permissions);
+                    existing = self.role_repo.update(existing).await?;
+                }
                 created_roles.push(existing);
             } else {
                 // Create new role
@@ -100,6 +106,21 @@ impl RoleService {
         Ok(created_roles)
     }
 
+    /// Require members of a role to sign in with a second factor
+    /// Allowed for system roles too, enforcing MFA for admins is the common case
+    pub async fn set_requires_mfa(&self, role_id: &str, required: bool) -> Result<Role, AuthError> {
+        let mut role = self.get_role_by_id(role_id).await?;
+        role.requires_mfa = required;
+        self.role_repo.update(role).await
+    }
+
+    /// Set or clear the quota members are held to, allowed on system roles too
+    pub async fn set_quota(&self, role_id: &str, quota: Option<Quota>) -> Result<Role, AuthError> {
+        let mut role = self.get_role_by_id(role_id).await?;
+        role.quota = quota;
+        self.role_repo.update(role).await
+    }
+
     /// Get role by name, returning None if not found (helper method)
     pub async fn find_role_by_name(&self, name: &str) -> Result<Option<Role>, AuthError> {
         self.role_repo.find_by_name(name).await
@@ -188,6 +209,30 @@ mod tests {
         assert!(roles.iter().all(|r| r.is_system_role));
     }
 
+    #[tokio::test]
+    async fn test_initialize_upgrades_system_roles() {
+        let temp_dir = TempDir::new().unwrap();
+        let role_repo =
+            Arc::new(SledRoleRepository::new(temp_dir.path().join("roles.sled")).unwrap())
+                as Arc<dyn RoleRepository>;
+
+        // An admin role stored before the finer-grained permissions existed
+        let mut permissions = HashSet::new();
+        permissions.insert(Permission::AdminWrite);
+        role_repo
+            .create(Role::new("admin".to_string(), permissions, true))
+            .await
+            .unwrap();
+
+        let role_service = RoleService::new(role_repo);
+        role_service.initialize_default_roles().await.unwrap();
+
+        let admin = role_service.get_role("admin").await.unwrap();
+        assert!(admin.has_permission(&Permission::AdminWrite));
+        assert!(admin.has_permission(&Permission::CreateCache));
+        assert!(admin.has_permission(&Permission::ClusterAdmin));
+    }
+
     #[tokio::test]
     async fn test_cannot_update_system_role() {
         let temp_dir = TempDir::new().unwrap();
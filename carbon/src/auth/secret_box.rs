@@ -0,0 +1,211 @@
+use super::error::AuthError;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use std::path::Path;
+
+/// Bytes in an AES-256 key
+pub const KEY_LEN: usize = 32;
+
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM encryption for secrets kept in the auth stores
+pub struct SecretBox {
+    cipher: Aes256Gcm,
+    key: [u8; KEY_LEN],
+}
+
+impl SecretBox {
+    pub fn new(key: &[u8; KEY_LEN]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+            key: *key,
+        }
+    }
+
+    /// Key for `purpose` derived from this one, for secrets that must follow the auth key
+    /// without reusing it
+    pub fn derive_key(&self, purpose: &str) -> [u8; KEY_LEN] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(purpose.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Read the key at `path`, writing a random one there if the file does not exist yet
+    /// Nodes sharing an auth store must be given the same key file
+    pub fn load_or_create(path: &Path) -> Result<Self, AuthError> {
+        let key = match std::fs::read(path) {
+            Ok(key) => key,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut key = [0u8; KEY_LEN];
+                rand::rng().fill(&mut key);
+                write_private(path, &key)?;
+                key.to_vec()
+            }
+            Err(e) => return Err(AuthError::StorageError(e.to_string())),
+        };
+
+        let key: [u8; KEY_LEN] = key.try_into().map_err(|_| {
+            AuthError::EncryptionError(format!(
+                "{} must hold a {}-byte key",
+                path.display(),
+                KEY_LEN
+            ))
+        })?;
+        Ok(Self::new(&key))
+    }
+
+    /// Key given as base64, as in CARBON_AUTH_KEY
+    pub fn from_base64(key: &str) -> Result<Self, AuthError> {
+        let key: [u8; KEY_LEN] = STANDARD
+            .decode(key.trim())
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(|| {
+                AuthError::EncryptionError(format!("key must be {} bytes of base64", KEY_LEN))
+            })?;
+        Ok(Self::new(&key))
+    }
+
+    /// Encrypt `plaintext` under a fresh nonce, returned as the nonce followed by the ciphertext
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, AuthError> {
+        self.encrypt_bound(plaintext, &[])
+    }
+
+    /// Like `encrypt`, authenticating `context` along with the plaintext, so the value only
+    /// decrypts with the same context, e.g. the id of the record it belongs to
+    pub fn encrypt_bound(&self, plaintext: &[u8], context: &[u8]) -> Result<Vec<u8>, AuthError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rng().fill(&mut nonce);
+        let payload = Payload {
+            msg: plaintext,
+            aad: context,
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|e| AuthError::EncryptionError(e.to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt a value from `encrypt`, failing if it was sealed under another key or tampered with
+    pub fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>, AuthError> {
+        self.decrypt_bound(sealed, &[])
+    }
+
+    /// Decrypt a value from `encrypt_bound`, failing as well if it was sealed with another
+    /// context
+    pub fn decrypt_bound(&self, sealed: &[u8], context: &[u8]) -> Result<Vec<u8>, AuthError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(AuthError::EncryptionError(
+                "sealed value too short".to_string(),
+            ));
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: context,
+        };
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|e| AuthError::EncryptionError(e.to_string()))
+    }
+
+    /// `encrypt` as base64, for secrets kept inside JSON records
+    pub fn seal(&self, plaintext: &[u8]) -> Result<String, AuthError> {
+        Ok(STANDARD.encode(self.encrypt(plaintext)?))
+    }
+
+    /// Decrypt a value from `seal`
+    pub fn open(&self, sealed: &str) -> Result<Vec<u8>, AuthError> {
+        let sealed = STANDARD
+            .decode(sealed)
+            .map_err(|e| AuthError::EncryptionError(e.to_string()))?;
+        self.decrypt(&sealed)
+    }
+}
+
+/// Write `contents` readable by the owner only
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), AuthError> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| AuthError::StorageError(e.to_string()))?;
+    file.write_all(contents)
+        .map_err(|e| AuthError::StorageError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("auth.key");
+
+        let secrets = SecretBox::load_or_create(&path).unwrap();
+        let sealed = secrets.seal(b"totp secret").unwrap();
+        assert_ne!(sealed.as_bytes(), b"totp secret");
+
+        // The key written on first use is read back by the next start
+        let reopened = SecretBox::load_or_create(&path).unwrap();
+        assert_eq!(reopened.open(&sealed).unwrap(), b"totp secret");
+
+        // Another key cannot open it
+        let other = SecretBox::new(&[7u8; KEY_LEN]);
+        assert!(other.open(&sealed).is_err());
+    }
+
+    #[test]
+    fn test_from_base64() {
+        let sealed = SecretBox::new(&[7u8; KEY_LEN]).seal(b"secret").unwrap();
+        let secrets = SecretBox::from_base64(&STANDARD.encode([7u8; KEY_LEN])).unwrap();
+        assert_eq!(secrets.open(&sealed).unwrap(), b"secret");
+
+        assert!(SecretBox::from_base64("c2hvcnQ=").is_err());
+    }
+
+    #[test]
+    fn test_bound_values_need_their_context() {
+        let secrets = SecretBox::new(&[7u8; KEY_LEN]);
+        let sealed = secrets.encrypt_bound(b"record", b"user-1").unwrap();
+        assert_eq!(
+            secrets.decrypt_bound(&sealed, b"user-1").unwrap(),
+            b"record"
+        );
+        assert!(secrets.decrypt_bound(&sealed, b"user-2").is_err());
+        assert!(secrets.decrypt(&sealed).is_err());
+
+        // Values sealed without a context open with an empty one
+        let unbound = secrets.encrypt(b"record").unwrap();
+        assert_eq!(secrets.decrypt_bound(&unbound, b"").unwrap(), b"record");
+    }
+
+    #[test]
+    fn test_derived_keys() {
+        let secrets = SecretBox::new(&[7u8; KEY_LEN]);
+        let events = secrets.derive_key("events");
+        assert_eq!(events, SecretBox::new(&[7u8; KEY_LEN]).derive_key("events"));
+        assert_ne!(events, secrets.derive_key("other"));
+        assert_ne!(events, SecretBox::new(&[8u8; KEY_LEN]).derive_key("events"));
+        assert_ne!(events, [7u8; KEY_LEN]);
+    }
+}
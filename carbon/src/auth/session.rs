@@ -31,6 +31,8 @@ pub struct Session {
     pub last_accessed: u64,         // UTC timestamp in milliseconds
     pub last_accessed_utc: String,  // Human-readable UTC time (ISO 8601)
     pub client_ip: Option<String>,  // IP address of the client
+    pub ttl_ms: u64,                // Idle window each access renews
+    pub max_expires_at: u64,        // Absolute cap on expires_at
 }
 
 impl Session {
@@ -48,20 +50,30 @@ impl Session {
             last_accessed: now,
             last_accessed_utc: now_utc,
             client_ip,
+            ttl_ms,
+            max_expires_at: now + ttl_ms,
         }
     }
 
+    /// Let each access renew the session, for at most `max_lifetime_ms` after creation
+    pub fn with_max_lifetime_ms(mut self, max_lifetime_ms: u64) -> Self {
+        self.max_expires_at = self.created_at + max_lifetime_ms.max(self.ttl_ms);
+        self
+    }
+
     /// Check if this session has expired
     pub fn is_expired(&self) -> bool {
         let now = current_timestamp_ms();
         now >= self.expires_at
     }
 
-    /// Update the last_accessed timestamp to current time
+    /// Update the last_accessed timestamp to current time and slide expires_at up to its cap
     pub fn update_last_accessed(&mut self) {
         let now = current_timestamp_ms();
         self.last_accessed = now;
         self.last_accessed_utc = format_utc_time(now);
+        let renewed = (now + self.ttl_ms).min(self.max_expires_at);
+        self.expires_at = self.expires_at.max(renewed);
     }
 
     /// Get remaining time to live in milliseconds
@@ -76,6 +88,49 @@ impl Session {
     }
 }
 
+/// Long-lived token traded once for a new session, so clients can stay signed in without
+/// sending the password again
+#[derive(Debug, Clone)]
+pub struct RefreshToken {
+    pub token: SessionToken,
+    pub user: User,
+    pub session_token: SessionToken, // Session issued alongside, ended on refresh
+    pub expires_at: u64,             // UTC timestamp in milliseconds, kept through rotation
+}
+
+impl RefreshToken {
+    /// Create a refresh token for the session `session_token` of `user`
+    pub fn new(user: User, session_token: SessionToken, ttl_ms: u64) -> Self {
+        Self {
+            token: generate_session_token(),
+            user,
+            session_token,
+            expires_at: current_timestamp_ms() + ttl_ms,
+        }
+    }
+
+    /// Token replacing this one for `session`, expiring with it so rotation never keeps a
+    /// login going past the refresh TTL
+    pub fn rotated(&self, session: &Session) -> Self {
+        Self {
+            token: generate_session_token(),
+            user: session.user.clone(),
+            session_token: session.token.clone(),
+            expires_at: self.expires_at,
+        }
+    }
+
+    /// Check if this refresh token has expired
+    pub fn is_expired(&self) -> bool {
+        current_timestamp_ms() >= self.expires_at
+    }
+
+    /// Get remaining time to live in milliseconds
+    pub fn remaining_ttl_ms(&self) -> u64 {
+        self.expires_at.saturating_sub(current_timestamp_ms())
+    }
+}
+
 /// Generate a cryptographically secure random session token
 pub fn generate_session_token() -> SessionToken {
     use rand::Rng;
@@ -150,4 +205,29 @@ mod tests {
         let remaining = session.remaining_ttl_ms();
         assert!(remaining > 4000 && remaining <= 5000);
     }
+
+    #[test]
+    fn test_session_sliding_expiration() {
+        let user = User::new("testuser".to_string(), "hash".to_string(), vec![]);
+
+        // Without a max lifetime an access does not extend the session
+        let mut fixed = Session::new("fixed".to_string(), user.clone(), 5000, None);
+        fixed.created_at -= 2000;
+        fixed.expires_at -= 2000;
+        fixed.max_expires_at -= 2000;
+        fixed.update_last_accessed();
+        assert!(fixed.remaining_ttl_ms() <= 3000);
+
+        let mut sliding =
+            Session::new("sliding".to_string(), user, 5000, None).with_max_lifetime_ms(6000);
+        sliding.created_at -= 2000;
+        sliding.expires_at -= 2000;
+        sliding.max_expires_at -= 2000;
+        sliding.update_last_accessed();
+
+        // Renewed by the idle window but capped at 6s after creation
+        let remaining = sliding.remaining_ttl_ms();
+        assert!(remaining > 3000 && remaining <= 4000);
+        assert_eq!(sliding.expires_at, sliding.max_expires_at);
+    }
 }
\ No newline at end of file
@@ -1,8 +1,10 @@
 use super::models::User;
-use super::session::{Session, SessionToken};
+use super::session::{RefreshToken, Session, SessionToken};
 use async_trait::async_trait;
 use shared::Result;
+use std::future::Future;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Trait for session storage operations
 #[async_trait]
@@ -13,7 +15,10 @@ pub trait SessionRepository: Send + Sync {
     /// Get a session by token
     async fn get_session(&self, token: &SessionToken) -> Result<User>;
 
-    /// Delete a session (logout)
+    /// Get a session by token, renewing its expiry as an access
+    async fn touch_session(&self, token: &SessionToken) -> Result<Session>;
+
+    /// Delete a session (logout), along with the refresh tokens issued with it
     async fn delete_session(&self, token: &SessionToken) -> Result<bool>;
 
     /// Check if a session exists
@@ -30,28 +35,110 @@ pub trait SessionRepository: Send + Sync {
     /// Delete all sessions for a user (for password change, logout all)
     async fn delete_user_sessions(&self, username: &str) -> Result<usize>;
 
-    /// Get existing valid session for username without password verification
+    /// Get existing valid session for username
     /// Returns most recently accessed session if one exists, None otherwise
-    /// This is used for fast-path authentication when session already exists
+    /// Only for users whose credentials were just verified, it does not check them
     async fn get_existing_user_session(&self, username: &str) -> Result<Option<Session>>;
 
     /// Update session's last_accessed timestamp
     async fn update_session(&self, session: &Session) -> Result<()>;
+
+    /// Issue a refresh token for `session` with specified TTL
+    async fn create_refresh_token(&self, session: &Session, ttl_ms: u64) -> Result<RefreshToken>;
+
+    /// Store a refresh token made by rotating another
+    async fn insert_refresh_token(&self, refresh: &RefreshToken) -> Result<()>;
+
+    /// Remove a refresh token and return it, so each one is used only once
+    async fn take_refresh_token(&self, token: &SessionToken) -> Result<RefreshToken>;
+
+    /// Delete all refresh tokens of a user, leaving their sessions
+    async fn delete_user_refresh_tokens(&self, username: &str) -> Result<usize>;
 }
 
+/// Default lifetime of new sessions (1 hour)
+pub const DEFAULT_SESSION_TTL_MS: u64 = 3_600_000;
+
+/// Default cap on how long activity can keep a session alive (24 hours)
+pub const DEFAULT_SESSION_MAX_LIFETIME_MS: u64 = 86_400_000;
+
+/// Default lifetime of refresh tokens (7 days)
+pub const DEFAULT_REFRESH_TTL_MS: u64 = 604_800_000;
+
 /// Session store service
 pub struct SessionStore<S: SessionRepository> {
     repository: Arc<S>,
+    ttl_ms: AtomicU64,
+    max_lifetime_ms: AtomicU64,
+    refresh_ttl_ms: AtomicU64,
 }
 
 impl<S: SessionRepository> SessionStore<S> {
     pub fn new(repository: Arc<S>) -> Self {
-        Self { repository }
+        Self {
+            repository,
+            ttl_ms: AtomicU64::new(DEFAULT_SESSION_TTL_MS),
+            max_lifetime_ms: AtomicU64::new(DEFAULT_SESSION_MAX_LIFETIME_MS),
+            refresh_ttl_ms: AtomicU64::new(DEFAULT_REFRESH_TTL_MS),
+        }
     }
 
-    /// Create a new session for a user with optional client IP
+    pub fn with_ttl_ms(self, ttl_ms: u64) -> Self {
+        self.set_ttl_ms(ttl_ms);
+        self
+    }
+
+    pub fn with_max_lifetime_ms(self, max_lifetime_ms: u64) -> Self {
+        self.set_max_lifetime_ms(max_lifetime_ms);
+        self
+    }
+
+    pub fn with_refresh_ttl_ms(self, refresh_ttl_ms: u64) -> Self {
+        self.set_refresh_ttl_ms(refresh_ttl_ms);
+        self
+    }
+
+    /// Lifetime given to new sessions
+    pub fn ttl_ms(&self) -> u64 {
+        self.ttl_ms.load(Ordering::Relaxed)
+    }
+
+    /// Change the lifetime of sessions created from now on, existing sessions keep theirs
+    pub fn set_ttl_ms(&self, ttl_ms: u64) {
+        self.ttl_ms.store(ttl_ms, Ordering::Relaxed);
+    }
+
+    /// How long after creation accesses can keep a session alive
+    pub fn max_lifetime_ms(&self) -> u64 {
+        self.max_lifetime_ms.load(Ordering::Relaxed)
+    }
+
+    /// Change the cap for sessions created from now on, existing sessions keep theirs
+    pub fn set_max_lifetime_ms(&self, max_lifetime_ms: u64) {
+        self.max_lifetime_ms
+            .store(max_lifetime_ms, Ordering::Relaxed);
+    }
+
+    /// Lifetime given to new refresh tokens
+    pub fn refresh_ttl_ms(&self) -> u64 {
+        self.refresh_ttl_ms.load(Ordering::Relaxed)
+    }
+
+    /// Change the lifetime of refresh tokens issued from now on
+    pub fn set_refresh_ttl_ms(&self, refresh_ttl_ms: u64) {
+        self.refresh_ttl_ms.store(refresh_ttl_ms, Ordering::Relaxed);
+    }
+
+    /// Create a new session for a user with optional client IP, renewed on access until the
+    /// max lifetime
     pub async fn create_session(&self, user: User, ttl_ms: u64, client_ip: Option<String>) -> Result<Session> {
-        self.repository.create_session(user, ttl_ms, client_ip).await
+        let session = self
+            .repository
+            .create_session(user, ttl_ms, client_ip)
+            .await?
+            .with_max_lifetime_ms(self.max_lifetime_ms());
+        self.repository.update_session(&session).await?;
+        Ok(session)
     }
 
     /// Validate a session token and return the associated user
@@ -59,6 +146,11 @@ impl<S: SessionRepository> SessionStore<S> {
         self.repository.get_session(token).await
     }
 
+    /// Validate a session token and return the session with its renewed expiry
+    pub async fn touch_session(&self, token: &SessionToken) -> Result<Session> {
+        self.repository.touch_session(token).await
+    }
+
     /// Invalidate a session (logout)
     pub async fn invalidate_session(&self, token: &SessionToken) -> Result<bool> {
         self.repository.delete_session(token).await
@@ -86,7 +178,7 @@ impl<S: SessionRepository> SessionStore<S> {
         self.repository.delete_user_sessions(username).await
     }
 
-    /// Get existing valid session for username (fast path - no password verification)
+    /// Get existing valid session for username, once their credentials were verified
     /// Returns most recently accessed session if one exists
     pub async fn get_existing_user_session(&self, username: &str) -> Result<Option<Session>> {
         self.repository.get_existing_user_session(username).await
@@ -96,4 +188,35 @@ impl<S: SessionRepository> SessionStore<S> {
     pub async fn update_session(&self, session: &Session) -> Result<()> {
         self.repository.update_session(session).await
     }
-}
\ No newline at end of file
+
+    /// Issue a refresh token for `session`
+    pub async fn create_refresh_token(&self, session: &Session) -> Result<RefreshToken> {
+        self.repository
+            .create_refresh_token(session, self.refresh_ttl_ms())
+            .await
+    }
+
+    /// Trade a refresh token for a new session and refresh token (rotation), ending the
+    /// session it was issued with
+    /// `reload` gets the user the token was issued to and returns them as they are now, or fails
+    /// the refresh
+    pub async fn refresh_session<F, Fut>(
+        &self,
+        refresh_token: &SessionToken,
+        client_ip: Option<String>,
+        reload: F,
+    ) -> Result<(Session, RefreshToken)>
+    where
+        F: FnOnce(User) -> Fut,
+        Fut: Future<Output = Result<User>>,
+    {
+        let refresh = self.repository.take_refresh_token(refresh_token).await?;
+        self.repository.delete_session(&refresh.session_token).await?;
+
+        let user = reload(refresh.user.clone()).await?;
+        let session = self.create_session(user, self.ttl_ms(), client_ip).await?;
+        let refresh = refresh.rotated(&session);
+        self.repository.insert_refresh_token(&refresh).await?;
+        Ok((session, refresh))
+    }
+}
@@ -1,24 +1,117 @@
 use super::error::AuthError;
-use super::models::{Role, User};
+use super::models::{MfaSettings, Role, User};
 use super::repository::{RoleRepository, UserRepository};
+use super::secret_box::SecretBox;
 use async_trait::async_trait;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use sled::Db;
 use std::path::Path;
+use std::sync::Arc;
 
 const USERS_TREE: &str = "users";
 const USERS_BY_USERNAME_TREE: &str = "users_by_username";
 const ROLES_TREE: &str = "roles";
 const ROLES_BY_NAME_TREE: &str = "roles_by_name";
 
+/// Leads records sealed with the store's key and bound to their id, plaintext records are
+/// JSON objects
+const SEALED_PREFIX: &[u8] = b"\0sealed2";
+
+/// Leads records sealed before they were bound to their id, `seal_existing` rewrites them
+const UNBOUND_SEALED_PREFIX: &[u8] = b"\0sealed1";
+
+/// How records are written to and read from the trees, sealed when the store has a key
+/// A sealed record authenticates the id it is stored under, so it cannot be moved to another
+/// id, and a store with a key refuses records that are not sealed that way; `seal_existing`
+/// rewrites those left from before. The lookup indexes keep names in the clear
+#[derive(Clone, Default)]
+struct RecordCodec {
+    secrets: Option<Arc<SecretBox>>,
+}
+
+impl RecordCodec {
+    fn encode<T: Serialize>(&self, id: &[u8], record: &T) -> Result<Vec<u8>, AuthError> {
+        let json = serde_json::to_vec(record)?;
+        match &self.secrets {
+            Some(secrets) => Ok(seal(secrets, id, &json)?),
+            None => Ok(json),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, id: &[u8], data: &[u8]) -> Result<T, AuthError> {
+        let Some(secrets) = &self.secrets else {
+            if data.starts_with(SEALED_PREFIX) || data.starts_with(UNBOUND_SEALED_PREFIX) {
+                return Err(AuthError::EncryptionError(
+                    "record is encrypted but no key was given".to_string(),
+                ));
+            }
+            return Ok(serde_json::from_slice(data)?);
+        };
+        let sealed = data.strip_prefix(SEALED_PREFIX).ok_or_else(|| {
+            AuthError::EncryptionError("record is not encrypted with the store's key".to_string())
+        })?;
+        Ok(serde_json::from_slice(&secrets.decrypt_bound(sealed, id)?)?)
+    }
+
+    /// Seal the plaintext records of `tree` and bind the ones sealed without their id to it,
+    /// returns how many were rewritten
+    fn seal_existing(&self, tree: &sled::Tree) -> Result<usize, AuthError> {
+        let Some(secrets) = &self.secrets else {
+            return Ok(0);
+        };
+
+        let mut sealed = 0;
+        for item in tree.iter() {
+            let (key, data) = item?;
+            if data.starts_with(SEALED_PREFIX) {
+                continue;
+            }
+            let json = match data.strip_prefix(UNBOUND_SEALED_PREFIX) {
+                Some(unbound) => secrets.decrypt(unbound)?,
+                None => data.to_vec(),
+            };
+            let record = seal(secrets, &key, &json)?;
+            // Left for the next start if the record changed in the meantime
+            if tree
+                .compare_and_swap(&key, Some(data), Some(record))?
+                .is_ok()
+            {
+                sealed += 1;
+            }
+        }
+        Ok(sealed)
+    }
+}
+
+fn seal(secrets: &SecretBox, id: &[u8], json: &[u8]) -> Result<Vec<u8>, AuthError> {
+    Ok([SEALED_PREFIX, secrets.encrypt_bound(json, id)?.as_slice()].concat())
+}
+
 #[derive(Clone)]
 pub struct SledUserRepository {
     db: Db,
+    records: RecordCodec,
 }
 
 impl SledUserRepository {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, AuthError> {
         let db = sled::open(path)?;
-        Ok(Self { db })
+        Ok(Self {
+            db,
+            records: RecordCodec::default(),
+        })
+    }
+
+    /// Encrypt user records with `secrets`, see `seal_existing` for stores written without it
+    pub fn with_secret_box(mut self, secrets: Arc<SecretBox>) -> Self {
+        self.records.secrets = Some(secrets);
+        self
+    }
+
+    /// Encrypt user records still stored in plaintext, returns how many were rewritten
+    pub fn seal_existing(&self) -> Result<usize, AuthError> {
+        self.records.seal_existing(&self.users_tree()?)
     }
 
     fn users_tree(&self) -> Result<sled::Tree, AuthError> {
@@ -40,7 +133,7 @@ impl UserRepository for SledUserRepository {
         let users_tree = self.users_tree()?;
         let username_tree = self.users_by_username_tree()?;
 
-        let user_json = serde_json::to_vec(&user)?;
+        let user_json = self.records.encode(user.id.as_bytes(), &user)?;
 
         // Store user by ID
         users_tree.insert(user.id.as_bytes(), user_json.clone())?;
@@ -59,7 +152,7 @@ impl UserRepository for SledUserRepository {
         if let Some(user_id) = username_tree.get(username.as_bytes())? {
             // Then get the user by ID
             if let Some(user_data) = users_tree.get(&user_id)? {
-                let user: User = serde_json::from_slice(&user_data)?;
+                let user: User = self.records.decode(&user_id, &user_data)?;
                 return Ok(Some(user));
             }
         }
@@ -71,7 +164,7 @@ impl UserRepository for SledUserRepository {
         let users_tree = self.users_tree()?;
 
         if let Some(user_data) = users_tree.get(id.as_bytes())? {
-            let user: User = serde_json::from_slice(&user_data)?;
+            let user: User = self.records.decode(id.as_bytes(), &user_data)?;
             return Ok(Some(user));
         }
 
@@ -83,8 +176,8 @@ impl UserRepository for SledUserRepository {
         let mut users = Vec::new();
 
         for item in users_tree.iter() {
-            let (_, user_data) = item?;
-            let user: User = serde_json::from_slice(&user_data)?;
+            let (id, user_data) = item?;
+            let user: User = self.records.decode(&id, &user_data)?;
             users.push(user);
         }
 
@@ -100,7 +193,7 @@ impl UserRepository for SledUserRepository {
             return Err(AuthError::UserNotFound);
         }
 
-        let user_json = serde_json::to_vec(&user)?;
+        let user_json = self.records.encode(user.id.as_bytes(), &user)?;
 
         // Update user by ID
         users_tree.insert(user.id.as_bytes(), user_json)?;
@@ -111,13 +204,34 @@ impl UserRepository for SledUserRepository {
         Ok(user)
     }
 
+    async fn update_mfa_if_unchanged(
+        &self,
+        user: User,
+        current: &MfaSettings,
+    ) -> Result<bool, AuthError> {
+        let users_tree = self.users_tree()?;
+        let stored_data = users_tree
+            .get(user.id.as_bytes())?
+            .ok_or(AuthError::UserNotFound)?;
+        let stored: User = self.records.decode(user.id.as_bytes(), &stored_data)?;
+        if serde_json::to_value(&stored.mfa)? != serde_json::to_value(Some(current))? {
+            return Ok(false);
+        }
+
+        // Swapped against the record read above, a write in between leaves it in place
+        let user_json = self.records.encode(user.id.as_bytes(), &user)?;
+        let swapped =
+            users_tree.compare_and_swap(user.id.as_bytes(), Some(stored_data), Some(user_json))?;
+        Ok(swapped.is_ok())
+    }
+
     async fn delete(&self, id: &str) -> Result<(), AuthError> {
         let users_tree = self.users_tree()?;
         let username_tree = self.users_by_username_tree()?;
 
         // Get user to find username
         if let Some(user_data) = users_tree.get(id.as_bytes())? {
-            let user: User = serde_json::from_slice(&user_data)?;
+            let user: User = self.records.decode(id.as_bytes(), &user_data)?;
 
             // Remove from username index
             username_tree.remove(user.username.as_bytes())?;
@@ -140,12 +254,27 @@ impl UserRepository for SledUserRepository {
 #[derive(Clone)]
 pub struct SledRoleRepository {
     db: Db,
+    records: RecordCodec,
 }
 
 impl SledRoleRepository {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, AuthError> {
         let db = sled::open(path)?;
-        Ok(Self { db })
+        Ok(Self {
+            db,
+            records: RecordCodec::default(),
+        })
+    }
+
+    /// Encrypt role records with `secrets`, see `seal_existing` for stores written without it
+    pub fn with_secret_box(mut self, secrets: Arc<SecretBox>) -> Self {
+        self.records.secrets = Some(secrets);
+        self
+    }
+
+    /// Encrypt role records still stored in plaintext, returns how many were rewritten
+    pub fn seal_existing(&self) -> Result<usize, AuthError> {
+        self.records.seal_existing(&self.roles_tree()?)
     }
 
     fn roles_tree(&self) -> Result<sled::Tree, AuthError> {
@@ -167,7 +296,7 @@ impl RoleRepository for SledRoleRepository {
         let roles_tree = self.roles_tree()?;
         let name_tree = self.roles_by_name_tree()?;
 
-        let role_json = serde_json::to_vec(&role)?;
+        let role_json = self.records.encode(role.id.as_bytes(), &role)?;
 
         // Store role by ID
         roles_tree.insert(role.id.as_bytes(), role_json)?;
@@ -186,7 +315,7 @@ impl RoleRepository for SledRoleRepository {
         if let Some(role_id) = name_tree.get(name.as_bytes())? {
             // Then get the role by ID
             if let Some(role_data) = roles_tree.get(&role_id)? {
-                let role: Role = serde_json::from_slice(&role_data)?;
+                let role: Role = self.records.decode(&role_id, &role_data)?;
                 return Ok(Some(role));
             }
         }
@@ -198,7 +327,7 @@ impl RoleRepository for SledRoleRepository {
         let roles_tree = self.roles_tree()?;
 
         if let Some(role_data) = roles_tree.get(id.as_bytes())? {
-            let role: Role = serde_json::from_slice(&role_data)?;
+            let role: Role = self.records.decode(id.as_bytes(), &role_data)?;
             return Ok(Some(role));
         }
 
@@ -211,7 +340,7 @@ impl RoleRepository for SledRoleRepository {
 
         for id in ids {
             if let Some(role_data) = roles_tree.get(id.as_bytes())? {
-                let role: Role = serde_json::from_slice(&role_data)?;
+                let role: Role = self.records.decode(id.as_bytes(), &role_data)?;
                 roles.push(role);
             }
         }
@@ -224,8 +353,8 @@ impl RoleRepository for SledRoleRepository {
         let mut roles = Vec::new();
 
         for item in roles_tree.iter() {
-            let (_, role_data) = item?;
-            let role: Role = serde_json::from_slice(&role_data)?;
+            let (id, role_data) = item?;
+            let role: Role = self.records.decode(&id, &role_data)?;
             roles.push(role);
         }
 
@@ -241,7 +370,7 @@ impl RoleRepository for SledRoleRepository {
             return Err(AuthError::RoleNotFound);
         }
 
-        let role_json = serde_json::to_vec(&role)?;
+        let role_json = self.records.encode(role.id.as_bytes(), &role)?;
 
         // Update role by ID
         roles_tree.insert(role.id.as_bytes(), role_json)?;
@@ -258,7 +387,7 @@ impl RoleRepository for SledRoleRepository {
 
         // Get role to find name and check if system role
         if let Some(role_data) = roles_tree.get(id.as_bytes())? {
-            let role: Role = serde_json::from_slice(&role_data)?;
+            let role: Role = self.records.decode(id.as_bytes(), &role_data)?;
 
             if role.is_system_role {
                 return Err(AuthError::CannotDeleteSystemRole);
@@ -356,4 +485,85 @@ mod tests {
         let not_found = repo.find_by_name("admin").await.unwrap();
         assert!(not_found.is_none());
     }
+
+    #[tokio::test]
+    async fn test_sealed_user_records() {
+        let temp_dir = TempDir::new().unwrap();
+        let plain = SledUserRepository::new(temp_dir.path().join("users.sled")).unwrap();
+        let secrets = Arc::new(SecretBox::new(&[3u8; 32]));
+
+        // Written before encryption was turned on
+        let legacy = plain
+            .create(User::new(
+                "legacy".to_string(),
+                "hash-a".to_string(),
+                Vec::new(),
+            ))
+            .await
+            .unwrap();
+
+        let repo = plain.clone().with_secret_box(secrets.clone());
+        // Plaintext is refused once there is a key, until it is sealed
+        assert!(matches!(
+            repo.find_by_id(&legacy.id).await,
+            Err(AuthError::EncryptionError(_))
+        ));
+        assert_eq!(repo.seal_existing().unwrap(), 1);
+        assert_eq!(repo.seal_existing().unwrap(), 0);
+        assert!(repo.find_by_id(&legacy.id).await.unwrap().is_some());
+
+        let created = repo
+            .create(User::new(
+                "new".to_string(),
+                "hash-b".to_string(),
+                Vec::new(),
+            ))
+            .await
+            .unwrap();
+        for id in [&legacy.id, &created.id] {
+            let raw = repo
+                .users_tree()
+                .unwrap()
+                .get(id.as_bytes())
+                .unwrap()
+                .unwrap();
+            assert!(raw.starts_with(SEALED_PREFIX));
+            assert!(!raw.windows(4).any(|window| window == b"hash"));
+        }
+        assert_eq!(repo.list_all().await.unwrap().len(), 2);
+
+        // Sealed records cannot be read without the key
+        assert!(matches!(
+            plain.find_by_id(&created.id).await,
+            Err(AuthError::EncryptionError(_))
+        ));
+
+        // Nor once moved under another user's id
+        let users = repo.users_tree().unwrap();
+        let moved = users.get(created.id.as_bytes()).unwrap().unwrap();
+        users.insert(legacy.id.as_bytes(), moved).unwrap();
+        assert!(matches!(
+            repo.find_by_id(&legacy.id).await,
+            Err(AuthError::EncryptionError(_))
+        ));
+
+        // Records sealed before they were bound to their id are bound at the next start
+        let json = serde_json::to_vec(&created).unwrap();
+        let unbound = [
+            UNBOUND_SEALED_PREFIX,
+            secrets.encrypt(&json).unwrap().as_slice(),
+        ]
+        .concat();
+        users.insert(created.id.as_bytes(), unbound).unwrap();
+        assert!(repo.find_by_id(&created.id).await.is_err());
+        assert_eq!(repo.seal_existing().unwrap(), 1);
+        assert_eq!(
+            repo.find_by_id(&created.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .username,
+            "new"
+        );
+    }
 }
@@ -1,13 +1,20 @@
+use super::api_key::generate_api_key;
 use super::error::AuthError;
-use super::models::User;
+use super::models::{ApiKey, Permission, User};
 use super::password::{hash_password, verify_password};
 use super::repository::{RoleRepository, UserRepository};
+use super::session_store::SessionRepository;
+use crate::domain::Quota;
+use argon2::PasswordHash;
 use chrono::Utc;
+use std::collections::HashSet;
 use std::sync::Arc;
+use uuid::Uuid;
 
 pub struct UserService {
     user_repo: Arc<dyn UserRepository>,
     role_repo: Arc<dyn RoleRepository>,
+    sessions: Option<Arc<dyn SessionRepository>>,
 }
 
 impl UserService {
@@ -15,9 +22,17 @@ impl UserService {
         Self {
             user_repo,
             role_repo,
+            sessions: None,
         }
     }
 
+    /// End a user's sessions when their password changes or they are deleted, and their refresh
+    /// tokens when their roles change
+    pub fn with_sessions(mut self, sessions: Arc<dyn SessionRepository>) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
     /// Create a new user
     pub async fn create_user(
         &self,
@@ -42,6 +57,84 @@ impl UserService {
         self.user_repo.create(user).await
     }
 
+    /// Create a user from an Argon2 hash of their password, as exported from another server
+    pub async fn create_user_with_hash(
+        &self,
+        username: String,
+        password_hash: String,
+        role_ids: Vec<String>,
+    ) -> Result<User, AuthError> {
+        if self.user_repo.username_exists(&username).await? {
+            return Err(AuthError::UserAlreadyExists);
+        }
+        self.validate_role_ids(&role_ids).await?;
+        PasswordHash::new(&password_hash)
+            .map_err(|e| AuthError::PasswordHashError(e.to_string()))?;
+
+        self.user_repo
+            .create(User::new(username, password_hash, role_ids))
+            .await
+    }
+
+    /// Create a service account, which signs in with API keys only
+    pub async fn create_service_account(
+        &self,
+        username: String,
+        role_ids: Vec<String>,
+    ) -> Result<User, AuthError> {
+        if self.user_repo.username_exists(&username).await? {
+            return Err(AuthError::UserAlreadyExists);
+        }
+        self.validate_role_ids(&role_ids).await?;
+
+        self.user_repo
+            .create(User::service_account(username, role_ids))
+            .await
+    }
+
+    /// Issue an API key to a service account, returns the key itself, which is not stored
+    pub async fn create_api_key(
+        &self,
+        username: &str,
+        name: String,
+        caches: Vec<String>,
+        permissions: HashSet<Permission>,
+    ) -> Result<(String, ApiKey), AuthError> {
+        let mut user = self.get_user(username).await?;
+        if !user.is_service_account() {
+            return Err(AuthError::NotServiceAccount);
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let (key, secret_hash) = generate_api_key(&user.username, &id);
+        let api_key = ApiKey {
+            id,
+            name,
+            secret_hash,
+            caches,
+            permissions,
+            created_at: Utc::now(),
+        };
+        user.api_keys.push(api_key.clone());
+        user.updated_at = Utc::now();
+
+        self.user_repo.update(user).await?;
+        Ok((key, api_key))
+    }
+
+    /// Revoke one of a service account's API keys
+    pub async fn revoke_api_key(&self, username: &str, key_id: &str) -> Result<User, AuthError> {
+        let mut user = self.get_user(username).await?;
+        let count = user.api_keys.len();
+        user.api_keys.retain(|key| key.id != key_id);
+        if user.api_keys.len() == count {
+            return Err(AuthError::ApiKeyNotFound);
+        }
+        user.updated_at = Utc::now();
+
+        self.user_repo.update(user).await
+    }
+
     /// Get a user by username
     pub async fn get_user(&self, username: &str) -> Result<User, AuthError> {
         self.user_repo
@@ -79,7 +172,10 @@ impl UserService {
         user.role_ids = role_ids;
         user.updated_at = Utc::now();
 
-        self.user_repo.update(user).await
+        // Sessions hold the roles they were signed in with
+        let user = self.user_repo.update(user).await?;
+        self.end_sessions(&user.username).await?;
+        Ok(user)
     }
 
     /// Change user's password
@@ -105,7 +201,9 @@ impl UserService {
         user.password_hash = new_hash;
         user.updated_at = Utc::now();
 
-        self.user_repo.update(user).await
+        let user = self.user_repo.update(user).await?;
+        self.end_sessions(&user.username).await?;
+        Ok(user)
     }
 
     /// Reset user's password (admin operation, no old password required)
@@ -124,6 +222,36 @@ impl UserService {
         user.password_hash = new_hash;
         user.updated_at = Utc::now();
 
+        let user = self.user_repo.update(user).await?;
+        self.end_sessions(&user.username).await?;
+        Ok(user)
+    }
+
+    /// Require a user to sign in with a second factor (admin operation)
+    pub async fn set_mfa_required(&self, user_id: &str, required: bool) -> Result<User, AuthError> {
+        let mut user = self.get_user_by_id(user_id).await?;
+        user.mfa_required = required;
+        user.updated_at = Utc::now();
+
+        self.user_repo.update(user).await
+    }
+
+    /// Set or clear the user's own quota, which takes the place of their roles' quotas
+    pub async fn set_quota(&self, user_id: &str, quota: Option<Quota>) -> Result<User, AuthError> {
+        let mut user = self.get_user_by_id(user_id).await?;
+        user.quota = quota;
+        user.updated_at = Utc::now();
+
+        self.user_repo.update(user).await
+    }
+
+    /// Forget a user's TOTP enrollment and recovery codes, so they can enroll a new device
+    /// (admin operation)
+    pub async fn reset_mfa(&self, user_id: &str) -> Result<User, AuthError> {
+        let mut user = self.get_user_by_id(user_id).await?;
+        user.mfa = None;
+        user.updated_at = Utc::now();
+
         self.user_repo.update(user).await
     }
 
@@ -138,7 +266,20 @@ impl UserService {
             return Err(AuthError::CannotDeleteSelf);
         }
 
-        self.user_repo.delete(user_id).await
+        let user = self.get_user_by_id(user_id).await?;
+        self.user_repo.delete(user_id).await?;
+        self.end_sessions(&user.username).await
+    }
+
+    /// Invalidate all sessions and refresh tokens of `username`
+    async fn end_sessions(&self, username: &str) -> Result<(), AuthError> {
+        if let Some(sessions) = &self.sessions {
+            sessions
+                .delete_user_sessions(username)
+                .await
+                .map_err(|e| AuthError::StorageError(e.to_string()))?;
+        }
+        Ok(())
     }
 
     /// Validate that all role IDs exist
@@ -160,7 +301,8 @@ impl UserService {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::auth::defaults::create_user_role;
+    use crate::auth::defaults::{create_admin_role, create_user_role};
+    use crate::auth::moka_session_repository::MokaSessionRepository;
     use crate::auth::sled_repository::{SledRoleRepository, SledUserRepository};
     use tempfile::TempDir;
 
@@ -232,6 +374,127 @@ mod tests {
         assert!(verify_password("newpass123", &updated_user.password_hash).unwrap());
     }
 
+    #[tokio::test]
+    async fn test_api_keys_belong_to_service_accounts() {
+        let temp_dir = TempDir::new().unwrap();
+        let user_repo =
+            Arc::new(SledUserRepository::new(temp_dir.path().join("users.sled")).unwrap())
+                as Arc<dyn UserRepository>;
+        let role_repo =
+            Arc::new(SledRoleRepository::new(temp_dir.path().join("roles.sled")).unwrap())
+                as Arc<dyn RoleRepository>;
+
+        let role = create_user_role();
+        let created_role = role_repo.create(role).await.unwrap();
+
+        let user_service = UserService::new(user_repo, role_repo);
+        user_service
+            .create_user(
+                "alice".to_string(),
+                "alicepass123".to_string(),
+                vec![created_role.id.clone()],
+            )
+            .await
+            .unwrap();
+        user_service
+            .create_service_account("ci".to_string(), vec![created_role.id])
+            .await
+            .unwrap();
+
+        let permissions = HashSet::from([Permission::ReadCache]);
+        assert!(matches!(
+            user_service
+                .create_api_key("alice", "key".to_string(), vec![], permissions.clone())
+                .await,
+            Err(AuthError::NotServiceAccount)
+        ));
+
+        let (key, api_key) = user_service
+            .create_api_key("ci", "deploy".to_string(), vec![], permissions)
+            .await
+            .unwrap();
+        assert!(key.contains(&api_key.id));
+        assert_eq!(user_service.get_user("ci").await.unwrap().api_keys.len(), 1);
+
+        let ci = user_service
+            .revoke_api_key("ci", &api_key.id)
+            .await
+            .unwrap();
+        assert!(ci.api_keys.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_password_change_ends_sessions() {
+        let temp_dir = TempDir::new().unwrap();
+        let user_repo =
+            Arc::new(SledUserRepository::new(temp_dir.path().join("users.sled")).unwrap())
+                as Arc<dyn UserRepository>;
+        let role_repo =
+            Arc::new(SledRoleRepository::new(temp_dir.path().join("roles.sled")).unwrap())
+                as Arc<dyn RoleRepository>;
+        let sessions = Arc::new(MokaSessionRepository::with_defaults());
+
+        let role = create_user_role();
+        let created_role = role_repo.create(role).await.unwrap();
+
+        let user_service = UserService::new(user_repo, role_repo).with_sessions(sessions.clone());
+        let user = user_service
+            .create_user(
+                "testuser".to_string(),
+                "oldpass123".to_string(),
+                vec![created_role.id],
+            )
+            .await
+            .unwrap();
+
+        let session = sessions
+            .create_session(user.clone(), 3600000, None)
+            .await
+            .unwrap();
+        user_service
+            .reset_password(&user.id, "newpass123".to_string())
+            .await
+            .unwrap();
+
+        assert!(!sessions.session_exists(&session.token).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_role_change_ends_sessions() {
+        let temp_dir = TempDir::new().unwrap();
+        let user_repo =
+            Arc::new(SledUserRepository::new(temp_dir.path().join("users.sled")).unwrap())
+                as Arc<dyn UserRepository>;
+        let role_repo =
+            Arc::new(SledRoleRepository::new(temp_dir.path().join("roles.sled")).unwrap())
+                as Arc<dyn RoleRepository>;
+        let sessions = Arc::new(MokaSessionRepository::with_defaults());
+
+        let user_role = role_repo.create(create_user_role()).await.unwrap();
+        let admin_role = role_repo.create(create_admin_role()).await.unwrap();
+
+        let user_service = UserService::new(user_repo, role_repo).with_sessions(sessions.clone());
+        let user = user_service
+            .create_user(
+                "testuser".to_string(),
+                "testpass123".to_string(),
+                vec![admin_role.id],
+            )
+            .await
+            .unwrap();
+
+        let session = sessions
+            .create_session(user.clone(), 3600000, None)
+            .await
+            .unwrap();
+        user_service
+            .assign_roles(&user.id, vec![user_role.id])
+            .await
+            .unwrap();
+
+        assert!(!sessions.session_exists(&session.token).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_cannot_delete_self() {
         let temp_dir = TempDir::new().unwrap();
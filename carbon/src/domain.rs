@@ -1,11 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub mod request {}
 
 pub mod response {
 
     pub mod admin {
-        use crate::domain::CacheInfo;
+        use crate::domain::{CacheInfo, FailedCache};
         use serde::Serialize;
 
         #[derive(Clone, Debug, Serialize)]
@@ -26,22 +26,44 @@ pub mod response {
         #[derive(Clone, Debug, Serialize)]
         pub struct DropCacheResponse {
             pub dropped: bool,
+            /// Until when the cache can be restored, None when it was purged right away
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub restorable_until_ms: Option<u64>,
         }
 
         impl DropCacheResponse {
             pub fn new(dropped: bool) -> Self {
-                Self { dropped }
+                Self {
+                    dropped,
+                    restorable_until_ms: None,
+                }
+            }
+
+            pub fn with_restorable_until(mut self, restorable_until_ms: u64) -> Self {
+                self.restorable_until_ms = Some(restorable_until_ms);
+                self
             }
         }
 
         #[derive(Clone, Debug, Serialize)]
         pub struct ListCachesResponse {
             pub caches: Vec<CacheInfo>,
+            /// Stored caches that could not be recreated, by name
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            pub failed: Vec<FailedCache>,
         }
 
         impl ListCachesResponse {
             pub fn new(caches: Vec<CacheInfo>) -> Self {
-                Self { caches }
+                Self {
+                    caches,
+                    failed: Vec::new(),
+                }
+            }
+
+            pub fn with_failed(mut self, failed: Vec<FailedCache>) -> Self {
+                self.failed = failed;
+                self
             }
         }
 
@@ -55,12 +77,30 @@ pub mod response {
                 Self { info }
             }
         }
+
+        #[derive(Clone, Debug, Serialize)]
+        pub struct UpdateCacheResponse {
+            pub info: CacheInfo,
+            /// Settings saved but only applied once the server restarts and rebuilds the cache
+            pub requires_restart: Vec<&'static str>,
+        }
+
+        impl UpdateCacheResponse {
+            pub fn new(info: CacheInfo, requires_restart: Vec<&'static str>) -> Self {
+                Self {
+                    info,
+                    requires_restart,
+                }
+            }
+        }
     }
 
     #[derive(Clone, Debug)]
     pub struct PutResponse {
         pub created: bool,
         pub message: String,
+        /// False when the store's admission policy turned the write away
+        pub admitted: bool,
     }
 
     impl PutResponse {
@@ -68,10 +108,27 @@ pub mod response {
             Self {
                 created,
                 message: message.into(),
+                admitted: true,
+            }
+        }
+
+        /// A write the store declined to keep, the key is left as it was
+        pub fn not_admitted() -> Self {
+            Self {
+                created: false,
+                message: "Not admitted by the cache's admission policy".to_string(),
+                admitted: false,
             }
         }
     }
 
+    /// Outcome of a bulk load, `not_admitted` counts records the admission policy turned away
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct BulkLoadResponse {
+        pub loaded: u64,
+        pub not_admitted: u64,
+    }
+
     #[derive(Clone, Debug)]
     pub struct GetResponse<V> {
         pub found: bool,
@@ -112,16 +169,76 @@ pub struct CacheInfo {
     pub config: CacheConfig,
     pub keys_estimate: u64,
     pub size_estimate: u64,
+    /// Algorithm the backend actually evicts with, which may differ from `config.policy`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_policy: Option<EvictionAlgorithm>,
+    /// Expired entries removed by the background reaper, None when the cache has none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reaped_entries: Option<u64>,
+    /// Writes turned away by the admission policy, None when the cache has none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admission_rejected: Option<u64>,
+    pub status: CacheStatus,
 }
 
 impl CacheInfo {
     pub fn from_config(config: &CacheConfig) -> Self {
         Self {
-            config: config.clone(),
+            config: config.redacted(),
             keys_estimate: 0,
             size_estimate: 0,
+            effective_policy: None,
+            reaped_entries: None,
+            admission_rejected: None,
+            status: CacheStatus::Ready,
         }
     }
+
+    /// Builder method to report the backend's eviction algorithm
+    pub fn with_effective_policy(mut self, effective_policy: Option<EvictionAlgorithm>) -> Self {
+        self.effective_policy = effective_policy;
+        self
+    }
+
+    /// Builder method to report what the background reaper removed
+    pub fn with_reaped_entries(mut self, reaped_entries: Option<u64>) -> Self {
+        self.reaped_entries = reaped_entries;
+        self
+    }
+
+    /// Builder method to report what the admission policy turned away
+    pub fn with_admission_rejected(mut self, admission_rejected: Option<u64>) -> Self {
+        self.admission_rejected = admission_rejected;
+        self
+    }
+
+    /// Builder method to report whether the cache came back healthy
+    pub fn with_status(mut self, status: CacheStatus) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+/// Whether a cache is serving, set when it is recreated at startup or retried
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum CacheStatus {
+    #[default]
+    Ready,
+    /// Serving, but its backend could not be reached
+    Degraded { reason: String },
+    /// Not serving, its stored configuration could not be turned back into a cache
+    FailedToLoad { reason: String },
+}
+
+/// A stored cache that could not be recreated, it keeps its name until retried or purged
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct FailedCache {
+    pub name: String,
+    pub reason: String,
+    /// None when the stored configuration could not be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<CacheConfig>,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -142,6 +259,182 @@ pub struct CacheConfig {
     pub description: Option<String>, // human-readable description
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<HashMap<String, String>>, // metadata tags for categorization
+    #[serde(default)]
+    pub value_type: ValueType, // how stored values are interpreted
+    #[serde(default)]
+    pub value_format: ValueFormat, // encoding values are checked against and stored in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_ops_per_sec: Option<u32>, // throttle operations beyond this rate
+    #[serde(default)]
+    pub capacity_unit: CapacityUnit, // what mem_bytes counts
+    #[serde(default)]
+    pub track_metadata: bool, // keep per-entry access statistics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_url: Option<String>, // redis:// URL of a Redis backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reap_interval_ms: Option<u64>, // scan for expired entries in the background
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admission_threshold: Option<u8>, // accesses a new key needs to enter a full cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_jitter_pct: Option<u8>, // shorten TTLs set on write by a random share up to this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pinned_bytes: Option<u64>, // keys and values that may be pinned, out of reach of eviction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_entries: Option<u64>, // entries kept in an in-process moka tier in front of the backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_ttl_ms: Option<u64>, // how long the L1 tier may serve an entry without the backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeseries_retention_ms: Option<u64>, // drop samples this much older than a series' newest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeseries_max_samples: Option<u64>, // drop a series' oldest samples beyond this many
+    #[serde(default)]
+    pub events: EventPolicy, // what events about entries reveal, if they are sent at all
+}
+
+/// An action run on a cache whenever its cron expression matches
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    pub cache: String,
+    /// Minute, hour, day of month, month and day of week in UTC, or a shortcut like `@daily`
+    pub cron: String,
+    pub action: ScheduledAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_run_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<ScheduleRun>,
+}
+
+/// What a schedule does to its cache
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduledAction {
+    /// Delete every entry
+    Clear,
+    /// Write every entry to `path` as bulk load NDJSON, replacing the previous snapshot
+    Snapshot { path: String },
+    /// Bulk load the NDJSON served at `url`
+    Warm { url: String },
+}
+
+/// Outcome of the latest run of a schedule
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ScheduleRun {
+    pub started_at_ms: u64,
+    pub finished_at_ms: u64,
+    pub ok: bool,
+    /// What the action did, or why it failed
+    pub message: String,
+}
+
+/// A cache whose entries are derived from those of `source`, kept up to date from the
+/// source's events
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MaterializedView {
+    /// The derived cache, created beforehand like any other
+    pub cache: String,
+    pub source: String,
+    pub transform: ViewTransform,
+}
+
+/// How the entry of a view is computed from the source entry under the same key
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ViewTransform {
+    /// JSON object of the source document's fields, by output name and JSON pointer
+    /// Missing fields are left out, source values that are not JSON have no view entry
+    Projection { fields: BTreeMap<String, String> },
+    /// A transformation registered under `name` by the process embedding the caches
+    Registered { name: String },
+}
+
+/// A dropped cache kept for the drop retention window, restorable until it is purged
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct DroppedCache {
+    pub config: CacheConfig,
+    pub dropped_at_ms: u64,
+    pub purge_at_ms: u64,
+}
+
+/// Another name for a cache, repointed to swap a rebuilt cache into service
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CacheAlias {
+    pub alias: String,
+    pub cache: String,
+}
+
+/// Changes to the configuration of an existing cache, None leaves a setting unchanged
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+pub struct CacheConfigUpdate {
+    pub default_ttl_ms: Option<u64>,
+    pub mem_bytes: Option<u64>,
+    pub max_value_bytes: Option<u64>,
+    pub description: Option<String>,
+    pub tags: Option<HashMap<String, String>>,
+    pub max_ops_per_sec: Option<u32>,
+    pub events: Option<EventPolicy>,
+}
+
+impl CacheConfigUpdate {
+    /// Set each setting given on `config`, without validating the result
+    pub fn apply_to(self, config: &mut CacheConfig) {
+        if let Some(default_ttl_ms) = self.default_ttl_ms {
+            config.default_ttl_ms = Some(default_ttl_ms);
+        }
+        if let Some(mem_bytes) = self.mem_bytes {
+            config.mem_bytes = Some(mem_bytes);
+        }
+        if let Some(max_value_bytes) = self.max_value_bytes {
+            config.max_value_bytes = Some(max_value_bytes);
+        }
+        if let Some(description) = self.description {
+            config.description = Some(description);
+        }
+        if let Some(tags) = self.tags {
+            config.tags = Some(tags);
+        }
+        if let Some(max_ops_per_sec) = self.max_ops_per_sec {
+            config.max_ops_per_sec = Some(max_ops_per_sec);
+        }
+        if let Some(events) = self.events {
+            config.events = events;
+        }
+    }
+}
+
+/// Selects caches by name prefix and tags, an empty filter matches every cache
+#[derive(Clone, Debug, Default)]
+pub struct CacheFilter {
+    pub name_prefix: Option<String>,
+    /// Every tag must be present, with the given value when one is set
+    pub tags: Vec<(String, Option<String>)>,
+}
+
+impl CacheFilter {
+    /// Builder method to require a tag, `key:value` matches the value and a bare `key` any value
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        let tag = match tag.split_once(':') {
+            Some((key, value)) => (key.to_string(), Some(value.to_string())),
+            None => (tag.to_string(), None),
+        };
+        self.tags.push(tag);
+        self
+    }
+
+    pub fn matches(&self, config: &CacheConfig) -> bool {
+        if let Some(prefix) = &self.name_prefix
+            && !config.name.starts_with(prefix.as_str())
+        {
+            return false;
+        }
+
+        self.tags.iter().all(|(key, value)| {
+            match config.tags.as_ref().and_then(|tags| tags.get(key)) {
+                Some(tag_value) => value.as_ref().is_none_or(|value| value == tag_value),
+                None => false,
+            }
+        })
+    }
 }
 
 fn default_backend() -> CacheEvictionStrategy {
@@ -171,6 +464,21 @@ impl CacheConfig {
             max_value_bytes,
             description,
             tags,
+            value_type: ValueType::Raw,
+            value_format: ValueFormat::Raw,
+            max_ops_per_sec: None,
+            capacity_unit: CapacityUnit::Entries,
+            track_metadata: false,
+            remote_url: None,
+            reap_interval_ms: None,
+            admission_threshold: None,
+            ttl_jitter_pct: None,
+            max_pinned_bytes: None,
+            l1_entries: None,
+            l1_ttl_ms: None,
+            timeseries_retention_ms: None,
+            timeseries_max_samples: None,
+            events: EventPolicy::default(),
         }
     }
 
@@ -198,6 +506,21 @@ impl CacheConfig {
             max_value_bytes,
             description,
             tags,
+            value_type: ValueType::Raw,
+            value_format: ValueFormat::Raw,
+            max_ops_per_sec: None,
+            capacity_unit: CapacityUnit::Entries,
+            track_metadata: false,
+            remote_url: None,
+            reap_interval_ms: None,
+            admission_threshold: None,
+            ttl_jitter_pct: None,
+            max_pinned_bytes: None,
+            l1_entries: None,
+            l1_ttl_ms: None,
+            timeseries_retention_ms: None,
+            timeseries_max_samples: None,
+            events: EventPolicy::default(),
         }
     }
 
@@ -212,15 +535,670 @@ impl CacheConfig {
         self.tags = Some(tags);
         self
     }
+
+    /// Builder method to set the value type
+    pub fn with_value_type(mut self, value_type: ValueType) -> Self {
+        self.value_type = value_type;
+        self
+    }
+
+    /// Builder method to set the format values are stored in
+    pub fn with_value_format(mut self, value_format: ValueFormat) -> Self {
+        self.value_format = value_format;
+        self
+    }
+
+    /// Builder method to cap operations per second on this cache
+    pub fn with_max_ops_per_sec(mut self, max_ops_per_sec: Option<u32>) -> Self {
+        self.max_ops_per_sec = max_ops_per_sec;
+        self
+    }
+
+    /// Builder method to choose what mem_bytes counts
+    pub fn with_capacity_unit(mut self, capacity_unit: CapacityUnit) -> Self {
+        self.capacity_unit = capacity_unit;
+        self
+    }
+
+    /// Builder method to point a Redis cache at its server
+    pub fn with_remote_url(mut self, remote_url: Option<String>) -> Self {
+        self.remote_url = remote_url;
+        self
+    }
+
+    /// Copy fit to show to clients, with the credentials in remote_url masked
+    pub fn redacted(&self) -> Self {
+        Self {
+            remote_url: self.remote_url.as_deref().map(redact_url_credentials),
+            ..self.clone()
+        }
+    }
+
+    /// Builder method to keep per-entry access statistics, at the cost of a map entry per key
+    pub fn with_track_metadata(mut self, track_metadata: bool) -> Self {
+        self.track_metadata = track_metadata;
+        self
+    }
+
+    /// Builder method to remove expired entries every interval instead of only when next touched
+    pub fn with_reap_interval_ms(mut self, reap_interval_ms: Option<u64>) -> Self {
+        self.reap_interval_ms = reap_interval_ms;
+        self
+    }
+
+    /// Builder method to keep keys accessed fewer times than `admission_threshold` out of a full cache
+    pub fn with_admission_threshold(mut self, admission_threshold: Option<u8>) -> Self {
+        self.admission_threshold = admission_threshold;
+        self
+    }
+
+    /// Builder method to spread the expiry of entries written together over up to `ttl_jitter_pct`
+    /// percent of their TTL
+    pub fn with_ttl_jitter_pct(mut self, ttl_jitter_pct: Option<u8>) -> Self {
+        self.ttl_jitter_pct = ttl_jitter_pct;
+        self
+    }
+
+    /// Builder method to let up to `max_pinned_bytes` of entries be pinned against eviction
+    pub fn with_max_pinned_bytes(mut self, max_pinned_bytes: Option<u64>) -> Self {
+        self.max_pinned_bytes = max_pinned_bytes;
+        self
+    }
+
+    /// Builder method to put an in-process tier of up to `l1_entries` in front of the backend,
+    /// serving each entry for up to `l1_ttl_ms`
+    pub fn with_l1_tier(mut self, l1_entries: Option<u64>, l1_ttl_ms: Option<u64>) -> Self {
+        self.l1_entries = l1_entries;
+        self.l1_ttl_ms = l1_ttl_ms;
+        self
+    }
+
+    /// Builder method to keep only the samples of a time series within `retention_ms` of its
+    /// newest sample, and at most `max_samples` of them
+    pub fn with_timeseries_retention(
+        mut self,
+        retention_ms: Option<u64>,
+        max_samples: Option<u64>,
+    ) -> Self {
+        self.timeseries_retention_ms = retention_ms;
+        self.timeseries_max_samples = max_samples;
+        self
+    }
+
+    /// Builder method to redact keys and values in events, or stop sending them
+    pub fn with_events(mut self, events: EventPolicy) -> Self {
+        self.events = events;
+        self
+    }
+}
+
+// Limits enforced on every cache configuration, whichever frontend created it
+pub const MIN_MEM_BYTES: u64 = 1_048_576; // 1 MB
+pub const MAX_MEM_BYTES: u64 = 1_099_511_627_776; // 1 TB
+pub const MAX_SHARDS: u8 = 128;
+pub const MIN_REAP_INTERVAL_MS: u64 = 100;
+pub const MAX_ADMISSION_THRESHOLD: u8 = 15; // frequency sketch counters saturate here
+pub const MAX_TTL_JITTER_PCT: u8 = 50;
+pub const DEFAULT_TIMESERIES_MAX_SAMPLES: u64 = 10_000; // series of caches without retention
+
+/// Why a cache configuration was rejected
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum CacheConfigError {
+    #[error("Missing required field '{field}' for {backend} cache")]
+    MissingRequiredField {
+        field: &'static str,
+        backend: &'static str,
+    },
+    #[error("Invalid cache name: {reason}")]
+    InvalidCacheName { reason: &'static str },
+    #[error("Field '{field}' value {value} is out of range (min: {min}, max: {max})")]
+    OutOfRange {
+        field: &'static str,
+        value: u64,
+        min: u64,
+        max: u64,
+    },
+    #[error("Capacity unit '{unit}' is not supported by {backend} caches")]
+    UnsupportedCapacityUnit {
+        unit: &'static str,
+        backend: &'static str,
+    },
+    #[error("Field '{field}' is not supported by {backend} caches")]
+    UnsupportedField {
+        field: &'static str,
+        backend: &'static str,
+    },
+}
+
+impl From<CacheConfigError> for shared::Error {
+    fn from(err: CacheConfigError) -> Self {
+        shared::Error::InvalidValue(err.to_string())
+    }
+}
+
+impl CacheConfig {
+    /// Check the configuration is usable by its backend
+    pub fn validate(&self) -> Result<(), CacheConfigError> {
+        if self.name.is_empty() {
+            return Err(CacheConfigError::InvalidCacheName {
+                reason: "cache name cannot be empty",
+            });
+        }
+
+        if !self
+            .name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(CacheConfigError::InvalidCacheName {
+                reason: "cache name must contain only alphanumeric characters, hyphens, or underscores",
+            });
+        }
+
+        let backend = match self.backend {
+            CacheEvictionStrategy::TimeBound => "TimeBound",
+            CacheEvictionStrategy::SizeBounded => "SizeBounded",
+            CacheEvictionStrategy::OverflowToDisk => "OverflowToDisk",
+            CacheEvictionStrategy::Redis => "Redis",
+        };
+
+        // Optional for TTL and Redis caches, the size and storage backends need a memory budget
+        match (self.mem_bytes, self.backend) {
+            (None, CacheEvictionStrategy::TimeBound | CacheEvictionStrategy::Redis) => {}
+            (None, _) => {
+                return Err(CacheConfigError::MissingRequiredField {
+                    field: "mem_bytes",
+                    backend,
+                });
+            }
+            (Some(mem_bytes), _) => {
+                if !(MIN_MEM_BYTES..=MAX_MEM_BYTES).contains(&mem_bytes) {
+                    return Err(CacheConfigError::OutOfRange {
+                        field: "mem_bytes",
+                        value: mem_bytes,
+                        min: MIN_MEM_BYTES,
+                        max: MAX_MEM_BYTES,
+                    });
+                }
+            }
+        }
+
+        // Only the moka backend weighs entries, foyer always counts them
+        if self.capacity_unit == CapacityUnit::Bytes
+            && self.backend != CacheEvictionStrategy::TimeBound
+        {
+            return Err(CacheConfigError::UnsupportedCapacityUnit {
+                unit: "bytes",
+                backend,
+            });
+        }
+
+        if self.backend == CacheEvictionStrategy::OverflowToDisk
+            && self.disk_path.as_deref().is_none_or(str::is_empty)
+        {
+            return Err(CacheConfigError::MissingRequiredField {
+                field: "disk_path",
+                backend,
+            });
+        }
+
+        if self.backend == CacheEvictionStrategy::Redis
+            && self.remote_url.as_deref().is_none_or(str::is_empty)
+        {
+            return Err(CacheConfigError::MissingRequiredField {
+                field: "remote_url",
+                backend,
+            });
+        }
+
+        if let Some(shards) = self.shards
+            && shards > MAX_SHARDS
+        {
+            return Err(CacheConfigError::OutOfRange {
+                field: "shards",
+                value: shards as u64,
+                min: 1,
+                max: MAX_SHARDS as u64,
+            });
+        }
+
+        // Only the moka backend expires entries lazily, Redis reaps them itself
+        if let Some(reap_interval_ms) = self.reap_interval_ms {
+            if self.backend != CacheEvictionStrategy::TimeBound {
+                return Err(CacheConfigError::UnsupportedField {
+                    field: "reap_interval_ms",
+                    backend,
+                });
+            }
+            if reap_interval_ms < MIN_REAP_INTERVAL_MS {
+                return Err(CacheConfigError::OutOfRange {
+                    field: "reap_interval_ms",
+                    value: reap_interval_ms,
+                    min: MIN_REAP_INTERVAL_MS,
+                    max: u64::MAX,
+                });
+            }
+        }
+
+        // Storage caches spill through foyer, which has neither LFU nor ARC
+        if self.backend == CacheEvictionStrategy::OverflowToDisk
+            && matches!(self.policy, EvictionAlgorithm::Lfu | EvictionAlgorithm::Arc)
+        {
+            return Err(CacheConfigError::UnsupportedField {
+                field: "policy",
+                backend,
+            });
+        }
+
+        // Admission sits in front of the foyer backends, whose eviction it protects
+        // LFU and ARC weigh how often keys are used themselves
+        if let Some(admission_threshold) = self.admission_threshold {
+            if !matches!(
+                self.backend,
+                CacheEvictionStrategy::SizeBounded | CacheEvictionStrategy::OverflowToDisk
+            ) {
+                return Err(CacheConfigError::UnsupportedField {
+                    field: "admission_threshold",
+                    backend,
+                });
+            }
+            if matches!(self.policy, EvictionAlgorithm::Lfu | EvictionAlgorithm::Arc) {
+                return Err(CacheConfigError::UnsupportedField {
+                    field: "admission_threshold",
+                    backend: "LFU and ARC",
+                });
+            }
+            if !(1..=MAX_ADMISSION_THRESHOLD).contains(&admission_threshold) {
+                return Err(CacheConfigError::OutOfRange {
+                    field: "admission_threshold",
+                    value: admission_threshold as u64,
+                    min: 1,
+                    max: MAX_ADMISSION_THRESHOLD as u64,
+                });
+            }
+        }
+
+        // Only the moka and Redis backends expire entries
+        if let Some(ttl_jitter_pct) = self.ttl_jitter_pct {
+            if !matches!(
+                self.backend,
+                CacheEvictionStrategy::TimeBound | CacheEvictionStrategy::Redis
+            ) {
+                return Err(CacheConfigError::UnsupportedField {
+                    field: "ttl_jitter_pct",
+                    backend,
+                });
+            }
+            if !(1..=MAX_TTL_JITTER_PCT).contains(&ttl_jitter_pct) {
+                return Err(CacheConfigError::OutOfRange {
+                    field: "ttl_jitter_pct",
+                    value: ttl_jitter_pct as u64,
+                    min: 1,
+                    max: MAX_TTL_JITTER_PCT as u64,
+                });
+            }
+        }
+
+        // Pinned entries are held next to the moka cache, outside its capacity
+        if let Some(max_pinned_bytes) = self.max_pinned_bytes {
+            if self.backend != CacheEvictionStrategy::TimeBound {
+                return Err(CacheConfigError::UnsupportedField {
+                    field: "max_pinned_bytes",
+                    backend,
+                });
+            }
+            if !(1..=MAX_MEM_BYTES).contains(&max_pinned_bytes) {
+                return Err(CacheConfigError::OutOfRange {
+                    field: "max_pinned_bytes",
+                    value: max_pinned_bytes,
+                    min: 1,
+                    max: MAX_MEM_BYTES,
+                });
+            }
+        }
+
+        // The L1 tier is a moka cache, in front of a moka cache it would only double up
+        if let Some(l1_entries) = self.l1_entries {
+            if self.backend == CacheEvictionStrategy::TimeBound {
+                return Err(CacheConfigError::UnsupportedField {
+                    field: "l1_entries",
+                    backend,
+                });
+            }
+            if !(1..=MAX_MEM_BYTES).contains(&l1_entries) {
+                return Err(CacheConfigError::OutOfRange {
+                    field: "l1_entries",
+                    value: l1_entries,
+                    min: 1,
+                    max: MAX_MEM_BYTES,
+                });
+            }
+        }
+        if let Some(l1_ttl_ms) = self.l1_ttl_ms {
+            if self.l1_entries.is_none() {
+                return Err(CacheConfigError::UnsupportedField {
+                    field: "l1_ttl_ms",
+                    backend: "single tier",
+                });
+            }
+            if l1_ttl_ms == 0 {
+                return Err(CacheConfigError::OutOfRange {
+                    field: "l1_ttl_ms",
+                    value: 0,
+                    min: 1,
+                    max: u64::MAX,
+                });
+            }
+        }
+
+        // A series retaining nothing would drop every sample as it is appended
+        if self.timeseries_retention_ms == Some(0) {
+            return Err(CacheConfigError::OutOfRange {
+                field: "timeseries_retention_ms",
+                value: 0,
+                min: 1,
+                max: u64::MAX,
+            });
+        }
+        if self.timeseries_max_samples == Some(0) {
+            return Err(CacheConfigError::OutOfRange {
+                field: "timeseries_max_samples",
+                value: 0,
+                min: 1,
+                max: u64::MAX,
+            });
+        }
+
+        // JSON pointer commands and indexes read the stored documents as JSON
+        if self.value_type == ValueType::Json && self.value_format == ValueFormat::MessagePack {
+            return Err(CacheConfigError::UnsupportedField {
+                field: "value_format",
+                backend: "JSON typed",
+            });
+        }
+
+        // A limit of zero would reject every operation
+        if self.max_ops_per_sec == Some(0) {
+            return Err(CacheConfigError::OutOfRange {
+                field: "max_ops_per_sec",
+                value: 0,
+                min: 1,
+                max: u32::MAX as u64,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Refuse a value larger than max_value_bytes
+    pub fn check_value_size(&self, size: usize) -> shared::Result<()> {
+        match self.max_value_bytes {
+            Some(max_value_bytes) if size as u64 > max_value_bytes => {
+                Err(shared::Error::InvalidValue(format!(
+                    "value of {} bytes is over the cache's max_value_bytes of {}",
+                    size, max_value_bytes
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How much a cache could hold, as estimated by its storage backend
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct CapacityEstimate {
+    /// What mem_bytes bounds for this cache
+    pub capacity_unit: CapacityUnit,
+    /// Bookkeeping the backend adds to every entry
+    pub entry_overhead_bytes: u64,
+    /// Key, value and overhead of an average entry
+    pub entry_bytes: u64,
+    /// None when the cache is unbounded
+    pub max_entries: Option<u64>,
+    /// Memory used once the cache is full of average entries
+    pub max_memory_bytes: Option<u64>,
+}
+
+/// How events show the key of the entry they are about
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKeyMode {
+    /// The key as stored
+    #[default]
+    Include,
+    /// Hex HMAC-SHA256 of the key under the server's event key, so subscribers can tell keys
+    /// apart without seeing them or recovering them by hashing guesses
+    Hash,
+    /// No key at all
+    Omit,
+}
+
+/// What events about a cache's entries carry, keys and values may hold personal data
+#[derive(PartialEq, Eq, Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct EventPolicy {
+    /// False sends no events about the cache
+    #[serde(default = "default_events_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub key: EventKeyMode,
+    /// Values larger than this are left out of events, which still report their size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_value_bytes: Option<u64>,
+}
+
+fn default_events_enabled() -> bool {
+    true
+}
+
+impl Default for EventPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            key: EventKeyMode::Include,
+            max_value_bytes: None,
+        }
+    }
+}
+
+impl EventPolicy {
+    /// The key as events about it show it, `secret` keys the hash of Hash mode
+    pub fn event_key(&self, key: &[u8], secret: &[u8]) -> Vec<u8> {
+        use hmac::{Hmac, Mac};
+        use sha2::Sha256;
+
+        match self.key {
+            EventKeyMode::Include => key.to_vec(),
+            EventKeyMode::Hash => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+                    .expect("HMAC accepts keys of any length");
+                mac.update(key);
+                mac.finalize()
+                    .into_bytes()
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>()
+                    .into_bytes()
+            }
+            EventKeyMode::Omit => Vec::new(),
+        }
+    }
+
+    /// The value as events about it carry it, empty when it is over max_value_bytes
+    pub fn event_value(&self, value: Vec<u8>) -> Vec<u8> {
+        match self.max_value_bytes {
+            Some(max_value_bytes) if value.len() as u64 > max_value_bytes => Vec::new(),
+            _ => value,
+        }
+    }
+}
+
+/// What a cache's mem_bytes bounds
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CapacityUnit {
+    /// Number of entries, whatever their size
+    #[default]
+    Entries,
+    /// Summed size of keys and values
+    Bytes,
+}
+
+/// Interpretation of the values stored in a cache
+#[derive(PartialEq, Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    /// Opaque bytes, never inspected by the server
+    #[default]
+    Raw,
+    /// UTF-8 JSON documents, validated on write and addressable by JSON pointer
+    Json,
+}
+
+/// Encoding of the values stored in a cache, the servers convert between the formats their
+/// clients speak and this one
+#[derive(PartialEq, Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueFormat {
+    /// Bytes stored as they are sent
+    #[default]
+    Raw,
+    /// JSON documents
+    Json,
+    /// MessagePack documents
+    #[serde(alias = "msgpack")]
+    MessagePack,
+}
+
+/// End of a list-typed value that a push or pop operates on
+#[derive(PartialEq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+/// Lease granted by LOCK; the token must be presented to UNLOCK and
+/// increases with every grant so it can fence out stale holders
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct LockLease {
+    pub token: u64,
+    /// Unix time in milliseconds after which the lock can be taken by another client
+    pub expires_at_ms: u64,
+}
+
+/// Strong entity tag of a stored value, quoted as sent in an ETag header
+/// FNV-1a keeps tags stable across restarts and builds, unlike the std hasher
+pub fn entity_tag(value: &[u8]) -> String {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let hash = value.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    });
+    format!("\"{:016x}\"", hash)
+}
+
+/// `url` with any user and password replaced by `***`, for responses, logs and errors
+pub fn redact_url_credentials(url: &str) -> String {
+    let authority_start = url.find("://").map_or(0, |scheme_end| scheme_end + 3);
+    let authority_end = url[authority_start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |end| authority_start + end);
+    match url[authority_start..authority_end].rfind('@') {
+        Some(at) => format!(
+            "{}***{}",
+            &url[..authority_start],
+            &url[authority_start + at..]
+        ),
+        None => url.to_string(),
+    }
+}
+
+/// Condition on the current value of a key, as given by an If-Match header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntryMatch {
+    /// Any existing entry (`*`)
+    Any,
+    /// An entry whose entity tag is one of these
+    Tags(Vec<String>),
+}
+
+impl EntryMatch {
+    /// Whether `current` satisfies the condition, a missing entry never does
+    pub fn matches(&self, current: Option<&[u8]>) -> bool {
+        match (self, current) {
+            (_, None) => false,
+            (EntryMatch::Any, Some(_)) => true,
+            (EntryMatch::Tags(tags), Some(value)) => {
+                let tag = entity_tag(value);
+                tags.contains(&tag)
+            }
+        }
+    }
+}
+
+/// Access statistics of an entry in a cache created with track_metadata
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize)]
+pub struct EntryMetadata {
+    /// Unix time in milliseconds the entry was last written
+    pub created_at_ms: u64,
+    /// Unix time in milliseconds the entry was last read, or written if never read
+    pub last_accessed_ms: u64,
+    /// None when the entry does not expire
+    pub ttl_remaining_ms: Option<u64>,
+    /// Key and value size in bytes
+    pub size_bytes: u64,
+    /// Reads since the entry was last written
+    pub access_count: u64,
+}
+
+/// Record of a bulk load, written without an event of its own
+#[derive(Clone, Debug)]
+pub struct BulkEntry<K, V> {
+    pub key: K,
+    pub value: V,
+    /// None leaves the entry to the cache's default TTL
+    pub ttl: Option<std::time::Duration>,
 }
 
 #[repr(i8)]
-#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+/// Limits on what one user may write, a limit left out does not apply
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Quota {
+    /// Key and value bytes of the entries the user wrote
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    #[serde(default)]
+    pub max_keys: Option<u64>,
+    /// Writes per UTC day
+    #[serde(default)]
+    pub max_ops_per_day: Option<u64>,
+}
+
+impl Quota {
+    /// The larger of each limit, as granted by holding both quotas
+    pub fn most_generous(self, other: Quota) -> Quota {
+        fn larger(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+            Some(a?.max(b?))
+        }
+        Quota {
+            max_bytes: larger(self.max_bytes, other.max_bytes),
+            max_keys: larger(self.max_keys, other.max_keys),
+            max_ops_per_day: larger(self.max_ops_per_day, other.max_ops_per_day),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub enum EvictionAlgorithm {
     Unspecified,
     Lru,
     TinyLfu,
     Sieve,
+    /// Evicts the entry used the fewest times, size caches only
+    Lfu,
+    /// Adaptive Replacement Cache, balancing recency and frequency, size caches only
+    Arc,
 }
 
 #[derive(PartialEq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
@@ -232,6 +1210,8 @@ pub enum CacheEvictionStrategy {
     SizeBounded,
     /// Foyer hybrid - Memory + disk overflow (future)
     OverflowToDisk,
+    /// External Redis/Valkey server at remote_url, evicting by its own maxmemory-policy
+    Redis,
 }
 
 impl TryFrom<i32> for EvictionAlgorithm {
@@ -243,7 +1223,316 @@ impl TryFrom<i32> for EvictionAlgorithm {
             1 => Ok(EvictionAlgorithm::Lru),
             2 => Ok(EvictionAlgorithm::TinyLfu),
             3 => Ok(EvictionAlgorithm::Sieve),
+            4 => Ok(EvictionAlgorithm::Lfu),
+            5 => Ok(EvictionAlgorithm::Arc),
             _ => Err("Invalid eviction policy value"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size_bounded(mem_bytes: Option<u64>) -> CacheConfig {
+        CacheConfig::with_backend(
+            "orders",
+            CacheEvictionStrategy::SizeBounded,
+            EvictionAlgorithm::Unspecified,
+            mem_bytes,
+            None,
+            Some(16),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_validate_accepts_valid_config() {
+        assert_eq!(size_bounded(Some(MIN_MEM_BYTES)).validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_config() {
+        assert!(matches!(
+            size_bounded(None).validate(),
+            Err(CacheConfigError::MissingRequiredField {
+                field: "mem_bytes",
+                ..
+            })
+        ));
+        assert!(matches!(
+            size_bounded(Some(1)).validate(),
+            Err(CacheConfigError::OutOfRange {
+                field: "mem_bytes",
+                ..
+            })
+        ));
+
+        let mut config = size_bounded(Some(MIN_MEM_BYTES));
+        config.name = "orders/eu".to_string();
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::InvalidCacheName { .. })
+        ));
+
+        let config = size_bounded(Some(MIN_MEM_BYTES)).with_max_ops_per_sec(Some(0));
+        assert!(config.validate().is_err());
+
+        let config = size_bounded(Some(MIN_MEM_BYTES)).with_capacity_unit(CapacityUnit::Bytes);
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::UnsupportedCapacityUnit { .. })
+        ));
+
+        let mut config = size_bounded(None);
+        config.backend = CacheEvictionStrategy::Redis;
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::MissingRequiredField {
+                field: "remote_url",
+                ..
+            })
+        ));
+        let config = config.with_remote_url(Some("redis://localhost:6379".to_string()));
+        assert_eq!(config.validate(), Ok(()));
+
+        let config = size_bounded(Some(MIN_MEM_BYTES)).with_reap_interval_ms(Some(1_000));
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::UnsupportedField {
+                field: "reap_interval_ms",
+                ..
+            })
+        ));
+        let config = size_bounded(Some(MIN_MEM_BYTES)).with_admission_threshold(Some(2));
+        assert_eq!(config.validate(), Ok(()));
+        let config = config.with_admission_threshold(Some(MAX_ADMISSION_THRESHOLD + 1));
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::OutOfRange {
+                field: "admission_threshold",
+                ..
+            })
+        ));
+        let mut config = config.with_admission_threshold(Some(2));
+        config.policy = EvictionAlgorithm::Arc;
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::UnsupportedField {
+                field: "admission_threshold",
+                ..
+            })
+        ));
+        let mut config = size_bounded(Some(MIN_MEM_BYTES));
+        config.policy = EvictionAlgorithm::Lfu;
+        assert_eq!(config.validate(), Ok(()));
+        config.backend = CacheEvictionStrategy::OverflowToDisk;
+        config.disk_path = Some("/tmp/carbon".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::UnsupportedField {
+                field: "policy",
+                ..
+            })
+        ));
+
+        let config = size_bounded(Some(MIN_MEM_BYTES)).with_ttl_jitter_pct(Some(10));
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::UnsupportedField {
+                field: "ttl_jitter_pct",
+                ..
+            })
+        ));
+        let mut config = config;
+        config.backend = CacheEvictionStrategy::TimeBound;
+        assert_eq!(config.validate(), Ok(()));
+        let config = config.with_ttl_jitter_pct(Some(MAX_TTL_JITTER_PCT + 1));
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::OutOfRange {
+                field: "ttl_jitter_pct",
+                ..
+            })
+        ));
+
+        let mut config = size_bounded(Some(MIN_MEM_BYTES)).with_reap_interval_ms(Some(10));
+        config.backend = CacheEvictionStrategy::TimeBound;
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::OutOfRange {
+                field: "reap_interval_ms",
+                ..
+            })
+        ));
+
+        let config = size_bounded(Some(MIN_MEM_BYTES)).with_max_pinned_bytes(Some(4_096));
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::UnsupportedField {
+                field: "max_pinned_bytes",
+                ..
+            })
+        ));
+        let mut config = config;
+        config.backend = CacheEvictionStrategy::TimeBound;
+        assert_eq!(config.validate(), Ok(()));
+
+        let config = size_bounded(Some(MIN_MEM_BYTES))
+            .with_value_type(ValueType::Json)
+            .with_value_format(ValueFormat::MessagePack);
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::UnsupportedField {
+                field: "value_format",
+                ..
+            })
+        ));
+        let config = config.with_value_type(ValueType::Raw);
+        assert_eq!(config.validate(), Ok(()));
+
+        let config = size_bounded(Some(MIN_MEM_BYTES)).with_l1_tier(None, Some(1_000));
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::UnsupportedField {
+                field: "l1_ttl_ms",
+                ..
+            })
+        ));
+        let config = config.with_l1_tier(Some(1_000), Some(1_000));
+        assert_eq!(config.validate(), Ok(()));
+        let mut config = config;
+        config.backend = CacheEvictionStrategy::TimeBound;
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::UnsupportedField {
+                field: "l1_entries",
+                ..
+            })
+        ));
+
+        let config = size_bounded(Some(MIN_MEM_BYTES)).with_timeseries_retention(Some(0), None);
+        assert!(matches!(
+            config.validate(),
+            Err(CacheConfigError::OutOfRange {
+                field: "timeseries_retention_ms",
+                ..
+            })
+        ));
+        let config = config.with_timeseries_retention(Some(3_600_000), Some(10_000));
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_check_value_size() {
+        let mut config = size_bounded(Some(MIN_MEM_BYTES));
+        assert!(config.check_value_size(1 << 20).is_ok());
+
+        config.max_value_bytes = Some(4);
+        assert!(config.check_value_size(4).is_ok());
+        assert!(matches!(
+            config.check_value_size(5),
+            Err(shared::Error::InvalidValue(_))
+        ));
+    }
+
+    #[test]
+    fn test_entry_match() {
+        let tag = entity_tag(b"hello");
+        assert_eq!(tag, entity_tag(b"hello"));
+        assert_ne!(tag, entity_tag(b"world"));
+        assert!(tag.starts_with('"') && tag.ends_with('"'));
+
+        assert!(EntryMatch::Any.matches(Some(b"hello")));
+        assert!(!EntryMatch::Any.matches(None));
+        assert!(EntryMatch::Tags(vec![tag.clone()]).matches(Some(b"hello")));
+        assert!(!EntryMatch::Tags(vec![tag]).matches(Some(b"world")));
+    }
+
+    #[test]
+    fn test_cache_filter() {
+        let config = size_bounded(Some(MIN_MEM_BYTES)).with_tags(HashMap::from([
+            ("env".to_string(), "prod".to_string()),
+            ("team".to_string(), "payments".to_string()),
+        ]));
+
+        assert!(CacheFilter::default().matches(&config));
+        assert!(CacheFilter::default().with_tag("env:prod").matches(&config));
+        assert!(CacheFilter::default().with_tag("team").matches(&config));
+        assert!(!CacheFilter::default().with_tag("env:dev").matches(&config));
+        assert!(
+            !CacheFilter::default()
+                .with_tag("env:prod")
+                .with_tag("region")
+                .matches(&config)
+        );
+
+        let filter = CacheFilter {
+            name_prefix: Some("ord".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&config));
+        let filter = CacheFilter {
+            name_prefix: Some("users".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&config));
+    }
+
+    #[test]
+    fn test_event_policy() {
+        let policy = EventPolicy::default();
+        assert_eq!(policy.event_key(b"alice", b"secret"), b"alice".to_vec());
+        assert_eq!(policy.event_value(vec![0; 4096]).len(), 4096);
+
+        let policy = EventPolicy {
+            key: EventKeyMode::Hash,
+            max_value_bytes: Some(16),
+            ..Default::default()
+        };
+        let hashed = policy.event_key(b"alice", b"secret");
+        assert_eq!(hashed.len(), 64);
+        assert_eq!(hashed, policy.event_key(b"alice", b"secret"));
+        assert_ne!(hashed, policy.event_key(b"bob", b"secret"));
+        // Without the server's secret, hashing a guessed key does not give it away
+        assert_ne!(hashed, policy.event_key(b"alice", b"other"));
+        assert_eq!(policy.event_value(b"short".to_vec()), b"short".to_vec());
+        assert!(policy.event_value(vec![0; 17]).is_empty());
+
+        let policy = EventPolicy {
+            key: EventKeyMode::Omit,
+            ..Default::default()
+        };
+        assert!(policy.event_key(b"alice", b"secret").is_empty());
+
+        // Configurations saved before event policies send events as they did
+        let policy: EventPolicy = serde_json::from_str(r#"{"key": "hash"}"#).unwrap();
+        assert!(policy.enabled);
+    }
+
+    #[test]
+    fn test_redact_url_credentials() {
+        assert_eq!(
+            redact_url_credentials("redis://:s3cret@cache.internal:6379/0"),
+            "redis://***@cache.internal:6379/0"
+        );
+        assert_eq!(
+            redact_url_credentials("rediss://carbon:p@ss@cache.internal"),
+            "rediss://***@cache.internal"
+        );
+        assert_eq!(
+            redact_url_credentials("redis://cache.internal:6379/0?user=a@b"),
+            "redis://cache.internal:6379/0?user=a@b"
+        );
+
+        let config =
+            size_bounded(None).with_remote_url(Some("redis://:s3cret@cache.internal".to_string()));
+        assert_eq!(
+            CacheInfo::from_config(&config).config.remote_url.as_deref(),
+            Some("redis://***@cache.internal")
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::domain::CacheConfig;
+use crate::domain::response::PutResponse;
+use crate::domain::response::admin::{CreateCacheResponse, DropCacheResponse, ListCachesResponse};
+use crate::planes::control::CacheManager;
+use crate::planes::control::operation::AdminOperations;
+use crate::planes::data::CacheOperationsService;
+use crate::planes::data::operation::CacheOperations;
+use crate::ports::StorageFactory;
+use bytes::Bytes;
+use shared::{Error, Result};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Carbon running inside the application, without the HTTP or TCP servers
+///
+/// Storage comes from the injected factory, e.g. `storage_engine::UnifiedStorageFactory`:
+///
+/// ```ignore
+/// let carbon = CarbonInstance::new(Arc::new(UnifiedStorageFactory));
+/// carbon.create_cache(config).await?;
+/// carbon.put("sessions", "abc", "payload").await?;
+/// ```
+#[derive(Clone)]
+pub struct CarbonInstance {
+    cache_manager: CacheManager<Vec<u8>, Bytes>,
+    cache_operations: CacheOperationsService<Vec<u8>, Bytes>,
+    factory: Arc<dyn StorageFactory<Vec<u8>, Bytes>>,
+}
+
+impl CarbonInstance {
+    /// In-memory instance, its caches are gone once it is dropped
+    pub fn new(factory: Arc<dyn StorageFactory<Vec<u8>, Bytes>>) -> Self {
+        Self::with_cache_manager(CacheManager::new(), factory)
+    }
+
+    /// Instance keeping cache configurations under `path`, caches created earlier are recreated
+    /// (empty) on open
+    pub async fn open(
+        path: impl AsRef<Path>,
+        factory: Arc<dyn StorageFactory<Vec<u8>, Bytes>>,
+    ) -> Result<Self> {
+        let cache_manager = CacheManager::new_with_persistence(path, factory.clone()).await?;
+        Ok(Self::with_cache_manager(cache_manager, factory))
+    }
+
+    fn with_cache_manager(
+        cache_manager: CacheManager<Vec<u8>, Bytes>,
+        factory: Arc<dyn StorageFactory<Vec<u8>, Bytes>>,
+    ) -> Self {
+        Self {
+            cache_operations: CacheOperationsService::new(cache_manager.clone()),
+            cache_manager,
+            factory,
+        }
+    }
+
+    /// Validate `config` and create its cache, `created` is false if the name is taken
+    pub async fn create_cache(&self, config: CacheConfig) -> Result<CreateCacheResponse> {
+        config.validate()?;
+        let store = self.factory.create_from_config(&config);
+        self.cache_manager.create_cache(config, store).await
+    }
+
+    pub async fn drop_cache(&self, name: &str) -> Result<DropCacheResponse> {
+        self.cache_manager.drop_cache(name).await
+    }
+
+    pub async fn list_caches(&self) -> Result<ListCachesResponse> {
+        self.cache_manager.list_caches().await
+    }
+
+    pub async fn put(
+        &self,
+        cache_name: &str,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Bytes>,
+    ) -> Result<PutResponse> {
+        self.cache_operations
+            .put(cache_name, key.into(), value.into())
+            .await
+    }
+
+    /// Value stored under `key`, None if there is none
+    pub async fn get(&self, cache_name: &str, key: impl AsRef<[u8]>) -> Result<Option<Bytes>> {
+        match self
+            .cache_operations
+            .get(cache_name, &key.as_ref().to_vec())
+            .await
+        {
+            Ok(result) if result.found => Ok(Some(result.message)),
+            Ok(_) | Err(Error::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Whether an entry was deleted
+    pub async fn delete(&self, cache_name: &str, key: impl AsRef<[u8]>) -> Result<bool> {
+        self.cache_operations
+            .delete(cache_name, &key.as_ref().to_vec())
+            .await
+            .map(|result| result.deleted)
+    }
+
+    /// Service behind put/get/delete, for the JSON, hash, list, set, lock and tag commands
+    pub fn cache_operations(&self) -> &CacheOperationsService<Vec<u8>, Bytes> {
+        &self.cache_operations
+    }
+
+    pub fn cache_manager(&self) -> &CacheManager<Vec<u8>, Bytes> {
+        &self.cache_manager
+    }
+}
@@ -0,0 +1,118 @@
+use crate::domain::ValueFormat;
+use bytes::Bytes;
+use serde::Serialize;
+use shared::{Error, Result};
+use std::borrow::Cow;
+
+/// Byte form of a key or value, as carried by events, secondary indexes and the slow log
+/// Lets CacheOperationsService work with typed keys and values, not only raw bytes
+pub trait ToBytes {
+    fn to_bytes(&self) -> Cow<'_, [u8]>;
+}
+
+impl ToBytes for Vec<u8> {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl ToBytes for Bytes {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl ToBytes for String {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+}
+
+impl ToBytes for &'static str {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+}
+
+/// Keys or values of any serde type, encoded as JSON
+/// A value that cannot be serialized (e.g. a map with non-string keys) encodes as nothing
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> ToBytes for Json<T> {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(serde_json::to_vec(&self.0).unwrap_or_default())
+    }
+}
+
+/// Parse a JSON or MessagePack value, MessagePack that has no JSON form (e.g. binary data or
+/// maps with integer keys) is refused
+pub fn decode_document(value: &[u8], format: ValueFormat) -> Result<serde_json::Value> {
+    match format {
+        ValueFormat::Json => serde_json::from_slice(value)
+            .map_err(|e| Error::InvalidValue(format!("value is not valid JSON: {}", e))),
+        ValueFormat::MessagePack => rmp_serde::from_slice(value)
+            .map_err(|e| Error::InvalidValue(format!("value is not valid MessagePack: {}", e))),
+        ValueFormat::Raw => Err(raw_document()),
+    }
+}
+
+/// Encode a document as JSON or MessagePack
+pub fn encode_document(document: &serde_json::Value, format: ValueFormat) -> Result<Vec<u8>> {
+    match format {
+        ValueFormat::Json => {
+            serde_json::to_vec(document).map_err(|e| Error::Internal(e.to_string()))
+        }
+        ValueFormat::MessagePack => {
+            rmp_serde::to_vec(document).map_err(|e| Error::Internal(e.to_string()))
+        }
+        ValueFormat::Raw => Err(raw_document()),
+    }
+}
+
+/// Re-encode a value sent or stored in `from` as `to`, raw values on either side are passed on
+/// as they are
+pub fn transcode(value: Bytes, from: ValueFormat, to: ValueFormat) -> Result<Bytes> {
+    if from == to || from == ValueFormat::Raw || to == ValueFormat::Raw {
+        return Ok(value);
+    }
+    let document = decode_document(&value, from)?;
+    encode_document(&document, to).map(Bytes::from)
+}
+
+fn raw_document() -> Error {
+    Error::Internal("raw values are not documents".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcode_between_formats() {
+        let json = Bytes::from_static(br#"{"name":"carbon","tags":[1,2.5,null]}"#);
+        let packed = transcode(json.clone(), ValueFormat::Json, ValueFormat::MessagePack).unwrap();
+        assert_ne!(packed, json);
+        assert_eq!(
+            decode_document(&packed, ValueFormat::MessagePack).unwrap(),
+            decode_document(&json, ValueFormat::Json).unwrap()
+        );
+        let back = transcode(packed, ValueFormat::MessagePack, ValueFormat::Json).unwrap();
+        assert_eq!(back, json);
+
+        // Raw values are not looked at
+        let raw = Bytes::from_static(b"\xff not json");
+        assert_eq!(
+            transcode(raw.clone(), ValueFormat::Raw, ValueFormat::Json).unwrap(),
+            raw
+        );
+        assert!(matches!(
+            transcode(raw, ValueFormat::Json, ValueFormat::MessagePack),
+            Err(Error::InvalidValue(_))
+        ));
+
+        // Binary MessagePack has no JSON form
+        let binary = rmp_serde::to_vec(&serde_bytes::ByteBuf::from(vec![1u8, 2])).unwrap();
+        assert!(decode_document(&binary, ValueFormat::MessagePack).is_err());
+    }
+}
@@ -0,0 +1,47 @@
+use crate::ports::EventPublisher;
+use async_trait::async_trait;
+use rdkafka::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+use shared::{Error, Result};
+use std::time::Duration;
+
+/// How long a message may wait for delivery, retries included
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Publishes events to Kafka topics, keyed by entry so a partition keeps each key in order
+pub struct KafkaPublisher {
+    producer: FutureProducer,
+}
+
+impl KafkaPublisher {
+    /// Producer for the comma-separated bootstrap `brokers`, connects in the background
+    pub fn new(brokers: &str) -> Result<Self> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("enable.idempotence", "true")
+            .set(
+                "message.timeout.ms",
+                DELIVERY_TIMEOUT.as_millis().to_string(),
+            )
+            .create()
+            .map_err(|e| Error::InvalidValue(format!("Invalid Kafka producer settings: {}", e)))?;
+        Ok(Self { producer })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for KafkaPublisher {
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+
+    async fn publish(&self, topic: &str, key: &[u8], payload: Vec<u8>) -> Result<()> {
+        let record = FutureRecord::to(topic).key(key).payload(&payload);
+        self.producer
+            .send(record, Timeout::After(DELIVERY_TIMEOUT))
+            .await
+            .map(|_| ())
+            .map_err(|(e, _)| Error::Internal(format!("Kafka delivery failed: {}", e)))
+    }
+}
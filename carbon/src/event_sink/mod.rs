@@ -0,0 +1,259 @@
+//! Forwarding of cache events from the broadcast channel to a message broker
+
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "nats")]
+mod nats;
+
+#[cfg(feature = "kafka")]
+pub use kafka::KafkaPublisher;
+#[cfg(feature = "nats")]
+pub use nats::NatsPublisher;
+
+use crate::events::CacheItemEvent;
+use crate::ports::EventPublisher;
+use dashmap::DashMap;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Events being published at once, delivery is awaited without holding up the next ones
+const MAX_IN_FLIGHT: usize = 64;
+
+#[derive(Default)]
+struct TopicCounters {
+    published: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Delivery counters of an event sink, by topic
+#[derive(Default)]
+pub struct EventSinkMetrics {
+    topics: DashMap<String, TopicCounters>,
+    lagged: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+impl EventSinkMetrics {
+    fn record(&self, topic: &str, result: &shared::Result<()>) {
+        let counters = match self.topics.get(topic) {
+            Some(counters) => counters,
+            None => self
+                .topics
+                .entry(topic.to_string())
+                .or_default()
+                .downgrade(),
+        };
+        match result {
+            Ok(()) => counters.published.fetch_add(1, Ordering::Relaxed),
+            Err(e) => {
+                *self.last_error.lock().unwrap() = Some(e.to_string());
+                counters.failed.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+    }
+
+    fn record_lagged(&self, missed: u64) {
+        self.lagged.fetch_add(missed, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TopicDeliveryStats {
+    pub topic: String,
+    pub published: u64,
+    pub failed: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventSinkStats {
+    pub sink: &'static str,
+    /// By topic name
+    pub topics: Vec<TopicDeliveryStats>,
+    /// Events dropped because the sink fell behind the broadcast channel
+    pub lagged: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Publishes every cache event to a topic of its cache, or to the default topic
+pub struct EventBridge {
+    publisher: Arc<dyn EventPublisher>,
+    default_topic: String,
+    cache_topics: HashMap<String, String>,
+    metrics: EventSinkMetrics,
+}
+
+impl EventBridge {
+    pub fn new(publisher: Arc<dyn EventPublisher>, default_topic: impl Into<String>) -> Self {
+        Self {
+            publisher,
+            default_topic: default_topic.into(),
+            cache_topics: HashMap::new(),
+            metrics: EventSinkMetrics::default(),
+        }
+    }
+
+    /// Builder method to route the events of some caches to their own topics
+    pub fn with_cache_topics(
+        mut self,
+        cache_topics: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        self.cache_topics = cache_topics.into_iter().collect();
+        self
+    }
+
+    /// Topic the events of `cache_name` are published to
+    pub fn topic(&self, cache_name: &str) -> &str {
+        self.cache_topics
+            .get(cache_name)
+            .unwrap_or(&self.default_topic)
+    }
+
+    pub fn stats(&self) -> EventSinkStats {
+        let mut topics: Vec<TopicDeliveryStats> = self
+            .metrics
+            .topics
+            .iter()
+            .map(|entry| TopicDeliveryStats {
+                topic: entry.key().clone(),
+                published: entry.published.load(Ordering::Relaxed),
+                failed: entry.failed.load(Ordering::Relaxed),
+            })
+            .collect();
+        topics.sort_by(|a, b| a.topic.cmp(&b.topic));
+        EventSinkStats {
+            sink: self.publisher.name(),
+            topics,
+            lagged: self.metrics.lagged.load(Ordering::Relaxed),
+            last_error: self.metrics.last_error.lock().unwrap().clone(),
+        }
+    }
+
+    /// Forward events from `receiver` until the channel closes
+    /// Subscribe before the server starts writing so no event is missed
+    pub async fn run(self: Arc<Self>, receiver: broadcast::Receiver<CacheItemEvent>) {
+        let bridge = self.clone();
+        let events = stream::unfold(receiver, move |mut receiver| {
+            let bridge = bridge.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) => return Some((event, receiver)),
+                        Err(RecvError::Lagged(missed)) => {
+                            tracing::warn!("Event sink fell behind, {} events dropped", missed);
+                            bridge.metrics.record_lagged(missed);
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+
+        events
+            .map(|event| {
+                let bridge = self.clone();
+                async move { bridge.forward(event).await }
+            })
+            .buffered(MAX_IN_FLIGHT)
+            .for_each(|()| std::future::ready(()))
+            .await;
+    }
+
+    async fn forward(&self, event: CacheItemEvent) {
+        let topic = self.topic(event.cache_name());
+        // Events about many entries have no key of their own
+        let key = match event.key() {
+            [] => event.cache_name().as_bytes(),
+            key => key,
+        };
+        let result = match serde_json::to_vec(&event) {
+            Ok(payload) => self.publisher.publish(topic, key, payload).await,
+            Err(e) => Err(shared::Error::Internal(format!(
+                "Event encoding failed: {}",
+                e
+            ))),
+        };
+        if let Err(ref e) = result {
+            tracing::warn!("Failed to publish event to {}: {}", topic, e);
+        }
+        self.metrics.record(topic, &result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{ItemDeletedEvent, now_timestamp};
+    use async_trait::async_trait;
+
+    #[derive(Default)]
+    struct RecordingPublisher {
+        published: Mutex<Vec<(String, Vec<u8>)>>,
+    }
+
+    #[async_trait]
+    impl EventPublisher for RecordingPublisher {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+
+        async fn publish(&self, topic: &str, key: &[u8], _payload: Vec<u8>) -> shared::Result<()> {
+            if topic == "broken" {
+                return Err(shared::Error::Internal("broker unavailable".to_string()));
+            }
+            self.published
+                .lock()
+                .unwrap()
+                .push((topic.to_string(), key.to_vec()));
+            Ok(())
+        }
+    }
+
+    fn deleted(cache_name: &str, key: &[u8]) -> CacheItemEvent {
+        CacheItemEvent::Deleted(ItemDeletedEvent {
+            cache_name: cache_name.to_string(),
+            key: key.to_vec(),
+            timestamp: now_timestamp(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_events_are_routed_by_cache() {
+        let publisher = Arc::new(RecordingPublisher::default());
+        let bridge = Arc::new(
+            EventBridge::new(publisher.clone(), "carbon.events").with_cache_topics([
+                ("orders".to_string(), "cache.orders".to_string()),
+                ("audit".to_string(), "broken".to_string()),
+            ]),
+        );
+        let (sender, receiver) = broadcast::channel(16);
+        let running = tokio::spawn(bridge.clone().run(receiver));
+
+        sender.send(deleted("orders", b"42")).unwrap();
+        sender.send(deleted("users", b"alice")).unwrap();
+        sender.send(deleted("audit", b"1")).unwrap();
+        drop(sender);
+        running.await.unwrap();
+
+        assert_eq!(
+            *publisher.published.lock().unwrap(),
+            vec![
+                ("cache.orders".to_string(), b"42".to_vec()),
+                ("carbon.events".to_string(), b"alice".to_vec()),
+            ]
+        );
+        let stats = bridge.stats();
+        assert_eq!(stats.topics.len(), 3);
+        assert_eq!(stats.topics[0].topic, "broken");
+        assert_eq!(stats.topics[0].failed, 1);
+        assert_eq!(stats.topics[1].published, 1);
+        assert_eq!(
+            stats.last_error.as_deref(),
+            Some("internal: broker unavailable")
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use crate::ports::EventPublisher;
+use async_trait::async_trait;
+use shared::{Error, Result};
+
+/// Publishes events to NATS subjects, core NATS delivers to whoever is subscribed at the time
+pub struct NatsPublisher {
+    client: async_nats::Client,
+}
+
+impl NatsPublisher {
+    /// Connect to the server at `url`, reconnecting on its own after a disconnect
+    pub async fn connect(url: &str) -> Result<Self> {
+        let client = async_nats::connect(url)
+            .await
+            .map_err(|e| Error::Internal(format!("Failed to connect to NATS at {}: {}", url, e)))?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for NatsPublisher {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    /// Subjects are ordered as a whole, so the key is not needed
+    async fn publish(&self, topic: &str, _key: &[u8], payload: Vec<u8>) -> Result<()> {
+        self.client
+            .publish(topic.to_string(), payload.into())
+            .await
+            .map_err(|e| Error::Internal(format!("NATS publish failed: {}", e)))
+    }
+}
@@ -7,6 +7,10 @@ pub enum CacheItemEvent {
     Added(ItemAddedEvent),
     Updated(ItemUpdatedEvent),
     Deleted(ItemDeletedEvent),
+    Expired(ItemExpiredEvent),
+    BulkLoaded(BulkLoadedEvent),
+    CloneProgress(CloneProgressEvent),
+    Renamed(CacheRenamedEvent),
 }
 
 impl CacheItemEvent {
@@ -15,14 +19,23 @@ impl CacheItemEvent {
             CacheItemEvent::Added(e) => &e.cache_name,
             CacheItemEvent::Updated(e) => &e.cache_name,
             CacheItemEvent::Deleted(e) => &e.cache_name,
+            CacheItemEvent::Expired(e) => &e.cache_name,
+            CacheItemEvent::BulkLoaded(e) => &e.cache_name,
+            CacheItemEvent::CloneProgress(e) => &e.cache_name,
+            CacheItemEvent::Renamed(e) => &e.cache_name,
         }
     }
 
+    /// Empty for events covering many entries
     pub fn key(&self) -> &[u8] {
         match self {
             CacheItemEvent::Added(e) => &e.key,
             CacheItemEvent::Updated(e) => &e.key,
             CacheItemEvent::Deleted(e) => &e.key,
+            CacheItemEvent::Expired(e) => &e.key,
+            CacheItemEvent::BulkLoaded(_)
+            | CacheItemEvent::CloneProgress(_)
+            | CacheItemEvent::Renamed(_) => &[],
         }
     }
 }
@@ -32,8 +45,12 @@ pub struct ItemAddedEvent {
     pub cache_name: String,
     #[serde(with = "serde_bytes")]
     pub key: Vec<u8>,
+    /// Empty when the cache's event policy leaves values of this size out
     #[serde(with = "serde_bytes")]
     pub value: Vec<u8>,
+    /// Size of the stored value, whether or not it is included
+    #[serde(default)]
+    pub value_size: u64,
     pub timestamp: u64,
 }
 
@@ -42,8 +59,12 @@ pub struct ItemUpdatedEvent {
     pub cache_name: String,
     #[serde(with = "serde_bytes")]
     pub key: Vec<u8>,
+    /// Empty when the cache's event policy leaves values of this size out
     #[serde(with = "serde_bytes")]
     pub value: Vec<u8>,
+    /// Size of the stored value, whether or not it is included
+    #[serde(default)]
+    pub value_size: u64,
     pub timestamp: u64,
 }
 
@@ -55,6 +76,45 @@ pub struct ItemDeletedEvent {
     pub timestamp: u64,
 }
 
+/// An entry outlived its TTL and was removed by the background reaper
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemExpiredEvent {
+    pub cache_name: String,
+    #[serde(with = "serde_bytes")]
+    pub key: Vec<u8>,
+    pub timestamp: u64,
+}
+
+/// A bulk load finished, sent once in place of an event per entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkLoadedEvent {
+    pub cache_name: String,
+    pub loaded: u64,
+    pub timestamp: u64,
+}
+
+/// Entries copied so far into a clone of `source`, sent after each batch and once more when
+/// the copy is `done`, with the `error` that stopped it if any
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloneProgressEvent {
+    pub cache_name: String,
+    pub source: String,
+    pub copied: u64,
+    pub total: u64,
+    pub done: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub timestamp: u64,
+}
+
+/// The cache formerly called `previous_name` now goes by `cache_name`, with its entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheRenamedEvent {
+    pub cache_name: String,
+    pub previous_name: String,
+    pub timestamp: u64,
+}
+
 /// Helper to get current timestamp in seconds since UNIX epoch
 pub fn now_timestamp() -> u64 {
     SystemTime::now()
@@ -1,6 +1,11 @@
 pub mod auth;
 pub mod domain;
+pub mod embedded;
+pub mod encoding;
+pub mod event_sink;
 pub mod events;
 pub mod persistence;
 pub mod planes;
 pub mod ports;
+
+pub use embedded::CarbonInstance;
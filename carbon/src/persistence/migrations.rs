@@ -0,0 +1,201 @@
+use crate::domain::{CacheConfig, DroppedCache};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use shared::{Error, Result};
+
+/// Version of the CacheConfig schema written by this build
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Upgrades a stored CacheConfig from the version before the one it is listed under
+type Migration = fn(&mut Map<String, Value>);
+
+/// Migrations in order, the one at index `n` takes a config from version `n` to `n + 1`
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [fill_policy];
+
+/// Version 0 is the bare JSON written before records were versioned, when `policy` could be
+/// left out
+fn fill_policy(config: &mut Map<String, Value>) {
+    config
+        .entry("policy")
+        .or_insert_with(|| Value::String("Unspecified".to_string()));
+}
+
+/// A stored record as this build reads it, with what loading it changed
+pub struct Migrated<T> {
+    pub record: T,
+    /// Version the record was stored with
+    pub from_version: u32,
+    /// Top-level config fields the migrations added, removed or changed
+    pub changed_fields: Vec<String>,
+}
+
+impl<T> Migrated<T> {
+    /// Whether the stored bytes differ from what `encode` writes for the record
+    pub fn is_outdated(&self) -> bool {
+        self.from_version < CONFIG_VERSION
+    }
+}
+
+/// Stored records that are not in the current schema, as `SledPersistence::migrate` found them
+#[derive(Debug, Serialize)]
+pub struct MigrationReport {
+    pub to_version: u32,
+    /// Whether the records were rewritten, or the report only says what would change
+    pub applied: bool,
+    pub records: Vec<RecordMigration>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordMigration {
+    pub name: String,
+    /// The record of a dropped cache awaiting purge
+    pub dropped: bool,
+    pub from_version: u32,
+    pub changed_fields: Vec<String>,
+    /// Why the record cannot be read, it is left as it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RecordMigration {
+    /// What loading the record stored under `key` does, None when it is already current
+    pub(crate) fn of<T>(key: &[u8], dropped: bool, migrated: &Result<Migrated<T>>) -> Option<Self> {
+        let name = String::from_utf8_lossy(key).into_owned();
+        match migrated {
+            Ok(migrated) if !migrated.is_outdated() => None,
+            Ok(migrated) => Some(Self {
+                name,
+                dropped,
+                from_version: migrated.from_version,
+                changed_fields: migrated.changed_fields.clone(),
+                error: None,
+            }),
+            Err(e) => Some(Self {
+                name,
+                dropped,
+                from_version: 0,
+                changed_fields: Vec::new(),
+                error: Some(e.to_string()),
+            }),
+        }
+    }
+}
+
+/// Wrap a record in the envelope stored since version 1, `{"version": 1, "data": ...}`
+pub fn encode<T: Serialize>(record: &T) -> Result<Vec<u8>> {
+    let data = serde_json::to_value(record)
+        .map_err(|e| Error::Internal(format!("Failed to serialize config: {}", e)))?;
+    let mut envelope = Map::new();
+    envelope.insert("version".to_string(), Value::from(CONFIG_VERSION));
+    envelope.insert("data".to_string(), data);
+    serde_json::to_vec(&envelope)
+        .map_err(|e| Error::Internal(format!("Failed to serialize config: {}", e)))
+}
+
+/// Read a stored CacheConfig, migrating it to CONFIG_VERSION
+pub fn decode_config(bytes: &[u8]) -> Result<Migrated<CacheConfig>> {
+    decode(bytes, "")
+}
+
+/// Read a stored dropped cache, migrating the config it holds to CONFIG_VERSION
+pub fn decode_dropped(bytes: &[u8]) -> Result<Migrated<DroppedCache>> {
+    decode(bytes, "/config")
+}
+
+/// `config_pointer` is the JSON pointer to the CacheConfig within the record
+fn decode<T: DeserializeOwned>(bytes: &[u8], config_pointer: &str) -> Result<Migrated<T>> {
+    let value: Value = serde_json::from_slice(bytes)
+        .map_err(|e| Error::Internal(format!("Failed to deserialize config: {}", e)))?;
+    let (from_version, mut data) = open_envelope(value)?;
+    if from_version > CONFIG_VERSION {
+        return Err(Error::Internal(format!(
+            "Config was stored with schema version {}, this build reads up to {}",
+            from_version, CONFIG_VERSION
+        )));
+    }
+
+    let mut changed_fields = Vec::new();
+    if from_version < CONFIG_VERSION {
+        let Some(Value::Object(config)) = data.pointer_mut(config_pointer) else {
+            return Err(Error::Internal(
+                "Failed to deserialize config: not a JSON object".to_string(),
+            ));
+        };
+        let before = config.clone();
+        for migration in &MIGRATIONS[from_version as usize..] {
+            migration(config);
+        }
+        changed_fields = diff_fields(&before, config);
+    }
+
+    let record = serde_json::from_value(data)
+        .map_err(|e| Error::Internal(format!("Failed to deserialize config: {}", e)))?;
+    Ok(Migrated {
+        record,
+        from_version,
+        changed_fields,
+    })
+}
+
+/// Split a record into its version and data, bare records from before the envelope are version 0
+fn open_envelope(value: Value) -> Result<(u32, Value)> {
+    match value {
+        Value::Object(mut envelope)
+            if envelope.len() == 2
+                && envelope.contains_key("data")
+                && envelope.contains_key("version") =>
+        {
+            let version = envelope
+                .get("version")
+                .and_then(Value::as_u64)
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| {
+                    Error::Internal("Failed to deserialize config: invalid version".to_string())
+                })?;
+            Ok((version, envelope.remove("data").unwrap_or_default()))
+        }
+        value => Ok((0, value)),
+    }
+}
+
+fn diff_fields(before: &Map<String, Value>, after: &Map<String, Value>) -> Vec<String> {
+    let mut fields: Vec<String> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|field| before.get(*field) != after.get(*field))
+        .cloned()
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{CacheEvictionStrategy, EvictionAlgorithm};
+
+    #[test]
+    fn test_bare_configs_are_migrated() {
+        let legacy = br#"{"name":"orders","backend":"timebound","mem_bytes":null,"shards":null}"#;
+        let migrated = decode_config(legacy).unwrap();
+        assert_eq!(migrated.from_version, 0);
+        assert!(migrated.is_outdated());
+        assert_eq!(migrated.changed_fields, ["policy"]);
+        assert_eq!(migrated.record.policy, EvictionAlgorithm::Unspecified);
+
+        // Written back in the envelope, nothing is left to migrate
+        let current = decode_config(&encode(&migrated.record).unwrap()).unwrap();
+        assert_eq!(current.from_version, CONFIG_VERSION);
+        assert!(!current.is_outdated());
+        assert!(current.changed_fields.is_empty());
+        assert_eq!(current.record.backend, CacheEvictionStrategy::TimeBound);
+    }
+
+    #[test]
+    fn test_newer_versions_are_refused() {
+        let newer = br#"{"version":99,"data":{"name":"orders"}}"#;
+        assert!(decode_config(newer).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+mod migrations;
 mod sled_store;
 
+pub use migrations::{CONFIG_VERSION, MigrationReport, RecordMigration};
 pub use sled_store::SledPersistence;
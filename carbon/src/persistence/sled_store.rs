@@ -1,10 +1,24 @@
-use crate::domain::CacheConfig;
+use super::migrations::{self, MigrationReport, RecordMigration};
+use crate::domain::{CacheAlias, CacheConfig, DroppedCache, MaterializedView, Schedule};
 use shared::{Error, Result};
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const HEALTH_TREE: &str = "__health";
+const HEALTH_KEY: &[u8] = b"probe";
+/// How long a write check answers for, so probes polling readiness do not each write and flush
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const DROPPED_TREE: &str = "__dropped";
+const ALIASES_TREE: &str = "__aliases";
+const SCHEDULES_TREE: &str = "__schedules";
+const VIEWS_TREE: &str = "__views";
 
 /// Sled-based persistence for cache configurations
 pub struct SledPersistence {
     db: sled::Db,
+    // Outcome of the last write check and when it ran
+    last_check: Mutex<Option<(Instant, std::result::Result<(), String>)>>,
 }
 
 impl SledPersistence {
@@ -20,14 +34,16 @@ impl SledPersistence {
         let db = sled::open(path)
             .map_err(|e| Error::Internal(format!("Failed to open Sled database: {}", e)))?;
 
-        Ok(Self { db })
+        Ok(Self {
+            db,
+            last_check: Mutex::new(None),
+        })
     }
 
     /// Save a cache configuration
     pub fn save_config(&self, config: &CacheConfig) -> Result<()> {
         let key = config.name.as_bytes();
-        let value = serde_json::to_vec(config)
-            .map_err(|e| Error::Internal(format!("Failed to serialize config: {}", e)))?;
+        let value = migrations::encode(config)?;
 
         self.db
             .insert(key, value)
@@ -48,10 +64,25 @@ impl SledPersistence {
             let (_, value) = result
                 .map_err(|e| Error::Internal(format!("Failed to iterate database: {}", e)))?;
 
-            let config: CacheConfig = serde_json::from_slice(&value)
-                .map_err(|e| Error::Internal(format!("Failed to deserialize config: {}", e)))?;
+            configs.push(migrations::decode_config(&value)?.record);
+        }
+
+        Ok(configs)
+    }
+
+    /// Load every cache configuration on its own, so one that cannot be read does not keep the
+    /// others from loading
+    pub fn load_each(&self) -> Result<Vec<(String, Result<CacheConfig>)>> {
+        let mut configs = Vec::new();
 
-            configs.push(config);
+        for result in self.db.iter() {
+            let (key, value) = result
+                .map_err(|e| Error::Internal(format!("Failed to iterate database: {}", e)))?;
+
+            let name = String::from_utf8_lossy(&key).into_owned();
+            let config = migrations::decode_config(&value).map(|migrated| migrated.record);
+
+            configs.push((name, config));
         }
 
         Ok(configs)
@@ -73,6 +104,263 @@ impl SledPersistence {
         Ok(removed)
     }
 
+    /// Store `config` under its new name and remove it from under `from` in one batch, so a
+    /// crash leaves the cache under one name or the other
+    pub fn rename_config(&self, from: &str, config: &CacheConfig) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        batch.insert(config.name.as_bytes(), migrations::encode(config)?);
+        batch.remove(from.as_bytes());
+
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| Error::Internal(format!("Failed to rename config: {}", e)))?;
+
+        self.db
+            .flush()
+            .map_err(|e| Error::Internal(format!("Failed to flush database: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Move a dropped cache's configuration aside, where `load_all` does not see it
+    pub fn save_dropped(&self, dropped: &DroppedCache) -> Result<()> {
+        let value = migrations::encode(dropped)?;
+
+        self.dropped_tree()?
+            .insert(dropped.config.name.as_bytes(), value)
+            .map_err(|e| Error::Internal(format!("Failed to save dropped config: {}", e)))?;
+        self.delete_config(&dropped.config.name)?;
+
+        Ok(())
+    }
+
+    /// Dropped caches still awaiting purge
+    pub fn load_dropped(&self) -> Result<Vec<DroppedCache>> {
+        let mut dropped = Vec::new();
+
+        for result in self.dropped_tree()?.iter() {
+            let (_, value) = result
+                .map_err(|e| Error::Internal(format!("Failed to iterate database: {}", e)))?;
+
+            dropped.push(migrations::decode_dropped(&value)?.record);
+        }
+
+        Ok(dropped)
+    }
+
+    /// Forget a dropped cache's configuration, once it is restored or purged
+    pub fn delete_dropped(&self, name: &str) -> Result<bool> {
+        let dropped = self.dropped_tree()?;
+        let removed = dropped
+            .remove(name.as_bytes())
+            .map_err(|e| Error::Internal(format!("Failed to delete dropped config: {}", e)))?
+            .is_some();
+
+        dropped
+            .flush()
+            .map_err(|e| Error::Internal(format!("Failed to flush database: {}", e)))?;
+
+        Ok(removed)
+    }
+
+    fn dropped_tree(&self) -> Result<sled::Tree> {
+        self.db
+            .open_tree(DROPPED_TREE)
+            .map_err(|e| Error::Internal(format!("Failed to open dropped tree: {}", e)))
+    }
+
+    /// Point `alias.alias` at `alias.cache`, replacing where it pointed before
+    pub fn save_alias(&self, alias: &CacheAlias) -> Result<()> {
+        let aliases = self.aliases_tree()?;
+        aliases
+            .insert(alias.alias.as_bytes(), alias.cache.as_bytes())
+            .map_err(|e| Error::Internal(format!("Failed to save alias: {}", e)))?;
+
+        aliases
+            .flush()
+            .map_err(|e| Error::Internal(format!("Failed to flush database: {}", e)))?;
+
+        Ok(())
+    }
+
+    pub fn load_aliases(&self) -> Result<Vec<CacheAlias>> {
+        let mut aliases = Vec::new();
+
+        for result in self.aliases_tree()?.iter() {
+            let (alias, cache) = result
+                .map_err(|e| Error::Internal(format!("Failed to iterate database: {}", e)))?;
+
+            aliases.push(CacheAlias {
+                alias: String::from_utf8_lossy(&alias).into_owned(),
+                cache: String::from_utf8_lossy(&cache).into_owned(),
+            });
+        }
+
+        Ok(aliases)
+    }
+
+    pub fn delete_alias(&self, alias: &str) -> Result<bool> {
+        let aliases = self.aliases_tree()?;
+        let removed = aliases
+            .remove(alias.as_bytes())
+            .map_err(|e| Error::Internal(format!("Failed to delete alias: {}", e)))?
+            .is_some();
+
+        aliases
+            .flush()
+            .map_err(|e| Error::Internal(format!("Failed to flush database: {}", e)))?;
+
+        Ok(removed)
+    }
+
+    fn aliases_tree(&self) -> Result<sled::Tree> {
+        self.db
+            .open_tree(ALIASES_TREE)
+            .map_err(|e| Error::Internal(format!("Failed to open aliases tree: {}", e)))
+    }
+
+    /// Store `schedule` under its id, replacing an earlier version of it
+    pub fn save_schedule(&self, schedule: &Schedule) -> Result<()> {
+        let value = serde_json::to_vec(schedule)
+            .map_err(|e| Error::Internal(format!("Failed to serialize schedule: {}", e)))?;
+
+        let schedules = self.schedules_tree()?;
+        schedules
+            .insert(schedule.id.as_bytes(), value)
+            .map_err(|e| Error::Internal(format!("Failed to save schedule: {}", e)))?;
+
+        schedules
+            .flush()
+            .map_err(|e| Error::Internal(format!("Failed to flush database: {}", e)))?;
+
+        Ok(())
+    }
+
+    pub fn load_schedules(&self) -> Result<Vec<Schedule>> {
+        let mut schedules = Vec::new();
+
+        for result in self.schedules_tree()?.iter() {
+            let (_, value) = result
+                .map_err(|e| Error::Internal(format!("Failed to iterate database: {}", e)))?;
+
+            schedules.push(serde_json::from_slice(&value).map_err(|e| {
+                Error::Internal(format!("Failed to deserialize schedule: {}", e))
+            })?);
+        }
+
+        Ok(schedules)
+    }
+
+    pub fn delete_schedule(&self, id: &str) -> Result<bool> {
+        let schedules = self.schedules_tree()?;
+        let removed = schedules
+            .remove(id.as_bytes())
+            .map_err(|e| Error::Internal(format!("Failed to delete schedule: {}", e)))?
+            .is_some();
+
+        schedules
+            .flush()
+            .map_err(|e| Error::Internal(format!("Failed to flush database: {}", e)))?;
+
+        Ok(removed)
+    }
+
+    fn schedules_tree(&self) -> Result<sled::Tree> {
+        self.db
+            .open_tree(SCHEDULES_TREE)
+            .map_err(|e| Error::Internal(format!("Failed to open schedules tree: {}", e)))
+    }
+
+    pub fn save_view(&self, view: &MaterializedView) -> Result<()> {
+        let value = serde_json::to_vec(view)
+            .map_err(|e| Error::Internal(format!("Failed to serialize view: {}", e)))?;
+
+        let views = self.views_tree()?;
+        views
+            .insert(view.cache.as_bytes(), value)
+            .map_err(|e| Error::Internal(format!("Failed to save view: {}", e)))?;
+
+        views
+            .flush()
+            .map_err(|e| Error::Internal(format!("Failed to flush database: {}", e)))?;
+
+        Ok(())
+    }
+
+    pub fn load_views(&self) -> Result<Vec<MaterializedView>> {
+        let mut views = Vec::new();
+
+        for result in self.views_tree()?.iter() {
+            let (_, value) = result
+                .map_err(|e| Error::Internal(format!("Failed to iterate database: {}", e)))?;
+
+            views.push(
+                serde_json::from_slice(&value)
+                    .map_err(|e| Error::Internal(format!("Failed to deserialize view: {}", e)))?,
+            );
+        }
+
+        Ok(views)
+    }
+
+    pub fn delete_view(&self, cache: &str) -> Result<bool> {
+        let views = self.views_tree()?;
+        let removed = views
+            .remove(cache.as_bytes())
+            .map_err(|e| Error::Internal(format!("Failed to delete view: {}", e)))?
+            .is_some();
+
+        views
+            .flush()
+            .map_err(|e| Error::Internal(format!("Failed to flush database: {}", e)))?;
+
+        Ok(removed)
+    }
+
+    fn views_tree(&self) -> Result<sled::Tree> {
+        self.db
+            .open_tree(VIEWS_TREE)
+            .map_err(|e| Error::Internal(format!("Failed to open views tree: {}", e)))
+    }
+
+    /// Write and remove a marker key to prove the database accepts writes
+    /// The outcome is reused for HEALTH_CHECK_INTERVAL, callers in between wait for a running
+    /// check instead of starting their own
+    pub fn check_writable(&self) -> Result<()> {
+        let mut last_check = self.last_check.lock().unwrap();
+        let outcome = match last_check.as_ref() {
+            Some((checked_at, outcome)) if checked_at.elapsed() < HEALTH_CHECK_INTERVAL => {
+                outcome.clone()
+            }
+            _ => {
+                let outcome = self.write_marker();
+                *last_check = Some((Instant::now(), outcome.clone()));
+                outcome
+            }
+        };
+        outcome.map_err(Error::Internal)
+    }
+
+    /// Uses its own tree so the marker never shows up among the cache configurations
+    fn write_marker(&self) -> std::result::Result<(), String> {
+        let health = self
+            .db
+            .open_tree(HEALTH_TREE)
+            .map_err(|e| format!("Failed to open health tree: {}", e))?;
+
+        health
+            .insert(HEALTH_KEY, &[1u8])
+            .map_err(|e| format!("Failed to write health marker: {}", e))?;
+        health
+            .remove(HEALTH_KEY)
+            .map_err(|e| format!("Failed to remove health marker: {}", e))?;
+        health
+            .flush()
+            .map_err(|e| format!("Failed to flush database: {}", e))?;
+
+        Ok(())
+    }
+
     /// Get a single cache configuration by name
     pub fn get_config(&self, name: &str) -> Result<Option<CacheConfig>> {
         let key = name.as_bytes();
@@ -82,14 +370,67 @@ impl SledPersistence {
             .map_err(|e| Error::Internal(format!("Failed to get config: {}", e)))?;
 
         match value {
-            Some(bytes) => {
-                let config: CacheConfig = serde_json::from_slice(&bytes)
-                    .map_err(|e| Error::Internal(format!("Failed to deserialize config: {}", e)))?;
-                Ok(Some(config))
-            }
+            Some(bytes) => Ok(Some(migrations::decode_config(&bytes)?.record)),
             None => Ok(None),
         }
     }
+
+    /// Report the stored records written with an older schema and how loading migrates them,
+    /// rewriting them in the current schema when `apply` is set
+    /// Records are migrated as they load either way, records that cannot be read are reported
+    /// and left as they are
+    pub fn migrate(&self, apply: bool) -> Result<MigrationReport> {
+        let mut records = Vec::new();
+
+        for result in self.db.iter() {
+            let (key, value) = result
+                .map_err(|e| Error::Internal(format!("Failed to iterate database: {}", e)))?;
+            let migrated = migrations::decode_config(&value);
+            if let Some(record) = RecordMigration::of(&key, false, &migrated) {
+                records.push(record);
+            }
+            if apply
+                && let Ok(migrated) = &migrated
+                && migrated.is_outdated()
+            {
+                self.db
+                    .insert(key, migrations::encode(&migrated.record)?)
+                    .map_err(|e| Error::Internal(format!("Failed to save config: {}", e)))?;
+            }
+        }
+
+        let dropped = self.dropped_tree()?;
+        for result in dropped.iter() {
+            let (key, value) = result
+                .map_err(|e| Error::Internal(format!("Failed to iterate database: {}", e)))?;
+            let migrated = migrations::decode_dropped(&value);
+            if let Some(record) = RecordMigration::of(&key, true, &migrated) {
+                records.push(record);
+            }
+            if apply
+                && let Ok(migrated) = &migrated
+                && migrated.is_outdated()
+            {
+                dropped
+                    .insert(key, migrations::encode(&migrated.record)?)
+                    .map_err(|e| {
+                        Error::Internal(format!("Failed to save dropped config: {}", e))
+                    })?;
+            }
+        }
+
+        if apply {
+            self.db
+                .flush()
+                .map_err(|e| Error::Internal(format!("Failed to flush database: {}", e)))?;
+        }
+
+        Ok(MigrationReport {
+            to_version: migrations::CONFIG_VERSION,
+            applied: apply,
+            records,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -142,4 +483,105 @@ mod tests {
         let loaded_after = persistence.load_all().unwrap();
         assert_eq!(loaded_after.len(), 0);
     }
+
+    #[test]
+    fn test_load_each_keeps_unreadable_configs_apart() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let persistence = SledPersistence::new(temp_dir.path().join("test.sled")).unwrap();
+
+        let config = CacheConfig::new(
+            "orders",
+            Some(1024 * 1024),
+            None,
+            None,
+            EvictionAlgorithm::TinyLfu,
+            None,
+            None,
+            None,
+            None,
+        );
+        persistence.save_config(&config).unwrap();
+        persistence.db.insert("broken", "{not json").unwrap();
+
+        let loaded = persistence.load_each().unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].0, "broken");
+        assert!(loaded[0].1.is_err());
+        assert_eq!(loaded[1].1.as_ref().unwrap().name, "orders");
+        assert!(persistence.load_all().is_err());
+    }
+
+    #[test]
+    fn test_dropped_configs_are_kept_apart() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let persistence = SledPersistence::new(temp_dir.path().join("test.sled")).unwrap();
+
+        let config = CacheConfig::new(
+            "orders",
+            Some(1024 * 1024),
+            None,
+            None,
+            EvictionAlgorithm::TinyLfu,
+            None,
+            None,
+            None,
+            None,
+        );
+        persistence.save_config(&config).unwrap();
+        persistence
+            .save_dropped(&DroppedCache {
+                config,
+                dropped_at_ms: 1,
+                purge_at_ms: 2,
+            })
+            .unwrap();
+
+        assert!(persistence.load_all().unwrap().is_empty());
+        let dropped = persistence.load_dropped().unwrap();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].config.name, "orders");
+        assert_eq!(dropped[0].purge_at_ms, 2);
+
+        assert!(persistence.delete_dropped("orders").unwrap());
+        assert!(persistence.load_dropped().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_writable_leaves_no_config() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let persistence = SledPersistence::new(temp_dir.path().join("test.sled")).unwrap();
+
+        persistence.check_writable().unwrap();
+
+        assert!(persistence.load_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_reports_then_rewrites_old_records() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let persistence = SledPersistence::new(temp_dir.path().join("test.sled")).unwrap();
+
+        // Written before records were versioned, without a policy
+        persistence
+            .db
+            .insert(
+                "orders",
+                br#"{"name":"orders","backend":"timebound","mem_bytes":null,"shards":null}"#
+                    .to_vec(),
+            )
+            .unwrap();
+
+        let report = persistence.migrate(false).unwrap();
+        assert!(!report.applied);
+        assert_eq!(report.records.len(), 1);
+        assert_eq!(report.records[0].name, "orders");
+        assert_eq!(report.records[0].changed_fields, ["policy"]);
+        // Old records load either way, a dry run leaves them as they were
+        assert_eq!(persistence.load_all().unwrap()[0].name, "orders");
+        assert_eq!(persistence.migrate(false).unwrap().records.len(), 1);
+
+        assert_eq!(persistence.migrate(true).unwrap().records.len(), 1);
+        assert!(persistence.migrate(false).unwrap().records.is_empty());
+        assert_eq!(persistence.load_all().unwrap()[0].name, "orders");
+    }
 }
@@ -1,19 +1,41 @@
 use crate::domain::response::admin::CreateCacheResponse;
 
 use crate::domain::response::admin::{
-    DescribeCacheResponse, DropCacheResponse, ListCachesResponse,
+    DescribeCacheResponse, DropCacheResponse, ListCachesResponse, UpdateCacheResponse,
+};
+use crate::domain::{
+    CacheAlias, CacheConfig, CacheConfigUpdate, CacheEvictionStrategy, CacheFilter, CacheInfo,
+    CacheStatus, DroppedCache, EventKeyMode, EventPolicy, FailedCache, MaterializedView, Schedule,
+    ScheduleRun, ScheduledAction, ViewTransform,
 };
-use crate::domain::{CacheConfig, CacheInfo};
 use crate::persistence::SledPersistence;
 use crate::planes::control::operation::AdminOperations;
-use crate::ports::{CacheStore, StorageFactory};
+use crate::planes::control::response_cache::AdminResponseCache;
+use crate::planes::control::schedules::CronExpr;
+use crate::planes::data::content_types::ContentTypes;
+use crate::planes::data::tag_index::TagIndex;
+use crate::ports::{CacheStore, StorageFactory, ViewTransformation};
 use async_trait::async_trait;
 use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use futures::stream::{self, StreamExt};
 use shared::Result;
+use std::borrow::Cow;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::panic::AssertUnwindSafe;
 use std::path::Path;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::runtime::Handle;
+
+/// How long a backend has to answer when a cache is recreated or retried
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Caches recreated at once on startup, so unreachable backends cost one PING_TIMEOUT per batch
+/// rather than one each
+const LOAD_CONCURRENCY: usize = 32;
 
 /// Entry containing both cache configuration and storage implementation
 pub struct CacheMetadata<K, V>
@@ -21,8 +43,21 @@ where
     K: Debug + Hash + Eq + Send + Sync + 'static,
     V: Debug + Send + Sync + 'static,
 {
-    pub config: CacheConfig,
+    pub config: Arc<CacheConfig>,
     pub store: Arc<dyn CacheStore<K, V>>,
+    /// Expired entries removed by the background reaper
+    pub reaped_entries: AtomicU64,
+    pub status: CacheStatus,
+}
+
+/// Dropped cache kept with its store for the drop retention window
+struct DroppedEntry<K, V>
+where
+    K: Debug + Hash + Eq + Send + Sync + 'static,
+    V: Debug + Send + Sync + 'static,
+{
+    dropped: DroppedCache,
+    metadata: CacheMetadata<K, V>,
 }
 
 /// CacheManager orchestrates cache operations using injected storage implementations
@@ -37,6 +72,30 @@ where
     cache_registry: Arc<DashMap<String, CacheMetadata<K, V>>>,
     // Optional persistence layer for cache configurations
     persistence: Option<Arc<SledPersistence>>,
+    // Entry tags, shared by every frontend operating on these caches
+    tag_index: Arc<TagIndex<K>>,
+    // Media types entries were uploaded with, shared the same way
+    content_types: Arc<ContentTypes<K>>,
+    // Runtime whose blocking pool takes configuration store writes, None runs them inline
+    storage_runtime: Option<Handle>,
+    // ListCaches and DescribeCache responses, cleared by every create, drop and update
+    responses: Arc<AdminResponseCache>,
+    // Dropped caches that can still be restored, by name
+    dropped: Arc<DashMap<String, DroppedEntry<K, V>>>,
+    // How long dropped caches are kept, zero purges them right away
+    drop_retention: Duration,
+    // Builds the stores of caches recreated from persistence, kept to retry those that failed
+    factory: Option<Arc<dyn StorageFactory<K, V>>>,
+    // Stored caches that could not be recreated, by name
+    failed: Arc<DashMap<String, FailedCache>>,
+    // Alias -> name of the cache data operations on it reach
+    aliases: Arc<DashMap<String, String>>,
+    // Scheduled actions by id, run by spawn_scheduler
+    schedules: Arc<DashMap<String, Schedule>>,
+    // Materialized views by the name of their cache, kept up to date by spawn_view_maintenance
+    views: Arc<DashMap<String, MaterializedView>>,
+    // Transformations views can name, registered by the embedding process
+    view_transformations: Arc<DashMap<String, Arc<dyn ViewTransformation>>>,
 }
 
 impl<K, V> Debug for CacheManager<K, V>
@@ -61,6 +120,18 @@ where
         Self {
             cache_registry: Arc::new(DashMap::new()),
             persistence: None,
+            tag_index: Arc::new(TagIndex::new()),
+            content_types: Arc::new(ContentTypes::new()),
+            storage_runtime: None,
+            responses: Arc::new(AdminResponseCache::default()),
+            dropped: Arc::new(DashMap::new()),
+            drop_retention: Duration::ZERO,
+            factory: None,
+            failed: Arc::new(DashMap::new()),
+            aliases: Arc::new(DashMap::new()),
+            schedules: Arc::new(DashMap::new()),
+            views: Arc::new(DashMap::new()),
+            view_transformations: Arc::new(DashMap::new()),
         }
     }
 
@@ -72,31 +143,775 @@ where
     ) -> Result<Self> {
         let persistence = SledPersistence::new(persistence_path)?;
 
-        // Load all configs from persistence
-        let configs = persistence.load_all()?;
+        // Older records are migrated in memory as they load, rewriting them is left to the operator
+        let outdated = persistence.migrate(false)?.records.len();
+        if outdated > 0 {
+            tracing::info!(
+                "{} stored cache configurations use an older schema, `carbon-server --migrate` shows what loading changes",
+                outdated
+            );
+        }
+
+        // Load all configs from persistence, each on its own so a bad one only fails itself
+        let configs = persistence.load_each()?;
+        let dropped = persistence.load_dropped()?;
+        let aliases = persistence.load_aliases()?;
+        let schedules = persistence.load_schedules()?;
+        let views = persistence.load_views()?;
 
         // Create manager
         let manager = Self {
             cache_registry: Arc::new(DashMap::new()),
             persistence: Some(Arc::new(persistence)),
+            tag_index: Arc::new(TagIndex::new()),
+            content_types: Arc::new(ContentTypes::new()),
+            storage_runtime: None,
+            responses: Arc::new(AdminResponseCache::default()),
+            dropped: Arc::new(DashMap::new()),
+            drop_retention: Duration::ZERO,
+            factory: Some(factory.clone()),
+            failed: Arc::new(DashMap::new()),
+            aliases: Arc::new(DashMap::new()),
+            schedules: Arc::new(DashMap::new()),
+            views: Arc::new(DashMap::new()),
+            view_transformations: Arc::new(DashMap::new()),
         };
 
-        // Eagerly recreate all caches from configs (Option B)
-        for config in configs {
-            let store = factory.create_from_config(&config);
-            let cache_name = config.name.clone();
-            let entry = CacheMetadata { config, store };
-            manager.cache_registry.insert(cache_name, entry);
+        // Eagerly recreate all caches from configs (Option B), pinging their backends concurrently
+        // A cache that cannot be recreated is reported and kept aside for a retry
+        let mut valid = Vec::new();
+        for (cache_name, config) in configs {
+            match config {
+                Ok(config) => valid.push((cache_name, config)),
+                Err(e) => manager.record_failed(cache_name, e.to_string(), None),
+            }
+        }
+        let mut loads = stream::iter(valid)
+            .map(|(cache_name, config)| {
+                let factory = factory.clone();
+                async move {
+                    let loaded = load_cache(factory.as_ref(), config.clone()).await;
+                    (cache_name, config, loaded)
+                }
+            })
+            .buffer_unordered(LOAD_CONCURRENCY);
+
+        let (mut ready, mut degraded) = (0, 0);
+        while let Some((cache_name, config, loaded)) = loads.next().await {
+            match loaded {
+                Ok(entry) => {
+                    if let CacheStatus::Degraded { reason } = &entry.status {
+                        tracing::warn!("Cache '{}' is degraded: {}", cache_name, reason);
+                        degraded += 1;
+                    } else {
+                        ready += 1;
+                    }
+                    manager.cache_registry.insert(cache_name, entry);
+                }
+                Err(reason) => manager.record_failed(cache_name, reason, Some(config)),
+            }
+        }
+        tracing::info!(
+            "Recovered caches: {} ready, {} degraded, {} failed to load",
+            ready,
+            degraded,
+            manager.failed.len()
+        );
+
+        // Dropped caches stay restorable across restarts, those past their window are purged
+        // by the next purge_expired
+        for dropped in dropped {
+            let store = factory.create_from_config(&dropped.config);
+            let cache_name = dropped.config.name.clone();
+            let metadata = CacheMetadata {
+                config: Arc::new(dropped.config.clone()),
+                store,
+                reaped_entries: AtomicU64::new(0),
+                status: CacheStatus::Ready,
+            };
+            manager
+                .dropped
+                .insert(cache_name, DroppedEntry { dropped, metadata });
+        }
+
+        for alias in aliases {
+            manager.aliases.insert(alias.alias, alias.cache);
+        }
+
+        // Runs missed while the server was down are skipped
+        let now = now_millis();
+        for mut schedule in schedules {
+            schedule.next_run_ms = CronExpr::parse(&schedule.cron)
+                .ok()
+                .and_then(|cron| cron.next_after_ms(now));
+            manager.schedules.insert(schedule.id.clone(), schedule);
+        }
+
+        for view in views {
+            manager.views.insert(view.cache.clone(), view);
         }
 
         Ok(manager)
     }
 
-    /// Get a cache store by name
+    fn record_failed(&self, name: String, reason: String, config: Option<CacheConfig>) {
+        tracing::warn!("Cache '{}' failed to load: {}", name, reason);
+        self.failed.insert(
+            name.clone(),
+            FailedCache {
+                name,
+                reason,
+                config,
+            },
+        );
+    }
+
+    /// Flush configuration changes on `runtime`, off the workers serving requests
+    pub fn with_storage_runtime(mut self, runtime: Handle) -> Self {
+        self.storage_runtime = Some(runtime);
+        self
+    }
+
+    /// Reuse ListCaches and DescribeCache responses for up to `max_age`, zero disables it
+    pub fn with_admin_response_max_age(mut self, max_age: Duration) -> Self {
+        self.responses = Arc::new(AdminResponseCache::new(max_age));
+        self
+    }
+
+    /// Keep dropped caches restorable for `retention` before purging them, zero purges at once
+    pub fn with_drop_retention(mut self, retention: Duration) -> Self {
+        self.drop_retention = retention;
+        self
+    }
+
+    /// Run a configuration store write, None when running in-memory
+    async fn persist<T, F>(&self, write: F) -> Result<Option<T>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&SledPersistence) -> Result<T> + Send + 'static,
+    {
+        let Some(persistence) = self.persistence.clone() else {
+            return Ok(None);
+        };
+
+        match &self.storage_runtime {
+            Some(runtime) => runtime
+                .spawn_blocking(move || write(&persistence))
+                .await
+                .map_err(|e| shared::Error::Internal(format!("Storage task failed: {}", e)))?
+                .map(Some),
+            None => write(&persistence).map(Some),
+        }
+    }
+
+    /// Get a cache store by name or alias
     pub async fn get_cache_store(&self, name: &str) -> Option<Arc<dyn CacheStore<K, V>>> {
         self.cache_registry
+            .get(self.resolve(name).as_ref())
+            .map(|entry| entry.store.clone())
+    }
+
+    pub(crate) fn tag_index(&self) -> &TagIndex<K> {
+        &self.tag_index
+    }
+
+    pub(crate) fn content_types(&self) -> &ContentTypes<K> {
+        &self.content_types
+    }
+
+    /// Verify the configuration store accepts writes, None when running in-memory
+    pub fn check_persistence(&self) -> Option<Result<()>> {
+        self.persistence
+            .as_ref()
+            .map(|persistence| persistence.check_writable())
+    }
+
+    /// Get a cache store by name or alias together with its configuration
+    pub async fn get_cache(
+        &self,
+        name: &str,
+    ) -> Option<(Arc<dyn CacheStore<K, V>>, Arc<CacheConfig>)> {
+        self.cache_registry
+            .get(self.resolve(name).as_ref())
+            .map(|entry| (entry.store.clone(), entry.config.clone()))
+    }
+
+    /// Name of the cache `name` points to when it is an alias, `name` itself otherwise
+    pub(crate) fn resolve<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match self.aliases.get(name) {
+            Some(target) => Cow::Owned(target.clone()),
+            None => Cow::Borrowed(name),
+        }
+    }
+
+    /// Cache an alias points to, None when `name` is not an alias
+    pub fn alias_target(&self, name: &str) -> Option<String> {
+        self.aliases.get(name).map(|target| target.clone())
+    }
+
+    /// Point `alias` at the live cache `cache`, creating the alias or repointing it in one step,
+    /// and return the cache it pointed to before
+    pub async fn set_alias(&self, alias: &str, cache: &str) -> Result<Option<String>> {
+        if alias.is_empty()
+            || !alias
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(shared::Error::InvalidValue(
+                "alias must contain only alphanumeric characters, hyphens, or underscores"
+                    .to_string(),
+            ));
+        }
+        if self.cache_registry.contains_key(alias)
+            || self.dropped.contains_key(alias)
+            || self.failed.contains_key(alias)
+        {
+            return Err(shared::Error::InvalidValue(format!(
+                "'{}' is the name of a cache",
+                alias
+            )));
+        }
+        if self.aliases.contains_key(cache) {
+            return Err(shared::Error::InvalidValue(format!(
+                "'{}' is an alias, point '{}' at a cache",
+                cache, alias
+            )));
+        }
+        if !self.cache_registry.contains_key(cache) {
+            return Err(shared::Error::CacheNotFound(cache.to_string()));
+        }
+
+        let persisted = CacheAlias {
+            alias: alias.to_string(),
+            cache: cache.to_string(),
+        };
+        self.persist(move |persistence| persistence.save_alias(&persisted))
+            .await?;
+
+        Ok(self.aliases.insert(alias.to_string(), cache.to_string()))
+    }
+
+    /// Remove an alias, the cache it pointed to is left as it is
+    pub async fn remove_alias(&self, alias: &str) -> Result<bool> {
+        if self.aliases.remove(alias).is_none() {
+            return Ok(false);
+        }
+
+        let persisted = alias.to_string();
+        self.persist(move |persistence| persistence.delete_alias(&persisted))
+            .await?;
+        Ok(true)
+    }
+
+    /// Configuration for a new cache `name` with the settings of `source`, or of the cache it
+    /// is an alias of, and `overrides` applied on top
+    pub async fn clone_config(
+        &self,
+        source: &str,
+        name: &str,
+        overrides: CacheConfigUpdate,
+    ) -> Result<CacheConfig> {
+        let (_, config) = self
+            .get_cache(source)
+            .await
+            .ok_or_else(|| shared::Error::CacheNotFound(source.to_string()))?;
+        let mut config = CacheConfig::clone(&config);
+        config.name = name.to_string();
+        overrides.apply_to(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Every alias with the cache it points to, sorted by alias
+    pub fn aliases(&self) -> Vec<CacheAlias> {
+        let mut aliases: Vec<CacheAlias> = self
+            .aliases
+            .iter()
+            .map(|entry| CacheAlias {
+                alias: entry.key().clone(),
+                cache: entry.value().clone(),
+            })
+            .collect();
+        aliases.sort_by(|a, b| a.alias.cmp(&b.alias));
+        aliases
+    }
+
+    /// Run `action` on `cache` whenever `cron` matches, from the next match on
+    pub async fn add_schedule(
+        &self,
+        cache: &str,
+        cron: &str,
+        action: ScheduledAction,
+    ) -> Result<Schedule> {
+        let next_run_ms = CronExpr::parse(cron)?.next_after_ms(now_millis());
+        if next_run_ms.is_none() {
+            return Err(shared::Error::InvalidValue(format!(
+                "cron expression '{}' never matches",
+                cron
+            )));
+        }
+        match &action {
+            ScheduledAction::Clear => {}
+            ScheduledAction::Snapshot { path } if path.is_empty() => {
+                return Err(shared::Error::InvalidValue(
+                    "snapshot path must not be empty".to_string(),
+                ));
+            }
+            ScheduledAction::Snapshot { .. } => {}
+            ScheduledAction::Warm { url } => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(shared::Error::InvalidValue(
+                        "warm url must be http:// or https://".to_string(),
+                    ));
+                }
+            }
+        }
+        if self.get_cache(cache).await.is_none() {
+            return Err(shared::Error::CacheNotFound(cache.to_string()));
+        }
+
+        let schedule = Schedule {
+            id: uuid::Uuid::new_v4().to_string(),
+            cache: cache.to_string(),
+            cron: cron.to_string(),
+            action,
+            next_run_ms,
+            last_run: None,
+        };
+        let persisted = schedule.clone();
+        self.persist(move |persistence| persistence.save_schedule(&persisted))
+            .await?;
+        self.schedules.insert(schedule.id.clone(), schedule.clone());
+        Ok(schedule)
+    }
+
+    /// Stop a schedule, a run already under way finishes
+    pub async fn remove_schedule(&self, id: &str) -> Result<bool> {
+        if self.schedules.remove(id).is_none() {
+            return Ok(false);
+        }
+        let persisted = id.to_string();
+        self.persist(move |persistence| persistence.delete_schedule(&persisted))
+            .await?;
+        Ok(true)
+    }
+
+    /// Every schedule, sorted by cache then id
+    pub fn schedules(&self) -> Vec<Schedule> {
+        let mut schedules: Vec<Schedule> = self
+            .schedules
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        schedules.sort_by(|a, b| (&a.cache, &a.id).cmp(&(&b.cache, &b.id)));
+        schedules
+    }
+
+    /// Schedules due at `now_ms`, moved on to their next run
+    pub fn take_due_schedules(&self, now_ms: u64) -> Vec<Schedule> {
+        let mut due = Vec::new();
+        for mut schedule in self.schedules.iter_mut() {
+            if schedule.next_run_ms.is_some_and(|next| next <= now_ms) {
+                due.push(schedule.clone());
+                schedule.next_run_ms = CronExpr::parse(&schedule.cron)
+                    .ok()
+                    .and_then(|cron| cron.next_after_ms(now_ms));
+            }
+        }
+        due
+    }
+
+    /// Keep how the latest run of a schedule went, unless it was removed in the meantime
+    pub async fn record_schedule_run(&self, id: &str, run: ScheduleRun) -> Result<()> {
+        let persisted = match self.schedules.get_mut(id) {
+            Some(mut schedule) => {
+                schedule.last_run = Some(run);
+                schedule.clone()
+            }
+            None => return Ok(()),
+        };
+        self.persist(move |persistence| persistence.save_schedule(&persisted))
+            .await?;
+        Ok(())
+    }
+
+    /// Make `transformation` available to views as `ViewTransform::Registered { name }`
+    pub fn register_view_transformation(
+        &self,
+        name: impl Into<String>,
+        transformation: Arc<dyn ViewTransformation>,
+    ) {
+        self.view_transformations
+            .insert(name.into(), transformation);
+    }
+
+    pub fn view_transformation(&self, name: &str) -> Option<Arc<dyn ViewTransformation>> {
+        self.view_transformations
+            .get(name)
+            .map(|entry| entry.value().clone())
+    }
+
+    /// Derive the entries of `view.cache` from those of `view.source` from now on
+    /// Both caches must exist and the source must send events with their keys, filling the
+    /// view with what the source holds is left to the caller
+    /// Building a view clears its cache, so it must be empty and not already a view
+    pub async fn add_view(&self, view: MaterializedView) -> Result<MaterializedView> {
+        if view.cache == view.source {
+            return Err(shared::Error::InvalidValue(
+                "a view cannot be its own source".to_string(),
+            ));
+        }
+        match &view.transform {
+            ViewTransform::Projection { fields } => {
+                if fields.is_empty() {
+                    return Err(shared::Error::InvalidValue(
+                        "a projection needs at least one field".to_string(),
+                    ));
+                }
+                if let Some(pointer) = fields
+                    .values()
+                    .find(|pointer| !pointer.is_empty() && !pointer.starts_with('/'))
+                {
+                    return Err(shared::Error::InvalidValue(format!(
+                        "'{}' is not a JSON pointer",
+                        pointer
+                    )));
+                }
+            }
+            ViewTransform::Registered { name } => {
+                if !self.view_transformations.contains_key(name) {
+                    return Err(shared::Error::InvalidValue(format!(
+                        "no view transformation is registered as '{}'",
+                        name
+                    )));
+                }
+            }
+        }
+        let target = self
+            .cache_registry
+            .get(&view.cache)
+            .map(|entry| entry.store.clone())
+            .ok_or_else(|| shared::Error::CacheNotFound(view.cache.clone()))?;
+        let already_a_view = || {
+            shared::Error::PreconditionFailed(format!(
+                "cache '{}' is already a view, remove it first",
+                view.cache
+            ))
+        };
+        if self.views.contains_key(&view.cache) {
+            return Err(already_a_view());
+        }
+        if !target.entries().await?.is_empty() {
+            return Err(shared::Error::PreconditionFailed(format!(
+                "cache '{}' holds entries, a view needs an empty cache",
+                view.cache
+            )));
+        }
+        let source = self
+            .cache_registry
+            .get(&view.source)
+            .map(|entry| entry.config.events.clone())
+            .ok_or_else(|| shared::Error::CacheNotFound(view.source.clone()))?;
+        if !source.enabled || source.key != EventKeyMode::Include {
+            return Err(shared::Error::PreconditionFailed(format!(
+                "cache '{}' must send events with their keys to be the source of a view",
+                view.source
+            )));
+        }
+
+        // Following the sources upwards must not lead back to the view
+        let mut upstream = view.source.clone();
+        while let Some(next) = self.views.get(&upstream).map(|v| v.source.clone()) {
+            if next == view.cache {
+                return Err(shared::Error::InvalidValue(format!(
+                    "cache '{}' is already derived from '{}'",
+                    view.source, view.cache
+                )));
+            }
+            upstream = next;
+        }
+
+        // Registering the view reserves its cache, so of two concurrent adds only one persists
+        match self.views.entry(view.cache.clone()) {
+            Entry::Occupied(_) => return Err(already_a_view()),
+            Entry::Vacant(vacant) => {
+                vacant.insert(view.clone());
+            }
+        }
+        let persisted = view.clone();
+        if let Err(e) = self
+            .persist(move |persistence| persistence.save_view(&persisted))
+            .await
+        {
+            self.views.remove(&view.cache);
+            return Err(e);
+        }
+        Ok(view)
+    }
+
+    /// Stop maintaining the view in `cache`, its entries stay as they are
+    pub async fn remove_view(&self, cache: &str) -> Result<bool> {
+        if self.views.remove(cache).is_none() {
+            return Ok(false);
+        }
+        let persisted = cache.to_string();
+        self.persist(move |persistence| persistence.delete_view(&persisted))
+            .await?;
+        Ok(true)
+    }
+
+    /// Remove the views and schedules of a cache that is going away, so none is left acting
+    /// on its name once another cache takes it
+    async fn remove_dependents(&self, name: &str) -> Result<()> {
+        let views: Vec<String> = self
+            .views
+            .iter()
+            .filter(|view| view.cache == name || view.source == name)
+            .map(|view| view.cache.clone())
+            .collect();
+        for view in views {
+            self.remove_view(&view).await?;
+        }
+
+        let schedules: Vec<String> = self
+            .schedules
+            .iter()
+            .filter(|schedule| schedule.cache == name)
+            .map(|schedule| schedule.id.clone())
+            .collect();
+        for id in schedules {
+            self.remove_schedule(&id).await?;
+        }
+        Ok(())
+    }
+
+    /// Every view, sorted by cache
+    pub fn views(&self) -> Vec<MaterializedView> {
+        let mut views: Vec<MaterializedView> = self
+            .views
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        views.sort_by(|a, b| a.cache.cmp(&b.cache));
+        views
+    }
+
+    /// Views derived from `source`
+    pub fn views_of(&self, source: &str) -> Vec<MaterializedView> {
+        self.views
+            .iter()
+            .filter(|entry| entry.source == source)
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    pub fn view(&self, cache: &str) -> Option<MaterializedView> {
+        self.views.get(cache).map(|entry| entry.value().clone())
+    }
+
+    /// Refuse to remove a cache while an alias points to it
+    fn check_not_aliased(&self, name: &str) -> Result<()> {
+        match self.aliases.iter().find(|entry| entry.value() == name) {
+            Some(entry) => Err(shared::Error::PreconditionFailed(format!(
+                "alias '{}' points to cache '{}', repoint or remove it first",
+                entry.key(),
+                name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Status of a live cache or of one that failed to load, None when there is no such cache
+    pub fn cache_status(&self, name: &str) -> Option<CacheStatus> {
+        if let Some(entry) = self.cache_registry.get(name) {
+            return Some(entry.status.clone());
+        }
+        self.failed
+            .get(name)
+            .map(|failed| CacheStatus::FailedToLoad {
+                reason: failed.reason.clone(),
+            })
+    }
+
+    /// Stored caches that could not be recreated, sorted by name
+    pub fn failed_caches(&self) -> Vec<FailedCache> {
+        let mut failed: Vec<FailedCache> = self
+            .failed
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        failed.sort_by(|a, b| a.name.cmp(&b.name));
+        failed
+    }
+
+    /// Load a cache that failed to load from its stored configuration again, or check the
+    /// backend of a live one, returning its status
+    pub async fn retry_cache(&self, name: &str) -> Result<CacheStatus> {
+        if let Some(store) = self
+            .cache_registry
             .get(name)
             .map(|entry| entry.store.clone())
+        {
+            let status = ping_status(store.as_ref()).await;
+            if let Some(mut entry) = self.cache_registry.get_mut(name) {
+                entry.status = status.clone();
+            }
+            self.responses.invalidate();
+            return Ok(status);
+        }
+        if !self.failed.contains_key(name) {
+            return Err(shared::Error::CacheNotFound(name.to_string()));
+        }
+        let Some(factory) = self.factory.clone() else {
+            return Err(shared::Error::Internal(
+                "caches can only be retried with persistence enabled".to_string(),
+            ));
+        };
+
+        let stored = name.to_string();
+        let loaded = match self
+            .persist(move |persistence| persistence.get_config(&stored))
+            .await
+        {
+            Ok(Some(Some(config))) => load_cache(factory.as_ref(), config.clone())
+                .await
+                .map_err(|reason| (reason, Some(config))),
+            // Its configuration is gone, nothing is left to retry
+            Ok(_) => {
+                self.failed.remove(name);
+                return Err(shared::Error::CacheNotFound(name.to_string()));
+            }
+            Err(e) => Err((e.to_string(), None)),
+        };
+
+        match loaded {
+            Ok(entry) => {
+                let status = entry.status.clone();
+                self.failed.remove(name);
+                self.cache_registry.insert(name.to_string(), entry);
+                self.responses.invalidate();
+                tracing::info!("Cache '{}' loaded on retry", name);
+                Ok(status)
+            }
+            Err((reason, config)) => {
+                self.record_failed(name.to_string(), reason.clone(), config);
+                Ok(CacheStatus::FailedToLoad { reason })
+            }
+        }
+    }
+
+    /// Event policy of a live cache, None when there is no such cache
+    pub(crate) fn event_policy(&self, name: &str) -> Option<EventPolicy> {
+        self.cache_registry
+            .get(self.resolve(name).as_ref())
+            .map(|entry| entry.config.events.clone())
+    }
+
+    /// Configuration of a live cache, None when there is no such cache
+    pub(crate) fn cache_config(&self, name: &str) -> Option<Arc<CacheConfig>> {
+        self.cache_registry
+            .get(self.resolve(name).as_ref())
+            .map(|entry| entry.config.clone())
+    }
+
+    /// Names of the caches configured with a reap interval, with their interval
+    pub(crate) fn reapable_caches(&self) -> Vec<(String, Duration)> {
+        self.cache_registry
+            .iter()
+            .filter_map(|entry| {
+                let interval = Duration::from_millis(entry.config.reap_interval_ms?);
+                Some((entry.key().clone(), interval))
+            })
+            .collect()
+    }
+
+    /// Names of every live cache
+    pub(crate) fn cache_names(&self) -> Vec<String> {
+        self.cache_registry
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// Remove a cache and its data for good, whether it is live, awaiting purge or failed to load
+    pub async fn purge_cache(&self, name: &str) -> Result<DropCacheResponse> {
+        self.check_not_aliased(name)?;
+        let live = self.cache_registry.remove(name).is_some();
+        let dropped = self.dropped.remove(name).is_some();
+        let failed = self.failed.remove(name).is_some();
+        self.tag_index.remove_cache(name);
+        self.content_types.remove_cache(name);
+
+        if live || failed {
+            self.responses.invalidate();
+            let name = name.to_string();
+            self.persist(move |persistence| persistence.delete_config(&name))
+                .await?;
+        }
+        if dropped {
+            let name = name.to_string();
+            self.persist(move |persistence| persistence.delete_dropped(&name))
+                .await?;
+        }
+        self.remove_dependents(name).await?;
+
+        Ok(DropCacheResponse::new(live || dropped || failed))
+    }
+
+    /// Purge the dropped caches whose retention window has passed, returning their names
+    pub async fn purge_expired(&self) -> Vec<String> {
+        let now = now_millis();
+        let expired: Vec<String> = self
+            .dropped
+            .iter()
+            .filter(|entry| entry.dropped.purge_at_ms <= now)
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        let mut purged = Vec::with_capacity(expired.len());
+        for name in expired {
+            // Restored since the list was taken
+            if self
+                .dropped
+                .remove_if(&name, |_, entry| entry.dropped.purge_at_ms <= now)
+                .is_none()
+            {
+                continue;
+            }
+            self.tag_index.remove_cache(&name);
+            self.content_types.remove_cache(&name);
+
+            let persisted = name.clone();
+            if let Err(e) = self
+                .persist(move |persistence| persistence.delete_dropped(&persisted))
+                .await
+            {
+                tracing::warn!("Failed to forget purged cache '{}': {}", name, e);
+            }
+            purged.push(name);
+        }
+        purged
+    }
+
+    /// Dropped caches that can still be restored, sorted by name
+    pub fn dropped_caches(&self) -> Vec<DroppedCache> {
+        let mut dropped: Vec<DroppedCache> = self
+            .dropped
+            .iter()
+            .map(|entry| DroppedCache {
+                config: entry.dropped.config.redacted(),
+                ..entry.dropped.clone()
+            })
+            .collect();
+        dropped.sort_by(|a, b| a.config.name.cmp(&b.config.name));
+        dropped
+    }
+
+    /// Count entries the background reaper removed from a cache
+    pub(crate) fn record_reaped(&self, name: &str, reaped: u64) {
+        if let Some(entry) = self.cache_registry.get(name) {
+            entry.reaped_entries.fetch_add(reaped, Ordering::Relaxed);
+        }
     }
 }
 
@@ -113,7 +928,7 @@ where
 #[async_trait]
 impl<K, V> AdminOperations<K, V> for CacheManager<K, V>
 where
-    K: Debug + Hash + Eq + Send + Sync + 'static,
+    K: Debug + Hash + Eq + Send + Sync + Clone + 'static,
     V: Debug + Send + Sync + Clone + 'static,
 {
     /// Create and register a cache with configuration and storage implementation (unified operation)
@@ -122,23 +937,69 @@ where
         config: CacheConfig,
         store: Arc<dyn CacheStore<K, V>>,
     ) -> Result<CreateCacheResponse> {
-        // Check if cache already exists
-        if self.cache_registry.contains_key(&config.name) {
+        config.validate()?;
+
+        if self.aliases.contains_key(&config.name) {
+            return Ok(CreateCacheResponse::new(
+                false,
+                format!("'{}' is an alias, remove it first", config.name),
+            ));
+        }
+
+        // Its stored configuration is kept for a retry, a new cache would overwrite it
+        if self.failed.contains_key(&config.name) {
+            return Ok(CreateCacheResponse::new(
+                false,
+                format!(
+                    "Cache '{}' failed to load, retry or purge it first",
+                    config.name
+                ),
+            ));
+        }
+
+        // The name stays taken until the dropped cache is restored or purged
+        if self.dropped.contains_key(&config.name) {
             return Ok(CreateCacheResponse::new(
                 false,
-                format!("Cache '{}' already exists", config.name),
+                format!(
+                    "Cache '{}' was dropped and can still be restored, purge it first",
+                    config.name
+                ),
             ));
         }
 
         let cache_name = config.name.clone();
+        let persisted = config.clone();
+
+        // Registering the cache reserves its name, so of two concurrent creates only one
+        // gets to persist
+        match self.cache_registry.entry(cache_name.clone()) {
+            Entry::Occupied(_) => {
+                return Ok(CreateCacheResponse::new(
+                    false,
+                    format!("Cache '{}' already exists", cache_name),
+                ));
+            }
+            Entry::Vacant(vacant) => {
+                vacant.insert(CacheMetadata {
+                    config: Arc::new(config),
+                    store,
+                    reaped_entries: AtomicU64::new(0),
+                    status: CacheStatus::Ready,
+                });
+            }
+        }
 
         // Persist to Sled if persistence is enabled
-        if let Some(ref persistence) = self.persistence {
-            persistence.save_config(&config)?;
+        if let Err(e) = self
+            .persist(move |persistence| persistence.save_config(&persisted))
+            .await
+        {
+            self.cache_registry.remove(&cache_name);
+            return Err(e);
         }
 
-        let entry = CacheMetadata { config, store };
-        self.cache_registry.insert(cache_name.clone(), entry);
+        self.responses.invalidate();
 
         Ok(CreateCacheResponse::new(
             true,
@@ -146,33 +1007,909 @@ where
         ))
     }
 
+    /// Keeps the cache with its data for the drop retention window, purges it if there is none
     async fn drop_cache(&self, name: &str) -> Result<DropCacheResponse> {
-        let dropped = self.cache_registry.remove(name).is_some();
-
-        // Delete from Sled if persistence is enabled and cache was dropped
-        if dropped && let Some(ref persistence) = self.persistence {
-            persistence.delete_config(name)?;
+        self.check_not_aliased(name)?;
+        // A cache that failed to load has no data to keep
+        if self.drop_retention.is_zero() || self.failed.contains_key(name) {
+            return self.purge_cache(name).await;
         }
 
-        Ok(DropCacheResponse::new(dropped))
+        let Some((name, metadata)) = self.cache_registry.remove(name) else {
+            return Ok(DropCacheResponse::new(false));
+        };
+        self.responses.invalidate();
+
+        let dropped_at_ms = now_millis();
+        let dropped = DroppedCache {
+            config: CacheConfig::clone(&metadata.config),
+            dropped_at_ms,
+            purge_at_ms: dropped_at_ms.saturating_add(self.drop_retention.as_millis() as u64),
+        };
+        let purge_at_ms = dropped.purge_at_ms;
+        let persisted = dropped.clone();
+        self.dropped
+            .insert(name.clone(), DroppedEntry { dropped, metadata });
+
+        // Moved aside in Sled so a restart keeps it restorable rather than live
+        self.persist(move |persistence| persistence.save_dropped(&persisted))
+            .await?;
+        self.remove_dependents(&name).await?;
+
+        Ok(DropCacheResponse::new(true).with_restorable_until(purge_at_ms))
+    }
+
+    async fn restore_cache(&self, name: &str) -> Result<()> {
+        // Holding the registry entry keeps a concurrent create from taking the name in between
+        let config = match self.cache_registry.entry(name.to_string()) {
+            Entry::Occupied(_) => {
+                return Err(shared::Error::InvalidValue(format!(
+                    "cache '{}' already exists",
+                    name
+                )));
+            }
+            Entry::Vacant(vacant) => {
+                let (_, entry) = self
+                    .dropped
+                    .remove(name)
+                    .ok_or_else(|| shared::Error::CacheNotFound(name.to_string()))?;
+                let config = entry.dropped.config.clone();
+                vacant.insert(entry.metadata);
+                config
+            }
+        };
+        self.responses.invalidate();
+
+        let name = name.to_string();
+
+        self.persist(move |persistence| {
+            persistence.save_config(&config)?;
+            persistence.delete_dropped(&name)
+        })
+        .await?;
+
+        Ok(())
     }
 
     async fn list_caches(&self) -> Result<ListCachesResponse> {
+        if let Some(response) = self.responses.list() {
+            return Ok(response);
+        }
+
+        let generation = self.responses.generation();
         let cache_infos: Vec<CacheInfo> = self
             .cache_registry
             .iter()
-            .map(|entry| CacheInfo::from_config(&entry.config))
+            .map(|entry| CacheInfo::from_config(&entry.config).with_status(entry.status.clone()))
             .collect();
-        Ok(ListCachesResponse::new(cache_infos))
+        let response = ListCachesResponse::new(cache_infos).with_failed(self.failed_caches());
+        self.responses.store_list(generation, &response);
+        Ok(response)
     }
 
+    async fn find_caches(&self, filter: &CacheFilter) -> Result<ListCachesResponse> {
+        let mut cache_infos: Vec<CacheInfo> = self
+            .cache_registry
+            .iter()
+            .filter(|entry| filter.matches(&entry.config))
+            .map(|entry| CacheInfo::from_config(&entry.config).with_status(entry.status.clone()))
+            .collect();
+        cache_infos.sort_by(|a, b| a.config.name.cmp(&b.config.name));
+
+        let failed = self
+            .failed_caches()
+            .into_iter()
+            .filter(|failed| match &failed.config {
+                Some(config) => filter.matches(config),
+                // Only the name is known
+                None => {
+                    filter.tags.is_empty()
+                        && filter
+                            .name_prefix
+                            .as_ref()
+                            .is_none_or(|prefix| failed.name.starts_with(prefix.as_str()))
+                }
+            })
+            .collect();
+        Ok(ListCachesResponse::new(cache_infos).with_failed(failed))
+    }
+
+    /// Live counters in the response may lag by up to the admin response max age
     async fn describe_cache(&self, name: &str) -> Result<DescribeCacheResponse> {
+        if let Some(response) = self.responses.describe(name) {
+            return Ok(response);
+        }
+
+        let generation = self.responses.generation();
         if let Some(entry) = self.cache_registry.get(name) {
-            Ok(DescribeCacheResponse::new(CacheInfo::from_config(
-                &entry.config,
-            )))
+            let reaped_entries = entry
+                .config
+                .reap_interval_ms
+                .map(|_| entry.reaped_entries.load(Ordering::Relaxed));
+            let info = CacheInfo::from_config(&entry.config)
+                .with_effective_policy(entry.store.eviction_policy())
+                .with_reaped_entries(reaped_entries)
+                .with_admission_rejected(entry.store.admission_rejected())
+                .with_status(entry.status.clone());
+            let response = DescribeCacheResponse::new(info);
+            self.responses.store_describe(generation, name, &response);
+            Ok(response)
         } else {
             Err(shared::Error::CacheNotFound(name.to_string()))
         }
     }
+
+    /// Apply `update` to a live cache, resizing its store where the backend supports it
+    async fn update_cache(
+        &self,
+        name: &str,
+        update: CacheConfigUpdate,
+    ) -> Result<UpdateCacheResponse> {
+        // Copied out so no registry guard is held across the configuration store write
+        let (mut config, store) = self
+            .cache_registry
+            .get(name)
+            .map(|entry| (CacheConfig::clone(&entry.config), entry.store.clone()))
+            .ok_or_else(|| shared::Error::CacheNotFound(name.to_string()))?;
+        let mut requires_restart = Vec::new();
+
+        let resize_to = update
+            .mem_bytes
+            .filter(|mem_bytes| config.mem_bytes != Some(*mem_bytes));
+
+        // Moka and Redis stores fix the TTL when the cache is built, other backends ignore it
+        if update
+            .default_ttl_ms
+            .is_some_and(|default_ttl_ms| config.default_ttl_ms != Some(default_ttl_ms))
+            && matches!(
+                config.backend,
+                CacheEvictionStrategy::TimeBound | CacheEvictionStrategy::Redis
+            )
+        {
+            requires_restart.push("default_ttl_ms");
+        }
+
+        // The rest is read from the config on every operation, so it takes effect immediately
+        update.apply_to(&mut config);
+
+        // Reject the whole update before touching the live store
+        config.validate()?;
+
+        if let Some(mem_bytes) = resize_to
+            && !store.resize(mem_bytes)?
+        {
+            requires_restart.push("mem_bytes");
+        }
+
+        // Persist so the cache is rebuilt with the new settings after a restart
+        let persisted = config.clone();
+        self.persist(move |persistence| persistence.save_config(&persisted))
+            .await?;
+
+        let info = CacheInfo::from_config(&config);
+        match self.cache_registry.get_mut(name) {
+            Some(mut entry) => entry.config = Arc::new(config),
+            // Dropped or renamed while the update was being persisted
+            None => return Err(shared::Error::CacheNotFound(name.to_string())),
+        }
+        self.responses.invalidate();
+
+        Ok(UpdateCacheResponse::new(info, requires_restart))
+    }
+
+    /// Aliases pointing at the cache follow it to its new name
+    async fn rename_cache(&self, name: &str, new_name: &str) -> Result<()> {
+        let mut config = self
+            .cache_registry
+            .get(name)
+            .map(|entry| CacheConfig::clone(&entry.config))
+            .ok_or_else(|| shared::Error::CacheNotFound(name.to_string()))?;
+        config.name = new_name.to_string();
+        config.validate()?;
+
+        // Redis keys carry the cache name, the entries would be left behind
+        if config.backend == CacheEvictionStrategy::Redis {
+            return Err(shared::Error::InvalidValue(
+                "Redis caches cannot be renamed".to_string(),
+            ));
+        }
+        if self.cache_registry.contains_key(new_name)
+            || self.dropped.contains_key(new_name)
+            || self.failed.contains_key(new_name)
+            || self.aliases.contains_key(new_name)
+        {
+            return Err(shared::Error::InvalidValue(format!(
+                "'{}' is already taken",
+                new_name
+            )));
+        }
+
+        let from = name.to_string();
+        let persisted = config.clone();
+        self.persist(move |persistence| persistence.rename_config(&from, &persisted))
+            .await?;
+
+        let (_, mut entry) = self
+            .cache_registry
+            .remove(name)
+            .ok_or_else(|| shared::Error::CacheNotFound(name.to_string()))?;
+        entry.config = Arc::new(config);
+        self.cache_registry.insert(new_name.to_string(), entry);
+        self.responses.invalidate();
+
+        self.tag_index.rename_cache(name, new_name);
+        self.content_types.rename_cache(name, new_name);
+
+        let aliased: Vec<String> = self
+            .aliases
+            .iter()
+            .filter(|entry| entry.value() == name)
+            .map(|entry| entry.key().clone())
+            .collect();
+        for alias in aliased {
+            self.aliases.insert(alias.clone(), new_name.to_string());
+            let persisted = CacheAlias {
+                alias,
+                cache: new_name.to_string(),
+            };
+            self.persist(move |persistence| persistence.save_alias(&persisted))
+                .await?;
+        }
+
+        let scheduled: Vec<Schedule> = self
+            .schedules
+            .iter_mut()
+            .filter(|schedule| schedule.cache == name)
+            .map(|mut schedule| {
+                schedule.cache = new_name.to_string();
+                schedule.clone()
+            })
+            .collect();
+        for persisted in scheduled {
+            self.persist(move |persistence| persistence.save_schedule(&persisted))
+                .await?;
+        }
+
+        let derived: Vec<MaterializedView> = self
+            .views
+            .iter_mut()
+            .filter(|view| view.source == name)
+            .map(|mut view| {
+                view.source = new_name.to_string();
+                view.clone()
+            })
+            .collect();
+        for persisted in derived {
+            self.persist(move |persistence| persistence.save_view(&persisted))
+                .await?;
+        }
+        if let Some((_, mut view)) = self.views.remove(name) {
+            view.cache = new_name.to_string();
+            self.views.insert(view.cache.clone(), view.clone());
+            let from = name.to_string();
+            self.persist(move |persistence| {
+                persistence.save_view(&view)?;
+                persistence.delete_view(&from)
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Build the store of a stored cache and check its backend answers, Err with the reason when
+/// the cache cannot be built
+async fn load_cache<K, V>(
+    factory: &dyn StorageFactory<K, V>,
+    config: CacheConfig,
+) -> std::result::Result<CacheMetadata<K, V>, String>
+where
+    K: Debug + Hash + Eq + Send + Sync + 'static,
+    V: Debug + Send + Sync + 'static,
+{
+    config.validate().map_err(|e| e.to_string())?;
+    // Factories panic on settings that validation should have turned away
+    let store = std::panic::catch_unwind(AssertUnwindSafe(|| factory.create_from_config(&config)))
+        .map_err(|_| "the storage backend could not be created".to_string())?;
+    let status = ping_status(store.as_ref()).await;
+    Ok(CacheMetadata {
+        config: Arc::new(config),
+        store,
+        reaped_entries: AtomicU64::new(0),
+        status,
+    })
+}
+
+/// Ready when the store's backend answers within PING_TIMEOUT
+async fn ping_status<K: 'static, V: 'static>(store: &dyn CacheStore<K, V>) -> CacheStatus {
+    match tokio::time::timeout(PING_TIMEOUT, store.ping()).await {
+        Ok(Ok(())) => CacheStatus::Ready,
+        Ok(Err(e)) => CacheStatus::Degraded {
+            reason: e.to_string(),
+        },
+        Err(_) => CacheStatus::Degraded {
+            reason: format!("backend did not answer within {:?}", PING_TIMEOUT),
+        },
+    }
+}
+
+pub(super) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::EvictionAlgorithm;
+    use crate::domain::response::{DeleteResponse, ExistsResponse, GetResponse, PutResponse};
+    use std::collections::HashMap;
+
+    /// Store that only records resizes
+    struct ResizableStore;
+
+    #[async_trait]
+    impl CacheStore<String, String> for ResizableStore {
+        async fn exists(&self, _key: &String) -> Result<ExistsResponse> {
+            Ok(ExistsResponse::new(false))
+        }
+
+        async fn put(&self, _key: String, _val: String) -> Result<PutResponse> {
+            Ok(PutResponse::new(true, "stored"))
+        }
+
+        async fn get(&self, _key: &String) -> Result<GetResponse<String>> {
+            Err(shared::Error::NotFound)
+        }
+
+        async fn delete(&self, _key: &String) -> Result<DeleteResponse> {
+            Ok(DeleteResponse::new(false))
+        }
+
+        async fn get_and_delete(&self, _key: &String) -> Result<GetResponse<String>> {
+            Err(shared::Error::NotFound)
+        }
+
+        fn resize(&self, _capacity: u64) -> Result<bool> {
+            Ok(true)
+        }
+
+        async fn entries(&self) -> Result<Vec<(String, String)>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_cache() {
+        let manager = CacheManager::<String, String>::new();
+        let config = CacheConfig::with_backend(
+            "orders",
+            CacheEvictionStrategy::SizeBounded,
+            EvictionAlgorithm::Unspecified,
+            Some(1_048_576),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        manager
+            .create_cache(config, Arc::new(ResizableStore))
+            .await
+            .unwrap();
+
+        let update = CacheConfigUpdate {
+            mem_bytes: Some(2_097_152),
+            tags: Some(HashMap::from([("env".to_string(), "prod".to_string())])),
+            ..Default::default()
+        };
+        let result = manager.update_cache("orders", update).await.unwrap();
+        assert!(result.requires_restart.is_empty());
+
+        let (_, config) = manager.get_cache("orders").await.unwrap();
+        assert_eq!(config.mem_bytes, Some(2_097_152));
+        assert_eq!(config.tags.as_ref().unwrap()["env"], "prod");
+
+        assert!(matches!(
+            manager
+                .update_cache("missing", CacheConfigUpdate::default())
+                .await,
+            Err(shared::Error::CacheNotFound(_))
+        ));
+
+        // Invalid changes are rejected and leave the cache as it was
+        let update = CacheConfigUpdate {
+            mem_bytes: Some(1),
+            ..Default::default()
+        };
+        assert!(matches!(
+            manager.update_cache("orders", update).await,
+            Err(shared::Error::InvalidValue(_))
+        ));
+        let (_, config) = manager.get_cache("orders").await.unwrap();
+        assert_eq!(config.mem_bytes, Some(2_097_152));
+    }
+
+    #[tokio::test]
+    async fn test_admin_responses_follow_changes() {
+        let manager = CacheManager::<String, String>::new()
+            .with_admin_response_max_age(Duration::from_secs(60));
+        let config = |name| {
+            CacheConfig::with_backend(
+                name,
+                CacheEvictionStrategy::SizeBounded,
+                EvictionAlgorithm::Unspecified,
+                Some(1_048_576),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+
+        manager
+            .create_cache(config("orders"), Arc::new(ResizableStore))
+            .await
+            .unwrap();
+        assert_eq!(manager.list_caches().await.unwrap().caches.len(), 1);
+        let info = manager.describe_cache("orders").await.unwrap().info;
+        assert_eq!(info.config.mem_bytes, Some(1_048_576));
+
+        // Cached responses never outlive the change that made them stale
+        manager
+            .create_cache(config("sessions"), Arc::new(ResizableStore))
+            .await
+            .unwrap();
+        assert_eq!(manager.list_caches().await.unwrap().caches.len(), 2);
+
+        let update = CacheConfigUpdate {
+            mem_bytes: Some(2_097_152),
+            ..Default::default()
+        };
+        manager.update_cache("orders", update).await.unwrap();
+        let info = manager.describe_cache("orders").await.unwrap().info;
+        assert_eq!(info.config.mem_bytes, Some(2_097_152));
+
+        manager.drop_cache("orders").await.unwrap();
+        assert!(matches!(
+            manager.describe_cache("orders").await,
+            Err(shared::Error::CacheNotFound(_))
+        ));
+        assert_eq!(manager.list_caches().await.unwrap().caches.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_cache_can_be_restored() {
+        let manager =
+            CacheManager::<String, String>::new().with_drop_retention(Duration::from_secs(60));
+        let config = CacheConfig::with_backend(
+            "orders",
+            CacheEvictionStrategy::SizeBounded,
+            EvictionAlgorithm::Unspecified,
+            Some(1_048_576),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        manager
+            .create_cache(config.clone(), Arc::new(ResizableStore))
+            .await
+            .unwrap();
+
+        let dropped = manager.drop_cache("orders").await.unwrap();
+        assert!(dropped.dropped && dropped.restorable_until_ms.is_some());
+        assert!(manager.get_cache("orders").await.is_none());
+        assert_eq!(manager.dropped_caches().len(), 1);
+
+        // The name stays taken while the cache can be restored
+        let created = manager
+            .create_cache(config, Arc::new(ResizableStore))
+            .await
+            .unwrap();
+        assert!(!created.created);
+
+        manager.restore_cache("orders").await.unwrap();
+        assert!(manager.get_cache("orders").await.is_some());
+        assert!(manager.dropped_caches().is_empty());
+        assert!(matches!(
+            manager.restore_cache("orders").await,
+            Err(shared::Error::InvalidValue(_))
+        ));
+
+        // Nothing is purged before its window has passed, purge_cache skips the window
+        manager.drop_cache("orders").await.unwrap();
+        assert!(manager.purge_expired().await.is_empty());
+        assert!(manager.purge_cache("orders").await.unwrap().dropped);
+        assert!(matches!(
+            manager.restore_cache("orders").await,
+            Err(shared::Error::CacheNotFound(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_restores_restore_once() {
+        let manager =
+            CacheManager::<String, String>::new().with_drop_retention(Duration::from_secs(60));
+        let config = CacheConfig::with_backend(
+            "orders",
+            CacheEvictionStrategy::SizeBounded,
+            EvictionAlgorithm::Unspecified,
+            Some(1_048_576),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        manager
+            .create_cache(config, Arc::new(ResizableStore))
+            .await
+            .unwrap();
+        manager.drop_cache("orders").await.unwrap();
+
+        let restores: Vec<_> = (0..8)
+            .map(|_| {
+                let manager = manager.clone();
+                tokio::spawn(async move { manager.restore_cache("orders").await })
+            })
+            .collect();
+        let mut restored = 0;
+        for restore in restores {
+            if restore.await.unwrap().is_ok() {
+                restored += 1;
+            }
+        }
+
+        assert_eq!(restored, 1);
+        assert!(manager.get_cache("orders").await.is_some());
+        assert!(manager.dropped_caches().is_empty());
+    }
+
+    struct ResizableStoreFactory;
+
+    impl StorageFactory<String, String> for ResizableStoreFactory {
+        fn create_from_config(&self, _config: &CacheConfig) -> Arc<dyn CacheStore<String, String>> {
+            Arc::new(ResizableStore)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_alias_swaps_caches() {
+        let manager = CacheManager::<String, String>::new();
+        for name in ["products-v41", "products-v42"] {
+            let config = CacheConfig::with_backend(
+                name,
+                CacheEvictionStrategy::SizeBounded,
+                EvictionAlgorithm::Unspecified,
+                Some(1_048_576),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+            manager
+                .create_cache(config, Arc::new(ResizableStore))
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            manager.set_alias("products", "products-v41").await.unwrap(),
+            None
+        );
+        let (_, config) = manager.get_cache("products").await.unwrap();
+        assert_eq!(config.name, "products-v41");
+
+        // Repointing swaps the rebuilt cache in and reports the one it replaced
+        assert_eq!(
+            manager.set_alias("products", "products-v42").await.unwrap(),
+            Some("products-v41".to_string())
+        );
+        let (_, config) = manager.get_cache("products").await.unwrap();
+        assert_eq!(config.name, "products-v42");
+
+        // Caches behind an alias stay, aliases cannot shadow caches or point at aliases
+        assert!(matches!(
+            manager.drop_cache("products-v42").await,
+            Err(shared::Error::PreconditionFailed(_))
+        ));
+        assert!(manager.drop_cache("products-v41").await.unwrap().dropped);
+        assert!(manager.set_alias("products-v42", "products").await.is_err());
+        assert!(manager.set_alias("latest", "products").await.is_err());
+
+        assert!(manager.remove_alias("products").await.unwrap());
+        assert!(manager.get_cache("products").await.is_none());
+        assert!(manager.aliases().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_views_refuse_cycles_and_follow_renames() {
+        let manager = CacheManager::<String, String>::new();
+        for name in ["users", "user-names", "user-cities", "audit"] {
+            let mut config = CacheConfig::with_backend(
+                name,
+                CacheEvictionStrategy::SizeBounded,
+                EvictionAlgorithm::Unspecified,
+                Some(1_048_576),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+            if name == "audit" {
+                config.events.key = EventKeyMode::Hash;
+            }
+            manager
+                .create_cache(config, Arc::new(ResizableStore))
+                .await
+                .unwrap();
+        }
+        let view = |cache: &str, source: &str| MaterializedView {
+            cache: cache.to_string(),
+            source: source.to_string(),
+            transform: ViewTransform::Projection {
+                fields: [("name".to_string(), "/name".to_string())].into(),
+            },
+        };
+
+        manager.add_view(view("user-names", "users")).await.unwrap();
+        manager
+            .add_view(view("user-cities", "user-names"))
+            .await
+            .unwrap();
+        assert!(
+            manager
+                .add_view(view("users", "user-cities"))
+                .await
+                .is_err()
+        );
+        assert!(manager.add_view(view("users", "users")).await.is_err());
+        // Building a view clears its cache, another view cannot take it over
+        assert!(matches!(
+            manager.add_view(view("user-names", "user-cities")).await,
+            Err(shared::Error::PreconditionFailed(_))
+        ));
+        // Views need the keys of their source's events
+        assert!(matches!(
+            manager.add_view(view("users", "audit")).await,
+            Err(shared::Error::PreconditionFailed(_))
+        ));
+
+        manager.rename_cache("user-names", "names").await.unwrap();
+        let views = manager.views();
+        assert_eq!(views[0], view("names", "users"));
+        assert_eq!(views[1], view("user-cities", "names"));
+
+        assert!(manager.remove_view("names").await.unwrap());
+        assert!(!manager.remove_view("names").await.unwrap());
+        assert!(manager.views_of("users").is_empty());
+
+        // Dropping a cache takes its views and schedules with it
+        manager
+            .add_schedule("user-cities", "0 * * * *", ScheduledAction::Clear)
+            .await
+            .unwrap();
+        manager.drop_cache("names").await.unwrap();
+        assert!(manager.views().is_empty());
+        manager.purge_cache("user-cities").await.unwrap();
+        assert!(manager.schedules().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rename_keeps_cache_and_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("caches.sled");
+        let manager = CacheManager::new_with_persistence(&path, Arc::new(ResizableStoreFactory))
+            .await
+            .unwrap();
+        for name in ["orders", "sessions"] {
+            let config = CacheConfig::with_backend(
+                name,
+                CacheEvictionStrategy::SizeBounded,
+                EvictionAlgorithm::Unspecified,
+                Some(1_048_576),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+            manager
+                .create_cache(config, Arc::new(ResizableStore))
+                .await
+                .unwrap();
+        }
+        manager.set_alias("current", "orders").await.unwrap();
+
+        // Names in use are refused
+        assert!(manager.rename_cache("orders", "sessions").await.is_err());
+        assert!(manager.rename_cache("orders", "current").await.is_err());
+
+        manager.rename_cache("orders", "orders-2024").await.unwrap();
+        assert!(manager.get_cache("orders").await.is_none());
+        let (_, config) = manager.get_cache("current").await.unwrap();
+        assert_eq!(config.name, "orders-2024");
+
+        let persistence = manager.persistence.as_ref().unwrap();
+        let mut names: Vec<_> = persistence
+            .load_all()
+            .unwrap()
+            .into_iter()
+            .map(|config| config.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["orders-2024", "sessions"]);
+        assert_eq!(persistence.load_aliases().unwrap()[0].cache, "orders-2024");
+    }
+
+    #[tokio::test]
+    async fn test_persist_on_storage_runtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("caches.sled");
+        let manager = CacheManager::new_with_persistence(&path, Arc::new(ResizableStoreFactory))
+            .await
+            .unwrap()
+            .with_storage_runtime(Handle::current());
+
+        for name in ["orders", "sessions"] {
+            let config = CacheConfig::with_backend(
+                name,
+                CacheEvictionStrategy::SizeBounded,
+                EvictionAlgorithm::Unspecified,
+                Some(1_048_576),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+            manager
+                .create_cache(config, Arc::new(ResizableStore))
+                .await
+                .unwrap();
+        }
+        assert!(manager.drop_cache("sessions").await.unwrap().dropped);
+
+        // Writes made on the storage runtime have reached the configuration store
+        let persisted = manager.persistence.as_ref().unwrap().load_all().unwrap();
+        let names: Vec<_> = persisted
+            .iter()
+            .map(|config| config.name.as_str())
+            .collect();
+        assert_eq!(names, ["orders"]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_creates_of_one_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("caches.sled");
+        let manager = CacheManager::new_with_persistence(&path, Arc::new(ResizableStoreFactory))
+            .await
+            .unwrap()
+            .with_storage_runtime(Handle::current());
+
+        let creates: Vec<_> = (0..8)
+            .map(|_| {
+                let manager = manager.clone();
+                let config = CacheConfig::with_backend(
+                    "orders",
+                    CacheEvictionStrategy::SizeBounded,
+                    EvictionAlgorithm::Unspecified,
+                    Some(1_048_576),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                );
+                tokio::spawn(async move {
+                    manager
+                        .create_cache(config, Arc::new(ResizableStore))
+                        .await
+                        .unwrap()
+                        .created
+                })
+            })
+            .collect();
+
+        let mut created = 0;
+        for create in creates {
+            if create.await.unwrap() {
+                created += 1;
+            }
+        }
+        assert_eq!(created, 1);
+    }
+
+    #[tokio::test]
+    async fn test_caches_that_fail_to_load_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("caches.sled");
+        {
+            let persistence = SledPersistence::new(&path).unwrap();
+            for (name, mem_bytes) in [("orders", 1_048_576), ("broken", 1)] {
+                let config = CacheConfig::with_backend(
+                    name,
+                    CacheEvictionStrategy::SizeBounded,
+                    EvictionAlgorithm::Unspecified,
+                    Some(mem_bytes),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                );
+                persistence.save_config(&config).unwrap();
+            }
+        }
+
+        // The broken configuration does not keep the others from loading
+        let manager = CacheManager::new_with_persistence(&path, Arc::new(ResizableStoreFactory))
+            .await
+            .unwrap();
+        let listed = manager.list_caches().await.unwrap();
+        assert_eq!(listed.caches.len(), 1);
+        assert_eq!(listed.caches[0].status, CacheStatus::Ready);
+        assert_eq!(listed.failed.len(), 1);
+        assert_eq!(listed.failed[0].name, "broken");
+        assert!(matches!(
+            manager.cache_status("broken"),
+            Some(CacheStatus::FailedToLoad { .. })
+        ));
+
+        // Its configuration is kept for a retry, not replaced by a new cache
+        let replacement = CacheConfig::with_backend(
+            "broken",
+            CacheEvictionStrategy::SizeBounded,
+            EvictionAlgorithm::Unspecified,
+            Some(1_048_576),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        let created = manager
+            .create_cache(replacement, Arc::new(ResizableStore))
+            .await
+            .unwrap();
+        assert!(!created.created);
+
+        assert_eq!(
+            manager.retry_cache("orders").await.unwrap(),
+            CacheStatus::Ready
+        );
+        assert!(matches!(
+            manager.retry_cache("broken").await.unwrap(),
+            CacheStatus::FailedToLoad { .. }
+        ));
+        assert!(matches!(
+            manager.retry_cache("missing").await,
+            Err(shared::Error::CacheNotFound(_))
+        ));
+
+        assert!(manager.purge_cache("broken").await.unwrap().dropped);
+        assert!(manager.failed_caches().is_empty());
+        assert!(manager.cache_status("broken").is_none());
+        let persisted = manager.persistence.as_ref().unwrap().load_all().unwrap();
+        assert_eq!(persisted.len(), 1);
+    }
 }
@@ -1,4 +1,6 @@
 pub mod admin_operations;
 pub mod operation;
+pub mod response_cache;
+pub mod schedules;
 
 pub use admin_operations::CacheManager;
@@ -6,9 +6,10 @@ use shared::Result;
 
 use crate::{
     domain::{
-        CacheConfig,
+        CacheConfig, CacheConfigUpdate, CacheFilter,
         response::admin::{
             CreateCacheResponse, DescribeCacheResponse, DropCacheResponse, ListCachesResponse,
+            UpdateCacheResponse,
         },
     },
     ports::CacheStore,
@@ -22,6 +23,17 @@ pub trait AdminOperations<K, V>: Send + Sync + 'static {
         store: Arc<dyn CacheStore<K, V>>,
     ) -> Result<CreateCacheResponse>;
     async fn drop_cache(&self, name: &str) -> Result<DropCacheResponse>;
+    /// Bring back a dropped cache that has not been purged yet
+    async fn restore_cache(&self, name: &str) -> Result<()>;
     async fn list_caches(&self) -> Result<ListCachesResponse>;
+    /// Caches matching `filter`, sorted by name
+    async fn find_caches(&self, filter: &CacheFilter) -> Result<ListCachesResponse>;
     async fn describe_cache(&self, name: &str) -> Result<DescribeCacheResponse>;
+    async fn update_cache(
+        &self,
+        name: &str,
+        update: CacheConfigUpdate,
+    ) -> Result<UpdateCacheResponse>;
+    /// Give a live cache a new name, keeping its entries and settings
+    async fn rename_cache(&self, name: &str, new_name: &str) -> Result<()>;
 }
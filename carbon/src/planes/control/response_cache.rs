@@ -0,0 +1,115 @@
+use crate::domain::response::admin::{DescribeCacheResponse, ListCachesResponse};
+use dashmap::DashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long admin responses are served from memory by default
+pub const DEFAULT_ADMIN_RESPONSE_MAX_AGE: Duration = Duration::from_secs(1);
+
+/// ListCaches and DescribeCache responses kept between calls
+/// Creating, dropping or updating a cache clears them, max_age bounds how stale the live
+/// counters in a described cache may get
+pub struct AdminResponseCache {
+    max_age: Duration,
+    // Bumped on every invalidation, so a response built before one is never stored after it
+    generation: AtomicU64,
+    list: Mutex<Option<(Instant, ListCachesResponse)>>,
+    describe: DashMap<String, (Instant, DescribeCacheResponse)>,
+}
+
+impl AdminResponseCache {
+    /// A zero `max_age` disables caching
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            generation: AtomicU64::new(0),
+            list: Mutex::new(None),
+            describe: DashMap::new(),
+        }
+    }
+
+    /// Token to hand back to `store_*` with a response built after this call
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    pub fn list(&self) -> Option<ListCachesResponse> {
+        let list = self.list.lock().unwrap();
+        list.as_ref()
+            .filter(|(built_at, _)| built_at.elapsed() < self.max_age)
+            .map(|(_, response)| response.clone())
+    }
+
+    pub fn store_list(&self, generation: u64, response: &ListCachesResponse) {
+        if self.max_age.is_zero() {
+            return;
+        }
+        let mut list = self.list.lock().unwrap();
+        // Checked under the lock, so an invalidation cannot slip in between
+        if self.generation() == generation {
+            *list = Some((Instant::now(), response.clone()));
+        }
+    }
+
+    pub fn describe(&self, name: &str) -> Option<DescribeCacheResponse> {
+        self.describe
+            .get(name)
+            .filter(|entry| entry.0.elapsed() < self.max_age)
+            .map(|entry| entry.1.clone())
+    }
+
+    pub fn store_describe(&self, generation: u64, name: &str, response: &DescribeCacheResponse) {
+        if self.max_age.is_zero() {
+            return;
+        }
+        // The list lock also orders describe stores against invalidations
+        let _list = self.list.lock().unwrap();
+        if self.generation() == generation {
+            self.describe
+                .insert(name.to_string(), (Instant::now(), response.clone()));
+        }
+    }
+
+    /// Forget every response, called whenever the set of caches or a configuration changes
+    pub fn invalidate(&self) {
+        let mut list = self.list.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        *list = None;
+        self.describe.clear();
+    }
+}
+
+impl Default for AdminResponseCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_ADMIN_RESPONSE_MAX_AGE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalidate_discards_responses_built_before_it() {
+        let cache = AdminResponseCache::new(Duration::from_secs(60));
+
+        let generation = cache.generation();
+        cache.store_list(generation, &ListCachesResponse::new(Vec::new()));
+        assert!(cache.list().is_some());
+
+        // A response built before the invalidation must not be stored after it
+        let stale = cache.generation();
+        cache.invalidate();
+        assert!(cache.list().is_none());
+        cache.store_list(stale, &ListCachesResponse::new(Vec::new()));
+        assert!(cache.list().is_none());
+    }
+
+    #[test]
+    fn test_zero_max_age_disables_caching() {
+        let cache = AdminResponseCache::new(Duration::ZERO);
+        cache.store_list(cache.generation(), &ListCachesResponse::new(Vec::new()));
+        assert!(cache.list().is_none());
+    }
+}
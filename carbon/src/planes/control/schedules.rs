@@ -0,0 +1,248 @@
+use crate::domain::ScheduleRun;
+use crate::planes::control::CacheManager;
+use crate::planes::control::admin_operations::now_millis;
+use crate::ports::ScheduledActionRunner;
+use chrono::{DateTime, Datelike, Duration as TimeDelta, Timelike, Utc};
+use shared::{Error, Result};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// How often the scheduler looks for schedules that are due
+const SCHEDULER_TICK: Duration = Duration::from_secs(1);
+
+/// How far ahead a cron expression is searched for its next match, as Feb 29 comes every
+/// four years
+const SEARCH_YEARS: i64 = 5;
+
+/// A five field cron expression, minute hour day-of-month month day-of-week, matched in UTC
+/// Fields take `*`, numbers, ranges `a-b`, lists `a,b` and steps `*/n` or `a-b/n`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Both day fields are restricted, and as in cron a day matching either runs
+    either_day: bool,
+}
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let expanded = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(Error::InvalidValue(format!(
+                "cron expression '{}' needs five fields: minute hour day month weekday",
+                expr
+            )));
+        };
+
+        // 7 is Sunday as well as 0
+        let weekdays = parse_field(weekday, 0, 7)?;
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays: (weekdays | (weekdays >> 7)) & 0x7f,
+            either_day: day != "*" && weekday != "*",
+        })
+    }
+
+    /// First minute matching the expression after `after`, None when none comes within
+    /// SEARCH_YEARS, such as for February 30
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let limit = after + TimeDelta::days(366 * SEARCH_YEARS);
+        let mut time = after.with_second(0)?.with_nanosecond(0)? + TimeDelta::minutes(1);
+
+        while time <= limit {
+            if !allows(self.months, time.month()) {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = time
+                    .date_naive()
+                    .with_day(1)?
+                    .with_year(year)?
+                    .with_month(month)?
+                    .and_hms_opt(0, 0, 0)?
+                    .and_utc();
+            } else if !self.day_matches(time) {
+                time = time
+                    .date_naive()
+                    .succ_opt()?
+                    .and_hms_opt(0, 0, 0)?
+                    .and_utc();
+            } else if !allows(self.hours, time.hour()) {
+                time = time.with_minute(0)? + TimeDelta::hours(1);
+            } else if !allows(self.minutes, time.minute()) {
+                time += TimeDelta::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    /// Like `next_after`, in milliseconds since the UNIX epoch
+    pub fn next_after_ms(&self, after_ms: u64) -> Option<u64> {
+        let after = DateTime::from_timestamp_millis(after_ms as i64)?;
+        self.next_after(after)
+            .map(|next| next.timestamp_millis() as u64)
+    }
+
+    fn day_matches(&self, time: DateTime<Utc>) -> bool {
+        let day = allows(self.days, time.day());
+        let weekday = allows(self.weekdays, time.weekday().num_days_from_sunday());
+        if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn allows(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+/// Bitmask of the values a field allows, bit n standing for value n
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let invalid = || Error::InvalidValue(format!("invalid cron field '{}'", field));
+    let number = |value: &str| value.parse::<u32>().map_err(|_| invalid());
+
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, number(step)?),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else {
+            match range.split_once('-') {
+                Some((start, end)) => (number(start)?, number(end)?),
+                // `5/15` runs from 5 to the end of the range
+                None if step > 1 => (number(range)?, max),
+                None => (number(range)?, number(range)?),
+            }
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl<K, V> CacheManager<K, V>
+where
+    K: Debug + Hash + Eq + Send + Sync + Clone + 'static,
+    V: Debug + Send + Sync + Clone + 'static,
+{
+    /// Run the schedules as they come due, until the task is aborted
+    /// Each run is a task of its own, so a slow warm-up does not hold up the other schedules
+    pub fn spawn_scheduler(&self, runner: Arc<dyn ScheduledActionRunner>) -> JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(SCHEDULER_TICK);
+            loop {
+                ticker.tick().await;
+
+                for schedule in manager.take_due_schedules(now_millis()) {
+                    let manager = manager.clone();
+                    let runner = runner.clone();
+                    tokio::spawn(async move {
+                        let started_at_ms = now_millis();
+                        let result = runner.run(&schedule.cache, &schedule.action).await;
+                        if let Err(e) = &result {
+                            tracing::warn!(
+                                "Schedule {} on cache '{}' failed: {}",
+                                schedule.id,
+                                schedule.cache,
+                                e
+                            );
+                        }
+                        let run = ScheduleRun {
+                            started_at_ms,
+                            finished_at_ms: now_millis(),
+                            ok: result.is_ok(),
+                            message: result.unwrap_or_else(|e| e.to_string()),
+                        };
+                        if let Err(e) = manager.record_schedule_run(&schedule.id, run).await {
+                            tracing::warn!(
+                                "Failed to record run of schedule {}: {}",
+                                schedule.id,
+                                e
+                            );
+                        }
+                    });
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_run_of_cron_expressions() {
+        // 2025-03-14 was a Friday
+        let now = at(2025, 3, 14, 10, 7);
+
+        let midnight = CronExpr::parse("@midnight").unwrap();
+        assert_eq!(midnight.next_after(now), Some(at(2025, 3, 15, 0, 0)));
+
+        let quarter_hour = CronExpr::parse("*/15 * * * *").unwrap();
+        assert_eq!(quarter_hour.next_after(now), Some(at(2025, 3, 14, 10, 15)));
+
+        let hourly = CronExpr::parse("30 * * * *").unwrap();
+        assert_eq!(hourly.next_after(now), Some(at(2025, 3, 14, 10, 30)));
+
+        let weekdays = CronExpr::parse("0 9 * * 1-5").unwrap();
+        assert_eq!(weekdays.next_after(now), Some(at(2025, 3, 17, 9, 0)));
+
+        let new_year = CronExpr::parse("0 0 1 1 *").unwrap();
+        assert_eq!(new_year.next_after(now), Some(at(2026, 1, 1, 0, 0)));
+
+        let leap_day = CronExpr::parse("0 12 29 2 *").unwrap();
+        assert_eq!(leap_day.next_after(now), Some(at(2028, 2, 29, 12, 0)));
+        assert_eq!(CronExpr::parse("0 0 30 2 *").unwrap().next_after(now), None);
+    }
+
+    #[test]
+    fn test_invalid_cron_expressions() {
+        for expr in [
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+        ] {
+            assert!(CronExpr::parse(expr).is_err(), "{}", expr);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::domain::response::BulkLoadResponse;
+use crate::domain::{BulkEntry, ValueFormat, ValueType};
+use crate::encoding::{ToBytes, decode_document};
+use crate::planes::data::cache_operations::CacheOperationsService;
+use shared::{Error, Result};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Records the HTTP and TCP frontends hand to the store at once
+pub const BULK_LOAD_BATCH_SIZE: usize = 1_000;
+
+/// Loads one cache in batches, for warming it up faster than with a PUT per entry
+/// Subscribers get a single BulkLoaded event from finish, the change log records each entry
+pub struct BulkLoader<'a, K, V>
+where
+    K: Debug + Hash + Eq + Send + Sync + Clone + 'static,
+    V: Debug + Send + Sync + Clone + 'static,
+{
+    service: &'a CacheOperationsService<K, V>,
+    cache_name: String,
+    summary: BulkLoadResponse,
+}
+
+impl<K, V> CacheOperationsService<K, V>
+where
+    K: ToBytes + Debug + Hash + Eq + Send + Sync + Clone + 'static,
+    V: ToBytes + Debug + Send + Sync + Clone + 'static,
+{
+    /// Start a bulk load into `cache_name`, failing if the cache does not exist
+    pub async fn bulk_loader(&self, cache_name: &str) -> Result<BulkLoader<'_, K, V>> {
+        let cache_name = &*self.resolve(cache_name);
+        self.cache_manager()
+            .get_cache(cache_name)
+            .await
+            .ok_or_else(|| Error::CacheNotFound(cache_name.to_string()))?;
+
+        Ok(BulkLoader {
+            service: self,
+            cache_name: cache_name.to_string(),
+            summary: BulkLoadResponse::default(),
+        })
+    }
+}
+
+impl<K, V> BulkLoader<'_, K, V>
+where
+    K: ToBytes + Debug + Hash + Eq + Send + Sync + Clone + 'static,
+    V: ToBytes + Debug + Send + Sync + Clone + 'static,
+{
+    /// Write one batch, counted as a single operation against the cache's ops/sec limit
+    /// A JSON cache rejects the whole batch if any value is not a valid document, as does a cache
+    /// with a value format for values not in it, or with max_value_bytes for a larger value
+    pub async fn load(&mut self, entries: Vec<BulkEntry<K, V>>) -> Result<()> {
+        let _timer = self
+            .service
+            .time_operation("BULKLOAD", &self.cache_name, &[]);
+        let (cache_store, config) = self.service.get_cache_for_write(&self.cache_name).await?;
+
+        let documents = if config.value_type == ValueType::Json {
+            let documents = entries
+                .iter()
+                .map(|entry| {
+                    serde_json::from_slice::<serde_json::Value>(&entry.value.to_bytes())
+                        .map_err(|e| Error::InvalidValue(format!("value is not valid JSON: {}", e)))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Some(documents)
+        } else {
+            None
+        };
+
+        for entry in &entries {
+            config.check_value_size(entry.value.to_bytes().len())?;
+        }
+
+        if config.value_format != ValueFormat::Raw {
+            for entry in &entries {
+                decode_document(&entry.value.to_bytes(), config.value_format)?;
+            }
+        }
+
+        // The whole batch is one write against the principal's quota
+        let charged = match self.service.quotas().filter(|quotas| quotas.attributed()) {
+            Some(quotas) => {
+                let charges: Vec<(Vec<u8>, u64)> = entries
+                    .iter()
+                    .map(|entry| {
+                        let key = entry.key.to_bytes().into_owned();
+                        let size = (key.len() + entry.value.to_bytes().len()) as u64;
+                        (key, size)
+                    })
+                    .collect();
+                let batch: Vec<(&[u8], u64)> = charges
+                    .iter()
+                    .map(|(key, size)| (key.as_slice(), *size))
+                    .collect();
+                quotas
+                    .check_write(&self.cache_name, &batch)?
+                    .map(|reservation| (reservation, charges))
+            }
+            None => None,
+        };
+
+        let keys: Vec<K> = entries.iter().map(|entry| entry.key.clone()).collect();
+        let mut logged = self
+            .service
+            .change_log_policy(&self.cache_name)
+            .map(|policy| {
+                let values: Vec<Vec<u8>> = entries
+                    .iter()
+                    .map(|entry| entry.value.to_bytes().into_owned())
+                    .collect();
+                (policy, values)
+            });
+        let admitted = cache_store.put_batch(entries).await?;
+
+        for (index, (key, admitted)) in keys.iter().zip(admitted).enumerate() {
+            if !admitted {
+                self.summary.not_admitted += 1;
+                continue;
+            }
+
+            self.summary.loaded += 1;
+            if let Some((reservation, charges)) = &charged {
+                let (key, size) = &charges[index];
+                reservation.record(&self.cache_name, key, *size);
+            }
+            let document = documents.as_ref().map(|documents| &documents[index]);
+
+            // A PUT may have replaced the entry since the batch went in, its tags, content
+            // type and index entries are then the ones to keep
+            let _guard = self.service.lock_key(&self.cache_name, key).await;
+            if let Some(document) = document {
+                let current = cache_store.get(key).await.ok().and_then(|current| {
+                    serde_json::from_slice::<serde_json::Value>(&current.message.to_bytes()).ok()
+                });
+                if current.as_ref() != Some(document) {
+                    continue;
+                }
+            }
+            self.service.entry_loaded(&self.cache_name, key, document);
+            if let Some((policy, values)) = &mut logged {
+                let value = std::mem::take(&mut values[index]);
+                self.service
+                    .log_loaded(&self.cache_name, policy, &key.to_bytes(), value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// What has been written so far
+    pub fn summary(&self) -> BulkLoadResponse {
+        self.summary
+    }
+
+    /// End the load, broadcasting one BulkLoaded event if anything was written
+    pub fn finish(self) -> BulkLoadResponse {
+        if self.summary.loaded > 0 {
+            self.service
+                .bulk_loaded(&self.cache_name, self.summary.loaded);
+        }
+        self.summary
+    }
+}
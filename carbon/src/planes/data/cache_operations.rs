@@ -1,17 +1,34 @@
 use crate::domain::response::{DeleteResponse, GetResponse, PutResponse};
+use crate::domain::{CacheConfig, EntryMetadata, EventPolicy, ValueFormat, ValueType};
+use crate::encoding::{ToBytes, decode_document};
 use crate::events::{
-    CacheItemEvent, ItemAddedEvent, ItemDeletedEvent, ItemUpdatedEvent, now_timestamp,
+    BulkLoadedEvent, CacheItemEvent, CacheRenamedEvent, CloneProgressEvent, ItemAddedEvent,
+    ItemDeletedEvent, ItemExpiredEvent, ItemUpdatedEvent, now_timestamp,
 };
 use crate::planes::control::CacheManager;
+use crate::planes::data::change_log::ChangeLog;
+use crate::planes::data::content_types::ContentTypes;
+use crate::planes::data::key_locks::KeyLocks;
+use crate::planes::data::key_tracking::KeyTracking;
+use crate::planes::data::latency::{LatencyOperation, LatencyTracker};
+use crate::planes::data::leases::LeaseTable;
+use crate::planes::data::maintenance::Maintenance;
 use crate::planes::data::operation::CacheOperations;
-use crate::ports::CacheStore;
+use crate::planes::data::ops_limiter::OpsLimiter;
+use crate::planes::data::quotas::QuotaTracker;
+use crate::planes::data::slow_log::{OperationTimer, SlowLog};
+use crate::planes::data::tag_index::TagIndex;
+use crate::ports::{CacheStore, IndexMaintainer};
 use async_trait::async_trait;
-use bytes::Bytes;
+use dashmap::DashMap;
+use rand::Rng;
 use shared::{Error, Result};
+use std::borrow::Cow;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::time::Duration;
+use tokio::sync::{MutexGuard, Notify, broadcast};
 
 /// Application service that orchestrates cache operations
 /// This is the main entry point for all cache operations in the application core
@@ -23,6 +40,18 @@ where
 {
     cache_manager: CacheManager<K, V>,
     event_broadcaster: Option<broadcast::Sender<CacheItemEvent>>,
+    key_locks: Arc<KeyLocks>,
+    leases: Arc<LeaseTable<K>>,
+    list_waiters: Arc<DashMap<(String, K), Arc<Notify>>>,
+    index_maintainer: Option<Arc<dyn IndexMaintainer>>,
+    slow_log: Option<Arc<SlowLog>>,
+    ops_limiter: Arc<OpsLimiter>,
+    latencies: Arc<LatencyTracker>,
+    quotas: Option<Arc<QuotaTracker>>,
+    change_log: Option<Arc<ChangeLog>>,
+    maintenance: Arc<Maintenance>,
+    key_tracking: Option<Arc<KeyTracking>>,
+    event_key_secret: Arc<[u8]>,
 }
 
 /// Factory methods to instantiate CacheOperationsService
@@ -35,6 +64,18 @@ where
         Self {
             cache_manager,
             event_broadcaster: None,
+            key_locks: Arc::new(KeyLocks::new()),
+            leases: Arc::new(LeaseTable::new()),
+            list_waiters: Arc::new(DashMap::new()),
+            index_maintainer: None,
+            slow_log: None,
+            ops_limiter: Arc::new(OpsLimiter::default()),
+            latencies: Arc::new(LatencyTracker::new()),
+            quotas: None,
+            change_log: None,
+            maintenance: Arc::new(Maintenance::new()),
+            key_tracking: None,
+            event_key_secret: random_event_key_secret(),
         }
     }
 
@@ -45,19 +86,332 @@ where
         Self {
             cache_manager,
             event_broadcaster: Some(broadcaster),
+            key_locks: Arc::new(KeyLocks::new()),
+            leases: Arc::new(LeaseTable::new()),
+            list_waiters: Arc::new(DashMap::new()),
+            index_maintainer: None,
+            slow_log: None,
+            ops_limiter: Arc::new(OpsLimiter::default()),
+            latencies: Arc::new(LatencyTracker::new()),
+            quotas: None,
+            change_log: None,
+            maintenance: Arc::new(Maintenance::new()),
+            key_tracking: None,
+            event_key_secret: random_event_key_secret(),
         }
     }
 
+    /// Keep secondary indexes in step with writes to JSON-typed caches
+    pub fn with_index_maintainer(mut self, index_maintainer: Arc<dyn IndexMaintainer>) -> Self {
+        self.index_maintainer = Some(index_maintainer);
+        self
+    }
+
+    /// Record operations slower than the log's threshold
+    pub fn with_slow_log(mut self, slow_log: Arc<SlowLog>) -> Self {
+        self.slow_log = Some(slow_log);
+        self
+    }
+
+    /// Enforce ops/sec limits, share one limiter across frontends for a common budget
+    pub fn with_ops_limiter(mut self, ops_limiter: Arc<OpsLimiter>) -> Self {
+        self.ops_limiter = ops_limiter;
+        self
+    }
+
+    pub fn ops_limiter(&self) -> &Arc<OpsLimiter> {
+        &self.ops_limiter
+    }
+
+    /// Record PUT, GET and DELETE latencies in `latencies`, share one tracker across frontends
+    /// to see their operations together
+    pub fn with_latency_tracker(mut self, latencies: Arc<LatencyTracker>) -> Self {
+        self.latencies = latencies;
+        self
+    }
+
+    pub fn latencies(&self) -> &Arc<LatencyTracker> {
+        &self.latencies
+    }
+
+    /// Charge writes to the principal making them and enforce their quotas
+    pub fn with_quota_tracker(mut self, quotas: Arc<QuotaTracker>) -> Self {
+        self.quotas = Some(quotas);
+        self
+    }
+
+    pub fn quotas(&self) -> Option<&Arc<QuotaTracker>> {
+        self.quotas.as_ref()
+    }
+
+    /// Append every event to `change_log` too, share one log across frontends to record
+    /// their writes together
+    pub fn with_change_log(mut self, change_log: Arc<ChangeLog>) -> Self {
+        self.change_log = Some(change_log);
+        self
+    }
+
+    pub fn change_log(&self) -> Option<&Arc<ChangeLog>> {
+        self.change_log.as_ref()
+    }
+
+    /// Key the hashes of event keys under `secret`, share one across frontends and nodes so
+    /// they hash a key alike
+    pub fn with_event_key_secret(mut self, secret: impl Into<Arc<[u8]>>) -> Self {
+        self.event_key_secret = secret.into();
+        self
+    }
+
+    /// Refuse writes while `maintenance` says so, share one across frontends to refuse them
+    /// over every protocol
+    pub fn with_maintenance(mut self, maintenance: Arc<Maintenance>) -> Self {
+        self.maintenance = maintenance;
+        self
+    }
+
+    pub fn maintenance(&self) -> &Arc<Maintenance> {
+        &self.maintenance
+    }
+
+    /// Tell clients tracking keys when writes make their copies stale, share one table across
+    /// frontends so writes over every protocol reach them
+    pub fn with_key_tracking(mut self, key_tracking: Arc<KeyTracking>) -> Self {
+        self.key_tracking = Some(key_tracking);
+        self
+    }
+
+    pub fn key_tracking(&self) -> Option<&Arc<KeyTracking>> {
+        self.key_tracking.as_ref()
+    }
+
+    /// Tell clients tracking keys of `cache_name` that all their copies are stale, for changes
+    /// no write reports such as an alias moving to another cache
+    pub fn invalidate_tracked_cache(&self, cache_name: &str) {
+        if let Some(ref key_tracking) = self.key_tracking {
+            key_tracking.invalidate_cache(cache_name);
+        }
+    }
+
+    /// Tell clients tracking `key` that their copies are stale, under the name of the cache it
+    /// is in and every alias of it
+    fn key_changed(&self, cache_name: &str, key: &[u8]) {
+        let Some(ref key_tracking) = self.key_tracking else {
+            return;
+        };
+        if !key_tracking.has_clients() {
+            return;
+        }
+        let cache = self
+            .cache_manager
+            .alias_target(cache_name)
+            .unwrap_or_else(|| cache_name.to_string());
+        for alias in self.cache_manager.aliases() {
+            if alias.cache == cache {
+                key_tracking.invalidate(&alias.alias, key);
+            }
+        }
+        key_tracking.invalidate(&cache, key);
+    }
+
+    /// Forget the indexes, quota charges, ops/sec budget, latencies, change log, maintenance
+    /// mode, lock leases and tracked keys of a cache removed for good
+    pub fn cache_purged(&self, cache_name: &str) {
+        self.invalidate_tracked_cache(cache_name);
+        self.leases.remove_cache(cache_name);
+        if let Some(ref maintainer) = self.index_maintainer {
+            maintainer.on_drop(cache_name);
+        }
+        if let Some(ref quotas) = self.quotas {
+            quotas.remove_cache(cache_name);
+        }
+        self.ops_limiter.remove_cache(cache_name);
+        self.latencies.remove_cache(cache_name);
+        self.maintenance.remove_cache(cache_name);
+        if let Some(ref change_log) = self.change_log
+            && let Err(e) = change_log.remove_cache(cache_name)
+        {
+            tracing::warn!("Failed to delete the change log of '{}': {}", cache_name, e);
+        }
+    }
+
+    /// Carry the indexes, quota charges, change log, maintenance mode and lock leases of a
+    /// renamed cache over to its new name and tell subscribers, its ops/sec budget, latencies
+    /// and tracked keys start over
+    pub fn cache_renamed(&self, from: &str, to: &str) {
+        self.invalidate_tracked_cache(from);
+        self.leases.rename_cache(from, to);
+        if let Some(ref maintainer) = self.index_maintainer {
+            maintainer.on_rename(from, to);
+        }
+        if let Some(ref quotas) = self.quotas {
+            quotas.rename_cache(from, to);
+        }
+        self.ops_limiter.remove_cache(from);
+        self.latencies.remove_cache(from);
+        self.maintenance.rename_cache(from, to);
+        if let Some(ref change_log) = self.change_log
+            && let Err(e) = change_log.rename_cache(from, to)
+        {
+            tracing::warn!("Failed to move the change log of '{}': {}", from, e);
+        }
+
+        if self.event_policy(to).is_some() {
+            let event = CacheItemEvent::Renamed(CacheRenamedEvent {
+                cache_name: to.to_string(),
+                previous_name: from.to_string(),
+                timestamp: now_timestamp(),
+            });
+            self.publish(event);
+        }
+    }
+
+    /// Name of the cache `cache_name` reaches, the cache an alias points to or the name itself
+    /// Each operation resolves it once up front and keeps its key lock, tags, content types,
+    /// indexes, quota charges and events under it, so writes through an alias and through the
+    /// cache's own name meet
+    pub(crate) fn resolve<'a>(&self, cache_name: &'a str) -> Cow<'a, str> {
+        self.cache_manager.resolve(cache_name)
+    }
+
+    pub(crate) fn cache_manager(&self) -> &CacheManager<K, V> {
+        &self.cache_manager
+    }
+
+    /// Tags of entries in every cache, held by the cache manager so all frontends share them
+    pub(crate) fn tag_index(&self) -> &TagIndex<K> {
+        self.cache_manager.tag_index()
+    }
+
+    /// Media types of entries uploaded with one, held by the cache manager like tags
+    pub(crate) fn content_types(&self) -> &ContentTypes<K> {
+        self.cache_manager.content_types()
+    }
+
+    /// Start timing an operation, None when the slow log is off
+    pub(crate) fn time_operation<'a>(
+        &'a self,
+        operation: &'static str,
+        cache_name: &'a str,
+        key: &[u8],
+    ) -> Option<OperationTimer<'a>> {
+        self.slow_log
+            .as_deref()
+            .map(|slow_log| OperationTimer::start(slow_log, operation, cache_name, key))
+    }
+
     /// Helper method to look up a cache by name
     async fn get_cache_store(&self, cache_name: &str) -> Result<Arc<dyn CacheStore<K, V>>> {
+        self.get_cache(cache_name).await.map(|(store, _)| store)
+    }
+
+    /// Helper method to look up a cache and its configuration by name
+    /// Each lookup is charged as one operation against the ops/sec limits, so a command looks
+    /// its cache up this way once
+    pub(crate) async fn get_cache(
+        &self,
+        cache_name: &str,
+    ) -> Result<(Arc<dyn CacheStore<K, V>>, Arc<CacheConfig>)> {
+        let (store, config) = self.find_cache(cache_name).await?;
+        self.ops_limiter.check(cache_name, config.max_ops_per_sec)?;
+        Ok((store, config))
+    }
+
+    /// Look up a cache to change its contents, refused while it or the cache it is an alias of
+    /// is in maintenance mode
+    pub(crate) async fn get_cache_for_write(
+        &self,
+        cache_name: &str,
+    ) -> Result<(Arc<dyn CacheStore<K, V>>, Arc<CacheConfig>)> {
+        self.check_writable(cache_name)?;
+        self.get_cache(cache_name).await
+    }
+
+    /// get_cache_for_write for a step of a command that was already charged
+    async fn find_cache_for_write(
+        &self,
+        cache_name: &str,
+    ) -> Result<(Arc<dyn CacheStore<K, V>>, Arc<CacheConfig>)> {
+        self.check_writable(cache_name)?;
+        self.find_cache(cache_name).await
+    }
+
+    async fn find_cache(
+        &self,
+        cache_name: &str,
+    ) -> Result<(Arc<dyn CacheStore<K, V>>, Arc<CacheConfig>)> {
         self.cache_manager
-            .get_cache_store(cache_name)
+            .get_cache(cache_name)
             .await
             .ok_or_else(|| Error::CacheNotFound(cache_name.to_string()))
     }
+
+    fn check_writable(&self, cache_name: &str) -> Result<()> {
+        self.maintenance.check_writable(cache_name)?;
+        if let Some(target) = self.cache_manager.alias_target(cache_name) {
+            self.maintenance.check_writable(&target)?;
+        }
+        Ok(())
+    }
+
+    /// What events about `cache_name` may carry, None when they are neither broadcast nor
+    /// logged
+    fn event_policy(&self, cache_name: &str) -> Option<EventPolicy> {
+        if self.event_broadcaster.is_none() && self.change_log.is_none() {
+            return None;
+        }
+        let policy = self.cache_manager.event_policy(cache_name)?;
+        policy.enabled.then_some(policy)
+    }
+
+    /// Append `event` to the change log and broadcast it, returning how many subscribers
+    /// received it
+    fn publish(&self, event: CacheItemEvent) -> Option<usize> {
+        if let Some(ref change_log) = self.change_log
+            && let Err(e) = change_log.append(&event)
+        {
+            tracing::warn!(
+                "Failed to log change to cache '{}': {}",
+                event.cache_name(),
+                e
+            );
+        }
+        self.event_broadcaster.as_ref()?.send(event).ok()
+    }
+
+    /// Serialise read-modify-write operations on a single key
+    pub(crate) async fn lock_key(&self, cache_name: &str, key: &K) -> MutexGuard<'_, ()> {
+        self.key_locks.lock(cache_name, key).await
+    }
+
+    /// Leases taken with LOCK, kept apart from the entries of every cache
+    pub(crate) fn leases(&self) -> &LeaseTable<K> {
+        &self.leases
+    }
+
+    /// Notifier that blocking pops on `key` wait on
+    pub(crate) fn list_waiter(&self, cache_name: &str, key: &K) -> Arc<Notify> {
+        self.list_waiters
+            .entry((cache_name.to_string(), key.clone()))
+            .or_insert_with(|| Arc::new(Notify::new()))
+            .clone()
+    }
+
+    /// Wake blocking pops waiting on `key`, if any
+    pub(crate) fn notify_list_waiters(&self, cache_name: &str, key: &K) {
+        if let Some(notify) = self.list_waiters.get(&(cache_name.to_string(), key.clone())) {
+            notify.notify_waiters();
+        }
+    }
+
+    /// Drop the notifier for `key` once no blocking pop holds it any more
+    pub(crate) fn release_list_waiter(&self, cache_name: &str, key: &K) {
+        self.list_waiters
+            .remove_if(&(cache_name.to_string(), key.clone()), |_, notify| {
+                Arc::strong_count(notify) == 1
+            });
+    }
 }
 
-// Generic implementation removed - using specialized implementation below for String/Vec<u8>
 impl<K, V> std::fmt::Debug for CacheOperationsService<K, V>
 where
     K: Debug + Hash + Eq + Send + Sync + Clone + 'static,
@@ -70,101 +424,587 @@ where
     }
 }
 
-// Specialized implementation for Vec<u8>/Vec<u8> with event broadcasting
-#[async_trait]
-impl CacheOperations<Vec<u8>, Bytes> for CacheOperationsService<Vec<u8>, Bytes> {
-    /// Execute a PUT operation on a named cache with event broadcasting
-    async fn put(&self, cache_name: &str, key: Vec<u8>, value: Bytes) -> Result<PutResponse> {
-        let cache_store = self.get_cache_store(cache_name).await?;
+// Internal write and delete paths shared by the basic and data structure commands
+// Keys and values reach events and secondary indexes in their byte encoding
+impl<K, V> CacheOperationsService<K, V>
+where
+    K: ToBytes + Debug + Hash + Eq + Send + Sync + Clone + 'static,
+    V: ToBytes + Debug + Send + Sync + Clone + 'static,
+{
+    /// PUT for callers already holding the key lock, which PUT takes so the entry, its
+    /// tags, content type and index entries change together
+    /// Callers charge the operation against the ops/sec limits, by looking up the cache first
+    /// `cache_name` is the name the caller resolved, never an alias
+    pub(crate) async fn put_locked(
+        &self,
+        cache_name: &str,
+        key: K,
+        value: V,
+    ) -> Result<PutResponse> {
+        let (cache_store, config) = self.find_cache_for_write(cache_name).await?;
+        let _latency = self.latencies.start(cache_name, LatencyOperation::Put);
 
-        // Check existence of a key in the cache ONLY if we have a broadcaster
-        let existed = if self.event_broadcaster.is_some() {
-            cache_store.exists(&key).await?.exists
+        // JSON caches only accept well-formed documents
+        let document = if config.value_type == ValueType::Json {
+            let document = serde_json::from_slice::<serde_json::Value>(&value.to_bytes())
+                .map_err(|e| Error::InvalidValue(format!("value is not valid JSON: {}", e)))?;
+            Some(document)
         } else {
-            false
+            None
         };
 
-        // Perform the put operation
-        let result = cache_store.put(key.clone(), value.clone()).await?;
+        // Caches with a value format only store documents in it
+        if config.value_format != ValueFormat::Raw {
+            decode_document(&value.to_bytes(), config.value_format)?;
+        }
 
-        if let Some(broadcaster) = self.event_broadcaster.clone() {
-            let cache_name = cache_name.to_string();
-            tokio::spawn(async move {
-                // Broadcast event if broadcaster is configured
-                let event = if existed {
-                    CacheItemEvent::Updated(ItemUpdatedEvent {
-                        cache_name,
-                        key,
-                        value: value.to_vec(),
-                        timestamp: now_timestamp(),
-                    })
-                } else {
-                    CacheItemEvent::Added(ItemAddedEvent {
-                        cache_name,
-                        key,
-                        value: value.to_vec(),
-                        timestamp: now_timestamp(),
-                    })
-                };
-
-                match broadcaster.send(event) {
-                    Ok(count) => {
-                        tracing::debug!(
-                            "Broadcasted {} event to {} subscriber(s)",
-                            if existed { "updated" } else { "added" },
-                            count
-                        );
-                    }
-                    Err(_) => {
-                        tracing::warn!("No subscribers for event");
-                    }
-                }
-            });
+        let result = self
+            .write_entry(cache_name, &cache_store, key.clone(), value)
+            .await?;
+        if result.admitted {
+            // A plain PUT replaces the entry, tags and content type included, a refused one
+            // leaves the entry it did not replace as it was
+            self.tag_index().remove_key(cache_name, &key);
+            self.content_types().remove_key(cache_name, &key);
+            if let (Some(maintainer), Some(document)) = (&self.index_maintainer, document) {
+                maintainer.on_put(cache_name, &key.to_bytes(), &document);
+            }
+        }
+        Ok(result)
+    }
+
+    /// DELETE for callers already holding the key lock, who charged the operation like they
+    /// do for put_locked
+    pub(crate) async fn delete_locked(&self, cache_name: &str, key: &K) -> Result<DeleteResponse> {
+        let (cache_store, _) = self.find_cache_for_write(cache_name).await?;
+        let _latency = self.latencies.start(cache_name, LatencyOperation::Delete);
+        let result = cache_store.delete(key).await?;
+        self.tag_index().remove_key(cache_name, key);
+        self.content_types().remove_key(cache_name, key);
+
+        if result.deleted {
+            self.entry_deleted(cache_name, key);
         }
 
         Ok(result)
     }
 
-    /// Execute a GET operation on a named cache (no event broadcasting)
-    async fn get(&self, cache_name: &str, key: &Vec<u8>) -> Result<GetResponse<Bytes>> {
-        let cache_store = self.get_cache_store(cache_name).await?;
-        cache_store.get(key).await
+    /// Store a value and broadcast the resulting Added/Updated event
+    /// Values over the cache's max_value_bytes are refused
+    pub(crate) async fn write_entry(
+        &self,
+        cache_name: &str,
+        cache_store: &Arc<dyn CacheStore<K, V>>,
+        key: K,
+        value: V,
+    ) -> Result<PutResponse> {
+        if let Some(config) = self.cache_manager.cache_config(cache_name) {
+            config.check_value_size(value.to_bytes().len())?;
+        }
+
+        // Entries are charged for their encoded key and value
+        let charged = match self.quotas.as_deref().filter(|quotas| quotas.attributed()) {
+            Some(quotas) => {
+                let encoded_key = key.to_bytes().into_owned();
+                let size = (encoded_key.len() + value.to_bytes().len()) as u64;
+                quotas
+                    .check_write(cache_name, &[(encoded_key.as_slice(), size)])?
+                    .map(|reservation| (reservation, encoded_key, size))
+            }
+            None => None,
+        };
+
+        // Encoded up front, the store takes ownership of the key and value
+        let encoded = self.event_policy(cache_name).map(|policy| {
+            (
+                policy,
+                key.to_bytes().into_owned(),
+                value.to_bytes().into_owned(),
+            )
+        });
+
+        // Kept for clients tracking the key, the store takes ownership of it
+        let tracked_key = self
+            .key_tracking
+            .is_some()
+            .then(|| key.to_bytes().into_owned());
+
+        // The store reports whether the key existed as part of the write
+        let result = cache_store.put(key, value).await?;
+
+        // Nothing changed when the store turned the write away
+        if !result.admitted {
+            return Ok(result);
+        }
+
+        if let Some(key) = tracked_key {
+            self.key_changed(cache_name, &key);
+        }
+
+        if let Some((reservation, key, size)) = charged {
+            reservation.record(cache_name, &key, size);
+        }
+
+        let existed = !result.created;
+
+        // Published before returning, so the change log has writes to a key in their order
+        if let Some((policy, key, value)) = encoded {
+            let key = policy.event_key(&key, &self.event_key_secret);
+            let value_size = value.len() as u64;
+            let value = policy.event_value(value);
+            let cache_name = cache_name.to_string();
+            let event = if existed {
+                CacheItemEvent::Updated(ItemUpdatedEvent {
+                    cache_name,
+                    key,
+                    value,
+                    value_size,
+                    timestamp: now_timestamp(),
+                })
+            } else {
+                CacheItemEvent::Added(ItemAddedEvent {
+                    cache_name,
+                    key,
+                    value,
+                    value_size,
+                    timestamp: now_timestamp(),
+                })
+            };
+
+            match self.publish(event) {
+                Some(count) => {
+                    tracing::debug!(
+                        "Broadcasted {} event to {} subscriber(s)",
+                        if existed { "updated" } else { "added" },
+                        count
+                    );
+                }
+                None if self.event_broadcaster.is_some() => {
+                    tracing::warn!("No subscribers for event");
+                }
+                None => {}
+            }
+        }
+
+        Ok(result)
     }
 
-    /// Execute a DELETE operation on a named cache with event broadcasting
-    async fn delete(&self, cache_name: &str, key: &Vec<u8>) -> Result<DeleteResponse> {
-        let cache_store = self.get_cache_store(cache_name).await?;
-        let result = cache_store.delete(key).await?;
+    /// Keep secondary indexes, subscribers and tracking clients in step with an entry that was
+    /// removed
+    pub(crate) fn entry_deleted(&self, cache_name: &str, key: &K) {
+        let key = key.to_bytes();
+        self.key_changed(cache_name, &key);
+        if let Some(ref maintainer) = self.index_maintainer {
+            maintainer.on_delete(cache_name, &key);
+        }
+        if let Some(ref quotas) = self.quotas {
+            quotas.record_delete(cache_name, &key);
+        }
 
-        // Broadcast event only if key actually existed (was deleted)
-        if result.deleted
-            && let Some(ref broadcaster) = self.event_broadcaster
-        {
+        if let Some(policy) = self.event_policy(cache_name) {
             let event = CacheItemEvent::Deleted(ItemDeletedEvent {
                 cache_name: cache_name.to_string(),
-                key: key.clone(),
+                key: policy.event_key(&key, &self.event_key_secret),
                 timestamp: now_timestamp(),
             });
 
-            match broadcaster.send(event) {
-                Ok(subscriber_count) => {
+            match self.publish(event) {
+                Some(subscriber_count) => {
                     tracing::debug!(
-                        "Broadcasted deleted event for key '{:?}' in cache '{}' to {} subscriber(s)",
-                        String::from_utf8_lossy(key),
+                        "Broadcasted deleted event in cache '{}' to {} subscriber(s)",
                         cache_name,
                         subscriber_count
                     );
                 }
-                Err(_) => {
-                    tracing::warn!(
-                        "No subscribers for deleted event on key '{:?}' in cache '{}'",
-                        String::from_utf8_lossy(key),
-                        cache_name
-                    );
+                None if self.event_broadcaster.is_some() => {
+                    tracing::warn!("No subscribers for deleted event in cache '{}'", cache_name);
                 }
+                None => {}
             }
         }
+    }
+
+    /// Keep tags, content types and secondary indexes in step with an entry a bulk load wrote
+    /// Like a plain PUT, the entry loses its tags and content type
+    pub(crate) fn entry_loaded(
+        &self,
+        cache_name: &str,
+        key: &K,
+        document: Option<&serde_json::Value>,
+    ) {
+        self.tag_index().remove_key(cache_name, key);
+        self.content_types().remove_key(cache_name, key);
+        self.key_changed(cache_name, &key.to_bytes());
+
+        if let (Some(maintainer), Some(document)) = (&self.index_maintainer, document) {
+            maintainer.on_put(cache_name, &key.to_bytes(), document);
+        }
+    }
+
+    /// Forget an entry the store dropped by itself, in tags, content types, secondary indexes,
+    /// quota charges and tracking clients
+    /// Nothing is published, subscribers are not told about evictions
+    pub(crate) fn entry_evicted(&self, cache_name: &str, key: &K) {
+        self.tag_index().remove_key(cache_name, key);
+        self.content_types().remove_key(cache_name, key);
+
+        let key = key.to_bytes();
+        self.key_changed(cache_name, &key);
+        if let Some(ref maintainer) = self.index_maintainer {
+            maintainer.on_delete(cache_name, &key);
+        }
+        if let Some(ref quotas) = self.quotas {
+            quotas.record_delete(cache_name, &key);
+        }
+    }
+
+    /// How events about `cache_name` are logged, None when there is no change log or the cache
+    /// sends no events
+    pub(crate) fn change_log_policy(&self, cache_name: &str) -> Option<EventPolicy> {
+        self.change_log.as_ref()?;
+        self.event_policy(cache_name)
+    }
+
+    /// Log an entry a bulk load wrote, so readers catching up from the change log see each key
+    /// Logged as an update, a load does not tell new entries from replaced ones
+    pub(crate) fn log_loaded(
+        &self,
+        cache_name: &str,
+        policy: &EventPolicy,
+        key: &[u8],
+        value: Vec<u8>,
+    ) {
+        let Some(ref change_log) = self.change_log else {
+            return;
+        };
+        let event = CacheItemEvent::Updated(ItemUpdatedEvent {
+            cache_name: cache_name.to_string(),
+            key: policy.event_key(key, &self.event_key_secret),
+            value_size: value.len() as u64,
+            value: policy.event_value(value),
+            timestamp: now_timestamp(),
+        });
+        if let Err(e) = change_log.append(&event) {
+            tracing::warn!("Failed to log change to cache '{}': {}", cache_name, e);
+        }
+    }
+
+    /// Tell subscribers a bulk load finished, in place of an event per entry
+    pub(crate) fn bulk_loaded(&self, cache_name: &str, loaded: u64) {
+        if self.event_policy(cache_name).is_some() {
+            let event = CacheItemEvent::BulkLoaded(BulkLoadedEvent {
+                cache_name: cache_name.to_string(),
+                loaded,
+                timestamp: now_timestamp(),
+            });
+
+            if let Some(subscriber_count) = self.publish(event) {
+                tracing::debug!(
+                    "Broadcasted bulk load of {} entries in cache '{}' to {} subscriber(s)",
+                    loaded,
+                    cache_name,
+                    subscriber_count
+                );
+            }
+        }
+    }
+
+    /// Tell subscribers how far copying entries into a clone has come
+    pub(crate) fn clone_progress(&self, progress: CloneProgressEvent) {
+        if self.event_policy(&progress.cache_name).is_some() {
+            let cache_name = progress.cache_name.clone();
+            let copied = progress.copied;
+            if let Some(subscriber_count) = self.publish(CacheItemEvent::CloneProgress(progress)) {
+                tracing::debug!(
+                    "Broadcasted {} entries copied into clone '{}' to {} subscriber(s)",
+                    copied,
+                    cache_name,
+                    subscriber_count
+                );
+            }
+        }
+    }
+
+    /// Keep secondary indexes, tags and subscribers in step with an entry the reaper removed
+    pub(crate) fn entry_expired(&self, cache_name: &str, key: &K) {
+        self.entry_evicted(cache_name, key);
+
+        let key = key.to_bytes();
+        if let Some(policy) = self.event_policy(cache_name) {
+            let event = CacheItemEvent::Expired(ItemExpiredEvent {
+                cache_name: cache_name.to_string(),
+                key: policy.event_key(&key, &self.event_key_secret),
+                timestamp: now_timestamp(),
+            });
+
+            // Expiry is routine, a missing subscriber is not worth a warning per key
+            if let Some(subscriber_count) = self.publish(event) {
+                tracing::debug!(
+                    "Broadcasted expired event in cache '{}' to {} subscriber(s)",
+                    cache_name,
+                    subscriber_count
+                );
+            }
+        }
+    }
+}
+
+/// Secret of services not given one, their hashed event keys only match within this process
+fn random_event_key_secret() -> Arc<[u8]> {
+    let mut secret = [0u8; 32];
+    rand::rng().fill(&mut secret);
+    Arc::from(&secret[..])
+}
 
+// Generic implementation, keys and values only need a byte encoding for events and indexes
+#[async_trait]
+impl<K, V> CacheOperations<K, V> for CacheOperationsService<K, V>
+where
+    K: ToBytes + Debug + Hash + Eq + Send + Sync + Clone + 'static,
+    V: ToBytes + Debug + Send + Sync + Clone + 'static,
+{
+    /// Execute a PUT operation on a named cache with event broadcasting
+    async fn put(&self, cache_name: &str, key: K, value: V) -> Result<PutResponse> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("PUT", cache_name, &key.to_bytes());
+        self.get_cache_for_write(cache_name).await?;
+        let _guard = self.lock_key(cache_name, &key).await;
+        self.put_locked(cache_name, key, value).await
+    }
+
+    /// Execute a GET operation on a named cache (no event broadcasting)
+    async fn get(&self, cache_name: &str, key: &K) -> Result<GetResponse<V>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("GET", cache_name, &key.to_bytes());
+        let cache_store = self.get_cache_store(cache_name).await?;
+        let _latency = self.latencies.start(cache_name, LatencyOperation::Get);
+        cache_store.get(key).await
+    }
+
+    /// Execute a DELETE operation on a named cache with event broadcasting
+    async fn delete(&self, cache_name: &str, key: &K) -> Result<DeleteResponse> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("DELETE", cache_name, &key.to_bytes());
+        self.get_cache_for_write(cache_name).await?;
+        let _guard = self.lock_key(cache_name, key).await;
+        self.delete_locked(cache_name, key).await
+    }
+
+    /// Execute a GETDEL operation, broadcasting the delete like DELETE does
+    async fn getdel(&self, cache_name: &str, key: &K) -> Result<GetResponse<V>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("GETDEL", cache_name, &key.to_bytes());
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+        let _guard = self.lock_key(cache_name, key).await;
+        let result = cache_store.get_and_delete(key).await?;
+        self.tag_index().remove_key(cache_name, key);
+        self.content_types().remove_key(cache_name, key);
+        self.entry_deleted(cache_name, key);
         Ok(result)
     }
+
+    /// Execute a GETEX operation (no event broadcasting)
+    async fn getex(
+        &self,
+        cache_name: &str,
+        key: &K,
+        ttl: Option<Duration>,
+    ) -> Result<GetResponse<V>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("GETEX", cache_name, &key.to_bytes());
+        if ttl.is_some_and(|ttl| ttl.is_zero()) {
+            return Err(Error::InvalidValue(
+                "expiry ttl must be positive".to_string(),
+            ));
+        }
+
+        // Changing the expiry is a write
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+        cache_store.get_and_expire(key, ttl).await
+    }
+
+    /// Read an entry's access statistics (no event broadcasting)
+    async fn metadata(&self, cache_name: &str, key: &K) -> Result<EntryMetadata> {
+        let cache_name = &*self.resolve(cache_name);
+        let cache_store = self.get_cache_store(cache_name).await?;
+        cache_store.metadata(key).await?.ok_or_else(|| {
+            Error::InvalidValue(format!(
+                "cache '{}' does not track entry metadata",
+                cache_name
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::response::ExistsResponse;
+    use crate::domain::{CacheEvictionStrategy, EvictionAlgorithm};
+    use crate::planes::control::operation::AdminOperations;
+    use crate::planes::data::operation::TagOperations;
+    use bytes::Bytes;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Store keeping its entries in a map
+    #[derive(Default)]
+    struct MapStore {
+        entries: Mutex<HashMap<Vec<u8>, Bytes>>,
+    }
+
+    #[async_trait]
+    impl CacheStore<Vec<u8>, Bytes> for MapStore {
+        async fn exists(&self, key: &Vec<u8>) -> Result<ExistsResponse> {
+            Ok(ExistsResponse::new(
+                self.entries.lock().unwrap().contains_key(key),
+            ))
+        }
+
+        async fn put(&self, key: Vec<u8>, val: Bytes) -> Result<PutResponse> {
+            let created = self.entries.lock().unwrap().insert(key, val).is_none();
+            Ok(PutResponse::new(created, "stored"))
+        }
+
+        async fn get(&self, key: &Vec<u8>) -> Result<GetResponse<Bytes>> {
+            match self.entries.lock().unwrap().get(key) {
+                Some(value) => Ok(GetResponse::new(true, value.clone())),
+                None => Err(Error::NotFound),
+            }
+        }
+
+        async fn delete(&self, key: &Vec<u8>) -> Result<DeleteResponse> {
+            Ok(DeleteResponse::new(
+                self.entries.lock().unwrap().remove(key).is_some(),
+            ))
+        }
+
+        async fn get_and_delete(&self, key: &Vec<u8>) -> Result<GetResponse<Bytes>> {
+            match self.entries.lock().unwrap().remove(key) {
+                Some(value) => Ok(GetResponse::new(true, value)),
+                None => Err(Error::NotFound),
+            }
+        }
+    }
+
+    /// Index of the keys holding a document in each cache
+    #[derive(Default)]
+    struct KeyIndex {
+        keys: Mutex<HashMap<String, Vec<Vec<u8>>>>,
+    }
+
+    impl IndexMaintainer for KeyIndex {
+        fn on_put(&self, cache_name: &str, key: &[u8], _document: &serde_json::Value) {
+            let mut keys = self.keys.lock().unwrap();
+            let keys = keys.entry(cache_name.to_string()).or_default();
+            if !keys.iter().any(|indexed| indexed == key) {
+                keys.push(key.to_vec());
+            }
+        }
+
+        fn on_delete(&self, cache_name: &str, key: &[u8]) {
+            if let Some(keys) = self.keys.lock().unwrap().get_mut(cache_name) {
+                keys.retain(|indexed| indexed != key);
+            }
+        }
+    }
+
+    async fn json_cache(manager: &CacheManager<Vec<u8>, Bytes>, name: &str) {
+        let config = CacheConfig::with_backend(
+            name,
+            CacheEvictionStrategy::SizeBounded,
+            EvictionAlgorithm::Unspecified,
+            Some(1_048_576),
+            None,
+            None,
+            None,
+            Some(64),
+            None,
+            None,
+        )
+        .with_value_type(ValueType::Json);
+        manager
+            .create_cache(config, Arc::new(MapStore::default()))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_writes_through_an_alias_reach_the_cache() {
+        let manager = CacheManager::<Vec<u8>, Bytes>::new();
+        json_cache(&manager, "products-v42").await;
+        manager.set_alias("products", "products-v42").await.unwrap();
+        let index = Arc::new(KeyIndex::default());
+        let service = CacheOperationsService::new(manager).with_index_maintainer(index.clone());
+
+        let key = b"sku-1".to_vec();
+        service
+            .put_tagged(
+                "products",
+                key.clone(),
+                Bytes::from(r#"{"name": "lamp"}"#),
+                vec!["lighting".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            index.keys.lock().unwrap()["products-v42"],
+            vec![key.clone()]
+        );
+        assert!(!index.keys.lock().unwrap().contains_key("products"));
+        assert_eq!(
+            service.tag_index().keys("products-v42", "lighting"),
+            vec![key.clone()]
+        );
+
+        // A write through the alias waits for the lock taken under the cache's own name
+        let guard = service.lock_key("products-v42", &key).await;
+        let write = service.put("products", key.clone(), Bytes::from("{}"));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), write)
+                .await
+                .is_err()
+        );
+        drop(guard);
+
+        service.delete("products", &key).await.unwrap();
+        assert!(index.keys.lock().unwrap()["products-v42"].is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_refused_put_keeps_tags() {
+        let manager = CacheManager::<Vec<u8>, Bytes>::new();
+        json_cache(&manager, "products").await;
+        let service = CacheOperationsService::new(manager);
+
+        let key = b"sku-1".to_vec();
+        service
+            .put_tagged(
+                "products",
+                key.clone(),
+                Bytes::from(r#"{"name": "lamp"}"#),
+                vec!["lighting".to_string()],
+            )
+            .await
+            .unwrap();
+
+        // Over max_value_bytes
+        let oversized = format!(r#"{{"name": "{}"}}"#, "x".repeat(64));
+        assert!(
+            service
+                .put("products", key.clone(), Bytes::from(oversized))
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            service.tag_index().keys("products", "lighting"),
+            vec![key.clone()]
+        );
+
+        service
+            .put("products", key.clone(), Bytes::from("{}"))
+            .await
+            .unwrap();
+        assert!(service.tag_index().keys("products", "lighting").is_empty());
+    }
 }
@@ -0,0 +1,462 @@
+use crate::events::CacheItemEvent;
+use dashmap::DashMap;
+use serde::Serialize;
+use shared::config::ChangeLogConfig;
+use shared::{Error, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Most changes returned by one read
+pub const MAX_CHANGES_PER_READ: usize = 1000;
+
+/// How long an appended change may wait before the writer puts it in the tree
+const FLUSH_INTERVAL: Duration = Duration::from_millis(10);
+/// Changes waiting past this are written by the append that queues the next one
+const MAX_PENDING_CHANGES: usize = 10_000;
+
+const TREE_PREFIX: &str = "changes/";
+/// Highest cursor trimmed from each cache's log, by cache name
+const TRIMMED_TREE: &str = "__trimmed";
+
+/// A change as it was published, with its place in the log
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    /// Pass as `since` to read the changes that came after this one
+    pub cursor: u64,
+    pub event: CacheItemEvent,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangePage {
+    pub changes: Vec<Change>,
+    /// Cursor to read on from, `since` when there was nothing new
+    pub next_cursor: u64,
+    /// Changes after `since` were trimmed before they could be read, the reader missed them
+    pub truncated: bool,
+}
+
+/// Log of one cache
+struct CacheLog {
+    tree: sled::Tree,
+    bytes: AtomicU64,
+}
+
+/// A change given its cursor but not yet in its cache's tree
+struct PendingChange {
+    cache_name: String,
+    cursor: u64,
+    record: Vec<u8>,
+}
+
+/// Append-only log of the changes to each cache, kept in Sled so readers can catch up after
+/// downtime
+/// Cursors grow across caches and restarts, each cache's log is trimmed to `max_bytes` and
+/// `retention_ms`
+/// Appends only queue the change, a background thread writes them in batches every few
+/// milliseconds and reads write what is queued first
+pub struct ChangeLog {
+    logs: Arc<Logs>,
+    stop: Option<Sender<()>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+struct Logs {
+    db: sled::Db,
+    trimmed: sled::Tree,
+    config: ChangeLogConfig,
+    caches: DashMap<String, Arc<CacheLog>>,
+    /// Queued in cursor order, cursors are handed out under this lock
+    pending: Mutex<Vec<PendingChange>>,
+    /// Held while a batch is written, so batches reach the trees in cursor order
+    flushing: Mutex<()>,
+}
+
+impl ChangeLog {
+    /// Open the log at `path`, creating the parent directory if it doesn't exist
+    pub fn open(path: impl AsRef<Path>, config: ChangeLogConfig) -> Result<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| Error::Internal(format!("Failed to create directory: {}", e)))?;
+        }
+        let db = sled::open(path)
+            .map_err(|e| Error::Internal(format!("Failed to open change log: {}", e)))?;
+        let trimmed = db
+            .open_tree(TRIMMED_TREE)
+            .map_err(|e| Error::Internal(format!("Failed to open change log: {}", e)))?;
+        let logs = Arc::new(Logs {
+            db,
+            trimmed,
+            config,
+            caches: DashMap::new(),
+            pending: Mutex::new(Vec::new()),
+            flushing: Mutex::new(()),
+        });
+
+        let (stop, stopped) = mpsc::channel::<()>();
+        let writer = std::thread::Builder::new()
+            .name("change-log-writer".to_string())
+            .spawn({
+                let logs = logs.clone();
+                move || {
+                    loop {
+                        let stopping = matches!(
+                            stopped.recv_timeout(FLUSH_INTERVAL),
+                            Err(RecvTimeoutError::Disconnected)
+                        );
+                        if let Err(e) = logs.flush() {
+                            tracing::warn!("Failed to write the change log: {}", e);
+                        }
+                        if stopping {
+                            break;
+                        }
+                    }
+                }
+            })
+            .map_err(|e| Error::Internal(format!("Failed to start change log writer: {}", e)))?;
+
+        Ok(Self {
+            logs,
+            stop: Some(stop),
+            writer: Some(writer),
+        })
+    }
+
+    /// Queue `event` at the end of its cache's log, returning its cursor
+    pub fn append(&self, event: &CacheItemEvent) -> Result<u64> {
+        let mut record = now_millis().to_be_bytes().to_vec();
+        serde_json::to_writer(&mut record, event)
+            .map_err(|e| Error::Internal(format!("Failed to serialize change: {}", e)))?;
+
+        let (cursor, backlog) = {
+            let mut pending = self.logs.pending.lock().unwrap();
+            // Ids start at 0, which readers pass to start from the beginning
+            let cursor = self.logs.db.generate_id().map_err(storage_error)? + 1;
+            pending.push(PendingChange {
+                cache_name: event.cache_name().to_string(),
+                cursor,
+                record,
+            });
+            (cursor, pending.len())
+        };
+
+        // The writer fell behind, the appender waits for the tree instead of queueing more
+        if backlog >= MAX_PENDING_CHANGES {
+            self.logs.flush()?;
+        }
+        Ok(cursor)
+    }
+
+    /// Write the queued changes to their logs
+    pub fn flush(&self) -> Result<()> {
+        self.logs.flush()
+    }
+
+    /// Up to `limit` changes to `cache_name` after `since`, oldest first
+    pub fn read(&self, cache_name: &str, since: u64, limit: usize) -> Result<ChangePage> {
+        self.logs.flush()?;
+        let log = self.logs.cache_log(cache_name)?;
+        // Readers see the retention applied even when nothing was written for a while
+        self.logs.trim(cache_name, &log)?;
+
+        let trimmed_up_to = self
+            .logs
+            .trimmed
+            .get(cache_name)
+            .map_err(storage_error)?
+            .map_or(0, |cursor| decode_cursor(&cursor));
+
+        let mut changes = Vec::new();
+        for entry in log
+            .tree
+            .range(since.saturating_add(1).to_be_bytes()..)
+            .take(limit.min(MAX_CHANGES_PER_READ))
+        {
+            let (cursor, record) = entry.map_err(storage_error)?;
+            let event = serde_json::from_slice(&record[8..])
+                .map_err(|e| Error::Internal(format!("Failed to deserialize change: {}", e)))?;
+            changes.push(Change {
+                cursor: decode_cursor(&cursor),
+                event,
+            });
+        }
+
+        Ok(ChangePage {
+            next_cursor: changes.last().map_or(since, |change| change.cursor),
+            truncated: since < trimmed_up_to,
+            changes,
+        })
+    }
+
+    /// Delete the log of a cache removed for good
+    pub fn remove_cache(&self, cache_name: &str) -> Result<()> {
+        // Queued changes would otherwise bring the log back
+        self.logs.flush()?;
+        self.logs.remove_cache(cache_name)
+    }
+
+    /// Carry the log of a renamed cache over to its new name, changes keep the cache name they
+    /// were published with
+    pub fn rename_cache(&self, from: &str, to: &str) -> Result<()> {
+        self.logs.flush()?;
+        let log = self.logs.cache_log(from)?;
+        let renamed = self
+            .logs
+            .db
+            .open_tree(tree_name(to))
+            .map_err(storage_error)?;
+        for entry in log.tree.iter() {
+            let (cursor, record) = entry.map_err(storage_error)?;
+            renamed.insert(cursor, record).map_err(storage_error)?;
+        }
+        if let Some(cursor) = self.logs.trimmed.get(from).map_err(storage_error)? {
+            self.logs
+                .trimmed
+                .insert(to, cursor)
+                .map_err(storage_error)?;
+        }
+        // Measured again when next opened
+        self.logs.caches.remove(to);
+        self.logs.remove_cache(from)
+    }
+}
+
+impl Drop for ChangeLog {
+    /// Stop the writer once it has written what is queued
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+impl Logs {
+    /// Write every queued change, one batch per cache, keeping the first error
+    fn flush(&self) -> Result<()> {
+        let _flushing = self.flushing.lock().unwrap();
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut batches: HashMap<String, (sled::Batch, u64)> = HashMap::new();
+        for change in pending {
+            let (batch, size) = batches.entry(change.cache_name).or_default();
+            *size += change.record.len() as u64;
+            batch.insert(&change.cursor.to_be_bytes(), change.record);
+        }
+
+        let mut result = Ok(());
+        for (cache_name, (batch, size)) in batches {
+            let written = self.cache_log(&cache_name).and_then(|log| {
+                log.tree.apply_batch(batch).map_err(storage_error)?;
+                log.bytes.fetch_add(size, Ordering::Relaxed);
+                self.trim(&cache_name, &log)
+            });
+            if result.is_ok() {
+                result = written;
+            }
+        }
+        result
+    }
+
+    fn remove_cache(&self, cache_name: &str) -> Result<()> {
+        self.caches.remove(cache_name);
+        self.db
+            .drop_tree(tree_name(cache_name))
+            .map_err(storage_error)?;
+        self.trimmed.remove(cache_name).map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn cache_log(&self, cache_name: &str) -> Result<Arc<CacheLog>> {
+        if let Some(log) = self.caches.get(cache_name) {
+            return Ok(log.clone());
+        }
+        let tree = self
+            .db
+            .open_tree(tree_name(cache_name))
+            .map_err(storage_error)?;
+        // Logs left from before a restart are measured once
+        let bytes = tree
+            .iter()
+            .values()
+            .map(|record| record.map(|record| record.len() as u64))
+            .sum::<std::result::Result<u64, _>>()
+            .map_err(storage_error)?;
+        Ok(self
+            .caches
+            .entry(cache_name.to_string())
+            .or_insert_with(|| {
+                Arc::new(CacheLog {
+                    tree,
+                    bytes: AtomicU64::new(bytes),
+                })
+            })
+            .clone())
+    }
+
+    /// Remove the oldest changes until the log fits its size and retention
+    fn trim(&self, cache_name: &str, log: &CacheLog) -> Result<()> {
+        let expired_before = now_millis().saturating_sub(self.config.retention_ms);
+        let mut trimmed_up_to = None;
+
+        while let Some((cursor, record)) = log.tree.first().map_err(storage_error)? {
+            let written_at = u64::from_be_bytes(record[..8].try_into().unwrap());
+            if log.bytes.load(Ordering::Relaxed) <= self.config.max_bytes
+                && written_at >= expired_before
+            {
+                break;
+            }
+            // Another trim may have taken it in the meantime
+            if log.tree.remove(&cursor).map_err(storage_error)?.is_some() {
+                log.bytes.fetch_sub(record.len() as u64, Ordering::Relaxed);
+            }
+            trimmed_up_to = Some(decode_cursor(&cursor));
+        }
+
+        if let Some(cursor) = trimmed_up_to {
+            self.trimmed
+                .fetch_and_update(cache_name, |previous| {
+                    let previous = previous.map_or(0, decode_cursor);
+                    Some(previous.max(cursor).to_be_bytes().to_vec())
+                })
+                .map_err(storage_error)?;
+        }
+        Ok(())
+    }
+}
+
+fn tree_name(cache_name: &str) -> String {
+    format!("{}{}", TREE_PREFIX, cache_name)
+}
+
+fn decode_cursor(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes.try_into().unwrap_or_default())
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+fn storage_error(e: sled::Error) -> Error {
+    Error::Internal(format!("Change log storage error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{ItemDeletedEvent, now_timestamp};
+
+    fn deleted(cache_name: &str, key: &str) -> CacheItemEvent {
+        CacheItemEvent::Deleted(ItemDeletedEvent {
+            cache_name: cache_name.to_string(),
+            key: key.as_bytes().to_vec(),
+            timestamp: now_timestamp(),
+        })
+    }
+
+    #[test]
+    fn test_read_from_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ChangeLogConfig {
+            max_bytes: u64::MAX,
+            retention_ms: u64::MAX,
+        };
+        let log = ChangeLog::open(dir.path().join("changes.sled"), config).unwrap();
+
+        let first = log.append(&deleted("users", "alice")).unwrap();
+        log.append(&deleted("orders", "1")).unwrap();
+        let third = log.append(&deleted("users", "bob")).unwrap();
+
+        let page = log.read("users", 0, 100).unwrap();
+        assert_eq!(page.changes.len(), 2);
+        assert_eq!(page.changes[0].cursor, first);
+        assert_eq!(page.next_cursor, third);
+        assert!(!page.truncated);
+
+        let page = log.read("users", first, 100).unwrap();
+        assert_eq!(page.changes.len(), 1);
+        assert_eq!(page.changes[0].event.key(), b"bob");
+
+        let page = log.read("users", third, 100).unwrap();
+        assert!(page.changes.is_empty());
+        assert_eq!(page.next_cursor, third);
+
+        log.remove_cache("users").unwrap();
+        assert!(log.read("users", 0, 100).unwrap().changes.is_empty());
+        assert_eq!(log.read("orders", 0, 100).unwrap().changes.len(), 1);
+    }
+
+    #[test]
+    fn test_trimmed_to_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ChangeLogConfig {
+            max_bytes: 300,
+            retention_ms: u64::MAX,
+        };
+        let log = ChangeLog::open(dir.path().join("changes.sled"), config).unwrap();
+
+        let cursors: Vec<u64> = (0..10)
+            .map(|i| log.append(&deleted("users", &i.to_string())).unwrap())
+            .collect();
+
+        let page = log.read("users", 0, 100).unwrap();
+        assert!(page.truncated);
+        assert!(page.changes.len() < 10);
+        assert_eq!(page.next_cursor, cursors[9]);
+
+        // Readers that kept up missed nothing
+        let page = log.read("users", cursors[8], 100).unwrap();
+        assert!(!page.truncated);
+        assert_eq!(page.changes.len(), 1);
+    }
+
+    #[test]
+    fn test_appends_are_written_in_the_background() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ChangeLogConfig {
+            max_bytes: u64::MAX,
+            retention_ms: u64::MAX,
+        };
+        let log = ChangeLog::open(dir.path().join("changes.sled"), config).unwrap();
+
+        log.append(&deleted("users", "alice")).unwrap();
+        for _ in 0..100 {
+            if log.logs.pending.lock().unwrap().is_empty() {
+                break;
+            }
+            std::thread::sleep(FLUSH_INTERVAL);
+        }
+        assert!(log.logs.pending.lock().unwrap().is_empty());
+        let tree = log.logs.cache_log("users").unwrap().tree.clone();
+        assert_eq!(tree.len(), 1);
+    }
+
+    #[test]
+    fn test_renamed_cache_keeps_its_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ChangeLogConfig {
+            max_bytes: u64::MAX,
+            retention_ms: u64::MAX,
+        };
+        let log = ChangeLog::open(dir.path().join("changes.sled"), config).unwrap();
+
+        let first = log.append(&deleted("users", "alice")).unwrap();
+        log.rename_cache("users", "members").unwrap();
+        let second = log.append(&deleted("members", "bob")).unwrap();
+
+        let page = log.read("members", 0, 100).unwrap();
+        let cursors: Vec<u64> = page.changes.iter().map(|change| change.cursor).collect();
+        assert_eq!(cursors, [first, second]);
+        assert!(log.read("users", 0, 100).unwrap().changes.is_empty());
+    }
+}
@@ -0,0 +1,32 @@
+use crate::encoding::ToBytes;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use shared::Result;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+impl<K, V> CacheOperationsService<K, V>
+where
+    K: ToBytes + Debug + Hash + Eq + Send + Sync + Clone + 'static,
+    V: ToBytes + Debug + Send + Sync + Clone + 'static,
+{
+    /// Delete every entry of `cache_name` as DELETE would, returning how many were deleted
+    /// Refused by backends that cannot list their entries, counted as one operation against
+    /// the cache's ops/sec limit
+    pub async fn clear(&self, cache_name: &str) -> Result<u64> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("CLEAR", cache_name, &[]);
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+
+        let mut cleared = 0;
+        for (key, _) in cache_store.entries().await? {
+            let result = cache_store.delete(&key).await?;
+            self.tag_index().remove_key(cache_name, &key);
+            self.content_types().remove_key(cache_name, &key);
+            if result.deleted {
+                self.entry_deleted(cache_name, &key);
+                cleared += 1;
+            }
+        }
+        Ok(cleared)
+    }
+}
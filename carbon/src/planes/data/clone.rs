@@ -0,0 +1,68 @@
+use crate::domain::BulkEntry;
+use crate::encoding::ToBytes;
+use crate::events::{CloneProgressEvent, now_timestamp};
+use crate::planes::data::bulk_load::BULK_LOAD_BATCH_SIZE;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use shared::Result;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+impl<K, V> CacheOperationsService<K, V>
+where
+    K: ToBytes + Debug + Hash + Eq + Send + Sync + Clone + 'static,
+    V: ToBytes + Debug + Send + Sync + Clone + 'static,
+{
+    /// Every live entry of `cache_name` as it is now, refused by backends that cannot list
+    /// their entries
+    pub async fn snapshot(&self, cache_name: &str) -> Result<Vec<(K, V)>> {
+        let cache_name = &*self.resolve(cache_name);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+        cache_store.entries().await
+    }
+
+    /// Load a snapshot of `source` into its clone `target` in bulk load batches, broadcasting
+    /// a CloneProgress event after each batch and a last one once the copy is done
+    /// Entries take the clone's default TTL, and the copy stops at the first batch refused
+    pub async fn copy_entries(
+        &self,
+        source: &str,
+        target: &str,
+        entries: Vec<(K, V)>,
+    ) -> Result<u64> {
+        let total = entries.len() as u64;
+        let progress = |copied: u64, done: bool, error: Option<String>| CloneProgressEvent {
+            cache_name: target.to_string(),
+            source: source.to_string(),
+            copied,
+            total,
+            done,
+            error,
+            timestamp: now_timestamp(),
+        };
+
+        let mut loader = self.bulk_loader(target).await?;
+        let mut entries = entries.into_iter().peekable();
+        while entries.peek().is_some() {
+            let batch = entries
+                .by_ref()
+                .take(BULK_LOAD_BATCH_SIZE)
+                .map(|(key, value)| BulkEntry {
+                    key,
+                    value,
+                    ttl: None,
+                })
+                .collect();
+            if let Err(e) = loader.load(batch).await {
+                self.clone_progress(progress(loader.summary().loaded, true, Some(e.to_string())));
+                return Err(e);
+            }
+            if entries.peek().is_some() {
+                self.clone_progress(progress(loader.summary().loaded, false, None));
+            }
+        }
+
+        let copied = loader.summary().loaded;
+        self.clone_progress(progress(copied, true, None));
+        Ok(copied)
+    }
+}
@@ -0,0 +1,77 @@
+use crate::domain::EntryMatch;
+use crate::domain::response::{DeleteResponse, PutResponse};
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::ConditionalOperations;
+use crate::ports::CacheStore;
+use async_trait::async_trait;
+use bytes::Bytes;
+use shared::{Error, Result};
+use std::sync::Arc;
+
+// Conditional writes for the Vec<u8>/Bytes service used by the servers
+// The key lock makes each check-and-write atomic against every other write of the key
+#[async_trait]
+impl ConditionalOperations<Vec<u8>, Bytes> for CacheOperationsService<Vec<u8>, Bytes> {
+    async fn put_if(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        value: Bytes,
+        tags: Vec<String>,
+        content_type: Option<String>,
+        condition: &EntryMatch,
+    ) -> Result<PutResponse> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("PUTIF", cache_name, &key);
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+        let _guard = self.lock_key(cache_name, &key).await;
+        check_condition(&cache_store, &key, condition).await?;
+
+        let result = if tags.is_empty() {
+            self.put_locked(cache_name, key.clone(), value).await?
+        } else {
+            self.put_tagged_locked(cache_name, key.clone(), value, tags)
+                .await?
+        };
+        if let Some(content_type) = content_type.filter(|_| result.admitted) {
+            self.content_types().set(cache_name, &key, content_type);
+        }
+        Ok(result)
+    }
+
+    async fn delete_if(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+        condition: &EntryMatch,
+    ) -> Result<DeleteResponse> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("DELETEIF", cache_name, key);
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+        let _guard = self.lock_key(cache_name, key).await;
+        check_condition(&cache_store, key, condition).await?;
+        self.delete_locked(cache_name, key).await
+    }
+}
+
+/// Refuse the write unless the entry, as the key lock holds it, matches `condition`
+#[allow(clippy::ptr_arg)] // cache stores are keyed by Vec<u8>
+async fn check_condition(
+    cache_store: &Arc<dyn CacheStore<Vec<u8>, Bytes>>,
+    key: &Vec<u8>,
+    condition: &EntryMatch,
+) -> Result<()> {
+    let current = match cache_store.get(key).await {
+        Ok(result) => Some(result.message),
+        Err(Error::NotFound) => None,
+        Err(e) => return Err(e),
+    };
+
+    if condition.matches(current.as_deref()) {
+        Ok(())
+    } else {
+        Err(Error::PreconditionFailed(
+            "entry does not match If-Match".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,331 @@
+use crate::events::now_timestamp;
+use dashmap::DashMap;
+use serde::Serialize;
+use shared::config::TcpLimits;
+use std::net::IpAddr;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+/// Counters of one frame direction, bytes as sent on the wire and once decompressed
+#[derive(Default)]
+struct TrafficCounters {
+    frames: AtomicU64,
+    compressed_frames: AtomicU64,
+    wire_bytes: AtomicU64,
+    raw_bytes: AtomicU64,
+}
+
+impl TrafficCounters {
+    fn record(&self, wire_bytes: usize, raw_bytes: usize, compressed: bool) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        if compressed {
+            self.compressed_frames.fetch_add(1, Ordering::Relaxed);
+        }
+        self.wire_bytes
+            .fetch_add(wire_bytes as u64, Ordering::Relaxed);
+        self.raw_bytes
+            .fetch_add(raw_bytes as u64, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> TrafficStats {
+        TrafficStats {
+            frames: self.frames.load(Ordering::Relaxed),
+            compressed_frames: self.compressed_frames.load(Ordering::Relaxed),
+            wire_bytes: self.wire_bytes.load(Ordering::Relaxed),
+            raw_bytes: self.raw_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrafficStats {
+    pub frames: u64,
+    pub compressed_frames: u64,
+    /// Bytes as sent on the wire, excluding length prefixes
+    pub wire_bytes: u64,
+    /// Bytes once decompressed
+    pub raw_bytes: u64,
+}
+
+/// A client connection to one of the binary frontends
+pub struct Connection {
+    id: u64,
+    peer: String,
+    connected_at: u64,
+    protocol_version: AtomicU32,
+    compression: AtomicBool,
+    checksums: AtomicBool,
+    received: TrafficCounters,
+    sent: TrafficCounters,
+    /// IP of a connection admitted under limits, with its load
+    load: Option<(IpAddr, Arc<PeerLoad>)>,
+    max_in_flight: Option<usize>,
+}
+
+/// Connections and requests open from one IP
+#[derive(Default)]
+struct PeerLoad {
+    connections: AtomicUsize,
+    in_flight: AtomicUsize,
+}
+
+/// A request counted against its IP's in-flight limit until dropped
+pub struct InFlightRequest {
+    load: Option<Arc<PeerLoad>>,
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        if let Some(load) = &self.load {
+            load.in_flight.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Connection {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Record what the connection agreed on in its protocol handshake
+    pub fn set_protocol(&self, version: u16, compression: bool, checksums: bool) {
+        self.protocol_version
+            .store(version as u32, Ordering::Relaxed);
+        self.compression.store(compression, Ordering::Relaxed);
+        self.checksums.store(checksums, Ordering::Relaxed);
+    }
+
+    /// Record a request frame of `wire_bytes` that held `raw_bytes` once decompressed
+    pub fn record_received(&self, wire_bytes: usize, raw_bytes: usize, compressed: bool) {
+        self.received.record(wire_bytes, raw_bytes, compressed);
+    }
+
+    /// Record a response frame of `wire_bytes` that held `raw_bytes` before compression
+    pub fn record_sent(&self, wire_bytes: usize, raw_bytes: usize, compressed: bool) {
+        self.sent.record(wire_bytes, raw_bytes, compressed);
+    }
+
+    /// Count a request against the IP's in-flight limit, None when the IP has as many
+    /// requests being served as it is allowed
+    pub fn begin_request(&self) -> Option<InFlightRequest> {
+        let Some((_, load)) = &self.load else {
+            return Some(InFlightRequest { load: None });
+        };
+        let in_flight = load.in_flight.fetch_add(1, Ordering::Relaxed);
+        let request = InFlightRequest {
+            load: Some(load.clone()),
+        };
+        match self.max_in_flight {
+            Some(max) if in_flight >= max => None,
+            _ => Some(request),
+        }
+    }
+
+    pub fn info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            id: self.id,
+            peer: self.peer.clone(),
+            connected_at: self.connected_at,
+            protocol_version: self.protocol_version.load(Ordering::Relaxed) as u16,
+            compression: self.compression.load(Ordering::Relaxed),
+            checksums: self.checksums.load(Ordering::Relaxed),
+            received: self.received.stats(),
+            sent: self.sent.stats(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionInfo {
+    pub id: u64,
+    pub peer: String,
+    /// Seconds since the Unix epoch when the connection was accepted
+    pub connected_at: u64,
+    pub protocol_version: u16,
+    pub compression: bool,
+    pub checksums: bool,
+    pub received: TrafficStats,
+    pub sent: TrafficStats,
+}
+
+/// Open connections of the binary frontends, pass the same registry to the HTTP server to
+/// list them on GET /admin/connections
+#[derive(Default)]
+pub struct ConnectionRegistry {
+    next_id: AtomicU64,
+    connections: DashMap<u64, Arc<Connection>>,
+    limits: TcpLimits,
+    peers: DashMap<IpAddr, Arc<PeerLoad>>,
+}
+
+impl ConnectionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder method to cap the connections and requests of each client IP
+    pub fn with_limits(mut self, limits: TcpLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> &TcpLimits {
+        &self.limits
+    }
+
+    /// Track a connection from `peer` until the returned handle is dropped
+    pub fn register(self: &Arc<Self>, peer: String) -> ConnectionHandle {
+        self.track(peer, None)
+    }
+
+    /// Track a connection from `peer` at `ip` under the limits of the registry, the error
+    /// says which limit turned it away
+    pub fn admit(self: &Arc<Self>, peer: String, ip: IpAddr) -> Result<ConnectionHandle, String> {
+        // Counted under the entry's lock, so a closing connection cannot remove it in between
+        let (load, connections) = {
+            let load = self.peers.entry(ip).or_default();
+            let connections = load.connections.fetch_add(1, Ordering::Relaxed);
+            (load.clone(), connections)
+        };
+        let connection = self.track(peer, Some((ip, load)));
+        match self.limits.max_connections_per_ip {
+            Some(max) if connections >= max => Err(format!(
+                "{} already has {} connections open, the most allowed",
+                ip, max
+            )),
+            _ => Ok(connection),
+        }
+    }
+
+    fn track(
+        self: &Arc<Self>,
+        peer: String,
+        load: Option<(IpAddr, Arc<PeerLoad>)>,
+    ) -> ConnectionHandle {
+        let connection = Arc::new(Connection {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            peer,
+            connected_at: now_timestamp(),
+            protocol_version: AtomicU32::new(0),
+            compression: AtomicBool::new(false),
+            checksums: AtomicBool::new(false),
+            received: TrafficCounters::default(),
+            sent: TrafficCounters::default(),
+            load,
+            max_in_flight: self.limits.max_in_flight_per_ip,
+        });
+        self.connections.insert(connection.id, connection.clone());
+        ConnectionHandle {
+            registry: self.clone(),
+            connection,
+        }
+    }
+
+    /// Open connections, oldest first
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        let mut connections: Vec<ConnectionInfo> = self
+            .connections
+            .iter()
+            .map(|connection| connection.info())
+            .collect();
+        connections.sort_by_key(|connection| connection.id);
+        connections
+    }
+
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+}
+
+/// A registered connection, unregistered when dropped
+pub struct ConnectionHandle {
+    registry: Arc<ConnectionRegistry>,
+    connection: Arc<Connection>,
+}
+
+impl Deref for ConnectionHandle {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Drop for ConnectionHandle {
+    fn drop(&mut self) {
+        self.registry.connections.remove(&self.connection.id);
+        if let Some((ip, load)) = &self.connection.load {
+            load.connections.fetch_sub(1, Ordering::Relaxed);
+            self.registry
+                .peers
+                .remove_if(ip, |_, load| load.connections.load(Ordering::Relaxed) == 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connections_are_tracked_until_dropped() {
+        let registry = Arc::new(ConnectionRegistry::new());
+        let first = registry.register("tcp:127.0.0.1:5000".to_string());
+        let second = registry.register("tcp:127.0.0.1:5001".to_string());
+
+        first.set_protocol(1, true, false);
+        first.record_received(40, 100, true);
+        first.record_sent(10, 10, false);
+
+        let connections = registry.connections();
+        assert_eq!(connections.len(), 2);
+        assert_eq!(connections[0].peer, "tcp:127.0.0.1:5000");
+        assert!(connections[0].compression);
+        assert_eq!(connections[0].received.compressed_frames, 1);
+        assert_eq!(connections[0].received.wire_bytes, 40);
+        assert_eq!(connections[0].received.raw_bytes, 100);
+        assert_eq!(connections[0].sent.compressed_frames, 0);
+
+        drop(first);
+        let connections = registry.connections();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].id, second.id());
+    }
+
+    #[test]
+    fn test_limits_apply_per_ip() {
+        let registry = Arc::new(ConnectionRegistry::new().with_limits(TcpLimits {
+            max_connections_per_ip: Some(2),
+            max_in_flight_per_ip: Some(1),
+            ..TcpLimits::default()
+        }));
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let first = registry.admit("tcp:10.0.0.1:5000".to_string(), ip).unwrap();
+        let second = registry.admit("tcp:10.0.0.1:5001".to_string(), ip).unwrap();
+        assert!(registry.admit("tcp:10.0.0.1:5002".to_string(), ip).is_err());
+        assert!(
+            registry
+                .admit("tcp:10.0.0.2:5000".to_string(), other)
+                .is_ok()
+        );
+        // Turned away connections are not left registered
+        assert_eq!(registry.len(), 2);
+
+        let request = first.begin_request().unwrap();
+        assert!(second.begin_request().is_none());
+        drop(request);
+        assert!(second.begin_request().is_some());
+
+        drop(first);
+        assert!(registry.admit("tcp:10.0.0.1:5003".to_string(), ip).is_ok());
+        drop(second);
+        assert!(registry.peers.is_empty());
+    }
+}
@@ -0,0 +1,32 @@
+use crate::domain::response::PutResponse;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::ContentTypeOperations;
+use async_trait::async_trait;
+use bytes::Bytes;
+use shared::Result;
+
+// Content type commands for the Vec<u8>/Bytes service used by the servers
+#[async_trait]
+impl ContentTypeOperations<Vec<u8>, Bytes> for CacheOperationsService<Vec<u8>, Bytes> {
+    async fn put_with_content_type(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        value: Bytes,
+        content_type: String,
+    ) -> Result<PutResponse> {
+        let cache_name = &*self.resolve(cache_name);
+        self.get_cache_for_write(cache_name).await?;
+        let _guard = self.lock_key(cache_name, &key).await;
+        let result = self.put_locked(cache_name, key.clone(), value).await?;
+        if result.admitted {
+            self.content_types().set(cache_name, &key, content_type);
+        }
+        Ok(result)
+    }
+
+    fn content_type(&self, cache_name: &str, key: &Vec<u8>) -> Option<String> {
+        let cache_name = &*self.resolve(cache_name);
+        self.content_types().get(cache_name, key)
+    }
+}
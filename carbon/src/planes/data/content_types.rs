@@ -0,0 +1,62 @@
+use crate::planes::data::tag_index::move_cache_entries;
+use dashmap::DashMap;
+use std::hash::Hash;
+
+/// Media types entries were uploaded with, per cache
+/// Like tags, set under the key lock and forgotten through entry_evicted
+pub(crate) struct ContentTypes<K> {
+    types: DashMap<(String, K), String>,
+}
+
+impl<K: Hash + Eq + Clone> ContentTypes<K> {
+    pub(crate) fn new() -> Self {
+        Self {
+            types: DashMap::new(),
+        }
+    }
+
+    pub(crate) fn set(&self, cache_name: &str, key: &K, content_type: String) {
+        self.types
+            .insert((cache_name.to_string(), key.clone()), content_type);
+    }
+
+    pub(crate) fn get(&self, cache_name: &str, key: &K) -> Option<String> {
+        self.types
+            .get(&(cache_name.to_string(), key.clone()))
+            .map(|content_type| content_type.clone())
+    }
+
+    pub(crate) fn remove_key(&self, cache_name: &str, key: &K) {
+        self.types.remove(&(cache_name.to_string(), key.clone()));
+    }
+
+    /// Forget every type of a dropped cache
+    pub(crate) fn remove_cache(&self, cache_name: &str) {
+        self.types.retain(|(name, _), _| name != cache_name);
+    }
+
+    pub(crate) fn rename_cache(&self, from: &str, to: &str) {
+        move_cache_entries(&self.types, from, to);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_types_are_per_cache() {
+        let types = ContentTypes::new();
+        types.set("images", &"logo", "image/png".to_string());
+        types.set("docs", &"logo", "image/svg+xml".to_string());
+
+        assert_eq!(types.get("images", &"logo").as_deref(), Some("image/png"));
+        assert!(types.get("images", &"banner").is_none());
+
+        types.remove_key("images", &"logo");
+        assert!(types.get("images", &"logo").is_none());
+
+        types.remove_cache("docs");
+        assert!(types.get("docs", &"logo").is_none());
+    }
+}
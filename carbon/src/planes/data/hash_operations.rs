@@ -0,0 +1,129 @@
+use crate::domain::response::{DeleteResponse, GetResponse, PutResponse};
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::HashOperations;
+use crate::planes::data::structured::{
+    HashValue, StructuredValue, ensure_raw_cache, load_structure,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde_bytes::ByteBuf;
+use shared::{Error, Result};
+use std::collections::BTreeMap;
+
+// Hash commands for the Vec<u8>/Bytes service used by the servers
+#[async_trait]
+impl HashOperations<Vec<u8>, Bytes> for CacheOperationsService<Vec<u8>, Bytes> {
+    async fn hset(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        field: String,
+        value: Bytes,
+    ) -> Result<PutResponse> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("HSET", cache_name, &key);
+        let (cache_store, config) = self.get_cache_for_write(cache_name).await?;
+        ensure_raw_cache(cache_name, &config)?;
+
+        let _guard = self.lock_key(cache_name, &key).await;
+
+        let mut hash = match load_structure(&cache_store, &key).await? {
+            Some(existing) => existing.into_hash()?,
+            None => HashValue::default(),
+        };
+
+        let created = hash
+            .fields
+            .insert(field, ByteBuf::from(value.to_vec()))
+            .is_none();
+
+        let encoded = StructuredValue::Hash(hash).encode()?;
+        let written = self
+            .write_entry(cache_name, &cache_store, key, encoded)
+            .await?;
+        if !written.admitted {
+            return Ok(written);
+        }
+
+        Ok(PutResponse::new(
+            created,
+            if created {
+                "Field created"
+            } else {
+                "Field updated"
+            },
+        ))
+    }
+
+    async fn hget(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+        field: &str,
+    ) -> Result<GetResponse<Bytes>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("HGET", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        let hash = load_structure(&cache_store, key)
+            .await?
+            .ok_or(Error::NotFound)?
+            .into_hash()?;
+
+        match hash.fields.get(field) {
+            Some(value) => Ok(GetResponse::new(true, Bytes::from(value.to_vec()))),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    async fn hdel(&self, cache_name: &str, key: &Vec<u8>, field: &str) -> Result<DeleteResponse> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("HDEL", cache_name, key);
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+
+        let _guard = self.lock_key(cache_name, key).await;
+
+        let mut hash = match load_structure(&cache_store, key).await? {
+            Some(existing) => existing.into_hash()?,
+            None => return Ok(DeleteResponse::new(false)),
+        };
+
+        if hash.fields.remove(field).is_none() {
+            return Ok(DeleteResponse::new(false));
+        }
+
+        // Like Redis, an empty hash is removed entirely
+        if hash.fields.is_empty() {
+            self.delete_locked(cache_name, key).await?;
+        } else {
+            let encoded = StructuredValue::Hash(hash).encode()?;
+            self.write_entry(cache_name, &cache_store, key.clone(), encoded)
+                .await?;
+        }
+
+        Ok(DeleteResponse::new(true))
+    }
+
+    async fn hgetall(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+    ) -> Result<GetResponse<BTreeMap<String, Bytes>>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("HGETALL", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        let hash = load_structure(&cache_store, key)
+            .await?
+            .ok_or(Error::NotFound)?
+            .into_hash()?;
+
+        let fields = hash
+            .fields
+            .into_iter()
+            .map(|(field, value)| (field, Bytes::from(value.into_vec())))
+            .collect();
+
+        Ok(GetResponse::new(true, fields))
+    }
+}
@@ -0,0 +1,204 @@
+use crate::domain::ValueType;
+use crate::domain::response::{GetResponse, PutResponse};
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::JsonOperations;
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde_json::{Map, Value};
+use shared::{Error, Result};
+
+// JSON document operations for the Vec<u8>/Bytes service used by the servers
+#[async_trait]
+impl JsonOperations<Vec<u8>> for CacheOperationsService<Vec<u8>, Bytes> {
+    /// Read the sub-document addressed by `pointer`
+    async fn json_get(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+        pointer: &str,
+    ) -> Result<GetResponse<Value>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("JSON_GET", cache_name, key);
+        let (cache_store, config) = self.get_cache(cache_name).await?;
+        ensure_json_cache(cache_name, config.value_type)?;
+
+        let document = parse_document(&cache_store.get(key).await?.message)?;
+        let value = document.pointer(pointer).ok_or(Error::NotFound)?;
+
+        Ok(GetResponse::new(true, value.clone()))
+    }
+
+    /// Replace the sub-document addressed by `pointer`, creating missing parent objects
+    async fn json_set(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        pointer: &str,
+        value: Value,
+    ) -> Result<PutResponse> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("JSON_SET", cache_name, &key);
+        let (cache_store, config) = self.get_cache_for_write(cache_name).await?;
+        ensure_json_cache(cache_name, config.value_type)?;
+
+        // Hold the key lock across the read-modify-write so concurrent patches don't interleave
+        let _guard = self.lock_key(cache_name, &key).await;
+
+        let mut document = match cache_store.get(&key).await {
+            Ok(existing) => parse_document(&existing.message)?,
+            Err(Error::NotFound) => Value::Object(Map::new()),
+            Err(e) => return Err(e),
+        };
+
+        set_pointer(&mut document, pointer, value)?;
+
+        let encoded = serde_json::to_vec(&document)
+            .map_err(|e| Error::Internal(format!("Failed to serialize document: {}", e)))?;
+
+        self.put_locked(cache_name, key, Bytes::from(encoded)).await
+    }
+}
+
+fn ensure_json_cache(cache_name: &str, value_type: ValueType) -> Result<()> {
+    if value_type != ValueType::Json {
+        return Err(Error::InvalidValue(format!(
+            "cache '{}' does not store JSON values",
+            cache_name
+        )));
+    }
+    Ok(())
+}
+
+fn parse_document(bytes: &[u8]) -> Result<Value> {
+    serde_json::from_slice(bytes)
+        .map_err(|e| Error::Internal(format!("Stored value is not valid JSON: {}", e)))
+}
+
+/// Set `value` at `pointer` inside `document` (RFC 6901)
+/// Missing object members along the path are created; array indices must exist,
+/// except for the final token which may also be `-` or the array length to append
+pub(crate) fn set_pointer(document: &mut Value, pointer: &str, value: Value) -> Result<()> {
+    if pointer.is_empty() {
+        *document = value;
+        return Ok(());
+    }
+
+    let tokens: Vec<String> = pointer
+        .strip_prefix('/')
+        .ok_or_else(|| Error::InvalidValue(format!("invalid JSON pointer '{}'", pointer)))?
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect();
+
+    let (last, parents) = tokens
+        .split_last()
+        .expect("non-empty pointer has at least one token");
+
+    let mut target = document;
+    for token in parents {
+        target = match target {
+            Value::Object(map) => map
+                .entry(token.clone())
+                .or_insert_with(|| Value::Object(Map::new())),
+            Value::Array(items) => {
+                let index = parse_index(token)?;
+                items.get_mut(index).ok_or_else(|| {
+                    Error::InvalidValue(format!("array index {} out of bounds", index))
+                })?
+            }
+            _ => {
+                return Err(Error::InvalidValue(format!(
+                    "cannot traverse into scalar value at '{}'",
+                    token
+                )));
+            }
+        };
+    }
+
+    match target {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+        }
+        Value::Array(items) => {
+            let index = if last == "-" {
+                items.len()
+            } else {
+                parse_index(last)?
+            };
+
+            if index < items.len() {
+                items[index] = value;
+            } else if index == items.len() {
+                items.push(value);
+            } else {
+                return Err(Error::InvalidValue(format!(
+                    "array index {} out of bounds",
+                    index
+                )));
+            }
+        }
+        _ => {
+            return Err(Error::InvalidValue(format!(
+                "cannot set member '{}' on a scalar value",
+                last
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_index(token: &str) -> Result<usize> {
+    token
+        .parse::<usize>()
+        .map_err(|_| Error::InvalidValue(format!("invalid array index '{}'", token)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_set_pointer_replaces_whole_document() {
+        let mut doc = json!({"a": 1});
+        set_pointer(&mut doc, "", json!([1, 2])).unwrap();
+        assert_eq!(doc, json!([1, 2]));
+    }
+
+    #[test]
+    fn test_set_pointer_creates_missing_objects() {
+        let mut doc = json!({"user": {"name": "alice"}});
+        set_pointer(&mut doc, "/user/address/city", json!("Paris")).unwrap();
+        assert_eq!(
+            doc,
+            json!({"user": {"name": "alice", "address": {"city": "Paris"}}})
+        );
+    }
+
+    #[test]
+    fn test_set_pointer_arrays() {
+        let mut doc = json!({"tags": ["a", "b"]});
+        set_pointer(&mut doc, "/tags/0", json!("z")).unwrap();
+        set_pointer(&mut doc, "/tags/-", json!("c")).unwrap();
+        assert_eq!(doc, json!({"tags": ["z", "b", "c"]}));
+
+        assert!(set_pointer(&mut doc, "/tags/7", json!("x")).is_err());
+        assert!(set_pointer(&mut doc, "/tags/x", json!("x")).is_err());
+    }
+
+    #[test]
+    fn test_set_pointer_escaped_tokens() {
+        let mut doc = json!({});
+        set_pointer(&mut doc, "/a~1b/c~0d", json!(true)).unwrap();
+        assert_eq!(doc, json!({"a/b": {"c~d": true}}));
+        assert_eq!(doc.pointer("/a~1b/c~0d"), Some(&json!(true)));
+    }
+
+    #[test]
+    fn test_set_pointer_rejects_scalars_and_bad_pointers() {
+        let mut doc = json!({"count": 1});
+        assert!(set_pointer(&mut doc, "/count/inner", json!(2)).is_err());
+        assert!(set_pointer(&mut doc, "count", json!(2)).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Default number of lock stripes shared by all caches
+const DEFAULT_STRIPES: usize = 256;
+
+/// Striped mutexes used to serialise read-modify-write operations on the same key
+/// Keys hashing to the same stripe share a lock, which bounds memory regardless of key count
+pub(crate) struct KeyLocks {
+    stripes: Vec<Mutex<()>>,
+}
+
+impl KeyLocks {
+    pub(crate) fn new() -> Self {
+        Self::with_stripes(DEFAULT_STRIPES)
+    }
+
+    pub(crate) fn with_stripes(stripes: usize) -> Self {
+        Self {
+            stripes: (0..stripes.max(1)).map(|_| Mutex::new(())).collect(),
+        }
+    }
+
+    /// Acquire the lock guarding `key` in `cache_name`
+    pub(crate) async fn lock<K: Hash>(&self, cache_name: &str, key: &K) -> MutexGuard<'_, ()> {
+        let mut hasher = DefaultHasher::new();
+        cache_name.hash(&mut hasher);
+        key.hash(&mut hasher);
+        let index = (hasher.finish() as usize) % self.stripes.len();
+        self.stripes[index].lock().await
+    }
+}
+
+impl Default for KeyLocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
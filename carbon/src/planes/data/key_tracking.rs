@@ -0,0 +1,389 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// Memory the keys tracked for one connection may take when no cap is configured
+pub const DEFAULT_MAX_TRACKED_BYTES: usize = 1024 * 1024;
+
+/// Notices queued for one connection before it is told to flush everything instead
+const NOTICE_QUEUE: usize = 1024;
+
+/// Stale entries the tracking order of a client may hold before it is compacted
+const ORDER_SLACK: usize = 64;
+
+/// Notice that the copies a client holds of some keys are stale
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Invalidation {
+    /// `keys` of the cache were written, removed or untracked to stay under the cap
+    Keys {
+        cache_name: String,
+        keys: Vec<Vec<u8>>,
+    },
+    /// Every key of the cache, sent when the cache is dropped or renamed or an alias moves
+    Cache { cache_name: String },
+    /// Everything the client holds, sent in place of notices that did not fit its queue
+    All,
+}
+
+/// A key a client read, under the cache name it read it through
+type TrackedKey = (String, Vec<u8>);
+
+/// Memory a tracked key is charged for
+fn tracked_size((cache_name, key): &TrackedKey) -> usize {
+    cache_name.len() + key.len()
+}
+
+struct TrackedClient {
+    sender: mpsc::Sender<Invalidation>,
+    overflowed: Arc<AtomicBool>,
+    /// Tracked keys with the sequence number they were last read at
+    keys: HashMap<TrackedKey, u64>,
+    /// Keys in the order they were read, entries whose sequence number is no longer current
+    /// were read again or untracked since
+    order: VecDeque<(TrackedKey, u64)>,
+    bytes: usize,
+    next_seq: u64,
+}
+
+impl TrackedClient {
+    /// Stop tracking `tracked`, false when it was not tracked
+    fn untrack(&mut self, tracked: &TrackedKey) -> bool {
+        match self.keys.remove(tracked) {
+            Some(_) => {
+                self.bytes -= tracked_size(tracked);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Clients tracking each key, by cache
+type Watchers = HashMap<String, HashMap<Vec<u8>, HashSet<u64>>>;
+
+#[derive(Default)]
+struct TrackingTable {
+    watchers: Watchers,
+    clients: HashMap<u64, TrackedClient>,
+}
+
+/// Remove `client` from the watchers of `tracked`, dropping entries left empty
+fn unwatch(watchers: &mut Watchers, (cache_name, key): &TrackedKey, client: u64) {
+    if let Some(keys) = watchers.get_mut(cache_name) {
+        if let Some(clients) = keys.get_mut(key) {
+            clients.remove(&client);
+            if clients.is_empty() {
+                keys.remove(key);
+            }
+        }
+        if keys.is_empty() {
+            watchers.remove(cache_name);
+        }
+    }
+}
+
+impl TrackingTable {
+    fn remove_client(&mut self, client: u64) {
+        if let Some(state) = self.clients.remove(&client) {
+            for tracked in state.keys.keys() {
+                unwatch(&mut self.watchers, tracked, client);
+            }
+        }
+    }
+
+    fn track(&mut self, client: u64, tracked: TrackedKey, max_bytes: usize) {
+        let Some(state) = self.clients.get_mut(&client) else {
+            return;
+        };
+        state.next_seq += 1;
+        let seq = state.next_seq;
+        if state.keys.insert(tracked.clone(), seq).is_none() {
+            state.bytes += tracked_size(&tracked);
+            self.watchers
+                .entry(tracked.0.clone())
+                .or_default()
+                .entry(tracked.1.clone())
+                .or_default()
+                .insert(client);
+        }
+        state.order.push_back((tracked, seq));
+        if state.order.len() > 2 * state.keys.len() + ORDER_SLACK {
+            let keys = &state.keys;
+            state
+                .order
+                .retain(|(tracked, seq)| keys.get(tracked) == Some(seq));
+        }
+
+        // Past the cap the least recently read keys are untracked, so the client must drop them
+        let mut evicted: HashMap<String, Vec<Vec<u8>>> = HashMap::new();
+        while state.bytes > max_bytes {
+            let Some((oldest, seq)) = state.order.pop_front() else {
+                break;
+            };
+            if state.keys.get(&oldest) != Some(&seq) {
+                continue;
+            }
+            state.untrack(&oldest);
+            unwatch(&mut self.watchers, &oldest, client);
+            evicted.entry(oldest.0).or_default().push(oldest.1);
+        }
+        for (cache_name, keys) in evicted {
+            self.notify(client, Invalidation::Keys { cache_name, keys });
+        }
+    }
+
+    fn invalidate(&mut self, cache_name: &str, key: &[u8]) {
+        let Some(keys) = self.watchers.get_mut(cache_name) else {
+            return;
+        };
+        let Some(clients) = keys.remove(key) else {
+            return;
+        };
+        if keys.is_empty() {
+            self.watchers.remove(cache_name);
+        }
+
+        let tracked = (cache_name.to_string(), key.to_vec());
+        for client in clients {
+            if let Some(state) = self.clients.get_mut(&client) {
+                state.untrack(&tracked);
+            }
+            let notice = Invalidation::Keys {
+                cache_name: cache_name.to_string(),
+                keys: vec![key.to_vec()],
+            };
+            self.notify(client, notice);
+        }
+    }
+
+    fn invalidate_cache(&mut self, cache_name: &str) {
+        let Some(keys) = self.watchers.remove(cache_name) else {
+            return;
+        };
+
+        let mut notified = HashSet::new();
+        for (key, clients) in keys {
+            let tracked = (cache_name.to_string(), key);
+            for client in clients {
+                if let Some(state) = self.clients.get_mut(&client) {
+                    state.untrack(&tracked);
+                }
+                notified.insert(client);
+            }
+        }
+        for client in notified {
+            let notice = Invalidation::Cache {
+                cache_name: cache_name.to_string(),
+            };
+            self.notify(client, notice);
+        }
+    }
+
+    /// Queue `notice` for `client`, when its queue is full it is told to flush everything
+    /// instead, which leaves it tracking nothing
+    fn notify(&mut self, client: u64, notice: Invalidation) {
+        let Some(state) = self.clients.get_mut(&client) else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = state.sender.try_send(notice) {
+            state.overflowed.store(true, Ordering::Release);
+            state.order.clear();
+            state.bytes = 0;
+            for tracked in std::mem::take(&mut state.keys).into_keys() {
+                unwatch(&mut self.watchers, &tracked, client);
+            }
+        }
+    }
+}
+
+/// Keys that clients with tracking on have read, so they can be told when their copies go
+/// stale and keep near caches of their own
+/// A key is tracked until its first invalidation and again once it is read again
+/// Each connection's keys may take up to `max_bytes_per_client`, past which its least recently
+/// read keys are untracked and invalidated
+pub struct KeyTracking {
+    max_bytes_per_client: usize,
+    table: Mutex<TrackingTable>,
+    /// Clients with tracking on, writes skip the table while there are none
+    clients: AtomicUsize,
+}
+
+impl KeyTracking {
+    pub fn new(max_bytes_per_client: usize) -> Self {
+        Self {
+            max_bytes_per_client,
+            table: Mutex::new(TrackingTable::default()),
+            clients: AtomicUsize::new(0),
+        }
+    }
+
+    /// Turn tracking on for connection `client`, returning where its notices arrive
+    /// Turning it on again starts over with no keys tracked
+    pub fn enable(&self, client: u64) -> InvalidationReceiver {
+        let (sender, receiver) = mpsc::channel(NOTICE_QUEUE);
+        let overflowed = Arc::new(AtomicBool::new(false));
+
+        let mut table = self.table.lock().unwrap();
+        table.remove_client(client);
+        table.clients.insert(
+            client,
+            TrackedClient {
+                sender,
+                overflowed: overflowed.clone(),
+                keys: HashMap::new(),
+                order: VecDeque::new(),
+                bytes: 0,
+                next_seq: 0,
+            },
+        );
+        self.clients.store(table.clients.len(), Ordering::Release);
+
+        InvalidationReceiver {
+            receiver,
+            overflowed,
+        }
+    }
+
+    /// Turn tracking off for `client` and forget its keys
+    pub fn disable(&self, client: u64) {
+        let mut table = self.table.lock().unwrap();
+        table.remove_client(client);
+        self.clients.store(table.clients.len(), Ordering::Release);
+    }
+
+    /// Whether any client has tracking on
+    pub fn has_clients(&self) -> bool {
+        self.clients.load(Ordering::Acquire) > 0
+    }
+
+    /// Track `key` of `cache_name` for `client`, nothing happens unless tracking is on for it
+    /// Track keys before reading them, so writes racing the read are not missed
+    pub fn track(&self, client: u64, cache_name: &str, key: &[u8]) {
+        if !self.has_clients() {
+            return;
+        }
+        self.table.lock().unwrap().track(
+            client,
+            (cache_name.to_string(), key.to_vec()),
+            self.max_bytes_per_client,
+        );
+    }
+
+    /// Tell the clients tracking `key` of `cache_name` that their copies are stale
+    pub fn invalidate(&self, cache_name: &str, key: &[u8]) {
+        if self.has_clients() {
+            self.table.lock().unwrap().invalidate(cache_name, key);
+        }
+    }
+
+    /// Tell the clients tracking keys of `cache_name` that all their copies are stale
+    pub fn invalidate_cache(&self, cache_name: &str) {
+        if self.has_clients() {
+            self.table.lock().unwrap().invalidate_cache(cache_name);
+        }
+    }
+}
+
+impl Default for KeyTracking {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TRACKED_BYTES)
+    }
+}
+
+/// Where the invalidations of one connection arrive
+pub struct InvalidationReceiver {
+    receiver: mpsc::Receiver<Invalidation>,
+    overflowed: Arc<AtomicBool>,
+}
+
+impl InvalidationReceiver {
+    /// The next notice, None once tracking was turned on again elsewhere
+    /// Notices that did not fit the queue arrive as Invalidation::All
+    pub async fn recv(&mut self) -> Option<Invalidation> {
+        if self.overflowed.swap(false, Ordering::AcqRel) {
+            return Some(Invalidation::All);
+        }
+        self.receiver.recv().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(cache_name: &str, keys: &[&[u8]]) -> Invalidation {
+        Invalidation::Keys {
+            cache_name: cache_name.to_string(),
+            keys: keys.iter().map(|key| key.to_vec()).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalidates_tracked_keys_once() {
+        let tracking = KeyTracking::default();
+        let mut first = tracking.enable(1);
+        let mut second = tracking.enable(2);
+
+        tracking.track(1, "users", b"alice");
+        tracking.track(2, "users", b"alice");
+        tracking.track(2, "users", b"bob");
+        // Nobody tracks other keys
+        tracking.invalidate("users", b"carol");
+        tracking.invalidate("users", b"alice");
+        assert_eq!(first.recv().await, Some(keys("users", &[b"alice"])));
+        assert_eq!(second.recv().await, Some(keys("users", &[b"alice"])));
+
+        // Tracking ends with the first invalidation
+        tracking.invalidate("users", b"alice");
+        tracking.invalidate_cache("users");
+        let cache = Invalidation::Cache {
+            cache_name: "users".to_string(),
+        };
+        assert_eq!(second.recv().await, Some(cache));
+        assert!(first.receiver.try_recv().is_err());
+
+        tracking.disable(1);
+        tracking.disable(2);
+        assert!(!tracking.has_clients());
+        assert!(tracking.table.lock().unwrap().watchers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cap_untracks_least_recently_read_keys() {
+        // Room for two keys of 6 bytes, cache name included
+        let tracking = KeyTracking::new(12);
+        let mut receiver = tracking.enable(7);
+
+        tracking.track(7, "c", b"key01");
+        tracking.track(7, "c", b"key02");
+        // Reading key01 again makes key02 the oldest
+        tracking.track(7, "c", b"key01");
+        tracking.track(7, "c", b"key03");
+        assert_eq!(receiver.recv().await, Some(keys("c", &[b"key02"])));
+        assert_eq!(tracking.table.lock().unwrap().clients[&7].bytes, 12);
+
+        tracking.invalidate("c", b"key02");
+        tracking.invalidate("c", b"key01");
+        assert_eq!(receiver.recv().await, Some(keys("c", &[b"key01"])));
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_flushes_everything() {
+        let tracking = KeyTracking::default();
+        let mut receiver = tracking.enable(1);
+        tracking.track(1, "other", b"key");
+
+        for i in 0..=NOTICE_QUEUE {
+            let key = i.to_be_bytes();
+            tracking.track(1, "c", &key);
+            tracking.invalidate("c", &key);
+        }
+
+        // Flushing everything covers the keys still tracked too
+        assert_eq!(receiver.recv().await, Some(Invalidation::All));
+        let table = tracking.table.lock().unwrap();
+        assert_eq!(table.clients[&1].bytes, 0);
+        assert!(table.watchers.is_empty());
+    }
+}
@@ -0,0 +1,304 @@
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Each power of two is split into this many buckets, so recorded values are within 1/16
+/// (about 6%) of the true latency
+const SUB_BUCKETS: usize = 16;
+const SUB_BUCKET_BITS: u32 = SUB_BUCKETS.trailing_zeros();
+
+/// Latencies are capped at 2^40 microseconds, about 12 days
+const MAX_BITS: u32 = 40;
+const BUCKETS: usize = (MAX_BITS - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS;
+
+/// Operations whose latency is tracked per cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LatencyOperation {
+    Put,
+    Get,
+    Delete,
+}
+
+impl LatencyOperation {
+    pub const ALL: [LatencyOperation; 3] = [Self::Put, Self::Get, Self::Delete];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Put => "put",
+            Self::Get => "get",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// Log-linear histogram of latencies in microseconds in the style of HDR histograms,
+/// recording takes a few atomic adds and no lock
+struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    sum_us: AtomicU64,
+    max_us: AtomicU64,
+}
+
+impl LatencyHistogram {
+    fn new() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            sum_us: AtomicU64::new(0),
+            max_us: AtomicU64::new(0),
+        }
+    }
+
+    fn bucket(value_us: u64) -> usize {
+        let value_us = value_us.min((1 << MAX_BITS) - 1);
+        if value_us < SUB_BUCKETS as u64 {
+            return value_us as usize;
+        }
+        let exponent = 63 - value_us.leading_zeros();
+        let shift = exponent - SUB_BUCKET_BITS;
+        let sub_bucket = (value_us >> shift) as usize - SUB_BUCKETS;
+        (shift as usize + 1) * SUB_BUCKETS + sub_bucket
+    }
+
+    /// Largest value that lands in `bucket`
+    fn bucket_upper_bound(bucket: usize) -> u64 {
+        if bucket < SUB_BUCKETS {
+            return bucket as u64;
+        }
+        let shift = (bucket / SUB_BUCKETS - 1) as u32;
+        let lower = ((SUB_BUCKETS + bucket % SUB_BUCKETS) as u64) << shift;
+        lower + (1 << shift) - 1
+    }
+
+    fn record(&self, value_us: u64) {
+        self.buckets[Self::bucket(value_us)].fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(value_us, Ordering::Relaxed);
+        self.max_us.fetch_max(value_us, Ordering::Relaxed);
+    }
+
+    fn summary(&self) -> LatencySummary {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect();
+        let count: u64 = counts.iter().sum();
+        let max_us = self.max_us.load(Ordering::Relaxed);
+
+        // Smallest value that at least `quantile` of the recorded latencies do not exceed
+        let percentile = |quantile: f64| {
+            if count == 0 {
+                return 0;
+            }
+            let rank = ((quantile * count as f64).ceil() as u64).max(1);
+            let mut seen = 0;
+            for (bucket, bucket_count) in counts.iter().enumerate() {
+                seen += bucket_count;
+                if seen >= rank {
+                    return Self::bucket_upper_bound(bucket).min(max_us);
+                }
+            }
+            max_us
+        };
+
+        LatencySummary {
+            count,
+            sum_us: self.sum_us.load(Ordering::Relaxed),
+            p50_us: percentile(0.50),
+            p95_us: percentile(0.95),
+            p99_us: percentile(0.99),
+            max_us,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub sum_us: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+struct CacheLatency {
+    put: LatencyHistogram,
+    get: LatencyHistogram,
+    delete: LatencyHistogram,
+}
+
+impl CacheLatency {
+    fn histogram(&self, operation: LatencyOperation) -> &LatencyHistogram {
+        match operation {
+            LatencyOperation::Put => &self.put,
+            LatencyOperation::Get => &self.get,
+            LatencyOperation::Delete => &self.delete,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheLatencyStats {
+    pub cache_name: String,
+    pub put: LatencySummary,
+    pub get: LatencySummary,
+    pub delete: LatencySummary,
+}
+
+impl CacheLatencyStats {
+    pub fn operation(&self, operation: LatencyOperation) -> &LatencySummary {
+        match operation {
+            LatencyOperation::Put => &self.put,
+            LatencyOperation::Get => &self.get,
+            LatencyOperation::Delete => &self.delete,
+        }
+    }
+}
+
+/// Latency histograms of PUT, GET and DELETE per cache
+/// Share one tracker between frontends so their operations land in the same histograms
+#[derive(Default)]
+pub struct LatencyTracker {
+    caches: DashMap<String, Arc<CacheLatency>>,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time an operation on `cache_name` until the returned timer is dropped
+    /// Only start timers for caches that exist, each cache timed keeps its histograms
+    pub fn start(&self, cache_name: &str, operation: LatencyOperation) -> LatencyTimer {
+        let latency = match self.caches.get(cache_name) {
+            Some(latency) => latency.clone(),
+            None => self
+                .caches
+                .entry(cache_name.to_string())
+                .or_insert_with(|| {
+                    Arc::new(CacheLatency {
+                        put: LatencyHistogram::new(),
+                        get: LatencyHistogram::new(),
+                        delete: LatencyHistogram::new(),
+                    })
+                })
+                .clone(),
+        };
+        LatencyTimer {
+            latency,
+            operation,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn cache(&self, cache_name: &str) -> Option<CacheLatencyStats> {
+        self.caches
+            .get(cache_name)
+            .map(|latency| Self::stats(cache_name, &latency))
+    }
+
+    /// Latencies of every cache timed so far, by cache name
+    pub fn caches(&self) -> Vec<CacheLatencyStats> {
+        let mut caches: Vec<_> = self
+            .caches
+            .iter()
+            .map(|entry| Self::stats(entry.key(), entry.value()))
+            .collect();
+        caches.sort_by(|a, b| a.cache_name.cmp(&b.cache_name));
+        caches
+    }
+
+    /// Forget the histograms of a dropped cache
+    pub fn remove_cache(&self, cache_name: &str) {
+        self.caches.remove(cache_name);
+    }
+
+    fn stats(cache_name: &str, latency: &CacheLatency) -> CacheLatencyStats {
+        CacheLatencyStats {
+            cache_name: cache_name.to_string(),
+            put: latency.put.summary(),
+            get: latency.get.summary(),
+            delete: latency.delete.summary(),
+        }
+    }
+}
+
+/// Records the time from creation to drop, so failed operations are measured too
+pub struct LatencyTimer {
+    latency: Arc<CacheLatency>,
+    operation: LatencyOperation,
+    started: Instant,
+}
+
+impl Drop for LatencyTimer {
+    fn drop(&mut self) {
+        let elapsed_us = self.started.elapsed().as_micros() as u64;
+        self.latency.histogram(self.operation).record(elapsed_us);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_bound_their_values() {
+        for value in [
+            0, 1, 15, 16, 17, 31, 32, 33, 100, 1_000, 123_456, 10_000_000,
+        ] {
+            let upper = LatencyHistogram::bucket_upper_bound(LatencyHistogram::bucket(value));
+            assert!(
+                upper >= value,
+                "{} lands in a bucket ending at {}",
+                value,
+                upper
+            );
+            assert!(
+                upper - value <= value / 16,
+                "{} is rounded up to {}",
+                value,
+                upper
+            );
+        }
+        assert_eq!(LatencyHistogram::bucket(u64::MAX), BUCKETS - 1);
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let histogram = LatencyHistogram::new();
+        for value in 1..=1000 {
+            histogram.record(value);
+        }
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 1000);
+        assert_eq!(summary.sum_us, 500_500);
+        assert_eq!(summary.max_us, 1000);
+        // Within the bucket precision of the exact percentiles
+        assert!((500..=532).contains(&summary.p50_us));
+        assert!((950..=1000).contains(&summary.p95_us));
+        assert!((990..=1000).contains(&summary.p99_us));
+
+        assert_eq!(LatencyHistogram::new().summary().p99_us, 0);
+    }
+
+    #[test]
+    fn test_tracker_times_per_cache_and_operation() {
+        let tracker = LatencyTracker::new();
+        drop(tracker.start("users", LatencyOperation::Get));
+        drop(tracker.start("users", LatencyOperation::Get));
+        drop(tracker.start("orders", LatencyOperation::Put));
+
+        let caches = tracker.caches();
+        assert_eq!(caches.len(), 2);
+        assert_eq!(caches[0].cache_name, "orders");
+        assert_eq!(caches[0].put.count, 1);
+        assert_eq!(tracker.cache("users").unwrap().get.count, 2);
+        assert_eq!(tracker.cache("users").unwrap().put.count, 0);
+
+        tracker.remove_cache("users");
+        assert!(tracker.cache("users").is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use dashmap::DashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Leases a table holds before it first sweeps out expired ones
+const MIN_SWEEP_LEASES: usize = 1024;
+
+/// Lease-based lock (LOCK/UNLOCK)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockValue {
+    pub token: u64,
+    pub expires_at_ms: u64,
+}
+
+impl LockValue {
+    pub fn is_held(&self, now_ms: u64) -> bool {
+        self.expires_at_ms > now_ms
+    }
+}
+
+/// Live leases taken with LOCK, kept apart from cache entries so neither eviction nor entry
+/// expiry can release a lease early
+/// Tokens come from one counter seeded with the start time in microseconds, so they keep rising
+/// for a key once its lease is forgotten and across restarts
+pub(crate) struct LeaseTable<K>
+where
+    K: Hash + Eq + Clone,
+{
+    leases: DashMap<(String, K), LockValue>,
+    next_token: AtomicU64,
+    sweep_at: AtomicUsize,
+}
+
+impl<K> LeaseTable<K>
+where
+    K: Hash + Eq + Clone,
+{
+    pub(crate) fn new() -> Self {
+        let started_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_micros() as u64);
+        Self {
+            leases: DashMap::new(),
+            next_token: AtomicU64::new(started_us.max(1)),
+            sweep_at: AtomicUsize::new(MIN_SWEEP_LEASES),
+        }
+    }
+
+    /// Take the lease on `key` until `expires_at_ms`, None while another one is live
+    pub(crate) fn acquire(
+        &self,
+        cache_name: &str,
+        key: &K,
+        now_ms: u64,
+        expires_at_ms: u64,
+    ) -> Option<LockValue> {
+        self.sweep(now_ms);
+
+        let mut lease = self
+            .leases
+            .entry((cache_name.to_string(), key.clone()))
+            .or_default();
+        if lease.is_held(now_ms) {
+            return None;
+        }
+        *lease = LockValue {
+            token: self.next_token.fetch_add(1, Ordering::Relaxed),
+            expires_at_ms,
+        };
+        Some(*lease)
+    }
+
+    /// Release the lease on `key` if `token` still holds it
+    pub(crate) fn release(&self, cache_name: &str, key: &K, token: u64, now_ms: u64) -> bool {
+        self.leases
+            .remove_if(&(cache_name.to_string(), key.clone()), |_, lease| {
+                lease.token == token && lease.is_held(now_ms)
+            })
+            .is_some()
+    }
+
+    /// Forget every lease on keys of `cache_name`
+    pub(crate) fn remove_cache(&self, cache_name: &str) {
+        self.leases.retain(|(cache, _), _| cache != cache_name);
+    }
+
+    /// Move the leases on keys of `from` over to `to`
+    pub(crate) fn rename_cache(&self, from: &str, to: &str) {
+        let moved: Vec<(K, LockValue)> = self
+            .leases
+            .iter()
+            .filter(|entry| entry.key().0 == from)
+            .map(|entry| (entry.key().1.clone(), *entry.value()))
+            .collect();
+        self.remove_cache(from);
+        for (key, lease) in moved {
+            self.leases.insert((to.to_string(), key), lease);
+        }
+    }
+
+    /// Drop expired leases once the table has doubled since the last sweep
+    fn sweep(&self, now_ms: u64) {
+        if self.leases.len() < self.sweep_at.load(Ordering::Relaxed) {
+            return;
+        }
+        self.leases.retain(|_, lease| lease.is_held(now_ms));
+        self.sweep_at.store(
+            (self.leases.len() * 2).max(MIN_SWEEP_LEASES),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+impl<K> Default for LeaseTable<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_expiry() {
+        let lock = LockValue {
+            token: 3,
+            expires_at_ms: 1_000,
+        };
+        assert!(lock.is_held(999));
+        assert!(!lock.is_held(1_000));
+    }
+
+    #[test]
+    fn test_tokens_rise_after_release() {
+        let leases = LeaseTable::new();
+        let key = b"job".to_vec();
+
+        let first = leases.acquire("jobs", &key, 0, 1_000).unwrap();
+        assert!(leases.acquire("jobs", &key, 500, 1_500).is_none());
+        assert!(!leases.release("jobs", &key, first.token + 1, 500));
+        assert!(leases.release("jobs", &key, first.token, 500));
+
+        // A forgotten lease still gets a higher token next time
+        let second = leases.acquire("jobs", &key, 600, 1_600).unwrap();
+        assert!(second.token > first.token);
+
+        // An expired holder cannot release a lease taken over since
+        let third = leases.acquire("jobs", &key, 2_000, 3_000).unwrap();
+        assert!(!leases.release("jobs", &key, second.token, 2_000));
+        assert!(third.token > second.token);
+    }
+}
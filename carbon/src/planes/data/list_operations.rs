@@ -0,0 +1,203 @@
+use crate::domain::ListEnd;
+use crate::domain::response::GetResponse;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::ListOperations;
+use crate::planes::data::structured::{
+    ListValue, StructuredValue, ensure_raw_cache, load_structure,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde_bytes::ByteBuf;
+use shared::{Error, Result};
+use std::pin::pin;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// List commands for the Vec<u8>/Bytes service used by the servers
+#[async_trait]
+impl ListOperations<Vec<u8>, Bytes> for CacheOperationsService<Vec<u8>, Bytes> {
+    async fn list_push(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        end: ListEnd,
+        values: Vec<Bytes>,
+    ) -> Result<usize> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("PUSH", cache_name, &key);
+        let (cache_store, config) = self.get_cache_for_write(cache_name).await?;
+        ensure_raw_cache(cache_name, &config)?;
+
+        let _guard = self.lock_key(cache_name, &key).await;
+
+        let mut list = match load_structure(&cache_store, &key).await? {
+            Some(existing) => existing.into_list()?,
+            None => ListValue::default(),
+        };
+
+        for value in values {
+            let item = ByteBuf::from(value.to_vec());
+            match end {
+                ListEnd::Left => list.items.push_front(item),
+                ListEnd::Right => list.items.push_back(item),
+            }
+        }
+
+        let length = list.items.len();
+        let encoded = StructuredValue::List(list).encode()?;
+        self.write_entry(cache_name, &cache_store, key.clone(), encoded)
+            .await?;
+
+        self.notify_list_waiters(cache_name, &key);
+        Ok(length)
+    }
+
+    async fn list_pop(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+        end: ListEnd,
+    ) -> Result<GetResponse<Bytes>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("POP", cache_name, key);
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+
+        let _guard = self.lock_key(cache_name, key).await;
+
+        let mut list = load_structure(&cache_store, key)
+            .await?
+            .ok_or(Error::NotFound)?
+            .into_list()?;
+
+        let item = match end {
+            ListEnd::Left => list.items.pop_front(),
+            ListEnd::Right => list.items.pop_back(),
+        }
+        .ok_or(Error::NotFound)?;
+
+        // Like Redis, an empty list is removed entirely
+        if list.items.is_empty() {
+            self.delete_locked(cache_name, key).await?;
+        } else {
+            let encoded = StructuredValue::List(list).encode()?;
+            self.write_entry(cache_name, &cache_store, key.clone(), encoded)
+                .await?;
+        }
+
+        Ok(GetResponse::new(true, Bytes::from(item.into_vec())))
+    }
+
+    async fn list_blocking_pop(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+        end: ListEnd,
+        timeout: Duration,
+    ) -> Result<GetResponse<Bytes>> {
+        let cache_name = &*self.resolve(cache_name);
+        // Not timed as a whole since waiting is the point, each pop attempt is timed
+        let deadline = Instant::now() + timeout;
+        let notify = self.list_waiter(cache_name, key);
+
+        let result = loop {
+            // Register interest before popping so a push in between is not missed
+            let mut notified = pin!(notify.notified());
+            notified.as_mut().enable();
+
+            match self.list_pop(cache_name, key, end).await {
+                Err(Error::NotFound) => {}
+                other => break other,
+            }
+
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                break Err(Error::NotFound);
+            }
+        };
+
+        drop(notify);
+        self.release_list_waiter(cache_name, key);
+        result
+    }
+
+    async fn list_range(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+        start: i64,
+        stop: i64,
+    ) -> Result<GetResponse<Vec<Bytes>>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("LRANGE", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        let list = match load_structure(&cache_store, key).await? {
+            Some(existing) => existing.into_list()?,
+            None => return Ok(GetResponse::new(true, Vec::new())),
+        };
+
+        let values = match range_bounds(list.items.len(), start, stop) {
+            Some((first, last)) => list
+                .items
+                .into_iter()
+                .skip(first)
+                .take(last - first + 1)
+                .map(|item| Bytes::from(item.into_vec()))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(GetResponse::new(true, values))
+    }
+
+    async fn list_len(&self, cache_name: &str, key: &Vec<u8>) -> Result<usize> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("LLEN", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        match load_structure(&cache_store, key).await? {
+            Some(existing) => Ok(existing.into_list()?.items.len()),
+            None => Ok(0),
+        }
+    }
+}
+
+/// Resolve LRANGE-style inclusive bounds against a list of `len` items
+pub(crate) fn range_bounds(len: usize, start: i64, stop: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+
+    if start >= len || start > stop {
+        return None;
+    }
+    Some((start as usize, stop as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_bounds() {
+        assert_eq!(range_bounds(5, 0, -1), Some((0, 4)));
+        assert_eq!(range_bounds(5, 1, 2), Some((1, 2)));
+        assert_eq!(range_bounds(5, -2, -1), Some((3, 4)));
+        assert_eq!(range_bounds(5, -10, 100), Some((0, 4)));
+    }
+
+    #[test]
+    fn test_range_bounds_empty() {
+        assert_eq!(range_bounds(0, 0, -1), None);
+        assert_eq!(range_bounds(5, 5, 10), None);
+        assert_eq!(range_bounds(5, 3, 1), None);
+        assert_eq!(range_bounds(5, 0, -6), None);
+    }
+}
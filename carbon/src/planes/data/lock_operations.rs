@@ -0,0 +1,61 @@
+use crate::domain::LockLease;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::LockOperations;
+use async_trait::async_trait;
+use bytes::Bytes;
+use shared::{Error, Result};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Lock commands for the Vec<u8>/Bytes service used by the servers
+// Leases live in the service's lease table rather than in the cache, so eviction and expiry of
+// entries cannot hand a held lock to another client
+#[async_trait]
+impl LockOperations<Vec<u8>> for CacheOperationsService<Vec<u8>, Bytes> {
+    async fn lock(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        ttl: Duration,
+    ) -> Result<Option<LockLease>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("LOCK", cache_name, &key);
+        if ttl.is_zero() {
+            return Err(Error::InvalidValue("lock ttl must be positive".to_string()));
+        }
+
+        // Checked for its maintenance mode and ops/sec budget like any other write
+        self.get_cache_for_write(cache_name).await?;
+
+        let now = now_millis();
+        let lease = self
+            .leases()
+            .acquire(
+                cache_name,
+                &key,
+                now,
+                now.saturating_add(ttl.as_millis() as u64),
+            )
+            .map(|lock| LockLease {
+                token: lock.token,
+                expires_at_ms: lock.expires_at_ms,
+            });
+
+        Ok(lease)
+    }
+
+    async fn unlock(&self, cache_name: &str, key: &Vec<u8>, token: u64) -> Result<bool> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("UNLOCK", cache_name, key);
+        self.get_cache_for_write(cache_name).await?;
+
+        // A holder whose lease expired has lost the lock, even if nobody took it since
+        Ok(self.leases().release(cache_name, key, token, now_millis()))
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
@@ -0,0 +1,118 @@
+use dashmap::DashSet;
+use serde::Serialize;
+use shared::{Error, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Which caches are in read-only maintenance mode
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceStatus {
+    /// Every cache refuses writes
+    pub read_only: bool,
+    /// Caches refusing writes on their own, sorted by name
+    pub caches: Vec<String>,
+}
+
+impl MaintenanceStatus {
+    /// Whether any writes are being refused
+    pub fn active(&self) -> bool {
+        self.read_only || !self.caches.is_empty()
+    }
+}
+
+/// Read-only switch for the whole server or single caches, used during migrations and backup
+/// snapshots
+/// Share one between frontends so writes are refused over every protocol
+#[derive(Debug, Default)]
+pub struct Maintenance {
+    read_only: AtomicBool,
+    caches: DashSet<String>,
+}
+
+impl Maintenance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refuse or allow writes to every cache, caches set read-only on their own stay so
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    /// Refuse or allow writes to `cache_name`
+    pub fn set_cache_read_only(&self, cache_name: &str, read_only: bool) {
+        if read_only {
+            self.caches.insert(cache_name.to_string());
+        } else {
+            self.caches.remove(cache_name);
+        }
+    }
+
+    pub fn is_read_only(&self, cache_name: &str) -> bool {
+        self.read_only.load(Ordering::Relaxed) || self.caches.contains(cache_name)
+    }
+
+    /// Error for writes to `cache_name` while it is read-only
+    pub fn check_writable(&self, cache_name: &str) -> Result<()> {
+        if self.read_only.load(Ordering::Relaxed) {
+            return Err(Error::ReadOnly("server is in maintenance mode".to_string()));
+        }
+        if self.caches.contains(cache_name) {
+            return Err(Error::ReadOnly(format!(
+                "cache '{}' is in maintenance mode",
+                cache_name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Forget a cache that was dropped
+    pub fn remove_cache(&self, cache_name: &str) {
+        self.caches.remove(cache_name);
+    }
+
+    /// Keep a renamed cache read-only under its new name
+    pub fn rename_cache(&self, from: &str, to: &str) {
+        if self.caches.remove(from).is_some() {
+            self.caches.insert(to.to_string());
+        }
+    }
+
+    pub fn status(&self) -> MaintenanceStatus {
+        let mut caches: Vec<String> = self.caches.iter().map(|name| name.clone()).collect();
+        caches.sort();
+        MaintenanceStatus {
+            read_only: self.read_only.load(Ordering::Relaxed),
+            caches,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_server_and_caches() {
+        let maintenance = Maintenance::new();
+        assert!(maintenance.check_writable("orders").is_ok());
+        assert!(!maintenance.status().active());
+
+        maintenance.set_cache_read_only("orders", true);
+        assert!(matches!(
+            maintenance.check_writable("orders"),
+            Err(Error::ReadOnly(_))
+        ));
+        assert!(maintenance.check_writable("users").is_ok());
+
+        maintenance.set_read_only(true);
+        assert!(maintenance.is_read_only("users"));
+
+        // Leaving server-wide maintenance keeps the cache read-only
+        maintenance.set_read_only(false);
+        assert!(!maintenance.is_read_only("users"));
+        assert_eq!(maintenance.status().caches, ["orders"]);
+
+        maintenance.set_cache_read_only("orders", false);
+        assert!(!maintenance.status().active());
+    }
+}
@@ -1,4 +1,47 @@
+pub mod bulk_load;
 pub mod cache_operations;
+pub mod change_log;
+pub mod clear;
+pub mod clone;
+pub mod conditional_operations;
+pub mod connections;
+pub mod content_type_operations;
+pub(crate) mod content_types;
+pub mod hash_operations;
+pub mod json_operations;
+mod key_locks;
+pub mod key_tracking;
+pub mod latency;
+pub(crate) mod leases;
+pub mod list_operations;
+pub mod lock_operations;
+pub mod maintenance;
 pub mod operation;
+pub mod ops_limiter;
+pub mod pinning;
+pub mod probabilistic;
+pub mod probabilistic_operations;
+pub mod quotas;
+pub mod set_operations;
+pub mod slow_log;
+pub mod sorted_set_operations;
+pub mod structured;
+pub(crate) mod tag_index;
+pub mod tag_operations;
+pub mod time_series_operations;
+pub mod ttl_reaper;
+pub mod value_formats;
+pub mod views;
 
+pub use bulk_load::{BULK_LOAD_BATCH_SIZE, BulkLoader};
 pub use cache_operations::CacheOperationsService;
+pub use change_log::{Change, ChangeLog, ChangePage};
+pub use connections::{ConnectionHandle, ConnectionInfo, ConnectionRegistry, InFlightRequest};
+pub use key_tracking::{
+    DEFAULT_MAX_TRACKED_BYTES, Invalidation, InvalidationReceiver, KeyTracking,
+};
+pub use latency::{CacheLatencyStats, LatencyOperation, LatencySummary, LatencyTracker};
+pub use maintenance::{Maintenance, MaintenanceStatus};
+pub use ops_limiter::{OpsLimiter, OpsLimiterStats};
+pub use quotas::{Principal, QuotaTracker, QuotaUsage, with_principal};
+pub use slow_log::{SlowLog, SlowLogEntry, with_caller};
@@ -1,6 +1,9 @@
+use crate::domain::{EntryMatch, EntryMetadata, ListEnd, LockLease};
 use crate::domain::response::{DeleteResponse, GetResponse, PutResponse};
 use async_trait::async_trait;
 use shared::Result;
+use std::collections::BTreeMap;
+use std::time::Duration;
 
 /// Application-level cache operations trait
 /// This is for orchestrating operations across named caches
@@ -11,4 +14,274 @@ pub trait CacheOperations<K, V>: Send + Sync + 'static {
     async fn get(&self, cache_name: &str, key: &K) -> Result<GetResponse<V>>;
 
     async fn delete(&self, cache_name: &str, key: &K) -> Result<DeleteResponse>;
+
+    /// Access statistics of an entry, for caches created with track_metadata
+    async fn metadata(&self, cache_name: &str, key: &K) -> Result<EntryMetadata>;
+
+    /// Read and delete an entry atomically, NotFound when the key is missing
+    async fn getdel(&self, cache_name: &str, key: &K) -> Result<GetResponse<V>>;
+
+    /// Read an entry and reset its TTL atomically, None keeps it until evicted
+    async fn getex(
+        &self,
+        cache_name: &str,
+        key: &K,
+        ttl: Option<Duration>,
+    ) -> Result<GetResponse<V>>;
+}
+
+/// Partial read/write of JSON documents stored in JSON-typed caches
+/// Paths are RFC 6901 JSON pointers ("" addresses the whole document)
+#[async_trait]
+pub trait JsonOperations<K>: Send + Sync + 'static {
+    async fn json_get(
+        &self,
+        cache_name: &str,
+        key: &K,
+        pointer: &str,
+    ) -> Result<GetResponse<serde_json::Value>>;
+
+    async fn json_set(
+        &self,
+        cache_name: &str,
+        key: K,
+        pointer: &str,
+        value: serde_json::Value,
+    ) -> Result<PutResponse>;
+}
+
+/// Field map operations (HSET/HGET/HDEL/HGETALL) on hash-typed entries
+#[async_trait]
+pub trait HashOperations<K, V>: Send + Sync + 'static {
+    /// Set a field, `created` is true when the field did not exist before
+    async fn hset(
+        &self,
+        cache_name: &str,
+        key: K,
+        field: String,
+        value: V,
+    ) -> Result<PutResponse>;
+
+    async fn hget(&self, cache_name: &str, key: &K, field: &str) -> Result<GetResponse<V>>;
+
+    /// Delete a field, removing the whole entry once its last field is gone
+    async fn hdel(&self, cache_name: &str, key: &K, field: &str) -> Result<DeleteResponse>;
+
+    async fn hgetall(
+        &self,
+        cache_name: &str,
+        key: &K,
+    ) -> Result<GetResponse<BTreeMap<String, V>>>;
+}
+
+/// Double-ended list operations (LPUSH/RPUSH/LPOP/RPOP/LRANGE) on list-typed entries
+#[async_trait]
+pub trait ListOperations<K, V>: Send + Sync + 'static {
+    /// Push values onto one end in order, returning the new list length
+    async fn list_push(
+        &self,
+        cache_name: &str,
+        key: K,
+        end: ListEnd,
+        values: Vec<V>,
+    ) -> Result<usize>;
+
+    /// Pop a value from one end, NotFound when the list is empty or missing
+    async fn list_pop(&self, cache_name: &str, key: &K, end: ListEnd) -> Result<GetResponse<V>>;
+
+    /// Pop a value, waiting up to `timeout` for one to be pushed
+    async fn list_blocking_pop(
+        &self,
+        cache_name: &str,
+        key: &K,
+        end: ListEnd,
+        timeout: Duration,
+    ) -> Result<GetResponse<V>>;
+
+    /// Values between `start` and `stop` inclusive, negative indices count from the tail
+    async fn list_range(
+        &self,
+        cache_name: &str,
+        key: &K,
+        start: i64,
+        stop: i64,
+    ) -> Result<GetResponse<Vec<V>>>;
+
+    async fn list_len(&self, cache_name: &str, key: &K) -> Result<usize>;
+}
+
+/// Set operations (SADD/SREM/SISMEMBER/SMEMBERS/SCARD/SINTER/SUNION) on set-typed entries
+#[async_trait]
+pub trait SetOperations<K, V>: Send + Sync + 'static {
+    /// Add members, returning how many were not already present
+    async fn sadd(&self, cache_name: &str, key: K, members: Vec<V>) -> Result<usize>;
+
+    /// Remove members, returning how many were present; an emptied set is removed
+    async fn srem(&self, cache_name: &str, key: &K, members: Vec<V>) -> Result<usize>;
+
+    async fn sismember(&self, cache_name: &str, key: &K, member: &V) -> Result<bool>;
+
+    async fn smembers(&self, cache_name: &str, key: &K) -> Result<GetResponse<Vec<V>>>;
+
+    async fn scard(&self, cache_name: &str, key: &K) -> Result<usize>;
+
+    /// Members present in every set, missing keys count as empty sets
+    async fn sinter(&self, cache_name: &str, keys: &[K]) -> Result<GetResponse<Vec<V>>>;
+
+    /// Members present in any of the sets
+    async fn sunion(&self, cache_name: &str, keys: &[K]) -> Result<GetResponse<Vec<V>>>;
+}
+
+/// Sorted set operations (ZADD/ZREM/ZSCORE/ZRANK/ZRANGE/ZRANGEBYSCORE/ZCARD) on entries
+/// whose members are ordered by score
+#[async_trait]
+pub trait SortedSetOperations<K, V>: Send + Sync + 'static {
+    /// Add members or update their scores, returning how many were not already present
+    async fn zadd(&self, cache_name: &str, key: K, members: Vec<(V, f64)>) -> Result<usize>;
+
+    /// Remove members, returning how many were present; an emptied sorted set is removed
+    async fn zrem(&self, cache_name: &str, key: &K, members: Vec<V>) -> Result<usize>;
+
+    async fn zscore(&self, cache_name: &str, key: &K, member: &V) -> Result<Option<f64>>;
+
+    /// Position of the member by ascending score, or by descending score with `reverse`
+    async fn zrank(
+        &self,
+        cache_name: &str,
+        key: &K,
+        member: &V,
+        reverse: bool,
+    ) -> Result<Option<usize>>;
+
+    /// Members from rank `start` to `stop` inclusive, negative ranks count from the last one
+    async fn zrange(
+        &self,
+        cache_name: &str,
+        key: &K,
+        start: i64,
+        stop: i64,
+        reverse: bool,
+    ) -> Result<GetResponse<Vec<(V, f64)>>>;
+
+    /// Members scoring between `min` and `max` inclusive, lowest first and at most `limit`
+    async fn zrangebyscore(
+        &self,
+        cache_name: &str,
+        key: &K,
+        min: f64,
+        max: f64,
+        limit: Option<usize>,
+    ) -> Result<GetResponse<Vec<(V, f64)>>>;
+
+    async fn zcard(&self, cache_name: &str, key: &K) -> Result<usize>;
+}
+
+/// Probabilistic structures (PFADD/PFCOUNT, BF.RESERVE/BF.ADD/BF.EXISTS) answering
+/// cardinality and membership questions in a fixed amount of memory
+#[async_trait]
+pub trait ProbabilisticOperations<K, V>: Send + Sync + 'static {
+    /// Add items to a HyperLogLog, returning whether its estimate may have changed
+    async fn pfadd(&self, cache_name: &str, key: K, items: Vec<V>) -> Result<bool>;
+
+    /// Estimated number of distinct items added to any of the keys, missing keys count as empty
+    async fn pfcount(&self, cache_name: &str, keys: &[K]) -> Result<u64>;
+
+    /// Create an empty Bloom filter sized for `capacity` items at `error_rate`
+    async fn bf_reserve(
+        &self,
+        cache_name: &str,
+        key: K,
+        error_rate: f64,
+        capacity: u64,
+    ) -> Result<()>;
+
+    /// Add an item, creating a default-sized filter, returning false when it may be present already
+    async fn bf_add(&self, cache_name: &str, key: K, item: V) -> Result<bool>;
+
+    /// Whether the item may have been added, a missing filter holds nothing
+    async fn bf_exists(&self, cache_name: &str, key: &K, item: &V) -> Result<bool>;
+}
+
+/// Append-only time series (APPEND/RANGE) of samples in timestamp order, trimmed to the
+/// retention settings of their cache as samples are appended
+#[async_trait]
+pub trait TimeSeriesOperations<K, V>: Send + Sync + 'static {
+    /// Append a sample newer than every sample of the series, returning how many it then holds
+    async fn ts_append(&self, cache_name: &str, key: K, timestamp: u64, value: V) -> Result<usize>;
+
+    /// Samples timestamped between `from` and `to` inclusive, oldest first and at most `limit`
+    async fn ts_range(
+        &self,
+        cache_name: &str,
+        key: &K,
+        from: u64,
+        to: u64,
+        limit: Option<usize>,
+    ) -> Result<GetResponse<Vec<(u64, V)>>>;
+}
+
+/// Lease-based distributed locks (LOCK/UNLOCK) with fencing tokens
+#[async_trait]
+pub trait LockOperations<K>: Send + Sync + 'static {
+    /// Take the lock on `key` for `ttl`, None while another lease is still live
+    async fn lock(&self, cache_name: &str, key: K, ttl: Duration) -> Result<Option<LockLease>>;
+
+    /// Release the lock if `token` still holds it, returning whether it was released
+    async fn unlock(&self, cache_name: &str, key: &K, token: u64) -> Result<bool>;
+}
+
+/// Tags grouping entries so they can be invalidated together
+#[async_trait]
+pub trait TagOperations<K, V>: Send + Sync + 'static {
+    /// Store a value carrying `tags`, replacing any tags the entry had
+    async fn put_tagged(
+        &self,
+        cache_name: &str,
+        key: K,
+        value: V,
+        tags: Vec<String>,
+    ) -> Result<PutResponse>;
+
+    /// Delete every entry carrying `tag`, returning how many were removed
+    async fn invalidate_tag(&self, cache_name: &str, tag: &str) -> Result<usize>;
+}
+
+/// Writes applied only while the entry is unchanged, for optimistic concurrency (HTTP If-Match)
+#[async_trait]
+pub trait ConditionalOperations<K, V>: Send + Sync + 'static {
+    /// Store a value carrying `tags` and `content_type` if the current entry satisfies
+    /// `condition`, PreconditionFailed otherwise
+    async fn put_if(
+        &self,
+        cache_name: &str,
+        key: K,
+        value: V,
+        tags: Vec<String>,
+        content_type: Option<String>,
+        condition: &EntryMatch,
+    ) -> Result<PutResponse>;
+
+    /// Delete the entry if it satisfies `condition`, PreconditionFailed otherwise
+    async fn delete_if(
+        &self,
+        cache_name: &str,
+        key: &K,
+        condition: &EntryMatch,
+    ) -> Result<DeleteResponse>;
+}
+
+/// Values kept with the media type they were uploaded as, so frontends can serve them back as such
+#[async_trait]
+pub trait ContentTypeOperations<K, V>: Send + Sync + 'static {
+    /// Store a value with its media type, a later plain PUT clears it
+    async fn put_with_content_type(
+        &self,
+        cache_name: &str,
+        key: K,
+        value: V,
+        content_type: String,
+    ) -> Result<PutResponse>;
+
+    /// Media type the entry was stored with, None if it was stored without one
+    fn content_type(&self, cache_name: &str, key: &K) -> Option<String>;
 }
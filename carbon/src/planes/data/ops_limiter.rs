@@ -0,0 +1,238 @@
+use dashmap::DashMap;
+use serde::Serialize;
+use shared::{Error, Result};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Token bucket allowing `ops_per_sec` sustained, with a burst of one second's worth
+struct OpsBucket {
+    ops_per_sec: u32,
+    tokens: f64,
+    refilled_at: Instant,
+    throttled: u64,
+}
+
+impl OpsBucket {
+    fn new(ops_per_sec: u32, now: Instant) -> Self {
+        Self {
+            ops_per_sec,
+            tokens: ops_per_sec as f64,
+            refilled_at: now,
+            throttled: 0,
+        }
+    }
+
+    /// Add the tokens earned since the last refill, up to one second's worth
+    fn refill(&mut self, now: Instant) {
+        let rate = self.ops_per_sec as f64;
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * rate).min(rate);
+        self.refilled_at = now;
+    }
+
+    /// Whether a token is left after refilling, the operation counts as throttled otherwise
+    fn has_token(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            true
+        } else {
+            self.throttled += 1;
+            false
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobalOpsStats {
+    pub ops_per_sec: u32,
+    pub throttled: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheOpsStats {
+    pub cache_name: String,
+    pub ops_per_sec: u32,
+    pub throttled: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpsLimiterStats {
+    pub global: Option<GlobalOpsStats>,
+    pub caches: Vec<CacheOpsStats>,
+}
+
+/// Caps operations per second per cache (from its config) and across all caches,
+/// so one busy cache cannot starve the others
+/// Share one limiter between frontends so they draw on the same budget
+pub struct OpsLimiter {
+    global: Option<Mutex<OpsBucket>>,
+    caches: DashMap<String, Mutex<OpsBucket>>,
+    global_throttled: AtomicU64,
+}
+
+impl OpsLimiter {
+    pub fn new(global_ops_per_sec: Option<u32>) -> Self {
+        let now = Instant::now();
+        Self {
+            global: global_ops_per_sec
+                .filter(|ops| *ops > 0)
+                .map(|ops| Mutex::new(OpsBucket::new(ops, now))),
+            caches: DashMap::new(),
+            global_throttled: AtomicU64::new(0),
+        }
+    }
+
+    /// Take a token for one operation on `cache_name`, limited to `cache_ops_per_sec` if set
+    /// Both the cache's limit and the global one are checked before either is charged
+    pub fn check(&self, cache_name: &str, cache_ops_per_sec: Option<u32>) -> Result<()> {
+        self.check_at(cache_name, cache_ops_per_sec, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        cache_name: &str,
+        cache_ops_per_sec: Option<u32>,
+        now: Instant,
+    ) -> Result<()> {
+        let Some(ops_per_sec) = cache_ops_per_sec.filter(|ops| *ops > 0) else {
+            // Only a cache whose limit was lifted still has a bucket to drop
+            if self.caches.contains_key(cache_name) {
+                self.caches.remove(cache_name);
+            }
+            return self.acquire_global(now);
+        };
+
+        // Buckets are found under a shard read lock, only a cache's first operation writes
+        let entry = match self.caches.get(cache_name) {
+            Some(entry) => entry,
+            None => self
+                .caches
+                .entry(cache_name.to_string())
+                .or_insert_with(|| Mutex::new(OpsBucket::new(ops_per_sec, now)))
+                .downgrade(),
+        };
+        let mut bucket = entry.lock().unwrap();
+        // The cache was reconfigured, start over at the new rate
+        if bucket.ops_per_sec != ops_per_sec {
+            *bucket = OpsBucket::new(ops_per_sec, now);
+        }
+        if !bucket.has_token(now) {
+            return Err(Error::Throttled(format!(
+                "cache '{}' is limited to {} operations per second",
+                cache_name, ops_per_sec
+            )));
+        }
+
+        // Still holding the cache's bucket, so its token is there to take once the global
+        // limit allows the operation
+        self.acquire_global(now)?;
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    /// Take a token from the global bucket, if there is a global limit
+    fn acquire_global(&self, now: Instant) -> Result<()> {
+        if let Some(global) = &self.global {
+            let mut bucket = global.lock().unwrap();
+            if !bucket.has_token(now) {
+                self.global_throttled.fetch_add(1, Ordering::Relaxed);
+                return Err(Error::Throttled(format!(
+                    "server is limited to {} operations per second",
+                    bucket.ops_per_sec
+                )));
+            }
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+
+    /// Forget the bucket of a dropped cache
+    pub fn remove_cache(&self, cache_name: &str) {
+        self.caches.remove(cache_name);
+    }
+
+    pub fn stats(&self) -> OpsLimiterStats {
+        let global = self.global.as_ref().map(|global| GlobalOpsStats {
+            ops_per_sec: global.lock().unwrap().ops_per_sec,
+            throttled: self.global_throttled.load(Ordering::Relaxed),
+        });
+
+        let mut caches: Vec<_> = self
+            .caches
+            .iter()
+            .map(|entry| {
+                let bucket = entry.value().lock().unwrap();
+                CacheOpsStats {
+                    cache_name: entry.key().clone(),
+                    ops_per_sec: bucket.ops_per_sec,
+                    throttled: bucket.throttled,
+                }
+            })
+            .collect();
+        caches.sort_by(|a, b| a.cache_name.cmp(&b.cache_name));
+
+        OpsLimiterStats { global, caches }
+    }
+}
+
+impl Default for OpsLimiter {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_per_cache_limit() {
+        let limiter = OpsLimiter::default();
+        let now = Instant::now();
+
+        assert!(limiter.check_at("orders", Some(2), now).is_ok());
+        assert!(limiter.check_at("orders", Some(2), now).is_ok());
+        assert!(matches!(
+            limiter.check_at("orders", Some(2), now),
+            Err(Error::Throttled(_))
+        ));
+
+        // Other caches are unaffected, and tokens come back over time
+        assert!(limiter.check_at("users", None, now).is_ok());
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.check_at("orders", Some(2), later).is_ok());
+
+        let stats = limiter.stats();
+        assert_eq!(stats.caches.len(), 1);
+        assert_eq!(stats.caches[0].throttled, 1);
+    }
+
+    #[test]
+    fn test_global_limit_spans_caches() {
+        let limiter = OpsLimiter::new(Some(2));
+        let now = Instant::now();
+
+        assert!(limiter.check_at("orders", None, now).is_ok());
+        assert!(limiter.check_at("users", None, now).is_ok());
+        assert!(limiter.check_at("sessions", None, now).is_err());
+
+        assert_eq!(limiter.stats().global.unwrap().throttled, 1);
+    }
+
+    #[test]
+    fn test_global_refusal_leaves_cache_tokens() {
+        let limiter = OpsLimiter::new(Some(1));
+        let now = Instant::now();
+
+        assert!(limiter.check_at("orders", Some(2), now).is_ok());
+        assert!(limiter.check_at("orders", Some(2), now).is_err());
+        assert!(limiter.check_at("orders", Some(2), now).is_err());
+
+        // Turned away by the global limit, orders keeps the token it had left
+        let bucket = limiter.caches.get("orders").unwrap();
+        assert_eq!(bucket.lock().unwrap().tokens, 1.0);
+        assert_eq!(bucket.lock().unwrap().throttled, 0);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::encoding::ToBytes;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use shared::Result;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+impl<K, V> CacheOperationsService<K, V>
+where
+    K: ToBytes + Debug + Hash + Eq + Send + Sync + Clone + 'static,
+    V: ToBytes + Debug + Send + Sync + Clone + 'static,
+{
+    /// Keep an entry of `cache_name` out of reach of eviction until it is unpinned or deleted
+    /// Returns false when it was already pinned, refused once the cache's pinned entries would
+    /// exceed max_pinned_bytes
+    pub async fn pin(&self, cache_name: &str, key: &K) -> Result<bool> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("PIN", cache_name, &key.to_bytes());
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+        cache_store.pin(key).await
+    }
+
+    /// Let a pinned entry be evicted again, returns false when it was not pinned
+    pub async fn unpin(&self, cache_name: &str, key: &K) -> Result<bool> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("UNPIN", cache_name, &key.to_bytes());
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+        cache_store.unpin(key).await
+    }
+
+    /// Bytes of keys and values pinned in `cache_name`, None when it does not pin entries
+    pub async fn pinned_bytes(&self, cache_name: &str) -> Result<Option<u64>> {
+        let cache_name = &*self.resolve(cache_name);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+        Ok(cache_store.pinned_bytes())
+    }
+}
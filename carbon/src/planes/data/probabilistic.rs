@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shared::{Error, Result};
+
+/// Index bits of a HyperLogLog hash, giving 2^14 registers and about 0.81% standard error
+const HLL_PRECISION: u32 = 14;
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+
+/// Filters reserved without sizing hold this many items at this false positive rate
+pub const DEFAULT_BLOOM_CAPACITY: u64 = 1_000;
+pub const DEFAULT_BLOOM_ERROR_RATE: f64 = 0.01;
+
+/// Largest bit array a filter may reserve, 4 MiB once stored
+const MAX_BLOOM_BITS: u64 = 32 * 1024 * 1024;
+
+/// Most bits set or checked per item, well past what any useful error rate needs
+const MAX_BLOOM_HASHES: u32 = 64;
+
+/// Cardinality estimate of every item ever added (PFADD/PFCOUNT)
+/// Registers are kept dense, so each estimate takes a fixed 16 KiB however many items it saw
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HyperLogLogValue {
+    #[serde(with = "base64_bytes")]
+    pub registers: Vec<u8>,
+}
+
+impl Default for HyperLogLogValue {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; HLL_REGISTERS],
+        }
+    }
+}
+
+impl HyperLogLogValue {
+    /// Observe an item, returning whether the estimate may have changed
+    pub fn add(&mut self, item: &[u8]) -> bool {
+        let hash = u64::from_be_bytes(item_hash(item)[..8].try_into().unwrap());
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        // The sentinel bit caps the rank once every remaining bit is zero
+        let remaining = (hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1));
+        let rank = remaining.leading_zeros() as u8 + 1;
+
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Fold another estimate in, as if its items had been added here
+    pub fn merge(&mut self, other: &HyperLogLogValue) {
+        for (register, theirs) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*theirs);
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|register| 2f64.powi(-i32::from(*register)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        // Small cardinalities are estimated better by counting the registers never touched
+        let zeros = self
+            .registers
+            .iter()
+            .filter(|register| **register == 0)
+            .count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+
+    /// Registers decoded from a stored entry must be complete before they are indexed
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.registers.len() != HLL_REGISTERS {
+            return Err(Error::Internal(format!(
+                "hyperloglog has {} registers instead of {}",
+                self.registers.len(),
+                HLL_REGISTERS
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Membership filter answering "possibly present" or "definitely absent" (BF.ADD/BF.EXISTS)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BloomValue {
+    #[serde(with = "base64_bytes")]
+    pub bits: Vec<u8>,
+    pub bit_count: u64,
+    pub hashes: u32,
+    pub capacity: u64,
+    pub error_rate: f64,
+    /// Items added that set at least one bit, an estimate like the filter itself
+    pub items: u64,
+}
+
+impl BloomValue {
+    /// Filter sized to hold `capacity` items with a false positive rate of `error_rate`
+    pub fn new(capacity: u64, error_rate: f64) -> Result<Self> {
+        if !(error_rate > 0.0 && error_rate < 1.0) {
+            return Err(Error::InvalidValue(
+                "bloom filter error rate must be between 0 and 1".to_string(),
+            ));
+        }
+        if capacity == 0 {
+            return Err(Error::InvalidValue(
+                "bloom filter capacity must be positive".to_string(),
+            ));
+        }
+
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(capacity as f64) * error_rate.ln() / (ln2 * ln2)).ceil();
+        if bits > MAX_BLOOM_BITS as f64 {
+            return Err(Error::InvalidValue(format!(
+                "bloom filter for {} items at error rate {} needs more than {} bits",
+                capacity, error_rate, MAX_BLOOM_BITS
+            )));
+        }
+        let bit_count = (bits as u64).max(8);
+        let hashes = ((bit_count as f64 / capacity as f64) * ln2)
+            .round()
+            .clamp(1.0, MAX_BLOOM_HASHES as f64) as u32;
+
+        Ok(Self {
+            bits: vec![0; bit_count.div_ceil(8) as usize],
+            bit_count,
+            hashes,
+            capacity,
+            error_rate,
+            items: 0,
+        })
+    }
+
+    /// Add an item, returning false when it may already have been added
+    pub fn insert(&mut self, item: &[u8]) -> bool {
+        let mut changed = false;
+        for position in self.positions(item) {
+            let (byte, mask) = ((position / 8) as usize, 1 << (position % 8));
+            if self.bits[byte] & mask == 0 {
+                self.bits[byte] |= mask;
+                changed = true;
+            }
+        }
+        if changed {
+            self.items += 1;
+        }
+        changed
+    }
+
+    /// Whether the item may have been added, false answers are always right
+    pub fn contains(&self, item: &[u8]) -> bool {
+        self.positions(item)
+            .all(|position| self.bits[(position / 8) as usize] & (1 << (position % 8)) != 0)
+    }
+
+    /// Bit positions of an item, derived from two halves of one hash by double hashing
+    fn positions(&self, item: &[u8]) -> impl Iterator<Item = u64> + use<> {
+        let hash = item_hash(item);
+        let h1 = u64::from_be_bytes(hash[..8].try_into().unwrap());
+        let h2 = u64::from_be_bytes(hash[8..16].try_into().unwrap());
+        let bit_count = self.bit_count;
+        (0..u64::from(self.hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bit_count)
+    }
+
+    /// A filter decoded from a stored entry must be one `new` could have made, with bits
+    /// covering every position and a bounded number of hashes, before it is used
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.bit_count == 0
+            || self.bit_count > MAX_BLOOM_BITS
+            || self.bits.len() as u64 != self.bit_count.div_ceil(8)
+            || self.hashes == 0
+            || self.hashes > MAX_BLOOM_HASHES
+        {
+            return Err(Error::Internal("bloom filter is malformed".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Hash that stays the same across processes and releases, as estimates outlive both
+fn item_hash(item: &[u8]) -> [u8; 32] {
+    Sha256::digest(item).into()
+}
+
+/// Register and bit arrays are stored as base64 rather than as JSON arrays of numbers
+mod base64_bytes {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyperloglog_estimate() {
+        let mut hll = HyperLogLogValue::default();
+        assert_eq!(hll.count(), 0);
+
+        for i in 0..10_000u32 {
+            hll.add(&i.to_be_bytes());
+        }
+        // Adding the same items again leaves the registers as they are
+        assert!(!(0..10_000u32).any(|i| hll.add(&i.to_be_bytes())));
+
+        let count = hll.count() as f64;
+        assert!((count - 10_000.0).abs() < 10_000.0 * 0.03, "{}", count);
+
+        let mut other = HyperLogLogValue::default();
+        for i in 5_000..15_000u32 {
+            other.add(&i.to_be_bytes());
+        }
+        hll.merge(&other);
+        let merged = hll.count() as f64;
+        assert!((merged - 15_000.0).abs() < 15_000.0 * 0.03, "{}", merged);
+    }
+
+    #[test]
+    fn test_bloom_membership() {
+        let mut bloom = BloomValue::new(1_000, 0.01).unwrap();
+        assert_eq!(bloom.hashes, 7);
+
+        for i in 0..1_000u32 {
+            bloom.insert(&i.to_be_bytes());
+        }
+        assert!((0..1_000u32).all(|i| bloom.contains(&i.to_be_bytes())));
+        assert!(!bloom.insert(&7u32.to_be_bytes()));
+
+        let false_positives = (1_000..11_000u32)
+            .filter(|i| bloom.contains(&i.to_be_bytes()))
+            .count();
+        assert!(false_positives < 200, "{}", false_positives);
+    }
+
+    #[test]
+    fn test_bloom_sizing_limits() {
+        assert!(matches!(
+            BloomValue::new(100, 1.0),
+            Err(Error::InvalidValue(_))
+        ));
+        assert!(matches!(
+            BloomValue::new(0, 0.01),
+            Err(Error::InvalidValue(_))
+        ));
+        assert!(matches!(
+            BloomValue::new(u64::MAX, 0.01),
+            Err(Error::InvalidValue(_))
+        ));
+        assert_eq!(BloomValue::new(1, 1e-300).unwrap().hashes, MAX_BLOOM_HASHES);
+    }
+
+    #[test]
+    fn test_bloom_validate() {
+        let bloom = BloomValue::new(1_000, 0.01).unwrap();
+        assert!(bloom.validate().is_ok());
+
+        let mut crafted = bloom.clone();
+        crafted.hashes = u32::MAX;
+        assert!(crafted.validate().is_err());
+
+        let mut crafted = bloom.clone();
+        crafted.bits.push(0);
+        assert!(crafted.validate().is_err());
+
+        let mut crafted = bloom;
+        crafted.bit_count = 0;
+        assert!(crafted.validate().is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::ProbabilisticOperations;
+use crate::planes::data::probabilistic::{
+    BloomValue, DEFAULT_BLOOM_CAPACITY, DEFAULT_BLOOM_ERROR_RATE, HyperLogLogValue,
+};
+use crate::planes::data::structured::{StructuredValue, ensure_raw_cache, load_structure};
+use crate::ports::CacheStore;
+use async_trait::async_trait;
+use bytes::Bytes;
+use shared::{Error, Result};
+use std::sync::Arc;
+
+// HyperLogLog and Bloom filter commands for the Vec<u8>/Bytes service used by the servers
+#[async_trait]
+impl ProbabilisticOperations<Vec<u8>, Bytes> for CacheOperationsService<Vec<u8>, Bytes> {
+    async fn pfadd(&self, cache_name: &str, key: Vec<u8>, items: Vec<Bytes>) -> Result<bool> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("PFADD", cache_name, &key);
+        let (cache_store, config) = self.get_cache_for_write(cache_name).await?;
+        ensure_raw_cache(cache_name, &config)?;
+
+        let _guard = self.lock_key(cache_name, &key).await;
+
+        // Like Redis, adding nothing still creates the key
+        let (mut hll, created) = match load_hyperloglog(&cache_store, &key).await? {
+            Some(hll) => (hll, false),
+            None => (HyperLogLogValue::default(), true),
+        };
+
+        let mut changed = created;
+        for item in &items {
+            changed |= hll.add(item);
+        }
+
+        if changed {
+            let encoded = StructuredValue::HyperLogLog(hll).encode()?;
+            self.write_entry(cache_name, &cache_store, key, encoded)
+                .await?;
+        }
+
+        Ok(changed)
+    }
+
+    async fn pfcount(&self, cache_name: &str, keys: &[Vec<u8>]) -> Result<u64> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("PFCOUNT", cache_name, &keys.join(&b","[..]));
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        let mut merged: Option<HyperLogLogValue> = None;
+        for key in keys {
+            if let Some(hll) = load_hyperloglog(&cache_store, key).await? {
+                match merged.as_mut() {
+                    Some(merged) => merged.merge(&hll),
+                    None => merged = Some(hll),
+                }
+            }
+        }
+
+        Ok(merged.map_or(0, |hll| hll.count()))
+    }
+
+    async fn bf_reserve(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        error_rate: f64,
+        capacity: u64,
+    ) -> Result<()> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("BF.RESERVE", cache_name, &key);
+        let bloom = BloomValue::new(capacity, error_rate)?;
+
+        let (cache_store, config) = self.get_cache_for_write(cache_name).await?;
+        ensure_raw_cache(cache_name, &config)?;
+
+        let _guard = self.lock_key(cache_name, &key).await;
+
+        if cache_store.exists(&key).await?.exists {
+            return Err(Error::PreconditionFailed(
+                "key already holds a value".to_string(),
+            ));
+        }
+
+        let encoded = StructuredValue::Bloom(bloom).encode()?;
+        self.write_entry(cache_name, &cache_store, key, encoded)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn bf_add(&self, cache_name: &str, key: Vec<u8>, item: Bytes) -> Result<bool> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("BF.ADD", cache_name, &key);
+        let (cache_store, config) = self.get_cache_for_write(cache_name).await?;
+        ensure_raw_cache(cache_name, &config)?;
+
+        let _guard = self.lock_key(cache_name, &key).await;
+
+        let mut bloom = match load_bloom(&cache_store, &key).await? {
+            Some(bloom) => bloom,
+            None => BloomValue::new(DEFAULT_BLOOM_CAPACITY, DEFAULT_BLOOM_ERROR_RATE)?,
+        };
+
+        let added = bloom.insert(&item);
+        if added {
+            let encoded = StructuredValue::Bloom(bloom).encode()?;
+            self.write_entry(cache_name, &cache_store, key, encoded)
+                .await?;
+        }
+
+        Ok(added)
+    }
+
+    async fn bf_exists(&self, cache_name: &str, key: &Vec<u8>, item: &Bytes) -> Result<bool> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("BF.EXISTS", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        Ok(load_bloom(&cache_store, key)
+            .await?
+            .is_some_and(|bloom| bloom.contains(item)))
+    }
+}
+
+#[allow(clippy::ptr_arg)] // cache stores are keyed by Vec<u8>
+async fn load_hyperloglog(
+    store: &Arc<dyn CacheStore<Vec<u8>, Bytes>>,
+    key: &Vec<u8>,
+) -> Result<Option<HyperLogLogValue>> {
+    load_structure(store, key)
+        .await?
+        .map(StructuredValue::into_hyperloglog)
+        .transpose()
+}
+
+#[allow(clippy::ptr_arg)] // cache stores are keyed by Vec<u8>
+async fn load_bloom(
+    store: &Arc<dyn CacheStore<Vec<u8>, Bytes>>,
+    key: &Vec<u8>,
+) -> Result<Option<BloomValue>> {
+    load_structure(store, key)
+        .await?
+        .map(StructuredValue::into_bloom)
+        .transpose()
+}
@@ -0,0 +1,437 @@
+use crate::domain::Quota;
+use crate::planes::data::tag_index::move_cache_entries;
+use dashmap::DashMap;
+use serde::Serialize;
+use shared::{Error, Result};
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Most entries charged to their writers at once, new entries past it are written uncharged
+pub const MAX_OWNED_ENTRIES: usize = 1_000_000;
+
+tokio::task_local! {
+    /// Who the writes running in the current task are charged to, set by the frontends
+    static PRINCIPAL: Principal;
+}
+
+/// Run `future` with its writes charged to `principal` and held to its quota
+pub async fn with_principal<F: Future>(principal: Principal, future: F) -> F::Output {
+    PRINCIPAL.scope(principal, future).await
+}
+
+/// Authenticated user writes are attributed to, with the quota resolved from their roles
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub name: String,
+    pub quota: Quota,
+}
+
+/// What a principal currently holds and has written today
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct QuotaUsage {
+    pub principal: String,
+    pub bytes: u64,
+    pub keys: u64,
+    pub ops_today: u64,
+}
+
+#[derive(Default)]
+struct Usage {
+    bytes: u64,
+    keys: u64,
+    // Held by writes that passed the check and are still running
+    reserved_bytes: u64,
+    reserved_keys: u64,
+    ops_today: u64,
+    // UTC day ops_today counts, since the Unix epoch
+    day: u64,
+}
+
+/// Charges entries to the principal that wrote them and enforces their quotas
+/// Entries are released when deleted, expired or evicted; writes made without a principal
+/// (over TCP) are not charged
+pub struct QuotaTracker {
+    usage: DashMap<Arc<str>, Usage>,
+    // Owner and charged size of each entry, keyed by cache name and key
+    owners: DashMap<(String, Vec<u8>), (Arc<str>, u64)>,
+    // Set once MAX_OWNED_ENTRIES was reached, so it is only logged once
+    full: AtomicBool,
+}
+
+/// Room a write holds in its principal's quota while it runs, given back when dropped
+/// Reserved by the check, so concurrent writes cannot all pass it against the same usage
+#[must_use]
+pub struct QuotaReservation<'a> {
+    tracker: &'a QuotaTracker,
+    principal: Arc<str>,
+    bytes: u64,
+    keys: u64,
+}
+
+impl QuotaReservation<'_> {
+    /// Charge an entry of the write that the store took
+    pub fn record(&self, cache_name: &str, key: &[u8], size: u64) {
+        self.tracker
+            .record_write_for(&self.principal, cache_name, key, size);
+    }
+}
+
+impl Drop for QuotaReservation<'_> {
+    fn drop(&mut self) {
+        if let Some(mut usage) = self.tracker.usage.get_mut(&self.principal) {
+            usage.reserved_bytes = usage.reserved_bytes.saturating_sub(self.bytes);
+            usage.reserved_keys = usage.reserved_keys.saturating_sub(self.keys);
+        }
+    }
+}
+
+impl QuotaTracker {
+    pub fn new() -> Self {
+        Self {
+            usage: DashMap::new(),
+            owners: DashMap::new(),
+            full: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the current task's writes are charged to a principal
+    pub fn attributed(&self) -> bool {
+        PRINCIPAL.try_with(|_| ()).is_ok()
+    }
+
+    /// Count one write of `entries` (key, charged size) against the current principal's quota
+    /// and reserve room for it, failing if it would take them over a limit
+    /// None when the task has no principal, whose writes are not charged
+    pub fn check_write(
+        &self,
+        cache_name: &str,
+        entries: &[(&[u8], u64)],
+    ) -> Result<Option<QuotaReservation<'_>>> {
+        PRINCIPAL
+            .try_with(|principal| self.check_write_for(principal, cache_name, entries, today()))
+            .map_or(Ok(None), |reservation| reservation.map(Some))
+    }
+
+    fn check_write_for(
+        &self,
+        principal: &Principal,
+        cache_name: &str,
+        entries: &[(&[u8], u64)],
+        day: u64,
+    ) -> Result<QuotaReservation<'_>> {
+        // Overwriting one's own entry only charges the difference in size
+        let (mut bytes, mut keys) = (0u64, 0u64);
+        for (key, size) in entries {
+            match self.owners.get(&(cache_name.to_string(), key.to_vec())) {
+                Some(owner) if *owner.0 == *principal.name => {
+                    bytes += size.saturating_sub(owner.1);
+                }
+                _ => {
+                    bytes += size;
+                    keys += 1;
+                }
+            }
+        }
+
+        // Checked and reserved under the lock on the principal's usage
+        let mut usage = self
+            .usage
+            .entry(Arc::from(principal.name.as_str()))
+            .or_default();
+        if usage.day != day {
+            usage.day = day;
+            usage.ops_today = 0;
+        }
+
+        let quota = &principal.quota;
+        if let Some(max) = quota.max_ops_per_day.filter(|max| usage.ops_today >= *max) {
+            return Err(Error::QuotaExceeded(format!(
+                "'{}' is limited to {} writes per day",
+                principal.name, max
+            )));
+        }
+        if let Some(max) = quota
+            .max_keys
+            .filter(|max| usage.keys + usage.reserved_keys + keys > *max)
+        {
+            return Err(Error::QuotaExceeded(format!(
+                "'{}' is limited to {} keys",
+                principal.name, max
+            )));
+        }
+        if let Some(max) = quota
+            .max_bytes
+            .filter(|max| usage.bytes + usage.reserved_bytes + bytes > *max)
+        {
+            return Err(Error::QuotaExceeded(format!(
+                "'{}' is limited to {} bytes",
+                principal.name, max
+            )));
+        }
+
+        usage.ops_today += 1;
+        usage.reserved_bytes += bytes;
+        usage.reserved_keys += keys;
+        Ok(QuotaReservation {
+            tracker: self,
+            principal: usage.key().clone(),
+            bytes,
+            keys,
+        })
+    }
+
+    fn record_write_for(&self, principal: &str, cache_name: &str, key: &[u8], size: u64) {
+        let principal: Arc<str> = match self.usage.get(principal) {
+            Some(usage) => usage.key().clone(),
+            None => Arc::from(principal),
+        };
+        let owner_key = (cache_name.to_string(), key.to_vec());
+        if self.owners.len() >= MAX_OWNED_ENTRIES && !self.owners.contains_key(&owner_key) {
+            if !self.full.swap(true, Ordering::Relaxed) {
+                tracing::warn!(
+                    "Quota tracking holds {} entries, new entries are no longer charged",
+                    MAX_OWNED_ENTRIES
+                );
+            }
+            return;
+        }
+        let previous = self.owners.insert(owner_key, (principal.clone(), size));
+        if let Some((owner, size)) = previous {
+            self.release(&owner, size);
+        }
+
+        let mut usage = self.usage.entry(principal).or_default();
+        usage.bytes += size;
+        usage.keys += 1;
+    }
+
+    /// Stop charging an entry that was deleted or expired, whoever removed it
+    pub fn record_delete(&self, cache_name: &str, key: &[u8]) {
+        if let Some((_, (owner, size))) =
+            self.owners.remove(&(cache_name.to_string(), key.to_vec()))
+        {
+            self.release(&owner, size);
+        }
+    }
+
+    /// Stop charging the entries of a dropped cache
+    pub fn remove_cache(&self, cache_name: &str) {
+        let mut released = Vec::new();
+        self.owners.retain(|(cache, _), (owner, size)| {
+            if cache == cache_name {
+                released.push((owner.clone(), *size));
+                false
+            } else {
+                true
+            }
+        });
+        for (owner, size) in released {
+            self.release(&owner, size);
+        }
+    }
+
+    /// Keep charging the entries of a renamed cache to the same principals
+    pub fn rename_cache(&self, from: &str, to: &str) {
+        move_cache_entries(&self.owners, from, to);
+    }
+
+    fn release(&self, owner: &str, size: u64) {
+        if let Some(mut usage) = self.usage.get_mut(owner) {
+            usage.bytes = usage.bytes.saturating_sub(size);
+            usage.keys = usage.keys.saturating_sub(1);
+        }
+    }
+
+    /// Usage of one principal, zero if they have not written anything
+    pub fn usage(&self, principal: &str) -> QuotaUsage {
+        self.usage_on(principal, today())
+    }
+
+    /// Usage of every principal that has written something, by name
+    pub fn all_usage(&self) -> Vec<QuotaUsage> {
+        let day = today();
+        let mut usage: Vec<_> = self
+            .usage
+            .iter()
+            .map(|entry| entry.value().report(entry.key(), day))
+            .collect();
+        usage.sort_by(|a, b| a.principal.cmp(&b.principal));
+        usage
+    }
+
+    fn usage_on(&self, principal: &str, day: u64) -> QuotaUsage {
+        match self.usage.get(principal) {
+            Some(usage) => usage.report(principal, day),
+            None => QuotaUsage {
+                principal: principal.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Usage {
+    fn report(&self, principal: &str, day: u64) -> QuotaUsage {
+        QuotaUsage {
+            principal: principal.to_string(),
+            bytes: self.bytes,
+            keys: self.keys,
+            ops_today: if self.day == day { self.ops_today } else { 0 },
+        }
+    }
+}
+
+impl Default for QuotaTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / SECS_PER_DAY)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(key: &str, size: u64) -> [(&[u8], u64); 1] {
+        [(key.as_bytes(), size)]
+    }
+
+    fn principal(name: &str, quota: Quota) -> Principal {
+        Principal {
+            name: name.to_string(),
+            quota,
+        }
+    }
+
+    #[test]
+    fn test_bytes_and_keys_quota() {
+        let tracker = QuotaTracker::new();
+        let alice = principal(
+            "alice",
+            Quota {
+                max_bytes: Some(100),
+                max_keys: Some(2),
+                max_ops_per_day: None,
+            },
+        );
+
+        for key in ["a", "b"] {
+            tracker
+                .check_write_for(&alice, "orders", &write(key, 40), 0)
+                .unwrap()
+                .record("orders", key.as_bytes(), 40);
+        }
+        assert!(matches!(
+            tracker.check_write_for(&alice, "orders", &write("c", 1), 0),
+            Err(Error::QuotaExceeded(_))
+        ));
+
+        // Growing an own entry only charges the difference
+        assert!(
+            tracker
+                .check_write_for(&alice, "orders", &write("a", 60), 0)
+                .is_ok()
+        );
+        assert!(
+            tracker
+                .check_write_for(&alice, "orders", &write("a", 61), 0)
+                .is_err()
+        );
+
+        // Deleting frees the entry, whoever deletes it
+        tracker.record_delete("orders", b"a");
+        let usage = tracker.usage_on("alice", 0);
+        assert_eq!((usage.bytes, usage.keys), (40, 1));
+    }
+
+    #[test]
+    fn test_writes_in_flight_hold_their_room() {
+        let tracker = QuotaTracker::new();
+        let alice = principal(
+            "alice",
+            Quota {
+                max_keys: Some(1),
+                ..Default::default()
+            },
+        );
+
+        // A second write checked before the first is recorded cannot pass too
+        let reservation = tracker
+            .check_write_for(&alice, "orders", &write("a", 10), 0)
+            .unwrap();
+        assert!(
+            tracker
+                .check_write_for(&alice, "orders", &write("b", 10), 0)
+                .is_err()
+        );
+
+        // A write that did not go through gives its room back
+        drop(reservation);
+        let reservation = tracker
+            .check_write_for(&alice, "orders", &write("b", 10), 0)
+            .unwrap();
+        reservation.record("orders", b"b", 10);
+        drop(reservation);
+        let usage = tracker.usage_on("alice", 0);
+        assert_eq!((usage.bytes, usage.keys), (10, 1));
+        assert!(
+            tracker
+                .check_write_for(&alice, "orders", &write("c", 10), 0)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_ops_per_day_resets() {
+        let tracker = QuotaTracker::new();
+        let bob = principal(
+            "bob",
+            Quota {
+                max_ops_per_day: Some(1),
+                ..Default::default()
+            },
+        );
+
+        assert!(
+            tracker
+                .check_write_for(&bob, "orders", &write("a", 1), 7)
+                .is_ok()
+        );
+        assert!(
+            tracker
+                .check_write_for(&bob, "orders", &write("a", 1), 7)
+                .is_err()
+        );
+        assert!(
+            tracker
+                .check_write_for(&bob, "orders", &write("a", 1), 8)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_overwrite_moves_ownership_and_drop_releases() {
+        let tracker = QuotaTracker::new();
+        tracker.record_write_for("alice", "orders", b"a", 10);
+        tracker.record_write_for("bob", "orders", b"a", 30);
+        tracker.record_write_for("bob", "users", b"a", 5);
+
+        assert_eq!(tracker.usage("alice").keys, 0);
+        assert_eq!(tracker.usage("bob").bytes, 35);
+
+        tracker.remove_cache("orders");
+        let usage = tracker.all_usage();
+        assert_eq!(usage.len(), 2);
+        assert_eq!((usage[1].principal.as_str(), usage[1].bytes), ("bob", 5));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::domain::response::GetResponse;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::SetOperations;
+use crate::planes::data::structured::{
+    SetValue, StructuredValue, ensure_raw_cache, load_structure,
+};
+use crate::ports::CacheStore;
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde_bytes::ByteBuf;
+use shared::Result;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+// Set commands for the Vec<u8>/Bytes service used by the servers
+#[async_trait]
+impl SetOperations<Vec<u8>, Bytes> for CacheOperationsService<Vec<u8>, Bytes> {
+    async fn sadd(&self, cache_name: &str, key: Vec<u8>, members: Vec<Bytes>) -> Result<usize> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("SADD", cache_name, &key);
+        let (cache_store, config) = self.get_cache_for_write(cache_name).await?;
+        ensure_raw_cache(cache_name, &config)?;
+
+        let _guard = self.lock_key(cache_name, &key).await;
+
+        let mut set = load_set(&cache_store, &key).await?.unwrap_or_default();
+
+        let added = members
+            .into_iter()
+            .filter(|member| set.members.insert(ByteBuf::from(member.to_vec())))
+            .count();
+
+        if added > 0 {
+            let encoded = StructuredValue::Set(set).encode()?;
+            self.write_entry(cache_name, &cache_store, key, encoded)
+                .await?;
+        }
+
+        Ok(added)
+    }
+
+    async fn srem(&self, cache_name: &str, key: &Vec<u8>, members: Vec<Bytes>) -> Result<usize> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("SREM", cache_name, key);
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+
+        let _guard = self.lock_key(cache_name, key).await;
+
+        let mut set = match load_set(&cache_store, key).await? {
+            Some(set) => set,
+            None => return Ok(0),
+        };
+
+        let removed = members
+            .into_iter()
+            .filter(|member| set.members.remove(&ByteBuf::from(member.to_vec())))
+            .count();
+
+        if removed == 0 {
+            return Ok(0);
+        }
+
+        // Like Redis, an empty set is removed entirely
+        if set.members.is_empty() {
+            self.delete_locked(cache_name, key).await?;
+        } else {
+            let encoded = StructuredValue::Set(set).encode()?;
+            self.write_entry(cache_name, &cache_store, key.clone(), encoded)
+                .await?;
+        }
+
+        Ok(removed)
+    }
+
+    async fn sismember(&self, cache_name: &str, key: &Vec<u8>, member: &Bytes) -> Result<bool> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("SISMEMBER", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        Ok(load_set(&cache_store, key)
+            .await?
+            .is_some_and(|set| set.members.contains(&ByteBuf::from(member.to_vec()))))
+    }
+
+    async fn smembers(&self, cache_name: &str, key: &Vec<u8>) -> Result<GetResponse<Vec<Bytes>>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("SMEMBERS", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        let members = load_set(&cache_store, key)
+            .await?
+            .map(|set| to_values(set.members))
+            .unwrap_or_default();
+
+        Ok(GetResponse::new(true, members))
+    }
+
+    async fn scard(&self, cache_name: &str, key: &Vec<u8>) -> Result<usize> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("SCARD", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        Ok(load_set(&cache_store, key)
+            .await?
+            .map_or(0, |set| set.members.len()))
+    }
+
+    async fn sinter(&self, cache_name: &str, keys: &[Vec<u8>]) -> Result<GetResponse<Vec<Bytes>>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("SINTER", cache_name, &keys.join(&b","[..]));
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        let mut result: Option<BTreeSet<ByteBuf>> = None;
+        for key in keys {
+            let members = load_set(&cache_store, key)
+                .await?
+                .map(|set| set.members)
+                .unwrap_or_default();
+
+            let intersection = match result {
+                Some(current) => current.intersection(&members).cloned().collect(),
+                None => members,
+            };
+
+            // Nothing can be added back once the intersection is empty
+            if intersection.is_empty() {
+                return Ok(GetResponse::new(true, Vec::new()));
+            }
+            result = Some(intersection);
+        }
+
+        Ok(GetResponse::new(
+            true,
+            result.map(to_values).unwrap_or_default(),
+        ))
+    }
+
+    async fn sunion(&self, cache_name: &str, keys: &[Vec<u8>]) -> Result<GetResponse<Vec<Bytes>>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("SUNION", cache_name, &keys.join(&b","[..]));
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        let mut union = BTreeSet::new();
+        for key in keys {
+            if let Some(set) = load_set(&cache_store, key).await? {
+                union.extend(set.members);
+            }
+        }
+
+        Ok(GetResponse::new(true, to_values(union)))
+    }
+}
+
+#[allow(clippy::ptr_arg)] // cache stores are keyed by Vec<u8>
+async fn load_set(
+    store: &Arc<dyn CacheStore<Vec<u8>, Bytes>>,
+    key: &Vec<u8>,
+) -> Result<Option<SetValue>> {
+    load_structure(store, key)
+        .await?
+        .map(StructuredValue::into_set)
+        .transpose()
+}
+
+fn to_values(members: BTreeSet<ByteBuf>) -> Vec<Bytes> {
+    members
+        .into_iter()
+        .map(|member| Bytes::from(member.into_vec()))
+        .collect()
+}
@@ -0,0 +1,183 @@
+use crate::events::now_timestamp;
+use serde::Serialize;
+use shared::config::SlowLogConfig;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+tokio::task_local! {
+    /// Who issued the operations running in the current task, set by the frontends
+    static CALLER: String;
+}
+
+/// Run `future` with `caller` attributed to any slow operation it performs
+pub async fn with_caller<F: Future>(caller: String, future: F) -> F::Output {
+    CALLER.scope(caller, future).await
+}
+
+/// An operation that took at least the slow log threshold
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowLogEntry {
+    pub id: u64,
+    /// Seconds since the Unix epoch when the operation finished
+    pub timestamp: u64,
+    pub operation: &'static str,
+    pub cache_name: String,
+    /// Lossy UTF-8 rendering of the key
+    pub key: String,
+    pub duration_us: u64,
+    pub caller: Option<String>,
+}
+
+/// Bounded log of the most recent slow operations, oldest entries are dropped first
+pub struct SlowLog {
+    threshold: Duration,
+    max_len: usize,
+    entries: Mutex<VecDeque<SlowLogEntry>>,
+    next_id: AtomicU64,
+}
+
+impl SlowLog {
+    pub fn new(config: SlowLogConfig) -> Self {
+        Self {
+            threshold: Duration::from_millis(config.threshold_ms),
+            max_len: config.max_len,
+            entries: Mutex::new(VecDeque::with_capacity(config.max_len)),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Entries newest first
+    pub fn entries(&self) -> Vec<SlowLogEntry> {
+        self.entries.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    /// Clear the log, returning how many entries were dropped
+    pub fn reset(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let cleared = entries.len();
+        entries.clear();
+        cleared
+    }
+
+    fn record(&self, operation: &'static str, cache_name: &str, key: &[u8], elapsed: Duration) {
+        if self.max_len == 0 {
+            return;
+        }
+
+        let entry = SlowLogEntry {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: now_timestamp(),
+            operation,
+            cache_name: cache_name.to_string(),
+            key: String::from_utf8_lossy(key).into_owned(),
+            duration_us: elapsed.as_micros() as u64,
+            caller: CALLER.try_with(Clone::clone).ok(),
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.max_len {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+/// Times an operation from creation to drop, so early returns are measured too
+pub struct OperationTimer<'a> {
+    slow_log: &'a SlowLog,
+    operation: &'static str,
+    cache_name: &'a str,
+    key: Vec<u8>,
+    started: Instant,
+}
+
+impl<'a> OperationTimer<'a> {
+    pub fn start(
+        slow_log: &'a SlowLog,
+        operation: &'static str,
+        cache_name: &'a str,
+        key: &[u8],
+    ) -> Self {
+        Self {
+            slow_log,
+            operation,
+            cache_name,
+            key: key.to_vec(),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for OperationTimer<'_> {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        if elapsed >= self.slow_log.threshold {
+            self.slow_log
+                .record(self.operation, self.cache_name, &self.key, elapsed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slow_log(max_len: usize) -> SlowLog {
+        SlowLog::new(SlowLogConfig {
+            threshold_ms: 0,
+            max_len,
+        })
+    }
+
+    #[test]
+    fn test_bounded_newest_first() {
+        let log = slow_log(2);
+        for key in ["a", "b", "c"] {
+            log.record("GET", "users", key.as_bytes(), Duration::from_millis(5));
+        }
+
+        let keys: Vec<_> = log.entries().into_iter().map(|entry| entry.key).collect();
+        assert_eq!(keys, vec!["c", "b"]);
+
+        assert_eq!(log.reset(), 2);
+        assert!(log.entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_timer_records_caller() {
+        let log = slow_log(8);
+
+        with_caller("tcp:127.0.0.1:5000".to_string(), async {
+            let _timer = OperationTimer::start(&log, "PUT", "users", b"alice");
+        })
+        .await;
+
+        let entries = log.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].operation, "PUT");
+        assert_eq!(entries[0].caller.as_deref(), Some("tcp:127.0.0.1:5000"));
+    }
+
+    #[test]
+    fn test_fast_operations_are_not_recorded() {
+        let log = SlowLog::new(SlowLogConfig {
+            threshold_ms: 60_000,
+            max_len: 8,
+        });
+
+        drop(OperationTimer::start(&log, "GET", "users", b"alice"));
+
+        assert!(log.entries().is_empty());
+    }
+}
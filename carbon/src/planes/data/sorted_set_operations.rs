@@ -0,0 +1,204 @@
+use crate::domain::response::GetResponse;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::list_operations::range_bounds;
+use crate::planes::data::operation::SortedSetOperations;
+use crate::planes::data::structured::{
+    ScoredMember, SortedSetValue, StructuredValue, ensure_raw_cache, load_structure,
+};
+use crate::ports::CacheStore;
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde_bytes::ByteBuf;
+use shared::{Error, Result};
+use std::sync::Arc;
+
+// Sorted set commands for the Vec<u8>/Bytes service used by the servers
+#[async_trait]
+impl SortedSetOperations<Vec<u8>, Bytes> for CacheOperationsService<Vec<u8>, Bytes> {
+    async fn zadd(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        members: Vec<(Bytes, f64)>,
+    ) -> Result<usize> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("ZADD", cache_name, &key);
+        if members.iter().any(|(_, score)| !score.is_finite()) {
+            return Err(Error::InvalidValue("scores must be finite".to_string()));
+        }
+
+        let (cache_store, config) = self.get_cache_for_write(cache_name).await?;
+        ensure_raw_cache(cache_name, &config)?;
+        if members.is_empty() {
+            return Ok(0);
+        }
+
+        let _guard = self.lock_key(cache_name, &key).await;
+
+        let mut sorted_set = load_sorted_set(&cache_store, &key)
+            .await?
+            .unwrap_or_default();
+
+        let added = members
+            .into_iter()
+            .filter(|(member, score)| sorted_set.insert(ByteBuf::from(member.to_vec()), *score))
+            .count();
+
+        // Score updates are written even when no member was added
+        let encoded = StructuredValue::SortedSet(sorted_set).encode()?;
+        self.write_entry(cache_name, &cache_store, key, encoded)
+            .await?;
+
+        Ok(added)
+    }
+
+    async fn zrem(&self, cache_name: &str, key: &Vec<u8>, members: Vec<Bytes>) -> Result<usize> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("ZREM", cache_name, key);
+        let (cache_store, _) = self.get_cache_for_write(cache_name).await?;
+
+        let _guard = self.lock_key(cache_name, key).await;
+
+        let mut sorted_set = match load_sorted_set(&cache_store, key).await? {
+            Some(sorted_set) => sorted_set,
+            None => return Ok(0),
+        };
+
+        let removed = members
+            .iter()
+            .filter(|member| sorted_set.remove(member).is_some())
+            .count();
+
+        if removed == 0 {
+            return Ok(0);
+        }
+
+        // Like sets, an empty sorted set is removed entirely
+        if sorted_set.is_empty() {
+            self.delete_locked(cache_name, key).await?;
+        } else {
+            let encoded = StructuredValue::SortedSet(sorted_set).encode()?;
+            self.write_entry(cache_name, &cache_store, key.clone(), encoded)
+                .await?;
+        }
+
+        Ok(removed)
+    }
+
+    async fn zscore(&self, cache_name: &str, key: &Vec<u8>, member: &Bytes) -> Result<Option<f64>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("ZSCORE", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        Ok(load_sorted_set(&cache_store, key)
+            .await?
+            .and_then(|sorted_set| sorted_set.score(member)))
+    }
+
+    async fn zrank(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+        member: &Bytes,
+        reverse: bool,
+    ) -> Result<Option<usize>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("ZRANK", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        Ok(load_sorted_set(&cache_store, key)
+            .await?
+            .and_then(|sorted_set| {
+                let rank = sorted_set.rank(member)?;
+                Some(if reverse {
+                    sorted_set.len() - 1 - rank
+                } else {
+                    rank
+                })
+            }))
+    }
+
+    async fn zrange(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+        start: i64,
+        stop: i64,
+        reverse: bool,
+    ) -> Result<GetResponse<Vec<(Bytes, f64)>>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("ZRANGE", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        let mut entries = match load_sorted_set(&cache_store, key).await? {
+            Some(sorted_set) => sorted_set.into_entries(),
+            None => return Ok(GetResponse::new(true, Vec::new())),
+        };
+        if reverse {
+            entries.reverse();
+        }
+
+        let members = match range_bounds(entries.len(), start, stop) {
+            Some((first, last)) => to_pairs(entries.drain(first..=last)),
+            None => Vec::new(),
+        };
+
+        Ok(GetResponse::new(true, members))
+    }
+
+    async fn zrangebyscore(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+        min: f64,
+        max: f64,
+        limit: Option<usize>,
+    ) -> Result<GetResponse<Vec<(Bytes, f64)>>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("ZRANGEBYSCORE", cache_name, key);
+        if min.is_nan() || max.is_nan() {
+            return Err(Error::InvalidValue(
+                "score bounds must be numbers".to_string(),
+            ));
+        }
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        let members = load_sorted_set(&cache_store, key)
+            .await?
+            .map(|sorted_set| {
+                let window = sorted_set.range_by_score(min, max);
+                let window = &window[..limit.unwrap_or(usize::MAX).min(window.len())];
+                to_pairs(window.iter().cloned())
+            })
+            .unwrap_or_default();
+
+        Ok(GetResponse::new(true, members))
+    }
+
+    async fn zcard(&self, cache_name: &str, key: &Vec<u8>) -> Result<usize> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("ZCARD", cache_name, key);
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        Ok(load_sorted_set(&cache_store, key)
+            .await?
+            .map_or(0, |sorted_set| sorted_set.len()))
+    }
+}
+
+#[allow(clippy::ptr_arg)] // cache stores are keyed by Vec<u8>
+async fn load_sorted_set(
+    store: &Arc<dyn CacheStore<Vec<u8>, Bytes>>,
+    key: &Vec<u8>,
+) -> Result<Option<SortedSetValue>> {
+    load_structure(store, key)
+        .await?
+        .map(StructuredValue::into_sorted_set)
+        .transpose()
+}
+
+fn to_pairs(entries: impl Iterator<Item = ScoredMember>) -> Vec<(Bytes, f64)> {
+    entries
+        .map(|entry| (Bytes::from(entry.member.into_vec()), entry.score))
+        .collect()
+}
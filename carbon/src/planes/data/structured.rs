@@ -0,0 +1,466 @@
+use crate::domain::{CacheConfig, ValueFormat, ValueType};
+use crate::planes::data::probabilistic::{BloomValue, HyperLogLogValue};
+use crate::ports::CacheStore;
+use bytes::{BufMut, Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use shared::{Error, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
+
+/// Prefix identifying values written by the data structure commands
+/// Raw values never start with a NUL byte followed by this tag in practice,
+/// which lets commands reject keys holding a different kind of value
+const STRUCTURE_MAGIC: &[u8] = b"\0CBS";
+
+/// Server-managed value types stored inside an ordinary cache entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StructuredValue {
+    Hash(HashValue),
+    List(ListValue),
+    Set(SetValue),
+    SortedSet(SortedSetValue),
+    #[serde(rename = "hyperloglog")]
+    HyperLogLog(HyperLogLogValue),
+    Bloom(BloomValue),
+    TimeSeries(TimeSeriesValue),
+}
+
+/// Field map (HSET/HGET/HDEL)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HashValue {
+    pub fields: BTreeMap<String, ByteBuf>,
+}
+
+/// Double-ended list (LPUSH/RPUSH/LPOP/RPOP/LRANGE)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListValue {
+    pub items: VecDeque<ByteBuf>,
+}
+
+/// Unordered collection of unique members (SADD/SREM/SMEMBERS/SINTER)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SetValue {
+    pub members: BTreeSet<ByteBuf>,
+}
+
+/// Members ordered by score (ZADD/ZREM/ZRANGE/ZRANGEBYSCORE)
+/// Entries are kept sorted by score and then by member, so a member's position is its rank
+/// Each member's score is also kept by member, which finds its rank by binary search
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "StoredSortedSet")]
+pub struct SortedSetValue {
+    entries: Vec<ScoredMember>,
+    #[serde(skip)]
+    scores: HashMap<Vec<u8>, f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoredMember {
+    pub member: ByteBuf,
+    pub score: f64,
+}
+
+/// Stored form of a sorted set, the scores by member are rebuilt from its entries
+#[derive(Deserialize)]
+struct StoredSortedSet {
+    entries: Vec<ScoredMember>,
+}
+
+impl From<StoredSortedSet> for SortedSetValue {
+    fn from(stored: StoredSortedSet) -> Self {
+        let scores: HashMap<Vec<u8>, f64> = stored
+            .entries
+            .iter()
+            .map(|entry| (entry.member.to_vec(), entry.score))
+            .collect();
+
+        // Entries out of order or repeating a member are put right rather than searched wrongly
+        let ordered = scores.len() == stored.entries.len()
+            && stored
+                .entries
+                .windows(2)
+                .all(|pair| compare(&pair[0], pair[1].score, &pair[1].member).is_lt());
+        if ordered {
+            return Self {
+                entries: stored.entries,
+                scores,
+            };
+        }
+
+        let mut sorted_set = Self::default();
+        for (member, score) in scores {
+            sorted_set.insert(ByteBuf::from(member), score);
+        }
+        sorted_set
+    }
+}
+
+/// Order of an entry against a score and member, by score and then by member
+fn compare(entry: &ScoredMember, score: f64, member: &[u8]) -> std::cmp::Ordering {
+    entry
+        .score
+        .total_cmp(&score)
+        .then_with(|| entry.member.as_slice().cmp(member))
+}
+
+impl SortedSetValue {
+    /// Set the score of a member, returning whether it was not present before
+    /// Scores must be finite, which callers check
+    pub fn insert(&mut self, member: ByteBuf, score: f64) -> bool {
+        // -0.0 and 0.0 are the same score
+        let score = score + 0.0;
+        let added = self.remove(&member).is_none();
+        let position = self.position(&member, score);
+        self.scores.insert(member.to_vec(), score);
+        self.entries
+            .insert(position, ScoredMember { member, score });
+        added
+    }
+
+    /// Remove a member, returning its score
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        let rank = self.position(member, score);
+        self.entries.remove(rank);
+        Some(score)
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Position of the member, lowest score first
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.position(member, score))
+    }
+
+    /// Where a member with this score sits, or would be inserted
+    fn position(&self, member: &[u8], score: f64) -> usize {
+        self.entries
+            .partition_point(|entry| compare(entry, score, member).is_lt())
+    }
+
+    /// Entries scoring between `min` and `max` inclusive, lowest score first
+    pub fn range_by_score(&self, min: f64, max: f64) -> &[ScoredMember] {
+        let first = self.entries.partition_point(|entry| entry.score < min);
+        let end = self.entries.partition_point(|entry| entry.score <= max);
+        &self.entries[first..end.max(first)]
+    }
+
+    /// Every entry, lowest score first
+    pub fn entries(&self) -> &[ScoredMember] {
+        &self.entries
+    }
+
+    pub fn into_entries(self) -> Vec<ScoredMember> {
+        self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Samples in timestamp order, appended at the newest end (APPEND/RANGE)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeSeriesValue {
+    pub samples: VecDeque<Sample>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub timestamp: u64,
+    pub value: ByteBuf,
+}
+
+impl TimeSeriesValue {
+    /// Append a sample, which must be newer than every sample already held
+    pub fn append(&mut self, timestamp: u64, value: ByteBuf) -> Result<()> {
+        if let Some(newest) = self.samples.back()
+            && timestamp <= newest.timestamp
+        {
+            return Err(Error::PreconditionFailed(format!(
+                "timestamp {} is not after the newest sample at {}",
+                timestamp, newest.timestamp
+            )));
+        }
+        self.samples.push_back(Sample { timestamp, value });
+        Ok(())
+    }
+
+    /// Drop the samples more than `retention_ms` older than the newest one, then the oldest
+    /// beyond `max_samples`
+    pub fn retain(&mut self, retention_ms: Option<u64>, max_samples: Option<u64>) {
+        if let (Some(retention_ms), Some(newest)) = (retention_ms, self.samples.back()) {
+            let oldest_kept = newest.timestamp.saturating_sub(retention_ms);
+            let expired = self
+                .samples
+                .partition_point(|sample| sample.timestamp < oldest_kept);
+            self.samples.drain(..expired);
+        }
+        if let Some(max_samples) = max_samples {
+            let max_samples = usize::try_from(max_samples).unwrap_or(usize::MAX);
+            let excess = self.samples.len().saturating_sub(max_samples);
+            self.samples.drain(..excess);
+        }
+    }
+
+    /// Samples timestamped between `from` and `to` inclusive, oldest first
+    pub fn range(&self, from: u64, to: u64) -> impl Iterator<Item = &Sample> {
+        let first = self
+            .samples
+            .partition_point(|sample| sample.timestamp < from);
+        let end = self
+            .samples
+            .partition_point(|sample| sample.timestamp <= to);
+        self.samples.range(first..end.max(first))
+    }
+}
+
+impl StructuredValue {
+    /// Human-readable type name used in WRONGTYPE errors
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            StructuredValue::Hash(_) => "hash",
+            StructuredValue::List(_) => "list",
+            StructuredValue::Set(_) => "set",
+            StructuredValue::SortedSet(_) => "sorted set",
+            StructuredValue::HyperLogLog(_) => "hyperloglog",
+            StructuredValue::Bloom(_) => "bloom",
+            StructuredValue::TimeSeries(_) => "time series",
+        }
+    }
+
+    /// Encode into the bytes stored in the cache
+    pub fn encode(&self) -> Result<Bytes> {
+        let payload = serde_json::to_vec(self)
+            .map_err(|e| Error::Internal(format!("Failed to serialize structure: {}", e)))?;
+
+        let mut buf = BytesMut::with_capacity(STRUCTURE_MAGIC.len() + payload.len());
+        buf.put_slice(STRUCTURE_MAGIC);
+        buf.put_slice(&payload);
+        Ok(buf.freeze())
+    }
+
+    /// Decode bytes read from the cache, failing with WrongType for plain values
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let payload = bytes
+            .strip_prefix(STRUCTURE_MAGIC)
+            .ok_or_else(|| Error::WrongType("key holds a plain value".to_string()))?;
+
+        serde_json::from_slice(payload)
+            .map_err(|e| Error::Internal(format!("Failed to deserialize structure: {}", e)))
+    }
+
+    pub fn into_hash(self) -> Result<HashValue> {
+        match self {
+            StructuredValue::Hash(hash) => Ok(hash),
+            other => Err(other.wrong_type("hash")),
+        }
+    }
+
+    pub fn into_list(self) -> Result<ListValue> {
+        match self {
+            StructuredValue::List(list) => Ok(list),
+            other => Err(other.wrong_type("list")),
+        }
+    }
+
+    pub fn into_set(self) -> Result<SetValue> {
+        match self {
+            StructuredValue::Set(set) => Ok(set),
+            other => Err(other.wrong_type("set")),
+        }
+    }
+
+    pub fn into_sorted_set(self) -> Result<SortedSetValue> {
+        match self {
+            StructuredValue::SortedSet(sorted_set) => Ok(sorted_set),
+            other => Err(other.wrong_type("sorted set")),
+        }
+    }
+
+    pub fn into_hyperloglog(self) -> Result<HyperLogLogValue> {
+        match self {
+            StructuredValue::HyperLogLog(hll) => hll.validate().map(|_| hll),
+            other => Err(other.wrong_type("hyperloglog")),
+        }
+    }
+
+    pub fn into_bloom(self) -> Result<BloomValue> {
+        match self {
+            StructuredValue::Bloom(bloom) => bloom.validate().map(|_| bloom),
+            other => Err(other.wrong_type("bloom")),
+        }
+    }
+
+    pub fn into_time_series(self) -> Result<TimeSeriesValue> {
+        match self {
+            StructuredValue::TimeSeries(series) => Ok(series),
+            other => Err(other.wrong_type("time series")),
+        }
+    }
+
+    fn wrong_type(&self, expected: &str) -> Error {
+        Error::WrongType(format!(
+            "expected {} but key holds a {}",
+            expected,
+            self.type_name()
+        ))
+    }
+}
+
+/// Load and decode a structured value, returning None when the key does not exist
+#[allow(clippy::ptr_arg)] // cache stores are keyed by Vec<u8>
+pub(crate) async fn load_structure(
+    store: &Arc<dyn CacheStore<Vec<u8>, Bytes>>,
+    key: &Vec<u8>,
+) -> Result<Option<StructuredValue>> {
+    match store.get(key).await {
+        Ok(response) => StructuredValue::decode(&response.message).map(Some),
+        Err(Error::NotFound) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Data structure commands are only available on caches storing raw values
+pub(crate) fn ensure_raw_cache(cache_name: &str, config: &CacheConfig) -> Result<()> {
+    if config.value_type != ValueType::Raw {
+        return Err(Error::WrongType(format!(
+            "cache '{}' only accepts {:?} values",
+            cache_name, config.value_type
+        )));
+    }
+    if config.value_format != ValueFormat::Raw {
+        return Err(Error::WrongType(format!(
+            "cache '{}' only accepts {:?} values",
+            cache_name, config.value_format
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structure_roundtrip() {
+        let mut hash = HashValue::default();
+        hash.fields
+            .insert("name".to_string(), ByteBuf::from(b"carbon".to_vec()));
+
+        let encoded = StructuredValue::Hash(hash).encode().unwrap();
+        let decoded = StructuredValue::decode(&encoded)
+            .unwrap()
+            .into_hash()
+            .unwrap();
+
+        assert_eq!(decoded.fields["name"].as_ref(), b"carbon");
+    }
+
+    #[test]
+    fn test_plain_value_is_wrong_type() {
+        let result = StructuredValue::decode(b"plain value");
+        assert!(matches!(result, Err(Error::WrongType(_))));
+    }
+
+    #[test]
+    fn test_sorted_set_order() {
+        let mut scores = SortedSetValue::default();
+        assert!(scores.insert(ByteBuf::from(b"carol".to_vec()), 30.0));
+        assert!(scores.insert(ByteBuf::from(b"alice".to_vec()), 10.0));
+        assert!(scores.insert(ByteBuf::from(b"bob".to_vec()), 20.0));
+        assert!(scores.insert(ByteBuf::from(b"adam".to_vec()), 20.0));
+
+        // Updating a score moves the member instead of adding it again
+        assert!(!scores.insert(ByteBuf::from(b"alice".to_vec()), 25.0));
+        let members: Vec<&[u8]> = scores
+            .entries()
+            .iter()
+            .map(|entry| entry.member.as_slice())
+            .collect();
+        assert_eq!(members, [&b"adam"[..], b"bob", b"alice", b"carol"]);
+        assert_eq!(scores.rank(b"alice"), Some(2));
+        assert_eq!(scores.score(b"alice"), Some(25.0));
+
+        let window = scores.range_by_score(20.0, 25.0);
+        assert_eq!(window.len(), 3);
+        assert!(scores.range_by_score(26.0, 29.0).is_empty());
+        assert!(scores.range_by_score(30.0, 10.0).is_empty());
+
+        assert_eq!(scores.remove(b"bob"), Some(20.0));
+        assert_eq!(scores.remove(b"bob"), None);
+        assert_eq!(scores.rank(b"carol"), Some(2));
+
+        // Decoding rebuilds the scores by member
+        let encoded = StructuredValue::SortedSet(scores).encode().unwrap();
+        let decoded = StructuredValue::decode(&encoded)
+            .unwrap()
+            .into_sorted_set()
+            .unwrap();
+        assert_eq!(decoded.score(b"carol"), Some(30.0));
+        assert_eq!(decoded.rank(b"alice"), Some(1));
+    }
+
+    #[test]
+    fn test_sorted_set_out_of_order_entries_are_sorted() {
+        let stored = br#"{"type":"sorted_set","entries":[
+            {"member":[98],"score":2.0},{"member":[97],"score":1.0},{"member":[98],"score":3.0}
+        ]}"#;
+        let mut encoded = STRUCTURE_MAGIC.to_vec();
+        encoded.extend_from_slice(stored);
+
+        let decoded = StructuredValue::decode(&encoded)
+            .unwrap()
+            .into_sorted_set()
+            .unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded.rank(b"a"), Some(0));
+        assert_eq!(decoded.rank(b"b"), Some(1));
+    }
+
+    #[test]
+    fn test_time_series_retention() {
+        let mut series = TimeSeriesValue::default();
+        for timestamp in [1_000, 2_000, 3_000, 4_000] {
+            let value = ByteBuf::from(timestamp.to_string().into_bytes());
+            series.append(timestamp, value).unwrap();
+        }
+
+        // Samples only ever go on the newest end
+        assert!(matches!(
+            series.append(4_000, ByteBuf::from(b"late".to_vec())),
+            Err(Error::PreconditionFailed(_))
+        ));
+
+        let window: Vec<u64> = series
+            .range(1_500, 3_000)
+            .map(|sample| sample.timestamp)
+            .collect();
+        assert_eq!(window, [2_000, 3_000]);
+        assert_eq!(series.range(3_000, 1_500).count(), 0);
+
+        series.retain(Some(2_000), None);
+        assert_eq!(series.samples.len(), 3);
+        series.retain(None, Some(1));
+        assert_eq!(series.samples.front().unwrap().timestamp, 4_000);
+    }
+
+    #[test]
+    fn test_mismatched_structure_is_wrong_type() {
+        let mut set = SetValue::default();
+        set.members.insert(ByteBuf::from(b"a".to_vec()));
+
+        let encoded = StructuredValue::Set(set).encode().unwrap();
+        let result = StructuredValue::decode(&encoded).unwrap().into_list();
+        assert!(matches!(result, Err(Error::WrongType(_))));
+    }
+}
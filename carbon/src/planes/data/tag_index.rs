@@ -0,0 +1,120 @@
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// Secondary map from tags to the keys carrying them, per cache
+/// Written under the key lock, keys that expire or are evicted leave it through entry_evicted
+pub(crate) struct TagIndex<K> {
+    keys_by_tag: DashMap<(String, String), HashSet<K>>,
+    tags_by_key: DashMap<(String, K), Vec<String>>,
+}
+
+impl<K: Hash + Eq + Clone> TagIndex<K> {
+    pub(crate) fn new() -> Self {
+        Self {
+            keys_by_tag: DashMap::new(),
+            tags_by_key: DashMap::new(),
+        }
+    }
+
+    /// Replace the tags on `key`, an empty list removes it from the index
+    pub(crate) fn set_tags(&self, cache_name: &str, key: &K, tags: Vec<String>) {
+        self.remove_key(cache_name, key);
+        if tags.is_empty() {
+            return;
+        }
+
+        for tag in &tags {
+            self.keys_by_tag
+                .entry((cache_name.to_string(), tag.clone()))
+                .or_default()
+                .insert(key.clone());
+        }
+        self.tags_by_key
+            .insert((cache_name.to_string(), key.clone()), tags);
+    }
+
+    pub(crate) fn remove_key(&self, cache_name: &str, key: &K) {
+        let Some((_, tags)) = self
+            .tags_by_key
+            .remove(&(cache_name.to_string(), key.clone()))
+        else {
+            return;
+        };
+
+        for tag in tags {
+            let tag_key = (cache_name.to_string(), tag);
+            if let Some(mut keys) = self.keys_by_tag.get_mut(&tag_key) {
+                keys.remove(key);
+            }
+            self.keys_by_tag
+                .remove_if(&tag_key, |_, keys| keys.is_empty());
+        }
+    }
+
+    /// Keys currently carrying `tag`
+    pub(crate) fn keys(&self, cache_name: &str, tag: &str) -> Vec<K> {
+        self.keys_by_tag
+            .get(&(cache_name.to_string(), tag.to_string()))
+            .map(|keys| keys.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Forget every tag of a dropped cache
+    pub(crate) fn remove_cache(&self, cache_name: &str) {
+        self.keys_by_tag.retain(|(name, _), _| name != cache_name);
+        self.tags_by_key.retain(|(name, _), _| name != cache_name);
+    }
+
+    pub(crate) fn rename_cache(&self, from: &str, to: &str) {
+        move_cache_entries(&self.keys_by_tag, from, to);
+        move_cache_entries(&self.tags_by_key, from, to);
+    }
+}
+
+/// File the entries of cache `from` under `to`, in a map keyed by cache name and key
+pub(crate) fn move_cache_entries<T, V>(map: &DashMap<(String, T), V>, from: &str, to: &str)
+where
+    T: Hash + Eq + Clone,
+{
+    let keys: Vec<T> = map
+        .iter()
+        .filter(|entry| entry.key().0 == from)
+        .map(|entry| entry.key().1.clone())
+        .collect();
+    for key in keys {
+        if let Some((_, value)) = map.remove(&(from.to_string(), key.clone())) {
+            map.insert((to.to_string(), key), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_tags_replaces_previous_tags() {
+        let index = TagIndex::new();
+        index.set_tags(
+            "users",
+            &"alice",
+            vec!["team:a".to_string(), "admin".to_string()],
+        );
+        index.set_tags("users", &"bob", vec!["team:a".to_string()]);
+
+        assert_eq!(index.keys("users", "team:a").len(), 2);
+        assert_eq!(index.keys("users", "admin"), vec!["alice"]);
+
+        index.set_tags("users", &"alice", vec!["team:b".to_string()]);
+        assert_eq!(index.keys("users", "team:a"), vec!["bob"]);
+        assert!(index.keys("users", "admin").is_empty());
+        assert!(index.keys("orders", "team:b").is_empty());
+
+        index.remove_key("users", &"bob");
+        assert!(index.keys("users", "team:a").is_empty());
+
+        index.remove_cache("users");
+        assert!(index.keys("users", "team:b").is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+use crate::domain::response::PutResponse;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::TagOperations;
+use async_trait::async_trait;
+use bytes::Bytes;
+use shared::Result;
+
+// Tag commands for the Vec<u8>/Bytes service used by the servers
+// Invalidation deletes each key under its lock, so events and secondary indexes stay in step
+#[async_trait]
+impl TagOperations<Vec<u8>, Bytes> for CacheOperationsService<Vec<u8>, Bytes> {
+    async fn put_tagged(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        value: Bytes,
+        tags: Vec<String>,
+    ) -> Result<PutResponse> {
+        let cache_name = &*self.resolve(cache_name);
+        self.get_cache_for_write(cache_name).await?;
+        let _guard = self.lock_key(cache_name, &key).await;
+        self.put_tagged_locked(cache_name, key, value, tags).await
+    }
+
+    async fn invalidate_tag(&self, cache_name: &str, tag: &str) -> Result<usize> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("INVALIDATE_TAG", cache_name, tag.as_bytes());
+        // One operation against the ops/sec limits, however many keys carry the tag
+        self.get_cache_for_write(cache_name).await?;
+
+        let mut invalidated = 0;
+        for key in self.tag_index().keys(cache_name, tag) {
+            let _guard = self.lock_key(cache_name, &key).await;
+            if self.delete_locked(cache_name, &key).await?.deleted {
+                invalidated += 1;
+            }
+        }
+        Ok(invalidated)
+    }
+}
+
+impl CacheOperationsService<Vec<u8>, Bytes> {
+    /// Tagged PUT for callers already holding the key lock
+    pub(crate) async fn put_tagged_locked(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        value: Bytes,
+        tags: Vec<String>,
+    ) -> Result<PutResponse> {
+        let result = self.put_locked(cache_name, key.clone(), value).await?;
+        // A value turned away by admission leaves the key as it was, tags included
+        if result.admitted {
+            self.tag_index().set_tags(cache_name, &key, tags);
+        }
+        Ok(result)
+    }
+}
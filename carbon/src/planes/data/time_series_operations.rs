@@ -0,0 +1,97 @@
+use crate::domain::DEFAULT_TIMESERIES_MAX_SAMPLES;
+use crate::domain::response::GetResponse;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::TimeSeriesOperations;
+use crate::planes::data::structured::{
+    StructuredValue, TimeSeriesValue, ensure_raw_cache, load_structure,
+};
+use crate::ports::CacheStore;
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde_bytes::ByteBuf;
+use shared::{Error, Result};
+use std::sync::Arc;
+
+// Time series commands for the Vec<u8>/Bytes service used by the servers
+#[async_trait]
+impl TimeSeriesOperations<Vec<u8>, Bytes> for CacheOperationsService<Vec<u8>, Bytes> {
+    async fn ts_append(
+        &self,
+        cache_name: &str,
+        key: Vec<u8>,
+        timestamp: u64,
+        value: Bytes,
+    ) -> Result<usize> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("APPEND", cache_name, &key);
+        let (cache_store, config) = self.get_cache_for_write(cache_name).await?;
+        ensure_raw_cache(cache_name, &config)?;
+
+        let _guard = self.lock_key(cache_name, &key).await;
+
+        let mut series = load_time_series(&cache_store, &key)
+            .await?
+            .unwrap_or_default();
+        series.append(timestamp, ByteBuf::from(value.to_vec()))?;
+        // Retention is enforced as samples arrive, so reads only ever see the trimmed series
+        // Caches without any are capped too, as every append rewrites the whole series
+        let max_samples = match (
+            config.timeseries_retention_ms,
+            config.timeseries_max_samples,
+        ) {
+            (None, None) => Some(DEFAULT_TIMESERIES_MAX_SAMPLES),
+            (_, max_samples) => max_samples,
+        };
+        series.retain(config.timeseries_retention_ms, max_samples);
+        let len = series.samples.len();
+
+        let encoded = StructuredValue::TimeSeries(series).encode()?;
+        self.write_entry(cache_name, &cache_store, key, encoded)
+            .await?;
+
+        Ok(len)
+    }
+
+    async fn ts_range(
+        &self,
+        cache_name: &str,
+        key: &Vec<u8>,
+        from: u64,
+        to: u64,
+        limit: Option<usize>,
+    ) -> Result<GetResponse<Vec<(u64, Bytes)>>> {
+        let cache_name = &*self.resolve(cache_name);
+        let _timer = self.time_operation("RANGE", cache_name, key);
+        if from > to {
+            return Err(Error::InvalidValue(format!(
+                "range start {} is after its end {}",
+                from, to
+            )));
+        }
+        let (cache_store, _) = self.get_cache(cache_name).await?;
+
+        let samples = load_time_series(&cache_store, key)
+            .await?
+            .map(|series| {
+                series
+                    .range(from, to)
+                    .take(limit.unwrap_or(usize::MAX))
+                    .map(|sample| (sample.timestamp, Bytes::from(sample.value.to_vec())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(GetResponse::new(true, samples))
+    }
+}
+
+#[allow(clippy::ptr_arg)] // cache stores are keyed by Vec<u8>
+async fn load_time_series(
+    store: &Arc<dyn CacheStore<Vec<u8>, Bytes>>,
+    key: &Vec<u8>,
+) -> Result<Option<TimeSeriesValue>> {
+    load_structure(store, key)
+        .await?
+        .map(StructuredValue::into_time_series)
+        .transpose()
+}
@@ -0,0 +1,130 @@
+use crate::domain::MIN_REAP_INTERVAL_MS;
+use crate::encoding::ToBytes;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use shared::{Error, Result};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// How often the reaper looks for caches that are due, no interval is shorter
+const REAPER_TICK: Duration = Duration::from_millis(MIN_REAP_INTERVAL_MS);
+
+// Background removal of expired entries
+// Moka only drops expired entries when they are next touched, so without a reaper
+// keys_estimate and size_estimate keep counting them
+impl<K, V> CacheOperationsService<K, V>
+where
+    K: ToBytes + Debug + Hash + Eq + Send + Sync + Clone + 'static,
+    V: ToBytes + Debug + Send + Sync + Clone + 'static,
+{
+    /// Remove the expired entries of a cache now, returning how many were removed
+    /// Each removal is broadcast as an Expired event; not subject to ops/sec limits
+    /// Keys written again since are left alone, checked under the key lock like sweep_evicted
+    pub async fn reap_expired(&self, cache_name: &str) -> Result<usize> {
+        let (cache_store, _) = self
+            .cache_manager()
+            .get_cache(cache_name)
+            .await
+            .ok_or_else(|| Error::CacheNotFound(cache_name.to_string()))?;
+
+        let reaped = cache_store.reap_expired().await?;
+        for key in &reaped {
+            let _guard = self.lock_key(cache_name, key).await;
+            if !cache_store.exists(key).await?.exists {
+                self.entry_expired(cache_name, key);
+            }
+        }
+        self.cache_manager()
+            .record_reaped(cache_name, reaped.len() as u64);
+
+        Ok(reaped.len())
+    }
+
+    /// Forget the entries a cache's store evicted or let expire by itself, returning how many
+    /// Keys written again since are left alone, checked under the key lock
+    pub async fn sweep_evicted(&self, cache_name: &str) -> Result<usize> {
+        let (cache_store, _) = self
+            .cache_manager()
+            .get_cache(cache_name)
+            .await
+            .ok_or_else(|| Error::CacheNotFound(cache_name.to_string()))?;
+
+        let mut swept = 0;
+        for key in cache_store.take_evicted().await? {
+            let _guard = self.lock_key(cache_name, &key).await;
+            if !cache_store.exists(&key).await?.exists {
+                self.entry_evicted(cache_name, &key);
+                swept += 1;
+            }
+        }
+        Ok(swept)
+    }
+
+    /// Purge dropped caches whose retention window has passed, returning how many were purged
+    pub async fn purge_dropped(&self) -> usize {
+        let purged = self.cache_manager().purge_expired().await;
+        for cache_name in &purged {
+            tracing::info!("Purged dropped cache '{}'", cache_name);
+            self.cache_purged(cache_name);
+        }
+        purged.len()
+    }
+
+    /// Reap every cache created with reap_interval_ms at its interval, until the task is aborted
+    /// Also purges dropped caches once their retention window has passed, and sweeps what every
+    /// cache evicted on each tick
+    /// Start one per process, on the service that broadcasts events
+    pub fn spawn_ttl_reaper(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut next_due: HashMap<String, Instant> = HashMap::new();
+            let mut ticker = tokio::time::interval(REAPER_TICK);
+
+            loop {
+                ticker.tick().await;
+                self.purge_dropped().await;
+
+                let now = Instant::now();
+                let caches = self.cache_manager().reapable_caches();
+                next_due.retain(|name, _| caches.iter().any(|(cache_name, _)| cache_name == name));
+
+                for (cache_name, interval) in caches {
+                    let due = next_due
+                        .entry(cache_name.clone())
+                        .or_insert_with(|| now + interval);
+                    if *due > now {
+                        continue;
+                    }
+                    *due = now + interval;
+
+                    match self.reap_expired(&cache_name).await {
+                        Ok(0) => {}
+                        Ok(reaped) => {
+                            tracing::debug!(
+                                "Reaped {} expired entries from '{}'",
+                                reaped,
+                                cache_name
+                            )
+                        }
+                        // Dropped since the list was taken
+                        Err(Error::CacheNotFound(_)) => {}
+                        Err(e) => tracing::warn!("Failed to reap cache '{}': {}", cache_name, e),
+                    }
+                }
+
+                for cache_name in self.cache_manager().cache_names() {
+                    match self.sweep_evicted(&cache_name).await {
+                        Ok(_) | Err(Error::CacheNotFound(_)) => {}
+                        Err(e) => tracing::warn!(
+                            "Failed to sweep evicted entries of '{}': {}",
+                            cache_name,
+                            e
+                        ),
+                    }
+                }
+            }
+        })
+    }
+}
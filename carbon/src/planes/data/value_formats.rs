@@ -0,0 +1,39 @@
+use crate::domain::ValueFormat;
+use crate::encoding::transcode;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use bytes::Bytes;
+use shared::{Error, Result};
+
+// Conversions between the formats clients speak and the one a cache stores, for the
+// Vec<u8>/Bytes service used by the servers
+impl CacheOperationsService<Vec<u8>, Bytes> {
+    /// Turn a value a client sent in `format` into the format `cache_name` stores
+    /// Values already in the stored format are passed on as they are, the write checks them
+    pub async fn to_canonical(
+        &self,
+        cache_name: &str,
+        value: Bytes,
+        format: ValueFormat,
+    ) -> Result<Bytes> {
+        transcode(value, format, self.value_format(cache_name).await?)
+    }
+
+    /// Turn a value read from `cache_name` into the `format` its client asked for
+    pub async fn from_canonical(
+        &self,
+        cache_name: &str,
+        value: Bytes,
+        format: ValueFormat,
+    ) -> Result<Bytes> {
+        transcode(value, self.value_format(cache_name).await?, format)
+    }
+
+    /// Format `cache_name` stores values in, looked up without counting as an operation
+    pub async fn value_format(&self, cache_name: &str) -> Result<ValueFormat> {
+        self.cache_manager()
+            .get_cache(cache_name)
+            .await
+            .map(|(_, config)| config.value_format)
+            .ok_or_else(|| Error::CacheNotFound(cache_name.to_string()))
+    }
+}
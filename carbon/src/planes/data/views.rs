@@ -0,0 +1,181 @@
+use crate::domain::{MaterializedView, ValueFormat, ViewTransform};
+use crate::encoding::{decode_document, encode_document};
+use crate::events::CacheItemEvent;
+use crate::planes::data::cache_operations::CacheOperationsService;
+use crate::planes::data::operation::CacheOperations;
+use bytes::Bytes;
+use shared::{Error, Result};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+// Materialized views, written through the same operations as any client so their caches
+// keep their events, quotas and indexes
+impl CacheOperationsService<Vec<u8>, Bytes> {
+    /// Keep the views of the CacheManager in step with the events of their sources, until
+    /// the channel closes
+    /// Subscribe before the server starts writing so no event is missed, events missed
+    /// later rebuild every view
+    pub fn spawn_view_maintenance(
+        self: Arc<Self>,
+        mut receiver: broadcast::Receiver<CacheItemEvent>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => self.maintain_views(&event).await,
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("View maintenance fell behind by {} events", missed);
+                        for view in self.cache_manager().views() {
+                            if let Err(e) = self.rebuild_view(&view).await {
+                                tracing::warn!("Failed to rebuild view '{}': {}", view.cache, e);
+                            }
+                        }
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        })
+    }
+
+    /// Clear the view and derive it again from every entry of its source, returning how
+    /// many entries the view holds afterwards
+    pub async fn rebuild_view(&self, view: &MaterializedView) -> Result<u64> {
+        self.clear(&view.cache).await?;
+
+        let mut written = 0;
+        for (key, value) in self.snapshot(&view.source).await? {
+            if let Some(derived) = self.view_value(view, &key, &value).await? {
+                self.put(&view.cache, key, Bytes::from(derived)).await?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    async fn maintain_views(&self, event: &CacheItemEvent) {
+        for view in self.cache_manager().views_of(event.cache_name()) {
+            let result = match event {
+                CacheItemEvent::Added(_) | CacheItemEvent::Updated(_) => {
+                    self.refresh_view_entry(&view, event.key()).await
+                }
+                CacheItemEvent::Deleted(_) | CacheItemEvent::Expired(_) => self
+                    .delete(&view.cache, &event.key().to_vec())
+                    .await
+                    .map(|_| ()),
+                // Bulk loads and clones send no event per entry
+                CacheItemEvent::BulkLoaded(_) => self.rebuild_view(&view).await.map(|_| ()),
+                CacheItemEvent::CloneProgress(progress) if progress.done => {
+                    self.rebuild_view(&view).await.map(|_| ())
+                }
+                CacheItemEvent::CloneProgress(_) | CacheItemEvent::Renamed(_) => Ok(()),
+            };
+            if let Err(e) = result {
+                tracing::warn!(
+                    "Failed to update view '{}' from '{}': {}",
+                    view.cache,
+                    view.source,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Derive the view entry under `key` from the source entry, which is read again as events
+    /// may leave values out
+    async fn refresh_view_entry(&self, view: &MaterializedView, key: &[u8]) -> Result<()> {
+        let key = key.to_vec();
+        let derived = match self.get(&view.source, &key).await {
+            Ok(response) => self.view_value(view, &key, &response.message).await?,
+            Err(Error::NotFound) => None,
+            Err(e) => return Err(e),
+        };
+        match derived {
+            Some(derived) => self
+                .put(&view.cache, key, Bytes::from(derived))
+                .await
+                .map(|_| ()),
+            None => self.delete(&view.cache, &key).await.map(|_| ()),
+        }
+    }
+
+    async fn view_value(
+        &self,
+        view: &MaterializedView,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<Vec<u8>>> {
+        match &view.transform {
+            ViewTransform::Projection { fields } => {
+                let from = self.value_format(&view.source).await?;
+                let to = self.value_format(&view.cache).await?;
+                project(fields, value, from)
+                    .map(|document| encode_document(&document, document_format(to)))
+                    .transpose()
+            }
+            ViewTransform::Registered { name } => self
+                .cache_manager()
+                .view_transformation(name)
+                .ok_or_else(|| {
+                    Error::InvalidValue(format!(
+                        "no view transformation is registered as '{}'",
+                        name
+                    ))
+                })?
+                .apply(key, value),
+        }
+    }
+}
+
+/// Raw values are taken to be JSON
+fn document_format(format: ValueFormat) -> ValueFormat {
+    match format {
+        ValueFormat::Raw => ValueFormat::Json,
+        format => format,
+    }
+}
+
+/// Object of the `fields` found in the document, None when the value is not a document
+fn project(
+    fields: &BTreeMap<String, String>,
+    value: &[u8],
+    format: ValueFormat,
+) -> Option<serde_json::Value> {
+    let document = decode_document(value, document_format(format)).ok()?;
+    let projected = fields
+        .iter()
+        .filter_map(|(name, pointer)| Some((name.clone(), document.pointer(pointer)?.clone())))
+        .collect();
+    Some(serde_json::Value::Object(projected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_projection() {
+        let fields = BTreeMap::from([
+            ("name".to_string(), "/name".to_string()),
+            ("city".to_string(), "/address/city".to_string()),
+            ("phone".to_string(), "/phone".to_string()),
+        ]);
+        let document = json!({"name": "Ada", "address": {"city": "London"}, "age": 36});
+
+        let projected = project(
+            &fields,
+            &serde_json::to_vec(&document).unwrap(),
+            ValueFormat::Raw,
+        );
+        assert_eq!(projected, Some(json!({"name": "Ada", "city": "London"})));
+
+        let packed = rmp_serde::to_vec(&document).unwrap();
+        assert_eq!(
+            project(&fields, &packed, ValueFormat::MessagePack),
+            projected
+        );
+        assert_eq!(project(&fields, b"not json", ValueFormat::Raw), None);
+    }
+}
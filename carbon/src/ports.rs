@@ -1,11 +1,12 @@
 #![deny(clippy::all)]
 
-use crate::domain::CacheConfig;
 use crate::domain::response::ExistsResponse;
 use crate::domain::response::{DeleteResponse, GetResponse, PutResponse};
+use crate::domain::{BulkEntry, CacheConfig, EntryMetadata, EvictionAlgorithm, ScheduledAction};
 use async_trait::async_trait;
-use shared::Result;
+use shared::{Error, Result};
 use std::sync::Arc;
+use std::time::Duration;
 
 // Ports are the pluggable extension points for underlying cache implementations
 
@@ -20,7 +21,153 @@ pub trait StorageFactory<K, V>: Send + Sync + 'static {
 #[async_trait]
 pub trait CacheStore<K, V>: Send + Sync + 'static {
     async fn exists(&self, key: &K) -> Result<ExistsResponse>;
+
+    /// Insert or replace an entry, `created` is false when the key already held a live value
+    /// The check is part of the write, so concurrent puts of one key see exactly one creation
     async fn put(&self, key: K, val: V) -> Result<PutResponse>;
     async fn get(&self, key: &K) -> Result<GetResponse<V>>;
     async fn delete(&self, key: &K) -> Result<DeleteResponse>;
+
+    /// Remove an entry and return its value in one step, NotFound when the key is missing
+    async fn get_and_delete(&self, key: &K) -> Result<GetResponse<V>>;
+
+    /// Write a batch of entries, returning for each whether it was admitted
+    /// Stores without a batched path write them one at a time and have no per-entry TTL
+    async fn put_batch(&self, entries: Vec<BulkEntry<K, V>>) -> Result<Vec<bool>>
+    where
+        K: Send + 'static,
+        V: Send + 'static,
+    {
+        if entries.iter().any(|entry| entry.ttl.is_some()) {
+            return Err(Error::InvalidValue(
+                "cache backend does not support per-entry expiry".to_string(),
+            ));
+        }
+
+        let mut admitted = Vec::with_capacity(entries.len());
+        for entry in entries {
+            admitted.push(self.put(entry.key, entry.value).await?.admitted);
+        }
+        Ok(admitted)
+    }
+
+    /// Read an entry and give it a new TTL in one step, None keeps it until evicted
+    async fn get_and_expire(&self, _key: &K, _ttl: Option<Duration>) -> Result<GetResponse<V>> {
+        Err(Error::InvalidValue(
+            "cache backend does not support per-entry expiry".to_string(),
+        ))
+    }
+
+    /// Change the capacity of the live store
+    /// Returns false when the backend can only take a new capacity by being rebuilt
+    fn resize(&self, _capacity: u64) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Algorithm the store evicts with, None when it is not known
+    fn eviction_policy(&self) -> Option<EvictionAlgorithm> {
+        None
+    }
+
+    /// Writes turned away by the store's admission policy, None when it admits everything
+    fn admission_rejected(&self) -> Option<u64> {
+        None
+    }
+
+    /// Access statistics of a live entry without counting as a read
+    /// None when the store does not track them, NotFound when the key is missing
+    async fn metadata(&self, _key: &K) -> Result<Option<EntryMetadata>> {
+        Ok(None)
+    }
+
+    /// Remove entries whose TTL has passed and return their keys
+    /// Only called for caches with a reap interval, stores that expire eagerly return nothing
+    async fn reap_expired(&self) -> Result<Vec<K>> {
+        Ok(Vec::new())
+    }
+
+    /// Keys the store dropped on its own since the last call, evicted to make room or expired
+    /// while no reaper collects them, so what is kept beside the store can forget them
+    /// Stores that evict without telling, or leave it to another server, return nothing
+    async fn take_evicted(&self) -> Result<Vec<K>> {
+        Ok(Vec::new())
+    }
+
+    /// Every live entry, for copying the cache
+    /// Stores that cannot enumerate their entries refuse
+    async fn entries(&self) -> Result<Vec<(K, V)>> {
+        Err(Error::InvalidValue(
+            "cache backend does not support listing its entries".to_string(),
+        ))
+    }
+
+    /// Keep a live entry out of reach of capacity-based eviction until it is unpinned or deleted
+    /// Returns false when it was already pinned, NotFound when the key is missing
+    async fn pin(&self, _key: &K) -> Result<bool> {
+        Err(Error::InvalidValue(
+            "cache does not allow pinning entries, it needs max_pinned_bytes".to_string(),
+        ))
+    }
+
+    /// Let a pinned entry be evicted again, returns false when it was not pinned
+    async fn unpin(&self, _key: &K) -> Result<bool> {
+        Err(Error::InvalidValue(
+            "cache does not allow pinning entries, it needs max_pinned_bytes".to_string(),
+        ))
+    }
+
+    /// Summed size of the keys and values pinned, None when the store does not pin entries
+    fn pinned_bytes(&self) -> Option<u64> {
+        None
+    }
+
+    /// Check the backend can be reached, stores held in process always can
+    async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Port notified of writes to JSON-typed caches (e.g., carbon-query secondary indexes)
+/// Called after the store has been updated, so implementations only mirror committed state
+pub trait IndexMaintainer: Send + Sync + 'static {
+    /// A JSON document was stored under `key`
+    fn on_put(&self, cache_name: &str, key: &[u8], document: &serde_json::Value);
+
+    /// `key` was removed from the cache
+    fn on_delete(&self, cache_name: &str, key: &[u8]);
+
+    /// The cache was removed for good, with all its entries
+    fn on_drop(&self, _cache_name: &str) {}
+
+    /// The cache was renamed, its entries unchanged
+    /// Maintainers that cannot move indexes forget them as on a drop
+    fn on_rename(&self, from: &str, _to: &str) {
+        self.on_drop(from);
+    }
+}
+
+/// Port for transformations materialized views are computed with, registered by name on the
+/// CacheManager
+pub trait ViewTransformation: Send + Sync + 'static {
+    /// The view value for a source entry, None when the entry has no counterpart in the view
+    fn apply(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>>;
+}
+
+/// Port for carrying out scheduled actions, implemented by the frontend holding the clients
+/// they need
+#[async_trait]
+pub trait ScheduledActionRunner: Send + Sync + 'static {
+    /// Run `action` on `cache_name`, returning a summary of what it did
+    async fn run(&self, cache_name: &str, action: &ScheduledAction) -> Result<String>;
+}
+
+/// Port for message brokers cache events are forwarded to (e.g., Kafka, NATS)
+#[async_trait]
+pub trait EventPublisher: Send + Sync + 'static {
+    /// Broker name reported in delivery metrics
+    fn name(&self) -> &'static str;
+
+    /// Publish `payload` to `topic` and wait for the broker to accept it
+    /// Brokers that partition by key keep the events of one `key` in order
+    async fn publish(&self, topic: &str, key: &[u8], payload: Vec<u8>) -> Result<()>;
 }
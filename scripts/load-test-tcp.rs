@@ -144,9 +144,11 @@ impl Response {
             }
             RESP_NOT_FOUND => Ok(Response::NotFound),
             RESP_ERROR => {
-                if buf.remaining() < 4 {
-                    return Err("Invalid ERROR response: missing length".to_string());
+                if buf.remaining() < 6 {
+                    return Err("Invalid ERROR response: missing code or length".to_string());
                 }
+                // Error code, the message is enough for this tool
+                buf.advance(2);
                 let msg_len = buf.get_u32() as usize;
                 if buf.remaining() < msg_len {
                     return Err("Invalid ERROR response: message too short".to_string());
@@ -1,5 +1,7 @@
 use carbon::auth::Permission;
-use serde::Deserialize;
+use carbon::domain::{EventPolicy, ScheduledAction};
+use serde::{Deserialize, Serialize};
+use shared::config::{CacheDefaults, DeclaredCache};
 use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Deserialize)]
@@ -9,6 +11,22 @@ pub struct CreateUserRequest {
     pub role_ids: Vec<String>,
 }
 
+/// A service account signs in with API keys only, so it has no password
+#[derive(Debug, Deserialize)]
+pub struct CreateServiceAccountRequest {
+    pub username: String,
+    pub role_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Caches the key reaches, all of them when empty
+    #[serde(default)]
+    pub caches: Vec<String>,
+    pub permissions: HashSet<Permission>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AssignRolesRequest {
     pub role_ids: Vec<String>,
@@ -25,6 +43,12 @@ pub struct ResetPasswordRequest {
     pub new_password: String,
 }
 
+/// Whether a user, or the members of a role, must sign in with a second factor
+#[derive(Debug, Deserialize)]
+pub struct SetMfaRequiredRequest {
+    pub required: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateRoleRequest {
     pub name: String,
@@ -37,13 +61,210 @@ pub struct UpdateRoleRequest {
 }
 // === Cache Operation Models ===
 
+/// How `value` is carried in JSON bodies, base64 for values that are not UTF-8 text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueEncoding {
+    #[default]
+    Utf8,
+    Base64,
+}
+
+impl ValueEncoding {
+    pub fn is_utf8(&self) -> bool {
+        *self == ValueEncoding::Utf8
+    }
+}
+
 #[derive(Deserialize)]
 pub struct PutRequest {
     pub value: String,
+    #[serde(default)]
+    pub encoding: ValueEncoding,
+    /// Groups the entry for POST /cache/{name}/invalidate-by-tag, replacing earlier tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// One line of the NDJSON body of POST /cache/{name}/bulkload, and of scheduled snapshots
+#[derive(Serialize, Deserialize)]
+pub struct BulkLoadRecord {
+    pub key: String,
+    pub value: String,
+    #[serde(default)]
+    pub encoding: ValueEncoding,
+    /// Expiry of this entry, the cache's TTL applies when omitted
+    #[serde(default)]
+    pub ttl_ms: Option<u64>,
+}
+
+/// Encoding of the value in a JSON GET response, by default base64 only when it is not UTF-8
+#[derive(Deserialize)]
+pub struct GetValueQuery {
+    #[serde(default)]
+    pub encoding: Option<ValueEncoding>,
+}
+
+#[derive(Deserialize)]
+pub struct InvalidateByTagRequest {
+    pub tag: String,
+}
+
+/// New TTL for POST /cache/{name}/{key}/getex, omitted to keep the entry until evicted
+#[derive(Deserialize)]
+pub struct GetExRequest {
+    #[serde(default)]
+    pub ttl_ms: Option<u64>,
+}
+
+/// Name of the clone, with settings of the source cache to change as PATCH accepts them
+#[derive(Deserialize)]
+pub struct CloneCacheRequest {
+    pub name: String,
+    #[serde(flatten)]
+    pub overrides: UpdateCacheRequest,
+}
+
+/// New name of a cache
+#[derive(Debug, Deserialize)]
+pub struct RenameCacheRequest {
+    pub name: String,
+}
+
+/// Run `action` on `cache` whenever `cron` matches
+#[derive(Debug, Deserialize)]
+pub struct CreateScheduleRequest {
+    pub cache: String,
+    pub cron: String,
+    pub action: ScheduledAction,
+}
+
+/// Cache an alias should point to
+#[derive(Debug, Deserialize)]
+pub struct SetAliasRequest {
+    pub cache: String,
+}
+
+/// Turn read-only maintenance mode on or off, for the whole server unless `cache` is named
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceRequest {
+    pub read_only: bool,
+    #[serde(default)]
+    pub cache: Option<String>,
+}
+
+/// Combine a set with other sets in the same cache (comma-separated keys)
+#[derive(Deserialize)]
+pub struct SetMembersQuery {
+    #[serde(default)]
+    pub intersect: Option<String>,
+    #[serde(default)]
+    pub union: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ScoreRequest {
+    pub score: f64,
+}
+
+/// Rank members from the highest score down with `reverse`
+#[derive(Deserialize)]
+pub struct MemberScoreQuery {
+    #[serde(default)]
+    pub reverse: bool,
+}
+
+/// Read a sorted set by rank (`start`, `stop`, `reverse`) or by score (`min`, `max`, `limit`)
+#[derive(Deserialize)]
+pub struct ScoreRangeQuery {
+    #[serde(default)]
+    pub start: Option<i64>,
+    #[serde(default)]
+    pub stop: Option<i64>,
+    #[serde(default)]
+    pub reverse: bool,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// A time series sample, newer than every sample the series holds
+#[derive(Deserialize)]
+pub struct AppendSampleRequest {
+    pub timestamp: u64,
+    pub value: String,
+}
+
+/// Read the samples timestamped from `from` to `to` inclusive, at most `limit` of them
+#[derive(Deserialize)]
+pub struct SampleRangeQuery {
+    #[serde(default)]
+    pub from: Option<u64>,
+    #[serde(default)]
+    pub to: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct HyperLogLogAddRequest {
+    pub items: Vec<String>,
+}
+
+/// Count the distinct items of other HyperLogLogs in the same cache too (comma-separated keys)
+#[derive(Deserialize)]
+pub struct CardinalityQuery {
+    #[serde(default)]
+    pub union: Option<String>,
+}
+
+/// Size a Bloom filter for `capacity` items at a false positive rate of `error_rate`
+#[derive(Deserialize)]
+pub struct BloomReserveRequest {
+    pub error_rate: f64,
+    pub capacity: u64,
+}
+
+#[derive(Deserialize)]
+pub struct LockRequest {
+    pub ttl_ms: u64,
+}
+
+#[derive(Deserialize)]
+pub struct UnlockQuery {
+    pub token: u64,
+}
+
+/// Read the change log after `since`, 0 to start from the oldest change kept
+#[derive(Deserialize)]
+pub struct ChangesQuery {
+    #[serde(default)]
+    pub since: u64,
+    #[serde(default)]
+    pub limit: Option<usize>,
 }
 
 // === Admin Operation Models ===
 
+/// Narrow GET /admin/caches, `tag` takes comma-separated `key:value` or `key` entries
+#[derive(Deserialize)]
+pub struct ListCachesQuery {
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub name_prefix: Option<String>,
+}
+
+/// DELETE /admin/caches/{name}, `purge` skips the drop retention window
+#[derive(Deserialize)]
+pub struct DropCacheQuery {
+    #[serde(default)]
+    pub purge: bool,
+}
+
 #[derive(Deserialize)]
 pub struct CreateCacheRequest {
     pub name: String,
@@ -65,8 +286,119 @@ pub struct CreateCacheRequest {
     pub description: Option<String>,
     #[serde(default)]
     pub tags: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub value_type: Option<String>, // "raw" or "json"
+    #[serde(default)]
+    pub value_format: Option<String>, // "raw", "json" or "msgpack", what values are stored as
+    #[serde(default)]
+    pub max_ops_per_sec: Option<u32>,
+    #[serde(default)]
+    pub capacity_unit: Option<String>, // "entries" or "bytes", what mem_bytes counts
+    #[serde(default)]
+    pub track_metadata: bool, // enables GET /cache/{name}/{key}/meta
+    #[serde(default)]
+    pub remote_url: Option<String>, // redis:// URL, required for the redis backend
+    #[serde(default)]
+    pub reap_interval_ms: Option<u64>, // remove expired entries in the background (ttl only)
+    #[serde(default)]
+    pub admission_threshold: Option<u8>, // accesses a new key needs to enter a full size cache
+    #[serde(default)]
+    pub ttl_jitter_pct: Option<u8>, // spread expiry of entries written together (ttl and redis)
+    #[serde(default)]
+    pub max_pinned_bytes: Option<u64>, // entries that may be pinned against eviction (ttl only)
+    #[serde(default)]
+    pub l1_entries: Option<u64>, // in-process tier in front of the backend (not ttl)
+    #[serde(default)]
+    pub l1_ttl_ms: Option<u64>, // how long the L1 tier serves an entry
+    #[serde(default)]
+    pub timeseries_retention_ms: Option<u64>, // age past a series' newest sample samples are kept
+    #[serde(default)]
+    pub timeseries_max_samples: Option<u64>, // samples a time series keeps at most
+    #[serde(default)]
+    pub events: Option<EventPolicy>, // redact keys and values in events, or turn them off
+}
+
+impl CreateCacheRequest {
+    /// The request a cache declared in the config stands for
+    pub fn declared(cache: &DeclaredCache) -> Self {
+        Self {
+            name: cache.name.clone(),
+            eviction: cache.backend.clone(),
+            mem_bytes: cache.mem_bytes,
+            disk_path: cache.disk_path.clone(),
+            shards: cache.shards,
+            policy: cache.policy.clone().unwrap_or_default(),
+            default_ttl_ms: cache.default_ttl_ms,
+            max_value_bytes: cache.max_value_bytes,
+            description: cache.description.clone(),
+            tags: None,
+            value_type: None,
+            value_format: None,
+            max_ops_per_sec: cache.max_ops_per_sec,
+            capacity_unit: cache.capacity_unit.clone(),
+            track_metadata: false,
+            remote_url: cache.remote_url.clone(),
+            reap_interval_ms: None,
+            admission_threshold: None,
+            ttl_jitter_pct: None,
+            max_pinned_bytes: None,
+            l1_entries: None,
+            l1_ttl_ms: None,
+            timeseries_retention_ms: None,
+            timeseries_max_samples: None,
+            events: None,
+        }
+    }
+
+    /// Fill in what the request leaves out from the defaults of this instance
+    pub fn with_defaults(mut self, defaults: &CacheDefaults) -> Self {
+        self.mem_bytes = self.mem_bytes.or(defaults.mem_bytes);
+        self.shards = self.shards.or(defaults.shards);
+        self.default_ttl_ms = self.default_ttl_ms.or(defaults.default_ttl_ms);
+        self.max_value_bytes = self.max_value_bytes.or(defaults.max_value_bytes);
+        self.max_ops_per_sec = self.max_ops_per_sec.or(defaults.max_ops_per_sec);
+        self
+    }
 }
 
 fn default_eviction() -> String {
     "timebound".to_string()
 }
+
+/// A create request checked without creating anything, average sizes feed the capacity estimate
+#[derive(Deserialize)]
+pub struct ValidateCacheRequest {
+    #[serde(flatten)]
+    pub cache: CreateCacheRequest,
+    #[serde(default = "default_avg_key_bytes")]
+    pub avg_key_bytes: u64,
+    #[serde(default = "default_avg_value_bytes")]
+    pub avg_value_bytes: u64,
+}
+
+fn default_avg_key_bytes() -> u64 {
+    32
+}
+
+fn default_avg_value_bytes() -> u64 {
+    1024
+}
+
+/// Fields left out keep their current value
+#[derive(Deserialize)]
+pub struct UpdateCacheRequest {
+    #[serde(default)]
+    pub default_ttl_ms: Option<u64>,
+    #[serde(default)]
+    pub mem_bytes: Option<u64>,
+    #[serde(default)]
+    pub max_value_bytes: Option<u64>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub max_ops_per_sec: Option<u32>,
+    #[serde(default)]
+    pub events: Option<EventPolicy>,
+}
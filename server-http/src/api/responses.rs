@@ -1,13 +1,30 @@
-use carbon::auth::{Permission, Role, User};
+use crate::api::ValueEncoding;
+use carbon::auth::{AccountKind, ApiKey, AuthError, Permission, Role, User};
+use carbon::domain::{
+    CacheAlias, CacheConfig, CacheStatus, CapacityEstimate, DroppedCache, MaterializedView, Quota,
+    Schedule,
+};
+use carbon::planes::data::{
+    CacheLatencyStats, ConnectionInfo, MaintenanceStatus, OpsLimiterStats, QuotaUsage, SlowLogEntry,
+};
+use carbon_query::IndexDefinition;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
-use std::collections::HashSet;
+use shared::ErrorCode;
+use std::collections::{BTreeMap, HashSet};
 
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
     pub id: String,
     pub username: String,
+    pub kind: AccountKind,
     pub role_ids: Vec<String>,
+    /// Keys of a service account, without their secrets
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<ApiKeyResponse>,
+    pub mfa_enrolled: bool,
+    pub mfa_required: bool,
+    pub quota: Option<Quota>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -15,9 +32,14 @@ pub struct UserResponse {
 impl From<User> for UserResponse {
     fn from(user: User) -> Self {
         Self {
+            mfa_enrolled: user.mfa_enrolled(),
             id: user.id,
             username: user.username,
+            kind: user.kind,
             role_ids: user.role_ids,
+            api_keys: user.api_keys.into_iter().map(Into::into).collect(),
+            mfa_required: user.mfa_required,
+            quota: user.quota,
             created_at: user.created_at,
             updated_at: user.updated_at,
         }
@@ -27,6 +49,36 @@ impl From<User> for UserResponse {
 #[derive(Debug, Serialize)]
 pub struct ListUsersResponse {
     pub users: Vec<UserResponse>,
+    pub service_accounts: Vec<UserResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    /// Caches the key reaches, all of them when empty
+    pub caches: Vec<String>,
+    pub permissions: HashSet<Permission>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            id: key.id,
+            name: key.name,
+            caches: key.caches,
+            permissions: key.permissions,
+            created_at: key.created_at,
+        }
+    }
+}
+
+/// A new API key, the only time its secret is shown
+#[derive(Debug, Serialize)]
+pub struct CreateApiKeyResponse {
+    pub key: String,
+    pub api_key: ApiKeyResponse,
 }
 
 #[derive(Debug, Serialize)]
@@ -35,6 +87,8 @@ pub struct RoleResponse {
     pub name: String,
     pub permissions: HashSet<Permission>,
     pub is_system_role: bool,
+    pub requires_mfa: bool,
+    pub quota: Option<Quota>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -45,6 +99,8 @@ impl From<Role> for RoleResponse {
             name: role.name,
             permissions: role.permissions,
             is_system_role: role.is_system_role,
+            requires_mfa: role.requires_mfa,
+            quota: role.quota,
             created_at: role.created_at,
         }
     }
@@ -59,14 +115,35 @@ pub struct ListRolesResponse {
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
+    /// Code from shared::ErrorCode, filled in from the status when not set here
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
 }
 
 impl ErrorResponse {
     pub fn new(error: impl Into<String>) -> Self {
         Self {
             error: error.into(),
+            code: None,
         }
     }
+
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code.as_str());
+        self
+    }
+}
+
+impl From<&shared::Error> for ErrorResponse {
+    fn from(error: &shared::Error) -> Self {
+        Self::new(error.to_string()).with_code(error.code())
+    }
+}
+
+impl From<&AuthError> for ErrorResponse {
+    fn from(error: &AuthError) -> Self {
+        Self::new(error.to_string()).with_code(error.code())
+    }
 }
 
 // === Cache Operation Models ===
@@ -81,23 +158,299 @@ pub struct GetResponse {
     pub found: bool,
     #[serde(skip_serializing_if = "String::is_empty")]
     pub value: String,
+    #[serde(skip_serializing_if = "ValueEncoding::is_utf8")]
+    pub encoding: ValueEncoding,
     pub ttl_ms_remaining: u64,
 }
 
+#[derive(Serialize)]
+pub struct BulkLoadResponse {
+    pub loaded: u64,
+    /// Records the cache's admission policy turned away
+    pub not_admitted: u64,
+}
+
 #[derive(Serialize)]
 pub struct DeleteResponse {
     pub deleted: bool,
 }
 
+#[derive(Serialize)]
+pub struct HashFieldsResponse {
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+pub struct QueryResponse {
+    pub keys: Vec<String>,
+    pub count: usize,
+    pub next_offset: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct IndexListResponse {
+    pub indexes: Vec<IndexDefinition>,
+}
+
+#[derive(Serialize)]
+pub struct SetMembersResponse {
+    pub members: Vec<String>,
+    pub count: usize,
+}
+
+#[derive(Serialize)]
+pub struct InvalidateByTagResponse {
+    pub invalidated: usize,
+}
+
+#[derive(Serialize)]
+pub struct SetMembershipResponse {
+    pub member: bool,
+}
+
+/// Score and rank of a sorted set member, both None when it is not a member
+#[derive(Serialize)]
+pub struct MemberScoreResponse {
+    pub found: bool,
+    pub score: Option<f64>,
+    pub rank: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct ScoredMember {
+    pub member: String,
+    pub score: f64,
+}
+
+#[derive(Serialize)]
+pub struct ScoredMembersResponse {
+    pub members: Vec<ScoredMember>,
+    pub count: usize,
+}
+
+#[derive(Serialize)]
+pub struct AppendSampleResponse {
+    /// Samples the series holds after the append and its cache's retention
+    pub samples: usize,
+}
+
+#[derive(Serialize)]
+pub struct TimeSeriesSample {
+    pub timestamp: u64,
+    pub value: String,
+}
+
+#[derive(Serialize)]
+pub struct SamplesResponse {
+    pub samples: Vec<TimeSeriesSample>,
+    pub count: usize,
+}
+
+#[derive(Serialize)]
+pub struct HyperLogLogAddResponse {
+    pub changed: bool,
+}
+
+/// Estimated number of distinct items
+#[derive(Serialize)]
+pub struct CardinalityResponse {
+    pub count: u64,
+}
+
+#[derive(Serialize)]
+pub struct BloomAddResponse {
+    pub added: bool,
+}
+
+/// False when the item was never added, true when it may have been
+#[derive(Serialize)]
+pub struct BloomExistsResponse {
+    pub exists: bool,
+}
+
+#[derive(Serialize)]
+pub struct UnlockResponse {
+    pub released: bool,
+}
+
+/// Whether a pin or unpin changed the entry, with the bytes pinned in its cache afterwards
+#[derive(Serialize)]
+pub struct PinResponse {
+    pub changed: bool,
+    pub pinned_bytes: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct RateLimitStats {
+    pub requests_per_second: u32,
+    pub burst: u32,
+    pub throttled: u64,
+    pub tracked_clients: usize,
+}
+
+#[derive(Serialize)]
+pub struct RateLimitStatsResponse {
+    pub data: Option<RateLimitStats>,
+    pub admin: Option<RateLimitStats>,
+    /// Cache operation limits, shared by all frontends
+    pub operations: OpsLimiterStats,
+}
+
+/// State of the server or one of its dependencies
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+    /// Not configured, does not affect readiness
+    Disabled,
+}
+
+#[derive(Serialize)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ComponentHealth {
+    pub fn new(status: HealthStatus, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            detail: Some(detail.into()),
+        }
+    }
+
+    pub fn up() -> Self {
+        Self {
+            status: HealthStatus::Up,
+            detail: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct HealthResponse {
+    pub status: HealthStatus,
+    pub components: BTreeMap<String, ComponentHealth>,
+    /// Present while the server or some caches refuse writes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<MaintenanceStatus>,
+}
+
+#[derive(Serialize)]
+pub struct SlowLogResponse {
+    pub threshold_ms: u64,
+    pub max_len: usize,
+    pub entries: Vec<SlowLogEntry>,
+}
+
+#[derive(Serialize)]
+pub struct SlowLogResetResponse {
+    pub cleared: usize,
+}
+
+#[derive(Serialize)]
+pub struct CacheStatsResponse {
+    pub latency: CacheLatencyStats,
+}
+
+#[derive(Serialize)]
+pub struct ConnectionsResponse {
+    pub connections: Vec<ConnectionInfo>,
+}
+
+#[derive(Serialize)]
+pub struct QuotaUsageResponse {
+    pub usage: Vec<QuotaUsage>,
+}
+
+#[derive(Serialize)]
+pub struct UserQuotaResponse {
+    /// Quota set on the user, None when it comes from their roles
+    pub quota: Option<Quota>,
+    /// Quota their writes are held to
+    pub effective: Quota,
+    pub usage: QuotaUsage,
+}
+
+#[derive(Serialize)]
+pub struct ConfigReloadResponse {
+    pub applied: Vec<String>,
+    pub requires_restart: Vec<String>,
+    pub errors: Vec<String>,
+}
+
 #[derive(Serialize)]
 pub struct CreateCacheResponse {
     pub created: bool,
     pub message: String,
 }
 
+#[derive(Serialize)]
+pub struct ValidateCacheResponse {
+    /// The configuration the cache would be created with, defaults applied
+    pub config: CacheConfig,
+    /// Creating it now would be a no-op because the name is taken
+    pub already_exists: bool,
+    pub estimate: CapacityEstimate,
+}
+
 #[derive(Serialize)]
 pub struct DropCacheResponse {
     pub dropped: bool,
+    /// Until when POST /admin/caches/{name}/restore brings it back, absent once purged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restorable_until_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct DroppedCachesResponse {
+    pub caches: Vec<DroppedCache>,
+}
+
+#[derive(Serialize)]
+pub struct ListAliasesResponse {
+    pub aliases: Vec<CacheAlias>,
+}
+
+#[derive(Serialize)]
+pub struct SetAliasResponse {
+    pub alias: String,
+    pub cache: String,
+    /// Cache the alias pointed to before, None when it was just created
+    pub previous: Option<String>,
+}
+
+/// A clone created, its entries still being copied, followed by cache.clone_progress events
+#[derive(Serialize)]
+pub struct CloneCacheResponse {
+    pub name: String,
+    pub source: String,
+    /// Entries of the source when it was cloned
+    pub entries: u64,
+}
+
+#[derive(Serialize)]
+pub struct ListSchedulesResponse {
+    pub schedules: Vec<Schedule>,
+}
+
+#[derive(Serialize)]
+pub struct ListViewsResponse {
+    pub views: Vec<MaterializedView>,
+}
+
+#[derive(Serialize)]
+pub struct RenameCacheResponse {
+    pub name: String,
+    pub previous_name: String,
+}
+
+#[derive(Serialize)]
+pub struct RetryCacheResponse {
+    pub name: String,
+    pub status: CacheStatus,
 }
 
 #[derive(Serialize)]
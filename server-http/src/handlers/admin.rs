@@ -1,3 +1,17 @@
+pub mod aliases;
 pub mod cache;
+pub mod config;
+pub mod connections;
+pub mod event_sink;
+pub mod indexes;
+pub mod maintenance;
+pub mod manifest;
+pub mod metrics;
+pub mod quotas;
+pub mod rate_limits;
 pub mod roles;
+pub mod schedules;
+pub mod service_accounts;
+pub mod slow_log;
 pub mod users;
+pub mod views;
@@ -0,0 +1,50 @@
+use crate::api::{ListAliasesResponse, SetAliasRequest, SetAliasResponse};
+use crate::handlers::cache::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use tracing::info;
+
+/// GET /admin/aliases - Every alias with the cache it points to
+pub async fn list_aliases(State(state): State<AppState>) -> Json<ListAliasesResponse> {
+    Json(ListAliasesResponse {
+        aliases: state.cache_manager.aliases(),
+    })
+}
+
+/// PUT /admin/aliases/{alias} - Create an alias or repoint it at another cache in one step
+pub async fn set_alias(
+    State(state): State<AppState>,
+    Path(alias): Path<String>,
+    Json(req): Json<SetAliasRequest>,
+) -> Result<Json<SetAliasResponse>, ApiError> {
+    info!("SET_ALIAS: alias={}, cache={}", alias, req.cache);
+
+    let previous = state.cache_manager.set_alias(&alias, &req.cache).await?;
+    // Copies read through the alias came from the cache it pointed to before
+    state.cache_operations.invalidate_tracked_cache(&alias);
+    Ok(Json(SetAliasResponse {
+        alias,
+        cache: req.cache,
+        previous,
+    }))
+}
+
+/// DELETE /admin/aliases/{alias} - Remove an alias, leaving its cache in place
+pub async fn remove_alias(
+    State(state): State<AppState>,
+    Path(alias): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    info!("REMOVE_ALIAS: alias={}", alias);
+
+    match state.cache_manager.remove_alias(&alias).await? {
+        true => {
+            state.cache_operations.invalidate_tracked_cache(&alias);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        false => Err(StatusCode::NOT_FOUND.into()),
+    }
+}
@@ -1,17 +1,27 @@
-use crate::api::requests::CreateCacheRequest;
+use crate::api::requests::{
+    CloneCacheRequest, CreateCacheRequest, DropCacheQuery, ListCachesQuery, RenameCacheRequest,
+    UpdateCacheRequest, ValidateCacheRequest,
+};
 
-use crate::api::responses::{CreateCacheResponse, DropCacheResponse, ValidationErrorResponse};
+use crate::api::responses::{
+    CloneCacheResponse, CreateCacheResponse, DropCacheResponse, DroppedCachesResponse,
+    ErrorResponse, RenameCacheResponse, RetryCacheResponse, ValidateCacheResponse,
+    ValidationErrorResponse,
+};
+use crate::handlers::cache::{error_status, ApiError};
 use crate::state::AppState;
 use crate::validation::CacheConfigFactory;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
+use carbon::domain::CacheFilter;
 use carbon::planes::control::operation::AdminOperations;
 use carbon::ports::StorageFactory;
+use shared::ErrorCode;
 use storage_engine::UnifiedStorageFactory;
-use tracing::info;
+use tracing::{info, warn};
 
 /// POST /admin/caches
 pub async fn create_cache(
@@ -21,6 +31,7 @@ pub async fn create_cache(
     info!("CREATE_CACHE: name={}, backend={}", req.name, req.eviction);
 
     // Validate and build config using factory
+    let req = req.with_defaults(&state.cache_defaults);
     let config = match CacheConfigFactory::from_request(req) {
         Ok(config) => config,
         Err(err) => {
@@ -45,6 +56,15 @@ pub async fn create_cache(
             created: result.created,
             message: result.message,
         })),
+        // Rejected by the validation every frontend shares
+        Err(shared::Error::InvalidValue(error)) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ValidationErrorResponse {
+                error,
+                field: None,
+                details: None,
+            }),
+        )),
         Err(_) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ValidationErrorResponse {
@@ -56,35 +76,266 @@ pub async fn create_cache(
     }
 }
 
+/// POST /admin/caches/:name/clone - Create a cache with the settings of `name` and the
+/// overrides given, then copy its entries over in the background
+pub async fn clone_cache(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<CloneCacheRequest>,
+) -> Result<(StatusCode, Json<CloneCacheResponse>), (StatusCode, Json<ErrorResponse>)> {
+    info!("CLONE_CACHE: name={}, clone={}", name, req.name);
+
+    let to_response = |e: shared::Error| (error_status(&e), Json(ErrorResponse::from(&e)));
+    let overrides = CacheConfigFactory::update_from_request(req.overrides);
+    let config = state
+        .cache_manager
+        .clone_config(&name, &req.name, overrides)
+        .await
+        .map_err(to_response)?;
+    // Taken before the clone exists, so a backend that cannot list its entries leaves nothing
+    // behind
+    let entries = state
+        .cache_operations
+        .snapshot(&name)
+        .await
+        .map_err(to_response)?;
+
+    let storage = UnifiedStorageFactory.create_from_config(&config);
+    let result = state
+        .cache_manager
+        .create_cache(config, storage)
+        .await
+        .map_err(to_response)?;
+    if !result.created {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse::new(result.message).with_code(ErrorCode::AlreadyExists)),
+        ));
+    }
+
+    let response = CloneCacheResponse {
+        name: req.name.clone(),
+        source: name.clone(),
+        entries: entries.len() as u64,
+    };
+    let cache_operations = state.cache_operations.clone();
+    tokio::spawn(async move {
+        if let Err(e) = cache_operations
+            .copy_entries(&name, &req.name, entries)
+            .await
+        {
+            warn!("Copying cache '{}' into '{}' failed: {}", name, req.name, e);
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(response)))
+}
+
+/// POST /admin/caches/validate - Dry run of POST /admin/caches with a capacity estimate
+pub async fn validate_cache(
+    State(state): State<AppState>,
+    Json(req): Json<ValidateCacheRequest>,
+) -> Result<Json<ValidateCacheResponse>, (StatusCode, Json<ValidationErrorResponse>)> {
+    info!(
+        "VALIDATE_CACHE: name={}, backend={}",
+        req.cache.name, req.cache.eviction
+    );
+
+    let req = req.cache.with_defaults(&state.cache_defaults);
+    let config = CacheConfigFactory::from_request(req).map_err(|err| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ValidationErrorResponse {
+                error: err.to_string(),
+                field: None,
+                details: Some(format!("{:?}", err)),
+            }),
+        )
+    })?;
+
+    let already_exists = state.cache_manager.get_cache(&config.name).await.is_some();
+    let estimate = UnifiedStorageFactory.estimate(&config, req.avg_key_bytes, req.avg_value_bytes);
+
+    Ok(Json(ValidateCacheResponse {
+        config: config.redacted(),
+        already_exists,
+        estimate,
+    }))
+}
+
 /// DELETE /admin/caches/:name
+/// The cache stays restorable for the drop retention window unless `purge=true`
 pub async fn drop_cache(
     State(state): State<AppState>,
     Path(name): Path<String>,
-) -> Result<Json<DropCacheResponse>, StatusCode> {
-    info!("DROP_CACHE: name={}", name);
+    Query(query): Query<DropCacheQuery>,
+) -> Result<Json<DropCacheResponse>, ApiError> {
+    info!("DROP_CACHE: name={}, purge={}", name, query.purge);
 
-    match state.cache_manager.drop_cache(&name).await {
-        Ok(result) => Ok(Json(DropCacheResponse {
-            dropped: result.dropped,
-        })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    let result = if query.purge {
+        state.cache_manager.purge_cache(&name).await
+    } else {
+        state.cache_manager.drop_cache(&name).await
+    };
+
+    match result {
+        Ok(result) => {
+            // Indexes and quota charges are kept while the cache can still be restored
+            if result.dropped && result.restorable_until_ms.is_none() {
+                state.cache_operations.cache_purged(&name);
+            } else if result.dropped {
+                state.cache_operations.invalidate_tracked_cache(&name);
+            }
+            Ok(Json(DropCacheResponse {
+                dropped: result.dropped,
+                restorable_until_ms: result.restorable_until_ms,
+            }))
+        }
+        // An alias still points to it
+        Err(e @ shared::Error::PreconditionFailed(_)) => Err(e.into()),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    }
+}
+
+/// POST /admin/caches/:name/restore - Bring back a dropped cache before it is purged
+pub async fn restore_cache(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    info!("RESTORE_CACHE: name={}", name);
+
+    match state.cache_manager.restore_cache(&name).await {
+        Ok(()) => describe_cache(State(state), Path(name)).await,
+        Err(e @ shared::Error::CacheNotFound(_)) => Err(e.into()),
+        Err(shared::Error::InvalidValue(_)) => Err(StatusCode::CONFLICT.into()),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    }
+}
+
+/// POST /admin/caches/:name/retry - Load a cache that failed to load at startup again, or
+/// check the backend of a live one
+pub async fn retry_cache(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<RetryCacheResponse>, ApiError> {
+    info!("RETRY_CACHE: name={}", name);
+
+    match state.cache_manager.retry_cache(&name).await {
+        Ok(status) => Ok(Json(RetryCacheResponse { name, status })),
+        Err(e @ shared::Error::CacheNotFound(_)) => Err(e.into()),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+    }
+}
+
+/// POST /admin/caches/:name/rename - Give a cache a new name, keeping its entries, settings
+/// and the aliases pointing at it
+pub async fn rename_cache(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<RenameCacheRequest>,
+) -> Result<Json<RenameCacheResponse>, ApiError> {
+    info!("RENAME_CACHE: name={}, new_name={}", name, req.name);
+
+    state.cache_manager.rename_cache(&name, &req.name).await?;
+    state.cache_operations.cache_renamed(&name, &req.name);
+
+    Ok(Json(RenameCacheResponse {
+        name: req.name,
+        previous_name: name,
+    }))
+}
+
+/// GET /admin/dropped-caches - Dropped caches that can still be restored
+pub async fn list_dropped_caches(State(state): State<AppState>) -> Json<DroppedCachesResponse> {
+    info!("LIST_DROPPED_CACHES");
+
+    Json(DroppedCachesResponse {
+        caches: state.cache_manager.dropped_caches(),
+    })
+}
+
+/// PATCH /admin/caches/:name
+pub async fn update_cache(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<UpdateCacheRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ValidationErrorResponse>)> {
+    info!("UPDATE_CACHE: name={}", name);
+
+    let update = CacheConfigFactory::update_from_request(req);
+
+    match state.cache_manager.update_cache(&name, update).await {
+        Ok(result) => {
+            let json = serde_json::to_value(result).map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ValidationErrorResponse {
+                        error: e.to_string(),
+                        field: None,
+                        details: None,
+                    }),
+                )
+            })?;
+            Ok(Json(json))
+        }
+        Err(shared::Error::InvalidValue(error)) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ValidationErrorResponse {
+                error,
+                field: None,
+                details: None,
+            }),
+        )),
+        Err(shared::Error::CacheNotFound(_)) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ValidationErrorResponse {
+                error: format!("Cache '{}' not found", name),
+                field: None,
+                details: None,
+            }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ValidationErrorResponse {
+                error: "Failed to update cache".to_string(),
+                field: None,
+                details: Some(e.to_string()),
+            }),
+        )),
     }
 }
 
-/// GET /admin/caches
+/// GET /admin/caches?tag=env:prod,team&name_prefix=orders
 pub async fn list_caches(
     State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    info!("LIST_CACHES");
+    Query(query): Query<ListCachesQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    info!(
+        "LIST_CACHES: tag={:?}, name_prefix={:?}",
+        query.tag, query.name_prefix
+    );
+
+    let filter = query
+        .tag
+        .iter()
+        .flat_map(|tags| tags.split(','))
+        .filter(|tag| !tag.is_empty())
+        .fold(
+            CacheFilter {
+                name_prefix: query.name_prefix.clone(),
+                ..Default::default()
+            },
+            CacheFilter::with_tag,
+        );
 
-    match state.cache_manager.list_caches().await {
+    match state.cache_manager.find_caches(&filter).await {
         Ok(result) => {
             // Convert to JSON
             let json =
                 serde_json::to_value(result).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
             Ok(Json(json))
         }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
     }
 }
 
@@ -92,7 +343,7 @@ pub async fn list_caches(
 pub async fn describe_cache(
     State(state): State<AppState>,
     Path(name): Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     info!("DESCRIBE_CACHE: name={}", name);
 
     match state.cache_manager.describe_cache(&name).await {
@@ -101,7 +352,7 @@ pub async fn describe_cache(
                 serde_json::to_value(result).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
             Ok(Json(json))
         }
-        Err(shared::Error::CacheNotFound(_)) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e @ shared::Error::CacheNotFound(_)) => Err(e.into()),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
     }
 }
@@ -0,0 +1,31 @@
+use crate::api::{ConfigReloadResponse, ErrorResponse};
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use carbon::auth::User;
+use tracing::info;
+
+/// POST /admin/config/reload - Re-read the configuration and apply runtime settings
+pub async fn reload_config(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<ConfigReloadResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Some(reloader) = state.config_reloader.as_ref() else {
+        return Err((
+            StatusCode::NOT_IMPLEMENTED,
+            Json(ErrorResponse::new("Configuration reload is not enabled")),
+        ));
+    };
+
+    info!("RELOAD_CONFIG: requested_by={}", current_user.username);
+
+    let report = reloader.reload();
+    Ok(Json(ConfigReloadResponse {
+        applied: report.applied.into_iter().map(String::from).collect(),
+        requires_restart: report
+            .requires_restart
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        errors: report.errors,
+    }))
+}
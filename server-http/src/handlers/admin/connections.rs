@@ -0,0 +1,26 @@
+use crate::api::{ConnectionsResponse, ErrorResponse};
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use carbon::auth::User;
+use tracing::info;
+
+/// GET /admin/connections - Open TCP connections with their negotiated features and traffic
+pub async fn list_connections(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<ConnectionsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let connections = state.connections.as_ref().ok_or_else(|| {
+        (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(ErrorResponse::new(
+                "Connections are only tracked when the TCP server runs in the same process",
+            )),
+        )
+    })?;
+
+    info!("LIST_CONNECTIONS: requested_by={}", current_user.username);
+
+    Ok(Json(ConnectionsResponse {
+        connections: connections.connections(),
+    }))
+}
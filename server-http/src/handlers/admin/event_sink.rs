@@ -0,0 +1,25 @@
+use crate::api::ErrorResponse;
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use carbon::auth::User;
+use carbon::event_sink::EventSinkStats;
+use tracing::info;
+
+/// GET /admin/event-sink - Events published to and failed on each topic of the event sink
+pub async fn event_sink_stats(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<EventSinkStats>, (StatusCode, Json<ErrorResponse>)> {
+    let event_sink = state.event_sink.as_ref().ok_or_else(|| {
+        (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(ErrorResponse::new(
+                "No event sink is configured, set CARBON_EVENT_SINK to kafka or nats",
+            )),
+        )
+    })?;
+
+    info!("EVENT_SINK_STATS: requested_by={}", current_user.username);
+
+    Ok(Json(event_sink.stats()))
+}
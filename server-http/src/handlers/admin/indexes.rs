@@ -0,0 +1,135 @@
+use crate::api::{DeleteResponse, ErrorResponse, IndexListResponse};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use carbon::domain::ValueType;
+use carbon::encoding::decode_document;
+use carbon_query::{CacheSchema, IndexDefinition};
+use tracing::{info, warn};
+
+type IndexError = (StatusCode, Json<ErrorResponse>);
+
+/// POST /admin/caches/:name/indexes
+pub async fn create_index(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(definition): Json<IndexDefinition>,
+) -> Result<(StatusCode, Json<IndexDefinition>), IndexError> {
+    info!("CREATE_INDEX: cache={}, index={}", name, definition.name);
+
+    let (store, config) =
+        state.cache_manager.get_cache(&name).await.ok_or_else(|| {
+            index_error(StatusCode::NOT_FOUND, format!("Cache not found: {}", name))
+        })?;
+
+    // Indexes read fields out of JSON documents
+    if config.value_type != ValueType::Json {
+        return Err(index_error(
+            StatusCode::BAD_REQUEST,
+            format!("cache '{}' does not store JSON values", name),
+        ));
+    }
+
+    definition
+        .validate()
+        .map_err(|e| index_error(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let indexes = state.index_registry.cache(&name);
+    indexes
+        .create_index(definition.clone())
+        .map_err(|e| index_error(StatusCode::CONFLICT, e.to_string()))?;
+
+    // Registered first, so writes made while the entries are read index themselves
+    match store.entries().await {
+        Ok(entries) => {
+            let documents = entries.into_iter().filter_map(|(key, value)| {
+                decode_document(&value, config.value_format)
+                    .ok()
+                    .map(|document| (key, document))
+            });
+            let added = indexes.backfill(&definition.name, documents);
+            info!(
+                "CREATE_INDEX: back-filled {} entries into {}",
+                added, definition.name
+            );
+        }
+        Err(e) => warn!(
+            "Index {} on cache {} only covers new writes: {}",
+            definition.name, name, e
+        ),
+    }
+
+    Ok((StatusCode::CREATED, Json(definition)))
+}
+
+/// GET /admin/caches/:name/indexes
+pub async fn list_indexes(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<IndexListResponse>, IndexError> {
+    info!("LIST_INDEXES: cache={}", name);
+
+    if state.cache_manager.get_cache(&name).await.is_none() {
+        return Err(index_error(
+            StatusCode::NOT_FOUND,
+            format!("Cache not found: {}", name),
+        ));
+    }
+
+    let indexes = state
+        .index_registry
+        .get(&name)
+        .map(|indexes| indexes.definitions())
+        .unwrap_or_default();
+
+    Ok(Json(IndexListResponse { indexes }))
+}
+
+/// GET /admin/caches/:name/schema
+/// Fields of the cache's documents as its indexes define them, the input of `carbon codegen`
+pub async fn get_schema(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<CacheSchema>, IndexError> {
+    info!("GET_SCHEMA: cache={}", name);
+
+    let (_, config) =
+        state.cache_manager.get_cache(&name).await.ok_or_else(|| {
+            index_error(StatusCode::NOT_FOUND, format!("Cache not found: {}", name))
+        })?;
+
+    let indexes = state
+        .index_registry
+        .get(&name)
+        .map(|indexes| indexes.definitions())
+        .unwrap_or_default();
+
+    Ok(Json(CacheSchema::from_indexes(
+        name,
+        config.value_type,
+        config.value_format,
+        &indexes,
+    )))
+}
+
+/// DELETE /admin/caches/:name/indexes/:index
+pub async fn drop_index(
+    State(state): State<AppState>,
+    Path((name, index)): Path<(String, String)>,
+) -> Json<DeleteResponse> {
+    info!("DROP_INDEX: cache={}, index={}", name, index);
+
+    let deleted = state
+        .index_registry
+        .get(&name)
+        .is_some_and(|indexes| indexes.drop_index(&index));
+
+    Json(DeleteResponse { deleted })
+}
+
+fn index_error(status: StatusCode, error: String) -> IndexError {
+    (status, Json(ErrorResponse::new(error)))
+}
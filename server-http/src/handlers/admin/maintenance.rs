@@ -0,0 +1,35 @@
+use crate::api::{ErrorResponse, MaintenanceRequest};
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use carbon::auth::User;
+use carbon::planes::data::MaintenanceStatus;
+use tracing::info;
+
+/// POST /admin/maintenance - Put the server, or a single cache, into read-only mode or take it
+/// out again
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Json(req): Json<MaintenanceRequest>,
+) -> Result<Json<MaintenanceStatus>, (StatusCode, Json<ErrorResponse>)> {
+    info!(
+        "SET_MAINTENANCE: read_only={}, cache={}, requested_by={}",
+        req.read_only,
+        req.cache.as_deref().unwrap_or("*"),
+        current_user.username
+    );
+
+    let maintenance = state.cache_operations.maintenance();
+    match req.cache {
+        Some(cache_name) => {
+            if state.cache_manager.get_cache(&cache_name).await.is_none() {
+                let error = shared::Error::CacheNotFound(cache_name);
+                return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&error))));
+            }
+            maintenance.set_cache_read_only(&cache_name, req.read_only);
+        }
+        None => maintenance.set_read_only(req.read_only),
+    }
+
+    Ok(Json(maintenance.status()))
+}
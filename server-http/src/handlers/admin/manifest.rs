@@ -0,0 +1,58 @@
+use crate::api::ErrorResponse;
+use crate::middleware::check_permission;
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use carbon::auth::{AuthManifest, ManifestReport, Permission, User};
+use tracing::{error, info};
+
+/// GET /admin/auth/export - Custom roles and all users, with password hashes, as a manifest
+pub async fn export_auth(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<AuthManifest>, (StatusCode, Json<ErrorResponse>)> {
+    info!("EXPORT_AUTH: requested_by={}", current_user.username);
+
+    match AuthManifest::export(&state.user_service, &state.role_service).await {
+        Ok(manifest) => Ok(Json(manifest)),
+        Err(e) => {
+            error!("Failed to export users and roles: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::from(&e)),
+            ))
+        }
+    }
+}
+
+/// POST /admin/auth/import - Apply a YAML or JSON manifest of users and roles
+pub async fn import_auth(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    body: String,
+) -> Result<Json<ManifestReport>, (StatusCode, Json<ErrorResponse>)> {
+    // Manifests create roles as well as users
+    if check_permission(&state.auth_service, &current_user, Permission::ManageRoles)
+        .await
+        .is_err()
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse::new("Insufficient permissions")),
+        ));
+    }
+
+    info!("IMPORT_AUTH: requested_by={}", current_user.username);
+
+    let manifest = AuthManifest::parse(&body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))?;
+    match manifest
+        .apply(&state.user_service, &state.role_service)
+        .await
+    {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            error!("Failed to import users and roles: {}", e);
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::api::{CacheStatsResponse, ErrorResponse};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use carbon::event_sink::EventSinkStats;
+use carbon::planes::data::{CacheLatencyStats, LatencyOperation};
+use std::fmt::Write;
+use tracing::info;
+
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// GET /admin/caches/{name}/stats - PUT, GET and DELETE latency percentiles of a cache
+pub async fn cache_stats(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<CacheStatsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    info!("CACHE_STATS: name={}", name);
+
+    if state.cache_manager.get_cache(&name).await.is_none() {
+        let e = shared::Error::CacheNotFound(name);
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e))));
+    }
+
+    // A cache nobody has used yet has empty histograms
+    let latency = state
+        .cache_operations
+        .latencies()
+        .cache(&name)
+        .unwrap_or_else(|| CacheLatencyStats {
+            cache_name: name.clone(),
+            put: Default::default(),
+            get: Default::default(),
+            delete: Default::default(),
+        });
+
+    Ok(Json(CacheStatsResponse { latency }))
+}
+
+/// GET /metrics - Operation latencies and event sink deliveries in the Prometheus text format
+pub async fn prometheus_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let mut body = render_prometheus(&state.cache_operations.latencies().caches());
+    if let Some(event_sink) = &state.event_sink {
+        render_event_sink(&mut body, &event_sink.stats());
+    }
+    ([(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)], body)
+}
+
+/// Latencies as a summary with p50, p95 and p99 quantiles and a max gauge, in seconds
+fn render_prometheus(caches: &[CacheLatencyStats]) -> String {
+    let mut out = String::new();
+    out.push_str("# HELP carbon_operation_latency_seconds Latency of cache operations\n");
+    out.push_str("# TYPE carbon_operation_latency_seconds summary\n");
+    for cache in caches {
+        let name = escape_label(&cache.cache_name);
+        for operation in LatencyOperation::ALL {
+            let summary = cache.operation(operation);
+            let labels = format!("cache=\"{}\",operation=\"{}\"", name, operation.as_str());
+            for (quantile, value_us) in [
+                ("0.5", summary.p50_us),
+                ("0.95", summary.p95_us),
+                ("0.99", summary.p99_us),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "carbon_operation_latency_seconds{{{},quantile=\"{}\"}} {}",
+                    labels,
+                    quantile,
+                    seconds(value_us)
+                );
+            }
+            let _ = writeln!(
+                out,
+                "carbon_operation_latency_seconds_sum{{{}}} {}",
+                labels,
+                seconds(summary.sum_us)
+            );
+            let _ = writeln!(
+                out,
+                "carbon_operation_latency_seconds_count{{{}}} {}",
+                labels, summary.count
+            );
+        }
+    }
+
+    out.push_str("# HELP carbon_operation_latency_max_seconds Slowest cache operation\n");
+    out.push_str("# TYPE carbon_operation_latency_max_seconds gauge\n");
+    for cache in caches {
+        let name = escape_label(&cache.cache_name);
+        for operation in LatencyOperation::ALL {
+            let _ = writeln!(
+                out,
+                "carbon_operation_latency_max_seconds{{cache=\"{}\",operation=\"{}\"}} {}",
+                name,
+                operation.as_str(),
+                seconds(cache.operation(operation).max_us)
+            );
+        }
+    }
+    out
+}
+
+/// Events published and failed per topic, and events the sink fell behind on
+fn render_event_sink(out: &mut String, stats: &EventSinkStats) {
+    for (metric, help, failed) in [
+        (
+            "carbon_event_sink_published_total",
+            "Cache events delivered to the event sink",
+            false,
+        ),
+        (
+            "carbon_event_sink_failed_total",
+            "Cache events the event sink failed to deliver",
+            true,
+        ),
+    ] {
+        let _ = writeln!(out, "# HELP {} {}", metric, help);
+        let _ = writeln!(out, "# TYPE {} counter", metric);
+        for topic in &stats.topics {
+            let _ = writeln!(
+                out,
+                "{}{{sink=\"{}\",topic=\"{}\"}} {}",
+                metric,
+                stats.sink,
+                escape_label(&topic.topic),
+                if failed {
+                    topic.failed
+                } else {
+                    topic.published
+                }
+            );
+        }
+    }
+    out.push_str("# HELP carbon_event_sink_lagged_total Cache events dropped because the event sink fell behind\n");
+    out.push_str("# TYPE carbon_event_sink_lagged_total counter\n");
+    let _ = writeln!(
+        out,
+        "carbon_event_sink_lagged_total{{sink=\"{}\"}} {}",
+        stats.sink, stats.lagged
+    );
+}
+
+fn seconds(us: u64) -> f64 {
+    us as f64 / 1_000_000.0
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use carbon::event_sink::TopicDeliveryStats;
+    use carbon::planes::data::LatencySummary;
+
+    #[test]
+    fn test_render_prometheus() {
+        let caches = vec![CacheLatencyStats {
+            cache_name: "us\"ers".to_string(),
+            put: LatencySummary::default(),
+            get: LatencySummary {
+                count: 4,
+                sum_us: 2_000,
+                p50_us: 250,
+                p95_us: 900,
+                p99_us: 1_000,
+                max_us: 1_500,
+            },
+            delete: LatencySummary::default(),
+        }];
+
+        let text = render_prometheus(&caches);
+        assert!(text.contains(
+            "carbon_operation_latency_seconds{cache=\"us\\\"ers\",operation=\"get\",quantile=\"0.5\"} 0.00025\n"
+        ));
+        assert!(text.contains(
+            "carbon_operation_latency_seconds_count{cache=\"us\\\"ers\",operation=\"get\"} 4\n"
+        ));
+        assert!(text.contains(
+            "carbon_operation_latency_max_seconds{cache=\"us\\\"ers\",operation=\"get\"} 0.0015\n"
+        ));
+        assert!(text.contains("# TYPE carbon_operation_latency_seconds summary\n"));
+    }
+
+    #[test]
+    fn test_render_event_sink() {
+        let stats = EventSinkStats {
+            sink: "kafka",
+            topics: vec![TopicDeliveryStats {
+                topic: "carbon.users".to_string(),
+                published: 7,
+                failed: 2,
+            }],
+            lagged: 3,
+            last_error: None,
+        };
+
+        let mut text = String::new();
+        render_event_sink(&mut text, &stats);
+        assert!(text.contains(
+            "carbon_event_sink_published_total{sink=\"kafka\",topic=\"carbon.users\"} 7\n"
+        ));
+        assert!(text
+            .contains("carbon_event_sink_failed_total{sink=\"kafka\",topic=\"carbon.users\"} 2\n"));
+        assert!(text.contains("carbon_event_sink_lagged_total{sink=\"kafka\"} 3\n"));
+        assert!(text.contains("# TYPE carbon_event_sink_failed_total counter\n"));
+    }
+}
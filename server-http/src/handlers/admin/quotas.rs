@@ -0,0 +1,70 @@
+use crate::api::{ErrorResponse, QuotaUsageResponse, UserQuotaResponse};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use carbon::auth::User;
+use carbon::planes::data::QuotaTracker;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// GET /admin/quotas - What every user holds and has written today
+pub async fn list_quota_usage(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<QuotaUsageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let quotas = quotas(&state)?;
+
+    info!("QUOTA_USAGE: requested_by={}", current_user.username);
+
+    Ok(Json(QuotaUsageResponse {
+        usage: quotas.all_usage(),
+    }))
+}
+
+/// GET /admin/users/{username}/quota - A user's quota and usage
+pub async fn get_user_quota(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Path(username): Path<String>,
+) -> Result<Json<UserQuotaResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let quotas = quotas(&state)?;
+
+    info!(
+        "GET_USER_QUOTA: username={}, requested_by={}",
+        username, current_user.username
+    );
+
+    let user = match state.user_service.get_user(&username).await {
+        Ok(user) => user,
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
+    };
+
+    match state.auth_service.effective_quota(&user).await {
+        Ok(effective) => Ok(Json(UserQuotaResponse {
+            quota: user.quota,
+            effective,
+            usage: quotas.usage(&user.username),
+        })),
+        Err(e) => {
+            error!("Failed to resolve quota of {}: {}", username, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::from(&e)),
+            ))
+        }
+    }
+}
+
+fn quotas(state: &AppState) -> Result<Arc<QuotaTracker>, (StatusCode, Json<ErrorResponse>)> {
+    state.quotas.clone().ok_or_else(|| {
+        (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(ErrorResponse::new(
+                "Quotas are not enabled, set CARBON_QUOTAS_ENABLED=true",
+            )),
+        )
+    })
+}
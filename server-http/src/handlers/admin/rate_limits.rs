@@ -0,0 +1,27 @@
+use crate::api::{RateLimitStats, RateLimitStatsResponse};
+use crate::middleware::RateLimiter;
+use crate::state::AppState;
+use axum::{extract::State, Json};
+use std::sync::Arc;
+use tracing::info;
+
+/// GET /admin/rate-limits
+pub async fn rate_limit_stats(State(state): State<AppState>) -> Json<RateLimitStatsResponse> {
+    info!("RATE_LIMIT_STATS");
+
+    Json(RateLimitStatsResponse {
+        data: state.rate_limits.data().as_ref().map(stats),
+        admin: state.rate_limits.admin().as_ref().map(stats),
+        operations: state.cache_operations.ops_limiter().stats(),
+    })
+}
+
+fn stats(limiter: &Arc<RateLimiter>) -> RateLimitStats {
+    let limit = limiter.limit();
+    RateLimitStats {
+        requests_per_second: limit.requests_per_second,
+        burst: limit.burst,
+        throttled: limiter.throttled(),
+        tracked_clients: limiter.tracked_clients(),
+    }
+}
@@ -1,14 +1,15 @@
 use crate::api::{
-    CreateRoleRequest, ErrorResponse, ListRolesResponse, RoleResponse, UpdateRoleRequest,
+    CreateRoleRequest, ErrorResponse, ListRolesResponse, RoleResponse, SetMfaRequiredRequest,
+    UpdateRoleRequest,
 };
-use crate::middleware::check_permission;
 use crate::state::AppState;
 use axum::{
     extract::{Path, State},
     http::StatusCode,
     Extension, Json,
 };
-use carbon::auth::{Permission, User};
+use carbon::auth::User;
+use carbon::domain::Quota;
 use tracing::{error, info};
 
 /// POST /admin/roles - Create a new custom role
@@ -17,13 +18,6 @@ pub async fn create_role(
     Extension(current_user): Extension<User>,
     Json(req): Json<CreateRoleRequest>,
 ) -> Result<(StatusCode, Json<RoleResponse>), (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has ManageRoles permission
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::ManageRoles).await
-    {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
-    }
-
     info!(
         "CREATE_ROLE: name={}, requested_by={}",
         req.name, current_user.username
@@ -37,10 +31,7 @@ pub async fn create_role(
         Ok(role) => Ok((StatusCode::CREATED, Json(role.into()))),
         Err(e) => {
             error!("Failed to create role: {}", e);
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
         }
     }
 }
@@ -48,15 +39,7 @@ pub async fn create_role(
 /// GET /admin/roles - List all roles
 pub async fn list_roles(
     State(state): State<AppState>,
-    Extension(current_user): Extension<User>,
 ) -> Result<Json<ListRolesResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has AdminRead permission (any user/admin can list roles)
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::AdminRead).await
-    {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
-    }
-
     match state.role_service.list_roles().await {
         Ok(roles) => {
             let role_responses = roles.into_iter().map(|r| r.into()).collect();
@@ -68,7 +51,7 @@ pub async fn list_roles(
             error!("Failed to list roles: {}", e);
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(e.to_string())),
+                Json(ErrorResponse::from(&e)),
             ))
         }
     }
@@ -77,24 +60,13 @@ pub async fn list_roles(
 /// GET /admin/roles/{name} - Get role by name
 pub async fn get_role(
     State(state): State<AppState>,
-    Extension(current_user): Extension<User>,
     Path(name): Path<String>,
 ) -> Result<Json<RoleResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has AdminRead permission
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::AdminRead).await
-    {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
-    }
-
     match state.role_service.get_role(&name).await {
         Ok(role) => Ok(Json(role.into())),
         Err(e) => {
             error!("Failed to get role {}: {}", name, e);
-            Err((
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e))))
         }
     }
 }
@@ -106,13 +78,6 @@ pub async fn update_role(
     Path(name): Path<String>,
     Json(req): Json<UpdateRoleRequest>,
 ) -> Result<Json<RoleResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has ManageRoles permission
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::ManageRoles).await
-    {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
-    }
-
     info!(
         "UPDATE_ROLE: name={}, requested_by={}",
         name, current_user.username
@@ -121,12 +86,7 @@ pub async fn update_role(
     // Get role by name first
     let role = match state.role_service.get_role(&name).await {
         Ok(role) => role,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
-        }
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
     };
 
     match state
@@ -137,10 +97,7 @@ pub async fn update_role(
         Ok(role) => Ok(Json(role.into())),
         Err(e) => {
             error!("Failed to update role {}: {}", name, e);
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
         }
     }
 }
@@ -151,13 +108,6 @@ pub async fn delete_role(
     Extension(current_user): Extension<User>,
     Path(name): Path<String>,
 ) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has ManageRoles permission
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::ManageRoles).await
-    {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
-    }
-
     info!(
         "DELETE_ROLE: name={}, requested_by={}",
         name, current_user.username
@@ -166,22 +116,91 @@ pub async fn delete_role(
     // Get role by name first
     let role = match state.role_service.get_role(&name).await {
         Ok(role) => role,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
-        }
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
     };
 
     match state.role_service.delete_role(&role.id).await {
         Ok(_) => Ok(StatusCode::NO_CONTENT),
         Err(e) => {
             error!("Failed to delete role {}: {}", name, e);
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
+        }
+    }
+}
+
+/// PUT /admin/roles/{name}/mfa - Require members to sign in with a second factor
+/// Allowed on system roles, unlike other updates
+pub async fn set_role_mfa_required(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Path(name): Path<String>,
+    Json(req): Json<SetMfaRequiredRequest>,
+) -> Result<Json<RoleResponse>, (StatusCode, Json<ErrorResponse>)> {
+    info!(
+        "SET_ROLE_MFA: name={}, required={}, requested_by={}",
+        name, req.required, current_user.username
+    );
+
+    // Get role by name first
+    let role = match state.role_service.get_role(&name).await {
+        Ok(role) => role,
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
+    };
+
+    match state
+        .role_service
+        .set_requires_mfa(&role.id, req.required)
+        .await
+    {
+        Ok(role) => Ok(Json(role.into())),
+        Err(e) => {
+            error!("Failed to set MFA requirement for role {}: {}", name, e);
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
+        }
+    }
+}
+
+/// PUT /admin/roles/{name}/quota - Limit what members write, allowed on system roles
+pub async fn set_role_quota(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Path(name): Path<String>,
+    Json(quota): Json<Quota>,
+) -> Result<Json<RoleResponse>, (StatusCode, Json<ErrorResponse>)> {
+    update_role_quota(&state, &current_user, &name, Some(quota)).await
+}
+
+/// DELETE /admin/roles/{name}/quota - Lift the role's limits
+pub async fn clear_role_quota(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Path(name): Path<String>,
+) -> Result<Json<RoleResponse>, (StatusCode, Json<ErrorResponse>)> {
+    update_role_quota(&state, &current_user, &name, None).await
+}
+
+async fn update_role_quota(
+    state: &AppState,
+    current_user: &User,
+    name: &str,
+    quota: Option<Quota>,
+) -> Result<Json<RoleResponse>, (StatusCode, Json<ErrorResponse>)> {
+    info!(
+        "SET_ROLE_QUOTA: name={}, quota={:?}, requested_by={}",
+        name, quota, current_user.username
+    );
+
+    // Get role by name first
+    let role = match state.role_service.get_role(name).await {
+        Ok(role) => role,
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
+    };
+
+    match state.role_service.set_quota(&role.id, quota).await {
+        Ok(role) => Ok(Json(role.into())),
+        Err(e) => {
+            error!("Failed to set quota for role {}: {}", name, e);
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
         }
     }
 }
@@ -0,0 +1,46 @@
+use crate::api::{CreateScheduleRequest, ListSchedulesResponse};
+use crate::handlers::cache::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use carbon::domain::Schedule;
+use tracing::info;
+
+/// GET /admin/schedules - Every schedule with its next run and the outcome of its last one
+pub async fn list_schedules(State(state): State<AppState>) -> Json<ListSchedulesResponse> {
+    Json(ListSchedulesResponse {
+        schedules: state.cache_manager.schedules(),
+    })
+}
+
+/// POST /admin/schedules - Run a clear, snapshot or warm-up of a cache whenever a cron
+/// expression matches, snapshots and warm-ups only where the server allows them
+pub async fn create_schedule(
+    State(state): State<AppState>,
+    Json(req): Json<CreateScheduleRequest>,
+) -> Result<(StatusCode, Json<Schedule>), ApiError> {
+    info!("CREATE_SCHEDULE: cache={}, cron={}", req.cache, req.cron);
+
+    state.schedule_policy.check(&req.action)?;
+    let schedule = state
+        .cache_manager
+        .add_schedule(&req.cache, &req.cron, req.action)
+        .await?;
+    Ok((StatusCode::CREATED, Json(schedule)))
+}
+
+/// DELETE /admin/schedules/{id} - Stop a schedule, a run in progress is left to finish
+pub async fn remove_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    info!("REMOVE_SCHEDULE: id={}", id);
+
+    match state.cache_manager.remove_schedule(&id).await? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(StatusCode::NOT_FOUND.into()),
+    }
+}
@@ -0,0 +1,96 @@
+use crate::api::{
+    CreateApiKeyRequest, CreateApiKeyResponse, CreateServiceAccountRequest, ErrorResponse,
+    UserResponse,
+};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use carbon::auth::{AuthError, User};
+use tracing::{error, info};
+
+/// POST /admin/service-accounts - Create an account for automation, which signs in with API
+/// keys only
+pub async fn create_service_account(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Json(req): Json<CreateServiceAccountRequest>,
+) -> Result<(StatusCode, Json<UserResponse>), (StatusCode, Json<ErrorResponse>)> {
+    info!(
+        "CREATE_SERVICE_ACCOUNT: username={}, requested_by={}",
+        req.username, current_user.username
+    );
+
+    match state
+        .user_service
+        .create_service_account(req.username, req.role_ids)
+        .await
+    {
+        Ok(user) => Ok((StatusCode::CREATED, Json(user.into()))),
+        Err(e) => {
+            error!("Failed to create service account: {}", e);
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
+        }
+    }
+}
+
+/// POST /admin/service-accounts/{username}/keys - Issue an API key, its secret is only shown
+/// in this response
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Path(username): Path<String>,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreateApiKeyResponse>), (StatusCode, Json<ErrorResponse>)> {
+    info!(
+        "CREATE_API_KEY: username={}, name={}, requested_by={}",
+        username, req.name, current_user.username
+    );
+
+    match state
+        .user_service
+        .create_api_key(&username, req.name, req.caches, req.permissions)
+        .await
+    {
+        Ok((key, api_key)) => Ok((
+            StatusCode::CREATED,
+            Json(CreateApiKeyResponse {
+                key,
+                api_key: api_key.into(),
+            }),
+        )),
+        Err(e) => {
+            error!("Failed to create API key for {}: {}", username, e);
+            Err((status_for(&e), Json(ErrorResponse::from(&e))))
+        }
+    }
+}
+
+/// DELETE /admin/service-accounts/{username}/keys/{key_id} - Revoke an API key
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Path((username, key_id)): Path<(String, String)>,
+) -> Result<Json<UserResponse>, (StatusCode, Json<ErrorResponse>)> {
+    info!(
+        "REVOKE_API_KEY: username={}, key_id={}, requested_by={}",
+        username, key_id, current_user.username
+    );
+
+    match state.user_service.revoke_api_key(&username, &key_id).await {
+        Ok(user) => Ok(Json(user.into())),
+        Err(e) => {
+            error!("Failed to revoke API key {} of {}: {}", key_id, username, e);
+            Err((status_for(&e), Json(ErrorResponse::from(&e))))
+        }
+    }
+}
+
+fn status_for(error: &AuthError) -> StatusCode {
+    match error {
+        AuthError::UserNotFound | AuthError::ApiKeyNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
@@ -0,0 +1,48 @@
+use crate::api::{ErrorResponse, SlowLogResetResponse, SlowLogResponse};
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use carbon::auth::User;
+use carbon::planes::data::SlowLog;
+use std::sync::Arc;
+use tracing::info;
+
+/// GET /admin/slowlog - Recent slow operations, newest first
+pub async fn get_slow_log(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<SlowLogResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let slow_log = slow_log(&state)?;
+
+    info!("SLOW_LOG: requested_by={}", current_user.username);
+
+    Ok(Json(SlowLogResponse {
+        threshold_ms: slow_log.threshold().as_millis() as u64,
+        max_len: slow_log.max_len(),
+        entries: slow_log.entries(),
+    }))
+}
+
+/// DELETE /admin/slowlog - Clear the slow log
+pub async fn reset_slow_log(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+) -> Result<Json<SlowLogResetResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let slow_log = slow_log(&state)?;
+
+    info!("SLOW_LOG_RESET: requested_by={}", current_user.username);
+
+    Ok(Json(SlowLogResetResponse {
+        cleared: slow_log.reset(),
+    }))
+}
+
+fn slow_log(state: &AppState) -> Result<Arc<SlowLog>, (StatusCode, Json<ErrorResponse>)> {
+    state.slow_log.clone().ok_or_else(|| {
+        (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(ErrorResponse::new(
+                "Slow log is not enabled, set CARBON_SLOW_LOG_THRESHOLD_MS",
+            )),
+        )
+    })
+}
@@ -1,6 +1,6 @@
 use crate::api::{
     AssignRolesRequest, ChangePasswordRequest, CreateUserRequest, ErrorResponse, ListUsersResponse,
-    ResetPasswordRequest, UserResponse,
+    ResetPasswordRequest, SetMfaRequiredRequest, UserResponse,
 };
 use crate::middleware::check_permission;
 use crate::state::AppState;
@@ -10,6 +10,7 @@ use axum::{
     Extension, Json,
 };
 use carbon::auth::{Permission, User};
+use carbon::domain::Quota;
 use tracing::{error, info};
 
 /// POST /admin/users - Create a new user
@@ -18,13 +19,6 @@ pub async fn create_user(
     Extension(current_user): Extension<User>,
     Json(req): Json<CreateUserRequest>,
 ) -> Result<(StatusCode, Json<UserResponse>), (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has ManageUsers permission
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::ManageUsers).await
-    {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
-    }
-
     info!(
         "CREATE_USER: username={}, requested_by={}",
         req.username, current_user.username
@@ -38,10 +32,7 @@ pub async fn create_user(
         Ok(user) => Ok((StatusCode::CREATED, Json(user.into()))),
         Err(e) => {
             error!("Failed to create user: {}", e);
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
         }
     }
 }
@@ -49,27 +40,21 @@ pub async fn create_user(
 /// GET /admin/users - List all users
 pub async fn list_users(
     State(state): State<AppState>,
-    Extension(current_user): Extension<User>,
 ) -> Result<Json<ListUsersResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has ManageUsers permission
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::ManageUsers).await
-    {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
-    }
-
     match state.user_service.list_users().await {
         Ok(users) => {
-            let user_responses = users.into_iter().map(|u| u.into()).collect();
+            let (service_accounts, users): (Vec<_>, Vec<_>) =
+                users.into_iter().partition(User::is_service_account);
             Ok(Json(ListUsersResponse {
-                users: user_responses,
+                users: users.into_iter().map(|u| u.into()).collect(),
+                service_accounts: service_accounts.into_iter().map(|u| u.into()).collect(),
             }))
         }
         Err(e) => {
             error!("Failed to list users: {}", e);
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(e.to_string())),
+                Json(ErrorResponse::from(&e)),
             ))
         }
     }
@@ -78,24 +63,13 @@ pub async fn list_users(
 /// GET /admin/users/{username} - Get user by username
 pub async fn get_user(
     State(state): State<AppState>,
-    Extension(current_user): Extension<User>,
     Path(username): Path<String>,
 ) -> Result<Json<UserResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has ManageUsers permission
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::ManageUsers).await
-    {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
-    }
-
     match state.user_service.get_user(&username).await {
         Ok(user) => Ok(Json(user.into())),
         Err(e) => {
             error!("Failed to get user {}: {}", username, e);
-            Err((
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e))))
         }
     }
 }
@@ -107,13 +81,6 @@ pub async fn assign_roles(
     Path(username): Path<String>,
     Json(req): Json<AssignRolesRequest>,
 ) -> Result<Json<UserResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has ManageUsers permission
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::ManageUsers).await
-    {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
-    }
-
     info!(
         "ASSIGN_ROLES: username={}, requested_by={}",
         username, current_user.username
@@ -122,12 +89,7 @@ pub async fn assign_roles(
     // Get user by username first
     let user = match state.user_service.get_user(&username).await {
         Ok(user) => user,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
-        }
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
     };
 
     match state
@@ -138,10 +100,7 @@ pub async fn assign_roles(
         Ok(user) => Ok(Json(user.into())),
         Err(e) => {
             error!("Failed to assign roles to {}: {}", username, e);
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
         }
     }
 }
@@ -175,12 +134,7 @@ pub async fn change_password(
     // Get user by username first
     let user = match state.user_service.get_user(&username).await {
         Ok(user) => user,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
-        }
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
     };
 
     match state
@@ -188,13 +142,13 @@ pub async fn change_password(
         .change_password(&user.id, &req.old_password, &req.new_password)
         .await
     {
-        Ok(user) => Ok(Json(user.into())),
+        Ok(user) => {
+            state.rejected_credentials.forget_user(&user.username);
+            Ok(Json(user.into()))
+        }
         Err(e) => {
             error!("Failed to change password for {}: {}", username, e);
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
         }
     }
 }
@@ -206,13 +160,6 @@ pub async fn reset_password(
     Path(username): Path<String>,
     Json(req): Json<ResetPasswordRequest>,
 ) -> Result<Json<UserResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has ManageUsers permission
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::ManageUsers).await
-    {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
-    }
-
     info!(
         "RESET_PASSWORD: username={}, requested_by={}",
         username, current_user.username
@@ -221,12 +168,7 @@ pub async fn reset_password(
     // Get user by username first
     let user = match state.user_service.get_user(&username).await {
         Ok(user) => user,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
-        }
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
     };
 
     match state
@@ -234,44 +176,134 @@ pub async fn reset_password(
         .reset_password(&user.id, req.new_password)
         .await
     {
-        Ok(user) => Ok(Json(user.into())),
+        Ok(user) => {
+            state.rejected_credentials.forget_user(&user.username);
+            Ok(Json(user.into()))
+        }
         Err(e) => {
             error!("Failed to reset password for {}: {}", username, e);
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
         }
     }
 }
 
-/// DELETE /admin/users/{username} - Delete user
-pub async fn delete_user(
+/// PUT /admin/users/{username}/mfa - Require the user to sign in with a second factor
+pub async fn set_user_mfa_required(
     State(state): State<AppState>,
     Extension(current_user): Extension<User>,
     Path(username): Path<String>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
-    // Check if current user has ManageUsers permission
-    if let Err(e) =
-        check_permission(&state.auth_service, &current_user, Permission::ManageUsers).await
+    Json(req): Json<SetMfaRequiredRequest>,
+) -> Result<Json<UserResponse>, (StatusCode, Json<ErrorResponse>)> {
+    info!(
+        "SET_USER_MFA: username={}, required={}, requested_by={}",
+        username, req.required, current_user.username
+    );
+
+    // Get user by username first
+    let user = match state.user_service.get_user(&username).await {
+        Ok(user) => user,
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
+    };
+
+    match state
+        .user_service
+        .set_mfa_required(&user.id, req.required)
+        .await
     {
-        return Err((e, Json(ErrorResponse::new("Insufficient permissions"))));
+        Ok(user) => Ok(Json(user.into())),
+        Err(e) => {
+            error!("Failed to set MFA requirement for {}: {}", username, e);
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
+        }
     }
+}
 
+/// DELETE /admin/users/{username}/mfa - Reset MFA, e.g. for a lost device (admin only)
+pub async fn reset_user_mfa(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Path(username): Path<String>,
+) -> Result<Json<UserResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!(
-        "DELETE_USER: username={}, requested_by={}",
+        "RESET_USER_MFA: username={}, requested_by={}",
         username, current_user.username
     );
 
     // Get user by username first
     let user = match state.user_service.get_user(&username).await {
         Ok(user) => user,
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
+    };
+
+    match state.user_service.reset_mfa(&user.id).await {
+        Ok(user) => Ok(Json(user.into())),
         Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            error!("Failed to reset MFA for {}: {}", username, e);
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
         }
+    }
+}
+
+/// PUT /admin/users/{username}/quota - Limit what the user writes, in place of their roles' quotas
+pub async fn set_user_quota(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Path(username): Path<String>,
+    Json(quota): Json<Quota>,
+) -> Result<Json<UserResponse>, (StatusCode, Json<ErrorResponse>)> {
+    update_user_quota(&state, &current_user, &username, Some(quota)).await
+}
+
+/// DELETE /admin/users/{username}/quota - Hold the user to their roles' quotas again
+pub async fn clear_user_quota(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Path(username): Path<String>,
+) -> Result<Json<UserResponse>, (StatusCode, Json<ErrorResponse>)> {
+    update_user_quota(&state, &current_user, &username, None).await
+}
+
+async fn update_user_quota(
+    state: &AppState,
+    current_user: &User,
+    username: &str,
+    quota: Option<Quota>,
+) -> Result<Json<UserResponse>, (StatusCode, Json<ErrorResponse>)> {
+    info!(
+        "SET_USER_QUOTA: username={}, quota={:?}, requested_by={}",
+        username, quota, current_user.username
+    );
+
+    // Get user by username first
+    let user = match state.user_service.get_user(username).await {
+        Ok(user) => user,
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
+    };
+
+    match state.user_service.set_quota(&user.id, quota).await {
+        Ok(user) => Ok(Json(user.into())),
+        Err(e) => {
+            error!("Failed to set quota for {}: {}", username, e);
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
+        }
+    }
+}
+
+/// DELETE /admin/users/{username} - Delete user
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Extension(current_user): Extension<User>,
+    Path(username): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    info!(
+        "DELETE_USER: username={}, requested_by={}",
+        username, current_user.username
+    );
+
+    // Get user by username first
+    let user = match state.user_service.get_user(&username).await {
+        Ok(user) => user,
+        Err(e) => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e)))),
     };
 
     match state
@@ -279,13 +311,14 @@ pub async fn delete_user(
         .delete_user(&user.id, &current_user.id)
         .await
     {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Ok(_) => {
+            // A user created again under the name starts with a clean slate
+            state.rejected_credentials.forget_user(&user.username);
+            Ok(StatusCode::NO_CONTENT)
+        }
         Err(e) => {
             error!("Failed to delete user {}: {}", username, e);
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new(e.to_string())),
-            ))
+            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::from(&e))))
         }
     }
 }
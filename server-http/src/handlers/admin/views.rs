@@ -0,0 +1,51 @@
+use crate::api::ListViewsResponse;
+use crate::handlers::cache::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use carbon::domain::MaterializedView;
+use tracing::{info, warn};
+
+/// GET /admin/views - Every materialized view with its source and transform
+pub async fn list_views(State(state): State<AppState>) -> Json<ListViewsResponse> {
+    Json(ListViewsResponse {
+        views: state.cache_manager.views(),
+    })
+}
+
+/// POST /admin/views - Derive an existing cache from another one, the view is filled from
+/// the source in the background and kept up to date from then on
+pub async fn create_view(
+    State(state): State<AppState>,
+    Json(req): Json<MaterializedView>,
+) -> Result<(StatusCode, Json<MaterializedView>), ApiError> {
+    info!("CREATE_VIEW: cache={}, source={}", req.cache, req.source);
+
+    let view = state.cache_manager.add_view(req).await?;
+
+    let cache_operations = state.cache_operations.clone();
+    let rebuilt = view.clone();
+    tokio::spawn(async move {
+        match cache_operations.rebuild_view(&rebuilt).await {
+            Ok(written) => info!("View '{}' built with {} entries", rebuilt.cache, written),
+            Err(e) => warn!("Failed to build view '{}': {}", rebuilt.cache, e),
+        }
+    });
+    Ok((StatusCode::CREATED, Json(view)))
+}
+
+/// DELETE /admin/views/{cache} - Stop maintaining a view, its cache and entries are kept
+pub async fn remove_view(
+    State(state): State<AppState>,
+    Path(cache): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    info!("REMOVE_VIEW: cache={}", cache);
+
+    match state.cache_manager.remove_view(&cache).await? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(StatusCode::NOT_FOUND.into()),
+    }
+}
@@ -1,11 +1,15 @@
+use crate::middleware::{ClientIp, MFA_CODE_HEADER};
+use crate::oidc::OidcClient;
 use axum::{
     extract::State,
     http::{header, StatusCode},
     response::{IntoResponse, Json},
+    Extension,
 };
 use base64::{engine::general_purpose::STANDARD, Engine};
-use carbon::auth::{AuthService, MokaSessionRepository, SessionStore};
+use carbon::auth::{AuthError, AuthService, MokaSessionRepository, SessionStore, User};
 use serde::{Deserialize, Serialize};
+use shared::ErrorCode;
 use std::sync::Arc;
 
 /// Request body for login endpoint
@@ -13,6 +17,18 @@ use std::sync::Arc;
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
+    /// TOTP or recovery code, for users enrolled in MFA
+    #[serde(default)]
+    pub code: Option<String>,
+    /// Also return a refresh token for POST /auth/refresh
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// Request body for refresh endpoint
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
 }
 
 /// Response body for successful login
@@ -24,6 +40,12 @@ pub struct LoginResponse {
     pub expires_in: u64,
     /// Username of the authenticated user
     pub username: String,
+    /// Single-use token trading for a new session, when asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Refresh token expiration time in seconds from now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_expires_in: Option<u64>,
 }
 
 /// Response body for logout
@@ -38,6 +60,7 @@ pub struct LogoutResponse {
 pub struct AuthHandlerState {
     pub auth_service: Arc<AuthService>,
     pub session_store: Arc<SessionStore<MokaSessionRepository>>,
+    pub oidc: Option<Arc<OidcClient>>,
 }
 
 /// POST /auth/login
@@ -48,24 +71,33 @@ pub struct AuthHandlerState {
 /// 1. JSON body: {"username": "admin", "password": "admin123"}
 /// 2. Basic Auth header: Authorization: Basic base64(username:password)
 ///
+/// Users enrolled in MFA add "code" to the JSON body, or the X-Carbon-OTP header.
+/// Adding "refresh": true to the JSON body also returns a refresh token.
+///
 /// Returns a session token that can be used for logout.
 /// Note: Regular API calls don't need to use this - they can just use Basic Auth
 /// and sessions will be managed automatically.
 pub async fn login(
     State(state): State<AuthHandlerState>,
+    client: Option<Extension<ClientIp>>,
     headers: axum::http::HeaderMap,
     body: Result<Json<LoginRequest>, axum::extract::rejection::JsonRejection>,
 ) -> Result<Json<LoginResponse>, impl IntoResponse> {
     // Try to extract credentials from either JSON body or Basic Auth header
-    let (username, password) = match body {
+    let (username, password, code, refresh) = match body {
         // Use JSON body credentials if available
-        Ok(Json(login_req)) => (login_req.username, login_req.password),
+        Ok(Json(login_req)) => (
+            login_req.username,
+            login_req.password,
+            login_req.code,
+            login_req.refresh,
+        ),
         // If no valid JSON body, try Basic Auth header
         Err(_) => {
             if let Some(auth_header) = headers.get(header::AUTHORIZATION) {
                 if let Ok(auth_str) = auth_header.to_str() {
                     if let Some((user, pass)) = extract_basic_auth(auth_str) {
-                        (user, pass)
+                        (user, pass, mfa_code(&headers), false)
                     } else {
                         return Err((
                             StatusCode::BAD_REQUEST,
@@ -93,22 +125,41 @@ pub async fn login(
         }
     };
 
-    // Extract client IP address from headers only
-    let client_ip = headers
-        .get("X-Forwarded-For")
-        .and_then(|h| h.to_str().ok())
-        .and_then(|s| s.split(',').next())
-        .map(|s| s.trim().to_string())
-        .or_else(|| {
-            headers
-                .get("X-Real-IP")
-                .and_then(|h| h.to_str().ok())
-                .map(|s| s.to_string())
-        });
-
-    // Authenticate user with Argon2 verification
-    let user = match state.auth_service.authenticate(&username, &password).await {
+    let client_ip = client_ip(client);
+
+    // Authenticate user with Argon2 verification, and the second factor if enrolled
+    let user = match state
+        .auth_service
+        .authenticate_with_code(&username, &password, code.as_deref())
+        .await
+    {
         Ok(user) => user,
+        Err(AuthError::MfaRequired) => {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({
+                    "error": "MFA code required",
+                    "mfa_required": true
+                })),
+            ));
+        }
+        Err(AuthError::MfaEnrollmentRequired) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({
+                    "error": "MFA enrollment required, see POST /auth/mfa/enroll",
+                    "mfa_enrollment_required": true
+                })),
+            ));
+        }
+        Err(AuthError::MfaLockedOut) => {
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(serde_json::json!({
+                    "error": "Too many invalid MFA codes, try again later"
+                })),
+            ));
+        }
         Err(_) => {
             return Err((
                 StatusCode::UNAUTHORIZED,
@@ -119,10 +170,11 @@ pub async fn login(
         }
     };
 
-    // Create session (1 hour TTL = 3600000 ms)
+    // Create session with the configured TTL
+    let ttl_ms = state.session_store.ttl_ms();
     let session = match state
         .session_store
-        .create_session(user.clone(), 3600000, client_ip)
+        .create_session(user.clone(), ttl_ms, client_ip)
         .await
     {
         Ok(session) => session,
@@ -136,11 +188,89 @@ pub async fn login(
         }
     };
 
+    let refresh_token = if refresh {
+        match state.session_store.create_refresh_token(&session).await {
+            Ok(refresh_token) => Some(refresh_token),
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({
+                        "error": "Failed to create refresh token"
+                    })),
+                ));
+            }
+        }
+    } else {
+        None
+    };
+
     // Return session token
     Ok(Json(LoginResponse {
         token: session.token,
-        expires_in: 3600, // 1 hour in seconds
+        expires_in: ttl_ms / 1000,
         username: user.username,
+        refresh_expires_in: refresh_token
+            .as_ref()
+            .map(|refresh_token| refresh_token.remaining_ttl_ms() / 1000),
+        refresh_token: refresh_token.map(|refresh_token| refresh_token.token),
+    }))
+}
+
+/// POST /auth/refresh
+///
+/// Trade a refresh token from login for a new session token and refresh token.
+///
+/// The session the refresh token was issued with ends, and the refresh token
+/// cannot be used again.
+///
+/// The new session gets the user as stored now. Refreshing fails once the user
+/// is deleted or their second factor changes, and the refresh tokens of a login
+/// all expire the refresh TTL after it.
+pub async fn refresh(
+    State(state): State<AuthHandlerState>,
+    client: Option<Extension<ClientIp>>,
+    Json(body): Json<RefreshRequest>,
+) -> Result<Json<LoginResponse>, impl IntoResponse> {
+    let auth_service = state.auth_service.clone();
+    let reload = |user: User| async move {
+        auth_service
+            .refreshed_user(&user)
+            .await
+            .map_err(|e| match e.code() {
+                ErrorCode::Internal => shared::Error::Internal(e.to_string()),
+                _ => shared::Error::NotFound,
+            })
+    };
+    let (session, refresh_token) = match state
+        .session_store
+        .refresh_session(&body.refresh_token, client_ip(client), reload)
+        .await
+    {
+        Ok(refreshed) => refreshed,
+        Err(shared::Error::NotFound) => {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({
+                    "error": "Invalid or expired refresh token"
+                })),
+            ));
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to refresh session"
+                })),
+            ));
+        }
+    };
+
+    Ok(Json(LoginResponse {
+        token: session.token,
+        expires_in: session.remaining_ttl_ms() / 1000,
+        username: session.user.username,
+        refresh_token: Some(refresh_token.token),
+        refresh_expires_in: Some(refresh_token.remaining_ttl_ms() / 1000),
     }))
 }
 
@@ -197,6 +327,74 @@ pub async fn logout(
     }
 }
 
+/// POST /auth/logout-all
+///
+/// Invalidate every session and refresh token of the user owning the session token,
+/// e.g. after losing a device.
+///
+/// Accepts the session token in the Authorization header as Bearer token:
+/// Authorization: Bearer <token>
+pub async fn logout_all(
+    State(state): State<AuthHandlerState>,
+    headers: axum::http::HeaderMap,
+) -> Result<Json<LogoutResponse>, impl IntoResponse> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(extract_bearer_token)
+        .ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({
+                    "error": "Missing Authorization header. Expected: Bearer <token>"
+                })),
+            )
+        })?;
+
+    let user = state
+        .session_store
+        .validate_session(&token)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({
+                    "error": "Invalid or expired session token"
+                })),
+            )
+        })?;
+
+    match state
+        .session_store
+        .invalidate_user_sessions(&user.username)
+        .await
+    {
+        Ok(count) => Ok(Json(LogoutResponse {
+            message: format!("Logged out {} session(s)", count),
+        })),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": "Failed to logout sessions"
+            })),
+        )),
+    }
+}
+
+/// Second factor sent in the `X-Carbon-OTP` header
+pub(crate) fn mfa_code(headers: &axum::http::HeaderMap) -> Option<String> {
+    headers
+        .get(MFA_CODE_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+/// Client address resolved by the client IP middleware, forwarding headers are only believed
+/// from trusted proxies
+pub(crate) fn client_ip(client: Option<Extension<ClientIp>>) -> Option<String> {
+    client.map(|Extension(ClientIp(ip))| ip.to_string())
+}
+
 /// Extract Basic Auth credentials from Authorization header
 fn extract_basic_auth(auth_header: &str) -> Option<(String, String)> {
     // Authorization: Basic <base64>
@@ -244,6 +442,140 @@ mod tests {
         assert_eq!(password, "password123");
     }
 
+    #[tokio::test]
+    async fn test_refresh_reloads_the_user() {
+        use carbon::auth::defaults::create_user_role;
+        use carbon::auth::{
+            generate_session_token, RoleRepository, SledRoleRepository, SledUserRepository,
+            UserRepository, UserService,
+        };
+
+        let dir = std::env::temp_dir().join(format!("carbon-refresh-{}", generate_session_token()));
+        let user_repo = Arc::new(SledUserRepository::new(dir.join("users.sled")).unwrap())
+            as Arc<dyn UserRepository>;
+        let role_repo = Arc::new(SledRoleRepository::new(dir.join("roles.sled")).unwrap())
+            as Arc<dyn RoleRepository>;
+        let sessions = Arc::new(MokaSessionRepository::new(None, None));
+        let session_store = Arc::new(SessionStore::new(sessions.clone()));
+        let users = UserService::new(user_repo.clone(), role_repo.clone()).with_sessions(sessions);
+        let state = AuthHandlerState {
+            auth_service: Arc::new(AuthService::new(user_repo.clone(), role_repo.clone())),
+            session_store: session_store.clone(),
+            oidc: None,
+        };
+
+        let role = role_repo.create(create_user_role()).await.unwrap();
+        let user = users
+            .create_user(
+                "alice".to_string(),
+                "password123".to_string(),
+                vec![role.id],
+            )
+            .await
+            .unwrap();
+        let refresh_with = |token: String| {
+            refresh(
+                State(state.clone()),
+                None,
+                Json(RefreshRequest {
+                    refresh_token: token,
+                }),
+            )
+        };
+        let status = |result: Result<Json<LoginResponse>, _>| match result {
+            Ok(_) => StatusCode::OK,
+            Err(e) => e.into_response().status(),
+        };
+
+        let session = session_store
+            .create_session(user.clone(), 60_000, None)
+            .await
+            .unwrap();
+        let first = session_store.create_refresh_token(&session).await.unwrap();
+        let Ok(Json(refreshed)) = refresh_with(first.token.clone()).await else {
+            panic!("refresh failed");
+        };
+        assert_eq!(refreshed.username, "alice");
+        assert_eq!(
+            status(refresh_with(first.token).await),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // A second factor required since sign-in has to be given by signing in again
+        users.set_mfa_required(&user.id, true).await.unwrap();
+        let second = refreshed.refresh_token.unwrap();
+        assert_eq!(status(refresh_with(second).await), StatusCode::UNAUTHORIZED);
+
+        // So does a user deleted since
+        users.set_mfa_required(&user.id, false).await.unwrap();
+        let session = session_store
+            .create_session(user.clone(), 60_000, None)
+            .await
+            .unwrap();
+        let third = session_store.create_refresh_token(&session).await.unwrap();
+        user_repo.delete(&user.id).await.unwrap();
+        assert_eq!(
+            status(refresh_with(third.token).await),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_after_logout_is_refused() {
+        use carbon::auth::{
+            generate_session_token, RoleRepository, SledRoleRepository, SledUserRepository,
+            UserRepository,
+        };
+
+        let dir = std::env::temp_dir().join(format!("carbon-logout-{}", generate_session_token()));
+        let user_repo = Arc::new(SledUserRepository::new(dir.join("users.sled")).unwrap())
+            as Arc<dyn UserRepository>;
+        let role_repo = Arc::new(SledRoleRepository::new(dir.join("roles.sled")).unwrap())
+            as Arc<dyn RoleRepository>;
+        let session_store = Arc::new(SessionStore::new(Arc::new(MokaSessionRepository::new(
+            None, None,
+        ))));
+        let state = AuthHandlerState {
+            auth_service: Arc::new(AuthService::new(user_repo.clone(), role_repo)),
+            session_store: session_store.clone(),
+            oidc: None,
+        };
+
+        let user = user_repo
+            .create(User::new("alice".to_string(), "hash".to_string(), vec![]))
+            .await
+            .unwrap();
+        let session = session_store
+            .create_session(user, 60_000, None)
+            .await
+            .unwrap();
+        let refresh_token = session_store.create_refresh_token(&session).await.unwrap();
+
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Bearer {}", session.token).parse().unwrap(),
+        );
+        assert!(logout(State(state.clone()), headers).await.is_ok());
+
+        let refreshed = refresh(
+            State(state),
+            None,
+            Json(RefreshRequest {
+                refresh_token: refresh_token.token,
+            }),
+        )
+        .await;
+        match refreshed {
+            Ok(_) => panic!("refreshed a logged out session"),
+            Err(e) => assert_eq!(e.into_response().status(), StatusCode::UNAUTHORIZED),
+        }
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn test_extract_bearer_token() {
         let header = "Bearer abc123def456";
@@ -1,3 +1,96 @@
 pub mod basic;
+pub mod bulk;
+pub mod changes;
 pub mod events;
+pub mod hash;
 pub mod health;
+pub mod json;
+pub mod lock;
+pub mod pin;
+pub mod probabilistic;
+pub mod query;
+pub mod set;
+pub mod sorted_set;
+pub mod time_series;
+
+use crate::api::{ErrorResponse, ValueEncoding};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use shared::ErrorCode;
+
+/// Map a data plane error to the HTTP status returned by the cache handlers
+pub(crate) fn error_status(error: &shared::Error) -> StatusCode {
+    match error {
+        shared::Error::NotFound | shared::Error::CacheNotFound(_) => StatusCode::NOT_FOUND,
+        shared::Error::InvalidValue(_) => StatusCode::BAD_REQUEST,
+        shared::Error::WrongType(_) => StatusCode::CONFLICT,
+        shared::Error::Throttled(_) => StatusCode::TOO_MANY_REQUESTS,
+        shared::Error::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+        shared::Error::QuotaExceeded(_) => StatusCode::FORBIDDEN,
+        shared::Error::ReadOnly(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Error of a cache handler, sent as an ErrorResponse carrying the error's code
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    body: ErrorResponse,
+}
+
+impl From<shared::Error> for ApiError {
+    fn from(error: shared::Error) -> Self {
+        Self {
+            status: error_status(&error),
+            body: ErrorResponse::from(&error),
+        }
+    }
+}
+
+/// Errors found by the handlers themselves, such as malformed requests
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        let error = status.canonical_reason().unwrap_or("Request failed");
+        Self {
+            status,
+            body: ErrorResponse::new(error).with_code(ErrorCode::from_http_status(status.as_u16())),
+        }
+    }
+}
+
+impl ApiError {
+    /// A write the cache's admission policy turned away, nothing was stored
+    pub(crate) fn not_admitted(message: String) -> Self {
+        Self {
+            status: StatusCode::INSUFFICIENT_STORAGE,
+            body: ErrorResponse::new(message).with_code(ErrorCode::NotAdmitted),
+        }
+    }
+
+    pub(crate) fn message(&self) -> &str {
+        &self.body.error
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body)).into_response()
+    }
+}
+
+/// Bytes of a value sent in a JSON body, 400 when base64 does not decode
+pub(crate) fn decode_value(value: String, encoding: ValueEncoding) -> Result<Bytes, StatusCode> {
+    match encoding {
+        ValueEncoding::Utf8 => Ok(Bytes::from(value)),
+        ValueEncoding::Base64 => STANDARD
+            .decode(value)
+            .map(Bytes::from)
+            .map_err(|_| StatusCode::BAD_REQUEST),
+    }
+}
@@ -1,77 +1,528 @@
-use crate::api::{DeleteResponse, GetResponse, PutRequest, PutResponse};
+use crate::api::{
+    DeleteResponse, GetExRequest, GetResponse, GetValueQuery, InvalidateByTagRequest,
+    InvalidateByTagResponse, PutRequest, PutResponse, ValueEncoding,
+};
+use crate::handlers::cache::{decode_value, ApiError};
 use crate::state::AppState;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use bytes::Bytes;
-use carbon::planes::data::operation::CacheOperations;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::{Bytes, BytesMut};
+use carbon::domain::{entity_tag, EntryMatch, EntryMetadata, ValueFormat};
+use carbon::encoding::transcode;
+use carbon::planes::data::operation::{
+    CacheOperations, ConditionalOperations, ContentTypeOperations, TagOperations,
+};
+use futures::{stream, StreamExt};
+use std::convert::Infallible;
+use std::time::Duration;
 use tracing::info;
 
 /// PUT /cache/:cache_name/:key
+/// A JSON body carries the value and its tags, any other Content-Type stores the body as is
+/// and serves it back with that type
+/// Caches with a value format read the value as JSON, or as MessagePack when the Content-Type
+/// is application/msgpack
+/// Bodies are read as they arrive, so raw values may be sent chunked and up to max_value_bytes
+/// With If-Match the value is only stored while the current entry matches, 412 otherwise
+/// A value the cache's admission policy turns away is answered with 507 and NOT_ADMITTED
 pub async fn put_value(
     State(state): State<AppState>,
     Path((cache_name, key)): Path<(String, String)>,
-    Json(req): Json<PutRequest>,
-) -> Result<Json<PutResponse>, StatusCode> {
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, ApiError> {
     info!("PUT: cache={}, key={}", cache_name, key);
 
-    match state
-        .cache_operations
-        .put(&cache_name, key.into_bytes(), Bytes::from(req.value))
-        .await
-    {
-        Ok(_) => Ok(Json(PutResponse { ok: true })),
-        Err(shared::Error::CacheNotFound(_)) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    let content_type = request_content_type(&headers)?
+        .filter(|content_type| media_type(content_type) != JSON_MEDIA_TYPE);
+    let max_bytes = if content_type.is_some() {
+        state.http_limits.max_value_bytes
+    } else {
+        state.http_limits.max_body_bytes
+    };
+    let body = read_body(body, &headers, max_bytes).await?;
+
+    let (value, tags) = if content_type.is_some() {
+        (body, Vec::new())
+    } else {
+        let req: PutRequest = serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+        if req.tags.iter().any(|tag| tag.is_empty()) {
+            return Err(StatusCode::BAD_REQUEST.into());
+        }
+        (decode_value(req.value, req.encoding)?, req.tags)
+    };
+
+    // Caches with a value format take JSON, or MessagePack sent as such, and store it in their
+    // format without a content type of its own
+    let (value, content_type) = match state.cache_operations.value_format(&cache_name).await? {
+        ValueFormat::Raw => (value, content_type),
+        format => {
+            let sent = match content_type.as_deref().map(media_type).as_deref() {
+                Some(MSGPACK_MEDIA_TYPE) => ValueFormat::MessagePack,
+                _ => ValueFormat::Json,
+            };
+            (transcode(value, sent, format)?, None)
+        }
+    };
+
+    let condition = if_match(&headers)?;
+    let etag = entity_tag(&value);
+    let key = key.into_bytes();
+
+    let result = match (condition, content_type) {
+        (Some(condition), content_type) => {
+            state
+                .cache_operations
+                .put_if(&cache_name, key, value, tags, content_type, &condition)
+                .await
+        }
+        (None, Some(content_type)) => {
+            state
+                .cache_operations
+                .put_with_content_type(&cache_name, key, value, content_type)
+                .await
+        }
+        (None, None) if tags.is_empty() => {
+            state.cache_operations.put(&cache_name, key, value).await
+        }
+        (None, None) => {
+            state
+                .cache_operations
+                .put_tagged(&cache_name, key, value, tags)
+                .await
+        }
+    };
+
+    match result {
+        Ok(result) if !result.admitted => Err(ApiError::not_admitted(result.message)),
+        Ok(_) => Ok(([(header::ETAG, etag)], Json(PutResponse { ok: true })).into_response()),
+        Err(e) => Err(e.into()),
     }
 }
 
 /// GET /cache/:cache_name/:key
+/// Found values carry an ETag, a matching If-None-Match is answered with 304 and no body
+/// Accept naming application/octet-stream or the type the value was stored with returns the
+/// raw bytes, streamed in slices, otherwise the value is wrapped in JSON (base64 with ?encoding=base64 or when
+/// it is not UTF-8)
+/// Caches with a value format answer Accept: application/msgpack with the value as MessagePack
 pub async fn get_value(
     State(state): State<AppState>,
     Path((cache_name, key)): Path<(String, String)>,
-) -> Result<Json<GetResponse>, StatusCode> {
+    Query(query): Query<GetValueQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
     info!("GET: cache={}, key={}", cache_name, key);
 
     let key_bytes = key.into_bytes();
 
     match state.cache_operations.get(&cache_name, &key_bytes).await {
+        Ok(mut result) => {
+            let etag = entity_tag(&result.message);
+            if if_none_match(&headers, &etag) {
+                return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+            }
+
+            // Values of caches with a value format are sent as JSON, or as MessagePack to
+            // clients accepting it
+            let content_type = match state.cache_operations.value_format(&cache_name).await? {
+                ValueFormat::Raw => state.cache_operations.content_type(&cache_name, &key_bytes),
+                format if accepts(&headers, MSGPACK_MEDIA_TYPE) => {
+                    result.message = transcode(result.message, format, ValueFormat::MessagePack)?;
+                    Some(MSGPACK_MEDIA_TYPE.to_string())
+                }
+                format => {
+                    result.message = transcode(result.message, format, ValueFormat::Json)?;
+                    None
+                }
+            };
+            if accepts_raw(&headers, content_type.as_deref()) {
+                let content_type =
+                    content_type.unwrap_or_else(|| OCTET_STREAM_MEDIA_TYPE.to_string());
+                let content_length = result.message.len().to_string();
+                return Ok((
+                    [
+                        (header::CONTENT_TYPE, content_type),
+                        (header::CONTENT_LENGTH, content_length),
+                        (header::ETAG, etag),
+                    ],
+                    value_body(result.message),
+                )
+                    .into_response());
+            }
+
+            let body = get_response(Ok(result), 0, query.encoding)?;
+            Ok(([(header::ETAG, etag)], body).into_response())
+        }
+        Err(e) => get_response(Err(e), 0, query.encoding).map(IntoResponse::into_response),
+    }
+}
+
+/// POST /cache/:cache_name/:key/getdel
+pub async fn getdel_value(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+) -> Result<Json<GetResponse>, ApiError> {
+    info!("GETDEL: cache={}, key={}", cache_name, key);
+
+    let result = state
+        .cache_operations
+        .getdel(&cache_name, &key.into_bytes())
+        .await;
+    get_response(result, 0, None)
+}
+
+/// POST /cache/:cache_name/:key/getex
+pub async fn getex_value(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+    Json(req): Json<GetExRequest>,
+) -> Result<Json<GetResponse>, ApiError> {
+    info!(
+        "GETEX: cache={}, key={}, ttl_ms={:?}",
+        cache_name, key, req.ttl_ms
+    );
+
+    let result = state
+        .cache_operations
+        .getex(
+            &cache_name,
+            &key.into_bytes(),
+            req.ttl_ms.map(Duration::from_millis),
+        )
+        .await;
+    get_response(result, req.ttl_ms.unwrap_or(0), None)
+}
+
+/// Render a read of a single value, a missing key is reported as not found rather than an error
+/// Without an explicit encoding, values that are not UTF-8 are sent base64 encoded
+fn get_response(
+    result: shared::Result<carbon::domain::response::GetResponse<Bytes>>,
+    ttl_ms_remaining: u64,
+    encoding: Option<ValueEncoding>,
+) -> Result<Json<GetResponse>, ApiError> {
+    match result {
         Ok(result) => {
-            let value = String::from_utf8(result.message.to_vec())
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let text = match encoding {
+                Some(ValueEncoding::Base64) => None,
+                _ => String::from_utf8(result.message.to_vec()).ok(),
+            };
+            let (value, encoding) = match text {
+                Some(text) => (text, ValueEncoding::Utf8),
+                None if encoding == Some(ValueEncoding::Utf8) => {
+                    return Err(StatusCode::NOT_ACCEPTABLE.into())
+                }
+                None => (STANDARD.encode(&result.message), ValueEncoding::Base64),
+            };
 
             Ok(Json(GetResponse {
                 found: result.found,
                 value,
-                ttl_ms_remaining: 0,
+                encoding,
+                ttl_ms_remaining,
             }))
         }
         Err(shared::Error::NotFound) => Ok(Json(GetResponse {
             found: false,
             value: String::new(),
+            encoding: ValueEncoding::Utf8,
             ttl_ms_remaining: 0,
         })),
-        Err(shared::Error::CacheNotFound(_)) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// GET /cache/:cache_name/:key/meta
+pub async fn get_metadata(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+) -> Result<Json<EntryMetadata>, ApiError> {
+    info!("META: cache={}, key={}", cache_name, key);
+
+    match state
+        .cache_operations
+        .metadata(&cache_name, &key.into_bytes())
+        .await
+    {
+        Ok(metadata) => Ok(Json(metadata)),
+        Err(e) => Err(e.into()),
     }
 }
 
 /// DELETE /cache/:cache_name/:key
+/// With If-Match the entry is only deleted while it matches, 412 otherwise
 pub async fn delete_value(
     State(state): State<AppState>,
     Path((cache_name, key)): Path<(String, String)>,
-) -> Result<Json<DeleteResponse>, StatusCode> {
+    headers: HeaderMap,
+) -> Result<Json<DeleteResponse>, ApiError> {
     info!("DELETE: cache={}, key={}", cache_name, key);
 
     let key_bytes = key.into_bytes();
 
-    match state.cache_operations.delete(&cache_name, &key_bytes).await {
+    let result = match if_match(&headers)? {
+        Some(condition) => {
+            state
+                .cache_operations
+                .delete_if(&cache_name, &key_bytes, &condition)
+                .await
+        }
+        None => state.cache_operations.delete(&cache_name, &key_bytes).await,
+    };
+
+    match result {
         Ok(result) => Ok(Json(DeleteResponse {
             deleted: result.deleted,
         })),
-        Err(shared::Error::CacheNotFound(_)) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// POST /cache/:cache_name/invalidate-by-tag
+pub async fn invalidate_by_tag(
+    State(state): State<AppState>,
+    Path(cache_name): Path<String>,
+    Json(req): Json<InvalidateByTagRequest>,
+) -> Result<Json<InvalidateByTagResponse>, ApiError> {
+    info!("INVALIDATE_BY_TAG: cache={}, tag={}", cache_name, req.tag);
+
+    match state
+        .cache_operations
+        .invalidate_tag(&cache_name, &req.tag)
+        .await
+    {
+        Ok(invalidated) => Ok(Json(InvalidateByTagResponse { invalidated })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+const JSON_MEDIA_TYPE: &str = "application/json";
+const OCTET_STREAM_MEDIA_TYPE: &str = "application/octet-stream";
+const MSGPACK_MEDIA_TYPE: &str = "application/msgpack";
+const VALUE_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Most of a request body's Content-Length allocated up front, the rest grows as data arrives
+/// so a client announcing a large body cannot reserve memory it never sends
+const MAX_PREALLOCATED_BYTES: usize = 64 * 1024;
+
+/// Collect a request body as its chunks arrive, 413 once more than `max_bytes` has been
+/// received or when Content-Length announces more
+async fn read_body(body: Body, headers: &HeaderMap, max_bytes: usize) -> Result<Bytes, StatusCode> {
+    let expected_bytes = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or_default();
+    if expected_bytes > max_bytes {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let mut buffer = BytesMut::with_capacity(expected_bytes.min(MAX_PREALLOCATED_BYTES));
+
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        if buffer.len() + chunk.len() > max_bytes {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+    Ok(buffer.freeze())
+}
+
+/// Response body sending a value in slices of the stored buffer, written out as the client
+/// reads them without copying the value
+fn value_body(value: Bytes) -> Body {
+    let len = value.len();
+    let chunks = (0..len).step_by(VALUE_CHUNK_BYTES).map(move |start| {
+        Ok::<_, Infallible>(value.slice(start..(start + VALUE_CHUNK_BYTES).min(len)))
+    });
+    Body::from_stream(stream::iter(chunks))
+}
+
+/// Content-Type of the request body, None when the client did not send one
+fn request_content_type(headers: &HeaderMap) -> Result<Option<String>, StatusCode> {
+    headers
+        .get(header::CONTENT_TYPE)
+        .map(|value| {
+            value
+                .to_str()
+                .map(|value| value.trim().to_string())
+                .map_err(|_| StatusCode::BAD_REQUEST)
+        })
+        .transpose()
+}
+
+/// Media type without parameters, lowercased for comparison
+fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Whether Accept asks for the raw value rather than the JSON wrapper
+fn accepts_raw(headers: &HeaderMap, content_type: Option<&str>) -> bool {
+    let Some(accept) = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let stored = content_type.map(media_type);
+
+    accept.split(',').map(media_type).any(|accepted| {
+        accepted == OCTET_STREAM_MEDIA_TYPE
+            || (accepted != JSON_MEDIA_TYPE && Some(&accepted) == stored.as_ref())
+    })
+}
+
+/// Whether Accept lists the media type `wanted`
+fn accepts(headers: &HeaderMap, wanted: &str) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| {
+            accept
+                .split(',')
+                .map(media_type)
+                .any(|accepted| accepted == wanted)
+        })
+}
+
+/// Parse If-Match into a condition on the current entry, `*` matches any existing entry
+fn if_match(headers: &HeaderMap) -> Result<Option<EntryMatch>, StatusCode> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+    let value = value.to_str().map_err(|_| StatusCode::BAD_REQUEST)?.trim();
+
+    if value == "*" {
+        return Ok(Some(EntryMatch::Any));
+    }
+
+    let tags: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect();
+    if tags.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(Some(EntryMatch::Tags(tags)))
+}
+
+/// Whether If-None-Match names `etag`, compared weakly as RFC 9110 asks for GET
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(value) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    value
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_if_match() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(if_match(&headers), Ok(None));
+
+        headers.insert(header::IF_MATCH, HeaderValue::from_static("*"));
+        assert_eq!(if_match(&headers), Ok(Some(EntryMatch::Any)));
+
+        headers.insert(header::IF_MATCH, HeaderValue::from_static("\"a\", \"b\""));
+        assert_eq!(
+            if_match(&headers),
+            Ok(Some(EntryMatch::Tags(vec![
+                "\"a\"".to_string(),
+                "\"b\"".to_string()
+            ])))
+        );
+
+        headers.insert(header::IF_MATCH, HeaderValue::from_static(" , "));
+        assert_eq!(if_match(&headers), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn test_accepts_raw() {
+        let mut headers = HeaderMap::new();
+        assert!(!accepts_raw(&headers, Some("image/png")));
+
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        assert!(!accepts_raw(&headers, Some("image/png")));
+
+        headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static("image/png;q=0.9, application/json"),
+        );
+        assert!(accepts_raw(&headers, Some("image/png")));
+        assert!(!accepts_raw(&headers, None));
+
+        headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static("application/octet-stream"),
+        );
+        assert!(accepts_raw(&headers, None));
+    }
+
+    #[tokio::test]
+    async fn test_read_body_limit() {
+        let headers = HeaderMap::new();
+        let body = read_body(Body::from("hello"), &headers, 5).await.unwrap();
+        assert_eq!(body, Bytes::from("hello"));
+
+        assert_eq!(
+            read_body(Body::from("hello world"), &headers, 5).await,
+            Err(StatusCode::PAYLOAD_TOO_LARGE)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_value_body_streams_whole_value() {
+        let value = Bytes::from(vec![7u8; VALUE_CHUNK_BYTES * 2 + 1]);
+        let mut chunks = value_body(value.clone()).into_data_stream();
+
+        let mut received = Vec::new();
+        let mut count = 0;
+        while let Some(chunk) = chunks.next().await {
+            received.extend_from_slice(&chunk.unwrap());
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        assert_eq!(received, value);
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let etag = entity_tag(b"value");
+        let mut headers = HeaderMap::new();
+        assert!(!if_none_match(&headers, &etag));
+
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_str(&format!("\"other\", W/{}", etag)).unwrap(),
+        );
+        assert!(if_none_match(&headers, &etag));
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"other\""));
+        assert!(!if_none_match(&headers, &etag));
     }
 }
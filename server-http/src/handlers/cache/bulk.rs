@@ -0,0 +1,134 @@
+use crate::api::{BulkLoadRecord, BulkLoadResponse};
+use crate::handlers::cache::{decode_value, ApiError};
+use crate::state::AppState;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use bytes::{Bytes, BytesMut};
+use carbon::domain::BulkEntry;
+use carbon::planes::data::{BulkLoader, BULK_LOAD_BATCH_SIZE};
+use futures::StreamExt;
+use std::time::Duration;
+use tracing::info;
+
+/// POST /cache/:cache_name/bulkload
+/// The body is newline-delimited JSON, one {"key", "value", "encoding", "ttl_ms"} record per
+/// line, written in batches as it arrives and announced by a single cache.bulk_loaded event
+/// Each line must fit in max_body_bytes, the body as a whole is not limited
+/// Batches written before a bad line stay in the cache
+pub async fn bulk_load(
+    State(state): State<AppState>,
+    Path(cache_name): Path<String>,
+    body: Body,
+) -> Result<Json<BulkLoadResponse>, ApiError> {
+    info!("BULKLOAD: cache={}", cache_name);
+
+    let mut loader = state.cache_operations.bulk_loader(&cache_name).await?;
+
+    let result = load_lines(&mut loader, body, state.http_limits.max_body_bytes).await;
+    let summary = loader.finish();
+    result?;
+
+    Ok(Json(BulkLoadResponse {
+        loaded: summary.loaded,
+        not_admitted: summary.not_admitted,
+    }))
+}
+
+pub(crate) async fn load_lines(
+    loader: &mut BulkLoader<'_, Vec<u8>, Bytes>,
+    body: Body,
+    max_line_bytes: usize,
+) -> Result<(), ApiError> {
+    let mut pending = BytesMut::new();
+    // Bytes of `pending` already known to hold no newline
+    let mut scanned = 0;
+    let mut batch = Vec::with_capacity(BULK_LOAD_BATCH_SIZE);
+
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        pending.extend_from_slice(&chunk);
+
+        while let Some(end) = pending[scanned..].iter().position(|byte| *byte == b'\n') {
+            let line = pending.split_to(scanned + end + 1);
+            scanned = 0;
+            if line.len() > max_line_bytes {
+                return Err(StatusCode::PAYLOAD_TOO_LARGE.into());
+            }
+            if let Some(entry) = parse_record(&line)? {
+                batch.push(entry);
+            }
+            if batch.len() == BULK_LOAD_BATCH_SIZE {
+                loader.load(std::mem::take(&mut batch)).await?;
+            }
+        }
+
+        if pending.len() > max_line_bytes {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE.into());
+        }
+        scanned = pending.len();
+    }
+
+    // The last line needs no trailing newline
+    if let Some(entry) = parse_record(&pending)? {
+        batch.push(entry);
+    }
+    if !batch.is_empty() {
+        loader.load(batch).await?;
+    }
+    Ok(())
+}
+
+/// Entry for one NDJSON line, None for a blank line
+fn parse_record(line: &[u8]) -> Result<Option<BulkEntry<Vec<u8>, Bytes>>, StatusCode> {
+    let line = line.trim_ascii();
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    let record: BulkLoadRecord =
+        serde_json::from_slice(line).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if record.key.is_empty() || record.ttl_ms == Some(0) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok(Some(BulkEntry {
+        key: record.key.into_bytes(),
+        value: decode_value(record.value, record.encoding)?,
+        ttl: record.ttl_ms.map(Duration::from_millis),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_record() {
+        let entry = parse_record(br#"{"key":"a","value":"aGk=","encoding":"base64","ttl_ms":500}"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.key, b"a".to_vec());
+        assert_eq!(entry.value, Bytes::from("hi"));
+        assert_eq!(entry.ttl, Some(Duration::from_millis(500)));
+
+        let entry = parse_record(b"{\"key\":\"b\",\"value\":\"x\"}\r\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.ttl, None);
+
+        assert!(parse_record(b"  \n").unwrap().is_none());
+        assert_eq!(
+            parse_record(br#"{"key":"a","value":"x","ttl_ms":0}"#).err(),
+            Some(StatusCode::BAD_REQUEST)
+        );
+        assert_eq!(
+            parse_record(b"not json").err(),
+            Some(StatusCode::BAD_REQUEST)
+        );
+    }
+}
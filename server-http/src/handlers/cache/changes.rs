@@ -0,0 +1,39 @@
+use crate::api::{ChangesQuery, ErrorResponse};
+use crate::handlers::cache::error_status;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use carbon::planes::data::change_log::{ChangePage, MAX_CHANGES_PER_READ};
+use tracing::info;
+
+/// GET /cache/:cache_name/changes?since=N - Changes logged after cursor N, oldest first
+/// Read on from `next_cursor` until no changes come back, `truncated` means some were missed
+pub async fn list_changes(
+    State(state): State<AppState>,
+    Path(cache_name): Path<String>,
+    Query(query): Query<ChangesQuery>,
+) -> Result<Json<ChangePage>, (StatusCode, Json<ErrorResponse>)> {
+    info!("CHANGES: cache={}, since={}", cache_name, query.since);
+
+    let change_log = state.change_log.as_ref().ok_or_else(|| {
+        (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(ErrorResponse::new(
+                "Changes are only logged with CARBON_CHANGE_LOG=true",
+            )),
+        )
+    })?;
+    if state.cache_manager.get_cache(&cache_name).await.is_none() {
+        let e = shared::Error::CacheNotFound(cache_name);
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::from(&e))));
+    }
+
+    let limit = query.limit.unwrap_or(MAX_CHANGES_PER_READ);
+    change_log
+        .read(&cache_name, query.since, limit)
+        .map(Json)
+        .map_err(|e| (error_status(&e), Json(ErrorResponse::from(&e))))
+}
@@ -1,15 +1,23 @@
+use crate::middleware::AuthenticatedSession;
 use crate::state::AppState;
 use axum::{
     extract::State,
     http::Uri,
     response::sse::{Event, KeepAlive, Sse},
+    Extension,
 };
+use carbon::auth::{MokaSessionRepository, SessionStore, SessionToken};
 use carbon::events::CacheItemEvent;
+use futures::future::{self, BoxFuture, FutureExt};
 use futures::stream::{Stream, StreamExt};
 use std::convert::Infallible;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio_stream::wrappers::BroadcastStream;
 
+/// How often a stream checks the session it was opened with is still valid
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Debug)]
 pub struct EventFilter {
     cache: Vec<String>,
@@ -42,8 +50,10 @@ impl EventFilter {
 }
 
 /// SSE endpoint that streams cache item events to clients
+/// The stream ends once the session it was opened with expires or is logged out
 pub async fn stream_events(
     State(state): State<AppState>,
+    session: Option<Extension<AuthenticatedSession>>,
     uri: Uri,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
     let filter = uri
@@ -90,13 +100,38 @@ pub async fn stream_events(
         }
     });
 
-    Sse::new(filtered_stream).keep_alive(
+    let session_ended: BoxFuture<'static, ()> = match session {
+        Some(Extension(session)) => {
+            session_ended(state.session_store.clone(), session.token).boxed()
+        }
+        None => future::pending().boxed(),
+    };
+
+    Sse::new(filtered_stream.take_until(session_ended)).keep_alive(
         KeepAlive::new()
             .interval(Duration::from_secs(15))
             .text("keep-alive"),
     )
 }
 
+/// Completes once `token` no longer names a valid session
+async fn session_ended(
+    session_store: Arc<SessionStore<MokaSessionRepository>>,
+    token: SessionToken,
+) {
+    loop {
+        tokio::time::sleep(SESSION_CHECK_INTERVAL).await;
+        if !session_store
+            .is_valid_session(&token)
+            .await
+            .unwrap_or(false)
+        {
+            tracing::info!("SSE client disconnected, its session ended");
+            return;
+        }
+    }
+}
+
 /// Check if an event should be sent based on the filter criteria
 fn should_send(event: &CacheItemEvent, filter: &EventFilter) -> bool {
     // Filter by cache name (only if filter is specified)
@@ -110,6 +145,10 @@ fn should_send(event: &CacheItemEvent, filter: &EventFilter) -> bool {
             CacheItemEvent::Added(_) => "added",
             CacheItemEvent::Updated(_) => "updated",
             CacheItemEvent::Deleted(_) => "deleted",
+            CacheItemEvent::Expired(_) => "expired",
+            CacheItemEvent::BulkLoaded(_) => "bulk_loaded",
+            CacheItemEvent::CloneProgress(_) => "clone_progress",
+            CacheItemEvent::Renamed(_) => "renamed",
         };
 
         if !filter.event_type.iter().any(|t| t == event_type_str) {
@@ -126,5 +165,18 @@ fn to_sse_event(event: CacheItemEvent) -> Event {
         CacheItemEvent::Added(e) => Event::default().event("item.added").json_data(e).unwrap(),
         CacheItemEvent::Updated(e) => Event::default().event("item.updated").json_data(e).unwrap(),
         CacheItemEvent::Deleted(e) => Event::default().event("item.deleted").json_data(e).unwrap(),
+        CacheItemEvent::Expired(e) => Event::default().event("item.expired").json_data(e).unwrap(),
+        CacheItemEvent::BulkLoaded(e) => Event::default()
+            .event("cache.bulk_loaded")
+            .json_data(e)
+            .unwrap(),
+        CacheItemEvent::CloneProgress(e) => Event::default()
+            .event("cache.clone_progress")
+            .json_data(e)
+            .unwrap(),
+        CacheItemEvent::Renamed(e) => Event::default()
+            .event("cache.renamed")
+            .json_data(e)
+            .unwrap(),
     }
 }
@@ -0,0 +1,117 @@
+use crate::api::{
+    DeleteResponse, GetResponse, HashFieldsResponse, PutRequest, PutResponse, ValueEncoding,
+};
+use crate::handlers::cache::{decode_value, ApiError};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use carbon::planes::data::operation::HashOperations;
+use tracing::info;
+
+/// PUT /cache/:cache_name/:key/fields/:field
+pub async fn put_field(
+    State(state): State<AppState>,
+    Path((cache_name, key, field)): Path<(String, String, String)>,
+    Json(req): Json<PutRequest>,
+) -> Result<Json<PutResponse>, ApiError> {
+    info!("HSET: cache={}, key={}, field={}", cache_name, key, field);
+
+    let value = decode_value(req.value, req.encoding)?;
+
+    match state
+        .cache_operations
+        .hset(&cache_name, key.into_bytes(), field, value)
+        .await
+    {
+        Ok(result) if !result.admitted => Err(ApiError::not_admitted(result.message)),
+        Ok(_) => Ok(Json(PutResponse { ok: true })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// GET /cache/:cache_name/:key/fields/:field
+pub async fn get_field(
+    State(state): State<AppState>,
+    Path((cache_name, key, field)): Path<(String, String, String)>,
+) -> Result<Json<GetResponse>, ApiError> {
+    info!("HGET: cache={}, key={}, field={}", cache_name, key, field);
+
+    match state
+        .cache_operations
+        .hget(&cache_name, &key.into_bytes(), &field)
+        .await
+    {
+        Ok(result) => {
+            let value = String::from_utf8(result.message.to_vec())
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            Ok(Json(GetResponse {
+                found: result.found,
+                value,
+                encoding: ValueEncoding::Utf8,
+                ttl_ms_remaining: 0,
+            }))
+        }
+        Err(shared::Error::NotFound) => Ok(Json(GetResponse {
+            found: false,
+            value: String::new(),
+            encoding: ValueEncoding::Utf8,
+            ttl_ms_remaining: 0,
+        })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// DELETE /cache/:cache_name/:key/fields/:field
+pub async fn delete_field(
+    State(state): State<AppState>,
+    Path((cache_name, key, field)): Path<(String, String, String)>,
+) -> Result<Json<DeleteResponse>, ApiError> {
+    info!("HDEL: cache={}, key={}, field={}", cache_name, key, field);
+
+    match state
+        .cache_operations
+        .hdel(&cache_name, &key.into_bytes(), &field)
+        .await
+    {
+        Ok(result) => Ok(Json(DeleteResponse {
+            deleted: result.deleted,
+        })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// GET /cache/:cache_name/:key/fields
+pub async fn get_all_fields(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+) -> Result<Json<HashFieldsResponse>, ApiError> {
+    info!("HGETALL: cache={}, key={}", cache_name, key);
+
+    match state
+        .cache_operations
+        .hgetall(&cache_name, &key.into_bytes())
+        .await
+    {
+        Ok(result) => {
+            let fields = result
+                .message
+                .into_iter()
+                .map(|(field, value)| {
+                    String::from_utf8(value.to_vec())
+                        .map(|value| (field, value))
+                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+                })
+                .collect::<Result<_, _>>()?;
+
+            Ok(Json(HashFieldsResponse { fields }))
+        }
+        Err(shared::Error::NotFound) => Ok(Json(HashFieldsResponse {
+            fields: Default::default(),
+        })),
+        Err(e) => Err(e.into()),
+    }
+}
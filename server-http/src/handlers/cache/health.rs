@@ -1,4 +1,122 @@
-/// GET /health
-pub async fn health_check() -> &'static str {
-    "OK"
+use crate::api::{ComponentHealth, HealthResponse, HealthStatus};
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use carbon::planes::control::operation::AdminOperations;
+use carbon::planes::data::MaintenanceStatus;
+use std::collections::BTreeMap;
+use tracing::warn;
+
+/// GET /health, flagged while the server or some caches are in read-only maintenance mode
+pub async fn health_check(State(state): State<AppState>) -> &'static str {
+    match maintenance(&state) {
+        Some(_) => "OK (read-only)",
+        None => "OK",
+    }
+}
+
+/// GET /health/live - the process is running and serving requests
+pub async fn liveness(State(state): State<AppState>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: HealthStatus::Up,
+        components: BTreeMap::new(),
+        maintenance: maintenance(&state),
+    })
+}
+
+/// GET /health/ready - dependencies are usable, 503 when any of them is down
+pub async fn readiness(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    readiness_report(&state, false).await
+}
+
+/// GET /admin/health/ready - readiness with every cache, kept behind auth as it lists their names
+pub async fn admin_readiness(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    readiness_report(&state, true).await
+}
+
+async fn readiness_report(state: &AppState, caches: bool) -> (StatusCode, Json<HealthResponse>) {
+    let mut components = BTreeMap::new();
+
+    let persistence = match state.cache_manager.check_persistence() {
+        Some(Ok(())) => ComponentHealth::up(),
+        Some(Err(e)) => ComponentHealth::new(HealthStatus::Down, e.to_string()),
+        None => ComponentHealth::new(HealthStatus::Disabled, "running in-memory"),
+    };
+    components.insert("persistence".to_string(), persistence);
+
+    // Reading the roles proves the auth databases are open
+    let auth = match state.role_service.list_roles().await {
+        Ok(_) => ComponentHealth::up(),
+        Err(e) => ComponentHealth::new(HealthStatus::Down, e.to_string()),
+    };
+    components.insert("auth".to_string(), auth);
+
+    // The channel stays open while the state holds its sender, report who is listening
+    components.insert(
+        "events".to_string(),
+        ComponentHealth::new(
+            HealthStatus::Up,
+            format!("{} subscribers", state.event_channel.receiver_count()),
+        ),
+    );
+
+    if caches {
+        match state.cache_manager.list_caches().await {
+            Ok(list) => {
+                for info in list.caches {
+                    let name = info.config.name;
+                    let health = match state.cache_manager.get_cache_store(&name).await {
+                        Some(store) => match store.exists(&Vec::new()).await {
+                            Ok(_) => ComponentHealth::up(),
+                            Err(e) => ComponentHealth::new(HealthStatus::Down, e.to_string()),
+                        },
+                        // Dropped while we were checking
+                        None => continue,
+                    };
+                    components.insert(format!("cache:{}", name), health);
+                }
+                // Stored caches that could not be recreated at startup
+                for failed in list.failed {
+                    components.insert(
+                        format!("cache:{}", failed.name),
+                        ComponentHealth::new(HealthStatus::Down, failed.reason),
+                    );
+                }
+            }
+            Err(e) => {
+                components.insert(
+                    "caches".to_string(),
+                    ComponentHealth::new(HealthStatus::Down, e.to_string()),
+                );
+            }
+        }
+    }
+
+    let down: Vec<&str> = components
+        .iter()
+        .filter(|(_, health)| health.status == HealthStatus::Down)
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    let (status_code, status) = if down.is_empty() {
+        (StatusCode::OK, HealthStatus::Up)
+    } else {
+        warn!("Readiness check failed: {:?}", down);
+        (StatusCode::SERVICE_UNAVAILABLE, HealthStatus::Down)
+    };
+
+    // Refusing writes on purpose does not make the server unready
+    let maintenance = maintenance(state);
+    (
+        status_code,
+        Json(HealthResponse {
+            status,
+            components,
+            maintenance,
+        }),
+    )
+}
+
+/// Maintenance mode, None unless something refuses writes
+fn maintenance(state: &AppState) -> Option<MaintenanceStatus> {
+    Some(state.cache_operations.maintenance().status()).filter(MaintenanceStatus::active)
 }
@@ -0,0 +1,56 @@
+use crate::api::{ErrorResponse, PutResponse};
+use crate::handlers::cache::error_status;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use carbon::planes::data::operation::JsonOperations;
+use shared::ErrorCode;
+use tracing::info;
+
+/// GET /cache/:cache_name/:key/path/*pointer
+pub async fn get_json_path(
+    State(state): State<AppState>,
+    Path((cache_name, key, pointer)): Path<(String, String, String)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let pointer = format!("/{}", pointer);
+    info!("JSON_GET: cache={}, key={}, path={}", cache_name, key, pointer);
+
+    match state
+        .cache_operations
+        .json_get(&cache_name, &key.into_bytes(), &pointer)
+        .await
+    {
+        Ok(result) => Ok(Json(result.message)),
+        Err(e) => Err(json_error(e)),
+    }
+}
+
+/// PATCH /cache/:cache_name/:key/path/*pointer
+pub async fn patch_json_path(
+    State(state): State<AppState>,
+    Path((cache_name, key, pointer)): Path<(String, String, String)>,
+    Json(value): Json<serde_json::Value>,
+) -> Result<Json<PutResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let pointer = format!("/{}", pointer);
+    info!("JSON_PATCH: cache={}, key={}, path={}", cache_name, key, pointer);
+
+    match state
+        .cache_operations
+        .json_set(&cache_name, key.into_bytes(), &pointer, value)
+        .await
+    {
+        Ok(result) if !result.admitted => Err((
+            StatusCode::INSUFFICIENT_STORAGE,
+            Json(ErrorResponse::new(result.message).with_code(ErrorCode::NotAdmitted)),
+        )),
+        Ok(_) => Ok(Json(PutResponse { ok: true })),
+        Err(e) => Err(json_error(e)),
+    }
+}
+
+fn json_error(error: shared::Error) -> (StatusCode, Json<ErrorResponse>) {
+    (error_status(&error), Json(ErrorResponse::from(&error)))
+}
@@ -0,0 +1,57 @@
+use crate::api::{LockRequest, UnlockQuery, UnlockResponse};
+use crate::handlers::cache::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use carbon::domain::LockLease;
+use carbon::planes::data::operation::LockOperations;
+use std::time::Duration;
+use tracing::info;
+
+/// POST /cache/:cache_name/:key/lock
+/// Responds 423 Locked while another client holds a live lease
+pub async fn acquire_lock(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+    Json(payload): Json<LockRequest>,
+) -> Result<Json<LockLease>, ApiError> {
+    info!(
+        "LOCK: cache={}, key={}, ttl_ms={}",
+        cache_name, key, payload.ttl_ms
+    );
+
+    match state
+        .cache_operations
+        .lock(
+            &cache_name,
+            key.into_bytes(),
+            Duration::from_millis(payload.ttl_ms),
+        )
+        .await
+    {
+        Ok(Some(lease)) => Ok(Json(lease)),
+        Ok(None) => Err(StatusCode::LOCKED.into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// DELETE /cache/:cache_name/:key/lock?token=N
+pub async fn release_lock(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+    Query(query): Query<UnlockQuery>,
+) -> Result<Json<UnlockResponse>, ApiError> {
+    info!("UNLOCK: cache={}, key={}", cache_name, key);
+
+    match state
+        .cache_operations
+        .unlock(&cache_name, &key.into_bytes(), query.token)
+        .await
+    {
+        Ok(released) => Ok(Json(UnlockResponse { released })),
+        Err(e) => Err(e.into()),
+    }
+}
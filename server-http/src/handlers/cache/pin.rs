@@ -0,0 +1,45 @@
+use crate::api::PinResponse;
+use crate::handlers::cache::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use tracing::info;
+
+/// PUT /cache/:cache_name/:key/pin
+/// Keeps the entry out of reach of eviction, 403 once the cache's max_pinned_bytes is used up
+pub async fn pin_key(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+) -> Result<Json<PinResponse>, ApiError> {
+    info!("PIN: cache={}, key={}", cache_name, key);
+
+    let changed = state
+        .cache_operations
+        .pin(&cache_name, &key.into_bytes())
+        .await?;
+    let pinned_bytes = state.cache_operations.pinned_bytes(&cache_name).await?;
+    Ok(Json(PinResponse {
+        changed,
+        pinned_bytes,
+    }))
+}
+
+/// DELETE /cache/:cache_name/:key/pin
+pub async fn unpin_key(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+) -> Result<Json<PinResponse>, ApiError> {
+    info!("UNPIN: cache={}, key={}", cache_name, key);
+
+    let changed = state
+        .cache_operations
+        .unpin(&cache_name, &key.into_bytes())
+        .await?;
+    let pinned_bytes = state.cache_operations.pinned_bytes(&cache_name).await?;
+    Ok(Json(PinResponse {
+        changed,
+        pinned_bytes,
+    }))
+}
@@ -0,0 +1,127 @@
+use crate::api::{
+    BloomAddResponse, BloomExistsResponse, BloomReserveRequest, CardinalityQuery,
+    CardinalityResponse, HyperLogLogAddRequest, HyperLogLogAddResponse, PutResponse,
+};
+use crate::handlers::cache::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use bytes::Bytes;
+use carbon::planes::data::operation::ProbabilisticOperations;
+use tracing::info;
+
+/// POST /cache/:cache_name/:key/hll
+pub async fn add_to_hyperloglog(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+    Json(payload): Json<HyperLogLogAddRequest>,
+) -> Result<Json<HyperLogLogAddResponse>, ApiError> {
+    info!(
+        "PFADD: cache={}, key={}, items={}",
+        cache_name,
+        key,
+        payload.items.len()
+    );
+
+    let items = payload.items.into_iter().map(Bytes::from).collect();
+    match state
+        .cache_operations
+        .pfadd(&cache_name, key.into_bytes(), items)
+        .await
+    {
+        Ok(changed) => Ok(Json(HyperLogLogAddResponse { changed })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// GET /cache/:cache_name/:key/hll[?union=k1,k2]
+pub async fn count_hyperloglog(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+    Query(query): Query<CardinalityQuery>,
+) -> Result<Json<CardinalityResponse>, ApiError> {
+    info!("PFCOUNT: cache={}, key={}", cache_name, key);
+
+    let keys: Vec<Vec<u8>> = std::iter::once(key.as_str())
+        .chain(
+            query
+                .union
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|other| !other.is_empty()),
+        )
+        .map(|key| key.as_bytes().to_vec())
+        .collect();
+
+    match state.cache_operations.pfcount(&cache_name, &keys).await {
+        Ok(count) => Ok(Json(CardinalityResponse { count })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// PUT /cache/:cache_name/:key/bloom
+/// Responds 412 Precondition Failed when the key already holds a value
+pub async fn reserve_bloom(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+    Json(payload): Json<BloomReserveRequest>,
+) -> Result<Json<PutResponse>, ApiError> {
+    info!(
+        "BF.RESERVE: cache={}, key={}, error_rate={}, capacity={}",
+        cache_name, key, payload.error_rate, payload.capacity
+    );
+
+    match state
+        .cache_operations
+        .bf_reserve(
+            &cache_name,
+            key.into_bytes(),
+            payload.error_rate,
+            payload.capacity,
+        )
+        .await
+    {
+        Ok(()) => Ok(Json(PutResponse { ok: true })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// PUT /cache/:cache_name/:key/bloom/:item
+pub async fn add_to_bloom(
+    State(state): State<AppState>,
+    Path((cache_name, key, item)): Path<(String, String, String)>,
+) -> Result<Json<BloomAddResponse>, ApiError> {
+    info!("BF.ADD: cache={}, key={}, item={}", cache_name, key, item);
+
+    match state
+        .cache_operations
+        .bf_add(&cache_name, key.into_bytes(), Bytes::from(item))
+        .await
+    {
+        Ok(added) => Ok(Json(BloomAddResponse { added })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// GET /cache/:cache_name/:key/bloom/:item
+pub async fn check_bloom(
+    State(state): State<AppState>,
+    Path((cache_name, key, item)): Path<(String, String, String)>,
+) -> Result<Json<BloomExistsResponse>, ApiError> {
+    info!(
+        "BF.EXISTS: cache={}, key={}, item={}",
+        cache_name, key, item
+    );
+
+    match state
+        .cache_operations
+        .bf_exists(&cache_name, &key.into_bytes(), &Bytes::from(item))
+        .await
+    {
+        Ok(exists) => Ok(Json(BloomExistsResponse { exists })),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,59 @@
+use crate::api::{ErrorResponse, QueryResponse};
+use crate::handlers::cache::error_status;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use carbon_query::{Query, QueryableCache};
+use tracing::info;
+
+/// POST /cache/:cache_name/query
+pub async fn query_cache(
+    State(state): State<AppState>,
+    Path(cache_name): Path<String>,
+    Json(query): Json<Query>,
+) -> Result<Json<QueryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    info!(
+        "QUERY: cache={}, predicates={}, offset={}",
+        cache_name,
+        query.predicates.len(),
+        query.offset
+    );
+
+    let (store, config) = state
+        .cache_manager
+        .get_cache(&cache_name)
+        .await
+        .ok_or_else(|| query_error(shared::Error::CacheNotFound(cache_name.clone())))?;
+
+    let indexes = state.index_registry.get(&cache_name).ok_or_else(|| {
+        query_error(shared::Error::InvalidValue(format!(
+            "cache '{}' has no indexes",
+            cache_name
+        )))
+    })?;
+
+    let result = QueryableCache::new(store, indexes)
+        .with_value_format(config.value_format)
+        .query(&query)
+        .await
+        .map_err(query_error)?;
+
+    let keys: Vec<String> = result
+        .keys
+        .iter()
+        .map(|key| String::from_utf8_lossy(key).into_owned())
+        .collect();
+
+    Ok(Json(QueryResponse {
+        count: keys.len(),
+        keys,
+        next_offset: result.next_offset,
+    }))
+}
+
+fn query_error(error: shared::Error) -> (StatusCode, Json<ErrorResponse>) {
+    (error_status(&error), Json(ErrorResponse::from(&error)))
+}
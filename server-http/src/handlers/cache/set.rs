@@ -0,0 +1,125 @@
+use crate::api::{
+    DeleteResponse, PutResponse, SetMembersQuery, SetMembersResponse, SetMembershipResponse,
+};
+use crate::handlers::cache::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use bytes::Bytes;
+use carbon::planes::data::operation::SetOperations;
+use tracing::info;
+
+/// PUT /cache/:cache_name/:key/members/:member
+pub async fn add_member(
+    State(state): State<AppState>,
+    Path((cache_name, key, member)): Path<(String, String, String)>,
+) -> Result<Json<PutResponse>, ApiError> {
+    info!("SADD: cache={}, key={}, member={}", cache_name, key, member);
+
+    match state
+        .cache_operations
+        .sadd(&cache_name, key.into_bytes(), vec![Bytes::from(member)])
+        .await
+    {
+        Ok(_) => Ok(Json(PutResponse { ok: true })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// GET /cache/:cache_name/:key/members/:member
+pub async fn is_member(
+    State(state): State<AppState>,
+    Path((cache_name, key, member)): Path<(String, String, String)>,
+) -> Result<Json<SetMembershipResponse>, ApiError> {
+    info!(
+        "SISMEMBER: cache={}, key={}, member={}",
+        cache_name, key, member
+    );
+
+    match state
+        .cache_operations
+        .sismember(&cache_name, &key.into_bytes(), &Bytes::from(member))
+        .await
+    {
+        Ok(member) => Ok(Json(SetMembershipResponse { member })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// DELETE /cache/:cache_name/:key/members/:member
+pub async fn remove_member(
+    State(state): State<AppState>,
+    Path((cache_name, key, member)): Path<(String, String, String)>,
+) -> Result<Json<DeleteResponse>, ApiError> {
+    info!("SREM: cache={}, key={}, member={}", cache_name, key, member);
+
+    match state
+        .cache_operations
+        .srem(&cache_name, &key.into_bytes(), vec![Bytes::from(member)])
+        .await
+    {
+        Ok(removed) => Ok(Json(DeleteResponse {
+            deleted: removed > 0,
+        })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// GET /cache/:cache_name/:key/members[?intersect=k1,k2 | ?union=k1,k2]
+pub async fn get_members(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+    Query(query): Query<SetMembersQuery>,
+) -> Result<Json<SetMembersResponse>, ApiError> {
+    info!("SMEMBERS: cache={}, key={}", cache_name, key);
+
+    let cache_ops = &state.cache_operations;
+    let result = match (query.intersect, query.union) {
+        (Some(_), Some(_)) => return Err(StatusCode::BAD_REQUEST.into()),
+        (Some(others), None) => {
+            cache_ops
+                .sinter(&cache_name, &with_other_keys(key, &others))
+                .await
+        }
+        (None, Some(others)) => {
+            cache_ops
+                .sunion(&cache_name, &with_other_keys(key, &others))
+                .await
+        }
+        (None, None) => cache_ops.smembers(&cache_name, &key.into_bytes()).await,
+    };
+
+    match result {
+        Ok(result) => {
+            let members = result
+                .message
+                .into_iter()
+                .map(|member| {
+                    String::from_utf8(member.to_vec())
+                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(Json(SetMembersResponse {
+                count: members.len(),
+                members,
+            }))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn with_other_keys(key: String, others: &str) -> Vec<Vec<u8>> {
+    std::iter::once(key)
+        .chain(
+            others
+                .split(',')
+                .filter(|other| !other.is_empty())
+                .map(str::to_string),
+        )
+        .map(String::into_bytes)
+        .collect()
+}
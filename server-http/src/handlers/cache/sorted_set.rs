@@ -0,0 +1,150 @@
+use crate::api::{
+    DeleteResponse, MemberScoreQuery, MemberScoreResponse, PutResponse, ScoreRangeQuery,
+    ScoreRequest, ScoredMember, ScoredMembersResponse,
+};
+use crate::handlers::cache::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use bytes::Bytes;
+use carbon::planes::data::operation::SortedSetOperations;
+use tracing::info;
+
+/// PUT /cache/:cache_name/:key/scores/:member
+pub async fn put_score(
+    State(state): State<AppState>,
+    Path((cache_name, key, member)): Path<(String, String, String)>,
+    Json(payload): Json<ScoreRequest>,
+) -> Result<Json<PutResponse>, ApiError> {
+    info!(
+        "ZADD: cache={}, key={}, member={}, score={}",
+        cache_name, key, member, payload.score
+    );
+
+    match state
+        .cache_operations
+        .zadd(
+            &cache_name,
+            key.into_bytes(),
+            vec![(Bytes::from(member), payload.score)],
+        )
+        .await
+    {
+        Ok(_) => Ok(Json(PutResponse { ok: true })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// GET /cache/:cache_name/:key/scores/:member[?reverse=true]
+pub async fn get_score(
+    State(state): State<AppState>,
+    Path((cache_name, key, member)): Path<(String, String, String)>,
+    Query(query): Query<MemberScoreQuery>,
+) -> Result<Json<MemberScoreResponse>, ApiError> {
+    info!(
+        "ZSCORE: cache={}, key={}, member={}",
+        cache_name, key, member
+    );
+
+    let cache_ops = &state.cache_operations;
+    let key = key.into_bytes();
+    let member = Bytes::from(member);
+
+    let score = cache_ops.zscore(&cache_name, &key, &member).await?;
+    let rank = match score {
+        Some(_) => {
+            cache_ops
+                .zrank(&cache_name, &key, &member, query.reverse)
+                .await?
+        }
+        None => None,
+    };
+
+    Ok(Json(MemberScoreResponse {
+        found: score.is_some(),
+        score,
+        rank,
+    }))
+}
+
+/// DELETE /cache/:cache_name/:key/scores/:member
+pub async fn remove_score(
+    State(state): State<AppState>,
+    Path((cache_name, key, member)): Path<(String, String, String)>,
+) -> Result<Json<DeleteResponse>, ApiError> {
+    info!("ZREM: cache={}, key={}, member={}", cache_name, key, member);
+
+    match state
+        .cache_operations
+        .zrem(&cache_name, &key.into_bytes(), vec![Bytes::from(member)])
+        .await
+    {
+        Ok(removed) => Ok(Json(DeleteResponse {
+            deleted: removed > 0,
+        })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// GET /cache/:cache_name/:key/scores[?start=0&stop=-1&reverse=true | ?min=..&max=..&limit=N]
+pub async fn get_scores(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+    Query(query): Query<ScoreRangeQuery>,
+) -> Result<Json<ScoredMembersResponse>, ApiError> {
+    info!("ZRANGE: cache={}, key={}", cache_name, key);
+
+    let cache_ops = &state.cache_operations;
+    let key = key.into_bytes();
+    let by_rank = query.start.is_some() || query.stop.is_some() || query.reverse;
+    let by_score = query.min.is_some() || query.max.is_some() || query.limit.is_some();
+
+    let result = match (by_rank, by_score) {
+        (true, true) => return Err(StatusCode::BAD_REQUEST.into()),
+        (_, true) => {
+            cache_ops
+                .zrangebyscore(
+                    &cache_name,
+                    &key,
+                    query.min.unwrap_or(f64::NEG_INFINITY),
+                    query.max.unwrap_or(f64::INFINITY),
+                    query.limit,
+                )
+                .await
+        }
+        (_, false) => {
+            cache_ops
+                .zrange(
+                    &cache_name,
+                    &key,
+                    query.start.unwrap_or(0),
+                    query.stop.unwrap_or(-1),
+                    query.reverse,
+                )
+                .await
+        }
+    };
+
+    match result {
+        Ok(result) => {
+            let members = result
+                .message
+                .into_iter()
+                .map(|(member, score)| {
+                    let member = String::from_utf8(member.to_vec())
+                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                    Ok(ScoredMember { member, score })
+                })
+                .collect::<Result<Vec<_>, StatusCode>>()?;
+
+            Ok(Json(ScoredMembersResponse {
+                count: members.len(),
+                members,
+            }))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
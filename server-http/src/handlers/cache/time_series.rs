@@ -0,0 +1,78 @@
+use crate::api::{
+    AppendSampleRequest, AppendSampleResponse, SampleRangeQuery, SamplesResponse, TimeSeriesSample,
+};
+use crate::handlers::cache::ApiError;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use bytes::Bytes;
+use carbon::planes::data::operation::TimeSeriesOperations;
+use tracing::info;
+
+/// POST /cache/:cache_name/:key/samples
+pub async fn append_sample(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+    Json(payload): Json<AppendSampleRequest>,
+) -> Result<Json<AppendSampleResponse>, ApiError> {
+    info!(
+        "APPEND: cache={}, key={}, timestamp={}",
+        cache_name, key, payload.timestamp
+    );
+
+    match state
+        .cache_operations
+        .ts_append(
+            &cache_name,
+            key.into_bytes(),
+            payload.timestamp,
+            Bytes::from(payload.value),
+        )
+        .await
+    {
+        Ok(samples) => Ok(Json(AppendSampleResponse { samples })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// GET /cache/:cache_name/:key/samples[?from=..&to=..&limit=N]
+pub async fn get_samples(
+    State(state): State<AppState>,
+    Path((cache_name, key)): Path<(String, String)>,
+    Query(query): Query<SampleRangeQuery>,
+) -> Result<Json<SamplesResponse>, ApiError> {
+    info!("RANGE: cache={}, key={}", cache_name, key);
+
+    match state
+        .cache_operations
+        .ts_range(
+            &cache_name,
+            &key.into_bytes(),
+            query.from.unwrap_or(0),
+            query.to.unwrap_or(u64::MAX),
+            query.limit,
+        )
+        .await
+    {
+        Ok(result) => {
+            let samples = result
+                .message
+                .into_iter()
+                .map(|(timestamp, value)| {
+                    let value = String::from_utf8(value.to_vec())
+                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                    Ok(TimeSeriesSample { timestamp, value })
+                })
+                .collect::<Result<Vec<_>, StatusCode>>()?;
+
+            Ok(Json(SamplesResponse {
+                count: samples.len(),
+                samples,
+            }))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,84 @@
+use super::auth::AuthHandlerState;
+use axum::{extract::State, http::StatusCode, response::Json};
+use carbon::auth::AuthError;
+use serde::{Deserialize, Serialize};
+
+/// Request body for MFA enrollment, authenticated by password as the user cannot sign in yet
+#[derive(Debug, Deserialize)]
+pub struct MfaEnrollRequest {
+    pub username: String,
+    pub password: String,
+}
+
+/// Request body confirming an enrollment with a first code from the authenticator app
+#[derive(Debug, Deserialize)]
+pub struct MfaConfirmRequest {
+    pub username: String,
+    pub password: String,
+    pub code: String,
+}
+
+/// Response body for MFA enrollment
+#[derive(Debug, Serialize)]
+pub struct MfaEnrollResponse {
+    /// Base32 secret for entering into the authenticator app by hand
+    pub secret: String,
+    /// otpauth:// URI, usually rendered as a QR code
+    pub provisioning_uri: String,
+}
+
+/// Response body for a confirmed enrollment
+#[derive(Debug, Serialize)]
+pub struct MfaConfirmResponse {
+    /// One-time codes for signing in without the authenticator app, shown only once
+    pub recovery_codes: Vec<String>,
+}
+
+type ErrorResponse = (StatusCode, Json<serde_json::Value>);
+
+fn error_response(e: AuthError) -> ErrorResponse {
+    let status = match e {
+        AuthError::InvalidCredentials | AuthError::InvalidMfaCode => StatusCode::UNAUTHORIZED,
+        AuthError::MfaLockedOut => StatusCode::TOO_MANY_REQUESTS,
+        AuthError::MfaAlreadyEnrolled | AuthError::MfaNotEnrolled => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let body = serde_json::json!({ "error": e.to_string(), "code": e.code().as_str() });
+    (status, Json(body))
+}
+
+/// POST /auth/mfa/enroll
+///
+/// Start TOTP enrollment: returns a new secret for the user's authenticator app.
+/// Sign-in asks for codes once the enrollment is confirmed.
+pub async fn enroll_mfa(
+    State(state): State<AuthHandlerState>,
+    Json(req): Json<MfaEnrollRequest>,
+) -> Result<Json<MfaEnrollResponse>, ErrorResponse> {
+    let enrollment = state
+        .auth_service
+        .enroll_mfa(&req.username, &req.password)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(MfaEnrollResponse {
+        secret: enrollment.secret,
+        provisioning_uri: enrollment.provisioning_uri,
+    }))
+}
+
+/// POST /auth/mfa/confirm
+///
+/// Finish enrollment with a code from the authenticator app, returning the recovery codes.
+pub async fn confirm_mfa(
+    State(state): State<AuthHandlerState>,
+    Json(req): Json<MfaConfirmRequest>,
+) -> Result<Json<MfaConfirmResponse>, ErrorResponse> {
+    let recovery_codes = state
+        .auth_service
+        .confirm_mfa(&req.username, &req.password, &req.code)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(MfaConfirmResponse { recovery_codes }))
+}
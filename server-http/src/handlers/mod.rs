@@ -1,13 +1,53 @@
 pub mod admin;
 pub mod auth;
 pub mod cache;
+pub mod mfa;
+pub mod oidc;
 
-pub use admin::cache::{create_cache, describe_cache, drop_cache, list_caches};
-pub use admin::roles::{create_role, delete_role, get_role, list_roles, update_role};
+pub use admin::aliases::{list_aliases, remove_alias, set_alias};
+pub use admin::cache::{
+    clone_cache, create_cache, describe_cache, drop_cache, list_caches, list_dropped_caches,
+    rename_cache, restore_cache, retry_cache, update_cache, validate_cache,
+};
+pub use admin::config::reload_config;
+pub use admin::connections::list_connections;
+pub use admin::event_sink::event_sink_stats;
+pub use admin::indexes::{create_index, drop_index, get_schema, list_indexes};
+pub use admin::maintenance::set_maintenance;
+pub use admin::manifest::{export_auth, import_auth};
+pub use admin::metrics::{cache_stats, prometheus_metrics};
+pub use admin::quotas::{get_user_quota, list_quota_usage};
+pub use admin::rate_limits::rate_limit_stats;
+pub use admin::slow_log::{get_slow_log, reset_slow_log};
+pub use admin::roles::{
+    clear_role_quota, create_role, delete_role, get_role, list_roles, set_role_mfa_required,
+    set_role_quota, update_role,
+};
+pub use admin::schedules::{create_schedule, list_schedules, remove_schedule};
+pub use admin::service_accounts::{create_api_key, create_service_account, revoke_api_key};
 pub use admin::users::{
-    assign_roles, change_password, create_user, delete_user, get_user, list_users, reset_password,
+    assign_roles, change_password, clear_user_quota, create_user, delete_user, get_user,
+    list_users, reset_password, reset_user_mfa, set_user_mfa_required, set_user_quota,
 };
-pub use auth::{login, logout, AuthHandlerState};
-pub use cache::basic::{delete_value, get_value, put_value};
+pub use admin::views::{create_view, list_views, remove_view};
+pub use auth::{login, logout, logout_all, refresh, AuthHandlerState};
+pub use cache::basic::{
+    delete_value, get_metadata, get_value, getdel_value, getex_value, invalidate_by_tag, put_value,
+};
+pub use cache::bulk::bulk_load;
+pub use cache::changes::list_changes;
 pub use cache::events::stream_events;
-pub use cache::health::health_check;
+pub use cache::hash::{delete_field, get_all_fields, get_field, put_field};
+pub use cache::health::{admin_readiness, health_check, liveness, readiness};
+pub use cache::json::{get_json_path, patch_json_path};
+pub use cache::lock::{acquire_lock, release_lock};
+pub use cache::pin::{pin_key, unpin_key};
+pub use cache::probabilistic::{
+    add_to_bloom, add_to_hyperloglog, check_bloom, count_hyperloglog, reserve_bloom,
+};
+pub use cache::query::query_cache;
+pub use cache::set::{add_member, get_members, is_member, remove_member};
+pub use cache::sorted_set::{get_score, get_scores, put_score, remove_score};
+pub use cache::time_series::{append_sample, get_samples};
+pub use mfa::{confirm_mfa, enroll_mfa};
+pub use oidc::{oidc_callback, oidc_login};
@@ -0,0 +1,105 @@
+use super::auth::{client_ip, AuthHandlerState, LoginResponse};
+use crate::middleware::ClientIp;
+use crate::oidc::{OidcClient, OidcError};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Json, Redirect},
+    Extension,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Query string the provider redirects back with
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set instead of `code` when the user or the provider refused the sign-in
+    pub error: Option<String>,
+}
+
+type ErrorResponse = (StatusCode, Json<serde_json::Value>);
+
+fn error(status: StatusCode, message: impl std::fmt::Display) -> ErrorResponse {
+    (
+        status,
+        Json(serde_json::json!({ "error": message.to_string() })),
+    )
+}
+
+fn configured(state: &AuthHandlerState) -> Result<&Arc<OidcClient>, ErrorResponse> {
+    state
+        .oidc
+        .as_ref()
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "OIDC sign-in is not configured"))
+}
+
+/// GET /auth/oidc/login
+///
+/// Redirect the browser to the OIDC provider's sign-in page.
+pub async fn oidc_login(State(state): State<AuthHandlerState>) -> Result<Redirect, ErrorResponse> {
+    let oidc = configured(&state)?;
+    match oidc.authorization_url() {
+        Ok(url) => Ok(Redirect::to(&url)),
+        Err(e @ OidcError::TooManyLogins) => Err(error(StatusCode::SERVICE_UNAVAILABLE, e)),
+        Err(e) => Err(error(StatusCode::INTERNAL_SERVER_ERROR, e)),
+    }
+}
+
+/// GET /auth/oidc/callback
+///
+/// Exchange the provider's code, map the user's claims to roles and issue a session
+/// token, returned like POST /auth/login.
+pub async fn oidc_callback(
+    State(state): State<AuthHandlerState>,
+    client: Option<Extension<ClientIp>>,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<Json<LoginResponse>, ErrorResponse> {
+    let oidc = configured(&state)?;
+
+    if let Some(reason) = query.error {
+        return Err(error(
+            StatusCode::UNAUTHORIZED,
+            format!("Sign-in refused by the provider: {}", reason),
+        ));
+    }
+    let (Some(code), Some(login_state)) = (query.code, query.state) else {
+        return Err(error(StatusCode::BAD_REQUEST, "Missing code or state"));
+    };
+
+    let identity = match oidc.identity(&login_state, &code).await {
+        Ok(identity) => identity,
+        Err(e @ OidcError::UnknownState) => return Err(error(StatusCode::BAD_REQUEST, e)),
+        Err(e) => {
+            tracing::warn!("OIDC sign-in failed: {}", e);
+            return Err(error(StatusCode::UNAUTHORIZED, "OIDC sign-in failed"));
+        }
+    };
+
+    let user = state
+        .auth_service
+        .external_user("oidc", identity)
+        .await
+        .map_err(|_| error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to resolve roles"))?;
+
+    let ttl_ms = state.session_store.ttl_ms();
+    let session = state
+        .session_store
+        .create_session(user.clone(), ttl_ms, client_ip(client))
+        .await
+        .map_err(|_| {
+            error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to create session",
+            )
+        })?;
+
+    Ok(Json(LoginResponse {
+        token: session.token,
+        expires_in: ttl_ms / 1000,
+        username: user.username,
+        refresh_token: None,
+        refresh_expires_in: None,
+    }))
+}
@@ -1,10 +1,17 @@
 pub mod api;
 pub mod handlers;
 pub mod middleware;
+pub mod oidc;
+pub mod provision;
+pub mod reload;
 pub mod routes;
+pub mod schedules;
+pub mod serve;
 pub mod state;
+pub mod telemetry;
 pub mod validation;
 
 // Re-export key types
 pub use state::AppState;
 pub use routes::build_router;
+pub use serve::serve;
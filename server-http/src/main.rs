@@ -1,18 +1,26 @@
 mod api;
 mod handlers;
 mod middleware;
+mod provision;
+mod reload;
 mod routes;
+mod schedules;
+mod serve;
 mod state;
+mod telemetry;
 mod validation;
 
 use carbon::auth::{
     defaults::create_default_admin, AuthService, MokaSessionRepository, RoleService,
     SledRoleRepository, SledUserRepository, SessionStore, UserRepository, UserService,
 };
+use carbon::planes::data::{OpsLimiter, QuotaTracker, SlowLog};
+use reload::ConfigReloader;
+use shared::config::{Config, ConfigSource};
 use state::AppState;
 use std::sync::Arc;
 use std::time::Duration;
-use tracing::{info, warn, Level};
+use tracing::{info, warn};
 
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
@@ -21,31 +29,118 @@ static ALLOC: dhat::Alloc = dhat::Alloc;
 async fn main() {
     let _profiler = dhat::Profiler::new_heap();
 
+    // Load environment variables from .env file (if exists), before tracing so it picks up the log level
+    let dotenv = dotenvy::dotenv();
+    // `--config <file>` and `--set key=value` layer a TOML or YAML file and overrides around the
+    // environment
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let source = ConfigSource::from_args(&args).expect("Invalid configuration");
+    let config = Config::from_source(&source);
+
     // Initialize tracing
-    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+    let log_level = reload::init_logging(
+        &config.log_level,
+        config.log_format,
+        config.otlp_endpoint.as_deref(),
+    );
 
     info!("Starting Carbon HTTP Server...");
 
-    // Load environment variables from .env file (if exists)
-    match dotenvy::dotenv() {
+    match dotenv {
         Ok(_) => info!("Loaded environment variables from .env file"),
         Err(_) => info!("No .env file found, using system environment variables"),
     }
+    if let Some(path) = source.file_path() {
+        info!("Loaded configuration from {}", path.display());
+    }
+    for key in source.unused_keys() {
+        warn!("Configuration setting {} has no effect", key);
+    }
 
-    // Initialize auth system
-    info!("Initializing authentication system...");
-    let (auth_service, user_service, role_service) = init_auth_system().await;
-
-    // Initialize session store (1 hour TTL)
+    // Initialize session store (TTL from CARBON_SESSION_TTL_MS, 1 hour by default, renewed on
+    // access for up to CARBON_SESSION_MAX_LIFETIME_MS)
     info!("Initializing session store...");
     let session_repository = Arc::new(MokaSessionRepository::new(
         None,                               // No max sessions limit
-        Some(Duration::from_secs(3600)),    // 1 hour TTL
+        None,                               // Sessions expire individually
     ));
-    let session_store = Arc::new(SessionStore::new(session_repository));
+    let session_store = Arc::new(
+        SessionStore::new(session_repository.clone())
+            .with_ttl_ms(config.session_ttl_ms)
+            .with_max_lifetime_ms(config.session_max_lifetime_ms)
+            .with_refresh_ttl_ms(config.session_refresh_ttl_ms),
+    );
+
+    // Initialize auth system
+    info!("Initializing authentication system...");
+    let (auth_service, user_service, role_service) = init_auth_system(session_repository).await;
 
     // Initialize state
-    let state = AppState::new(auth_service, user_service, role_service, session_store).await;
+    let state = AppState::new(auth_service, user_service, role_service, session_store)
+        .await
+        .with_rate_limits(config.rate_limit)
+        .with_cache_defaults(config.cache_defaults)
+        .with_http_limits(config.http_limits)
+        .with_schedules(&config.schedules)
+        .with_trusted_proxies(&config.trusted_proxies)
+        .with_ops_limiter(Arc::new(OpsLimiter::new(config.max_ops_per_sec)))
+        .with_drop_retention(Duration::from_millis(config.drop_retention_ms));
+
+    let state = match config.slow_log {
+        Some(slow_log) => state.with_slow_log(Arc::new(SlowLog::new(slow_log))),
+        None => state,
+    };
+
+    let state = if config.quotas_enabled {
+        state.with_quotas(Arc::new(QuotaTracker::new()))
+    } else {
+        state
+    };
+
+    // Connection settings only apply at startup, the reloader takes the config
+    let http_server = config.http_server;
+
+    // Apply configuration changes on SIGHUP or POST /admin/config/reload
+    let config_reloader = Arc::new(
+        ConfigReloader::new(
+            config,
+            log_level,
+            state.rate_limits.clone(),
+            state.session_store.clone(),
+        )
+        .with_source(source),
+    );
+    config_reloader.clone().spawn_sighup_listener();
+    let state = state.with_config_reloader(config_reloader);
+
+    // Caches declared in the config, so deployments need no provisioning step after start
+    let created = provision::create_declared_caches(
+        &state.cache_manager,
+        &config.caches,
+        &config.cache_defaults,
+    )
+    .await
+    .expect("Failed to create the caches declared in the config");
+    if !created.is_empty() {
+        info!(
+            "Created caches declared in the config: {}",
+            created.join(", ")
+        );
+    }
+
+    // Remove expired entries of caches created with reap_interval_ms
+    state.cache_operations.clone().spawn_ttl_reaper();
+
+    // Keep materialized views in step with their sources
+    state
+        .cache_operations
+        .clone()
+        .spawn_view_maintenance(state.event_channel.subscribe());
+
+    // Run scheduled clears, snapshots and warm-ups of caches
+    state
+        .cache_manager
+        .spawn_scheduler(Arc::new(schedules::ScheduleRunner::new(&state)));
 
     // Build router
     let router = routes::build_router(state);
@@ -57,10 +152,10 @@ async fn main() {
     info!("Try: curl -u admin:admin123 http://localhost:8080/health");
 
     // Graceful shutdown handler
-    axum::serve(listener, router)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .unwrap();
+    // Connection info gives the rate limiter a client IP when no proxy header is set
+    serve::serve(listener, router, http_server, shutdown_signal()).await;
+
+    telemetry::shutdown();
 
     info!("Server shutdown complete. Writing dhat profiling data...");
     drop(_profiler); // Explicitly drop profiler to write output
@@ -99,7 +194,9 @@ async fn shutdown_signal() {
     info!("Shutting down gracefully...");
 }
 
-async fn init_auth_system() -> (Arc<AuthService>, Arc<UserService>, Arc<RoleService>) {
+async fn init_auth_system(
+    session_repository: Arc<MokaSessionRepository>,
+) -> (Arc<AuthService>, Arc<UserService>, Arc<RoleService>) {
     // Get home directory for auth storage
     let home_dir = std::env::var("HOME")
         .or_else(|_| std::env::var("USERPROFILE"))
@@ -124,7 +221,10 @@ async fn init_auth_system() -> (Arc<AuthService>, Arc<UserService>, Arc<RoleServ
 
     // Initialize services
     let auth_service = Arc::new(AuthService::new(user_repo.clone(), role_repo.clone()));
-    let user_service = Arc::new(UserService::new(user_repo.clone(), role_repo.clone()));
+    // Password changes end the user's sessions
+    let user_service = Arc::new(
+        UserService::new(user_repo.clone(), role_repo.clone()).with_sessions(session_repository),
+    );
     let role_service = Arc::new(RoleService::new(role_repo.clone()));
 
     // Initialize default roles
@@ -1,19 +1,47 @@
+use super::{ClientIp, RejectedCredentials};
 use axum::{
     extract::{ConnectInfo, Request, State},
-    http::{header, StatusCode},
+    http::{header, HeaderValue, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use base64::{engine::general_purpose::STANDARD, Engine};
-use carbon::auth::{AuthService, MokaSessionRepository, SessionStore, User};
+use carbon::auth::{
+    AuthError, AuthService, MokaSessionRepository, Session, SessionStore, SessionToken, User,
+};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use tracing::Span;
+
+/// Header carrying the TOTP or recovery code of users enrolled in MFA alongside Basic Auth
+pub const MFA_CODE_HEADER: &str = "X-Carbon-OTP";
+
+/// Header carrying the API key of a service account
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Query parameter carrying a session token for clients that cannot set headers, like the
+/// browser's EventSource
+pub const ACCESS_TOKEN_PARAM: &str = "access_token";
+
+/// Response header with the milliseconds left before the session expires unless used again
+pub const SESSION_REMAINING_HEADER: &str = "X-Session-Remaining-Ms";
+
+/// Routes accepting the session token as ACCESS_TOKEN_PARAM, kept to streams as URLs end up
+/// in proxy and browser logs
+const QUERY_TOKEN_PATHS: &[&str] = &["/events"];
+
+/// Session the request was authenticated with, lets long-lived responses end with it
+#[derive(Clone, Debug)]
+pub struct AuthenticatedSession {
+    pub token: SessionToken,
+}
 
 /// Shared state for authentication middleware
 #[derive(Clone)]
 pub struct AuthMiddlewareState {
     pub auth_service: Arc<AuthService>,
     pub session_store: Arc<SessionStore<MokaSessionRepository>>,
+    pub rejected_credentials: Arc<RejectedCredentials>,
 }
 
 /// Authentication middleware with session support
@@ -22,6 +50,24 @@ pub async fn auth_middleware(
     mut request: Request,
     next: Next,
 ) -> Result<Response, Response> {
+    // Service accounts send an API key instead of Authorization
+    let api_key = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string);
+    if let Some(key) = api_key {
+        return match state.auth_service.authenticate_api_key(&key).await {
+            Ok((user, api_key)) => {
+                // Scopes the request in the authorization middleware
+                attach_user(&mut request, user);
+                request.extensions_mut().insert(api_key);
+                Ok(next.run(request).await)
+            }
+            Err(_) => Err((StatusCode::UNAUTHORIZED, "Invalid API key").into_response()),
+        };
+    }
+
     // Get Authorization header
     let auth_header = request
         .headers()
@@ -30,6 +76,31 @@ pub async fn auth_middleware(
 
     let auth_header = match auth_header {
         Some(h) => h,
+        None if QUERY_TOKEN_PATHS.contains(&request.uri().path()) => {
+            let token = request.uri().query().and_then(extract_query_token);
+            let Some(token) = token else {
+                return Err((
+                    StatusCode::UNAUTHORIZED,
+                    [(header::WWW_AUTHENTICATE, "Bearer realm=\"Carbon Cache\"")],
+                    "Missing Authorization header or access_token parameter",
+                )
+                    .into_response());
+            };
+            return match state.session_store.touch_session(&token).await {
+                Ok(session) => {
+                    attach_session(&mut request, session.user.clone(), token);
+                    let mut response = next.run(request).await;
+                    insert_remaining_lifetime(&mut response, &session);
+                    Ok(response)
+                }
+                Err(_) => Err((
+                    StatusCode::UNAUTHORIZED,
+                    [(header::WWW_AUTHENTICATE, "Bearer realm=\"Carbon Cache\"")],
+                    "Invalid or expired session token",
+                )
+                    .into_response()),
+            };
+        }
         None => {
             return Err((
                 StatusCode::UNAUTHORIZED,
@@ -42,11 +113,13 @@ pub async fn auth_middleware(
 
     // Try Bearer token first (fast path)
     if let Some(token) = extract_bearer_token(auth_header) {
-        match state.session_store.validate_session(&token).await {
-            Ok(user) => {
-                // Session valid - attach user and continue
-                request.extensions_mut().insert(user);
-                return Ok(next.run(request).await);
+        match state.session_store.touch_session(&token).await {
+            Ok(session) => {
+                // Session valid and renewed - attach user and continue
+                attach_session(&mut request, session.user.clone(), token);
+                let mut response = next.run(request).await;
+                insert_remaining_lifetime(&mut response, &session);
+                return Ok(response);
             }
             Err(_) => {
                 // Invalid or expired session
@@ -73,64 +146,88 @@ pub async fn auth_middleware(
         }
     };
 
-    // Extract client IP address from headers or connection info
+    // Client address, behind trusted proxies the one they forwarded
     let client_ip = extract_client_ip(&request);
 
-    // OPTIMIZATION: Check for existing valid session FIRST (avoids expensive Argon2 verification)
-    // This is the fast path - only does 1ms session lookup instead of 250ms Argon2
-    if let Ok(Some(mut session)) = state
-        .session_store
-        .get_existing_user_session(&username)
-        .await
+    let code = request
+        .headers()
+        .get(MFA_CODE_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string);
+    let code = code.as_deref();
+
+    // Credentials that just failed are refused again without another Argon2 verification,
+    // on every Basic request before any session is looked up
+    if state
+        .rejected_credentials
+        .contains(&username, &password, code)
     {
-        // Valid session exists - update last accessed timestamp and return
-        session.update_last_accessed();
-        let _ = state.session_store.update_session(&session).await;
-
-        // Attach user to request extensions
-        request.extensions_mut().insert(session.user.clone());
-
-        // Return response with session token and reuse indicator
-        let mut response = next.run(request).await;
-        response
-            .headers_mut()
-            .insert("X-Session-Token", session.token.parse().unwrap());
-        response
-            .headers_mut()
-            .insert("X-Session-Reused", "true".parse().unwrap());
-
-        return Ok(response);
+        return Err(invalid_credentials());
     }
 
-    // No valid session found - do full authentication with Argon2 (slow path)
-    let user = match state.auth_service.authenticate(&username, &password).await {
+    // Credentials are verified on every Basic request, a session is never handed out on the
+    // username alone
+    let user = match state
+        .auth_service
+        .authenticate_with_code(&username, &password, code)
+        .await
+    {
         Ok(user) => user,
-        Err(_) => {
+        Err(AuthError::InvalidCredentials | AuthError::InvalidMfaCode) => {
+            state
+                .rejected_credentials
+                .insert(&username, &password, code)
+                .await;
+            return Err(invalid_credentials());
+        }
+        Err(AuthError::MfaLockedOut) => {
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many invalid MFA codes, try again later",
+            )
+                .into_response())
+        }
+        Err(AuthError::MfaRequired) => {
             return Err((
                 StatusCode::UNAUTHORIZED,
                 [(header::WWW_AUTHENTICATE, "Basic realm=\"Carbon Cache\"")],
-                "Invalid credentials",
+                "MFA code required in the X-Carbon-OTP header",
             )
                 .into_response())
         }
+        Err(AuthError::MfaEnrollmentRequired) => {
+            return Err((StatusCode::FORBIDDEN, "MFA enrollment required").into_response())
+        }
+        Err(_) => return Err(invalid_credentials()),
     };
 
-    // Create new session (not get_or_create - we already checked above)
-    let (session, session_reused) = match state
+    // Reuse the user's live session rather than starting one per request
+    let existing = state
         .session_store
-        .create_session(user.clone(), 3600000, client_ip)
-        .await
-    {
-        Ok(session) => (session, false), // New session created
-        Err(_) => {
-            // Failed to create session, but auth succeeded - continue without session
-            request.extensions_mut().insert(user);
-            return Ok(next.run(request).await);
+        .get_existing_user_session(&user.username)
+        .await;
+    let (session, session_reused) = match existing {
+        Ok(Some(mut session)) => {
+            session.update_last_accessed();
+            let _ = state.session_store.update_session(&session).await;
+            (session, true)
         }
+        _ => match state
+            .session_store
+            .create_session(user.clone(), state.session_store.ttl_ms(), client_ip)
+            .await
+        {
+            Ok(session) => (session, false), // New session created
+            Err(_) => {
+                // Failed to create session, but auth succeeded - continue without session
+                attach_user(&mut request, user);
+                return Ok(next.run(request).await);
+            }
+        },
     };
 
     // Attach user to request extensions
-    request.extensions_mut().insert(user);
+    attach_session(&mut request, user, session.token.clone());
 
     // Return response with session token and reuse indicator
     let mut response = next.run(request).await;
@@ -145,28 +242,34 @@ pub async fn auth_middleware(
             .parse()
             .unwrap(),
     );
+    insert_remaining_lifetime(&mut response, &session);
 
     Ok(response)
 }
 
-/// Extract client IP address from request
-/// Checks X-Forwarded-For header first, then X-Real-IP, then connection info
-fn extract_client_ip(request: &Request) -> Option<String> {
-    // Try X-Forwarded-For header (proxy/load balancer)
-    if let Some(forwarded_for) = request.headers().get("X-Forwarded-For") {
-        if let Ok(value) = forwarded_for.to_str() {
-            // X-Forwarded-For can contain multiple IPs, take the first one
-            if let Some(ip) = value.split(',').next() {
-                return Some(ip.trim().to_string());
-            }
-        }
-    }
+/// Response to Basic credentials that failed verification
+fn invalid_credentials() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Basic realm=\"Carbon Cache\"")],
+        "Invalid credentials",
+    )
+        .into_response()
+}
 
-    // Try X-Real-IP header (some proxies)
-    if let Some(real_ip) = request.headers().get("X-Real-IP") {
-        if let Ok(value) = real_ip.to_str() {
-            return Some(value.to_string());
-        }
+/// Tell the client how long `session` lasts without further use
+fn insert_remaining_lifetime(response: &mut Response, session: &Session) {
+    response.headers_mut().insert(
+        SESSION_REMAINING_HEADER,
+        HeaderValue::from(session.remaining_ttl_ms()),
+    );
+}
+
+/// Client address of a request, as resolved by `client_ip_middleware`
+/// Falls back to the connection's address, forwarding headers are never read here
+pub(crate) fn extract_client_ip(request: &Request) -> Option<String> {
+    if let Some(ClientIp(ip)) = request.extensions().get::<ClientIp>() {
+        return Some(ip.to_string());
     }
 
     // Try to get from connection info (direct connection)
@@ -210,7 +313,31 @@ fn extract_bearer_token(auth_header: &str) -> Option<String> {
     Some(parts[1].to_string())
 }
 
+/// Extract the session token from a query string such as `?cache=users&access_token=...`
+fn extract_query_token(query: &str) -> Option<String> {
+    query
+        .split('&')
+        .find_map(|pair| match pair.split_once('=') {
+            Some((ACCESS_TOKEN_PARAM, token)) if !token.is_empty() => Some(token.to_string()),
+            _ => None,
+        })
+}
+
 /// Extract authenticated user from request extensions
+/// Make `user` available to handlers and record it on the request span
+fn attach_user(request: &mut Request, user: User) {
+    Span::current().record("user", user.username.as_str());
+    request.extensions_mut().insert(user);
+}
+
+/// Attach `user` along with the session it was authenticated with
+fn attach_session(request: &mut Request, user: User, token: SessionToken) {
+    attach_user(request, user);
+    request
+        .extensions_mut()
+        .insert(AuthenticatedSession { token });
+}
+
 pub fn get_authenticated_user(request: &Request) -> Option<&User> {
     request.extensions().get::<User>()
 }
@@ -259,4 +386,80 @@ mod tests {
         assert!(extract_bearer_token("Bearer").is_none());
         assert!(extract_bearer_token("invalid").is_none());
     }
+
+    #[test]
+    fn test_extract_query_token() {
+        assert_eq!(
+            extract_query_token("cache=users&access_token=abc123"),
+            Some("abc123".to_string())
+        );
+        assert_eq!(
+            extract_query_token("access_token=abc123"),
+            Some("abc123".to_string())
+        );
+        assert!(extract_query_token("access_token=").is_none());
+        assert!(extract_query_token("cache=users&token=abc123").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth_needs_the_password_to_reuse_a_session() {
+        use axum::{body::Body, routing::get, Router};
+        use carbon::auth::defaults::create_user_role;
+        use carbon::auth::{
+            generate_session_token, RoleRepository, SledRoleRepository, SledUserRepository,
+            UserRepository, UserService,
+        };
+        use tower::ServiceExt;
+
+        let dir = std::env::temp_dir().join(format!("carbon-basic-{}", generate_session_token()));
+        let user_repo = Arc::new(SledUserRepository::new(dir.join("users.sled")).unwrap())
+            as Arc<dyn UserRepository>;
+        let role_repo = Arc::new(SledRoleRepository::new(dir.join("roles.sled")).unwrap())
+            as Arc<dyn RoleRepository>;
+        let sessions = Arc::new(MokaSessionRepository::new(None, None));
+        let users = UserService::new(user_repo.clone(), role_repo.clone());
+        let state = AuthMiddlewareState {
+            auth_service: Arc::new(AuthService::new(user_repo, role_repo.clone())),
+            session_store: Arc::new(SessionStore::new(sessions)),
+            rejected_credentials: Arc::new(RejectedCredentials::new()),
+        };
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(state, auth_middleware));
+
+        let role = role_repo.create(create_user_role()).await.unwrap();
+        users
+            .create_user(
+                "alice".to_string(),
+                "password123".to_string(),
+                vec![role.id],
+            )
+            .await
+            .unwrap();
+        let basic = |password: &str| {
+            let credentials = STANDARD.encode(format!("alice:{}", password));
+            Request::builder()
+                .uri("/")
+                .header(header::AUTHORIZATION, format!("Basic {}", credentials))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(basic("password123")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let token = response.headers()["X-Session-Token"].clone();
+
+        // A logged-in username is not enough, nor is a rejected password tried again
+        for _ in 0..2 {
+            let response = app.clone().oneshot(basic("wrong")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert!(!response.headers().contains_key("X-Session-Token"));
+        }
+
+        let response = app.oneshot(basic("password123")).await.unwrap();
+        assert_eq!(response.headers()["X-Session-Token"], token);
+        assert_eq!(response.headers()["X-Session-Reused"], "true");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
@@ -1,11 +1,15 @@
+use crate::api::ErrorResponse;
 use axum::{
-    extract::{Request, State},
-    http::StatusCode,
+    extract::{MatchedPath, Request, State},
+    http::{Method, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
+    Json,
 };
-use carbon::auth::{AuthService, Permission, User};
+use carbon::auth::{ApiKey, AuthService, Permission, User};
+use std::collections::HashMap;
 use std::sync::Arc;
+use tracing::warn;
 
 /// Check if authenticated user has required permission
 pub async fn require_permission(
@@ -98,3 +102,239 @@ pub async fn check_any_permission(
         .await
         .map_err(|_| StatusCode::FORBIDDEN)
 }
+
+/// What a protected route requires of the authenticated user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Access {
+    /// Any authenticated user, for handlers that decide per request (e.g. changing one's own password)
+    Authenticated,
+    Permission(Permission),
+    /// Every one of these, for routes acting on more than one kind of data
+    AllOf(Vec<Permission>),
+}
+
+/// Required access of every protected route, keyed by method and route pattern
+/// Requests to routes missing from the registry are refused
+#[derive(Debug, Default)]
+pub struct RoutePermissions {
+    routes: HashMap<Method, HashMap<&'static str, Access>>,
+}
+
+impl RoutePermissions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require `permission` for `method` on the route pattern `path`
+    pub fn with_permission(
+        self,
+        method: Method,
+        path: &'static str,
+        permission: Permission,
+    ) -> Self {
+        self.with_access(method, path, Access::Permission(permission))
+    }
+
+    pub fn with_access(mut self, method: Method, path: &'static str, access: Access) -> Self {
+        self.routes.entry(method).or_default().insert(path, access);
+        self
+    }
+
+    /// Access declared for a request, HEAD is answered by GET routes and checked like them
+    pub fn access(&self, method: &Method, path: &str) -> Option<&Access> {
+        let method = if method == Method::HEAD {
+            &Method::GET
+        } else {
+            method
+        };
+        self.routes.get(method)?.get(path)
+    }
+
+    /// Served routes with no declared access, the router refuses to start with any
+    pub fn undeclared<'a>(
+        &self,
+        routes: &'a [(Method, &'static str)],
+    ) -> Vec<&'a (Method, &'static str)> {
+        routes
+            .iter()
+            .filter(|(method, path)| self.access(method, path).is_none())
+            .collect()
+    }
+
+    /// Declared routes that are not served, left behind when a route is removed
+    pub fn unserved(&self, routes: &[(Method, &'static str)]) -> Vec<(Method, &'static str)> {
+        self.routes
+            .iter()
+            .flat_map(|(method, paths)| paths.keys().map(|path| (method.clone(), *path)))
+            .filter(|route| !routes.contains(route))
+            .collect()
+    }
+}
+
+/// Shared state for the authorization middleware
+#[derive(Clone)]
+pub struct AuthorizationState {
+    pub auth_service: Arc<AuthService>,
+    pub permissions: Arc<RoutePermissions>,
+}
+
+/// Authorize each request against the access its route declares in the registry
+/// Runs after authentication, routes without a declaration are denied
+pub async fn authorization_middleware(
+    State(state): State<AuthorizationState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let user = match extract_user_from_request(&request) {
+        Ok(value) => value,
+        Err(value) => return value,
+    };
+
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str);
+    let access = path.and_then(|path| state.permissions.access(request.method(), path));
+
+    let allowed = match access {
+        Some(Access::Authenticated) => true,
+        Some(Access::Permission(permission)) => state
+            .auth_service
+            .authorize(user, permission.clone())
+            .await
+            .is_ok(),
+        Some(Access::AllOf(permissions)) => state
+            .auth_service
+            .has_all_permissions(user, permissions)
+            .await
+            .is_ok(),
+        None => {
+            warn!(
+                "No permission declared for {} {}, denying",
+                request.method(),
+                path.unwrap_or_else(|| request.uri().path())
+            );
+            false
+        }
+    };
+
+    // API keys narrow what the service account's roles grant to their own scope
+    let allowed = allowed
+        && match (request.extensions().get::<ApiKey>(), access, path) {
+            (None, _, _) => true,
+            (Some(api_key), Some(Access::Permission(permission)), Some(path)) => {
+                api_key.allows(permission, path_cache(path, request.uri().path()))
+            }
+            (Some(api_key), Some(Access::AllOf(permissions)), Some(path)) => {
+                let cache = path_cache(path, request.uri().path());
+                permissions
+                    .iter()
+                    .all(|permission| api_key.allows(permission, cache))
+            }
+            (Some(_), _, _) => false,
+        };
+
+    if allowed {
+        Ok(next.run(request).await)
+    } else {
+        Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse::new("Insufficient permissions")),
+        )
+            .into_response())
+    }
+}
+
+/// Cache named by a request to the route pattern `pattern`, if any
+fn path_cache<'a>(pattern: &str, path: &'a str) -> Option<&'a str> {
+    let cache_param = if pattern.starts_with("/admin/caches/") {
+        "{name}"
+    } else {
+        "{cache_name}"
+    };
+    pattern
+        .split('/')
+        .zip(path.split('/'))
+        .find_map(|(param, segment)| (param == cache_param).then_some(segment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions() -> RoutePermissions {
+        RoutePermissions::new()
+            .with_permission(
+                Method::GET,
+                "/cache/{cache_name}/{key}",
+                Permission::ReadCache,
+            )
+            .with_permission(
+                Method::PUT,
+                "/cache/{cache_name}/{key}",
+                Permission::WriteCache,
+            )
+            .with_access(
+                Method::PUT,
+                "/admin/users/{username}/password",
+                Access::Authenticated,
+            )
+    }
+
+    #[test]
+    fn test_access_by_method_and_pattern() {
+        let permissions = permissions();
+        assert_eq!(
+            permissions.access(&Method::GET, "/cache/{cache_name}/{key}"),
+            Some(&Access::Permission(Permission::ReadCache))
+        );
+        assert_eq!(
+            permissions.access(&Method::HEAD, "/cache/{cache_name}/{key}"),
+            Some(&Access::Permission(Permission::ReadCache))
+        );
+        assert_eq!(
+            permissions.access(&Method::PUT, "/admin/users/{username}/password"),
+            Some(&Access::Authenticated)
+        );
+        // Deny by default: another method on a declared route is not declared
+        assert_eq!(
+            permissions.access(&Method::DELETE, "/cache/{cache_name}/{key}"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_undeclared_and_unserved_routes() {
+        let permissions = permissions();
+        let routes = [
+            (Method::GET, "/cache/{cache_name}/{key}"),
+            (Method::DELETE, "/cache/{cache_name}/{key}"),
+            (Method::PUT, "/admin/users/{username}/password"),
+        ];
+
+        assert_eq!(
+            permissions.undeclared(&routes),
+            [&(Method::DELETE, "/cache/{cache_name}/{key}")]
+        );
+        assert_eq!(
+            permissions.unserved(&routes),
+            [(Method::PUT, "/cache/{cache_name}/{key}")]
+        );
+    }
+
+    #[test]
+    fn test_path_cache() {
+        assert_eq!(
+            path_cache("/cache/{cache_name}/{key}", "/cache/orders/42"),
+            Some("orders")
+        );
+        assert_eq!(
+            path_cache("/admin/caches/{name}/stats", "/admin/caches/orders/stats"),
+            Some("orders")
+        );
+        assert_eq!(
+            path_cache("/admin/users/{username}", "/admin/users/ci"),
+            None
+        );
+    }
+}
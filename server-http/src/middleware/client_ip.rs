@@ -0,0 +1,187 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Address a request came from, set by `client_ip_middleware`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// One address or CIDR range of proxies in front of the server
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ProxyRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl ProxyRange {
+    fn parse(range: &str) -> Option<Self> {
+        let (address, prefix_len) = match range.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len.parse::<u8>().ok()?)),
+            None => (range, None),
+        };
+        let network: IpAddr = address.parse().ok()?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len.unwrap_or(max_len);
+        (prefix_len <= max_len).then_some(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Proxies allowed to tell the server who their clients are
+#[derive(Debug, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<ProxyRange>,
+}
+
+impl TrustedProxies {
+    /// Entries that are neither an address nor a CIDR range are left out, with a warning
+    pub fn new(proxies: &[String]) -> Self {
+        let ranges = proxies
+            .iter()
+            .filter_map(|proxy| {
+                let range = ProxyRange::parse(proxy);
+                if range.is_none() {
+                    tracing::warn!(
+                        "Ignoring trusted proxy '{}': not an address or range",
+                        proxy
+                    );
+                }
+                range
+            })
+            .collect();
+        Self { ranges }
+    }
+
+    fn trusts(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|range| range.contains(ip))
+    }
+
+    /// Client behind `peer`, read from the forwarding headers only when `peer` is trusted
+    /// X-Forwarded-For is walked from the right past trusted proxies, as the entries before
+    /// the first untrusted one could have been written by the client itself
+    pub fn client_of(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.trusts(peer) {
+            return peer;
+        }
+
+        if let Some(forwarded_for) = headers
+            .get("X-Forwarded-For")
+            .and_then(|value| value.to_str().ok())
+        {
+            let mut client = peer;
+            for hop in forwarded_for.rsplit(',') {
+                let Ok(hop) = hop.trim().parse::<IpAddr>() else {
+                    break;
+                };
+                client = hop;
+                if !self.trusts(hop) {
+                    break;
+                }
+            }
+            return client;
+        }
+
+        headers
+            .get("X-Real-IP")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(peer)
+    }
+}
+
+/// Record the client address of each request for rate limiting, sessions and the slow log
+/// Requests served without connection info, as in tests, are left without one
+pub async fn client_ip_middleware(
+    State(proxies): State<Arc<TrustedProxies>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if let Some(ConnectInfo(peer)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        let client = proxies.client_of(peer.ip(), request.headers());
+        request.extensions_mut().insert(ClientIp(client));
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ranges() {
+        let range = ProxyRange::parse("10.0.0.0/8").unwrap();
+        assert!(range.contains(ip("10.1.2.3")));
+        assert!(!range.contains(ip("11.0.0.1")));
+        assert!(!range.contains(ip("::1")));
+
+        assert!(ProxyRange::parse("::1").unwrap().contains(ip("::1")));
+        assert!(ProxyRange::parse("0.0.0.0/0")
+            .unwrap()
+            .contains(ip("8.8.8.8")));
+        assert_eq!(ProxyRange::parse("10.0.0.0/33"), None);
+        assert_eq!(ProxyRange::parse("proxy.internal"), None);
+    }
+
+    #[test]
+    fn test_headers_only_count_from_trusted_proxies() {
+        let proxies = TrustedProxies::new(&["10.0.0.0/8".to_string()]);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Forwarded-For",
+            HeaderValue::from_static("1.1.1.1, 203.0.113.7, 10.0.0.2"),
+        );
+
+        // A direct client cannot pick its address
+        assert_eq!(
+            proxies.client_of(ip("198.51.100.1"), &headers),
+            ip("198.51.100.1")
+        );
+
+        // Behind the proxies, the first hop they did not add is the client
+        assert_eq!(
+            proxies.client_of(ip("10.0.0.1"), &headers),
+            ip("203.0.113.7")
+        );
+
+        headers.clear();
+        headers.insert("X-Real-IP", HeaderValue::from_static("203.0.113.9"));
+        assert_eq!(
+            proxies.client_of(ip("10.0.0.1"), &headers),
+            ip("203.0.113.9")
+        );
+        assert_eq!(
+            TrustedProxies::default().client_of(ip("10.0.0.1"), &headers),
+            ip("10.0.0.1")
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+use shared::ErrorCode;
+
+/// Error bodies larger than this are passed on untouched
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// Give every error response a JSON body with an `error` message and a `code`
+/// Handlers that know the error set its code, the others get the code for their status
+pub async fn error_code_middleware(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    if !(status.is_client_error() || status.is_server_error()) {
+        return response;
+    }
+    let small = response
+        .body()
+        .size_hint()
+        .upper()
+        .is_some_and(|upper| upper <= MAX_ERROR_BODY_BYTES as u64);
+    if !small {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, MAX_ERROR_BODY_BYTES).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    let Some(error_body) = error_body(status, is_json, &body) else {
+        return Response::from_parts(parts, Body::from(body));
+    };
+    let body = Bytes::from(error_body.to_string());
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Body to send in place of an error response's, None when it already carries a code
+/// Bodies that are not JSON, such as extractor rejections, become the message
+fn error_body(status: StatusCode, is_json: bool, body: &[u8]) -> Option<Value> {
+    let code = ErrorCode::from_http_status(status.as_u16()).as_str();
+
+    if is_json {
+        return match serde_json::from_slice(body) {
+            Ok(Value::Object(mut object)) if !object.contains_key("code") => {
+                object.insert("code".to_string(), code.into());
+                Some(Value::Object(object))
+            }
+            _ => None,
+        };
+    }
+
+    let message = String::from_utf8_lossy(body).trim().to_string();
+    let message = if message.is_empty() {
+        status
+            .canonical_reason()
+            .unwrap_or("Request failed")
+            .to_string()
+    } else {
+        message
+    };
+    Some(serde_json::json!({ "error": message, "code": code }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_body() {
+        // Bare statuses and rejections get a message and the code for their status
+        assert_eq!(
+            error_body(StatusCode::NOT_FOUND, false, b""),
+            Some(serde_json::json!({ "error": "Not Found", "code": "NOT_FOUND" }))
+        );
+        assert_eq!(
+            error_body(
+                StatusCode::UNAUTHORIZED,
+                false,
+                b"Missing authorization header"
+            ),
+            Some(serde_json::json!({
+                "error": "Missing authorization header",
+                "code": "UNAUTHORIZED",
+            }))
+        );
+
+        // JSON errors keep their fields, and their code when they have one
+        assert_eq!(
+            error_body(
+                StatusCode::TOO_MANY_REQUESTS,
+                true,
+                br#"{"error":"slow down"}"#
+            ),
+            Some(serde_json::json!({ "error": "slow down", "code": "THROTTLED" }))
+        );
+        assert_eq!(
+            error_body(
+                StatusCode::FORBIDDEN,
+                true,
+                br#"{"error":"over quota","code":"QUOTA_EXCEEDED"}"#
+            ),
+            None
+        );
+    }
+}
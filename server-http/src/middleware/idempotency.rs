@@ -0,0 +1,295 @@
+use crate::api::ErrorResponse;
+use axum::{
+    body::{Body, Bytes},
+    extract::{MatchedPath, Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use carbon::auth::User;
+use moka::future::Cache;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Header clients set to make retries of a request safe
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Header marking a response replayed from an earlier request with the same key
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Keys remembered at once, the least recently used are forgotten first
+const MAX_KEYS: u64 = 10_000;
+
+/// How long a key is remembered
+const KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const MAX_KEY_LEN: usize = 255;
+
+/// Response of a request made with an idempotency key, replayed to its retries
+pub struct StoredResponse {
+    /// Digest of the request body, retries must send the same body
+    fingerprint: [u8; 32],
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl StoredResponse {
+    async fn capture(fingerprint: [u8; 32], response: Response) -> Self {
+        let (parts, body) = response.into_parts();
+        match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(body) => Self {
+                fingerprint,
+                status: parts.status,
+                headers: parts.headers,
+                body,
+            },
+            Err(_) => Self {
+                fingerprint,
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                headers: HeaderMap::new(),
+                body: Bytes::new(),
+            },
+        }
+    }
+
+    fn to_response(&self, replayed: bool) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        if replayed {
+            response
+                .headers_mut()
+                .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+        }
+        response
+    }
+}
+
+/// What became of a request made with an idempotency key
+pub enum Outcome {
+    /// The request ran, its response is stored unless it was a server error
+    Fresh(Arc<StoredResponse>),
+    /// An earlier request with the key already ran, this is its response
+    Replayed(Arc<StoredResponse>),
+    /// The key was used before with a different body
+    Mismatch,
+}
+
+/// Recent idempotency keys with the responses of their requests
+/// Concurrent requests with the same key wait for the first one rather than running again
+pub struct IdempotencyKeys {
+    responses: Cache<String, Arc<StoredResponse>>,
+}
+
+impl IdempotencyKeys {
+    pub fn new() -> Self {
+        Self {
+            responses: Cache::builder()
+                .max_capacity(MAX_KEYS)
+                .time_to_live(KEY_TTL)
+                .build(),
+        }
+    }
+
+    /// Run `request` unless `key` was seen, server errors are not stored so they can be retried
+    pub async fn run<F>(&self, key: String, fingerprint: [u8; 32], request: F) -> Outcome
+    where
+        F: Future<Output = StoredResponse>,
+    {
+        let entry = self
+            .responses
+            .entry(key)
+            .or_try_insert_with(async move {
+                let stored = Arc::new(request.await);
+                if stored.status.is_server_error() {
+                    Err(stored)
+                } else {
+                    Ok(stored)
+                }
+            })
+            .await;
+
+        match entry {
+            Ok(entry) if entry.is_fresh() => Outcome::Fresh(entry.into_value()),
+            Ok(entry) if entry.value().fingerprint == fingerprint => {
+                Outcome::Replayed(entry.into_value())
+            }
+            Ok(_) => Outcome::Mismatch,
+            Err(stored) => Outcome::Fresh(Arc::clone(&*stored)),
+        }
+    }
+}
+
+impl Default for IdempotencyKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// State for idempotency middleware
+#[derive(Clone)]
+pub struct IdempotencyState {
+    pub keys: Arc<IdempotencyKeys>,
+    /// Method and route pattern of the requests that honour the header
+    pub routes: Arc<HashSet<(Method, &'static str)>>,
+    pub max_body_bytes: usize,
+}
+
+/// Replay the response of an earlier request with the same `Idempotency-Key`, so clients can
+/// retry creates and drops after a timeout without duplicating them
+/// Keys are scoped to the user and path, so clients cannot see each other's responses
+pub async fn idempotency_middleware(
+    State(state): State<IdempotencyState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let covered = request
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|path| {
+            state
+                .routes
+                .contains(&(request.method().clone(), path.as_str()))
+        });
+    if !covered {
+        return next.run(request).await;
+    }
+
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LEN => key.to_string(),
+        _ => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Idempotency-Key must be 1 to {} visible ASCII characters",
+                    MAX_KEY_LEN
+                ),
+            )
+        }
+    };
+    let username = request
+        .extensions()
+        .get::<User>()
+        .map(|user| user.username.clone())
+        .unwrap_or_default();
+    // The query is part of the scope, dropping with ?purge=true is a different request
+    let scoped_key = format!(
+        "{}\n{}\n{}\n{}",
+        username,
+        request.method(),
+        request.uri(),
+        key
+    );
+
+    // The body is read up front so a retry with a different one can be refused
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, state.max_body_bytes).await {
+        Ok(body) => body,
+        Err(_) => {
+            return error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "Request body exceeds the limit of {} bytes",
+                    state.max_body_bytes
+                ),
+            )
+        }
+    };
+    let fingerprint: [u8; 32] = Sha256::digest(&body).into();
+    let request = Request::from_parts(parts, Body::from(body));
+
+    let outcome = state
+        .keys
+        .run(scoped_key, fingerprint, async move {
+            StoredResponse::capture(fingerprint, next.run(request).await).await
+        })
+        .await;
+
+    match outcome {
+        Outcome::Fresh(stored) => stored.to_response(false),
+        Outcome::Replayed(stored) => stored.to_response(true),
+        Outcome::Mismatch => error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Idempotency-Key was already used with a different request body".to_string(),
+        ),
+    }
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(ErrorResponse::new(message))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn stored(status: StatusCode, body: &'static str) -> StoredResponse {
+        StoredResponse {
+            fingerprint: [1; 32],
+            status,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_replay_the_first_response() {
+        let keys = IdempotencyKeys::new();
+
+        let first = keys
+            .run("k".to_string(), [1; 32], async {
+                stored(StatusCode::OK, "created")
+            })
+            .await;
+        assert!(matches!(first, Outcome::Fresh(_)));
+
+        // A retry gets the stored response without running again
+        let ran = AtomicBool::new(false);
+        let retry = keys
+            .run("k".to_string(), [1; 32], async {
+                ran.store(true, Ordering::SeqCst);
+                stored(StatusCode::OK, "created again")
+            })
+            .await;
+        match retry {
+            Outcome::Replayed(stored) => assert_eq!(stored.body, "created"),
+            _ => panic!("expected a replay"),
+        }
+        assert!(!ran.load(Ordering::SeqCst));
+
+        let changed = keys
+            .run("k".to_string(), [2; 32], async {
+                stored(StatusCode::OK, "other")
+            })
+            .await;
+        assert!(matches!(changed, Outcome::Mismatch));
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_are_not_stored() {
+        let keys = IdempotencyKeys::new();
+
+        let failed = keys
+            .run("k".to_string(), [1; 32], async {
+                stored(StatusCode::INTERNAL_SERVER_ERROR, "")
+            })
+            .await;
+        assert!(matches!(failed, Outcome::Fresh(_)));
+
+        let retry = keys
+            .run("k".to_string(), [1; 32], async {
+                stored(StatusCode::OK, "created")
+            })
+            .await;
+        assert!(matches!(retry, Outcome::Fresh(_)));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::api::ErrorResponse;
+use axum::{
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use shared::config::HttpLimits;
+use std::time::Duration;
+use tracing::warn;
+
+/// Milliseconds the client waits for its response, shortens the configured request timeout
+pub const REQUEST_TIMEOUT_HEADER: &str = "x-request-timeout";
+
+/// Reject oversized bodies up front and bound the time taken to respond
+/// The error bodies state the configured limit so clients can adjust
+/// A request that runs out of time is dropped where it stands, so work nobody waits for stops
+/// at its next await
+pub async fn request_limits_middleware(
+    State(limits): State<HttpLimits>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Bulk loads stream for as long as the client sends, each record is held to
+    // max_body_bytes by the handler instead
+    if is_bulk_load(&request) {
+        return next.run(request).await;
+    }
+
+    if let Some(length) = content_length(&request) {
+        let max_bytes = if is_raw_value_upload(&request) {
+            limits.max_value_bytes
+        } else {
+            limits.max_body_bytes
+        };
+        if length > max_bytes {
+            return limit_error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "Request body of {} bytes exceeds the limit of {} bytes",
+                    length, max_bytes
+                ),
+            );
+        }
+    }
+
+    let path = request.uri().path().to_string();
+    let timeout_ms = match client_timeout_ms(&request) {
+        Ok(Some(client_ms)) => client_ms.min(limits.request_timeout_ms),
+        Ok(None) => limits.request_timeout_ms,
+        Err(error) => return limit_error(StatusCode::BAD_REQUEST, error),
+    };
+    let timeout = Duration::from_millis(timeout_ms);
+
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!("Request timed out: path={}", path);
+            limit_error(
+                StatusCode::REQUEST_TIMEOUT,
+                format!("Request did not complete within {} ms", timeout_ms),
+            )
+        }
+    }
+}
+
+/// X-Request-Timeout in milliseconds, zero is refused as the request could never complete
+fn client_timeout_ms(request: &Request) -> Result<Option<u64>, String> {
+    let Some(value) = request.headers().get(REQUEST_TIMEOUT_HEADER) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|ms| *ms > 0)
+        .map(Some)
+        .ok_or_else(|| {
+            format!(
+                "Invalid {} header, expected a positive number of milliseconds",
+                REQUEST_TIMEOUT_HEADER
+            )
+        })
+}
+
+fn content_length(request: &Request) -> Option<usize> {
+    request
+        .headers()
+        .get(header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// PUT of a value with a non-JSON Content-Type, streamed by the handler under max_value_bytes
+fn is_raw_value_upload(request: &Request) -> bool {
+    request.method() == Method::PUT
+        && request.uri().path().starts_with("/cache/")
+        && request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| {
+                !value
+                    .trim_start()
+                    .to_ascii_lowercase()
+                    .starts_with("application/json")
+            })
+}
+
+/// POST /cache/{name}/bulkload
+fn is_bulk_load(request: &Request) -> bool {
+    request.method() == Method::POST
+        && request.uri().path().starts_with("/cache/")
+        && request.uri().path().ends_with("/bulkload")
+}
+
+fn limit_error(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse::new(error))).into_response()
+}
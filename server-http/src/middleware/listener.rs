@@ -0,0 +1,100 @@
+use crate::api::ErrorResponse;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use shared::config::{ListenerAuth, ListenerConfig, ListenerRoutes};
+
+/// Routes and credentials one HTTP listener accepts
+#[derive(Clone, Copy, Debug)]
+pub struct ListenerScope {
+    pub routes: ListenerRoutes,
+    pub auth: ListenerAuth,
+}
+
+impl ListenerScope {
+    pub fn of(listener: &ListenerConfig) -> Self {
+        Self {
+            routes: listener.routes,
+            auth: listener.auth,
+        }
+    }
+
+    /// Health checks and login are served everywhere
+    fn serves(&self, path: &str) -> bool {
+        if path.starts_with("/health") || path.starts_with("/auth/") {
+            return true;
+        }
+        let admin = path.starts_with("/admin") || path == "/metrics";
+        match self.routes {
+            ListenerRoutes::All => true,
+            ListenerRoutes::Data => !admin,
+            ListenerRoutes::Admin => admin,
+        }
+    }
+}
+
+/// Hide the routes a listener does not serve and turn away credentials it does not accept
+pub async fn listener_scope_middleware(
+    State(scope): State<ListenerScope>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !scope.serves(request.uri().path()) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new("Not served on this listener")),
+        )
+            .into_response();
+    }
+
+    let basic = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("Basic "));
+    // Login itself takes a password
+    if scope.auth == ListenerAuth::Session && basic && !request.uri().path().starts_with("/auth/") {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer realm=\"Carbon Cache\"")],
+            Json(ErrorResponse::new(
+                "This listener only accepts session tokens, log in at /auth/login",
+            )),
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes_split_admin_and_data_routes() {
+        let scope = |routes| ListenerScope {
+            routes,
+            auth: ListenerAuth::Any,
+        };
+
+        let admin = scope(ListenerRoutes::Admin);
+        assert!(admin.serves("/admin/caches"));
+        assert!(admin.serves("/metrics"));
+        assert!(!admin.serves("/cache/orders/1"));
+
+        let data = scope(ListenerRoutes::Data);
+        assert!(data.serves("/cache/orders/1"));
+        assert!(data.serves("/events"));
+        assert!(!data.serves("/admin/caches"));
+
+        for scope in [admin, data, scope(ListenerRoutes::All)] {
+            assert!(scope.serves("/health/ready"));
+            assert!(scope.serves("/auth/login"));
+        }
+    }
+}
@@ -1,5 +1,32 @@
 pub mod authentication;
 pub mod authorization;
+pub mod client_ip;
+pub mod error_codes;
+pub mod idempotency;
+pub mod limits;
+pub mod listener;
+pub mod quota;
+pub mod rate_limit;
+pub mod rejected_credentials;
+pub mod request_context;
 
-pub use authentication::{auth_middleware, AuthMiddlewareState};
-pub use authorization::check_permission;
+pub use authentication::{
+    auth_middleware, AuthMiddlewareState, AuthenticatedSession, ACCESS_TOKEN_PARAM, API_KEY_HEADER,
+    MFA_CODE_HEADER,
+};
+pub use authorization::{
+    authorization_middleware, check_permission, Access, AuthorizationState, RoutePermissions,
+};
+pub use client_ip::{client_ip_middleware, ClientIp, TrustedProxies};
+pub use error_codes::error_code_middleware;
+pub use idempotency::{
+    idempotency_middleware, IdempotencyKeys, IdempotencyState, IDEMPOTENCY_KEY_HEADER,
+};
+pub use limits::{request_limits_middleware, REQUEST_TIMEOUT_HEADER};
+pub use listener::{listener_scope_middleware, ListenerScope};
+pub use quota::{quota_middleware, QuotaState};
+pub use rate_limit::{rate_limit_middleware, RateLimiter, RateLimits};
+pub use rejected_credentials::RejectedCredentials;
+pub use request_context::{
+    caller_middleware, make_request_span, record_response, REQUEST_ID_HEADER,
+};
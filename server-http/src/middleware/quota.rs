@@ -0,0 +1,52 @@
+use crate::api::ErrorResponse;
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use carbon::auth::{AuthService, User};
+use carbon::planes::data::{with_principal, Principal, QuotaTracker};
+use std::sync::Arc;
+
+/// State for quota middleware
+#[derive(Clone)]
+pub struct QuotaState {
+    pub auth_service: Arc<AuthService>,
+    /// None when quotas are off, requests then pass straight through
+    pub quotas: Option<Arc<QuotaTracker>>,
+}
+
+/// Charge the writes of a request to its user and hold them to the user's quota
+pub async fn quota_middleware(
+    State(state): State<QuotaState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Reads are never charged, so their quota is not worth resolving
+    if state.quotas.is_none() || matches!(*request.method(), Method::GET | Method::HEAD) {
+        return next.run(request).await;
+    }
+    let Some(user) = request.extensions().get::<User>() else {
+        return next.run(request).await;
+    };
+
+    let quota = match state.auth_service.effective_quota(user).await {
+        Ok(quota) => quota,
+        Err(e) => {
+            tracing::error!("Failed to resolve quota of {}: {}", user.username, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::new("Failed to resolve quota")),
+            )
+                .into_response();
+        }
+    };
+    let principal = Principal {
+        name: user.username.clone(),
+        quota,
+    };
+
+    with_principal(principal, next.run(request)).await
+}
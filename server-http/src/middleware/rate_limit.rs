@@ -0,0 +1,289 @@
+use crate::middleware::authentication::{extract_client_ip, get_authenticated_user};
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use shared::config::{RateLimit, RateLimitConfig};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Number of tracked clients above which idle buckets are pruned
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Tokens left for one client and when they were last topped up
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Token-bucket limiter keyed by client (authenticated user or IP address)
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: DashMap<String, TokenBucket>,
+    throttled: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: DashMap::new(),
+            throttled: AtomicU64::new(0),
+        }
+    }
+
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// Requests rejected since startup
+    pub fn throttled(&self) -> u64 {
+        self.throttled.load(Ordering::Relaxed)
+    }
+
+    pub fn tracked_clients(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// Take a token for `client`, or return how long until one is available
+    pub fn try_acquire(&self, client: &str) -> Result<(), Duration> {
+        self.try_acquire_at(client, Instant::now())
+    }
+
+    fn try_acquire_at(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        if self.buckets.len() > MAX_TRACKED_CLIENTS {
+            self.prune(now);
+        }
+
+        let rate = self.limit.requests_per_second as f64;
+        let burst = self.limit.burst as f64;
+
+        let mut bucket = self
+            .buckets
+            .entry(client.to_string())
+            .or_insert_with(|| TokenBucket {
+                tokens: burst,
+                refilled_at: now,
+            });
+
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * rate).min(burst);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    /// Forget clients whose bucket has refilled completely, they start full again anyway
+    /// When more clients than that are active, the least recently seen half is forgotten too,
+    /// so the map stays bounded however many addresses send requests
+    fn prune(&self, now: Instant) {
+        let refill = Duration::from_secs_f64(
+            self.limit.burst as f64 / self.limit.requests_per_second as f64,
+        );
+        self.buckets
+            .retain(|_, bucket| now.saturating_duration_since(bucket.refilled_at) < refill);
+        if self.buckets.len() <= MAX_TRACKED_CLIENTS {
+            return;
+        }
+
+        let mut refilled: Vec<Instant> = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.refilled_at)
+            .collect();
+        let keep = MAX_TRACKED_CLIENTS / 2;
+        let cutoff_index = refilled.len() - keep;
+        let (_, cutoff, _) = refilled.select_nth_unstable(cutoff_index);
+        let cutoff = *cutoff;
+        self.buckets
+            .retain(|_, bucket| bucket.refilled_at >= cutoff);
+    }
+}
+
+/// Limiters for the data (cache, query, events) and admin route groups
+/// Swappable at runtime when the configuration is reloaded
+#[derive(Default)]
+pub struct RateLimits {
+    data: RwLock<Option<Arc<RateLimiter>>>,
+    admin: RwLock<Option<Arc<RateLimiter>>>,
+}
+
+impl RateLimits {
+    pub fn new(config: RateLimitConfig) -> Self {
+        let limits = Self::default();
+        limits.update(config);
+        limits
+    }
+
+    pub fn data(&self) -> Option<Arc<RateLimiter>> {
+        self.data.read().unwrap().clone()
+    }
+
+    pub fn admin(&self) -> Option<Arc<RateLimiter>> {
+        self.admin.read().unwrap().clone()
+    }
+
+    /// Apply a new configuration
+    /// A group whose limit is unchanged keeps its limiter, and with it the clients' buckets
+    pub fn update(&self, config: RateLimitConfig) {
+        for (slot, limit) in [(&self.data, config.data), (&self.admin, config.admin)] {
+            let mut limiter = slot.write().unwrap();
+            if limiter.as_ref().map(|current| current.limit()) != limit {
+                *limiter = limit.map(|limit| Arc::new(RateLimiter::new(limit)));
+            }
+        }
+    }
+
+    fn for_path(&self, path: &str) -> Option<Arc<RateLimiter>> {
+        if path.starts_with("/admin") {
+            self.admin()
+        } else {
+            self.data()
+        }
+    }
+}
+
+/// Rate limiting middleware
+/// Must run after authentication so authenticated requests are limited per user,
+/// anonymous requests (login) are limited per IP address
+pub async fn rate_limit_middleware(
+    State(limits): State<Arc<RateLimits>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(limiter) = limits.for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    let client = match get_authenticated_user(&request) {
+        Some(user) => format!("user:{}", user.username),
+        None => format!(
+            "ip:{}",
+            extract_client_ip(&request).unwrap_or_else(|| "unknown".to_string())
+        ),
+    };
+
+    match limiter.try_acquire(&client) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            warn!(
+                "Rate limit exceeded: client={}, path={}",
+                client,
+                request.uri().path()
+            );
+
+            // Retry-After is in whole seconds, round up so the client does not retry too early
+            let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, seconds.to_string())],
+                "Rate limit exceeded",
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimit {
+            requests_per_second: 10,
+            burst: 2,
+        })
+    }
+
+    #[test]
+    fn test_burst_then_throttle() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at("ip:1.2.3.4", now).is_ok());
+        assert!(limiter.try_acquire_at("ip:1.2.3.4", now).is_ok());
+
+        let retry_after = limiter.try_acquire_at("ip:1.2.3.4", now).unwrap_err();
+        assert_eq!(retry_after, Duration::from_millis(100));
+        assert_eq!(limiter.throttled(), 1);
+
+        // Other clients have their own bucket
+        assert!(limiter.try_acquire_at("user:alice", now).is_ok());
+    }
+
+    #[test]
+    fn test_refill_is_capped_at_burst() {
+        let limiter = limiter();
+        let start = Instant::now();
+        for _ in 0..2 {
+            limiter.try_acquire_at("user:alice", start).unwrap();
+        }
+
+        let later = start + Duration::from_secs(60);
+        assert!(limiter.try_acquire_at("user:alice", later).is_ok());
+        assert!(limiter.try_acquire_at("user:alice", later).is_ok());
+        assert!(limiter.try_acquire_at("user:alice", later).is_err());
+    }
+
+    #[test]
+    fn test_tracked_clients_stay_bounded() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_second: 1,
+            burst: 10,
+        });
+        let start = Instant::now();
+        for client in 0..MAX_TRACKED_CLIENTS * 2 {
+            let now = start + Duration::from_millis(client as u64);
+            limiter
+                .try_acquire_at(&format!("ip:{}", client), now)
+                .unwrap();
+        }
+        assert!(limiter.tracked_clients() <= MAX_TRACKED_CLIENTS + 1);
+    }
+
+    #[test]
+    fn test_admin_routes_use_admin_limiter() {
+        let limits = RateLimits::new(RateLimitConfig {
+            data: None,
+            admin: Some(RateLimit {
+                requests_per_second: 1,
+                burst: 1,
+            }),
+        });
+
+        assert!(limits.for_path("/admin/caches").is_some());
+        assert!(limits.for_path("/cache/users/alice").is_none());
+    }
+
+    #[test]
+    fn test_update_keeps_unchanged_limiters() {
+        let limit = RateLimit {
+            requests_per_second: 5,
+            burst: 5,
+        };
+        let limits = RateLimits::new(RateLimitConfig {
+            data: Some(limit),
+            admin: Some(limit),
+        });
+        let data = limits.data().unwrap();
+
+        limits.update(RateLimitConfig {
+            data: Some(limit),
+            admin: None,
+        });
+
+        assert!(Arc::ptr_eq(&data, &limits.data().unwrap()));
+        assert!(limits.admin().is_none());
+    }
+}
@@ -0,0 +1,106 @@
+use carbon::auth::generate_session_token;
+use moka::future::Cache;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// Credentials remembered at once, the least recently used are forgotten first
+const MAX_ENTRIES: u64 = 100_000;
+
+/// How long rejected credentials are refused without verifying them again
+const REJECTION_TTL: Duration = Duration::from_secs(30);
+
+/// Basic credentials that recently failed verification, so retries of the same wrong password
+/// are refused without another Argon2 hash
+/// Only a salted digest of each attempt is kept, and correct credentials are never cached
+pub struct RejectedCredentials {
+    /// Digest of each attempt, with the digest of its username to forget a user's at once
+    digests: Cache<[u8; 32], [u8; 32]>,
+    /// Random per process, so digests cannot be matched against precomputed ones
+    salt: String,
+}
+
+impl RejectedCredentials {
+    pub fn new() -> Self {
+        Self {
+            digests: Cache::builder()
+                .max_capacity(MAX_ENTRIES)
+                .time_to_live(REJECTION_TTL)
+                .support_invalidation_closures()
+                .build(),
+            salt: generate_session_token(),
+        }
+    }
+
+    /// Whether these credentials were rejected in the last REJECTION_TTL
+    pub fn contains(&self, username: &str, password: &str, code: Option<&str>) -> bool {
+        self.digests
+            .contains_key(&self.digest(&[username, password, code.unwrap_or("")]))
+    }
+
+    /// Remember that these credentials were rejected
+    pub async fn insert(&self, username: &str, password: &str, code: Option<&str>) {
+        self.digests
+            .insert(
+                self.digest(&[username, password, code.unwrap_or("")]),
+                self.digest(&[username]),
+            )
+            .await;
+    }
+
+    /// Forget the rejected attempts of `username`, once their password was changed or reset
+    /// or they were deleted, so the new password is not refused as one of them
+    pub fn forget_user(&self, username: &str) {
+        let user = self.digest(&[username]);
+        if let Err(e) = self
+            .digests
+            .invalidate_entries_if(move |_, rejected_user| *rejected_user == user)
+        {
+            tracing::warn!("Failed to forget rejected credentials: {}", e);
+        }
+    }
+
+    fn digest(&self, fields: &[&str]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        // Length prefixes keep "ab" + "c" apart from "a" + "bc"
+        for field in std::iter::once(self.salt.as_str()).chain(fields.iter().copied()) {
+            hasher.update((field.len() as u64).to_be_bytes());
+            hasher.update(field.as_bytes());
+        }
+        hasher.finalize().into()
+    }
+}
+
+impl Default for RejectedCredentials {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_only_rejected_attempts_are_remembered() {
+        let rejected = RejectedCredentials::new();
+        rejected.insert("admin", "wrong", None).await;
+
+        assert!(rejected.contains("admin", "wrong", None));
+        assert!(!rejected.contains("admin", "right", None));
+        assert!(!rejected.contains("other", "wrong", None));
+        // A second factor added to the same password is verified again
+        assert!(!rejected.contains("admin", "wrong", Some("123456")));
+    }
+
+    #[tokio::test]
+    async fn test_forget_user() {
+        let rejected = RejectedCredentials::new();
+        rejected.insert("admin", "new-password", None).await;
+        rejected.insert("other", "wrong", None).await;
+
+        // The password an admin just reset to is verified again
+        rejected.forget_user("admin");
+        assert!(!rejected.contains("admin", "new-password", None));
+        assert!(rejected.contains("other", "wrong", None));
+    }
+}
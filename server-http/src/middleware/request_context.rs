@@ -0,0 +1,87 @@
+use crate::middleware::authentication::{extract_client_ip, get_authenticated_user};
+use crate::telemetry::set_parent_from_headers;
+use axum::{
+    body::Body,
+    http::{HeaderName, Request, Response},
+    middleware::Next,
+};
+use carbon::planes::data::with_caller;
+use std::time::Duration;
+use tracing::{field, info, info_span, Span};
+
+/// Set on every response, taken from the request when the client sent one
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Span wrapping a whole request, `user` is filled in by the authentication middleware
+pub fn make_request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    let path = request.uri().path();
+
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %path,
+        cache = cache_name(path).unwrap_or_default(),
+        user = field::Empty,
+        status = field::Empty,
+        latency_ms = field::Empty,
+    );
+
+    // Join the caller's distributed trace, if any
+    set_parent_from_headers(&span, request.headers());
+    span
+}
+
+/// Record the outcome on the request span and log the completed request
+pub fn record_response(response: &Response<Body>, latency: Duration, span: &Span) {
+    let latency_ms = latency.as_secs_f64() * 1000.0;
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", latency_ms);
+
+    info!(
+        status = response.status().as_u16(),
+        latency_ms, "request completed"
+    );
+}
+
+/// Attribute the cache operations of a request to its user (or IP address) in the slow log
+pub async fn caller_middleware(request: Request<Body>, next: Next) -> Response<Body> {
+    let caller = match get_authenticated_user(&request) {
+        Some(user) => format!("http:{}", user.username),
+        None => format!(
+            "http:{}",
+            extract_client_ip(&request).unwrap_or_else(|| "unknown".to_string())
+        ),
+    };
+
+    with_caller(caller, next.run(request)).await
+}
+
+/// Cache targeted by data (`/cache/{name}/...`) and admin (`/admin/caches/{name}`) routes
+fn cache_name(path: &str) -> Option<&str> {
+    let mut segments = path.trim_start_matches('/').split('/');
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some("cache"), Some(name), _) | (Some("admin"), Some("caches"), Some(name)) => Some(name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_name() {
+        assert_eq!(cache_name("/cache/users/alice"), Some("users"));
+        assert_eq!(cache_name("/cache/users/query"), Some("users"));
+        assert_eq!(cache_name("/admin/caches/users/indexes"), Some("users"));
+        assert_eq!(cache_name("/admin/caches"), None);
+        assert_eq!(cache_name("/admin/users/alice"), None);
+        assert_eq!(cache_name("/health"), None);
+    }
+}
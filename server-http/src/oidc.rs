@@ -0,0 +1,270 @@
+//! OpenID Connect sign-in for the admin UI
+//! Authorization code flow with PKCE, the user's identity is read from the provider's user
+//! info endpoint over TLS rather than from the ID token, so no token signature is checked here
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use carbon::auth::{generate_session_token, ExternalIdentity};
+use dashmap::DashMap;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use shared::config::OidcConfig;
+use std::time::{Duration, Instant};
+
+/// Time a user has to sign in at the provider before the login must be restarted
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Logins awaiting their callback, beyond which new ones are refused
+const MAX_PENDING_LOGINS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OidcError {
+    /// The callback's state is unknown or its login timed out
+    UnknownState,
+    /// Too many logins are awaiting their callback
+    TooManyLogins,
+    /// The provider refused the exchange or could not be reached
+    Provider(String),
+}
+
+impl std::fmt::Display for OidcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OidcError::UnknownState => write!(f, "unknown or expired login state"),
+            OidcError::TooManyLogins => write!(f, "too many logins in progress"),
+            OidcError::Provider(e) => write!(f, "OIDC provider: {}", e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for OidcError {
+    fn from(err: reqwest::Error) -> Self {
+        OidcError::Provider(err.to_string())
+    }
+}
+
+/// Endpoints from the provider's discovery document
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+struct PendingLogin {
+    code_verifier: String,
+    started_at: Instant,
+}
+
+pub struct OidcClient {
+    config: OidcConfig,
+    metadata: ProviderMetadata,
+    http: reqwest::Client,
+    // Keyed by the state parameter sent to the provider
+    pending: DashMap<String, PendingLogin>,
+}
+
+impl OidcClient {
+    /// Read the provider's endpoints from its discovery document
+    pub async fn discover(config: OidcConfig) -> Result<Self, OidcError> {
+        let http = reqwest::Client::new();
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            config.issuer_url.trim_end_matches('/')
+        );
+        let metadata = http
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(Self::new(config, metadata, http))
+    }
+
+    fn new(config: OidcConfig, metadata: ProviderMetadata, http: reqwest::Client) -> Self {
+        Self {
+            config,
+            metadata,
+            http,
+            pending: DashMap::new(),
+        }
+    }
+
+    /// Provider URL to send the browser to, the login is remembered until its callback
+    pub fn authorization_url(&self) -> Result<String, OidcError> {
+        self.pending
+            .retain(|_, login| login.started_at.elapsed() < LOGIN_TIMEOUT);
+        if self.pending.len() >= MAX_PENDING_LOGINS {
+            return Err(OidcError::TooManyLogins);
+        }
+
+        let state = generate_session_token();
+        let code_verifier = generate_session_token();
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+        self.pending.insert(
+            state.clone(),
+            PendingLogin {
+                code_verifier,
+                started_at: Instant::now(),
+            },
+        );
+
+        let scope = self.config.scopes.join(" ");
+        reqwest::Url::parse_with_params(
+            &self.metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("scope", scope.as_str()),
+                ("state", state.as_str()),
+                ("code_challenge", code_challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map(String::from)
+        .map_err(|e| OidcError::Provider(format!("invalid authorization endpoint: {}", e)))
+    }
+
+    /// Exchange the callback's code for the user's identity, each state is accepted once
+    pub async fn identity(&self, state: &str, code: &str) -> Result<ExternalIdentity, OidcError> {
+        let (_, login) = self
+            .pending
+            .remove(state)
+            .filter(|(_, login)| login.started_at.elapsed() < LOGIN_TIMEOUT)
+            .ok_or(OidcError::UnknownState)?;
+
+        let token: TokenResponse = self
+            .http
+            .post(&self.metadata.token_endpoint)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("code_verifier", login.code_verifier.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let claims: Value = self
+            .http
+            .get(&self.metadata.userinfo_endpoint)
+            .bearer_auth(&token.access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        self.identity_from_claims(&claims)
+    }
+
+    /// Username from `preferred_username`, `email` or `sub`, roles mapped from `roles_claim`
+    fn identity_from_claims(&self, claims: &Value) -> Result<ExternalIdentity, OidcError> {
+        let username = ["preferred_username", "email", "sub"]
+            .iter()
+            .find_map(|claim| claims.get(claim)?.as_str())
+            .filter(|username| !username.is_empty())
+            .ok_or_else(|| OidcError::Provider("user info has no username".to_string()))?;
+
+        // Providers send either a list of values or a single one
+        let values: Vec<&str> = match claims.get(&self.config.roles_claim) {
+            Some(Value::Array(values)) => values.iter().filter_map(Value::as_str).collect(),
+            Some(Value::String(value)) => vec![value.as_str()],
+            _ => Vec::new(),
+        };
+
+        let mut roles: Vec<String> = self.config.default_role.iter().cloned().collect();
+        for (claim_value, role) in &self.config.claim_roles {
+            if values.contains(&claim_value.as_str()) && !roles.contains(role) {
+                roles.push(role.clone());
+            }
+        }
+
+        Ok(ExternalIdentity {
+            username: username.to_string(),
+            roles,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn client() -> OidcClient {
+        let config = OidcConfig {
+            issuer_url: "https://sso.example.com".to_string(),
+            client_id: "carbon".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "https://carbon.example.com/auth/oidc/callback".to_string(),
+            scopes: vec!["openid".to_string(), "profile".to_string()],
+            roles_claim: "groups".to_string(),
+            claim_roles: vec![
+                ("carbon-admins".to_string(), "admin".to_string()),
+                ("carbon-devs".to_string(), "user".to_string()),
+            ],
+            default_role: Some("read-only".to_string()),
+        };
+        let metadata = ProviderMetadata {
+            authorization_endpoint: "https://sso.example.com/authorize".to_string(),
+            token_endpoint: "https://sso.example.com/token".to_string(),
+            userinfo_endpoint: "https://sso.example.com/userinfo".to_string(),
+        };
+        OidcClient::new(config, metadata, reqwest::Client::new())
+    }
+
+    #[test]
+    fn test_authorization_url_remembers_login() {
+        let client = client();
+        let url = reqwest::Url::parse(&client.authorization_url().unwrap()).unwrap();
+        let params: std::collections::HashMap<_, _> = url.query_pairs().collect();
+
+        assert_eq!(params["client_id"], "carbon");
+        assert_eq!(params["scope"], "openid profile");
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert!(client.pending.contains_key(params["state"].as_ref()));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_state_rejected() {
+        assert_eq!(
+            client().identity("forged", "code").await,
+            Err(OidcError::UnknownState)
+        );
+    }
+
+    #[test]
+    fn test_identity_from_claims() {
+        let client = client();
+        let identity = client
+            .identity_from_claims(&json!({
+                "sub": "248289761001",
+                "preferred_username": "jane",
+                "groups": ["carbon-admins", "finance"],
+            }))
+            .unwrap();
+        assert_eq!(identity.username, "jane");
+        assert_eq!(identity.roles, ["read-only", "admin"]);
+
+        // A single claim value, and no preferred_username
+        let identity = client
+            .identity_from_claims(&json!({"sub": "248289761001", "groups": "carbon-devs"}))
+            .unwrap();
+        assert_eq!(identity.username, "248289761001");
+        assert_eq!(identity.roles, ["read-only", "user"]);
+
+        assert!(client.identity_from_claims(&json!({})).is_err());
+    }
+}
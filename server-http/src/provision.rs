@@ -0,0 +1,84 @@
+use crate::api::requests::CreateCacheRequest;
+use crate::validation::CacheConfigFactory;
+use bytes::Bytes;
+use carbon::planes::control::operation::AdminOperations;
+use carbon::planes::control::CacheManager;
+use carbon::ports::StorageFactory;
+use shared::config::{CacheDefaults, DeclaredCache};
+use shared::{Error, Result};
+use storage_engine::UnifiedStorageFactory;
+use tracing::info;
+
+/// Create the caches declared in the config that don't exist yet, returning the names created
+/// Every declaration is validated first, so one invalid cache creates none of them
+pub async fn create_declared_caches(
+    cache_manager: &CacheManager<Vec<u8>, Bytes>,
+    caches: &[DeclaredCache],
+    defaults: &CacheDefaults,
+) -> Result<Vec<String>> {
+    let configs = caches
+        .iter()
+        .map(|cache| {
+            let req = CreateCacheRequest::declared(cache).with_defaults(defaults);
+            CacheConfigFactory::from_request(req).map_err(|e| {
+                Error::InvalidValue(format!("Cache '{}' in the config: {}", cache.name, e))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut created = Vec::new();
+    for config in configs {
+        // Caches that failed to load are left to POST /admin/caches/{name}/retry
+        if cache_manager.cache_status(&config.name).is_some() {
+            continue;
+        }
+        let name = config.name.clone();
+        let storage = UnifiedStorageFactory.create_from_config(&config);
+        let result = cache_manager.create_cache(config, storage).await?;
+        if result.created {
+            info!("Created cache '{}' declared in the config", name);
+            created.push(name);
+        } else {
+            info!("{}", result.message);
+        }
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declared(name: &str, backend: &str) -> DeclaredCache {
+        DeclaredCache {
+            name: name.to_string(),
+            backend: backend.to_string(),
+            ..DeclaredCache::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_declared_caches_are_created_once() {
+        let cache_manager = CacheManager::new();
+        let defaults = CacheDefaults::default();
+        let caches = [declared("orders", "ttl"), declared("sessions", "ttl")];
+
+        let created = create_declared_caches(&cache_manager, &caches, &defaults)
+            .await
+            .unwrap();
+        assert_eq!(created, ["orders", "sessions"]);
+        assert!(cache_manager.cache_status("orders").is_some());
+
+        // A restart finds them in place
+        let created = create_declared_caches(&cache_manager, &caches, &defaults)
+            .await
+            .unwrap();
+        assert!(created.is_empty());
+
+        let invalid = [declared("users", "ttl"), declared("events", "fifo")];
+        assert!(create_declared_caches(&cache_manager, &invalid, &defaults)
+            .await
+            .is_err());
+        assert!(cache_manager.cache_status("users").is_none());
+    }
+}
@@ -0,0 +1,314 @@
+use crate::middleware::RateLimits;
+use carbon::auth::{MokaSessionRepository, SessionStore};
+use shared::config::{Config, ConfigSource, LogFormat};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+use tracing_subscriber::{
+    fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+};
+
+/// Handle to swap the tracing filter of the global subscriber
+#[derive(Clone)]
+pub struct LogLevel {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogLevel {
+    pub fn set(&self, level: &str) -> Result<(), String> {
+        let filter = EnvFilter::try_new(level).map_err(|e| e.to_string())?;
+        self.handle.reload(filter).map_err(|e| e.to_string())
+    }
+}
+
+/// Install the global tracing subscriber with a filter that can be changed later
+/// An invalid `level` falls back to `info`, spans are exported over OTLP when `otlp_endpoint` is set
+pub fn init_logging(level: &str, format: LogFormat, otlp_endpoint: Option<&str>) -> LogLevel {
+    let filter = EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(filter);
+
+    #[cfg(feature = "otel")]
+    let (otel, otel_error) = match otlp_endpoint.map(crate::telemetry::layer) {
+        Some(Ok(layer)) => (Some(layer), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+    #[cfg(not(feature = "otel"))]
+    let otel = None::<tracing_subscriber::layer::Identity>;
+
+    // JSON events carry the fields of the enclosing request span
+    let (json, text) = match format {
+        LogFormat::Json => (
+            Some(
+                fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(false),
+            ),
+            None,
+        ),
+        LogFormat::Text => (None, Some(fmt::layer())),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(otel)
+        .with(json)
+        .with(text)
+        .init();
+
+    #[cfg(feature = "otel")]
+    if let Some(e) = otel_error {
+        warn!("Failed to set up OTLP export: {}", e);
+    }
+    #[cfg(not(feature = "otel"))]
+    if otlp_endpoint.is_some() {
+        warn!(
+            "OTEL_EXPORTER_OTLP_ENDPOINT is set but the server was built without the otel feature"
+        );
+    }
+
+    LogLevel { handle }
+}
+
+/// Outcome of a reload, by config field name
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    pub applied: Vec<&'static str>,
+    /// Changed in the environment but only read at startup
+    pub requires_restart: Vec<&'static str>,
+    pub errors: Vec<String>,
+}
+
+/// Re-reads the environment (and .env file) and applies what can change without a restart
+/// The .env file is read into the config source, the process environment is never changed
+pub struct ConfigReloader {
+    running: Mutex<Config>,
+    /// Flags and config file the running config was read from
+    source: ConfigSource,
+    /// The .env file as it was at startup, to tell the values it put in the environment from
+    /// ones the service manager set
+    startup_env_file: HashMap<String, String>,
+    log_level: LogLevel,
+    rate_limits: Arc<RateLimits>,
+    session_store: Arc<SessionStore<MokaSessionRepository>>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        config: Config,
+        log_level: LogLevel,
+        rate_limits: Arc<RateLimits>,
+        session_store: Arc<SessionStore<MokaSessionRepository>>,
+    ) -> Self {
+        Self {
+            running: Mutex::new(config),
+            source: ConfigSource::env(),
+            startup_env_file: read_env_file().unwrap_or_default(),
+            log_level,
+            rate_limits,
+            session_store,
+        }
+    }
+
+    /// Read the config file and flags of `source` again on reload, the environment alone
+    /// otherwise
+    pub fn with_source(mut self, source: ConfigSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn reload(&self) -> ReloadReport {
+        let mut report = ReloadReport::default();
+
+        // Values the .env file put in the environment at startup follow it, values the
+        // service manager set are kept
+        let env_overrides = match read_env_file() {
+            Ok(env_file) => env_file
+                .into_iter()
+                .filter(|(name, _)| match std::env::var(name) {
+                    Ok(value) => self.startup_env_file.get(name) == Some(&value),
+                    Err(_) => true,
+                })
+                .collect(),
+            Err(e) => {
+                report
+                    .errors
+                    .push(format!("Failed to read .env file: {}", e));
+                HashMap::new()
+            }
+        };
+
+        // Nothing changes while the config file cannot be read
+        let source = match self.source.reread() {
+            Ok(source) => source.with_env_overrides(env_overrides),
+            Err(e) => {
+                warn!("Configuration reload: {}", e);
+                report.errors.push(e.to_string());
+                return report;
+            }
+        };
+        let updated = Config::from_source(&source);
+        let mut running = self.running.lock().unwrap();
+
+        if updated.log_level != running.log_level {
+            match self.log_level.set(&updated.log_level) {
+                Ok(()) => {
+                    running.log_level = updated.log_level.clone();
+                    report.applied.push("log_level");
+                }
+                Err(e) => report
+                    .errors
+                    .push(format!("Invalid log level '{}': {}", updated.log_level, e)),
+            }
+        }
+
+        if updated.rate_limit != running.rate_limit {
+            self.rate_limits.update(updated.rate_limit);
+            running.rate_limit = updated.rate_limit;
+            report.applied.push("rate_limit");
+        }
+
+        if updated.session_ttl_ms != running.session_ttl_ms {
+            // Applies to sessions created from now on
+            self.session_store.set_ttl_ms(updated.session_ttl_ms);
+            running.session_ttl_ms = updated.session_ttl_ms;
+            report.applied.push("session_ttl_ms");
+        }
+
+        if updated.session_max_lifetime_ms != running.session_max_lifetime_ms {
+            // Applies to sessions created from now on
+            self.session_store
+                .set_max_lifetime_ms(updated.session_max_lifetime_ms);
+            running.session_max_lifetime_ms = updated.session_max_lifetime_ms;
+            report.applied.push("session_max_lifetime_ms");
+        }
+
+        if updated.session_refresh_ttl_ms != running.session_refresh_ttl_ms {
+            // Applies to refresh tokens issued from now on
+            self.session_store
+                .set_refresh_ttl_ms(updated.session_refresh_ttl_ms);
+            running.session_refresh_ttl_ms = updated.session_refresh_ttl_ms;
+            report.applied.push("session_refresh_ttl_ms");
+        }
+
+        // Listeners, storage and the bootstrap admin are only read at startup
+        let restart_fields = [
+            ("host", updated.host != running.host),
+            ("http", updated.http != running.http),
+            ("tcp", updated.tcp != running.tcp),
+            ("data_dir", updated.data_dir != running.data_dir),
+            ("log_format", updated.log_format != running.log_format),
+            ("slow_log", updated.slow_log != running.slow_log),
+            (
+                "max_ops_per_sec",
+                updated.max_ops_per_sec != running.max_ops_per_sec,
+            ),
+            (
+                "otlp_endpoint",
+                updated.otlp_endpoint != running.otlp_endpoint,
+            ),
+            (
+                "admin_username",
+                updated.admin_username != running.admin_username,
+            ),
+            (
+                "admin_password",
+                updated.admin_password != running.admin_password,
+            ),
+            ("http_limits", updated.http_limits != running.http_limits),
+            ("http_server", updated.http_server != running.http_server),
+            (
+                "tcp_acceptors",
+                updated.tcp_acceptors != running.tcp_acceptors,
+            ),
+            ("tcp_limits", updated.tcp_limits != running.tcp_limits),
+            ("runtime", updated.runtime != running.runtime),
+            ("auth_store", updated.auth_store != running.auth_store),
+            ("ldap", updated.ldap != running.ldap),
+            ("oidc", updated.oidc != running.oidc),
+            (
+                "auth_local_accounts",
+                updated.auth_local_accounts != running.auth_local_accounts,
+            ),
+            (
+                "auth_key_file",
+                updated.auth_key_file != running.auth_key_file,
+            ),
+            ("auth_key", updated.auth_key != running.auth_key),
+            (
+                "auth_encrypt_at_rest",
+                updated.auth_encrypt_at_rest != running.auth_encrypt_at_rest,
+            ),
+            (
+                "admin_response_max_age_ms",
+                updated.admin_response_max_age_ms != running.admin_response_max_age_ms,
+            ),
+            (
+                "quotas_enabled",
+                updated.quotas_enabled != running.quotas_enabled,
+            ),
+            (
+                "drop_retention_ms",
+                updated.drop_retention_ms != running.drop_retention_ms,
+            ),
+            ("event_sink", updated.event_sink != running.event_sink),
+            ("change_log", updated.change_log != running.change_log),
+            (
+                "cache_defaults",
+                updated.cache_defaults != running.cache_defaults,
+            ),
+            ("listeners", updated.listeners != running.listeners),
+            ("caches", updated.caches != running.caches),
+        ];
+        report.requires_restart = restart_fields
+            .into_iter()
+            .filter(|(_, changed)| *changed)
+            .map(|(field, _)| field)
+            .collect();
+
+        info!(
+            "Configuration reloaded: applied={:?}, requires_restart={:?}",
+            report.applied, report.requires_restart
+        );
+        for error in &report.errors {
+            warn!("Configuration reload: {}", error);
+        }
+
+        report
+    }
+
+    /// Reload whenever the process receives SIGHUP
+    #[cfg(unix)]
+    pub fn spawn_sighup_listener(self: Arc<Self>) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                warn!("Failed to install SIGHUP handler: {}", e);
+                return;
+            }
+        };
+
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                info!("Received SIGHUP, reloading configuration");
+                self.reload();
+            }
+        });
+    }
+
+    #[cfg(not(unix))]
+    pub fn spawn_sighup_listener(self: Arc<Self>) {}
+}
+
+/// Variables of the .env file, as `dotenvy::dotenv` finds it, without setting them
+/// No file reads as no variables
+fn read_env_file() -> Result<HashMap<String, String>, dotenvy::Error> {
+    match dotenvy::dotenv_iter() {
+        Ok(vars) => vars.collect(),
+        Err(e) if e.not_found() => Ok(HashMap::new()),
+        Err(e) => Err(e),
+    }
+}
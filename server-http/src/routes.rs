@@ -1,12 +1,26 @@
 use crate::handlers;
-use crate::middleware::{auth_middleware, AuthMiddlewareState};
+use crate::middleware::{
+    auth_middleware, authorization_middleware, caller_middleware, client_ip_middleware,
+    error_code_middleware, idempotency_middleware, listener_scope_middleware, make_request_span,
+    quota_middleware, rate_limit_middleware, record_response, request_limits_middleware, Access,
+    AuthMiddlewareState, AuthorizationState, IdempotencyState, ListenerScope, QuotaState,
+    RoutePermissions, REQUEST_ID_HEADER,
+};
 use crate::state::AppState;
 use axum::{
+    extract::DefaultBodyLimit,
+    handler::Handler,
+    http::Method,
     middleware,
-    routing::{delete, get, post, put},
+    routing::{get, on, post, MethodFilter},
     Router,
 };
+use carbon::auth::Permission;
+use shared::config::ListenerConfig;
+use std::collections::HashSet;
+use std::sync::Arc;
 use tower_http::normalize_path::NormalizePathLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::trace::TraceLayer;
 
 /// Build and configure the application router
@@ -14,58 +28,88 @@ pub fn build_router(state: AppState) -> Router {
     // Public routes (no authentication required)
     let public_routes = Router::new()
         .route("/health", get(handlers::health_check))
+        // Kubernetes probes, unauthenticated like /health
+        .route("/health/live", get(handlers::liveness))
+        .route("/health/ready", get(handlers::readiness))
         .with_state(state.clone());
 
     // Auth routes (login/logout endpoints)
     let auth_state = handlers::AuthHandlerState {
         auth_service: state.auth_service.clone(),
         session_store: state.session_store.clone(),
+        oidc: state.oidc.clone(),
     };
 
+    // Anonymous, so login attempts are rate limited per IP address
     let auth_routes = Router::new()
         .route("/auth/login", post(handlers::login))
         .route("/auth/logout", post(handlers::logout))
+        .route("/auth/logout-all", post(handlers::logout_all))
+        .route("/auth/refresh", post(handlers::refresh))
+        .route("/auth/mfa/enroll", post(handlers::enroll_mfa))
+        .route("/auth/mfa/confirm", post(handlers::confirm_mfa))
+        .route("/auth/oidc/login", get(handlers::oidc_login))
+        .route("/auth/oidc/callback", get(handlers::oidc_callback))
+        .layer(middleware::from_fn_with_state(
+            state.rate_limits.clone(),
+            rate_limit_middleware,
+        ))
         .with_state(auth_state);
 
     // Create auth middleware state
     let auth_state = AuthMiddlewareState {
         auth_service: state.auth_service.clone(),
         session_store: state.session_store.clone(),
+        rejected_credentials: state.rejected_credentials.clone(),
     };
 
-    // Protected routes (authentication required)
-    let protected_routes = Router::new()
-        // SSE Events endpoint - requires ReadCache permission (checked in handler if needed)
-        .route("/events", get(handlers::stream_events))
-        // Cache operation routes - requires cache permissions (checked in handlers)
-        .route("/cache/{cache_name}/{key}", put(handlers::put_value))
-        .route("/cache/{cache_name}/{key}", get(handlers::get_value))
-        .route("/cache/{cache_name}/{key}", delete(handlers::delete_value))
-        // Admin cache routes - requires admin permissions (checked in handlers)
-        .route("/admin/caches", post(handlers::create_cache))
-        .route("/admin/caches", get(handlers::list_caches))
-        .route("/admin/caches/{name}", get(handlers::describe_cache))
-        .route("/admin/caches/{name}", delete(handlers::drop_cache))
-        // User management routes - requires ManageUsers permission (checked in handlers)
-        .route("/admin/users", post(handlers::create_user))
-        .route("/admin/users", get(handlers::list_users))
-        .route("/admin/users/{username}", get(handlers::get_user))
-        .route("/admin/users/{username}/roles", put(handlers::assign_roles))
-        .route(
-            "/admin/users/{username}/password",
-            put(handlers::change_password),
-        )
-        .route(
-            "/admin/users/{username}/reset-password",
-            put(handlers::reset_password),
-        )
-        .route("/admin/users/{username}", delete(handlers::delete_user))
-        // Role management routes - requires ManageRoles/AdminRead permission (checked in handlers)
-        .route("/admin/roles", post(handlers::create_role))
-        .route("/admin/roles", get(handlers::list_roles))
-        .route("/admin/roles/{name}", get(handlers::get_role))
-        .route("/admin/roles/{name}", put(handlers::update_role))
-        .route("/admin/roles/{name}", delete(handlers::delete_role))
+    // Protected routes (authentication required), authorized against route_permissions()
+    let protected = protected_routes();
+    let permissions = route_permissions();
+    let undeclared = permissions.undeclared(&protected.routes);
+    assert!(
+        undeclared.is_empty(),
+        "routes without a declared permission: {:?}",
+        undeclared
+    );
+    let authorization_state = AuthorizationState {
+        auth_service: state.auth_service.clone(),
+        permissions: Arc::new(permissions),
+    };
+    let quota_state = QuotaState {
+        auth_service: state.auth_service.clone(),
+        quotas: state.quotas.clone(),
+    };
+    let idempotency_state = IdempotencyState {
+        keys: state.idempotency_keys.clone(),
+        routes: Arc::new(idempotent_routes()),
+        max_body_bytes: state.http_limits.max_body_bytes,
+    };
+
+    let protected_routes = protected
+        .router
+        // Replays retried creates and drops, inside quotas so a replay is not charged again
+        .layer(middleware::from_fn_with_state(
+            idempotency_state,
+            idempotency_middleware,
+        ))
+        // Charges writes to the authenticated user, once they are known to be allowed
+        .layer(middleware::from_fn_with_state(
+            quota_state,
+            quota_middleware,
+        ))
+        // Innermost, so the user is known and the route matched
+        .layer(middleware::from_fn_with_state(
+            authorization_state,
+            authorization_middleware,
+        ))
+        // Attributes slow operations to the authenticated user
+        .layer(middleware::from_fn(caller_middleware))
+        // Rate limiting runs inside authentication so clients are limited per user
+        .layer(middleware::from_fn_with_state(
+            state.rate_limits.clone(),
+            rate_limit_middleware,
+        ))
         // Apply authentication middleware to all protected routes
         .layer(middleware::from_fn_with_state(auth_state, auth_middleware));
 
@@ -74,7 +118,762 @@ pub fn build_router(state: AppState) -> Router {
         .merge(public_routes)
         .merge(auth_routes)
         .merge(protected_routes)
+        // Bodies without a Content-Length are cut off by the extractors at the same limit
+        .layer(DefaultBodyLimit::max(state.http_limits.max_body_bytes))
+        .layer(middleware::from_fn_with_state(
+            state.http_limits,
+            request_limits_middleware,
+        ))
+        // Outside the limits so timeouts and oversized bodies are reported with a code too
+        .layer(middleware::from_fn(error_code_middleware))
+        // Resolved once for rate limiting, sessions and the slow log
+        .layer(middleware::from_fn_with_state(
+            state.trusted_proxies.clone(),
+            client_ip_middleware,
+        ))
         .layer(NormalizePathLayer::trim_trailing_slash())
-        .layer(TraceLayer::new_for_http())
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(make_request_span)
+                .on_response(record_response),
+        )
+        // Outermost so the request span and the response carry the request ID
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
         .with_state(state)
 }
+
+/// The router as `listener` serves it, limited to its routes and credentials
+pub fn listener_router(router: Router, listener: &ListenerConfig) -> Router {
+    router.layer(middleware::from_fn_with_state(
+        ListenerScope::of(listener),
+        listener_scope_middleware,
+    ))
+}
+
+/// Router for authenticated routes that records each method and pattern, so routes missing
+/// from route_permissions() are caught when the router is built
+struct ProtectedRoutes {
+    router: Router<AppState>,
+    routes: Vec<(Method, &'static str)>,
+}
+
+impl ProtectedRoutes {
+    fn new() -> Self {
+        Self {
+            router: Router::new(),
+            routes: Vec::new(),
+        }
+    }
+
+    fn route<H, T>(mut self, method: Method, path: &'static str, handler: H) -> Self
+    where
+        H: Handler<T, AppState>,
+        T: 'static,
+    {
+        let filter = MethodFilter::try_from(method.clone()).expect("method is routable");
+        self.router = self.router.route(path, on(filter, handler));
+        self.routes.push((method, path));
+        self
+    }
+}
+
+fn protected_routes() -> ProtectedRoutes {
+    ProtectedRoutes::new()
+        // SSE Events endpoint
+        .route(Method::GET, "/events", handlers::stream_events)
+        // Cache operation routes
+        .route(
+            Method::POST,
+            "/cache/{cache_name}/query",
+            handlers::query_cache,
+        )
+        .route(
+            Method::POST,
+            "/cache/{cache_name}/bulkload",
+            handlers::bulk_load,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/changes",
+            handlers::list_changes,
+        )
+        .route(
+            Method::POST,
+            "/cache/{cache_name}/invalidate-by-tag",
+            handlers::invalidate_by_tag,
+        )
+        .route(
+            Method::PUT,
+            "/cache/{cache_name}/{key}",
+            handlers::put_value,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}",
+            handlers::get_value,
+        )
+        .route(
+            Method::DELETE,
+            "/cache/{cache_name}/{key}",
+            handlers::delete_value,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/meta",
+            handlers::get_metadata,
+        )
+        .route(
+            Method::POST,
+            "/cache/{cache_name}/{key}/getdel",
+            handlers::getdel_value,
+        )
+        .route(
+            Method::POST,
+            "/cache/{cache_name}/{key}/getex",
+            handlers::getex_value,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/fields",
+            handlers::get_all_fields,
+        )
+        .route(
+            Method::PUT,
+            "/cache/{cache_name}/{key}/fields/{field}",
+            handlers::put_field,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/fields/{field}",
+            handlers::get_field,
+        )
+        .route(
+            Method::DELETE,
+            "/cache/{cache_name}/{key}/fields/{field}",
+            handlers::delete_field,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/members",
+            handlers::get_members,
+        )
+        .route(
+            Method::PUT,
+            "/cache/{cache_name}/{key}/members/{member}",
+            handlers::add_member,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/members/{member}",
+            handlers::is_member,
+        )
+        .route(
+            Method::DELETE,
+            "/cache/{cache_name}/{key}/members/{member}",
+            handlers::remove_member,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/scores",
+            handlers::get_scores,
+        )
+        .route(
+            Method::PUT,
+            "/cache/{cache_name}/{key}/scores/{member}",
+            handlers::put_score,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/scores/{member}",
+            handlers::get_score,
+        )
+        .route(
+            Method::DELETE,
+            "/cache/{cache_name}/{key}/scores/{member}",
+            handlers::remove_score,
+        )
+        .route(
+            Method::POST,
+            "/cache/{cache_name}/{key}/samples",
+            handlers::append_sample,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/samples",
+            handlers::get_samples,
+        )
+        .route(
+            Method::POST,
+            "/cache/{cache_name}/{key}/hll",
+            handlers::add_to_hyperloglog,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/hll",
+            handlers::count_hyperloglog,
+        )
+        .route(
+            Method::PUT,
+            "/cache/{cache_name}/{key}/bloom",
+            handlers::reserve_bloom,
+        )
+        .route(
+            Method::PUT,
+            "/cache/{cache_name}/{key}/bloom/{item}",
+            handlers::add_to_bloom,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/bloom/{item}",
+            handlers::check_bloom,
+        )
+        .route(
+            Method::POST,
+            "/cache/{cache_name}/{key}/lock",
+            handlers::acquire_lock,
+        )
+        .route(
+            Method::DELETE,
+            "/cache/{cache_name}/{key}/lock",
+            handlers::release_lock,
+        )
+        .route(
+            Method::PUT,
+            "/cache/{cache_name}/{key}/pin",
+            handlers::pin_key,
+        )
+        .route(
+            Method::DELETE,
+            "/cache/{cache_name}/{key}/pin",
+            handlers::unpin_key,
+        )
+        .route(
+            Method::GET,
+            "/cache/{cache_name}/{key}/path/{*pointer}",
+            handlers::get_json_path,
+        )
+        .route(
+            Method::PATCH,
+            "/cache/{cache_name}/{key}/path/{*pointer}",
+            handlers::patch_json_path,
+        )
+        // Admin cache routes
+        .route(Method::POST, "/admin/caches", handlers::create_cache)
+        .route(Method::GET, "/admin/caches", handlers::list_caches)
+        .route(
+            Method::POST,
+            "/admin/caches/validate",
+            handlers::validate_cache,
+        )
+        .route(
+            Method::GET,
+            "/admin/caches/{name}",
+            handlers::describe_cache,
+        )
+        .route(Method::DELETE, "/admin/caches/{name}", handlers::drop_cache)
+        .route(
+            Method::POST,
+            "/admin/caches/{name}/restore",
+            handlers::restore_cache,
+        )
+        .route(
+            Method::POST,
+            "/admin/caches/{name}/retry",
+            handlers::retry_cache,
+        )
+        .route(
+            Method::POST,
+            "/admin/caches/{name}/clone",
+            handlers::clone_cache,
+        )
+        .route(
+            Method::POST,
+            "/admin/caches/{name}/rename",
+            handlers::rename_cache,
+        )
+        .route(Method::GET, "/admin/aliases", handlers::list_aliases)
+        .route(Method::PUT, "/admin/aliases/{alias}", handlers::set_alias)
+        .route(
+            Method::DELETE,
+            "/admin/aliases/{alias}",
+            handlers::remove_alias,
+        )
+        .route(Method::GET, "/admin/schedules", handlers::list_schedules)
+        .route(Method::POST, "/admin/schedules", handlers::create_schedule)
+        .route(
+            Method::DELETE,
+            "/admin/schedules/{id}",
+            handlers::remove_schedule,
+        )
+        .route(Method::GET, "/admin/views", handlers::list_views)
+        .route(Method::POST, "/admin/views", handlers::create_view)
+        .route(
+            Method::DELETE,
+            "/admin/views/{cache}",
+            handlers::remove_view,
+        )
+        .route(
+            Method::GET,
+            "/admin/dropped-caches",
+            handlers::list_dropped_caches,
+        )
+        .route(
+            Method::PATCH,
+            "/admin/caches/{name}",
+            handlers::update_cache,
+        )
+        .route(
+            Method::POST,
+            "/admin/caches/{name}/indexes",
+            handlers::create_index,
+        )
+        .route(
+            Method::GET,
+            "/admin/caches/{name}/indexes",
+            handlers::list_indexes,
+        )
+        .route(
+            Method::DELETE,
+            "/admin/caches/{name}/indexes/{index}",
+            handlers::drop_index,
+        )
+        .route(
+            Method::GET,
+            "/admin/caches/{name}/schema",
+            handlers::get_schema,
+        )
+        .route(
+            Method::GET,
+            "/admin/caches/{name}/stats",
+            handlers::cache_stats,
+        )
+        // User management routes
+        .route(Method::POST, "/admin/users", handlers::create_user)
+        .route(Method::GET, "/admin/users", handlers::list_users)
+        .route(Method::GET, "/admin/users/{username}", handlers::get_user)
+        .route(
+            Method::PUT,
+            "/admin/users/{username}/roles",
+            handlers::assign_roles,
+        )
+        .route(
+            Method::PUT,
+            "/admin/users/{username}/password",
+            handlers::change_password,
+        )
+        .route(
+            Method::PUT,
+            "/admin/users/{username}/reset-password",
+            handlers::reset_password,
+        )
+        .route(
+            Method::DELETE,
+            "/admin/users/{username}",
+            handlers::delete_user,
+        )
+        .route(
+            Method::PUT,
+            "/admin/users/{username}/mfa",
+            handlers::set_user_mfa_required,
+        )
+        .route(
+            Method::DELETE,
+            "/admin/users/{username}/mfa",
+            handlers::reset_user_mfa,
+        )
+        .route(
+            Method::GET,
+            "/admin/users/{username}/quota",
+            handlers::get_user_quota,
+        )
+        .route(
+            Method::PUT,
+            "/admin/users/{username}/quota",
+            handlers::set_user_quota,
+        )
+        .route(
+            Method::DELETE,
+            "/admin/users/{username}/quota",
+            handlers::clear_user_quota,
+        )
+        .route(Method::GET, "/admin/auth/export", handlers::export_auth)
+        .route(Method::POST, "/admin/auth/import", handlers::import_auth)
+        // Service account routes
+        .route(
+            Method::POST,
+            "/admin/service-accounts",
+            handlers::create_service_account,
+        )
+        .route(
+            Method::POST,
+            "/admin/service-accounts/{username}/keys",
+            handlers::create_api_key,
+        )
+        .route(
+            Method::DELETE,
+            "/admin/service-accounts/{username}/keys/{key_id}",
+            handlers::revoke_api_key,
+        )
+        // Role management routes
+        .route(Method::POST, "/admin/roles", handlers::create_role)
+        .route(Method::GET, "/admin/roles", handlers::list_roles)
+        .route(Method::GET, "/admin/roles/{name}", handlers::get_role)
+        .route(Method::PUT, "/admin/roles/{name}", handlers::update_role)
+        .route(Method::DELETE, "/admin/roles/{name}", handlers::delete_role)
+        .route(
+            Method::PUT,
+            "/admin/roles/{name}/mfa",
+            handlers::set_role_mfa_required,
+        )
+        .route(
+            Method::PUT,
+            "/admin/roles/{name}/quota",
+            handlers::set_role_quota,
+        )
+        .route(
+            Method::DELETE,
+            "/admin/roles/{name}/quota",
+            handlers::clear_role_quota,
+        )
+        // Operational routes
+        .route(
+            Method::GET,
+            "/admin/rate-limits",
+            handlers::rate_limit_stats,
+        )
+        .route(
+            Method::POST,
+            "/admin/config/reload",
+            handlers::reload_config,
+        )
+        .route(
+            Method::POST,
+            "/admin/maintenance",
+            handlers::set_maintenance,
+        )
+        .route(
+            Method::GET,
+            "/admin/health/ready",
+            handlers::admin_readiness,
+        )
+        .route(Method::GET, "/admin/slowlog", handlers::get_slow_log)
+        .route(Method::DELETE, "/admin/slowlog", handlers::reset_slow_log)
+        .route(Method::GET, "/admin/quotas", handlers::list_quota_usage)
+        .route(
+            Method::GET,
+            "/admin/connections",
+            handlers::list_connections,
+        )
+        .route(Method::GET, "/admin/event-sink", handlers::event_sink_stats)
+        .route(Method::GET, "/metrics", handlers::prometheus_metrics)
+}
+
+/// Routes that honour an Idempotency-Key header, so a retry after a timeout does not
+/// create a duplicate or fail with "already exists"
+fn idempotent_routes() -> HashSet<(Method, &'static str)> {
+    HashSet::from([
+        (Method::POST, "/admin/caches"),
+        (Method::DELETE, "/admin/caches/{name}"),
+        (Method::POST, "/admin/users"),
+    ])
+}
+
+/// Permission each protected route requires, every route in protected_routes() must be listed
+fn route_permissions() -> RoutePermissions {
+    use Permission::*;
+
+    RoutePermissions::new()
+        .with_permission(Method::GET, "/events", SubscribeEvents)
+        .with_permission(Method::GET, "/cache/{cache_name}/changes", SubscribeEvents)
+        // Matching keys can list a whole cache, so queries count as exporting its data
+        .with_permission(Method::POST, "/cache/{cache_name}/query", ExportData)
+        .with_permission(Method::POST, "/cache/{cache_name}/bulkload", WriteCache)
+        .with_permission(
+            Method::POST,
+            "/cache/{cache_name}/invalidate-by-tag",
+            DeleteCache,
+        )
+        .with_permission(Method::PUT, "/cache/{cache_name}/{key}", WriteCache)
+        .with_permission(Method::GET, "/cache/{cache_name}/{key}", ReadCache)
+        .with_permission(Method::DELETE, "/cache/{cache_name}/{key}", DeleteCache)
+        .with_permission(Method::GET, "/cache/{cache_name}/{key}/meta", ReadCache)
+        // Both return the stored value, so they read it as well
+        .with_access(
+            Method::POST,
+            "/cache/{cache_name}/{key}/getdel",
+            Access::AllOf(vec![ReadCache, DeleteCache]),
+        )
+        .with_access(
+            Method::POST,
+            "/cache/{cache_name}/{key}/getex",
+            Access::AllOf(vec![ReadCache, WriteCache]),
+        )
+        .with_permission(Method::GET, "/cache/{cache_name}/{key}/fields", ReadCache)
+        .with_permission(
+            Method::PUT,
+            "/cache/{cache_name}/{key}/fields/{field}",
+            WriteCache,
+        )
+        .with_permission(
+            Method::GET,
+            "/cache/{cache_name}/{key}/fields/{field}",
+            ReadCache,
+        )
+        .with_permission(
+            Method::DELETE,
+            "/cache/{cache_name}/{key}/fields/{field}",
+            DeleteCache,
+        )
+        .with_permission(Method::GET, "/cache/{cache_name}/{key}/members", ReadCache)
+        .with_permission(
+            Method::PUT,
+            "/cache/{cache_name}/{key}/members/{member}",
+            WriteCache,
+        )
+        .with_permission(
+            Method::GET,
+            "/cache/{cache_name}/{key}/members/{member}",
+            ReadCache,
+        )
+        .with_permission(
+            Method::DELETE,
+            "/cache/{cache_name}/{key}/members/{member}",
+            DeleteCache,
+        )
+        .with_permission(Method::GET, "/cache/{cache_name}/{key}/scores", ReadCache)
+        .with_permission(
+            Method::PUT,
+            "/cache/{cache_name}/{key}/scores/{member}",
+            WriteCache,
+        )
+        .with_permission(
+            Method::GET,
+            "/cache/{cache_name}/{key}/scores/{member}",
+            ReadCache,
+        )
+        .with_permission(
+            Method::DELETE,
+            "/cache/{cache_name}/{key}/scores/{member}",
+            DeleteCache,
+        )
+        .with_permission(
+            Method::POST,
+            "/cache/{cache_name}/{key}/samples",
+            WriteCache,
+        )
+        .with_permission(Method::GET, "/cache/{cache_name}/{key}/samples", ReadCache)
+        .with_permission(Method::POST, "/cache/{cache_name}/{key}/hll", WriteCache)
+        .with_permission(Method::GET, "/cache/{cache_name}/{key}/hll", ReadCache)
+        .with_permission(Method::PUT, "/cache/{cache_name}/{key}/bloom", WriteCache)
+        .with_permission(
+            Method::PUT,
+            "/cache/{cache_name}/{key}/bloom/{item}",
+            WriteCache,
+        )
+        .with_permission(
+            Method::GET,
+            "/cache/{cache_name}/{key}/bloom/{item}",
+            ReadCache,
+        )
+        .with_permission(Method::POST, "/cache/{cache_name}/{key}/lock", WriteCache)
+        .with_permission(Method::DELETE, "/cache/{cache_name}/{key}/lock", WriteCache)
+        .with_permission(Method::PUT, "/cache/{cache_name}/{key}/pin", WriteCache)
+        .with_permission(Method::DELETE, "/cache/{cache_name}/{key}/pin", WriteCache)
+        .with_permission(
+            Method::GET,
+            "/cache/{cache_name}/{key}/path/{*pointer}",
+            ReadCache,
+        )
+        .with_permission(
+            Method::PATCH,
+            "/cache/{cache_name}/{key}/path/{*pointer}",
+            WriteCache,
+        )
+        .with_permission(Method::POST, "/admin/caches", CreateCache)
+        .with_permission(Method::GET, "/admin/caches", AdminRead)
+        .with_permission(Method::POST, "/admin/caches/validate", CreateCache)
+        .with_permission(Method::GET, "/admin/caches/{name}", AdminRead)
+        .with_permission(Method::DELETE, "/admin/caches/{name}", DropCache)
+        .with_permission(Method::POST, "/admin/caches/{name}/restore", CreateCache)
+        .with_permission(Method::POST, "/admin/caches/{name}/retry", CreateCache)
+        // A clone copies every entry of the source, so it is read and exported as well
+        .with_access(
+            Method::POST,
+            "/admin/caches/{name}/clone",
+            Access::AllOf(vec![CreateCache, ReadCache, ExportData]),
+        )
+        .with_permission(Method::POST, "/admin/caches/{name}/rename", AdminWrite)
+        .with_permission(Method::GET, "/admin/dropped-caches", AdminRead)
+        .with_permission(Method::GET, "/admin/aliases", AdminRead)
+        .with_permission(Method::PUT, "/admin/aliases/{alias}", AdminWrite)
+        .with_permission(Method::DELETE, "/admin/aliases/{alias}", AdminWrite)
+        .with_permission(Method::GET, "/admin/schedules", AdminRead)
+        .with_permission(Method::POST, "/admin/schedules", ClusterAdmin)
+        .with_permission(Method::DELETE, "/admin/schedules/{id}", AdminWrite)
+        .with_permission(Method::GET, "/admin/views", AdminRead)
+        // A view copies every entry of its source, like a clone
+        .with_access(
+            Method::POST,
+            "/admin/views",
+            Access::AllOf(vec![AdminWrite, ReadCache, ExportData]),
+        )
+        .with_permission(Method::DELETE, "/admin/views/{cache}", AdminWrite)
+        .with_permission(Method::PATCH, "/admin/caches/{name}", AdminWrite)
+        .with_permission(Method::POST, "/admin/caches/{name}/indexes", AdminWrite)
+        .with_permission(Method::GET, "/admin/caches/{name}/indexes", AdminRead)
+        .with_permission(
+            Method::DELETE,
+            "/admin/caches/{name}/indexes/{index}",
+            AdminDelete,
+        )
+        .with_permission(Method::GET, "/admin/caches/{name}/schema", AdminRead)
+        .with_permission(Method::GET, "/admin/caches/{name}/stats", ReadMetrics)
+        .with_permission(Method::POST, "/admin/users", ManageUsers)
+        .with_permission(Method::GET, "/admin/users", ManageUsers)
+        .with_permission(Method::GET, "/admin/users/{username}", ManageUsers)
+        .with_permission(Method::PUT, "/admin/users/{username}/roles", ManageUsers)
+        // Users change their own password, the handler requires ManageUsers for anyone else's
+        .with_access(
+            Method::PUT,
+            "/admin/users/{username}/password",
+            Access::Authenticated,
+        )
+        .with_permission(
+            Method::PUT,
+            "/admin/users/{username}/reset-password",
+            ManageUsers,
+        )
+        .with_permission(Method::DELETE, "/admin/users/{username}", ManageUsers)
+        .with_permission(Method::PUT, "/admin/users/{username}/mfa", ManageUsers)
+        .with_permission(Method::DELETE, "/admin/users/{username}/mfa", ManageUsers)
+        .with_permission(Method::GET, "/admin/users/{username}/quota", ManageUsers)
+        .with_permission(Method::PUT, "/admin/users/{username}/quota", ManageUsers)
+        .with_permission(Method::DELETE, "/admin/users/{username}/quota", ManageUsers)
+        // Exports carry password hashes, imports also need ManageRoles checked by the handler
+        .with_permission(Method::GET, "/admin/auth/export", ManageUsers)
+        .with_permission(Method::POST, "/admin/auth/import", ManageUsers)
+        .with_permission(Method::POST, "/admin/service-accounts", ManageUsers)
+        .with_permission(
+            Method::POST,
+            "/admin/service-accounts/{username}/keys",
+            ManageUsers,
+        )
+        .with_permission(
+            Method::DELETE,
+            "/admin/service-accounts/{username}/keys/{key_id}",
+            ManageUsers,
+        )
+        .with_permission(Method::POST, "/admin/roles", ManageRoles)
+        .with_permission(Method::GET, "/admin/roles", AdminRead)
+        .with_permission(Method::GET, "/admin/roles/{name}", AdminRead)
+        .with_permission(Method::PUT, "/admin/roles/{name}", ManageRoles)
+        .with_permission(Method::DELETE, "/admin/roles/{name}", ManageRoles)
+        .with_permission(Method::PUT, "/admin/roles/{name}/mfa", ManageRoles)
+        .with_permission(Method::PUT, "/admin/roles/{name}/quota", ManageRoles)
+        .with_permission(Method::DELETE, "/admin/roles/{name}/quota", ManageRoles)
+        .with_permission(Method::GET, "/admin/rate-limits", ReadMetrics)
+        .with_permission(Method::POST, "/admin/config/reload", ClusterAdmin)
+        .with_permission(Method::POST, "/admin/maintenance", ClusterAdmin)
+        .with_permission(Method::GET, "/admin/health/ready", AdminRead)
+        .with_permission(Method::GET, "/admin/slowlog", ReadSlowLog)
+        .with_permission(Method::DELETE, "/admin/slowlog", AdminWrite)
+        .with_permission(Method::GET, "/admin/quotas", ReadMetrics)
+        .with_permission(Method::GET, "/admin/connections", ReadMetrics)
+        .with_permission(Method::GET, "/admin/event-sink", ReadMetrics)
+        .with_permission(Method::GET, "/metrics", ReadMetrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_protected_route_declares_a_permission() {
+        let routes = protected_routes().routes;
+        let permissions = route_permissions();
+
+        assert!(permissions.undeclared(&routes).is_empty());
+        assert!(permissions.unserved(&routes).is_empty());
+    }
+
+    #[test]
+    fn test_idempotent_routes_are_served() {
+        let routes = protected_routes().routes;
+        for route in idempotent_routes() {
+            assert!(routes.contains(&route), "{:?} is not served", route);
+        }
+    }
+
+    #[test]
+    fn test_route_permissions() {
+        let permissions = route_permissions();
+        assert_eq!(
+            permissions.access(&Method::DELETE, "/admin/caches/{name}"),
+            Some(&Access::Permission(Permission::DropCache))
+        );
+        assert_eq!(
+            permissions.access(&Method::GET, "/events"),
+            Some(&Access::Permission(Permission::SubscribeEvents))
+        );
+        assert_eq!(
+            permissions.access(&Method::POST, "/admin/config/reload"),
+            Some(&Access::Permission(Permission::ClusterAdmin))
+        );
+        assert_eq!(
+            permissions.access(&Method::POST, "/admin/caches/{name}/clone"),
+            Some(&Access::AllOf(vec![
+                Permission::CreateCache,
+                Permission::ReadCache,
+                Permission::ExportData
+            ]))
+        );
+    }
+
+    /// Whether a role holding only `held` may use the route
+    fn grants(held: &[Permission], method: Method, path: &str) -> bool {
+        match route_permissions().access(&method, path) {
+            Some(Access::Authenticated) => true,
+            Some(Access::Permission(permission)) => held.contains(permission),
+            Some(Access::AllOf(permissions)) => permissions.iter().all(|p| held.contains(p)),
+            None => false,
+        }
+    }
+
+    #[test]
+    fn test_value_returning_writes_need_read() {
+        let getdel = "/cache/{cache_name}/{key}/getdel";
+        let getex = "/cache/{cache_name}/{key}/getex";
+
+        assert!(!grants(&[Permission::DeleteCache], Method::POST, getdel));
+        assert!(!grants(&[Permission::WriteCache], Method::POST, getex));
+        assert!(grants(
+            &[Permission::ReadCache, Permission::DeleteCache],
+            Method::POST,
+            getdel
+        ));
+        assert!(grants(
+            &[Permission::ReadCache, Permission::WriteCache],
+            Method::POST,
+            getex
+        ));
+    }
+
+    #[test]
+    fn test_views_need_read_and_export() {
+        assert!(!grants(
+            &[Permission::AdminWrite],
+            Method::POST,
+            "/admin/views"
+        ));
+        assert!(grants(
+            &[
+                Permission::AdminWrite,
+                Permission::ReadCache,
+                Permission::ExportData
+            ],
+            Method::POST,
+            "/admin/views"
+        ));
+    }
+}
@@ -0,0 +1,286 @@
+use crate::api::{BulkLoadRecord, ValueEncoding};
+use crate::handlers::cache::bulk::load_lines;
+use crate::state::AppState;
+use async_trait::async_trait;
+use axum::body::Body;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use carbon::domain::ScheduledAction;
+use carbon::planes::data::cache_operations::CacheOperationsService;
+use carbon::ports::ScheduledActionRunner;
+use reqwest::Url;
+use shared::config::ScheduleConfig;
+use shared::{Error, Result};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// Where snapshots may be written and warm-ups may fetch from
+/// Schedules are checked when created and again on every run, so ones stored before a
+/// setting changed cannot reach past it
+#[derive(Debug, Default)]
+pub struct SchedulePolicy {
+    snapshot_dir: Option<PathBuf>,
+    warm_url_prefixes: Vec<Url>,
+}
+
+impl SchedulePolicy {
+    /// Prefixes that are not absolute URLs are left out, with a warning
+    pub fn new(config: &ScheduleConfig) -> Self {
+        let warm_url_prefixes = config
+            .warm_url_prefixes
+            .iter()
+            .filter_map(|prefix| match Url::parse(prefix) {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    tracing::warn!("Ignoring warm URL prefix '{}': {}", prefix, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            snapshot_dir: config.snapshot_dir.as_ref().map(PathBuf::from),
+            warm_url_prefixes,
+        }
+    }
+
+    /// Refuse actions that would write or fetch outside what is configured
+    pub fn check(&self, action: &ScheduledAction) -> Result<()> {
+        match action {
+            ScheduledAction::Clear => Ok(()),
+            ScheduledAction::Snapshot { path } => self.snapshot_path(path).map(|_| ()),
+            ScheduledAction::Warm { url } => self.warm_url(url).map(|_| ()),
+        }
+    }
+
+    /// `path` resolved under the snapshot directory, which it must stay inside
+    fn snapshot_path(&self, path: &str) -> Result<PathBuf> {
+        let Some(ref snapshot_dir) = self.snapshot_dir else {
+            return Err(Error::InvalidValue(
+                "snapshots need CARBON_SNAPSHOT_DIR to be set".to_string(),
+            ));
+        };
+        let relative = Path::new(path);
+        let contained = !path.is_empty()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !contained {
+            return Err(Error::InvalidValue(format!(
+                "snapshot path '{}' must be relative to the snapshot directory and not use '..'",
+                path
+            )));
+        }
+        Ok(snapshot_dir.join(relative))
+    }
+
+    /// `url` when it starts with one of the allowed prefixes
+    fn warm_url(&self, url: &str) -> Result<Url> {
+        let parsed = Url::parse(url)
+            .map_err(|e| Error::InvalidValue(format!("invalid warm url '{}': {}", url, e)))?;
+        if self
+            .warm_url_prefixes
+            .iter()
+            .any(|prefix| url_has_prefix(&parsed, prefix))
+        {
+            Ok(parsed)
+        } else {
+            Err(Error::InvalidValue(format!(
+                "warm url '{}' is not under a prefix in CARBON_WARM_URL_PREFIXES",
+                url
+            )))
+        }
+    }
+}
+
+/// Whether `url` is on the origin of `prefix` and under its path, by whole path segments
+fn url_has_prefix(url: &Url, prefix: &Url) -> bool {
+    if url.scheme() != prefix.scheme()
+        || url.host_str() != prefix.host_str()
+        || url.port_or_known_default() != prefix.port_or_known_default()
+    {
+        return false;
+    }
+    let base = prefix.path().trim_end_matches('/');
+    match url.path().strip_prefix(base) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Carries out scheduled actions against the HTTP server's caches
+/// Snapshots and warm-ups use the NDJSON format of POST /cache/{name}/bulkload, so a snapshot
+/// can warm a cache again
+pub struct ScheduleRunner {
+    cache_operations: Arc<CacheOperationsService<Vec<u8>, Bytes>>,
+    policy: Arc<SchedulePolicy>,
+    http: reqwest::Client,
+    max_line_bytes: usize,
+}
+
+impl ScheduleRunner {
+    pub fn new(state: &AppState) -> Self {
+        Self {
+            cache_operations: state.cache_operations.clone(),
+            policy: state.schedule_policy.clone(),
+            // A redirect could lead a warm-up away from the allowed prefixes
+            http: reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap_or_default(),
+            max_line_bytes: state.http_limits.max_body_bytes,
+        }
+    }
+
+    /// Write every entry to `path` under the snapshot directory, through a temporary file so a
+    /// failed run leaves the last snapshot in place
+    /// Keys that are not UTF-8 cannot be bulk loaded and are left out
+    async fn snapshot(&self, cache_name: &str, path: &str) -> Result<String> {
+        let target = self.policy.snapshot_path(path)?;
+        let entries = self.cache_operations.snapshot(cache_name).await?;
+        let total = entries.len();
+
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+        let mut partial = target.clone().into_os_string();
+        partial.push(".partial");
+        let file = tokio::fs::File::create(&partial).await.map_err(io_error)?;
+
+        // Records are written as they are encoded, each entry is let go once it is on its way
+        let mut writer = BufWriter::new(file);
+        let mut line = Vec::new();
+        let mut skipped = 0;
+        for (key, value) in entries {
+            let Ok(key) = String::from_utf8(key) else {
+                skipped += 1;
+                continue;
+            };
+            let (value, encoding) = match std::str::from_utf8(&value) {
+                Ok(text) => (text.to_string(), ValueEncoding::Utf8),
+                Err(_) => (STANDARD.encode(&value), ValueEncoding::Base64),
+            };
+            let record = BulkLoadRecord {
+                key,
+                value,
+                encoding,
+                ttl_ms: None,
+            };
+            line.clear();
+            serde_json::to_writer(&mut line, &record)
+                .map_err(|e| Error::Internal(e.to_string()))?;
+            line.push(b'\n');
+            writer.write_all(&line).await.map_err(io_error)?;
+        }
+        writer.flush().await.map_err(io_error)?;
+        writer.get_ref().sync_all().await.map_err(io_error)?;
+        tokio::fs::rename(&partial, &target)
+            .await
+            .map_err(io_error)?;
+
+        let written = total - skipped;
+        Ok(match skipped {
+            0 => format!("wrote {} entries to {}", written, path),
+            _ => format!(
+                "wrote {} entries to {}, skipped {} keys that are not UTF-8",
+                written, path, skipped
+            ),
+        })
+    }
+
+    /// Load the NDJSON served at `url` into the cache, when it is under an allowed prefix
+    async fn warm(&self, cache_name: &str, url: &str) -> Result<String> {
+        let allowed = self.policy.warm_url(url)?;
+        let body = self
+            .http
+            .get(allowed)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::InvalidValue(format!("failed to fetch {}: {}", url, e)))?
+            .bytes()
+            .await
+            .map_err(|e| Error::InvalidValue(format!("failed to read {}: {}", url, e)))?;
+
+        let mut loader = self.cache_operations.bulk_loader(cache_name).await?;
+        let result = load_lines(&mut loader, Body::from(body), self.max_line_bytes).await;
+        let summary = loader.finish();
+        result.map_err(|e| {
+            Error::InvalidValue(format!(
+                "{} after loading {} entries from {}",
+                e.message(),
+                summary.loaded,
+                url
+            ))
+        })?;
+
+        Ok(format!(
+            "loaded {} entries from {}, {} not admitted",
+            summary.loaded, url, summary.not_admitted
+        ))
+    }
+}
+
+#[async_trait]
+impl ScheduledActionRunner for ScheduleRunner {
+    async fn run(&self, cache_name: &str, action: &ScheduledAction) -> Result<String> {
+        match action {
+            ScheduledAction::Clear => {
+                let cleared = self.cache_operations.clear(cache_name).await?;
+                Ok(format!("cleared {} entries", cleared))
+            }
+            ScheduledAction::Snapshot { path } => self.snapshot(cache_name, path).await,
+            ScheduledAction::Warm { url } => self.warm(cache_name, url).await,
+        }
+    }
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::Internal(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> SchedulePolicy {
+        SchedulePolicy::new(&ScheduleConfig {
+            snapshot_dir: Some("/var/lib/carbon/snapshots".to_string()),
+            warm_url_prefixes: vec!["https://assets.internal/warm/".to_string()],
+        })
+    }
+
+    #[test]
+    fn test_snapshot_paths_stay_in_snapshot_dir() {
+        let policy = policy();
+        assert_eq!(
+            policy.snapshot_path("nightly/sessions.ndjson").unwrap(),
+            PathBuf::from("/var/lib/carbon/snapshots/nightly/sessions.ndjson")
+        );
+        assert!(policy.snapshot_path("/etc/passwd").is_err());
+        assert!(policy.snapshot_path("../sessions.ndjson").is_err());
+        assert!(policy.snapshot_path("nightly/../../x").is_err());
+        assert!(policy.snapshot_path("").is_err());
+        assert!(SchedulePolicy::default().snapshot_path("x").is_err());
+    }
+
+    #[test]
+    fn test_warm_urls_need_an_allowed_prefix() {
+        let policy = policy();
+        assert!(policy
+            .warm_url("https://assets.internal/warm/sessions")
+            .is_ok());
+        assert!(policy.warm_url("https://assets.internal/warm").is_ok());
+        assert!(policy.warm_url("https://assets.internal/warmer").is_err());
+        assert!(policy
+            .warm_url("http://assets.internal/warm/sessions")
+            .is_err());
+        assert!(policy
+            .warm_url("https://assets.internal:8443/warm/x")
+            .is_err());
+        assert!(policy.warm_url("http://169.254.169.254/latest").is_err());
+        assert!(SchedulePolicy::default()
+            .warm_url("https://assets.internal/warm/sessions")
+            .is_err());
+    }
+}
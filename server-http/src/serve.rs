@@ -0,0 +1,130 @@
+//! Serving the router with the connection settings of `HttpServerConfig`
+//! `axum::serve` keeps hyper's defaults, which cap throughput at high concurrency
+
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use shared::config::HttpServerConfig;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower::Service;
+
+/// Pause after a failed accept, such as when the process is out of file descriptors
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Serve `router` on `listener` until `shutdown` completes, then wait for open connections to
+/// finish their requests
+/// Handlers can extract the client address as `ConnectInfo<SocketAddr>`
+pub async fn serve<F>(listener: TcpListener, router: Router, config: HttpServerConfig, shutdown: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let mut make_service = router.into_make_service_with_connect_info::<SocketAddr>();
+    let builder = connection_builder(&config);
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!("HTTP accept error: {}", e);
+                    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+        if config.tcp_nodelay {
+            stream.set_nodelay(true).ok();
+        }
+
+        let service = make_service
+            .call(addr)
+            .await
+            .unwrap_or_else(|never| match never {});
+        let connection = builder
+            .serve_connection_with_upgrades(TokioIo::new(stream), TowerToHyperService::new(service))
+            .into_owned();
+        let connection = graceful.watch(connection);
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::debug!("HTTP connection {} closed: {}", addr, e);
+            }
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+}
+
+/// HTTP/1.1 and, unless disabled, HTTP/2 connections with the configured limits
+fn connection_builder(config: &HttpServerConfig) -> Builder<TokioExecutor> {
+    let mut builder = Builder::new(TokioExecutor::new());
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .keep_alive(config.keep_alive)
+        .header_read_timeout(Duration::from_millis(config.header_read_timeout_ms));
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .max_concurrent_streams(config.http2_max_concurrent_streams)
+        .keep_alive_interval(
+            config
+                .http2_keep_alive_interval_ms
+                .map(Duration::from_millis),
+        )
+        .keep_alive_timeout(Duration::from_millis(config.http2_keep_alive_timeout_ms));
+
+    if config.http2 {
+        builder
+    } else {
+        builder.http1_only()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::ConnectInfo;
+    use axum::routing::get;
+
+    #[tokio::test]
+    async fn test_serves_until_shutdown() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new().route(
+            "/peer",
+            get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move { peer.ip().to_string() }),
+        );
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(
+            listener,
+            router,
+            HttpServerConfig::default(),
+            async move {
+                stopped.await.ok();
+            },
+        ));
+
+        let body = reqwest::get(format!("http://{}/peer", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "127.0.0.1");
+
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
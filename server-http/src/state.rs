@@ -1,9 +1,20 @@
 use bytes::Bytes;
 use carbon::auth::{AuthService, MokaSessionRepository, RoleService, SessionStore, UserService};
+use carbon::event_sink::EventBridge;
 use carbon::events::CacheItemEvent;
 use carbon::planes::control::CacheManager;
-use carbon::planes::data::CacheOperationsService;
+use carbon::planes::data::{
+    CacheOperationsService, ChangeLog, ConnectionRegistry, KeyTracking, LatencyTracker,
+    Maintenance, OpsLimiter, QuotaTracker, SlowLog,
+};
+use carbon_query::IndexRegistry;
+use crate::middleware::{IdempotencyKeys, RateLimits, RejectedCredentials, TrustedProxies};
+use crate::oidc::OidcClient;
+use crate::reload::ConfigReloader;
+use crate::schedules::SchedulePolicy;
+use shared::config::{CacheDefaults, HttpLimits, RateLimitConfig, ScheduleConfig};
 use std::sync::Arc;
+use std::time::Duration;
 use storage_engine::UnifiedStorageFactory;
 use tokio::sync::broadcast;
 
@@ -12,11 +23,37 @@ use tokio::sync::broadcast;
 pub struct AppState {
     pub cache_manager: CacheManager<Vec<u8>, Bytes>,
     pub cache_operations: Arc<CacheOperationsService<Vec<u8>, Bytes>>,
+    pub index_registry: Arc<IndexRegistry>,
     pub event_channel: broadcast::Sender<CacheItemEvent>,
     pub auth_service: Arc<AuthService>,
     pub user_service: Arc<UserService>,
     pub role_service: Arc<RoleService>,
     pub session_store: Arc<SessionStore<MokaSessionRepository>>,
+    pub rate_limits: Arc<RateLimits>,
+    /// Proxies whose forwarding headers name the client, none by default
+    pub trusted_proxies: Arc<TrustedProxies>,
+    pub http_limits: HttpLimits,
+    /// Responses of recent requests made with an Idempotency-Key, replayed to their retries
+    pub idempotency_keys: Arc<IdempotencyKeys>,
+    /// Basic credentials that recently failed, refused again without verifying them
+    pub rejected_credentials: Arc<RejectedCredentials>,
+    /// Set when the server supports reloading its configuration at runtime
+    pub config_reloader: Option<Arc<ConfigReloader>>,
+    pub slow_log: Option<Arc<SlowLog>>,
+    /// Set when writes are charged to users and held to their quotas
+    pub quotas: Option<Arc<QuotaTracker>>,
+    /// Set when single sign-on through an OIDC provider is configured
+    pub oidc: Option<Arc<OidcClient>>,
+    /// Set when running next to the TCP server, whose connections it lists
+    pub connections: Option<Arc<ConnectionRegistry>>,
+    /// Set when cache events are forwarded to Kafka or NATS
+    pub event_sink: Option<Arc<EventBridge>>,
+    /// Set when changes to caches are logged for replay
+    pub change_log: Option<Arc<ChangeLog>>,
+    /// Settings filled in when a create request leaves them out
+    pub cache_defaults: CacheDefaults,
+    /// Where scheduled snapshots and warm-ups may reach, neither is allowed by default
+    pub schedule_policy: Arc<SchedulePolicy>,
 }
 
 impl AppState {
@@ -44,26 +81,45 @@ impl AppState {
         // Create broadcast channel for SSE events (1000 event buffer capacity)
         let (event_tx, _event_rx) = broadcast::channel(1000);
 
-        // Create cache operations service with event broadcaster
-        let cache_operations = Arc::new(CacheOperationsService::with_event_broadcaster(
-            cache_manager.clone(),
-            event_tx.clone(),
-        ));
+        // Secondary indexes over JSON caches
+        let index_registry = Arc::new(IndexRegistry::new());
+
+        // Create cache operations service with event broadcaster and index maintenance
+        let cache_operations = Arc::new(
+            CacheOperationsService::with_event_broadcaster(cache_manager.clone(), event_tx.clone())
+                .with_index_maintainer(index_registry.clone()),
+        );
 
         Self {
             cache_manager,
             cache_operations,
+            index_registry,
             event_channel: event_tx,
             auth_service,
             user_service,
             role_service,
             session_store,
+            rate_limits: Arc::new(RateLimits::default()),
+            trusted_proxies: Arc::new(TrustedProxies::default()),
+            http_limits: HttpLimits::default(),
+            idempotency_keys: Arc::new(IdempotencyKeys::new()),
+            rejected_credentials: Arc::new(RejectedCredentials::new()),
+            config_reloader: None,
+            slow_log: None,
+            quotas: None,
+            oidc: None,
+            connections: None,
+            event_sink: None,
+            change_log: None,
+            cache_defaults: CacheDefaults::default(),
+            schedule_policy: Arc::new(SchedulePolicy::default()),
         }
     }
 
     /// Create AppState with an existing CacheManager (for unified server)
     pub async fn new_with_cache_manager(
         cache_manager: CacheManager<Vec<u8>, Bytes>,
+        index_registry: Arc<IndexRegistry>,
         auth_service: Arc<AuthService>,
         user_service: Arc<UserService>,
         role_service: Arc<RoleService>,
@@ -72,37 +128,188 @@ impl AppState {
         // Create broadcast channel for SSE events
         let (event_tx, _event_rx) = broadcast::channel(1000);
 
-        // Create cache operations service with event broadcaster
-        let cache_operations = Arc::new(CacheOperationsService::with_event_broadcaster(
-            cache_manager.clone(),
-            event_tx.clone(),
-        ));
+        // Create cache operations service with event broadcaster and index maintenance
+        let cache_operations = Arc::new(
+            CacheOperationsService::with_event_broadcaster(cache_manager.clone(), event_tx.clone())
+                .with_index_maintainer(index_registry.clone()),
+        );
 
         Self {
             cache_manager,
             cache_operations,
+            index_registry,
             event_channel: event_tx,
             auth_service,
             user_service,
             role_service,
             session_store,
+            rate_limits: Arc::new(RateLimits::default()),
+            trusted_proxies: Arc::new(TrustedProxies::default()),
+            http_limits: HttpLimits::default(),
+            idempotency_keys: Arc::new(IdempotencyKeys::new()),
+            rejected_credentials: Arc::new(RejectedCredentials::new()),
+            config_reloader: None,
+            slow_log: None,
+            quotas: None,
+            oidc: None,
+            connections: None,
+            event_sink: None,
+            change_log: None,
+            cache_defaults: CacheDefaults::default(),
+            schedule_policy: Arc::new(SchedulePolicy::default()),
         }
     }
 
-    pub async fn init_with_persistence() -> shared::Result<CacheManager<Vec<u8>, Bytes>> {
-        // Get home directory for persistence path
-        let home_dir = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
-            .unwrap_or_else(|_| ".".to_string());
+    /// Override the default body size limit and request timeout
+    pub fn with_http_limits(mut self, http_limits: HttpLimits) -> Self {
+        self.http_limits = http_limits;
+        self
+    }
 
-        let persistence_path = std::path::Path::new(&home_dir)
-            .join(".carbon")
-            .join("caches.sled");
+    /// Throttle clients exceeding `config` (rate limiting is off by default)
+    pub fn with_rate_limits(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limits = Arc::new(RateLimits::new(config));
+        self
+    }
+
+    /// Record slow cache operations, pass the same log to other frontends to share it
+    pub fn with_slow_log(mut self, slow_log: Arc<SlowLog>) -> Self {
+        self.cache_operations = Arc::new(
+            CacheOperationsService::clone(&self.cache_operations).with_slow_log(slow_log.clone()),
+        );
+        self.slow_log = Some(slow_log);
+        self
+    }
+
+    /// Throttle cache operations, pass the same limiter to other frontends to share the budget
+    pub fn with_ops_limiter(mut self, ops_limiter: Arc<OpsLimiter>) -> Self {
+        self.cache_operations = Arc::new(
+            CacheOperationsService::clone(&self.cache_operations).with_ops_limiter(ops_limiter),
+        );
+        self
+    }
+
+    /// Refuse writes while `maintenance` says so, pass the same switch to other frontends to
+    /// refuse their writes too
+    pub fn with_maintenance(mut self, maintenance: Arc<Maintenance>) -> Self {
+        self.cache_operations = Arc::new(
+            CacheOperationsService::clone(&self.cache_operations).with_maintenance(maintenance),
+        );
+        self
+    }
+
+    /// Key the hashes of event keys under `secret`, pass the same secret to other frontends so
+    /// they hash a key alike
+    pub fn with_event_key_secret(mut self, secret: impl Into<Arc<[u8]>>) -> Self {
+        self.cache_operations = Arc::new(
+            CacheOperationsService::clone(&self.cache_operations).with_event_key_secret(secret),
+        );
+        self
+    }
+
+    /// Record operation latencies in `latencies`, pass the same tracker to other frontends to
+    /// report their operations too
+    pub fn with_latency_tracker(mut self, latencies: Arc<LatencyTracker>) -> Self {
+        self.cache_operations = Arc::new(
+            CacheOperationsService::clone(&self.cache_operations).with_latency_tracker(latencies),
+        );
+        self
+    }
+
+    /// Keep dropped caches restorable for `retention` before purging them
+    pub fn with_drop_retention(mut self, retention: Duration) -> Self {
+        self.cache_manager = self.cache_manager.with_drop_retention(retention);
+        self
+    }
+
+    /// Enforce quotas on HTTP writes, pass the same tracker to other frontends to share usage
+    pub fn with_quotas(mut self, quotas: Arc<QuotaTracker>) -> Self {
+        self.cache_operations = Arc::new(
+            CacheOperationsService::clone(&self.cache_operations)
+                .with_quota_tracker(quotas.clone()),
+        );
+        self.quotas = Some(quotas);
+        self
+    }
+
+    /// Log changes for GET /cache/{name}/changes, pass the same log to other frontends to
+    /// record their writes too
+    pub fn with_change_log(mut self, change_log: Arc<ChangeLog>) -> Self {
+        self.cache_operations = Arc::new(
+            CacheOperationsService::clone(&self.cache_operations)
+                .with_change_log(change_log.clone()),
+        );
+        self.change_log = Some(change_log);
+        self
+    }
 
+    /// Tell TCP clients tracking keys when HTTP writes make their copies stale, pass the table
+    /// the TCP server tracks keys in
+    pub fn with_key_tracking(mut self, key_tracking: Arc<KeyTracking>) -> Self {
+        self.cache_operations = Arc::new(
+            CacheOperationsService::clone(&self.cache_operations).with_key_tracking(key_tracking),
+        );
+        self
+    }
+
+    /// Believe the X-Forwarded-For and X-Real-IP headers of requests from `proxies`
+    pub fn with_trusted_proxies(mut self, proxies: &[String]) -> Self {
+        self.trusted_proxies = Arc::new(TrustedProxies::new(proxies));
+        self
+    }
+
+    /// Defaults of this instance for caches created over HTTP
+    pub fn with_cache_defaults(mut self, cache_defaults: CacheDefaults) -> Self {
+        self.cache_defaults = cache_defaults;
+        self
+    }
+
+    /// Allow scheduled snapshots and warm-ups within `config`
+    pub fn with_schedules(mut self, config: &ScheduleConfig) -> Self {
+        self.schedule_policy = Arc::new(SchedulePolicy::new(config));
+        self
+    }
+
+    /// Enable GET /auth/oidc/login and its callback
+    pub fn with_oidc(mut self, oidc: Arc<OidcClient>) -> Self {
+        self.oidc = Some(oidc);
+        self
+    }
+
+    /// Enable GET /admin/connections, listing the connections registered in `connections`
+    pub fn with_connections(mut self, connections: Arc<ConnectionRegistry>) -> Self {
+        self.connections = Some(connections);
+        self
+    }
+
+    /// Enable GET /admin/event-sink, reporting the deliveries of `event_sink`
+    pub fn with_event_sink(mut self, event_sink: Arc<EventBridge>) -> Self {
+        self.event_sink = Some(event_sink);
+        self
+    }
+
+    /// Enable POST /admin/config/reload
+    pub fn with_config_reloader(mut self, config_reloader: Arc<ConfigReloader>) -> Self {
+        self.config_reloader = Some(config_reloader);
+        self
+    }
+
+    pub async fn init_with_persistence() -> shared::Result<CacheManager<Vec<u8>, Bytes>> {
         // Create unified storage factory (supports Moka, Foyer Memory, and Foyer Hybrid)
         let factory = Arc::new(UnifiedStorageFactory);
 
         // Initialize CacheManager with persistence
-        CacheManager::new_with_persistence(persistence_path, factory).await
+        CacheManager::new_with_persistence(Self::persistence_path(), factory).await
+    }
+
+    /// Where cache configurations are stored, under the home directory
+    pub fn persistence_path() -> std::path::PathBuf {
+        let home_dir = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .unwrap_or_else(|_| ".".to_string());
+
+        std::path::Path::new(&home_dir)
+            .join(".carbon")
+            .join("caches.sled")
     }
 }
@@ -0,0 +1,90 @@
+//! OpenTelemetry export, compiled in with the `otel` feature
+//! Without it these functions are no-ops so callers need no feature gates of their own
+
+use axum::http::HeaderMap;
+use tracing::Span;
+
+#[cfg(feature = "otel")]
+mod otel {
+    use axum::http::HeaderMap;
+    use opentelemetry::propagation::Extractor;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::{global, KeyValue};
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+    use tracing::Subscriber;
+    use tracing_opentelemetry::OpenTelemetryLayer;
+    use tracing_subscriber::registry::LookupSpan;
+
+    /// Service name reported when OTEL_SERVICE_NAME is not set
+    const DEFAULT_SERVICE_NAME: &str = "carbon-cache";
+
+    /// Layer exporting spans to the OTLP (gRPC) collector at `endpoint`
+    pub fn layer<S>(
+        endpoint: &str,
+    ) -> Result<OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>, String>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let service_name =
+            std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string());
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                service_name,
+            )]))
+            .build();
+
+        let tracer = provider.tracer("carbon");
+        global::set_tracer_provider(provider);
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+    }
+
+    /// Reads W3C trace context (`traceparent`, `tracestate`) from HTTP headers
+    pub struct HeaderExtractor<'a>(pub &'a HeaderMap);
+
+    impl Extractor for HeaderExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|value| value.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(|key| key.as_str()).collect()
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+pub use otel::layer;
+
+/// Continue the caller's trace when the request carries a `traceparent` header
+pub fn set_parent_from_headers(span: &Span, headers: &HeaderMap) {
+    #[cfg(feature = "otel")]
+    {
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.extract(&otel::HeaderExtractor(headers))
+        });
+        span.set_parent(parent);
+    }
+
+    #[cfg(not(feature = "otel"))]
+    let _ = (span, headers);
+}
+
+/// Flush spans still buffered for export
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    opentelemetry::global::shutdown_tracer_provider();
+}
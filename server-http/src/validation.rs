@@ -1,71 +1,68 @@
-use crate::api::requests::CreateCacheRequest;
-use carbon::domain::{CacheConfig, CacheEvictionStrategy, EvictionAlgorithm};
+use crate::api::requests::{CreateCacheRequest, UpdateCacheRequest};
+use carbon::domain::{
+    CacheConfig, CacheConfigError, CacheConfigUpdate, CacheEvictionStrategy, CapacityUnit,
+    EvictionAlgorithm, ValueFormat, ValueType,
+};
 
-// Constants for validation ranges
-const MIN_MEM_BYTES: u64 = 1_048_576; // 1 MB
-const MAX_MEM_BYTES: u64 = 1_099_511_627_776; // 1 TB
-const MAX_SHARDS: u8 = 128; // Max 128 shards
+// Defaults applied to create requests, limits are enforced by CacheConfig::validate
 const DEFAULT_TTL_MS: u64 = 1_800_000; // 30 minutes
 const DEFAULT_SHARDS: u8 = 16; // Default to 16 shards
 
 #[derive(Debug)]
 pub enum ValidationError {
-    MissingRequiredField {
-        field: &'static str,
-        backend: &'static str,
-    },
-    InvalidCacheName {
-        reason: &'static str,
-    },
     InvalidBackendType(String),
     InvalidPolicy(String),
-    OutOfRange {
-        field: &'static str,
-        value: u64,
-        min: u64,
-        max: u64,
-    },
+    InvalidValueType(String),
+    InvalidValueFormat(String),
+    InvalidCapacityUnit(String),
+    Config(CacheConfigError),
+}
+
+impl From<CacheConfigError> for ValidationError {
+    fn from(err: CacheConfigError) -> Self {
+        ValidationError::Config(err)
+    }
 }
 
 impl std::fmt::Display for ValidationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ValidationError::MissingRequiredField { field, backend } => {
+            ValidationError::InvalidBackendType(backend) => {
                 write!(
                     f,
-                    "Missing required field '{}' for {} cache",
-                    field, backend
+                    "Invalid backend type '{}'. Must be 'ttl', 'size', 'storage', or 'redis'",
+                    backend
                 )
             }
-            ValidationError::InvalidCacheName { reason } => {
-                write!(f, "Invalid cache name: {}", reason)
+            ValidationError::InvalidPolicy(policy) => {
+                write!(
+                    f,
+                    "Invalid eviction policy '{}'. Must be 'lru', 'sieve', 'tinylfu', 'lfu' or 'arc'",
+                    policy
+                )
             }
-            ValidationError::InvalidBackendType(backend) => {
+            ValidationError::InvalidValueType(value_type) => {
                 write!(
                     f,
-                    "Invalid backend type '{}'. Must be 'ttl', 'size', or 'storage'",
-                    backend
+                    "Invalid value type '{}'. Must be 'raw' or 'json'",
+                    value_type
                 )
             }
-            ValidationError::InvalidPolicy(policy) => {
+            ValidationError::InvalidValueFormat(value_format) => {
                 write!(
                     f,
-                    "Invalid eviction policy '{}'. Must be 'lru', 'sieve', or 'tinylfu'",
-                    policy
+                    "Invalid value format '{}'. Must be 'raw', 'json' or 'msgpack'",
+                    value_format
                 )
             }
-            ValidationError::OutOfRange {
-                field,
-                value,
-                min,
-                max,
-            } => {
+            ValidationError::InvalidCapacityUnit(capacity_unit) => {
                 write!(
                     f,
-                    "Field '{}' value {} is out of range (min: {}, max: {})",
-                    field, value, min, max
+                    "Invalid capacity unit '{}'. Must be 'entries' or 'bytes'",
+                    capacity_unit
                 )
             }
+            ValidationError::Config(err) => write!(f, "{}", err),
         }
     }
 }
@@ -83,14 +80,35 @@ impl CacheConfigFactory {
         // Parse policy
         let policy = Self::parse_policy(&req.policy)?;
 
-        // Validate based on backend type
-        Self::validate_for_backend(&req, backend)?;
+        // Parse value type
+        let value_type = Self::parse_value_type(req.value_type.as_deref())?;
 
-        // Validate common fields
-        Self::validate_common_fields(&req)?;
+        // Parse the format values are stored in
+        let value_format = Self::parse_value_format(req.value_format.as_deref())?;
 
-        // Build config with validated and defaulted values
-        Ok(Self::build_config(req, backend, policy))
+        // Parse what mem_bytes counts
+        let capacity_unit = Self::parse_capacity_unit(req.capacity_unit.as_deref())?;
+
+        // Build config with defaulted values, then apply the rules shared by all frontends
+        let config = Self::build_config(req, backend, policy)
+            .with_value_type(value_type)
+            .with_value_format(value_format)
+            .with_capacity_unit(capacity_unit);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Changes to an existing cache, validated by CacheManager against the resulting config
+    pub fn update_from_request(req: UpdateCacheRequest) -> CacheConfigUpdate {
+        CacheConfigUpdate {
+            default_ttl_ms: req.default_ttl_ms,
+            mem_bytes: req.mem_bytes,
+            max_value_bytes: req.max_value_bytes,
+            description: req.description,
+            tags: req.tags,
+            max_ops_per_sec: req.max_ops_per_sec,
+            events: req.events,
+        }
     }
 
     fn parse_backend(eviction: &str) -> Result<CacheEvictionStrategy, ValidationError> {
@@ -98,6 +116,7 @@ impl CacheConfigFactory {
             "ttl" => Ok(CacheEvictionStrategy::TimeBound),
             "size" => Ok(CacheEvictionStrategy::SizeBounded),
             "storage" => Ok(CacheEvictionStrategy::OverflowToDisk),
+            "redis" => Ok(CacheEvictionStrategy::Redis),
             _ => Err(ValidationError::InvalidBackendType(eviction.to_string())),
         }
     }
@@ -111,111 +130,38 @@ impl CacheConfigFactory {
             "lru" => Ok(EvictionAlgorithm::Lru),
             "sieve" => Ok(EvictionAlgorithm::Sieve),
             "tinylfu" => Ok(EvictionAlgorithm::TinyLfu),
+            "lfu" => Ok(EvictionAlgorithm::Lfu),
+            "arc" => Ok(EvictionAlgorithm::Arc),
             _ => Err(ValidationError::InvalidPolicy(policy.to_string())),
         }
     }
 
-    fn validate_for_backend(
-        req: &CreateCacheRequest,
-        backend: CacheEvictionStrategy,
-    ) -> Result<(), ValidationError> {
-        match backend {
-            CacheEvictionStrategy::TimeBound => {
-                // TTL cache - all fields optional, will use defaults
-                // If mem_bytes is provided, validate it
-                if let Some(mem_bytes) = req.mem_bytes {
-                    if !(MIN_MEM_BYTES..=MAX_MEM_BYTES).contains(&mem_bytes) {
-                        return Err(ValidationError::OutOfRange {
-                            field: "mem_bytes",
-                            value: mem_bytes,
-                            min: MIN_MEM_BYTES,
-                            max: MAX_MEM_BYTES,
-                        });
-                    }
-                }
-                Ok(())
-            }
-            CacheEvictionStrategy::SizeBounded => {
-                // Size cache - mem_bytes is required
-                let mem_bytes = req.mem_bytes.ok_or(ValidationError::MissingRequiredField {
-                    field: "mem_bytes",
-                    backend: "SizeBounded",
-                })?;
-
-                // Validate range
-                if !(MIN_MEM_BYTES..=MAX_MEM_BYTES).contains(&mem_bytes) {
-                    return Err(ValidationError::OutOfRange {
-                        field: "mem_bytes",
-                        value: mem_bytes,
-                        min: MIN_MEM_BYTES,
-                        max: MAX_MEM_BYTES,
-                    });
-                }
-
-                Ok(())
-            }
-            CacheEvictionStrategy::OverflowToDisk => {
-                // Storage cache - mem_bytes and disk_path required
-                let mem_bytes = req.mem_bytes.ok_or(ValidationError::MissingRequiredField {
-                    field: "mem_bytes",
-                    backend: "OverflowToDisk",
-                })?;
-
-                if req.disk_path.is_none() || req.disk_path.as_ref().unwrap().is_empty() {
-                    return Err(ValidationError::MissingRequiredField {
-                        field: "disk_path",
-                        backend: "OverflowToDisk",
-                    });
-                }
-
-                // Validate range
-                if !(MIN_MEM_BYTES..=MAX_MEM_BYTES).contains(&mem_bytes) {
-                    return Err(ValidationError::OutOfRange {
-                        field: "mem_bytes",
-                        value: mem_bytes,
-                        min: MIN_MEM_BYTES,
-                        max: MAX_MEM_BYTES,
-                    });
-                }
-
-                Ok(())
-            }
+    fn parse_value_type(value_type: Option<&str>) -> Result<ValueType, ValidationError> {
+        match value_type.map(|v| v.to_lowercase()) {
+            None => Ok(ValueType::Raw), // Default
+            Some(v) if v.is_empty() || v == "raw" => Ok(ValueType::Raw),
+            Some(v) if v == "json" => Ok(ValueType::Json),
+            Some(v) => Err(ValidationError::InvalidValueType(v)),
         }
     }
 
-    fn validate_common_fields(req: &CreateCacheRequest) -> Result<(), ValidationError> {
-        // Validate name
-        if req.name.is_empty() {
-            return Err(ValidationError::InvalidCacheName {
-                reason: "cache name cannot be empty",
-            });
-        }
-
-        // Validate name contains only alphanumeric, hyphens, underscores
-        if !req
-            .name
-            .chars()
-            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
-        {
-            return Err(ValidationError::InvalidCacheName {
-                reason:
-                    "cache name must contain only alphanumeric characters, hyphens, or underscores",
-            });
+    fn parse_value_format(value_format: Option<&str>) -> Result<ValueFormat, ValidationError> {
+        match value_format.map(|v| v.to_lowercase()) {
+            None => Ok(ValueFormat::Raw), // Default
+            Some(v) if v.is_empty() || v == "raw" => Ok(ValueFormat::Raw),
+            Some(v) if v == "json" => Ok(ValueFormat::Json),
+            Some(v) if v == "msgpack" || v == "messagepack" => Ok(ValueFormat::MessagePack),
+            Some(v) => Err(ValidationError::InvalidValueFormat(v)),
         }
+    }
 
-        // Validate shards range if provided
-        if let Some(shards) = req.shards {
-            if shards > MAX_SHARDS {
-                return Err(ValidationError::OutOfRange {
-                    field: "shards",
-                    value: shards as u64,
-                    min: 1,
-                    max: MAX_SHARDS as u64,
-                });
-            }
+    fn parse_capacity_unit(capacity_unit: Option<&str>) -> Result<CapacityUnit, ValidationError> {
+        match capacity_unit.map(|v| v.to_lowercase()) {
+            None => Ok(CapacityUnit::Entries), // Default
+            Some(v) if v.is_empty() || v == "entries" => Ok(CapacityUnit::Entries),
+            Some(v) if v == "bytes" => Ok(CapacityUnit::Bytes),
+            Some(v) => Err(ValidationError::InvalidCapacityUnit(v)),
         }
-
-        Ok(())
     }
 
     fn build_config(
@@ -237,6 +183,17 @@ impl CacheConfigFactory {
 
         // Default shards to 16 if not provided
         let shards = req.shards.or(Some(DEFAULT_SHARDS));
+        let max_ops_per_sec = req.max_ops_per_sec;
+        let track_metadata = req.track_metadata;
+        let remote_url = req.remote_url;
+        let reap_interval_ms = req.reap_interval_ms;
+        let admission_threshold = req.admission_threshold;
+        let ttl_jitter_pct = req.ttl_jitter_pct;
+        let max_pinned_bytes = req.max_pinned_bytes;
+        let (l1_entries, l1_ttl_ms) = (req.l1_entries, req.l1_ttl_ms);
+        let timeseries_retention_ms = req.timeseries_retention_ms;
+        let timeseries_max_samples = req.timeseries_max_samples;
+        let events = req.events.unwrap_or_default();
 
         CacheConfig::with_backend(
             req.name,
@@ -250,5 +207,15 @@ impl CacheConfigFactory {
             req.description,
             req.tags,
         )
+        .with_max_ops_per_sec(max_ops_per_sec)
+        .with_track_metadata(track_metadata)
+        .with_remote_url(remote_url)
+        .with_reap_interval_ms(reap_interval_ms)
+        .with_admission_threshold(admission_threshold)
+        .with_ttl_jitter_pct(ttl_jitter_pct)
+        .with_max_pinned_bytes(max_pinned_bytes)
+        .with_l1_tier(l1_entries, l1_ttl_ms)
+        .with_timeseries_retention(timeseries_retention_ms, timeseries_max_samples)
+        .with_events(events)
     }
 }
@@ -0,0 +1,86 @@
+//! When quiet connections are pinged or closed
+
+use shared::config::TcpLimits;
+use std::time::Duration;
+
+/// What to do once a connection has been quiet for a while
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum IdleAction {
+    Wait,
+    Ping,
+    Close,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct IdlePolicy {
+    idle_timeout: Option<Duration>,
+    keepalive: Option<Duration>,
+}
+
+impl IdlePolicy {
+    pub(crate) fn new(limits: &TcpLimits) -> Self {
+        Self {
+            idle_timeout: limits.idle_timeout_ms.map(Duration::from_millis),
+            keepalive: limits.keepalive_ms.map(Duration::from_millis),
+        }
+    }
+
+    /// How long to wait for the next frame after `silent` without one, None to wait for as
+    /// long as it takes
+    pub(crate) fn next_wait(&self, silent: Duration, keepalive: bool) -> Option<Duration> {
+        let until_close = self
+            .idle_timeout
+            .map(|timeout| timeout.saturating_sub(silent));
+        let until_ping = self
+            .keepalive
+            .filter(|_| keepalive)
+            .map(|interval| interval - since_interval(silent, interval));
+        match (until_close, until_ping) {
+            (Some(close), Some(ping)) => Some(close.min(ping)),
+            (close, ping) => close.or(ping),
+        }
+    }
+
+    /// What is due once the connection has been quiet for `silent`, keepalives go to clients
+    /// that agreed on them
+    pub(crate) fn on_silence(&self, silent: Duration, keepalive: bool) -> IdleAction {
+        if self.idle_timeout.is_some_and(|timeout| silent >= timeout) {
+            return IdleAction::Close;
+        }
+        match self.keepalive {
+            Some(interval) if keepalive && since_interval(silent, interval).is_zero() => {
+                IdleAction::Ping
+            }
+            _ => IdleAction::Wait,
+        }
+    }
+}
+
+/// Time `silent` runs past its last whole `interval`
+fn since_interval(silent: Duration, interval: Duration) -> Duration {
+    Duration::from_nanos((silent.as_nanos() % interval.as_nanos()) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_connections_are_pinged_then_closed() {
+        let policy = IdlePolicy::new(&TcpLimits {
+            idle_timeout_ms: Some(25_000),
+            keepalive_ms: Some(10_000),
+            ..TcpLimits::default()
+        });
+        let secs = Duration::from_secs;
+
+        assert_eq!(policy.next_wait(secs(0), true), Some(secs(10)));
+        assert_eq!(policy.on_silence(secs(10), true), IdleAction::Ping);
+        assert_eq!(policy.next_wait(secs(20), true), Some(secs(5)));
+        assert_eq!(policy.on_silence(secs(25), true), IdleAction::Close);
+
+        // Clients that did not agree on keepalives are only closed
+        assert_eq!(policy.next_wait(secs(0), false), Some(secs(25)));
+        assert_eq!(IdlePolicy::default().next_wait(secs(0), true), None);
+    }
+}
@@ -1,5 +1,9 @@
+mod idle;
+pub mod listener;
 pub mod protocol;
 pub mod server;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
 
 pub use protocol::{Request, Response};
 pub use server::process_connection;
@@ -0,0 +1,314 @@
+//! Accepting TCP connections, optionally on several cores
+//! With more than one acceptor each accept loop gets its own listener bound to the same address
+//! with SO_REUSEPORT, so the kernel spreads new connections across them instead of queueing on
+//! a single loop
+
+use crate::server::process_connection;
+use bytes::Bytes;
+use carbon::planes::data::cache_operations::CacheOperationsService;
+use carbon::planes::data::connections::ConnectionRegistry;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+
+/// Whether several sockets may listen on one address, elsewhere a single accept loop is used
+pub const REUSE_PORT: bool = cfg!(all(
+    unix,
+    not(any(target_os = "solaris", target_os = "illumos"))
+));
+
+/// Pending connections the kernel queues per listener
+const BACKLOG: i32 = 1024;
+
+/// Pause after a failed accept, such as when the process is out of file descriptors
+pub(crate) const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A single accept loop unless more are configured
+/// Several loops share the port with SO_REUSEPORT, which lets any process of the same user
+/// bind it too, so it is only turned on when asked for
+pub fn default_acceptors() -> usize {
+    1
+}
+
+/// Non-blocking listening socket on `addr`, shareable with other sockets bound with
+/// `reuse_port` where REUSE_PORT holds
+pub fn bind_std(addr: SocketAddr, reuse_port: bool) -> io::Result<std::net::TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+    if reuse_port && REUSE_PORT {
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        socket.set_reuse_port(true)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(BACKLOG)?;
+    Ok(socket.into())
+}
+
+/// Bind `acceptors` listeners to `addr`
+/// With port 0 the first listener picks the port and the others join it
+pub fn bind(addr: SocketAddr, acceptors: usize) -> io::Result<Vec<TcpListener>> {
+    let acceptors = if REUSE_PORT { acceptors.max(1) } else { 1 };
+    let reuse_port = acceptors > 1;
+
+    let first = TcpListener::from_std(bind_std(addr, reuse_port)?)?;
+    let addr = first.local_addr()?;
+    let mut listeners = vec![first];
+    for _ in 1..acceptors {
+        listeners.push(TcpListener::from_std(bind_std(addr, reuse_port)?)?);
+    }
+    Ok(listeners)
+}
+
+/// Bind `acceptors` listeners to `addr` and serve them until the process exits
+/// Built with the `io-uring` feature on Linux, connections are served over io_uring instead
+/// Open connections are tracked in `connections`
+pub async fn run(
+    addr: SocketAddr,
+    acceptors: usize,
+    cache_ops: Arc<CacheOperationsService<Vec<u8>, Bytes>>,
+    connections: Arc<ConnectionRegistry>,
+) -> io::Result<()> {
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    {
+        let threads = crate::uring::serve(addr, acceptors, cache_ops, connections)?;
+        tracing::info!(
+            "TCP server accepting on {addr} with {} io_uring acceptor(s)",
+            threads.len()
+        );
+        tokio::task::spawn_blocking(move || {
+            for thread in threads {
+                let _ = thread.join();
+            }
+        })
+        .await
+        .ok();
+        Ok(())
+    }
+
+    #[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+    {
+        let listeners = bind(addr, acceptors)?;
+        tracing::info!(
+            "TCP server accepting on {} with {} acceptor(s)",
+            listeners[0].local_addr()?,
+            listeners.len()
+        );
+        serve(listeners, cache_ops, connections).await;
+        Ok(())
+    }
+}
+
+/// Run an accept loop on each listener, every connection served on its own task
+/// Only returns if all accept loops stop
+pub async fn serve(
+    listeners: Vec<TcpListener>,
+    cache_ops: Arc<CacheOperationsService<Vec<u8>, Bytes>>,
+    connections: Arc<ConnectionRegistry>,
+) {
+    let mut accept_loops = JoinSet::new();
+    for (acceptor, listener) in listeners.into_iter().enumerate() {
+        accept_loops.spawn(accept_loop(
+            acceptor,
+            listener,
+            cache_ops.clone(),
+            connections.clone(),
+        ));
+    }
+    while accept_loops.join_next().await.is_some() {}
+}
+
+async fn accept_loop(
+    acceptor: usize,
+    listener: TcpListener,
+    cache_ops: Arc<CacheOperationsService<Vec<u8>, Bytes>>,
+    connections: Arc<ConnectionRegistry>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((socket, addr)) => {
+                tracing::info!("TCP connection from {addr} on acceptor {acceptor}");
+                let cache_ops = cache_ops.clone();
+                let connections = connections.clone();
+
+                tokio::spawn(async move {
+                    if let Err(err) = process_connection(socket, cache_ops, connections).await {
+                        tracing::warn!("TCP connection {addr} error: {err:?}");
+                    }
+                });
+            }
+            Err(e) => {
+                tracing::error!("TCP accept error on acceptor {acceptor}: {}", e);
+                tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{
+        FEATURE_BULK_LOAD, FEATURE_CHECKSUM, FEATURE_COMPRESSION, Negotiated, PROTOCOL_VERSION,
+        Request, Response, checksum, compression,
+    };
+    use crate::server::MAX_FRAME_LENGTH;
+    use carbon::planes::control::CacheManager;
+    use futures::{SinkExt, StreamExt};
+    use shared::ErrorCode;
+    use tokio::net::TcpStream;
+    use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+    #[tokio::test]
+    async fn test_bind_shares_port() {
+        let listeners = bind("127.0.0.1:0".parse().unwrap(), 4).unwrap();
+        let addr = listeners[0].local_addr().unwrap();
+
+        let expected = if REUSE_PORT { 4 } else { 1 };
+        assert_eq!(listeners.len(), expected);
+        for listener in &listeners {
+            assert_eq!(listener.local_addr().unwrap(), addr);
+        }
+
+        let cache_ops = Arc::new(CacheOperationsService::new(CacheManager::new()));
+        tokio::spawn(serve(listeners, cache_ops, Arc::default()));
+
+        // Whichever acceptor takes them, every connection is served
+        for _ in 0..8 {
+            let socket = TcpStream::connect(addr).await.unwrap();
+            let mut framed = Framed::new(socket, LengthDelimitedCodec::new());
+            framed.send(Request::Ping.encode()).await.unwrap();
+            let frame = framed.next().await.unwrap().unwrap();
+            assert!(matches!(
+                Response::decode(frame.freeze()).unwrap(),
+                Response::Pong
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hello_negotiates_version_and_features() {
+        let listeners = bind("127.0.0.1:0".parse().unwrap(), 1).unwrap();
+        let addr = listeners[0].local_addr().unwrap();
+        let cache_ops = Arc::new(CacheOperationsService::new(CacheManager::new()));
+        tokio::spawn(serve(listeners, cache_ops, Arc::default()));
+
+        let socket = TcpStream::connect(addr).await.unwrap();
+        let mut framed = Framed::new(socket, LengthDelimitedCodec::new());
+        let hello = Request::Hello {
+            version: PROTOCOL_VERSION + 1,
+            features: u32::MAX,
+        };
+        framed.send(hello.encode()).await.unwrap();
+        let frame = framed.next().await.unwrap().unwrap();
+
+        match Response::decode(frame.freeze()).unwrap() {
+            Response::Hello { version, features } => {
+                let negotiated = Negotiated::new(u32::MAX, version, features);
+                assert_eq!(negotiated.version, PROTOCOL_VERSION);
+                assert!(negotiated.supports(FEATURE_BULK_LOAD));
+            }
+            response => panic!("Expected Hello, got {:?}", response),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checksummed_frames() {
+        let listeners = bind("127.0.0.1:0".parse().unwrap(), 1).unwrap();
+        let addr = listeners[0].local_addr().unwrap();
+        let cache_ops = Arc::new(CacheOperationsService::new(CacheManager::new()));
+        tokio::spawn(serve(listeners, cache_ops, Arc::default()));
+
+        let socket = TcpStream::connect(addr).await.unwrap();
+        let mut framed = Framed::new(socket, LengthDelimitedCodec::new());
+        let hello = Request::Hello {
+            version: PROTOCOL_VERSION,
+            features: FEATURE_CHECKSUM,
+        };
+        framed.send(hello.encode()).await.unwrap();
+        let frame = framed.next().await.unwrap().unwrap();
+        assert!(matches!(
+            Response::decode(frame.freeze()).unwrap(),
+            Response::Hello { .. }
+        ));
+
+        // Frames now end with a checksum, both ways
+        framed
+            .send(checksum::seal(&Request::Ping.encode()))
+            .await
+            .unwrap();
+        let frame = framed.next().await.unwrap().unwrap();
+        let response = checksum::open(frame.freeze()).unwrap();
+        assert!(matches!(
+            Response::decode(response).unwrap(),
+            Response::Pong
+        ));
+
+        let mut corrupted = checksum::seal(&Request::Ping.encode()).to_vec();
+        corrupted[0] ^= 0x01;
+        framed.send(Bytes::from(corrupted)).await.unwrap();
+        let frame = framed.next().await.unwrap().unwrap();
+        let response = checksum::open(frame.freeze()).unwrap();
+        assert!(matches!(
+            Response::decode(response).unwrap(),
+            Response::Error {
+                code: ErrorCode::ChecksumMismatch,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_compressed_frames() {
+        let listeners = bind("127.0.0.1:0".parse().unwrap(), 1).unwrap();
+        let addr = listeners[0].local_addr().unwrap();
+        let cache_ops = Arc::new(CacheOperationsService::new(CacheManager::new()));
+        let connections = Arc::new(ConnectionRegistry::new());
+        tokio::spawn(serve(listeners, cache_ops, connections.clone()));
+
+        let socket = TcpStream::connect(addr).await.unwrap();
+        let mut framed = Framed::new(socket, LengthDelimitedCodec::new());
+        let hello = Request::Hello {
+            version: PROTOCOL_VERSION,
+            features: FEATURE_COMPRESSION,
+        };
+        framed.send(hello.encode()).await.unwrap();
+        let frame = framed.next().await.unwrap().unwrap();
+        assert!(matches!(
+            Response::decode(frame.freeze()).unwrap(),
+            Response::Hello { .. }
+        ));
+
+        // Frames now start with their encoding, large ones are compressed
+        let put = Request::Put {
+            cache_name: "missing".to_string(),
+            key: Bytes::from("key"),
+            value: Bytes::from(vec![b'a'; 64 * 1024]),
+        };
+        let request = compression::compress(&put.encode());
+        assert!(compression::is_compressed(&request));
+        framed.send(request).await.unwrap();
+        let frame = framed.next().await.unwrap().unwrap();
+        let response = compression::decompress(frame.freeze(), MAX_FRAME_LENGTH).unwrap();
+        assert!(matches!(
+            Response::decode(response).unwrap(),
+            Response::Error {
+                code: ErrorCode::CacheNotFound,
+                ..
+            }
+        ));
+
+        let connections = connections.connections();
+        assert_eq!(connections.len(), 1);
+        let connection = &connections[0];
+        assert!(connection.compression);
+        assert_eq!(connection.received.frames, 2);
+        assert_eq!(connection.received.compressed_frames, 1);
+        assert!(connection.received.wire_bytes < connection.received.raw_bytes);
+        assert_eq!(connection.sent.frames, 2);
+    }
+}
@@ -1,16 +1,14 @@
-mod protocol;
-mod server;
-
-use server::process_connection;
-
 use bytes::Bytes;
 use std::sync::Arc;
 
 use carbon::{
     planes::data::cache_operations::CacheOperationsService,
+    planes::data::connections::ConnectionRegistry,
+    planes::data::key_tracking::{DEFAULT_MAX_TRACKED_BYTES, KeyTracking},
     planes::control::CacheManager,
 };
-use tokio::net::TcpListener;
+use server_tcp::listener;
+use shared::config::{ConfigSource, TcpLimits};
 use tracing::{Level, info};
 
 #[tokio::main]
@@ -29,26 +27,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Err(_) => info!("No .env file found, using system environment variables"),
     }
 
+    let limits = TcpLimits::from_source(&ConfigSource::env());
+    let key_tracking = Arc::new(KeyTracking::new(
+        limits
+            .max_tracked_bytes
+            .unwrap_or(DEFAULT_MAX_TRACKED_BYTES),
+    ));
+
     // Initialize CacheManager and CacheOperations
     let cache_manager = CacheManager::<Vec<u8>, Bytes>::new();
-    let cache_ops = Arc::new(CacheOperationsService::new(cache_manager));
-
-    let listener = TcpListener::bind(format!("{}:{}", DEFAULT_HOST, DEFAULT_PORT)).await?;
-
-    info!(
-        "TCP Server listening on tcp://{}:{}",
-        DEFAULT_HOST, DEFAULT_PORT
-    );
-
-    loop {
-        let (socket, addr) = listener.accept().await?;
-        let cache_ops_clone = cache_ops.clone();
-        tokio::spawn(async move {
-            tracing::info!("Connection {addr} successful.");
-
-            if let Err(err) = process_connection(socket, cache_ops_clone).await {
-                tracing::warn!("Connection {addr} error: {err:?}");
-            }
-        });
-    }
+    let cache_ops =
+        Arc::new(CacheOperationsService::new(cache_manager).with_key_tracking(key_tracking));
+
+    let addr = format!("{}:{}", DEFAULT_HOST, DEFAULT_PORT).parse()?;
+    let acceptors = std::env::var("CARBON_TCP_ACCEPTORS")
+        .ok()
+        .and_then(|acceptors| acceptors.parse::<usize>().ok())
+        .filter(|acceptors| *acceptors > 0)
+        .unwrap_or_else(listener::default_acceptors);
+
+    let connections = Arc::new(ConnectionRegistry::new().with_limits(limits));
+    listener::run(addr, acceptors, cache_ops, connections).await?;
+    Ok(())
 }
@@ -0,0 +1,50 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// Bytes of the CRC32C that ends a checksummed frame
+pub const CHECKSUM_LEN: usize = 4;
+
+/// Append the CRC32C of `message`, as frames are sent once FEATURE_CHECKSUM is negotiated
+pub fn seal(message: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(message.len() + CHECKSUM_LEN);
+    buf.put_slice(message);
+    buf.put_u32(crc32c::crc32c(message));
+    buf.freeze()
+}
+
+/// Strip the CRC32C from a checksummed frame, failing if the message does not match it
+pub fn open(mut frame: Bytes) -> Result<Bytes, String> {
+    if frame.len() < CHECKSUM_LEN {
+        return Err("Invalid frame: missing checksum".to_string());
+    }
+    let mut checksum = frame.split_off(frame.len() - CHECKSUM_LEN);
+    let expected = checksum.get_u32();
+    let actual = crc32c::crc32c(&frame);
+    if actual != expected {
+        return Err(format!(
+            "Checksum mismatch: frame carries 0x{:08X}, message hashes to 0x{:08X}",
+            expected, actual
+        ));
+    }
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let sealed = seal(b"hello");
+        assert_eq!(sealed.len(), 5 + CHECKSUM_LEN);
+        assert_eq!(open(sealed).unwrap(), Bytes::from("hello"));
+    }
+
+    #[test]
+    fn test_open_detects_corruption() {
+        let mut corrupted = seal(b"hello").to_vec();
+        corrupted[1] ^= 0x01;
+        assert!(open(Bytes::from(corrupted)).is_err());
+
+        assert!(open(Bytes::from_static(b"abc")).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// Messages smaller than this are sent uncompressed, LZ4 rarely pays for itself on them
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Encoding byte of a message sent as is
+pub const ENCODING_RAW: u8 = 0x00;
+/// Encoding byte of an LZ4 block, preceded by the length of the message it holds
+pub const ENCODING_LZ4: u8 = 0x01;
+
+/// Prefix `message` with its encoding, as frames are sent once FEATURE_COMPRESSION is
+/// negotiated
+/// Messages from COMPRESSION_THRESHOLD up are LZ4 compressed unless that does not shrink them
+pub fn compress(message: &[u8]) -> Bytes {
+    if message.len() >= COMPRESSION_THRESHOLD {
+        let compressed = lz4_flex::block::compress(message);
+        if compressed.len() + 4 < message.len() {
+            let mut buf = BytesMut::with_capacity(1 + 4 + compressed.len());
+            buf.put_u8(ENCODING_LZ4);
+            buf.put_u32(message.len() as u32);
+            buf.put_slice(&compressed);
+            return buf.freeze();
+        }
+    }
+
+    let mut buf = BytesMut::with_capacity(1 + message.len());
+    buf.put_u8(ENCODING_RAW);
+    buf.put_slice(message);
+    buf.freeze()
+}
+
+/// Whether a frame built by `compress` holds an LZ4 block
+pub fn is_compressed(frame: &[u8]) -> bool {
+    frame.first() == Some(&ENCODING_LZ4)
+}
+
+/// Recover the message of a frame built by `compress`, refusing messages over `max_len` before
+/// allocating them
+pub fn decompress(mut frame: Bytes, max_len: usize) -> Result<Bytes, String> {
+    if frame.is_empty() {
+        return Err("Invalid frame: missing encoding".to_string());
+    }
+    match frame.get_u8() {
+        ENCODING_RAW => Ok(frame),
+        ENCODING_LZ4 => {
+            if frame.len() < 4 {
+                return Err("Invalid frame: missing decompressed length".to_string());
+            }
+            let len = frame.get_u32() as usize;
+            if len > max_len {
+                return Err(format!(
+                    "Compressed frame holds {} bytes, more than the limit of {}",
+                    len, max_len
+                ));
+            }
+            let message = lz4_flex::block::decompress(&frame, len)
+                .map_err(|e| format!("Invalid LZ4 block: {}", e))?;
+            if message.len() != len {
+                return Err(format!(
+                    "Invalid LZ4 block: holds {} bytes, frame announced {}",
+                    message.len(),
+                    len
+                ));
+            }
+            Ok(Bytes::from(message))
+        }
+        encoding => Err(format!("Unknown frame encoding: 0x{:02X}", encoding)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_decompress_roundtrip() {
+        // Small messages are only tagged
+        let small = compress(b"hello");
+        assert_eq!(small.len(), 1 + 5);
+        assert!(!is_compressed(&small));
+        assert_eq!(decompress(small, 1024).unwrap(), Bytes::from("hello"));
+
+        let large = vec![b'a'; 64 * 1024];
+        let compressed = compress(&large);
+        assert!(is_compressed(&compressed));
+        assert!(compressed.len() < large.len() / 10);
+        assert_eq!(decompress(compressed, large.len()).unwrap(), large);
+    }
+
+    #[test]
+    fn test_decompress_rejects_bad_frames() {
+        let large = vec![b'a'; 64 * 1024];
+        // Over the limit before anything is allocated
+        assert!(decompress(compress(&large), 1024).is_err());
+
+        let mut truncated = compress(&large).to_vec();
+        truncated.truncate(truncated.len() - 1);
+        assert!(decompress(Bytes::from(truncated), large.len()).is_err());
+
+        assert!(decompress(Bytes::new(), 1024).is_err());
+        assert!(decompress(Bytes::from_static(&[0x7F, 1, 2]), 1024).is_err());
+    }
+}
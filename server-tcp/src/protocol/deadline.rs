@@ -0,0 +1,40 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::time::Duration;
+
+/// Bytes of the timeout that starts a request once FEATURE_DEADLINE is negotiated
+pub const DEADLINE_LEN: usize = 4;
+
+/// Prefix `message` with the milliseconds the client waits for its response, 0 for no deadline
+pub fn prefix(timeout_ms: u32, message: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(DEADLINE_LEN + message.len());
+    buf.put_u32(timeout_ms);
+    buf.put_slice(message);
+    buf.freeze()
+}
+
+/// Strip the timeout from a request, None when the client set no deadline
+pub fn split(mut frame: Bytes) -> Result<(Option<Duration>, Bytes), String> {
+    if frame.len() < DEADLINE_LEN {
+        return Err("Invalid frame: missing deadline".to_string());
+    }
+    let timeout_ms = frame.get_u32();
+    let timeout = (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms.into()));
+    Ok((timeout, frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_split_roundtrip() {
+        let (timeout, message) = split(prefix(250, b"hello")).unwrap();
+        assert_eq!(timeout, Some(Duration::from_millis(250)));
+        assert_eq!(message, Bytes::from("hello"));
+
+        let (timeout, _) = split(prefix(0, b"hello")).unwrap();
+        assert_eq!(timeout, None);
+
+        assert!(split(Bytes::from_static(b"ab")).is_err());
+    }
+}
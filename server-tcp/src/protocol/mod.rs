@@ -1,10 +1,71 @@
 use bytes::{Buf, BufMut, Bytes, BytesMut};
+use shared::ErrorCode;
+
+pub mod checksum;
+pub mod compression;
+pub mod deadline;
 
 // Command type identifiers
 pub const CMD_PING: u8 = 0x00;
 pub const CMD_PUT: u8 = 0x01;
 pub const CMD_GET: u8 = 0x02;
 pub const CMD_DELETE: u8 = 0x03;
+pub const CMD_GETDEL: u8 = 0x04;
+pub const CMD_GETEX: u8 = 0x05;
+
+// Hash command identifiers
+pub const CMD_HSET: u8 = 0x10;
+pub const CMD_HGET: u8 = 0x11;
+pub const CMD_HDEL: u8 = 0x12;
+pub const CMD_HGETALL: u8 = 0x13;
+
+// List command identifiers
+pub const CMD_LPUSH: u8 = 0x20;
+pub const CMD_RPUSH: u8 = 0x21;
+pub const CMD_LPOP: u8 = 0x22;
+pub const CMD_RPOP: u8 = 0x23;
+pub const CMD_BLPOP: u8 = 0x24;
+pub const CMD_BRPOP: u8 = 0x25;
+pub const CMD_LRANGE: u8 = 0x26;
+pub const CMD_LLEN: u8 = 0x27;
+
+// Set command identifiers
+pub const CMD_SADD: u8 = 0x30;
+pub const CMD_SREM: u8 = 0x31;
+pub const CMD_SISMEMBER: u8 = 0x32;
+pub const CMD_SMEMBERS: u8 = 0x33;
+pub const CMD_SCARD: u8 = 0x34;
+pub const CMD_SINTER: u8 = 0x35;
+pub const CMD_SUNION: u8 = 0x36;
+
+// Lock command identifiers
+pub const CMD_LOCK: u8 = 0x40;
+pub const CMD_UNLOCK: u8 = 0x41;
+
+// Bulk load command identifiers
+pub const CMD_BULKLOAD: u8 = 0x50;
+
+// Connection command identifiers
+pub const CMD_HELLO: u8 = 0x60;
+pub const CMD_TRACKING: u8 = 0x61;
+
+// Probabilistic command identifiers
+pub const CMD_PFADD: u8 = 0x70;
+pub const CMD_PFCOUNT: u8 = 0x71;
+pub const CMD_BF_RESERVE: u8 = 0x72;
+pub const CMD_BF_ADD: u8 = 0x73;
+pub const CMD_BF_EXISTS: u8 = 0x74;
+
+// Sorted set command identifiers
+pub const CMD_ZADD: u8 = 0x80;
+pub const CMD_ZREM: u8 = 0x81;
+pub const CMD_ZSCORE: u8 = 0x82;
+pub const CMD_ZRANK: u8 = 0x83;
+pub const CMD_ZRANGE: u8 = 0x84;
+pub const CMD_ZRANGEBYSCORE: u8 = 0x85;
+pub const CMD_ZCARD: u8 = 0x86;
+pub const CMD_APPEND: u8 = 0x90;
+pub const CMD_RANGE: u8 = 0x91;
 
 // Response type identifiers
 pub const RESP_PONG: u8 = 0x00;
@@ -12,13 +73,134 @@ pub const RESP_OK: u8 = 0x01;
 pub const RESP_VALUE: u8 = 0x02;
 pub const RESP_NOT_FOUND: u8 = 0x03;
 pub const RESP_ERROR: u8 = 0x04;
+pub const RESP_INTEGER: u8 = 0x05;
+pub const RESP_FIELDS: u8 = 0x06;
+pub const RESP_VALUES: u8 = 0x07;
+pub const RESP_THROTTLED: u8 = 0x08;
+pub const RESP_HELLO: u8 = 0x09;
+pub const RESP_PING: u8 = 0x0A;
+pub const RESP_INVALIDATE: u8 = 0x0B;
+pub const RESP_SCORED: u8 = 0x0C;
+pub const RESP_SAMPLES: u8 = 0x0D;
+
+/// Version of the protocol spoken here, exchanged in HELLO
+/// Servers from before HELLO speak version 0
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Version from which ERROR frames carry a code, sessions that never sent HELLO get them
+/// without one
+pub const ERROR_CODE_VERSION: u16 = 1;
+
+// Feature flags exchanged in HELLO
+/// BULKLOAD batches of entries
+pub const FEATURE_BULK_LOAD: u32 = 1 << 0;
+/// LZ4 compressed frames, see `compression`
+pub const FEATURE_COMPRESSION: u32 = 1 << 1;
+/// Connections must authenticate before running commands
+pub const FEATURE_AUTH_REQUIRED: u32 = 1 << 2;
+/// Every frame after the HELLO exchange ends with a CRC32C of its message, see `checksum`
+pub const FEATURE_CHECKSUM: u32 = 1 << 3;
+/// Every request after the HELLO exchange starts with the time its client waits, see `deadline`
+pub const FEATURE_DEADLINE: u32 = 1 << 4;
+/// The server may send PING between responses when the connection is quiet, clients answer with
+/// a PING request
+pub const FEATURE_KEEPALIVE: u32 = 1 << 5;
+/// TRACKING turns on invalidation notices for the keys the connection reads, which the server
+/// sends between responses
+pub const FEATURE_TRACKING: u32 = 1 << 6;
+
+/// What a client and server agreed on in HELLO
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Negotiated {
+    pub version: u16,
+    pub features: u32,
+}
+
+impl Negotiated {
+    /// Servers from before HELLO answer it with an ERROR, they support no optional features
+    pub const LEGACY: Self = Self {
+        version: 0,
+        features: 0,
+    };
+
+    /// Agreement from the features a client offered and the version of the other side and the
+    /// features of the server, so either side can work it out
+    /// Requirements the server imposes, such as authentication, apply whatever was offered
+    pub fn new(offered: u32, peer_version: u16, server_features: u32) -> Self {
+        Self {
+            version: peer_version.min(PROTOCOL_VERSION),
+            features: (offered & server_features) | (server_features & FEATURE_AUTH_REQUIRED),
+        }
+    }
+
+    pub fn supports(&self, feature: u32) -> bool {
+        self.features & feature != 0
+    }
+
+    /// Whether ERROR frames sent under this agreement carry a code
+    pub fn error_codes(&self) -> bool {
+        self.version >= ERROR_CODE_VERSION
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum Request {
     Ping,
+    /// Protocol version and feature flags the client supports
+    Hello { version: u16, features: u32 },
     Put { cache_name: String, key: Bytes, value: Bytes },
     Get { cache_name: String, key: Bytes },
     Delete { cache_name: String, key: Bytes },
+    GetDel { cache_name: String, key: Bytes },
+    GetEx { cache_name: String, key: Bytes, ttl_ms: u64 },
+    HSet { cache_name: String, key: Bytes, field: String, value: Bytes },
+    HGet { cache_name: String, key: Bytes, field: String },
+    HDel { cache_name: String, key: Bytes, field: String },
+    HGetAll { cache_name: String, key: Bytes },
+    LPush { cache_name: String, key: Bytes, values: Vec<Bytes> },
+    RPush { cache_name: String, key: Bytes, values: Vec<Bytes> },
+    LPop { cache_name: String, key: Bytes },
+    RPop { cache_name: String, key: Bytes },
+    BLPop { cache_name: String, key: Bytes, timeout_ms: u64 },
+    BRPop { cache_name: String, key: Bytes, timeout_ms: u64 },
+    LRange { cache_name: String, key: Bytes, start: i64, stop: i64 },
+    LLen { cache_name: String, key: Bytes },
+    SAdd { cache_name: String, key: Bytes, members: Vec<Bytes> },
+    SRem { cache_name: String, key: Bytes, members: Vec<Bytes> },
+    SIsMember { cache_name: String, key: Bytes, member: Bytes },
+    SMembers { cache_name: String, key: Bytes },
+    SCard { cache_name: String, key: Bytes },
+    SInter { cache_name: String, keys: Vec<Bytes> },
+    SUnion { cache_name: String, keys: Vec<Bytes> },
+    Lock { cache_name: String, key: Bytes, ttl_ms: u64 },
+    Unlock { cache_name: String, key: Bytes, token: u64 },
+    PfAdd { cache_name: String, key: Bytes, items: Vec<Bytes> },
+    PfCount { cache_name: String, keys: Vec<Bytes> },
+    BfReserve { cache_name: String, key: Bytes, error_rate: f64, capacity: u64 },
+    BfAdd { cache_name: String, key: Bytes, item: Bytes },
+    BfExists { cache_name: String, key: Bytes, item: Bytes },
+    ZAdd { cache_name: String, key: Bytes, members: Vec<(Bytes, f64)> },
+    ZRem { cache_name: String, key: Bytes, members: Vec<Bytes> },
+    ZScore { cache_name: String, key: Bytes, member: Bytes },
+    ZRank { cache_name: String, key: Bytes, member: Bytes, reverse: bool },
+    ZRange { cache_name: String, key: Bytes, start: i64, stop: i64, reverse: bool },
+    /// Members scoring between `min` and `max` inclusive, at most `limit` of them unless it is 0
+    ZRangeByScore { cache_name: String, key: Bytes, min: f64, max: f64, limit: u32 },
+    ZCard { cache_name: String, key: Bytes },
+    Append { cache_name: String, key: Bytes, timestamp: u64, value: Bytes },
+    /// Samples timestamped from `from` to `to` inclusive, at most `limit` of them unless it is 0
+    Range { cache_name: String, key: Bytes, from: u64, to: u64, limit: u32 },
+    BulkLoad { cache_name: String, records: Vec<BulkRecord> },
+    /// Turn invalidation notices for the keys this connection reads on or off
+    Tracking { enabled: bool },
+}
+
+/// One entry of a BULKLOAD frame, a zero `ttl_ms` leaves the cache's TTL in place
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkRecord {
+    pub key: Bytes,
+    pub value: Bytes,
+    pub ttl_ms: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -27,17 +209,193 @@ pub enum Response {
     Ok,
     Value { value: Bytes },
     NotFound,
-    Error { msg: String },
+    /// Failed request, `code` is the same one HTTP reports by name
+    Error { code: ErrorCode, msg: String },
+    Integer { value: i64 },
+    Fields { fields: Vec<(String, Bytes)> },
+    Values { values: Vec<Bytes> },
+    Throttled { msg: String },
+    /// Version agreed on, the lower of both sides', and the features the server supports
+    Hello { version: u16, features: u32 },
+    /// Keepalive sent by the server, not in answer to a request
+    Ping,
+    /// Sent by the server to connections with tracking on, not in answer to a request: copies
+    /// of `keys` of the cache are stale, all keys of it when `keys` is empty and everything the
+    /// connection read when `cache_name` is empty too
+    Invalidate { cache_name: String, keys: Vec<Bytes> },
+    /// Sorted set members with their scores, in rank order
+    Scored { members: Vec<(Bytes, f64)> },
+    /// Time series samples with their timestamps, oldest first
+    Samples { samples: Vec<(u64, Bytes)> },
 }
 
 impl Request {
+    /// Command name, as used in logs and spans
+    pub fn command(&self) -> &'static str {
+        match self {
+            Request::Ping => "PING",
+            Request::Hello { .. } => "HELLO",
+            Request::Put { .. } => "PUT",
+            Request::Get { .. } => "GET",
+            Request::Delete { .. } => "DELETE",
+            Request::GetDel { .. } => "GETDEL",
+            Request::GetEx { .. } => "GETEX",
+            Request::HSet { .. } => "HSET",
+            Request::HGet { .. } => "HGET",
+            Request::HDel { .. } => "HDEL",
+            Request::HGetAll { .. } => "HGETALL",
+            Request::LPush { .. } => "LPUSH",
+            Request::RPush { .. } => "RPUSH",
+            Request::LPop { .. } => "LPOP",
+            Request::RPop { .. } => "RPOP",
+            Request::BLPop { .. } => "BLPOP",
+            Request::BRPop { .. } => "BRPOP",
+            Request::LRange { .. } => "LRANGE",
+            Request::LLen { .. } => "LLEN",
+            Request::SAdd { .. } => "SADD",
+            Request::SRem { .. } => "SREM",
+            Request::SIsMember { .. } => "SISMEMBER",
+            Request::SMembers { .. } => "SMEMBERS",
+            Request::SCard { .. } => "SCARD",
+            Request::SInter { .. } => "SINTER",
+            Request::SUnion { .. } => "SUNION",
+            Request::Lock { .. } => "LOCK",
+            Request::Unlock { .. } => "UNLOCK",
+            Request::PfAdd { .. } => "PFADD",
+            Request::PfCount { .. } => "PFCOUNT",
+            Request::BfReserve { .. } => "BF.RESERVE",
+            Request::BfAdd { .. } => "BF.ADD",
+            Request::BfExists { .. } => "BF.EXISTS",
+            Request::ZAdd { .. } => "ZADD",
+            Request::ZRem { .. } => "ZREM",
+            Request::ZScore { .. } => "ZSCORE",
+            Request::ZRank { .. } => "ZRANK",
+            Request::ZRange { .. } => "ZRANGE",
+            Request::ZRangeByScore { .. } => "ZRANGEBYSCORE",
+            Request::ZCard { .. } => "ZCARD",
+            Request::Append { .. } => "APPEND",
+            Request::Range { .. } => "RANGE",
+            Request::BulkLoad { .. } => "BULKLOAD",
+            Request::Tracking { .. } => "TRACKING",
+        }
+    }
+
+    /// Cache the command targets, None for PING, HELLO and TRACKING
+    pub fn cache_name(&self) -> Option<&str> {
+        match self {
+            Request::Ping | Request::Hello { .. } | Request::Tracking { .. } => None,
+            Request::Put { cache_name, .. }
+            | Request::Get { cache_name, .. }
+            | Request::Delete { cache_name, .. }
+            | Request::GetDel { cache_name, .. }
+            | Request::GetEx { cache_name, .. }
+            | Request::HSet { cache_name, .. }
+            | Request::HGet { cache_name, .. }
+            | Request::HDel { cache_name, .. }
+            | Request::HGetAll { cache_name, .. }
+            | Request::LPush { cache_name, .. }
+            | Request::RPush { cache_name, .. }
+            | Request::LPop { cache_name, .. }
+            | Request::RPop { cache_name, .. }
+            | Request::BLPop { cache_name, .. }
+            | Request::BRPop { cache_name, .. }
+            | Request::LRange { cache_name, .. }
+            | Request::LLen { cache_name, .. }
+            | Request::SAdd { cache_name, .. }
+            | Request::SRem { cache_name, .. }
+            | Request::SIsMember { cache_name, .. }
+            | Request::SMembers { cache_name, .. }
+            | Request::SCard { cache_name, .. }
+            | Request::SInter { cache_name, .. }
+            | Request::SUnion { cache_name, .. }
+            | Request::Lock { cache_name, .. }
+            | Request::Unlock { cache_name, .. }
+            | Request::PfAdd { cache_name, .. }
+            | Request::PfCount { cache_name, .. }
+            | Request::BfReserve { cache_name, .. }
+            | Request::BfAdd { cache_name, .. }
+            | Request::BfExists { cache_name, .. }
+            | Request::ZAdd { cache_name, .. }
+            | Request::ZRem { cache_name, .. }
+            | Request::ZScore { cache_name, .. }
+            | Request::ZRank { cache_name, .. }
+            | Request::ZRange { cache_name, .. }
+            | Request::ZRangeByScore { cache_name, .. }
+            | Request::ZCard { cache_name, .. }
+            | Request::Append { cache_name, .. }
+            | Request::Range { cache_name, .. }
+            | Request::BulkLoad { cache_name, .. } => Some(cache_name),
+        }
+    }
+
+    /// Keys whose values the command returns, which connections with tracking on are told
+    /// about when they change
+    pub fn read_keys(&self) -> Vec<&Bytes> {
+        match self {
+            Request::Get { key, .. }
+            | Request::GetEx { key, .. }
+            | Request::HGet { key, .. }
+            | Request::HGetAll { key, .. }
+            | Request::LRange { key, .. }
+            | Request::LLen { key, .. }
+            | Request::SIsMember { key, .. }
+            | Request::SMembers { key, .. }
+            | Request::SCard { key, .. }
+            | Request::BfExists { key, .. }
+            | Request::ZScore { key, .. }
+            | Request::ZRank { key, .. }
+            | Request::ZRange { key, .. }
+            | Request::ZRangeByScore { key, .. }
+            | Request::ZCard { key, .. }
+            | Request::Range { key, .. } => vec![key],
+            Request::SInter { keys, .. }
+            | Request::SUnion { keys, .. }
+            | Request::PfCount { keys, .. } => keys.iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
     /// Encode a Request into Bytes for transmission
     ///
     /// Format:
     /// - PING: [0x00]
+    /// - HELLO: [0x60][version: u16][features: u32]
+    /// - TRACKING: [0x61][enabled: u8]
     /// - PUT: [0x01][key_len: u32][value_len: u32][key bytes][value bytes]
     /// - GET: [0x02][key_len: u32][key bytes]
     /// - DELETE: [0x03][key_len: u32][key bytes]
+    /// - GETDEL: [0x04][key_len: u32][key bytes]
+    /// - GETEX: [0x05][key_len: u32][key bytes][ttl_ms: u64]
+    /// - HSET: [0x10][key_len: u32][key bytes][field_len: u32][field][value_len: u32][value]
+    /// - HGET / HDEL: [0x11 / 0x12][key_len: u32][key bytes][field_len: u32][field]
+    /// - HGETALL: [0x13][key_len: u32][key bytes]
+    /// - LPUSH / RPUSH: [0x20 / 0x21][key_len: u32][key bytes][count: u32]([value_len: u32][value])*
+    /// - LPOP / RPOP / LLEN: [0x22 / 0x23 / 0x27][key_len: u32][key bytes]
+    /// - BLPOP / BRPOP: [0x24 / 0x25][key_len: u32][key bytes][timeout_ms: u64]
+    /// - LRANGE: [0x26][key_len: u32][key bytes][start: i64][stop: i64]
+    /// - SADD / SREM: [0x30 / 0x31][key_len: u32][key bytes][count: u32]([member_len: u32][member])*
+    /// - SISMEMBER: [0x32][key_len: u32][key bytes][member_len: u32][member]
+    /// - SMEMBERS / SCARD: [0x33 / 0x34][key_len: u32][key bytes]
+    /// - SINTER / SUNION: [0x35 / 0x36][count: u32]([key_len: u32][key bytes])*
+    /// - LOCK: [0x40][key_len: u32][key bytes][ttl_ms: u64]
+    /// - UNLOCK: [0x41][key_len: u32][key bytes][token: u64]
+    /// - PFADD: [0x70][key_len: u32][key bytes][count: u32]([item_len: u32][item])*
+    /// - PFCOUNT: [0x71][count: u32]([key_len: u32][key bytes])*
+    /// - BF.RESERVE: [0x72][key_len: u32][key bytes][error_rate: f64][capacity: u64]
+    /// - BF.ADD / BF.EXISTS: [0x73 / 0x74][key_len: u32][key bytes][item_len: u32][item]
+    /// - ZADD: [0x80][key_len: u32][key bytes][count: u32]([member_len: u32][member][score: f64])*
+    /// - ZREM: [0x81][key_len: u32][key bytes][count: u32]([member_len: u32][member])*
+    /// - ZSCORE: [0x82][key_len: u32][key bytes][member_len: u32][member]
+    /// - ZRANK: [0x83][key_len: u32][key bytes][member_len: u32][member][reverse: u8]
+    /// - ZRANGE: [0x84][key_len: u32][key bytes][start: i64][stop: i64][reverse: u8]
+    /// - ZRANGEBYSCORE: [0x85][key_len: u32][key bytes][min: f64][max: f64][limit: u32]
+    /// - ZCARD: [0x86][key_len: u32][key bytes]
+    /// - APPEND: [0x90][key_len: u32][key bytes][timestamp: u64][value_len: u32][value]
+    /// - RANGE: [0x91][key_len: u32][key bytes][from: u64][to: u64][limit: u32]
+    /// - BULKLOAD: [0x50][count: u32]([key_len: u32][key][value_len: u32][value][ttl_ms: u64])*
+    ///
+    /// Every command except PING, HELLO and TRACKING is preceded by
+    /// [cache_name_len: u32][cache_name]
     pub fn encode(&self) -> Bytes {
         let mut buf = BytesMut::new();
 
@@ -45,6 +403,15 @@ impl Request {
             Request::Ping => {
                 buf.put_u8(CMD_PING);
             }
+            Request::Hello { version, features } => {
+                buf.put_u8(CMD_HELLO);
+                buf.put_u16(*version);
+                buf.put_u32(*features);
+            }
+            Request::Tracking { enabled } => {
+                buf.put_u8(CMD_TRACKING);
+                buf.put_u8(*enabled as u8);
+            }
             Request::Put { cache_name, key, value } => {
                 buf.put_u8(CMD_PUT);
                 // Encode cache_name
@@ -77,6 +444,227 @@ impl Request {
                 buf.put_u32(key.len() as u32);
                 buf.put_slice(key);
             }
+            Request::GetDel { cache_name, key } => {
+                buf.put_u8(CMD_GETDEL);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+            }
+            Request::GetEx { cache_name, key, ttl_ms } => {
+                buf.put_u8(CMD_GETEX);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                buf.put_u64(*ttl_ms);
+            }
+            Request::HSet { cache_name, key, field, value } => {
+                buf.put_u8(CMD_HSET);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_length_prefixed(&mut buf, field.as_bytes());
+                put_length_prefixed(&mut buf, value);
+            }
+            Request::HGet { cache_name, key, field } => {
+                buf.put_u8(CMD_HGET);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_length_prefixed(&mut buf, field.as_bytes());
+            }
+            Request::HDel { cache_name, key, field } => {
+                buf.put_u8(CMD_HDEL);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_length_prefixed(&mut buf, field.as_bytes());
+            }
+            Request::HGetAll { cache_name, key } => {
+                buf.put_u8(CMD_HGETALL);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+            }
+            Request::LPush { cache_name, key, values }
+            | Request::RPush { cache_name, key, values } => {
+                buf.put_u8(if matches!(self, Request::LPush { .. }) {
+                    CMD_LPUSH
+                } else {
+                    CMD_RPUSH
+                });
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_values(&mut buf, values);
+            }
+            Request::LPop { cache_name, key }
+            | Request::RPop { cache_name, key }
+            | Request::LLen { cache_name, key } => {
+                buf.put_u8(match self {
+                    Request::LPop { .. } => CMD_LPOP,
+                    Request::RPop { .. } => CMD_RPOP,
+                    _ => CMD_LLEN,
+                });
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+            }
+            Request::BLPop { cache_name, key, timeout_ms }
+            | Request::BRPop { cache_name, key, timeout_ms } => {
+                buf.put_u8(if matches!(self, Request::BLPop { .. }) {
+                    CMD_BLPOP
+                } else {
+                    CMD_BRPOP
+                });
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                buf.put_u64(*timeout_ms);
+            }
+            Request::LRange { cache_name, key, start, stop } => {
+                buf.put_u8(CMD_LRANGE);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                buf.put_i64(*start);
+                buf.put_i64(*stop);
+            }
+            Request::SAdd { cache_name, key, members }
+            | Request::SRem { cache_name, key, members } => {
+                buf.put_u8(if matches!(self, Request::SAdd { .. }) {
+                    CMD_SADD
+                } else {
+                    CMD_SREM
+                });
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_values(&mut buf, members);
+            }
+            Request::SIsMember { cache_name, key, member } => {
+                buf.put_u8(CMD_SISMEMBER);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_length_prefixed(&mut buf, member);
+            }
+            Request::SMembers { cache_name, key } | Request::SCard { cache_name, key } => {
+                buf.put_u8(if matches!(self, Request::SMembers { .. }) {
+                    CMD_SMEMBERS
+                } else {
+                    CMD_SCARD
+                });
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+            }
+            Request::SInter { cache_name, keys } | Request::SUnion { cache_name, keys } => {
+                buf.put_u8(if matches!(self, Request::SInter { .. }) {
+                    CMD_SINTER
+                } else {
+                    CMD_SUNION
+                });
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_values(&mut buf, keys);
+            }
+            Request::Lock { cache_name, key, ttl_ms: argument }
+            | Request::Unlock { cache_name, key, token: argument } => {
+                buf.put_u8(if matches!(self, Request::Lock { .. }) {
+                    CMD_LOCK
+                } else {
+                    CMD_UNLOCK
+                });
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                buf.put_u64(*argument);
+            }
+            Request::PfAdd { cache_name, key, items } => {
+                buf.put_u8(CMD_PFADD);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_values(&mut buf, items);
+            }
+            Request::PfCount { cache_name, keys } => {
+                buf.put_u8(CMD_PFCOUNT);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_values(&mut buf, keys);
+            }
+            Request::BfReserve { cache_name, key, error_rate, capacity } => {
+                buf.put_u8(CMD_BF_RESERVE);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                buf.put_f64(*error_rate);
+                buf.put_u64(*capacity);
+            }
+            Request::BfAdd { cache_name, key, item }
+            | Request::BfExists { cache_name, key, item } => {
+                buf.put_u8(if matches!(self, Request::BfAdd { .. }) {
+                    CMD_BF_ADD
+                } else {
+                    CMD_BF_EXISTS
+                });
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_length_prefixed(&mut buf, item);
+            }
+            Request::ZAdd { cache_name, key, members } => {
+                buf.put_u8(CMD_ZADD);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_scored(&mut buf, members);
+            }
+            Request::ZRem { cache_name, key, members } => {
+                buf.put_u8(CMD_ZREM);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_values(&mut buf, members);
+            }
+            Request::ZScore { cache_name, key, member } => {
+                buf.put_u8(CMD_ZSCORE);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_length_prefixed(&mut buf, member);
+            }
+            Request::ZRank { cache_name, key, member, reverse } => {
+                buf.put_u8(CMD_ZRANK);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                put_length_prefixed(&mut buf, member);
+                buf.put_u8(*reverse as u8);
+            }
+            Request::ZRange { cache_name, key, start, stop, reverse } => {
+                buf.put_u8(CMD_ZRANGE);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                buf.put_i64(*start);
+                buf.put_i64(*stop);
+                buf.put_u8(*reverse as u8);
+            }
+            Request::ZRangeByScore { cache_name, key, min, max, limit } => {
+                buf.put_u8(CMD_ZRANGEBYSCORE);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                buf.put_f64(*min);
+                buf.put_f64(*max);
+                buf.put_u32(*limit);
+            }
+            Request::ZCard { cache_name, key } => {
+                buf.put_u8(CMD_ZCARD);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+            }
+            Request::Append { cache_name, key, timestamp, value } => {
+                buf.put_u8(CMD_APPEND);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                buf.put_u64(*timestamp);
+                put_length_prefixed(&mut buf, value);
+            }
+            Request::Range { cache_name, key, from, to, limit } => {
+                buf.put_u8(CMD_RANGE);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_length_prefixed(&mut buf, key);
+                buf.put_u64(*from);
+                buf.put_u64(*to);
+                buf.put_u32(*limit);
+            }
+            Request::BulkLoad { cache_name, records } => {
+                buf.put_u8(CMD_BULKLOAD);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                buf.put_u32(records.len() as u32);
+                for record in records {
+                    put_length_prefixed(&mut buf, &record.key);
+                    put_length_prefixed(&mut buf, &record.value);
+                    buf.put_u64(record.ttl_ms);
+                }
+            }
         }
 
         buf.freeze()
@@ -194,6 +782,280 @@ impl Request {
                 let key = buf.copy_to_bytes(key_len);
                 Ok(Request::Delete { cache_name, key })
             }
+            CMD_GETDEL => {
+                let cache_name = get_string(&mut buf, "GETDEL", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "GETDEL", "key")?;
+                Ok(Request::GetDel { cache_name, key })
+            }
+            CMD_GETEX => {
+                let cache_name = get_string(&mut buf, "GETEX", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "GETEX", "key")?;
+                if buf.remaining() < 8 {
+                    return Err("Invalid GETEX: missing ttl".to_string());
+                }
+                let ttl_ms = buf.get_u64();
+                Ok(Request::GetEx { cache_name, key, ttl_ms })
+            }
+            CMD_HSET => {
+                let cache_name = get_string(&mut buf, "HSET", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "HSET", "key")?;
+                let field = get_string(&mut buf, "HSET", "field")?;
+                let value = get_length_prefixed(&mut buf, "HSET", "value")?;
+                Ok(Request::HSet { cache_name, key, field, value })
+            }
+            CMD_HGET => {
+                let cache_name = get_string(&mut buf, "HGET", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "HGET", "key")?;
+                let field = get_string(&mut buf, "HGET", "field")?;
+                Ok(Request::HGet { cache_name, key, field })
+            }
+            CMD_HDEL => {
+                let cache_name = get_string(&mut buf, "HDEL", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "HDEL", "key")?;
+                let field = get_string(&mut buf, "HDEL", "field")?;
+                Ok(Request::HDel { cache_name, key, field })
+            }
+            CMD_HGETALL => {
+                let cache_name = get_string(&mut buf, "HGETALL", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "HGETALL", "key")?;
+                Ok(Request::HGetAll { cache_name, key })
+            }
+            CMD_LPUSH | CMD_RPUSH => {
+                let command = if cmd == CMD_LPUSH { "LPUSH" } else { "RPUSH" };
+                let cache_name = get_string(&mut buf, command, "cache_name")?;
+                let key = get_length_prefixed(&mut buf, command, "key")?;
+                let values = get_values(&mut buf, command)?;
+                if cmd == CMD_LPUSH {
+                    Ok(Request::LPush { cache_name, key, values })
+                } else {
+                    Ok(Request::RPush { cache_name, key, values })
+                }
+            }
+            CMD_LPOP | CMD_RPOP | CMD_LLEN => {
+                let command = match cmd {
+                    CMD_LPOP => "LPOP",
+                    CMD_RPOP => "RPOP",
+                    _ => "LLEN",
+                };
+                let cache_name = get_string(&mut buf, command, "cache_name")?;
+                let key = get_length_prefixed(&mut buf, command, "key")?;
+                match cmd {
+                    CMD_LPOP => Ok(Request::LPop { cache_name, key }),
+                    CMD_RPOP => Ok(Request::RPop { cache_name, key }),
+                    _ => Ok(Request::LLen { cache_name, key }),
+                }
+            }
+            CMD_BLPOP | CMD_BRPOP => {
+                let command = if cmd == CMD_BLPOP { "BLPOP" } else { "BRPOP" };
+                let cache_name = get_string(&mut buf, command, "cache_name")?;
+                let key = get_length_prefixed(&mut buf, command, "key")?;
+                if buf.remaining() < 8 {
+                    return Err(format!("Invalid {}: missing timeout", command));
+                }
+                let timeout_ms = buf.get_u64();
+                if cmd == CMD_BLPOP {
+                    Ok(Request::BLPop { cache_name, key, timeout_ms })
+                } else {
+                    Ok(Request::BRPop { cache_name, key, timeout_ms })
+                }
+            }
+            CMD_LRANGE => {
+                let cache_name = get_string(&mut buf, "LRANGE", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "LRANGE", "key")?;
+                if buf.remaining() < 16 {
+                    return Err("Invalid LRANGE: missing start/stop".to_string());
+                }
+                let start = buf.get_i64();
+                let stop = buf.get_i64();
+                Ok(Request::LRange { cache_name, key, start, stop })
+            }
+            CMD_SADD | CMD_SREM => {
+                let command = if cmd == CMD_SADD { "SADD" } else { "SREM" };
+                let cache_name = get_string(&mut buf, command, "cache_name")?;
+                let key = get_length_prefixed(&mut buf, command, "key")?;
+                let members = get_values(&mut buf, command)?;
+                if cmd == CMD_SADD {
+                    Ok(Request::SAdd { cache_name, key, members })
+                } else {
+                    Ok(Request::SRem { cache_name, key, members })
+                }
+            }
+            CMD_SISMEMBER => {
+                let cache_name = get_string(&mut buf, "SISMEMBER", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "SISMEMBER", "key")?;
+                let member = get_length_prefixed(&mut buf, "SISMEMBER", "member")?;
+                Ok(Request::SIsMember { cache_name, key, member })
+            }
+            CMD_SMEMBERS | CMD_SCARD => {
+                let command = if cmd == CMD_SMEMBERS { "SMEMBERS" } else { "SCARD" };
+                let cache_name = get_string(&mut buf, command, "cache_name")?;
+                let key = get_length_prefixed(&mut buf, command, "key")?;
+                if cmd == CMD_SMEMBERS {
+                    Ok(Request::SMembers { cache_name, key })
+                } else {
+                    Ok(Request::SCard { cache_name, key })
+                }
+            }
+            CMD_SINTER | CMD_SUNION => {
+                let command = if cmd == CMD_SINTER { "SINTER" } else { "SUNION" };
+                let cache_name = get_string(&mut buf, command, "cache_name")?;
+                let keys = get_values(&mut buf, command)?;
+                if cmd == CMD_SINTER {
+                    Ok(Request::SInter { cache_name, keys })
+                } else {
+                    Ok(Request::SUnion { cache_name, keys })
+                }
+            }
+            CMD_LOCK | CMD_UNLOCK => {
+                let command = if cmd == CMD_LOCK { "LOCK" } else { "UNLOCK" };
+                let cache_name = get_string(&mut buf, command, "cache_name")?;
+                let key = get_length_prefixed(&mut buf, command, "key")?;
+                if buf.remaining() < 8 {
+                    return Err(if cmd == CMD_LOCK {
+                        "Invalid LOCK: missing ttl".to_string()
+                    } else {
+                        "Invalid UNLOCK: missing token".to_string()
+                    });
+                }
+                let argument = buf.get_u64();
+                if cmd == CMD_LOCK {
+                    Ok(Request::Lock { cache_name, key, ttl_ms: argument })
+                } else {
+                    Ok(Request::Unlock { cache_name, key, token: argument })
+                }
+            }
+            CMD_PFADD => {
+                let cache_name = get_string(&mut buf, "PFADD", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "PFADD", "key")?;
+                let items = get_values(&mut buf, "PFADD")?;
+                Ok(Request::PfAdd { cache_name, key, items })
+            }
+            CMD_PFCOUNT => {
+                let cache_name = get_string(&mut buf, "PFCOUNT", "cache_name")?;
+                let keys = get_values(&mut buf, "PFCOUNT")?;
+                Ok(Request::PfCount { cache_name, keys })
+            }
+            CMD_BF_RESERVE => {
+                let cache_name = get_string(&mut buf, "BF.RESERVE", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "BF.RESERVE", "key")?;
+                if buf.remaining() < 16 {
+                    return Err("Invalid BF.RESERVE: missing error_rate/capacity".to_string());
+                }
+                let error_rate = buf.get_f64();
+                let capacity = buf.get_u64();
+                Ok(Request::BfReserve { cache_name, key, error_rate, capacity })
+            }
+            CMD_BF_ADD | CMD_BF_EXISTS => {
+                let command = if cmd == CMD_BF_ADD { "BF.ADD" } else { "BF.EXISTS" };
+                let cache_name = get_string(&mut buf, command, "cache_name")?;
+                let key = get_length_prefixed(&mut buf, command, "key")?;
+                let item = get_length_prefixed(&mut buf, command, "item")?;
+                if cmd == CMD_BF_ADD {
+                    Ok(Request::BfAdd { cache_name, key, item })
+                } else {
+                    Ok(Request::BfExists { cache_name, key, item })
+                }
+            }
+            CMD_ZADD => {
+                let cache_name = get_string(&mut buf, "ZADD", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "ZADD", "key")?;
+                let members = get_scored(&mut buf, "ZADD")?;
+                Ok(Request::ZAdd { cache_name, key, members })
+            }
+            CMD_ZREM => {
+                let cache_name = get_string(&mut buf, "ZREM", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "ZREM", "key")?;
+                let members = get_values(&mut buf, "ZREM")?;
+                Ok(Request::ZRem { cache_name, key, members })
+            }
+            CMD_ZSCORE | CMD_ZRANK => {
+                let command = if cmd == CMD_ZSCORE { "ZSCORE" } else { "ZRANK" };
+                let cache_name = get_string(&mut buf, command, "cache_name")?;
+                let key = get_length_prefixed(&mut buf, command, "key")?;
+                let member = get_length_prefixed(&mut buf, command, "member")?;
+                if cmd == CMD_ZSCORE {
+                    return Ok(Request::ZScore { cache_name, key, member });
+                }
+                if buf.remaining() < 1 {
+                    return Err("Invalid ZRANK: missing reverse flag".to_string());
+                }
+                Ok(Request::ZRank { cache_name, key, member, reverse: buf.get_u8() != 0 })
+            }
+            CMD_ZRANGE => {
+                let cache_name = get_string(&mut buf, "ZRANGE", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "ZRANGE", "key")?;
+                if buf.remaining() < 17 {
+                    return Err("Invalid ZRANGE: missing start/stop/reverse".to_string());
+                }
+                let start = buf.get_i64();
+                let stop = buf.get_i64();
+                let reverse = buf.get_u8() != 0;
+                Ok(Request::ZRange { cache_name, key, start, stop, reverse })
+            }
+            CMD_ZRANGEBYSCORE => {
+                let cache_name = get_string(&mut buf, "ZRANGEBYSCORE", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "ZRANGEBYSCORE", "key")?;
+                if buf.remaining() < 20 {
+                    return Err("Invalid ZRANGEBYSCORE: missing min/max/limit".to_string());
+                }
+                let min = buf.get_f64();
+                let max = buf.get_f64();
+                let limit = buf.get_u32();
+                Ok(Request::ZRangeByScore { cache_name, key, min, max, limit })
+            }
+            CMD_ZCARD => {
+                let cache_name = get_string(&mut buf, "ZCARD", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "ZCARD", "key")?;
+                Ok(Request::ZCard { cache_name, key })
+            }
+            CMD_APPEND => {
+                let cache_name = get_string(&mut buf, "APPEND", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "APPEND", "key")?;
+                if buf.remaining() < 8 {
+                    return Err("Invalid APPEND: missing timestamp".to_string());
+                }
+                let timestamp = buf.get_u64();
+                let value = get_length_prefixed(&mut buf, "APPEND", "value")?;
+                Ok(Request::Append { cache_name, key, timestamp, value })
+            }
+            CMD_RANGE => {
+                let cache_name = get_string(&mut buf, "RANGE", "cache_name")?;
+                let key = get_length_prefixed(&mut buf, "RANGE", "key")?;
+                if buf.remaining() < 20 {
+                    return Err("Invalid RANGE: missing from/to/limit".to_string());
+                }
+                let from = buf.get_u64();
+                let to = buf.get_u64();
+                let limit = buf.get_u32();
+                Ok(Request::Range { cache_name, key, from, to, limit })
+            }
+            CMD_BULKLOAD => {
+                let cache_name = get_string(&mut buf, "BULKLOAD", "cache_name")?;
+                if buf.remaining() < 4 {
+                    return Err("Invalid BULKLOAD: missing record count".to_string());
+                }
+                let count = buf.get_u32() as usize;
+                let mut records = Vec::with_capacity(count.min(1024));
+                for _ in 0..count {
+                    let key = get_length_prefixed(&mut buf, "BULKLOAD", "key")?;
+                    let value = get_length_prefixed(&mut buf, "BULKLOAD", "value")?;
+                    if buf.remaining() < 8 {
+                        return Err("Invalid BULKLOAD: missing ttl".to_string());
+                    }
+                    records.push(BulkRecord { key, value, ttl_ms: buf.get_u64() });
+                }
+                Ok(Request::BulkLoad { cache_name, records })
+            }
+            CMD_HELLO => {
+                let (version, features) = get_hello(&mut buf, "HELLO")?;
+                Ok(Request::Hello { version, features })
+            }
+            CMD_TRACKING => {
+                if buf.remaining() < 1 {
+                    return Err("Invalid TRACKING: missing flag".to_string());
+                }
+                Ok(Request::Tracking { enabled: buf.get_u8() != 0 })
+            }
             _ => Err(format!("Unknown command: 0x{:02X}", cmd)),
         }
     }
@@ -207,7 +1069,16 @@ impl Response {
     /// - OK: [0x01]
     /// - VALUE: [0x02][value_len: u32][value bytes]
     /// - NOT_FOUND: [0x03]
-    /// - ERROR: [0x04][msg_len: u32][msg bytes]
+    /// - ERROR: [0x04][code: u16][msg_len: u32][msg bytes], code from shared::ErrorCode, or
+    ///   [0x04][msg_len: u32][msg bytes] for sessions that never sent HELLO, see `encode_for`
+    /// - INTEGER: [0x05][value: i64]
+    /// - FIELDS: [0x06][count: u32]([field_len: u32][field][value_len: u32][value])*
+    /// - VALUES: [0x07][count: u32]([value_len: u32][value])*
+    /// - THROTTLED: [0x08][msg_len: u32][msg bytes]
+    /// - HELLO: [0x09][version: u16][features: u32]
+    /// - PING: [0x0A]
+    /// - INVALIDATE: [0x0B][cache_name_len: u32][cache_name][count: u32]([key_len: u32][key])*
+    /// - SCORED: [0x0C][count: u32]([member_len: u32][member][score: f64])*
     pub fn encode(&self) -> Bytes {
         let mut buf = BytesMut::new();
 
@@ -226,17 +1097,77 @@ impl Response {
             Response::NotFound => {
                 buf.put_u8(RESP_NOT_FOUND);
             }
-            Response::Error { msg } => {
+            Response::Error { code, msg } => {
                 buf.put_u8(RESP_ERROR);
+                buf.put_u16(code.number());
                 let msg_bytes = msg.as_bytes();
                 buf.put_u32(msg_bytes.len() as u32);
                 buf.put_slice(msg_bytes);
             }
+            Response::Integer { value } => {
+                buf.put_u8(RESP_INTEGER);
+                buf.put_i64(*value);
+            }
+            Response::Fields { fields } => {
+                buf.put_u8(RESP_FIELDS);
+                buf.put_u32(fields.len() as u32);
+                for (field, value) in fields {
+                    put_length_prefixed(&mut buf, field.as_bytes());
+                    put_length_prefixed(&mut buf, value);
+                }
+            }
+            Response::Values { values } => {
+                buf.put_u8(RESP_VALUES);
+                put_values(&mut buf, values);
+            }
+            Response::Throttled { msg } => {
+                buf.put_u8(RESP_THROTTLED);
+                put_length_prefixed(&mut buf, msg.as_bytes());
+            }
+            Response::Hello { version, features } => {
+                buf.put_u8(RESP_HELLO);
+                buf.put_u16(*version);
+                buf.put_u32(*features);
+            }
+            Response::Ping => {
+                buf.put_u8(RESP_PING);
+            }
+            Response::Invalidate { cache_name, keys } => {
+                buf.put_u8(RESP_INVALIDATE);
+                put_length_prefixed(&mut buf, cache_name.as_bytes());
+                put_values(&mut buf, keys);
+            }
+            Response::Scored { members } => {
+                buf.put_u8(RESP_SCORED);
+                put_scored(&mut buf, members);
+            }
+            Response::Samples { samples } => {
+                buf.put_u8(RESP_SAMPLES);
+                buf.put_u32(samples.len() as u32);
+                for (timestamp, value) in samples {
+                    buf.put_u64(*timestamp);
+                    put_length_prefixed(&mut buf, value);
+                }
+            }
         }
 
         buf.freeze()
     }
 
+    /// Encode a Response for a session that agreed on `agreed`
+    /// Clients that never sent HELLO predate error codes and read ERROR frames without one
+    pub fn encode_for(&self, agreed: Negotiated) -> Bytes {
+        match self {
+            Response::Error { msg, .. } if !agreed.error_codes() => {
+                let mut buf = BytesMut::new();
+                buf.put_u8(RESP_ERROR);
+                put_length_prefixed(&mut buf, msg.as_bytes());
+                buf.freeze()
+            }
+            response => response.encode(),
+        }
+    }
+
     /// Decode a Response from Bytes received from the network
     pub fn decode(mut buf: Bytes) -> Result<Self, String> {
         if buf.is_empty() {
@@ -268,10 +1199,23 @@ impl Response {
             }
             RESP_NOT_FOUND => Ok(Response::NotFound),
             RESP_ERROR => {
-                if buf.remaining() < 4 {
-                    return Err("Invalid ERROR: missing length".to_string());
+                // Servers from before error codes, and sessions without HELLO, send the message
+                // alone, whose length then accounts for the whole frame
+                let coded =
+                    buf.remaining() >= 6 && (&buf[2..6]).get_u32() as usize == buf.remaining() - 6;
+                let legacy =
+                    buf.remaining() >= 4 && (&buf[..4]).get_u32() as usize == buf.remaining() - 4;
+                if !coded && !legacy {
+                    return Err("Invalid ERROR: missing code or length".to_string());
                 }
 
+                // Codes from newer servers, and errors without one, are reported as internal
+                let code = if coded {
+                    ErrorCode::from_number(buf.get_u16()).unwrap_or(ErrorCode::Internal)
+                } else {
+                    ErrorCode::Internal
+                };
+
                 let msg_len = buf.get_u32() as usize;
 
                 if buf.remaining() < msg_len {
@@ -284,13 +1228,157 @@ impl Response {
 
                 let msg_bytes = buf.copy_to_bytes(msg_len);
                 let msg = String::from_utf8_lossy(&msg_bytes).to_string();
-                Ok(Response::Error { msg })
+                Ok(Response::Error { code, msg })
+            }
+            RESP_INTEGER => {
+                if buf.remaining() < 8 {
+                    return Err("Invalid INTEGER: missing value".to_string());
+                }
+                Ok(Response::Integer { value: buf.get_i64() })
+            }
+            RESP_FIELDS => {
+                if buf.remaining() < 4 {
+                    return Err("Invalid FIELDS: missing count".to_string());
+                }
+                let count = buf.get_u32() as usize;
+                let mut fields = Vec::with_capacity(count.min(1024));
+                for _ in 0..count {
+                    let field = get_string(&mut buf, "FIELDS", "field")?;
+                    let value = get_length_prefixed(&mut buf, "FIELDS", "value")?;
+                    fields.push((field, value));
+                }
+                Ok(Response::Fields { fields })
+            }
+            RESP_VALUES => {
+                let values = get_values(&mut buf, "VALUES")?;
+                Ok(Response::Values { values })
+            }
+            RESP_THROTTLED => {
+                let msg = get_string(&mut buf, "THROTTLED", "message")?;
+                Ok(Response::Throttled { msg })
+            }
+            RESP_HELLO => {
+                let (version, features) = get_hello(&mut buf, "HELLO response")?;
+                Ok(Response::Hello { version, features })
+            }
+            RESP_PING => Ok(Response::Ping),
+            RESP_INVALIDATE => {
+                let cache_name = get_string(&mut buf, "INVALIDATE", "cache_name")?;
+                let keys = get_values(&mut buf, "INVALIDATE")?;
+                Ok(Response::Invalidate { cache_name, keys })
+            }
+            RESP_SCORED => {
+                let members = get_scored(&mut buf, "SCORED")?;
+                Ok(Response::Scored { members })
+            }
+            RESP_SAMPLES => {
+                if buf.remaining() < 4 {
+                    return Err("Invalid SAMPLES: missing sample count".to_string());
+                }
+                let count = buf.get_u32() as usize;
+                let mut samples = Vec::with_capacity(count.min(1024));
+                for _ in 0..count {
+                    if buf.remaining() < 8 {
+                        return Err("Invalid SAMPLES: missing timestamp".to_string());
+                    }
+                    let timestamp = buf.get_u64();
+                    let value = get_length_prefixed(&mut buf, "SAMPLES", "value")?;
+                    samples.push((timestamp, value));
+                }
+                Ok(Response::Samples { samples })
             }
             _ => Err(format!("Unknown response type: 0x{:02X}", resp_type)),
         }
     }
 }
 
+/// Read the version and feature flags of a HELLO
+fn get_hello(buf: &mut Bytes, frame: &str) -> Result<(u16, u32), String> {
+    if buf.remaining() < 6 {
+        return Err(format!("Invalid {}: missing version or features", frame));
+    }
+    Ok((buf.get_u16(), buf.get_u32()))
+}
+
+/// Write a u32 length-prefixed byte string
+fn put_length_prefixed(buf: &mut BytesMut, bytes: &[u8]) {
+    buf.put_u32(bytes.len() as u32);
+    buf.put_slice(bytes);
+}
+
+/// Read a u32 length-prefixed byte string (zero-copy)
+fn get_length_prefixed(buf: &mut Bytes, command: &str, name: &str) -> Result<Bytes, String> {
+    if buf.remaining() < 4 {
+        return Err(format!("Invalid {}: missing {} length", command, name));
+    }
+    let len = buf.get_u32() as usize;
+
+    if buf.remaining() < len {
+        return Err(format!(
+            "Invalid {}: expected {} bytes for {}, got {}",
+            command,
+            len,
+            name,
+            buf.remaining()
+        ));
+    }
+
+    Ok(buf.copy_to_bytes(len))
+}
+
+/// Read a u32 length-prefixed UTF-8 string
+fn get_string(buf: &mut Bytes, command: &str, name: &str) -> Result<String, String> {
+    let bytes = get_length_prefixed(buf, command, name)?;
+    String::from_utf8(bytes.to_vec()).map_err(|e| format!("Invalid {} UTF-8: {}", name, e))
+}
+
+/// Write a u32 count followed by each value length-prefixed
+fn put_values(buf: &mut BytesMut, values: &[Bytes]) {
+    buf.put_u32(values.len() as u32);
+    for value in values {
+        put_length_prefixed(buf, value);
+    }
+}
+
+/// Read a u32 count followed by that many length-prefixed byte strings
+fn get_values(buf: &mut Bytes, command: &str) -> Result<Vec<Bytes>, String> {
+    if buf.remaining() < 4 {
+        return Err(format!("Invalid {}: missing value count", command));
+    }
+    let count = buf.get_u32() as usize;
+    let mut values = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        values.push(get_length_prefixed(buf, command, "value")?);
+    }
+    Ok(values)
+}
+
+/// Write a u32 count followed by that many length-prefixed members, each with its score
+fn put_scored(buf: &mut BytesMut, members: &[(Bytes, f64)]) {
+    buf.put_u32(members.len() as u32);
+    for (member, score) in members {
+        put_length_prefixed(buf, member);
+        buf.put_f64(*score);
+    }
+}
+
+/// Read a u32 count followed by that many length-prefixed members, each with its score
+fn get_scored(buf: &mut Bytes, command: &str) -> Result<Vec<(Bytes, f64)>, String> {
+    if buf.remaining() < 4 {
+        return Err(format!("Invalid {}: missing member count", command));
+    }
+    let count = buf.get_u32() as usize;
+    let mut members = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        let member = get_length_prefixed(buf, command, "member")?;
+        if buf.remaining() < 8 {
+            return Err(format!("Invalid {}: missing score", command));
+        }
+        members.push((member, buf.get_f64()));
+    }
+    Ok(members)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -338,4 +1426,479 @@ mod tests {
             _ => panic!("Expected Value"),
         }
     }
+
+    #[test]
+    fn test_getdel_getex_encode_decode() {
+        let req = Request::GetDel {
+            cache_name: "sessions".to_string(),
+            key: Bytes::from("token"),
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::GetDel { cache_name, key } => {
+                assert_eq!(cache_name, "sessions");
+                assert_eq!(key, Bytes::from("token"));
+            }
+            _ => panic!("Expected GetDel"),
+        }
+
+        let req = Request::GetEx {
+            cache_name: "sessions".to_string(),
+            key: Bytes::from("token"),
+            ttl_ms: 60_000,
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::GetEx { cache_name, key, ttl_ms } => {
+                assert_eq!(cache_name, "sessions");
+                assert_eq!(key, Bytes::from("token"));
+                assert_eq!(ttl_ms, 60_000);
+            }
+            _ => panic!("Expected GetEx"),
+        }
+    }
+
+    #[test]
+    fn test_hset_encode_decode() {
+        let req = Request::HSet {
+            cache_name: "users".to_string(),
+            key: Bytes::from("user:1"),
+            field: "name".to_string(),
+            value: Bytes::from("alice"),
+        };
+        let decoded = Request::decode(req.encode()).unwrap();
+
+        match decoded {
+            Request::HSet { cache_name, key, field, value } => {
+                assert_eq!(cache_name, "users");
+                assert_eq!(key, Bytes::from("user:1"));
+                assert_eq!(field, "name");
+                assert_eq!(value, Bytes::from("alice"));
+            }
+            _ => panic!("Expected HSet"),
+        }
+    }
+
+    #[test]
+    fn test_response_fields_encode_decode() {
+        let resp = Response::Fields {
+            fields: vec![
+                ("name".to_string(), Bytes::from("alice")),
+                ("age".to_string(), Bytes::from("30")),
+            ],
+        };
+        let decoded = Response::decode(resp.encode()).unwrap();
+
+        match decoded {
+            Response::Fields { fields } => {
+                assert_eq!(fields.len(), 2);
+                assert_eq!(fields[0], ("name".to_string(), Bytes::from("alice")));
+                assert_eq!(fields[1], ("age".to_string(), Bytes::from("30")));
+            }
+            _ => panic!("Expected Fields"),
+        }
+    }
+
+    #[test]
+    fn test_list_commands_encode_decode() {
+        let req = Request::RPush {
+            cache_name: "jobs".to_string(),
+            key: Bytes::from("queue"),
+            values: vec![Bytes::from("a"), Bytes::from("b")],
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::RPush { cache_name, key, values } => {
+                assert_eq!(cache_name, "jobs");
+                assert_eq!(key, Bytes::from("queue"));
+                assert_eq!(values, vec![Bytes::from("a"), Bytes::from("b")]);
+            }
+            _ => panic!("Expected RPush"),
+        }
+
+        let req = Request::BLPop {
+            cache_name: "jobs".to_string(),
+            key: Bytes::from("queue"),
+            timeout_ms: 1500,
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::BLPop { timeout_ms, .. } => assert_eq!(timeout_ms, 1500),
+            _ => panic!("Expected BLPop"),
+        }
+
+        let req = Request::LRange {
+            cache_name: "jobs".to_string(),
+            key: Bytes::from("queue"),
+            start: 0,
+            stop: -1,
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::LRange { start, stop, .. } => assert_eq!((start, stop), (0, -1)),
+            _ => panic!("Expected LRange"),
+        }
+    }
+
+    #[test]
+    fn test_response_values_encode_decode() {
+        let resp = Response::Values {
+            values: vec![Bytes::from("a"), Bytes::new()],
+        };
+        match Response::decode(resp.encode()).unwrap() {
+            Response::Values { values } => {
+                assert_eq!(values, vec![Bytes::from("a"), Bytes::new()]);
+            }
+            _ => panic!("Expected Values"),
+        }
+    }
+
+    #[test]
+    fn test_hello_encode_decode() {
+        let req = Request::Hello { version: 3, features: FEATURE_BULK_LOAD | FEATURE_COMPRESSION };
+        match Request::decode(req.encode()).unwrap() {
+            Request::Hello { version, features } => {
+                assert_eq!(version, 3);
+                assert_eq!(features, FEATURE_BULK_LOAD | FEATURE_COMPRESSION);
+            }
+            _ => panic!("Expected Hello"),
+        }
+
+        let resp = Response::Hello { version: 1, features: FEATURE_AUTH_REQUIRED };
+        assert!(matches!(
+            Response::decode(resp.encode()).unwrap(),
+            Response::Hello { version: 1, features: FEATURE_AUTH_REQUIRED }
+        ));
+    }
+
+    #[test]
+    fn test_negotiated_features() {
+        // Only features both sides support are used, and server requirements always apply
+        let negotiated = Negotiated::new(
+            FEATURE_BULK_LOAD | FEATURE_COMPRESSION,
+            PROTOCOL_VERSION + 1,
+            FEATURE_BULK_LOAD | FEATURE_AUTH_REQUIRED,
+        );
+        assert_eq!(negotiated.version, PROTOCOL_VERSION);
+        assert!(negotiated.supports(FEATURE_BULK_LOAD));
+        assert!(!negotiated.supports(FEATURE_COMPRESSION));
+        assert!(negotiated.supports(FEATURE_AUTH_REQUIRED));
+
+        assert!(!Negotiated::LEGACY.supports(FEATURE_BULK_LOAD));
+    }
+
+    #[test]
+    fn test_response_error_encode_decode() {
+        let resp = Response::Error {
+            code: ErrorCode::CacheNotFound,
+            msg: "Cache not found: orders".to_string(),
+        };
+        match Response::decode(resp.encode()).unwrap() {
+            Response::Error { code, msg } => {
+                assert_eq!(code, ErrorCode::CacheNotFound);
+                assert_eq!(msg, "Cache not found: orders");
+            }
+            _ => panic!("Expected Error"),
+        }
+    }
+
+    #[test]
+    fn test_legacy_sessions_get_errors_without_code() {
+        let resp = Response::Error {
+            code: ErrorCode::CacheNotFound,
+            msg: "Cache not found: orders".to_string(),
+        };
+
+        let legacy = resp.encode_for(Negotiated::LEGACY);
+        assert_eq!(&legacy[..], b"\x04\x00\x00\x00\x17Cache not found: orders");
+        match Response::decode(legacy).unwrap() {
+            Response::Error { code, msg } => {
+                assert_eq!(code, ErrorCode::Internal);
+                assert_eq!(msg, "Cache not found: orders");
+            }
+            _ => panic!("Expected Error"),
+        }
+
+        let agreed = Negotiated::new(0, PROTOCOL_VERSION, 0);
+        assert_eq!(resp.encode_for(agreed), resp.encode());
+        assert!(Response::decode(Bytes::from_static(b"\x04\x00\x00\x00\x09short")).is_err());
+    }
+
+    #[test]
+    fn test_response_throttled_encode_decode() {
+        let resp = Response::Throttled { msg: "slow down".to_string() };
+        match Response::decode(resp.encode()).unwrap() {
+            Response::Throttled { msg } => assert_eq!(msg, "slow down"),
+            _ => panic!("Expected Throttled"),
+        }
+    }
+
+    #[test]
+    fn test_server_ping_is_not_a_pong() {
+        assert!(matches!(Response::decode(Response::Ping.encode()).unwrap(), Response::Ping));
+        assert!(matches!(Response::decode(Response::Pong.encode()).unwrap(), Response::Pong));
+    }
+
+    #[test]
+    fn test_set_commands_encode_decode() {
+        let req = Request::SAdd {
+            cache_name: "tags".to_string(),
+            key: Bytes::from("post:1"),
+            members: vec![Bytes::from("rust"), Bytes::from("cache")],
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::SAdd { cache_name, key, members } => {
+                assert_eq!(cache_name, "tags");
+                assert_eq!(key, Bytes::from("post:1"));
+                assert_eq!(members, vec![Bytes::from("rust"), Bytes::from("cache")]);
+            }
+            _ => panic!("Expected SAdd"),
+        }
+
+        let req = Request::SInter {
+            cache_name: "tags".to_string(),
+            keys: vec![Bytes::from("post:1"), Bytes::from("post:2")],
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::SInter { cache_name, keys } => {
+                assert_eq!(cache_name, "tags");
+                assert_eq!(keys, vec![Bytes::from("post:1"), Bytes::from("post:2")]);
+            }
+            _ => panic!("Expected SInter"),
+        }
+    }
+
+    #[test]
+    fn test_lock_commands_encode_decode() {
+        let req = Request::Lock {
+            cache_name: "locks".to_string(),
+            key: Bytes::from("job:42"),
+            ttl_ms: 30_000,
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::Lock { cache_name, key, ttl_ms } => {
+                assert_eq!(cache_name, "locks");
+                assert_eq!(key, Bytes::from("job:42"));
+                assert_eq!(ttl_ms, 30_000);
+            }
+            _ => panic!("Expected Lock"),
+        }
+
+        let req = Request::Unlock {
+            cache_name: "locks".to_string(),
+            key: Bytes::from("job:42"),
+            token: 7,
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::Unlock { token, .. } => assert_eq!(token, 7),
+            _ => panic!("Expected Unlock"),
+        }
+    }
+
+    #[test]
+    fn test_probabilistic_commands_encode_decode() {
+        let req = Request::PfAdd {
+            cache_name: "stats".to_string(),
+            key: Bytes::from("visitors"),
+            items: vec![Bytes::from("alice"), Bytes::from("bob")],
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::PfAdd { cache_name, key, items } => {
+                assert_eq!(cache_name, "stats");
+                assert_eq!(key, Bytes::from("visitors"));
+                assert_eq!(items, vec![Bytes::from("alice"), Bytes::from("bob")]);
+            }
+            _ => panic!("Expected PfAdd"),
+        }
+
+        let req = Request::BfReserve {
+            cache_name: "stats".to_string(),
+            key: Bytes::from("seen"),
+            error_rate: 0.001,
+            capacity: 50_000,
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::BfReserve { error_rate, capacity, .. } => {
+                assert_eq!(error_rate, 0.001);
+                assert_eq!(capacity, 50_000);
+            }
+            _ => panic!("Expected BfReserve"),
+        }
+
+        let req = Request::BfExists {
+            cache_name: "stats".to_string(),
+            key: Bytes::from("seen"),
+            item: Bytes::from("alice"),
+        };
+        assert_eq!(req.read_keys(), vec![&Bytes::from("seen")]);
+        match Request::decode(req.encode()).unwrap() {
+            Request::BfExists { key, item, .. } => {
+                assert_eq!(key, Bytes::from("seen"));
+                assert_eq!(item, Bytes::from("alice"));
+            }
+            _ => panic!("Expected BfExists"),
+        }
+    }
+
+    #[test]
+    fn test_sorted_set_commands_encode_decode() {
+        let scored = vec![(Bytes::from("alice"), 1200.5), (Bytes::from("bob"), -3.0)];
+        let req = Request::ZAdd {
+            cache_name: "games".to_string(),
+            key: Bytes::from("leaderboard"),
+            members: scored.clone(),
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::ZAdd { cache_name, key, members } => {
+                assert_eq!(cache_name, "games");
+                assert_eq!(key, Bytes::from("leaderboard"));
+                assert_eq!(members, scored);
+            }
+            _ => panic!("Expected ZAdd"),
+        }
+
+        let req = Request::ZRange {
+            cache_name: "games".to_string(),
+            key: Bytes::from("leaderboard"),
+            start: 0,
+            stop: 9,
+            reverse: true,
+        };
+        match Request::decode(req.encode()).unwrap() {
+            Request::ZRange { start, stop, reverse, .. } => {
+                assert_eq!((start, stop), (0, 9));
+                assert!(reverse);
+            }
+            _ => panic!("Expected ZRange"),
+        }
+
+        let req = Request::ZRangeByScore {
+            cache_name: "events".to_string(),
+            key: Bytes::from("recent"),
+            min: 1_700_000_000.0,
+            max: f64::INFINITY,
+            limit: 100,
+        };
+        assert_eq!(req.read_keys(), vec![&Bytes::from("recent")]);
+        match Request::decode(req.encode()).unwrap() {
+            Request::ZRangeByScore { min, max, limit, .. } => {
+                assert_eq!(min, 1_700_000_000.0);
+                assert_eq!(max, f64::INFINITY);
+                assert_eq!(limit, 100);
+            }
+            _ => panic!("Expected ZRangeByScore"),
+        }
+
+        // A ZRANK without its reverse flag is rejected
+        let req = Request::ZRank {
+            cache_name: "games".to_string(),
+            key: Bytes::from("leaderboard"),
+            member: Bytes::from("alice"),
+            reverse: false,
+        };
+        let encoded = req.encode();
+        assert!(Request::decode(encoded.slice(..encoded.len() - 1)).is_err());
+    }
+
+    #[test]
+    fn test_scored_response_encode_decode() {
+        let scored = vec![(Bytes::from("alice"), 1200.5), (Bytes::from("bob"), 980.0)];
+        let resp = Response::Scored { members: scored.clone() };
+        match Response::decode(resp.encode()).unwrap() {
+            Response::Scored { members } => assert_eq!(members, scored),
+            _ => panic!("Expected Scored"),
+        }
+    }
+
+    #[test]
+    fn test_time_series_commands_encode_decode() {
+        let req = Request::Append {
+            cache_name: "metrics".to_string(),
+            key: Bytes::from("cpu"),
+            timestamp: 1_700_000_000_000,
+            value: Bytes::from("0.42"),
+        };
+        assert!(req.read_keys().is_empty());
+        match Request::decode(req.encode()).unwrap() {
+            Request::Append { cache_name, key, timestamp, value } => {
+                assert_eq!(cache_name, "metrics");
+                assert_eq!(key, Bytes::from("cpu"));
+                assert_eq!(timestamp, 1_700_000_000_000);
+                assert_eq!(value, Bytes::from("0.42"));
+            }
+            _ => panic!("Expected Append"),
+        }
+
+        let req = Request::Range {
+            cache_name: "metrics".to_string(),
+            key: Bytes::from("cpu"),
+            from: 1_700_000_000_000,
+            to: u64::MAX,
+            limit: 0,
+        };
+        assert_eq!(req.read_keys(), vec![&Bytes::from("cpu")]);
+        let encoded = req.encode();
+        match Request::decode(encoded.clone()).unwrap() {
+            Request::Range { from, to, limit, .. } => {
+                assert_eq!((from, to, limit), (1_700_000_000_000, u64::MAX, 0));
+            }
+            _ => panic!("Expected Range"),
+        }
+        assert!(Request::decode(encoded.slice(..encoded.len() - 4)).is_err());
+    }
+
+    #[test]
+    fn test_samples_response_encode_decode() {
+        let sampled = vec![(1_000, Bytes::from("0.42")), (2_000, Bytes::from("0.57"))];
+        let resp = Response::Samples { samples: sampled.clone() };
+        match Response::decode(resp.encode()).unwrap() {
+            Response::Samples { samples } => assert_eq!(samples, sampled),
+            _ => panic!("Expected Samples"),
+        }
+    }
+
+    #[test]
+    fn test_bulkload_encode_decode() {
+        let records = vec![
+            BulkRecord { key: Bytes::from("a"), value: Bytes::from("1"), ttl_ms: 0 },
+            BulkRecord { key: Bytes::from("b"), value: Bytes::from("2"), ttl_ms: 60_000 },
+        ];
+        let req = Request::BulkLoad { cache_name: "warm".to_string(), records: records.clone() };
+        match Request::decode(req.encode()).unwrap() {
+            Request::BulkLoad { cache_name, records: decoded } => {
+                assert_eq!(cache_name, "warm");
+                assert_eq!(decoded, records);
+            }
+            _ => panic!("Expected BulkLoad"),
+        }
+
+        // A record cut short is rejected rather than loaded without its TTL
+        let encoded = req.encode();
+        assert!(Request::decode(encoded.slice(..encoded.len() - 4)).is_err());
+    }
+
+    #[test]
+    fn test_tracking_encode_decode() {
+        match Request::decode(Request::Tracking { enabled: true }.encode()).unwrap() {
+            Request::Tracking { enabled } => assert!(enabled),
+            _ => panic!("Expected Tracking"),
+        }
+
+        let resp = Response::Invalidate {
+            cache_name: "users".to_string(),
+            keys: vec![Bytes::from("alice"), Bytes::from("bob")],
+        };
+        match Response::decode(resp.encode()).unwrap() {
+            Response::Invalidate { cache_name, keys } => {
+                assert_eq!(cache_name, "users");
+                assert_eq!(keys, vec![Bytes::from("alice"), Bytes::from("bob")]);
+            }
+            _ => panic!("Expected Invalidate"),
+        }
+
+        // Reads are tracked, writes are not
+        let get = Request::Get { cache_name: "users".to_string(), key: Bytes::from("alice") };
+        assert_eq!(get.read_keys(), vec![&Bytes::from("alice")]);
+        let put = Request::Put {
+            cache_name: "users".to_string(),
+            key: Bytes::from("alice"),
+            value: Bytes::from("1"),
+        };
+        assert!(put.read_keys().is_empty());
+    }
 }
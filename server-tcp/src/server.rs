@@ -1,98 +1,878 @@
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
+use carbon::domain::response::PutResponse;
+use carbon::domain::{BulkEntry, ListEnd, ValueFormat};
+use carbon::encoding::transcode;
 use carbon::planes::data::{
+    BULK_LOAD_BATCH_SIZE,
     cache_operations::CacheOperationsService,
-    operation::CacheOperations,
+    connections::{Connection, ConnectionHandle, ConnectionRegistry},
+    key_tracking::{Invalidation, InvalidationReceiver, KeyTracking},
+    slow_log::with_caller,
+    operation::{
+        CacheOperations, HashOperations, ListOperations, LockOperations, ProbabilisticOperations,
+        SetOperations, SortedSetOperations, TimeSeriesOperations,
+    },
 };
 use futures::{SinkExt, StreamExt};
 use tokio::net::TcpStream;
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use crate::protocol::{Request, Response};
-use tracing::info;
+use std::time::{Duration, Instant};
+use crate::idle::{IdleAction, IdlePolicy};
+use crate::protocol::{
+    BulkRecord, FEATURE_BULK_LOAD, FEATURE_CHECKSUM, FEATURE_COMPRESSION, FEATURE_DEADLINE,
+    FEATURE_KEEPALIVE, FEATURE_TRACKING, Negotiated, Request, Response, checksum, compression,
+    deadline,
+};
+use shared::ErrorCode;
+use tracing::{Instrument, info, info_span};
+
+/// Largest request or response frame, excluding its 4-byte length prefix
+pub const MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
 
 pub async fn process_connection(
     socket: TcpStream,
-    cache_ops: Arc<CacheOperationsService<Vec<u8>, Bytes>>
+    cache_ops: Arc<CacheOperationsService<Vec<u8>, Bytes>>,
+    connections: Arc<ConnectionRegistry>
 ) -> Result<(), Box<dyn std::error::Error>> {
     socket.set_nodelay(true).ok();
 
+    // Slow operations are attributed to the connection's peer address
+    let caller = peer_caller(socket.peer_addr());
+    let admitted = admit(&connections, caller.clone(), socket.peer_addr().ok());
+
     // Build a length-delimited codec with a 4-byte big-endian length prefix.
     // This handles framing - splitting the TCP stream into discrete messages
     let codec = LengthDelimitedCodec::builder()
         .length_field_length(4)
-        .max_frame_length(8 * 1024 * 1024)
+        .max_frame_length(MAX_FRAME_LENGTH)
         .new_codec();
 
     // Wrap the socket with the codec - now we get BytesMut frames instead of raw bytes
     let mut framed = Framed::new(socket, codec);
 
+    let connection = match admitted {
+        Ok(connection) => connection,
+        Err(busy) => {
+            tracing::warn!("Turned away TCP connection from {}: {}", caller, busy);
+            framed
+                .send(busy_response(busy).encode_for(Negotiated::LEGACY))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // What was agreed in HELLO, if the client sent one
+    let mut session = Negotiated::LEGACY;
+
+    // Quiet connections are pinged if they agreed on keepalives, and closed after the idle timeout
+    let idle = IdlePolicy::new(connections.limits());
+    let mut silent = Duration::ZERO;
+
+    // Keys this connection reads once it turns tracking on, forgotten when it closes
+    let mut tracking = Tracking::new(cache_ops.key_tracking().cloned(), connection.id());
+
     // Process each frame (message) from the client
-    while let Some(frame_result) = framed.next().await {
+    loop {
+        let keepalive = session.supports(FEATURE_KEEPALIVE);
+        let waited = Instant::now();
+        let incoming = next_incoming(&mut framed, &mut tracking);
+        let incoming = match idle.next_wait(silent, keepalive) {
+            // Framed keeps what it has read so far when the wait ends
+            Some(wait) => match tokio::time::timeout(wait, incoming).await {
+                Ok(incoming) => incoming,
+                Err(_) => {
+                    silent += wait;
+                    match idle.on_silence(silent, keepalive) {
+                        IdleAction::Close => {
+                            info!("Closing TCP connection {} after {:?} idle", caller, silent);
+                            break;
+                        }
+                        IdleAction::Ping => {
+                            framed
+                                .send(encode_response(Response::Ping, session, &connection))
+                                .await?;
+                        }
+                        IdleAction::Wait => {}
+                    }
+                    continue;
+                }
+            },
+            None => incoming.await,
+        };
+        let next = match incoming {
+            Incoming::Frame(next) => next,
+            Incoming::Notice(notice) => {
+                // Notices are no sign of life from the client
+                silent += waited.elapsed();
+                let notice = encode_response(invalidate_response(notice), session, &connection);
+                framed.send(notice).await?;
+                continue;
+            }
+        };
+        let Some(frame_result) = next else {
+            break;
+        };
+        silent = Duration::ZERO;
+
         // LengthDelimitedCodec gives us BytesMut
         let frame = frame_result?;
 
-        // Convert BytesMut to Bytes and decode into our Request enum
-        let request = match Request::decode(frame.freeze()) {
-            Ok(req) => req,
-            Err(e) => {
-                tracing::error!("Failed to decode request: {}", e);
-                let error_resp = Response::Error { msg: e };
-                framed.send(error_resp.encode()).await?;
-                continue;
-            }
+        let response = serve_frame(
+            &cache_ops,
+            &caller,
+            &connection,
+            &mut session,
+            &mut tracking,
+            frame.freeze(),
+        )
+        .await;
+
+        // Send the encoded response back
+        framed.send(response).await?;
+    }
+
+    Ok(())
+}
+
+/// What the connection loop waits on: the client's next frame or a notice to push to it
+enum Incoming {
+    Frame(Option<std::io::Result<BytesMut>>),
+    Notice(Invalidation),
+}
+
+async fn next_incoming(
+    framed: &mut Framed<TcpStream, LengthDelimitedCodec>,
+    tracking: &mut Tracking,
+) -> Incoming {
+    tokio::select! {
+        next = framed.next() => Incoming::Frame(next),
+        notice = tracking.next_notice() => Incoming::Notice(notice),
+    }
+}
+
+/// INVALIDATE frame telling the client which of its copies are stale
+fn invalidate_response(notice: Invalidation) -> Response {
+    let (cache_name, keys) = match notice {
+        Invalidation::Keys { cache_name, keys } => {
+            (cache_name, keys.into_iter().map(Bytes::from).collect())
+        }
+        Invalidation::Cache { cache_name } => (cache_name, Vec::new()),
+        Invalidation::All => (String::new(), Vec::new()),
+    };
+    Response::Invalidate { cache_name, keys }
+}
+
+/// Optional features this server offers in HELLO
+pub const SERVER_FEATURES: u32 = FEATURE_BULK_LOAD
+    | FEATURE_COMPRESSION
+    | FEATURE_CHECKSUM
+    | FEATURE_DEADLINE
+    | FEATURE_KEEPALIVE
+    | FEATURE_TRACKING;
+
+/// Key tracking of one connection, turned off when the connection closes
+pub(crate) struct Tracking {
+    key_tracking: Option<Arc<KeyTracking>>,
+    client: u64,
+    receiver: Option<InvalidationReceiver>,
+}
+
+impl Tracking {
+    /// Tracking for connection `client`, neither offered nor turned on without a table
+    pub(crate) fn new(key_tracking: Option<Arc<KeyTracking>>, client: u64) -> Self {
+        Self {
+            key_tracking,
+            client,
+            receiver: None,
+        }
+    }
+
+    /// Features to offer in HELLO
+    fn server_features(&self) -> u32 {
+        match self.key_tracking {
+            Some(_) => SERVER_FEATURES,
+            None => SERVER_FEATURES & !FEATURE_TRACKING,
+        }
+    }
+
+    fn set(&mut self, enabled: bool) -> Result<(), String> {
+        if !enabled {
+            self.stop();
+            return Ok(());
+        }
+        let Some(ref key_tracking) = self.key_tracking else {
+            return Err("Key tracking is not available on this connection".to_string());
         };
+        self.receiver = Some(key_tracking.enable(self.client));
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if self.receiver.take().is_some()
+            && let Some(ref key_tracking) = self.key_tracking
+        {
+            key_tracking.disable(self.client);
+        }
+    }
+
+    /// Track a key the connection is about to read, if tracking is on
+    fn track(&self, cache_name: &str, key: &[u8]) {
+        if let (Some(key_tracking), Some(_)) = (&self.key_tracking, &self.receiver) {
+            key_tracking.track(self.client, cache_name, key);
+        }
+    }
+
+    /// The next notice to push, never ready while tracking is off
+    async fn next_notice(&mut self) -> Invalidation {
+        if let Some(receiver) = self.receiver.as_mut()
+            && let Some(notice) = receiver.recv().await
+        {
+            return notice;
+        }
+        std::future::pending().await
+    }
+}
+
+impl Drop for Tracking {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Register a connection, under the per-IP limits when its address is known
+pub(crate) fn admit(
+    connections: &Arc<ConnectionRegistry>,
+    caller: String,
+    peer: Option<SocketAddr>,
+) -> Result<ConnectionHandle, String> {
+    match peer {
+        Some(addr) => connections.admit(caller, addr.ip()),
+        None => Ok(connections.register(caller)),
+    }
+}
+
+/// Sent before closing a connection or in place of a response when a limit is reached
+pub(crate) fn busy_response(msg: String) -> Response {
+    Response::Error {
+        code: ErrorCode::Busy,
+        msg,
+    }
+}
+
+/// Name slow operations are attributed to
+pub(crate) fn peer_caller(peer: std::io::Result<SocketAddr>) -> String {
+    match peer {
+        Ok(addr) => format!("tcp:{}", addr),
+        Err(_) => "tcp:unknown".to_string(),
+    }
+}
 
-        info!("Received request: {:?}", request);
+/// Answer one request frame with an encoded response frame, shared by the Tokio and io_uring
+/// connection loops
+/// Once agreed, messages are compressed before being checksummed, so corruption is caught before
+/// anything is decompressed; a HELLO response is sent under the agreement that was in place
+/// before it
+/// A request whose deadline passes is dropped where it stands, so work nobody waits for stops at
+/// its next await, and answered with TIMEOUT
+pub(crate) async fn serve_frame(
+    cache_ops: &CacheOperationsService<Vec<u8>, Bytes>,
+    caller: &str,
+    connection: &Connection,
+    session: &mut Negotiated,
+    tracking: &mut Tracking,
+    frame: Bytes,
+) -> Bytes {
+    let agreed = *session;
+    let checksummed = agreed.supports(FEATURE_CHECKSUM);
+    let compressed = agreed.supports(FEATURE_COMPRESSION);
+    let wire_len = frame.len();
 
-        // Process the request and generate a response
-        let response = match request {
-            Request::Ping => Response::Pong,
+    let frame = if checksummed {
+        checksum::open(frame).map_err(|e| (ErrorCode::ChecksumMismatch, e))
+    } else {
+        Ok(frame)
+    };
+    let frame = frame.and_then(|frame| {
+        if compressed {
+            let was_compressed = compression::is_compressed(&frame);
+            compression::decompress(frame, MAX_FRAME_LENGTH)
+                .map(|message| (message, was_compressed))
+                .map_err(|e| (ErrorCode::BadRequest, e))
+        } else {
+            Ok((frame, false))
+        }
+    });
 
-            Request::Put { cache_name, key, value } => {
-                match cache_ops.put(&cache_name, key.to_vec(), value).await {
-                    Ok(_) => Response::Ok,
-                    Err(shared::Error::CacheNotFound(name)) => {
-                        Response::Error { msg: format!("Cache not found: {}", name) }
+    let response = match frame {
+        Ok((message, was_compressed)) => {
+            connection.record_received(wire_len, message.len(), was_compressed);
+            let Some(_request) = connection.begin_request() else {
+                return encode_response(
+                    busy_response("Too many requests in flight from this address".to_string()),
+                    agreed,
+                    connection,
+                );
+            };
+            if agreed.supports(FEATURE_DEADLINE) {
+                match deadline::split(message) {
+                    Ok((Some(timeout), message)) => {
+                        let responding = respond(cache_ops, caller, session, tracking, message);
+                        tokio::time::timeout(timeout, responding)
+                            .await
+                            .unwrap_or_else(|_| Response::Error {
+                                code: ErrorCode::Timeout,
+                                msg: format!("Deadline of {} ms exceeded", timeout.as_millis()),
+                            })
                     }
-                    Err(e) => {
-                        Response::Error { msg: format!("Put failed: {}", e) }
+                    Ok((None, message)) => {
+                        respond(cache_ops, caller, session, tracking, message).await
                     }
+                    Err(e) => Response::Error {
+                        code: ErrorCode::BadRequest,
+                        msg: e,
+                    },
                 }
+            } else {
+                respond(cache_ops, caller, session, tracking, message).await
             }
+        }
+        Err((code, e)) => {
+            tracing::warn!("Rejected corrupted frame from {}: {}", caller, e);
+            connection.record_received(wire_len, 0, false);
+            Response::Error { code, msg: e }
+        }
+    };
+    if *session != agreed {
+        connection.set_protocol(
+            session.version,
+            session.supports(FEATURE_COMPRESSION),
+            session.supports(FEATURE_CHECKSUM),
+        );
+    }
 
-            Request::Get { cache_name, key } => {
-                match cache_ops.get(&cache_name, &key.to_vec()).await {
-                    Ok(get_resp) if get_resp.found => {
-                        Response::Value { value: get_resp.message }
-                    }
-                    Ok(_) => {
-                        Response::NotFound
-                    }
-                    Err(shared::Error::CacheNotFound(name)) => {
-                        Response::Error { msg: format!("Cache not found: {}", name) }
-                    }
-                    Err(e) => {
-                        Response::Error { msg: format!("Get failed: {}", e) }
-                    }
+    encode_response(response, agreed, connection)
+}
+
+/// Encode a response frame under the agreement `agreed`
+fn encode_response(response: Response, agreed: Negotiated, connection: &Connection) -> Bytes {
+    let checksummed = agreed.supports(FEATURE_CHECKSUM);
+    let compressed = agreed.supports(FEATURE_COMPRESSION);
+    let response = response.encode_for(agreed);
+    let raw_len = response.len();
+    let response = if compressed {
+        compression::compress(&response)
+    } else {
+        response
+    };
+    let was_compressed = compressed && compression::is_compressed(&response);
+    let response = if checksummed {
+        checksum::seal(&response)
+    } else {
+        response
+    };
+    connection.record_sent(response.len(), raw_len, was_compressed);
+    response
+}
+
+/// Decode one request frame and execute it
+async fn respond(
+    cache_ops: &CacheOperationsService<Vec<u8>, Bytes>,
+    caller: &str,
+    session: &mut Negotiated,
+    tracking: &mut Tracking,
+    frame: Bytes,
+) -> Response {
+    // Decode into our Request enum
+    let request = match Request::decode(frame) {
+        Ok(req) => req,
+        Err(e) => {
+            tracing::error!("Failed to decode request: {}", e);
+            return Response::Error {
+                code: ErrorCode::BadRequest,
+                msg: e,
+            };
+        }
+    };
+
+    info!("Received request: {:?}", request);
+
+    // Process the request and generate a response
+    let span = info_span!(
+        "tcp.request",
+        command = request.command(),
+        cache = request.cache_name().unwrap_or_default(),
+    );
+    let handling = handle_request(cache_ops, session, tracking, request);
+    with_caller(caller.to_string(), handling)
+        .instrument(span)
+        .await
+}
+
+/// Execute a decoded request against the caches
+async fn handle_request(
+    cache_ops: &CacheOperationsService<Vec<u8>, Bytes>,
+    session: &mut Negotiated,
+    tracking: &mut Tracking,
+    request: Request,
+) -> Response {
+    // Keys are tracked before they are read, so a write racing the read still invalidates them
+    if let Some(cache_name) = request.cache_name() {
+        for key in request.read_keys() {
+            tracking.track(cache_name, key);
+        }
+    }
+
+    match request {
+        Request::Ping => Response::Pong,
+
+        // HELLO is optional, clients that skip it are served as version 0
+        Request::Hello { version, features } => {
+            let server_features = tracking.server_features();
+            *session = Negotiated::new(features, version, server_features);
+            // Notices are only pushed to clients that agreed to receive them
+            if !session.supports(FEATURE_TRACKING) {
+                tracking.stop();
+            }
+            Response::Hello {
+                version: session.version,
+                features: server_features,
+            }
+        }
+
+        Request::Tracking { enabled } => {
+            if !session.supports(FEATURE_TRACKING) {
+                return Response::Error {
+                    code: ErrorCode::NotEnabled,
+                    msg: "TRACKING needs the tracking feature agreed in HELLO".to_string(),
+                };
+            }
+            match tracking.set(enabled) {
+                Ok(()) => Response::Ok,
+                Err(msg) => Response::Error { code: ErrorCode::NotEnabled, msg },
+            }
+        }
+
+        Request::Put { cache_name, key, value } => {
+            // Caches with a value format take MessagePack from TCP clients
+            let value = match cache_ops
+                .to_canonical(&cache_name, value, ValueFormat::MessagePack)
+                .await
+            {
+                Ok(value) => value,
+                Err(e) => return error_response("Put", e),
+            };
+            match cache_ops.put(&cache_name, key.to_vec(), value).await {
+                Ok(put_resp) if !put_resp.admitted => not_admitted(put_resp),
+                Ok(_) => Response::Ok,
+                Err(e) => error_response("Put", e),
+            }
+        }
+
+        Request::Get { cache_name, key } => {
+            match cache_ops.get(&cache_name, &key.to_vec()).await {
+                Ok(get_resp) if get_resp.found => {
+                    value_response(cache_ops, &cache_name, get_resp.message, "Get").await
+                }
+                Ok(_) => {
+                    Response::NotFound
                 }
+                Err(e) => error_response("Get", e),
             }
+        }
 
-            Request::Delete { cache_name, key } => {
-                match cache_ops.delete(&cache_name, &key.to_vec()).await {
-                    Ok(_) => Response::Ok,
-                    Err(shared::Error::CacheNotFound(name)) => {
-                        Response::Error { msg: format!("Cache not found: {}", name) }
-                    }
-                    Err(e) => {
-                        Response::Error { msg: format!("Delete failed: {}", e) }
-                    }
+        Request::Delete { cache_name, key } => {
+            match cache_ops.delete(&cache_name, &key.to_vec()).await {
+                Ok(_) => Response::Ok,
+                Err(e) => error_response("Delete", e),
+            }
+        }
+
+        Request::GetDel { cache_name, key } => {
+            match cache_ops.getdel(&cache_name, &key.to_vec()).await {
+                Ok(get_resp) => {
+                    value_response(cache_ops, &cache_name, get_resp.message, "GETDEL").await
                 }
+                Err(shared::Error::NotFound) => Response::NotFound,
+                Err(e) => error_response("GETDEL", e),
             }
+        }
+
+        Request::GetEx { cache_name, key, ttl_ms } => {
+            // A zero TTL removes the entry's expiry
+            let ttl = (ttl_ms > 0).then(|| Duration::from_millis(ttl_ms));
+            match cache_ops.getex(&cache_name, &key.to_vec(), ttl).await {
+                Ok(get_resp) => {
+                    value_response(cache_ops, &cache_name, get_resp.message, "GETEX").await
+                }
+                Err(shared::Error::NotFound) => Response::NotFound,
+                Err(e) => error_response("GETEX", e),
+            }
+        }
+
+        Request::HSet { cache_name, key, field, value } => {
+            match cache_ops.hset(&cache_name, key.to_vec(), field, value).await {
+                Ok(put_resp) if !put_resp.admitted => not_admitted(put_resp),
+                Ok(put_resp) => Response::Integer { value: put_resp.created as i64 },
+                Err(e) => error_response("HSET", e),
+            }
+        }
+
+        Request::HGet { cache_name, key, field } => {
+            match cache_ops.hget(&cache_name, &key.to_vec(), &field).await {
+                Ok(get_resp) => Response::Value { value: get_resp.message },
+                Err(shared::Error::NotFound) => Response::NotFound,
+                Err(e) => error_response("HGET", e),
+            }
+        }
+
+        Request::HDel { cache_name, key, field } => {
+            match cache_ops.hdel(&cache_name, &key.to_vec(), &field).await {
+                Ok(del_resp) => Response::Integer { value: del_resp.deleted as i64 },
+                Err(e) => error_response("HDEL", e),
+            }
+        }
+
+        Request::HGetAll { cache_name, key } => {
+            match cache_ops.hgetall(&cache_name, &key.to_vec()).await {
+                Ok(get_resp) => Response::Fields {
+                    fields: get_resp.message.into_iter().collect(),
+                },
+                Err(shared::Error::NotFound) => Response::Fields { fields: Vec::new() },
+                Err(e) => error_response("HGETALL", e),
+            }
+        }
+
+        Request::LPush { cache_name, key, values } => {
+            list_push(cache_ops, &cache_name, key, ListEnd::Left, values).await
+        }
+
+        Request::RPush { cache_name, key, values } => {
+            list_push(cache_ops, &cache_name, key, ListEnd::Right, values).await
+        }
+
+        Request::LPop { cache_name, key } => {
+            list_pop(cache_ops, &cache_name, key, ListEnd::Left, None).await
+        }
+
+        Request::RPop { cache_name, key } => {
+            list_pop(cache_ops, &cache_name, key, ListEnd::Right, None).await
+        }
+
+        // Blocking pops hold this connection until a value arrives or the timeout expires
+        Request::BLPop { cache_name, key, timeout_ms } => {
+            let timeout = Duration::from_millis(timeout_ms);
+            list_pop(cache_ops, &cache_name, key, ListEnd::Left, Some(timeout)).await
+        }
+
+        Request::BRPop { cache_name, key, timeout_ms } => {
+            let timeout = Duration::from_millis(timeout_ms);
+            list_pop(cache_ops, &cache_name, key, ListEnd::Right, Some(timeout)).await
+        }
+
+        Request::LRange { cache_name, key, start, stop } => {
+            match cache_ops.list_range(&cache_name, &key.to_vec(), start, stop).await {
+                Ok(get_resp) => Response::Values { values: get_resp.message },
+                Err(e) => error_response("LRANGE", e),
+            }
+        }
+
+        Request::LLen { cache_name, key } => {
+            match cache_ops.list_len(&cache_name, &key.to_vec()).await {
+                Ok(length) => Response::Integer { value: length as i64 },
+                Err(e) => error_response("LLEN", e),
+            }
+        }
+
+        Request::SAdd { cache_name, key, members } => {
+            match cache_ops.sadd(&cache_name, key.to_vec(), members).await {
+                Ok(added) => Response::Integer { value: added as i64 },
+                Err(e) => error_response("SADD", e),
+            }
+        }
+
+        Request::SRem { cache_name, key, members } => {
+            match cache_ops.srem(&cache_name, &key.to_vec(), members).await {
+                Ok(removed) => Response::Integer { value: removed as i64 },
+                Err(e) => error_response("SREM", e),
+            }
+        }
+
+        Request::SIsMember { cache_name, key, member } => {
+            match cache_ops.sismember(&cache_name, &key.to_vec(), &member).await {
+                Ok(is_member) => Response::Integer { value: is_member as i64 },
+                Err(e) => error_response("SISMEMBER", e),
+            }
+        }
+
+        Request::SMembers { cache_name, key } => {
+            match cache_ops.smembers(&cache_name, &key.to_vec()).await {
+                Ok(get_resp) => Response::Values { values: get_resp.message },
+                Err(e) => error_response("SMEMBERS", e),
+            }
+        }
+
+        Request::SCard { cache_name, key } => {
+            match cache_ops.scard(&cache_name, &key.to_vec()).await {
+                Ok(count) => Response::Integer { value: count as i64 },
+                Err(e) => error_response("SCARD", e),
+            }
+        }
+
+        Request::SInter { cache_name, keys } => {
+            let keys: Vec<Vec<u8>> = keys.iter().map(|key| key.to_vec()).collect();
+            match cache_ops.sinter(&cache_name, &keys).await {
+                Ok(get_resp) => Response::Values { values: get_resp.message },
+                Err(e) => error_response("SINTER", e),
+            }
+        }
+
+        Request::SUnion { cache_name, keys } => {
+            let keys: Vec<Vec<u8>> = keys.iter().map(|key| key.to_vec()).collect();
+            match cache_ops.sunion(&cache_name, &keys).await {
+                Ok(get_resp) => Response::Values { values: get_resp.message },
+                Err(e) => error_response("SUNION", e),
+            }
+        }
+
+        Request::Lock { cache_name, key, ttl_ms } => {
+            let ttl = Duration::from_millis(ttl_ms);
+            match cache_ops.lock(&cache_name, key.to_vec(), ttl).await {
+                // Tokens start at 1, so 0 tells the client the lock is held elsewhere
+                Ok(lease) => Response::Integer {
+                    value: lease.map_or(0, |lease| lease.token as i64),
+                },
+                Err(e) => error_response("LOCK", e),
+            }
+        }
+
+        Request::Unlock { cache_name, key, token } => {
+            match cache_ops.unlock(&cache_name, &key.to_vec(), token).await {
+                Ok(released) => Response::Integer { value: released as i64 },
+                Err(e) => error_response("UNLOCK", e),
+            }
+        }
+
+        Request::PfAdd { cache_name, key, items } => {
+            match cache_ops.pfadd(&cache_name, key.to_vec(), items).await {
+                Ok(changed) => Response::Integer { value: changed as i64 },
+                Err(e) => error_response("PFADD", e),
+            }
+        }
+
+        Request::PfCount { cache_name, keys } => {
+            let keys: Vec<Vec<u8>> = keys.iter().map(|key| key.to_vec()).collect();
+            match cache_ops.pfcount(&cache_name, &keys).await {
+                Ok(count) => Response::Integer { value: count as i64 },
+                Err(e) => error_response("PFCOUNT", e),
+            }
+        }
+
+        Request::BfReserve { cache_name, key, error_rate, capacity } => {
+            match cache_ops.bf_reserve(&cache_name, key.to_vec(), error_rate, capacity).await {
+                Ok(()) => Response::Ok,
+                Err(e) => error_response("BF.RESERVE", e),
+            }
+        }
+
+        Request::BfAdd { cache_name, key, item } => {
+            match cache_ops.bf_add(&cache_name, key.to_vec(), item).await {
+                Ok(added) => Response::Integer { value: added as i64 },
+                Err(e) => error_response("BF.ADD", e),
+            }
+        }
+
+        Request::BfExists { cache_name, key, item } => {
+            match cache_ops.bf_exists(&cache_name, &key.to_vec(), &item).await {
+                Ok(exists) => Response::Integer { value: exists as i64 },
+                Err(e) => error_response("BF.EXISTS", e),
+            }
+        }
+
+        Request::ZAdd { cache_name, key, members } => {
+            match cache_ops.zadd(&cache_name, key.to_vec(), members).await {
+                Ok(added) => Response::Integer { value: added as i64 },
+                Err(e) => error_response("ZADD", e),
+            }
+        }
+
+        Request::ZRem { cache_name, key, members } => {
+            match cache_ops.zrem(&cache_name, &key.to_vec(), members).await {
+                Ok(removed) => Response::Integer { value: removed as i64 },
+                Err(e) => error_response("ZREM", e),
+            }
+        }
+
+        Request::ZScore { cache_name, key, member } => {
+            match cache_ops.zscore(&cache_name, &key.to_vec(), &member).await {
+                // Sent as text, like Redis, so clients need not agree on a float encoding
+                Ok(Some(score)) => Response::Value { value: Bytes::from(score.to_string()) },
+                Ok(None) => Response::NotFound,
+                Err(e) => error_response("ZSCORE", e),
+            }
+        }
+
+        Request::ZRank { cache_name, key, member, reverse } => {
+            match cache_ops.zrank(&cache_name, &key.to_vec(), &member, reverse).await {
+                Ok(Some(rank)) => Response::Integer { value: rank as i64 },
+                Ok(None) => Response::NotFound,
+                Err(e) => error_response("ZRANK", e),
+            }
+        }
+
+        Request::ZRange { cache_name, key, start, stop, reverse } => {
+            match cache_ops.zrange(&cache_name, &key.to_vec(), start, stop, reverse).await {
+                Ok(get_resp) => Response::Scored { members: get_resp.message },
+                Err(e) => error_response("ZRANGE", e),
+            }
+        }
+
+        Request::ZRangeByScore { cache_name, key, min, max, limit } => {
+            let limit = (limit > 0).then_some(limit as usize);
+            match cache_ops.zrangebyscore(&cache_name, &key.to_vec(), min, max, limit).await {
+                Ok(get_resp) => Response::Scored { members: get_resp.message },
+                Err(e) => error_response("ZRANGEBYSCORE", e),
+            }
+        }
+
+        Request::ZCard { cache_name, key } => {
+            match cache_ops.zcard(&cache_name, &key.to_vec()).await {
+                Ok(count) => Response::Integer { value: count as i64 },
+                Err(e) => error_response("ZCARD", e),
+            }
+        }
+
+        Request::Append { cache_name, key, timestamp, value } => {
+            match cache_ops.ts_append(&cache_name, key.to_vec(), timestamp, value).await {
+                Ok(len) => Response::Integer { value: len as i64 },
+                Err(e) => error_response("APPEND", e),
+            }
+        }
+
+        Request::Range { cache_name, key, from, to, limit } => {
+            let limit = (limit > 0).then_some(limit as usize);
+            match cache_ops.ts_range(&cache_name, &key.to_vec(), from, to, limit).await {
+                Ok(get_resp) => Response::Samples { samples: get_resp.message },
+                Err(e) => error_response("RANGE", e),
+            }
+        }
+
+        Request::BulkLoad { cache_name, records } => {
+            bulk_load(cache_ops, &cache_name, records).await
+        }
+    }
+}
+
+/// Answer a read with the value, as MessagePack when the cache has a value format
+async fn value_response(
+    cache_ops: &CacheOperationsService<Vec<u8>, Bytes>,
+    cache_name: &str,
+    value: Bytes,
+    operation: &str,
+) -> Response {
+    match cache_ops.from_canonical(cache_name, value, ValueFormat::MessagePack).await {
+        Ok(value) => Response::Value { value },
+        Err(e) => error_response(operation, e),
+    }
+}
+
+/// Load one BULKLOAD frame, answering with the number of entries written
+/// Each frame is a load of its own with one BulkLoaded event, larger loads span several frames
+async fn bulk_load(
+    cache_ops: &CacheOperationsService<Vec<u8>, Bytes>,
+    cache_name: &str,
+    records: Vec<BulkRecord>,
+) -> Response {
+    let mut loader = match cache_ops.bulk_loader(cache_name).await {
+        Ok(loader) => loader,
+        Err(e) => return error_response("BULKLOAD", e),
+    };
+    let format = match cache_ops.value_format(cache_name).await {
+        Ok(format) => format,
+        Err(e) => return error_response("BULKLOAD", e),
+    };
+
+    let mut records = records.into_iter().peekable();
+    let mut result = Ok(());
+    while result.is_ok() && records.peek().is_some() {
+        // Values are MessagePack, as for PUT
+        let batch = records
+            .by_ref()
+            .take(BULK_LOAD_BATCH_SIZE)
+            .map(|record| {
+                Ok(BulkEntry {
+                    key: record.key.to_vec(),
+                    value: transcode(record.value, ValueFormat::MessagePack, format)?,
+                    ttl: (record.ttl_ms > 0).then(|| Duration::from_millis(record.ttl_ms)),
+                })
+            })
+            .collect::<shared::Result<Vec<_>>>();
+        result = match batch {
+            Ok(batch) => loader.load(batch).await,
+            Err(e) => Err(e),
         };
+    }
 
-        // Encode the response and send it back
-        framed.send(response.encode()).await?;
+    let summary = loader.finish();
+    match result {
+        Ok(()) => Response::Integer { value: summary.loaded as i64 },
+        Err(e) => error_response("BULKLOAD", e),
     }
+}
 
-    Ok(())
+/// Map a failed operation to an ERROR response
+/// Answer to a write the cache's admission policy turned away
+fn not_admitted(put_resp: PutResponse) -> Response {
+    Response::Error {
+        code: ErrorCode::NotAdmitted,
+        msg: put_resp.message,
+    }
+}
+
+fn error_response(operation: &str, error: shared::Error) -> Response {
+    match error {
+        shared::Error::CacheNotFound(name) => Response::Error {
+            code: ErrorCode::CacheNotFound,
+            msg: format!("Cache not found: {}", name),
+        },
+        shared::Error::Throttled(msg) => Response::Throttled { msg },
+        e => Response::Error {
+            code: e.code(),
+            msg: format!("{} failed: {}", operation, e),
+        },
+    }
+}
+
+async fn list_push(
+    cache_ops: &CacheOperationsService<Vec<u8>, Bytes>,
+    cache_name: &str,
+    key: Bytes,
+    end: ListEnd,
+    values: Vec<Bytes>,
+) -> Response {
+    match cache_ops.list_push(cache_name, key.to_vec(), end, values).await {
+        Ok(length) => Response::Integer { value: length as i64 },
+        Err(e) => error_response("PUSH", e),
+    }
+}
+
+async fn list_pop(
+    cache_ops: &CacheOperationsService<Vec<u8>, Bytes>,
+    cache_name: &str,
+    key: Bytes,
+    end: ListEnd,
+    timeout: Option<Duration>,
+) -> Response {
+    let key = key.to_vec();
+    let result = match timeout {
+        Some(timeout) => cache_ops.list_blocking_pop(cache_name, &key, end, timeout).await,
+        None => cache_ops.list_pop(cache_name, &key, end).await,
+    };
+
+    match result {
+        Ok(get_resp) => Response::Value { value: get_resp.message },
+        Err(shared::Error::NotFound) => Response::NotFound,
+        Err(e) => error_response("POP", e),
+    }
 }
@@ -0,0 +1,183 @@
+//! io_uring connection handling, compiled in with the `io-uring` feature on Linux
+//! tokio-uring drives a current-thread runtime per OS thread, so each acceptor owns a thread,
+//! a runtime and a SO_REUSEPORT listener, and serves the connections it accepts itself
+
+use crate::listener::{ACCEPT_ERROR_BACKOFF, bind_std};
+use crate::protocol::Negotiated;
+use crate::server::{MAX_FRAME_LENGTH, Tracking, admit, busy_response, peer_caller, serve_frame};
+use bytes::Bytes;
+use carbon::planes::data::cache_operations::CacheOperationsService;
+use carbon::planes::data::connections::ConnectionRegistry;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio_uring::buf::BoundedBuf;
+use tokio_uring::net::{TcpListener, TcpStream};
+
+/// Start `acceptors` threads accepting and serving connections on `addr`
+/// Returns once every listener is bound, the threads run until the process exits
+pub fn serve(
+    addr: SocketAddr,
+    acceptors: usize,
+    cache_ops: Arc<CacheOperationsService<Vec<u8>, Bytes>>,
+    connections: Arc<ConnectionRegistry>,
+) -> io::Result<Vec<JoinHandle<()>>> {
+    // With port 0 the first listener picks the port and the others join it
+    let reuse_port = acceptors > 1;
+    let first = bind_std(addr, reuse_port)?;
+    let addr = first.local_addr()?;
+    let mut listeners = vec![first];
+    for _ in 1..acceptors.max(1) {
+        listeners.push(bind_std(addr, reuse_port)?);
+    }
+
+    listeners
+        .into_iter()
+        .enumerate()
+        .map(|(acceptor, listener)| {
+            let cache_ops = cache_ops.clone();
+            let connections = connections.clone();
+            std::thread::Builder::new()
+                .name(format!("tcp-uring-{acceptor}"))
+                .spawn(move || {
+                    tokio_uring::start(accept_loop(
+                        acceptor,
+                        TcpListener::from_std(listener),
+                        cache_ops,
+                        connections,
+                    ))
+                })
+        })
+        .collect()
+}
+
+async fn accept_loop(
+    acceptor: usize,
+    listener: TcpListener,
+    cache_ops: Arc<CacheOperationsService<Vec<u8>, Bytes>>,
+    connections: Arc<ConnectionRegistry>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, addr)) => {
+                tracing::info!("TCP connection from {addr} on acceptor {acceptor}");
+                let cache_ops = cache_ops.clone();
+                let connections = connections.clone();
+
+                tokio_uring::spawn(async move {
+                    if let Err(err) = process_connection(stream, addr, cache_ops, connections).await
+                    {
+                        tracing::warn!("TCP connection {addr} error: {err:?}");
+                    }
+                });
+            }
+            Err(e) => {
+                tracing::error!("TCP accept error on acceptor {acceptor}: {}", e);
+                tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+            }
+        }
+    }
+}
+
+/// Same framing as the Tokio loop: a 4-byte big-endian length, then the message
+async fn process_connection(
+    stream: TcpStream,
+    addr: SocketAddr,
+    cache_ops: Arc<CacheOperationsService<Vec<u8>, Bytes>>,
+    connections: Arc<ConnectionRegistry>,
+) -> io::Result<()> {
+    stream.set_nodelay(true).ok();
+    let caller = peer_caller(Ok(addr));
+    let connection = match admit(&connections, caller.clone(), Some(addr)) {
+        Ok(connection) => connection,
+        Err(busy) => {
+            tracing::warn!("Turned away TCP connection from {}: {}", caller, busy);
+            let (result, _) = stream
+                .write_all(length_prefixed(
+                    &busy_response(busy).encode_for(Negotiated::LEGACY),
+                ))
+                .await;
+            return result;
+        }
+    };
+
+    let mut session = Negotiated::LEGACY;
+    // Notices could only be pushed by cancelling reads the kernel owns, so key tracking is left
+    // to the Tokio loop
+    let mut tracking = Tracking::new(None, connection.id());
+
+    // Connections are closed after the idle timeout, keepalives are only sent by the Tokio loop
+    let idle_timeout = connections
+        .limits()
+        .idle_timeout_ms
+        .map(Duration::from_millis);
+
+    // The header buffer is handed to the kernel and back on every read
+    let mut header = vec![0u8; 4];
+    loop {
+        let read = read_full(&stream, header);
+        let read = match idle_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, read).await {
+                Ok(read) => read,
+                Err(_) => {
+                    tracing::info!("Closing TCP connection {} after {:?} idle", caller, timeout);
+                    return Ok(());
+                }
+            },
+            None => read.await,
+        };
+        header = match read? {
+            Some(header) => header,
+            None => return Ok(()),
+        };
+
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        if length > MAX_FRAME_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame of {length} bytes exceeds {MAX_FRAME_LENGTH}"),
+            ));
+        }
+
+        let frame = read_full(&stream, vec![0u8; length])
+            .await?
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+
+        let response = serve_frame(
+            &cache_ops,
+            &caller,
+            &connection,
+            &mut session,
+            &mut tracking,
+            Bytes::from(frame),
+        )
+        .await;
+
+        let (result, _) = stream.write_all(length_prefixed(&response)).await;
+        result?;
+    }
+}
+
+fn length_prefixed(response: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(4 + response.len());
+    out.extend_from_slice(&(response.len() as u32).to_be_bytes());
+    out.extend_from_slice(response);
+    out
+}
+
+/// Fill `buf` from the stream, None if the peer closed the connection before sending anything
+async fn read_full(stream: &TcpStream, mut buf: Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let (result, slice) = stream.read(buf.slice(filled..)).await;
+        buf = slice.into_inner();
+        match result? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            read => filled += read,
+        }
+    }
+    Ok(Some(buf))
+}
@@ -1,3 +1,8 @@
+mod source;
+
+pub use source::{CONFIG_FILE_VAR, ConfigSource};
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Protocol {
     Http(u16),                  // port
     Https(u16, String, String), // port, cert_path, key_path,
@@ -5,6 +10,7 @@ pub enum Protocol {
     Tcps(u16, String, String),  // port, cert_path, key_path,
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Config {
     pub host: String,
     pub http: Protocol,
@@ -12,37 +18,964 @@ pub struct Config {
     pub data_dir: String,
     pub admin_username: String,
     pub admin_password: String,
+    pub rate_limit: RateLimitConfig,
+    /// Proxies whose X-Forwarded-For and X-Real-IP headers are believed, as addresses or CIDR
+    /// ranges, every other client is known by its connection's address
+    pub trusted_proxies: Vec<String>,
+    pub http_limits: HttpLimits,
+    pub http_server: HttpServerConfig,
+    /// tracing filter directive, e.g. `info` or `carbon=debug,info`
+    pub log_level: String,
+    pub log_format: LogFormat,
+    /// OTLP collector spans are exported to (needs the `otel` feature)
+    pub otlp_endpoint: Option<String>,
+    pub slow_log: Option<SlowLogConfig>,
+    pub session_ttl_ms: u64,
+    /// How long after login activity can keep renewing a session
+    pub session_max_lifetime_ms: u64,
+    /// Lifetime of refresh tokens handed out by `/auth/login`
+    pub session_refresh_ttl_ms: u64,
+    /// Ceiling on cache operations per second across all caches and frontends
+    pub max_ops_per_sec: Option<u32>,
+    /// TCP accept loops sharing the port, None for one per core
+    pub tcp_acceptors: Option<usize>,
+    pub tcp_limits: TcpLimits,
+    pub runtime: RuntimeConfig,
+    /// How long ListCaches and DescribeCache responses are reused, 0 rebuilds them every call
+    pub admin_response_max_age_ms: u64,
+    pub auth_store: AuthStore,
+    /// Directory users are verified against before the local accounts (needs the `ldap` feature)
+    pub ldap: Option<LdapConfig>,
+    /// Single sign-on for the admin UI through an OpenID Connect provider
+    pub oidc: Option<OidcConfig>,
+    /// Whether local password hashes are still accepted next to the directory
+    pub auth_local_accounts: bool,
+    /// Key sealing secrets in the auth store, `.carbon/auth.key` under the data directory
+    /// when unset, nodes sharing a PostgreSQL store need the same file
+    pub auth_key_file: Option<String>,
+    /// Base64 key used instead of `auth_key_file`, e.g. injected by a secrets manager
+    pub auth_key: Option<String>,
+    /// Encrypt whole user and role records in the Sled auth store, existing plaintext
+    /// records are encrypted at startup
+    pub auth_encrypt_at_rest: bool,
+    /// Track what each user stores over HTTP and enforce the quotas of users and roles
+    pub quotas_enabled: bool,
+    /// How long dropped caches can be restored before they are purged, 0 purges at once
+    pub drop_retention_ms: u64,
+    /// Forward cache events to Kafka or NATS (needs the `kafka` or `nats` feature)
+    pub event_sink: Option<EventSinkConfig>,
+    /// Keep a replayable log of changes to each cache under the data directory
+    pub change_log: Option<ChangeLogConfig>,
+    /// Settings new caches get when their request leaves them out
+    pub cache_defaults: CacheDefaults,
+    /// Listeners replacing the HTTP and TCP ones of `host`, `http` and `tcp` when any is defined
+    pub listeners: Vec<ListenerConfig>,
+    /// Caches created at startup when they don't exist yet
+    pub caches: Vec<DeclaredCache>,
+    /// Where scheduled snapshots and warm-ups may write and fetch
+    pub schedules: ScheduleConfig,
+}
+
+/// Where users and roles are kept
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum AuthStore {
+    /// Sled databases under the data directory, private to this node
+    #[default]
+    Sled,
+    /// A PostgreSQL database several nodes can share (needs the `postgres` feature)
+    Postgres { url: String },
+}
+
+impl AuthStore {
+    /// `CARBON_AUTH_STORE=postgres` with the connection string in `CARBON_AUTH_DATABASE_URL`,
+    /// anything else keeps Sled
+    pub fn from_source(source: &ConfigSource) -> Self {
+        match source.var("CARBON_AUTH_STORE") {
+            Ok(store) if store.eq_ignore_ascii_case("postgres") => AuthStore::Postgres {
+                url: source.var("CARBON_AUTH_DATABASE_URL").unwrap_or_default(),
+            },
+            _ => AuthStore::Sled,
+        }
+    }
+}
+
+/// Caps on what one client IP may hold open on the TCP server and on how long connections
+/// stay quiet, unset for no cap
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TcpLimits {
+    pub max_connections_per_ip: Option<usize>,
+    /// Requests being served at once over all of the IP's connections, each connection serves
+    /// one request at a time
+    pub max_in_flight_per_ip: Option<usize>,
+    /// Close connections that send nothing for this long
+    pub idle_timeout_ms: Option<u64>,
+    /// Send PING to clients that agreed on keepalives after this long without a request
+    pub keepalive_ms: Option<u64>,
+    /// Memory the keys tracked for one connection's near cache may take, the server's default
+    /// when unset
+    pub max_tracked_bytes: Option<usize>,
+}
+
+impl TcpLimits {
+    pub fn from_source(source: &ConfigSource) -> Self {
+        let limit = |var: &str| {
+            source
+                .var(var)
+                .ok()
+                .and_then(|limit| limit.parse::<usize>().ok())
+                .filter(|limit| *limit > 0)
+        };
+        let millis = |var: &str| {
+            source
+                .var(var)
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .filter(|ms| *ms > 0)
+        };
+        Self {
+            max_connections_per_ip: limit("CARBON_TCP_MAX_CONNECTIONS_PER_IP"),
+            max_in_flight_per_ip: limit("CARBON_TCP_MAX_IN_FLIGHT_PER_IP"),
+            idle_timeout_ms: millis("CARBON_TCP_IDLE_TIMEOUT_MS"),
+            keepalive_ms: millis("CARBON_TCP_KEEPALIVE_MS"),
+            max_tracked_bytes: limit("CARBON_TCP_MAX_TRACKED_BYTES"),
+        }
+    }
+}
+
+/// Tokio runtime layout, fields left unset keep Tokio's defaults
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RuntimeConfig {
+    /// Worker threads of the main runtime, which serves HTTP and background tasks
+    pub worker_threads: Option<usize>,
+    /// Ceiling on the main runtime's blocking pool
+    pub max_blocking_threads: Option<usize>,
+    /// Give the TCP data plane its own runtime with this many workers,
+    /// so heavy HTTP traffic cannot delay cache operations over TCP
+    pub tcp_worker_threads: Option<usize>,
+    /// Run configuration store flushes on a dedicated runtime with up to this many threads
+    pub storage_threads: Option<usize>,
+}
+
+impl RuntimeConfig {
+    pub fn from_source(source: &ConfigSource) -> Self {
+        Self {
+            worker_threads: Self::threads_from_source(source, "CARBON_WORKER_THREADS"),
+            max_blocking_threads: Self::threads_from_source(source, "CARBON_MAX_BLOCKING_THREADS"),
+            tcp_worker_threads: Self::threads_from_source(source, "CARBON_TCP_WORKER_THREADS"),
+            storage_threads: Self::threads_from_source(source, "CARBON_STORAGE_THREADS"),
+        }
+    }
+
+    /// None unless `var` holds a positive integer, Tokio panics on zero threads
+    fn threads_from_source(source: &ConfigSource, var: &str) -> Option<usize> {
+        source
+            .var(var)
+            .ok()
+            .and_then(|threads| threads.parse::<usize>().ok())
+            .filter(|threads| *threads > 0)
+    }
+}
+
+/// Log output: human-readable lines or one JSON object per event for log shippers
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    /// `json` (any case) selects JSON, anything else is text
+    pub fn from_source(source: &ConfigSource) -> Self {
+        match source.var("CARBON_LOG_FORMAT") {
+            Ok(format) if format.eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
+/// Guards against clients posting huge payloads or holding requests open
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HttpLimits {
+    pub max_body_bytes: usize,
+    /// Raw value uploads (PUT /cache/{name}/{key} with a non-JSON Content-Type) are streamed
+    /// into the cache and may be larger than other bodies
+    pub max_value_bytes: usize,
+    /// Time allowed to produce a response (streams such as SSE are not cut off once started)
+    pub request_timeout_ms: u64,
+}
+
+impl HttpLimits {
+    /// Matches the TCP server's maximum frame length
+    const DEFAULT_MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+    const DEFAULT_MAX_VALUE_BYTES: usize = 512 * 1024 * 1024;
+    const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 30_000;
+
+    pub fn from_source(source: &ConfigSource) -> Self {
+        Self {
+            max_body_bytes: source
+                .var("CARBON_HTTP_MAX_BODY_BYTES")
+                .ok()
+                .and_then(|bytes| bytes.parse::<usize>().ok())
+                .unwrap_or(Self::DEFAULT_MAX_BODY_BYTES),
+            max_value_bytes: source
+                .var("CARBON_HTTP_MAX_VALUE_BYTES")
+                .ok()
+                .and_then(|bytes| bytes.parse::<usize>().ok())
+                .unwrap_or(Self::DEFAULT_MAX_VALUE_BYTES),
+            request_timeout_ms: source
+                .var("CARBON_HTTP_REQUEST_TIMEOUT_MS")
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .unwrap_or(Self::DEFAULT_REQUEST_TIMEOUT_MS),
+        }
+    }
+}
+
+impl Default for HttpLimits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: Self::DEFAULT_MAX_BODY_BYTES,
+            max_value_bytes: Self::DEFAULT_MAX_VALUE_BYTES,
+            request_timeout_ms: Self::DEFAULT_REQUEST_TIMEOUT_MS,
+        }
+    }
+}
+
+/// Connection handling of the HTTP server
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HttpServerConfig {
+    /// Accept HTTP/2 next to HTTP/1.1, over cleartext clients must use prior knowledge
+    pub http2: bool,
+    /// Requests one HTTP/2 connection may have in flight at once
+    pub http2_max_concurrent_streams: u32,
+    /// How often idle HTTP/2 connections are pinged, None never pings them
+    pub http2_keep_alive_interval_ms: Option<u64>,
+    /// How long a ping may go unanswered before its connection is closed
+    pub http2_keep_alive_timeout_ms: u64,
+    /// Serve several HTTP/1.1 requests on one connection
+    pub keep_alive: bool,
+    /// Time a client has to send the headers of a request, which also closes idle
+    /// HTTP/1.1 keep-alive connections
+    pub header_read_timeout_ms: u64,
+    /// Disable Nagle's algorithm so small responses are not held back
+    pub tcp_nodelay: bool,
+}
+
+impl HttpServerConfig {
+    const DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS: u32 = 256;
+    const DEFAULT_HTTP2_KEEP_ALIVE_TIMEOUT_MS: u64 = 20_000;
+    const DEFAULT_HEADER_READ_TIMEOUT_MS: u64 = 30_000;
+
+    pub fn from_source(source: &ConfigSource) -> Self {
+        Self {
+            http2: source
+                .var("CARBON_HTTP2")
+                .map(|enabled| enabled != "false")
+                .unwrap_or(true),
+            http2_max_concurrent_streams: source
+                .var("CARBON_HTTP2_MAX_CONCURRENT_STREAMS")
+                .ok()
+                .and_then(|streams| streams.parse::<u32>().ok())
+                .filter(|streams| *streams > 0)
+                .unwrap_or(Self::DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS),
+            http2_keep_alive_interval_ms: source
+                .var("CARBON_HTTP2_KEEP_ALIVE_INTERVAL_MS")
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .filter(|ms| *ms > 0),
+            http2_keep_alive_timeout_ms: source
+                .var("CARBON_HTTP2_KEEP_ALIVE_TIMEOUT_MS")
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .unwrap_or(Self::DEFAULT_HTTP2_KEEP_ALIVE_TIMEOUT_MS),
+            keep_alive: source
+                .var("CARBON_HTTP_KEEP_ALIVE")
+                .map(|enabled| enabled != "false")
+                .unwrap_or(true),
+            header_read_timeout_ms: source
+                .var("CARBON_HTTP_HEADER_READ_TIMEOUT_MS")
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .filter(|ms| *ms > 0)
+                .unwrap_or(Self::DEFAULT_HEADER_READ_TIMEOUT_MS),
+            tcp_nodelay: source
+                .var("CARBON_HTTP_TCP_NODELAY")
+                .map(|enabled| enabled != "false")
+                .unwrap_or(true),
+        }
+    }
+}
+
+impl Default for HttpServerConfig {
+    fn default() -> Self {
+        Self {
+            http2: true,
+            http2_max_concurrent_streams: Self::DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS,
+            http2_keep_alive_interval_ms: None,
+            http2_keep_alive_timeout_ms: Self::DEFAULT_HTTP2_KEEP_ALIVE_TIMEOUT_MS,
+            keep_alive: true,
+            header_read_timeout_ms: Self::DEFAULT_HEADER_READ_TIMEOUT_MS,
+            tcp_nodelay: true,
+        }
+    }
+}
+
+/// LDAP or Active Directory server users bind to with their own credentials
+#[derive(Clone, Debug, PartialEq)]
+pub struct LdapConfig {
+    /// e.g. `ldaps://ldap.example.com:636`
+    pub url: String,
+    /// DN users bind as, `{username}` is replaced with the escaped login name,
+    /// e.g. `uid={username},ou=people,dc=example,dc=com` or `{username}@corp.example.com`
+    pub bind_dn: String,
+    /// Where the user's entry is searched for after binding
+    pub search_base: String,
+    /// Filter finding the user's entry, `{username}` is replaced as in `bind_dn`
+    pub user_filter: String,
+    /// Attribute of the user's entry listing its groups
+    pub group_attribute: String,
+    /// Carbon role granted for each group, keyed by group CN or full DN (case-insensitive)
+    pub group_roles: Vec<(String, String)>,
+    /// Role granted to every directory user on top of the mapped ones
+    pub default_role: Option<String>,
+}
+
+impl LdapConfig {
+    const DEFAULT_USER_FILTER: &str = "(uid={username})";
+    const DEFAULT_GROUP_ATTRIBUTE: &str = "memberOf";
+
+    /// None (LDAP off) unless CARBON_LDAP_URL and CARBON_LDAP_BIND_DN are set
+    /// CARBON_LDAP_GROUP_ROLES maps groups to roles, see `parse_role_mapping`
+    pub fn from_source(source: &ConfigSource) -> Option<Self> {
+        let url = source.var("CARBON_LDAP_URL").ok()?;
+        let bind_dn = source.var("CARBON_LDAP_BIND_DN").ok()?;
+        let group_roles = source
+            .var("CARBON_LDAP_GROUP_ROLES")
+            .map(|mapping| parse_role_mapping(&mapping))
+            .unwrap_or_default();
+        Some(Self {
+            url,
+            bind_dn,
+            search_base: source.var("CARBON_LDAP_SEARCH_BASE").unwrap_or_default(),
+            user_filter: source
+                .var("CARBON_LDAP_USER_FILTER")
+                .unwrap_or_else(|_| Self::DEFAULT_USER_FILTER.to_string()),
+            group_attribute: source
+                .var("CARBON_LDAP_GROUP_ATTRIBUTE")
+                .unwrap_or_else(|_| Self::DEFAULT_GROUP_ATTRIBUTE.to_string()),
+            group_roles,
+            default_role: source.var("CARBON_LDAP_DEFAULT_ROLE").ok(),
+        })
+    }
+}
+
+/// `group:role` pairs separated by `;`, the role follows the last `:` so group DNs or URLs
+/// may be used as keys
+fn parse_role_mapping(mapping: &str) -> Vec<(String, String)> {
+    mapping
+        .split(';')
+        .filter_map(|pair| {
+            let (group, role) = pair.rsplit_once(':')?;
+            let (group, role) = (group.trim(), role.trim());
+            (!group.is_empty() && !role.is_empty()).then(|| (group.to_string(), role.to_string()))
+        })
+        .collect()
+}
+
+/// OpenID Connect provider users are sent to from `/auth/oidc/login`
+#[derive(Clone, Debug, PartialEq)]
+pub struct OidcConfig {
+    /// Discovery is read from `{issuer_url}/.well-known/openid-configuration`
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Must point at this server's `/auth/oidc/callback` and be registered with the provider
+    pub redirect_url: String,
+    pub scopes: Vec<String>,
+    /// Claim of the user info listing the user's groups or roles
+    pub roles_claim: String,
+    /// Carbon role granted for each value of `roles_claim`
+    pub claim_roles: Vec<(String, String)>,
+    /// Role granted to every signed-in user on top of the mapped ones
+    pub default_role: Option<String>,
+}
+
+impl OidcConfig {
+    const DEFAULT_SCOPES: &str = "openid profile email";
+    const DEFAULT_ROLES_CLAIM: &str = "groups";
+
+    /// None (OIDC off) unless the issuer, client and redirect URL are all set
+    /// CARBON_OIDC_CLAIM_ROLES maps claim values to roles, see `parse_role_mapping`
+    pub fn from_source(source: &ConfigSource) -> Option<Self> {
+        Some(Self {
+            issuer_url: source.var("CARBON_OIDC_ISSUER_URL").ok()?,
+            client_id: source.var("CARBON_OIDC_CLIENT_ID").ok()?,
+            client_secret: source.var("CARBON_OIDC_CLIENT_SECRET").ok()?,
+            redirect_url: source.var("CARBON_OIDC_REDIRECT_URL").ok()?,
+            scopes: source
+                .var("CARBON_OIDC_SCOPES")
+                .unwrap_or_else(|_| Self::DEFAULT_SCOPES.to_string())
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            roles_claim: source
+                .var("CARBON_OIDC_ROLES_CLAIM")
+                .unwrap_or_else(|_| Self::DEFAULT_ROLES_CLAIM.to_string()),
+            claim_roles: source
+                .var("CARBON_OIDC_CLAIM_ROLES")
+                .map(|mapping| parse_role_mapping(&mapping))
+                .unwrap_or_default(),
+            default_role: source.var("CARBON_OIDC_DEFAULT_ROLE").ok(),
+        })
+    }
+}
+
+/// Message broker cache events are forwarded to
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventSinkKind {
+    /// Comma-separated bootstrap servers, e.g. `kafka-1:9092,kafka-2:9092`
+    Kafka { brokers: String },
+    /// Server URL, e.g. `nats://nats.example.com:4222`
+    Nats { url: String },
+}
+
+/// Where cache events are published, one topic (subject for NATS) per cache or a shared one
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventSinkConfig {
+    pub kind: EventSinkKind,
+    /// Topic of caches without their own
+    pub default_topic: String,
+    /// Topic of each cache routed elsewhere, by cache name
+    pub cache_topics: Vec<(String, String)>,
+}
+
+impl EventSinkConfig {
+    const DEFAULT_TOPIC: &str = "carbon.events";
+
+    /// None (no sink) unless CARBON_EVENT_SINK is `kafka` or `nats` and CARBON_EVENT_SINK_URL
+    /// is set
+    /// CARBON_EVENT_SINK_TOPICS routes caches as `cache:topic` pairs separated by `;`
+    pub fn from_source(source: &ConfigSource) -> Option<Self> {
+        let sink = source.var("CARBON_EVENT_SINK").ok()?;
+        let url = source.var("CARBON_EVENT_SINK_URL").ok()?;
+        let kind = if sink.eq_ignore_ascii_case("kafka") {
+            EventSinkKind::Kafka { brokers: url }
+        } else if sink.eq_ignore_ascii_case("nats") {
+            EventSinkKind::Nats { url }
+        } else {
+            return None;
+        };
+        Some(Self {
+            kind,
+            default_topic: source
+                .var("CARBON_EVENT_SINK_TOPIC")
+                .unwrap_or_else(|_| Self::DEFAULT_TOPIC.to_string()),
+            cache_topics: source
+                .var("CARBON_EVENT_SINK_TOPICS")
+                .map(|routes| parse_topic_routes(&routes))
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// `cache:topic` pairs separated by `;`, cache names cannot hold a `:` but topics may
+fn parse_topic_routes(routes: &str) -> Vec<(String, String)> {
+    routes
+        .split(';')
+        .filter_map(|pair| {
+            let (cache, topic) = pair.split_once(':')?;
+            let (cache, topic) = (cache.trim(), topic.trim());
+            (!cache.is_empty() && !topic.is_empty()).then(|| (cache.to_string(), topic.to_string()))
+        })
+        .collect()
+}
+
+/// Record cache operations taking at least `threshold_ms`, keeping the latest `max_len`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SlowLogConfig {
+    pub threshold_ms: u64,
+    pub max_len: usize,
+}
+
+impl SlowLogConfig {
+    const DEFAULT_MAX_LEN: usize = 128;
+
+    /// None (slow log off) unless CARBON_SLOW_LOG_THRESHOLD_MS is set
+    pub fn from_source(source: &ConfigSource) -> Option<Self> {
+        let threshold_ms = source
+            .var("CARBON_SLOW_LOG_THRESHOLD_MS")
+            .ok()?
+            .parse::<u64>()
+            .ok()?;
+        let max_len = source
+            .var("CARBON_SLOW_LOG_MAX_LEN")
+            .ok()
+            .and_then(|len| len.parse::<usize>().ok())
+            .unwrap_or(Self::DEFAULT_MAX_LEN);
+        Some(Self {
+            threshold_ms,
+            max_len,
+        })
+    }
+}
+
+/// Bounds of the change log of each cache, the oldest changes go first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChangeLogConfig {
+    pub max_bytes: u64,
+    pub retention_ms: u64,
+}
+
+impl ChangeLogConfig {
+    const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+    const DEFAULT_RETENTION_MS: u64 = 24 * 60 * 60 * 1000;
+
+    /// None (change log off) unless CARBON_CHANGE_LOG is `true`
+    pub fn from_source(source: &ConfigSource) -> Option<Self> {
+        source
+            .var("CARBON_CHANGE_LOG")
+            .ok()?
+            .parse::<bool>()
+            .ok()
+            .filter(|enabled| *enabled)?;
+        Some(Self {
+            max_bytes: source
+                .var("CARBON_CHANGE_LOG_MAX_BYTES")
+                .ok()
+                .and_then(|bytes| bytes.parse::<u64>().ok())
+                .unwrap_or(Self::DEFAULT_MAX_BYTES),
+            retention_ms: source
+                .var("CARBON_CHANGE_LOG_RETENTION_MS")
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .unwrap_or(Self::DEFAULT_RETENTION_MS),
+        })
+    }
+}
+
+/// Where scheduled actions may reach outside the server, snapshots and warm-ups are refused
+/// until their setting is given
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScheduleConfig {
+    /// Directory snapshot paths are resolved under
+    pub snapshot_dir: Option<String>,
+    /// URL prefixes warm-ups may fetch from, compared by scheme, host, port and path
+    pub warm_url_prefixes: Vec<String>,
+}
+
+impl ScheduleConfig {
+    /// From CARBON_SNAPSHOT_DIR and CARBON_WARM_URL_PREFIXES, a comma-separated list
+    pub fn from_source(source: &ConfigSource) -> Self {
+        Self {
+            snapshot_dir: source
+                .var("CARBON_SNAPSHOT_DIR")
+                .ok()
+                .filter(|dir| !dir.is_empty()),
+            warm_url_prefixes: source
+                .var("CARBON_WARM_URL_PREFIXES")
+                .map(|prefixes| {
+                    prefixes
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|prefix| !prefix.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Settings of this instance for new caches, applied where a create request leaves them out
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheDefaults {
+    pub mem_bytes: Option<u64>,
+    pub shards: Option<u8>,
+    pub default_ttl_ms: Option<u64>,
+    pub max_value_bytes: Option<u64>,
+    pub max_ops_per_sec: Option<u32>,
+}
+
+impl CacheDefaults {
+    /// From the CARBON_CACHE_DEFAULTS_* variables, the `[cache_defaults]` section of a file
+    pub fn from_source(source: &ConfigSource) -> Self {
+        fn parse<T: std::str::FromStr>(source: &ConfigSource, var: &str) -> Option<T> {
+            source.var(var).ok().and_then(|value| value.parse().ok())
+        }
+        Self {
+            mem_bytes: parse(source, "CARBON_CACHE_DEFAULTS_MEM_BYTES"),
+            shards: parse(source, "CARBON_CACHE_DEFAULTS_SHARDS"),
+            default_ttl_ms: parse(source, "CARBON_CACHE_DEFAULTS_DEFAULT_TTL_MS"),
+            max_value_bytes: parse(source, "CARBON_CACHE_DEFAULTS_MAX_VALUE_BYTES"),
+            max_ops_per_sec: parse(source, "CARBON_CACHE_DEFAULTS_MAX_OPS_PER_SEC"),
+        }
+    }
+}
+
+/// A cache declared in the config, read from the CARBON_CACHES_{NAME}_* variables (the
+/// `[caches.{name}]` section of a file)
+/// Fields mirror the body of POST /admin/caches and are validated the same way
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeclaredCache {
+    pub name: String,
+    /// `ttl`, `size`, `storage` or `redis`
+    pub backend: String,
+    pub policy: Option<String>,
+    pub mem_bytes: Option<u64>,
+    pub capacity_unit: Option<String>,
+    pub shards: Option<u8>,
+    pub default_ttl_ms: Option<u64>,
+    pub max_value_bytes: Option<u64>,
+    pub max_ops_per_sec: Option<u32>,
+    pub disk_path: Option<String>,
+    pub remote_url: Option<String>,
+    pub description: Option<String>,
+}
+
+impl DeclaredCache {
+    const PREFIX: &str = "CARBON_CACHES_";
+    const BACKEND_SUFFIX: &str = "_BACKEND";
+
+    /// Every cache with a backend set, sorted by name
+    pub fn all_from_source(source: &ConfigSource) -> Vec<Self> {
+        source
+            .var_names(Self::PREFIX)
+            .iter()
+            .filter_map(|var| {
+                var.strip_prefix(Self::PREFIX)?
+                    .strip_suffix(Self::BACKEND_SUFFIX)
+            })
+            .filter_map(|name| Self::from_source(source, name))
+            .collect()
+    }
+
+    /// None unless the backend is set, the cache is named after its section in lowercase
+    pub fn from_source(source: &ConfigSource, name: &str) -> Option<Self> {
+        let var = |field: &str| {
+            source
+                .var(&format!("{}{}_{}", Self::PREFIX, name, field))
+                .ok()
+        };
+        let parse = |field: &str| var(field).and_then(|value| value.parse().ok());
+        Some(Self {
+            name: name.to_lowercase(),
+            backend: var("BACKEND")?,
+            policy: var("POLICY"),
+            mem_bytes: parse("MEM_BYTES"),
+            capacity_unit: var("CAPACITY_UNIT"),
+            shards: var("SHARDS").and_then(|shards| shards.parse().ok()),
+            default_ttl_ms: parse("DEFAULT_TTL_MS"),
+            max_value_bytes: parse("MAX_VALUE_BYTES"),
+            max_ops_per_sec: var("MAX_OPS_PER_SEC").and_then(|ops| ops.parse().ok()),
+            disk_path: var("DISK_PATH"),
+            remote_url: var("REMOTE_URL"),
+            description: var("DESCRIPTION"),
+        })
+    }
+}
+
+/// What a listener serves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ListenerKind {
+    Http,
+    Tcp,
+    /// Accepted in the config, no gRPC server is built yet
+    Grpc,
+}
+
+/// Routes an HTTP listener serves, health checks and login are served by all of them
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ListenerRoutes {
+    #[default]
+    All,
+    /// Cache, query and event routes
+    Data,
+    /// `/admin` routes and `/metrics`
+    Admin,
+}
+
+/// Credentials an HTTP listener accepts
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ListenerAuth {
+    /// Basic credentials or session tokens
+    #[default]
+    Any,
+    /// Session tokens only, so passwords are only sent to `/auth/login`
+    Session,
+}
+
+/// A named listener, read from the CARBON_LISTENERS_{NAME}_* variables (the
+/// `[listeners.{name}]` section of a file)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListenerConfig {
+    pub name: String,
+    pub kind: ListenerKind,
+    pub host: String,
+    pub port: u16,
+    /// Certificate and key paths, as CARBON_TLS_CERT_PATH and CARBON_TLS_KEY_PATH set them for
+    /// the main listeners
+    pub tls: Option<(String, String)>,
+    pub routes: ListenerRoutes,
+    pub auth: ListenerAuth,
+}
+
+impl ListenerConfig {
+    const PREFIX: &str = "CARBON_LISTENERS_";
+    const PROTOCOL_SUFFIX: &str = "_PROTOCOL";
+
+    /// Every listener with a protocol set, sorted by name
+    pub fn all_from_source(source: &ConfigSource, default_host: &str) -> Vec<Self> {
+        source
+            .var_names(Self::PREFIX)
+            .iter()
+            .filter_map(|var| {
+                var.strip_prefix(Self::PREFIX)?
+                    .strip_suffix(Self::PROTOCOL_SUFFIX)
+            })
+            .filter_map(|name| Self::from_source(source, name, default_host))
+            .collect()
+    }
+
+    /// None unless the protocol is `http`, `https`, `tcp`, `tcps` or `grpc` and the port is
+    /// set, the secure protocols also need `cert_path` and `key_path`
+    /// `routes` is `all`, `data` or `admin`, `auth` is `any` or `session`
+    pub fn from_source(source: &ConfigSource, name: &str, default_host: &str) -> Option<Self> {
+        let var = |field: &str| source.var(&format!("{}{}_{}", Self::PREFIX, name, field));
+        let protocol = var("PROTOCOL").ok()?.to_lowercase();
+        let port = var("PORT").ok()?.parse::<u16>().ok()?;
+        let tls = match (var("CERT_PATH"), var("KEY_PATH")) {
+            (Ok(cert), Ok(key)) => Some((cert, key)),
+            _ => None,
+        };
+        let kind = match protocol.as_str() {
+            "http" | "https" => ListenerKind::Http,
+            "tcp" | "tcps" => ListenerKind::Tcp,
+            "grpc" => ListenerKind::Grpc,
+            _ => return None,
+        };
+        if matches!(protocol.as_str(), "https" | "tcps") && tls.is_none() {
+            return None;
+        }
+        let routes = match var("ROUTES").map(|routes| routes.to_lowercase()).as_deref() {
+            Ok("data") => ListenerRoutes::Data,
+            Ok("admin") => ListenerRoutes::Admin,
+            _ => ListenerRoutes::All,
+        };
+        let auth = match var("AUTH").map(|auth| auth.to_lowercase()).as_deref() {
+            Ok("session") => ListenerAuth::Session,
+            _ => ListenerAuth::Any,
+        };
+        Some(Self {
+            name: name.to_lowercase(),
+            kind,
+            host: var("HOST").unwrap_or_else(|_| default_host.to_string()),
+            port,
+            tls,
+            routes,
+            auth,
+        })
+    }
+
+    /// Scheme of the listener's URLs, for logs
+    pub fn scheme(&self) -> &'static str {
+        match (self.kind, self.tls.is_some()) {
+            (ListenerKind::Http, false) => "http",
+            (ListenerKind::Http, true) => "https",
+            (ListenerKind::Tcp, false) => "tcp",
+            (ListenerKind::Tcp, true) => "tcp+tls",
+            (ListenerKind::Grpc, _) => "grpc",
+        }
+    }
+}
+
+/// Token bucket settings: sustained requests per second and the burst allowed on top
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub requests_per_second: u32,
+    pub burst: u32,
+}
+
+/// HTTP rate limits per client, None disables limiting for that group of routes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RateLimitConfig {
+    pub data: Option<RateLimit>,
+    /// Admin routes use the data limit unless overridden
+    pub admin: Option<RateLimit>,
+}
+
+impl RateLimitConfig {
+    pub fn from_source(source: &ConfigSource) -> Self {
+        let data =
+            RateLimit::from_source(source, "CARBON_RATE_LIMIT_RPS", "CARBON_RATE_LIMIT_BURST");
+        let admin = RateLimit::from_source(
+            source,
+            "CARBON_ADMIN_RATE_LIMIT_RPS",
+            "CARBON_ADMIN_RATE_LIMIT_BURST",
+        )
+        .or(data);
+        Self { data, admin }
+    }
+}
+
+impl RateLimit {
+    /// None unless `rps_var` holds a positive integer, the burst defaults to one second's worth
+    fn from_source(source: &ConfigSource, rps_var: &str, burst_var: &str) -> Option<Self> {
+        let requests_per_second = source
+            .var(rps_var)
+            .ok()?
+            .parse::<u32>()
+            .ok()
+            .filter(|rps| *rps > 0)?;
+        let burst = source
+            .var(burst_var)
+            .ok()
+            .and_then(|burst| burst.parse::<u32>().ok())
+            .unwrap_or(requests_per_second)
+            .max(1);
+        Some(Self {
+            requests_per_second,
+            burst,
+        })
+    }
 }
 
 impl Config {
     const DEFAULT_ADMIN_USERNAME: &str = "admin";
     const DEFAULT_ADMIN_PASSWORD: &str = "admin123";
     const DEFAULT_DATA_DIR: &str = "./data";
+    const DEFAULT_LOG_LEVEL: &str = "info";
+    const DEFAULT_SESSION_TTL_MS: u64 = 3_600_000;
+    const DEFAULT_SESSION_MAX_LIFETIME_MS: u64 = 86_400_000;
+    const DEFAULT_SESSION_REFRESH_TTL_MS: u64 = 604_800_000;
+    const DEFAULT_ADMIN_RESPONSE_MAX_AGE_MS: u64 = 1_000;
 
+    /// Read from the environment alone
     pub fn from_env() -> Self {
-        let host = std::env::var("CARBON_HOST").unwrap_or_else(|_| "localhost".to_string());
-        let tcp_port = std::env::var("CARBON_TCP_PORT")
+        Self::from_source(&ConfigSource::env())
+    }
+
+    /// Read from a config file, the environment and command line flags, see `ConfigSource`
+    pub fn from_source(source: &ConfigSource) -> Self {
+        let host = source
+            .var("CARBON_HOST")
+            .unwrap_or_else(|_| "localhost".to_string());
+        let tcp_port = source
+            .var("CARBON_TCP_PORT")
             .unwrap_or_else(|_| "5500".to_string())
             .parse::<u16>()
             .unwrap_or(5500);
-        let http_port = std::env::var("CARBON_HTTP_PORT")
+        let http_port = source
+            .var("CARBON_HTTP_PORT")
             .unwrap_or_else(|_| "8080".to_string())
             .parse::<u16>()
             .unwrap_or(8080);
-        let https_port = std::env::var("CARBON_HTTPS_PORT")
+        let https_port = source
+            .var("CARBON_HTTPS_PORT")
             .unwrap_or_else(|_| "8443".to_string())
             .parse::<u16>()
             .unwrap_or(8443);
-        let tls_cert_path = std::env::var("CARBON_TLS_CERT_PATH").ok();
-        let tls_key_path = std::env::var("CARBON_TLS_KEY_PATH").ok();
+        let tls_cert_path = source.var("CARBON_TLS_CERT_PATH").ok();
+        let tls_key_path = source.var("CARBON_TLS_KEY_PATH").ok();
+        let listeners = ListenerConfig::all_from_source(source, &host);
         Self {
             host,
-            data_dir: std::env::var("CARBON_DATA_DIR")
+            data_dir: source
+                .var("CARBON_DATA_DIR")
                 .unwrap_or_else(|_| Self::DEFAULT_DATA_DIR.to_string()),
-            admin_username: std::env::var("CARBON_ADMIN_USERNAME")
+            admin_username: source
+                .var("CARBON_ADMIN_USERNAME")
                 .unwrap_or_else(|_| Self::DEFAULT_ADMIN_USERNAME.to_string()),
-            admin_password: std::env::var("CARBON_ADMIN_PASSWORD")
+            admin_password: source
+                .var("CARBON_ADMIN_PASSWORD")
                 .unwrap_or_else(|_| Self::DEFAULT_ADMIN_PASSWORD.to_string()),
+            rate_limit: RateLimitConfig::from_source(source),
+            trusted_proxies: source
+                .var("CARBON_TRUSTED_PROXIES")
+                .map(|proxies| {
+                    proxies
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|proxy| !proxy.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            http_limits: HttpLimits::from_source(source),
+            http_server: HttpServerConfig::from_source(source),
+            log_level: source
+                .var("CARBON_LOG_LEVEL")
+                .unwrap_or_else(|_| Self::DEFAULT_LOG_LEVEL.to_string()),
+            log_format: LogFormat::from_source(source),
+            // `otlp_endpoint` in a config file
+            otlp_endpoint: source
+                .var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .or_else(|_| source.var("CARBON_OTLP_ENDPOINT"))
+                .ok(),
+            slow_log: SlowLogConfig::from_source(source),
+            session_ttl_ms: source
+                .var("CARBON_SESSION_TTL_MS")
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .filter(|ms| *ms > 0)
+                .unwrap_or(Self::DEFAULT_SESSION_TTL_MS),
+            session_max_lifetime_ms: source
+                .var("CARBON_SESSION_MAX_LIFETIME_MS")
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .filter(|ms| *ms > 0)
+                .unwrap_or(Self::DEFAULT_SESSION_MAX_LIFETIME_MS),
+            session_refresh_ttl_ms: source
+                .var("CARBON_SESSION_REFRESH_TTL_MS")
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .filter(|ms| *ms > 0)
+                .unwrap_or(Self::DEFAULT_SESSION_REFRESH_TTL_MS),
+            max_ops_per_sec: source
+                .var("CARBON_MAX_OPS_PER_SEC")
+                .ok()
+                .and_then(|ops| ops.parse::<u32>().ok())
+                .filter(|ops| *ops > 0),
+            tcp_acceptors: source
+                .var("CARBON_TCP_ACCEPTORS")
+                .ok()
+                .and_then(|acceptors| acceptors.parse::<usize>().ok())
+                .filter(|acceptors| *acceptors > 0),
+            tcp_limits: TcpLimits::from_source(source),
+            runtime: RuntimeConfig::from_source(source),
+            admin_response_max_age_ms: source
+                .var("CARBON_ADMIN_RESPONSE_MAX_AGE_MS")
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .unwrap_or(Self::DEFAULT_ADMIN_RESPONSE_MAX_AGE_MS),
+            auth_store: AuthStore::from_source(source),
+            ldap: LdapConfig::from_source(source),
+            oidc: OidcConfig::from_source(source),
+            auth_local_accounts: source
+                .var("CARBON_AUTH_LOCAL_ACCOUNTS")
+                .map(|enabled| enabled != "false")
+                .unwrap_or(true),
+            auth_key_file: source.var("CARBON_AUTH_KEY_FILE").ok(),
+            auth_key: source.var("CARBON_AUTH_KEY").ok(),
+            auth_encrypt_at_rest: source
+                .var("CARBON_AUTH_ENCRYPT_AT_REST")
+                .map(|enabled| enabled == "true")
+                .unwrap_or(false),
+            quotas_enabled: source
+                .var("CARBON_QUOTAS_ENABLED")
+                .map(|enabled| enabled == "true")
+                .unwrap_or(false),
+            drop_retention_ms: source
+                .var("CARBON_DROP_RETENTION_MS")
+                .ok()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .unwrap_or(0),
+            event_sink: EventSinkConfig::from_source(source),
+            change_log: ChangeLogConfig::from_source(source),
+            cache_defaults: CacheDefaults::from_source(source),
+            listeners,
+            caches: DeclaredCache::all_from_source(source),
+            schedules: ScheduleConfig::from_source(source),
             http: match (&tls_cert_path, &tls_key_path) {
                 (Some(cert), Some(key)) => Protocol::Https(https_port, cert.clone(), key.clone()),
                 _ => Protocol::Http(http_port),
@@ -55,6 +988,32 @@ impl Config {
     }
 }
 
+impl Config {
+    /// The configured listeners, or the HTTP and TCP listeners of `host`, `http` and `tcp`
+    pub fn listeners(&self) -> Vec<ListenerConfig> {
+        if !self.listeners.is_empty() {
+            return self.listeners.clone();
+        }
+        [
+            ("http", ListenerKind::Http, &self.http),
+            ("tcp", ListenerKind::Tcp, &self.tcp),
+        ]
+        .into_iter()
+        .map(|(name, kind, protocol)| ListenerConfig {
+            name: name.to_string(),
+            kind,
+            host: self.host.clone(),
+            port: protocol.port(),
+            tls: protocol
+                .tls_paths()
+                .map(|(cert, key)| (cert.to_string(), key.to_string())),
+            routes: ListenerRoutes::All,
+            auth: ListenerAuth::Any,
+        })
+        .collect()
+    }
+}
+
 impl Protocol {
     pub fn port(&self) -> u16 {
         match self {
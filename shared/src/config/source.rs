@@ -0,0 +1,288 @@
+use crate::{Error, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env::VarError;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Variable naming the config file when `--config` is not given
+pub const CONFIG_FILE_VAR: &str = "CARBON_CONFIG_FILE";
+
+/// Where configuration values come from, `--set` flags win over the environment, which wins
+/// over the config file, and .env values read again on reload stand in for the environment's
+/// Keys of the file and of `--set` name the variable they stand for: nested keys are joined
+/// with `_`, uppercased and prefixed with `CARBON_`, so `http.max_body_bytes` is
+/// `CARBON_HTTP_MAX_BODY_BYTES`, and `enabled` stands for its section, so `change_log.enabled`
+/// is `CARBON_CHANGE_LOG`
+#[derive(Debug, Default)]
+pub struct ConfigSource {
+    file_path: Option<PathBuf>,
+    file: HashMap<String, String>,
+    /// Values taking the place of the environment's, from a .env file read again on reload
+    env_overrides: HashMap<String, String>,
+    flags: HashMap<String, String>,
+    /// Variables looked up so far, to point out keys that had no effect
+    read: Mutex<HashSet<String>>,
+}
+
+/// A value of the config file, TOML and YAML files both read into it
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FileValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Table(BTreeMap<String, FileValue>),
+}
+
+impl ConfigSource {
+    /// The environment alone
+    pub fn env() -> Self {
+        Self::default()
+    }
+
+    /// The file of `--config <path>` (or CARBON_CONFIG_FILE) and the `--set key=value` flags
+    /// in `args`, other arguments are left to the caller
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        let mut source = Self::default();
+        let mut file_path = std::env::var(CONFIG_FILE_VAR).ok().map(PathBuf::from);
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = |args: &mut std::slice::Iter<String>| {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| Error::InvalidValue(format!("{} needs a value", arg)))
+            };
+            match arg.as_str() {
+                "--config" => file_path = Some(PathBuf::from(value(&mut args)?)),
+                "--set" => {
+                    let setting = value(&mut args)?;
+                    let (key, value) = setting.split_once('=').ok_or_else(|| {
+                        Error::InvalidValue(format!("--set {} is not key=value", setting))
+                    })?;
+                    source.flags.insert(var_name(key.trim()), value.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        match file_path {
+            Some(path) => source.with_file(path),
+            None => Ok(source),
+        }
+    }
+
+    /// Read the TOML or YAML file at `path`, told apart by its extension
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            Error::InvalidValue(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let table: BTreeMap<String, FileValue> =
+            match path.extension().and_then(|extension| extension.to_str()) {
+                Some("toml") => toml::from_str(&contents).map_err(|e| e.to_string()),
+                Some("yaml" | "yml") => serde_yaml::from_str(&contents).map_err(|e| e.to_string()),
+                _ => Err("expected a .toml, .yaml or .yml file".to_string()),
+            }
+            .map_err(|e| {
+                Error::InvalidValue(format!("Invalid config file {}: {}", path.display(), e))
+            })?;
+
+        self.file.clear();
+        flatten("CARBON", table, &mut self.file);
+        self.file_path = Some(path.to_path_buf());
+        Ok(self)
+    }
+
+    /// The same flags with the config file read again, as a reload sees it
+    pub fn reread(&self) -> Result<Self> {
+        let source = Self {
+            flags: self.flags.clone(),
+            ..Self::default()
+        };
+        match &self.file_path {
+            Some(path) => source.with_file(path),
+            None => Ok(source),
+        }
+    }
+
+    /// Read `vars` in place of the environment's values, without changing the process
+    /// environment, which other threads may be reading
+    pub fn with_env_overrides(mut self, vars: HashMap<String, String>) -> Self {
+        self.env_overrides = vars;
+        self
+    }
+
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file_path.as_deref()
+    }
+
+    /// Value of the variable `name`, from the first layer that sets it
+    pub fn var(&self, name: &str) -> std::result::Result<String, VarError> {
+        self.read.lock().unwrap().insert(name.to_string());
+        if let Some(value) = self
+            .flags
+            .get(name)
+            .or_else(|| self.env_overrides.get(name))
+        {
+            return Ok(value.clone());
+        }
+        match std::env::var(name) {
+            Err(VarError::NotPresent) => self.file.get(name).cloned().ok_or(VarError::NotPresent),
+            value => value,
+        }
+    }
+
+    /// Names of the variables starting with `prefix` that any layer sets, sorted
+    pub fn var_names(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> = std::env::vars_os()
+            .filter_map(|(name, _)| name.into_string().ok())
+            .chain(self.file.keys().cloned())
+            .chain(self.env_overrides.keys().cloned())
+            .chain(self.flags.keys().cloned())
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Variables set in the file or by flags that nothing has read, most likely misspelled
+    pub fn unused_keys(&self) -> Vec<String> {
+        let read = self.read.lock().unwrap();
+        let mut unused: Vec<String> = self
+            .file
+            .keys()
+            .chain(self.flags.keys())
+            .filter(|name| !read.contains(*name))
+            .cloned()
+            .collect();
+        unused.sort();
+        unused.dedup();
+        unused
+    }
+}
+
+/// `http.max_body_bytes` to `CARBON_HTTP_MAX_BODY_BYTES`
+fn var_name(key: &str) -> String {
+    let mut name = "CARBON".to_string();
+    for part in key.split('.') {
+        if part != "enabled" {
+            name.push('_');
+            name.push_str(&part.to_uppercase());
+        }
+    }
+    name
+}
+
+fn flatten(prefix: &str, table: BTreeMap<String, FileValue>, vars: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let name = if key == "enabled" {
+            prefix.to_string()
+        } else {
+            format!("{}_{}", prefix, key.to_uppercase())
+        };
+        let value = match value {
+            FileValue::Table(table) => {
+                flatten(&name, table, vars);
+                continue;
+            }
+            FileValue::Bool(value) => value.to_string(),
+            FileValue::Integer(value) => value.to_string(),
+            FileValue::Float(value) => value.to_string(),
+            FileValue::String(value) => value,
+        };
+        vars.insert(name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_keys_name_variables() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml = dir.path().join("carbon.toml");
+        std::fs::write(
+            &toml,
+            "data_dir = \"/var/lib/carbon\"\n\n[http]\nmax_body_bytes = 1024\n\n[change_log]\nenabled = true\nretention_ms = 60000\n",
+        )
+        .unwrap();
+        let yaml = dir.path().join("carbon.yaml");
+        std::fs::write(
+            &yaml,
+            "data_dir: /var/lib/carbon\nhttp:\n  max_body_bytes: 1024\nchange_log:\n  enabled: true\n  retention_ms: 60000\n",
+        )
+        .unwrap();
+
+        for path in [toml, yaml] {
+            let source = ConfigSource::env().with_file(&path).unwrap();
+            assert_eq!(source.file["CARBON_DATA_DIR"], "/var/lib/carbon");
+            assert_eq!(source.file["CARBON_HTTP_MAX_BODY_BYTES"], "1024");
+            assert_eq!(source.file["CARBON_CHANGE_LOG"], "true");
+            assert_eq!(source.file["CARBON_CHANGE_LOG_RETENTION_MS"], "60000");
+        }
+
+        assert!(
+            ConfigSource::env()
+                .with_file(dir.path().join("carbon.ini"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_flags_win_and_unused_keys_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("carbon.toml");
+        std::fs::write(&path, "[test_source]\nport = 1\ntypo = 2\n").unwrap();
+        let args: Vec<String> = [
+            "--config",
+            path.to_str().unwrap(),
+            "--set",
+            "test_source.port=2",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+        let source = ConfigSource::from_args(&args).unwrap();
+        assert_eq!(source.var("CARBON_TEST_SOURCE_PORT").unwrap(), "2");
+        assert_eq!(source.unused_keys(), ["CARBON_TEST_SOURCE_TYPO"]);
+        assert_eq!(
+            source.var_names("CARBON_TEST_SOURCE_"),
+            ["CARBON_TEST_SOURCE_PORT", "CARBON_TEST_SOURCE_TYPO"]
+        );
+        assert_eq!(source.reread().unwrap().file_path(), Some(path.as_path()));
+
+        assert!(ConfigSource::from_args(&["--set".to_string(), "port".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_env_overrides_sit_between_flags_and_the_environment() {
+        let args: Vec<String> = ["--set", "test_overrides.port=3"]
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let overrides = HashMap::from([
+            ("CARBON_TEST_OVERRIDES_PORT".to_string(), "2".to_string()),
+            (
+                "CARBON_TEST_OVERRIDES_HOST".to_string(),
+                "cache".to_string(),
+            ),
+        ]);
+
+        let source = ConfigSource::from_args(&args)
+            .unwrap()
+            .with_env_overrides(overrides);
+        assert_eq!(source.var("CARBON_TEST_OVERRIDES_PORT").unwrap(), "3");
+        assert_eq!(source.var("CARBON_TEST_OVERRIDES_HOST").unwrap(), "cache");
+        assert!(std::env::var("CARBON_TEST_OVERRIDES_HOST").is_err());
+        assert_eq!(
+            source.var_names("CARBON_TEST_OVERRIDES_"),
+            ["CARBON_TEST_OVERRIDES_HOST", "CARBON_TEST_OVERRIDES_PORT"]
+        );
+    }
+}
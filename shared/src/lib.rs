@@ -6,10 +6,174 @@ pub enum Error {
     NotFound,
     #[error("cache not found: {0}")]
     CacheNotFound(String),
+    #[error("invalid value: {0}")]
+    InvalidValue(String),
+    #[error("wrong type: {0}")]
+    WrongType(String),
+    #[error("throttled: {0}")]
+    Throttled(String),
+    #[error("precondition failed: {0}")]
+    PreconditionFailed(String),
+    #[error("quota exceeded: {0}")]
+    QuotaExceeded(String),
+    #[error("read only: {0}")]
+    ReadOnly(String),
     #[error("internal: {0}")]
     Internal(String),
 }
 
+impl Error {
+    /// Code reported for this error by every frontend
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::NotFound => ErrorCode::NotFound,
+            Error::CacheNotFound(_) => ErrorCode::CacheNotFound,
+            Error::InvalidValue(_) => ErrorCode::InvalidValue,
+            Error::WrongType(_) => ErrorCode::WrongType,
+            Error::Throttled(_) => ErrorCode::Throttled,
+            Error::PreconditionFailed(_) => ErrorCode::PreconditionFailed,
+            Error::QuotaExceeded(_) => ErrorCode::QuotaExceeded,
+            Error::ReadOnly(_) => ErrorCode::ReadOnly,
+            Error::Internal(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// Stable error codes, sent by name over HTTP and by number over TCP
+/// Numbers are part of the wire protocol, new codes take the next free one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    Internal,
+    NotFound,
+    CacheNotFound,
+    InvalidValue,
+    WrongType,
+    Throttled,
+    PreconditionFailed,
+    QuotaExceeded,
+    Unauthorized,
+    Forbidden,
+    ValueTooLarge,
+    BadRequest,
+    AlreadyExists,
+    Locked,
+    Timeout,
+    NotEnabled,
+    ChecksumMismatch,
+    /// A client has as many connections or requests open as it is allowed
+    Busy,
+    /// The server or cache is in maintenance mode and refuses writes
+    ReadOnly,
+    /// The cache's admission policy turned the write away, nothing was stored
+    NotAdmitted,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 20] = [
+        ErrorCode::Internal,
+        ErrorCode::NotFound,
+        ErrorCode::CacheNotFound,
+        ErrorCode::InvalidValue,
+        ErrorCode::WrongType,
+        ErrorCode::Throttled,
+        ErrorCode::PreconditionFailed,
+        ErrorCode::QuotaExceeded,
+        ErrorCode::Unauthorized,
+        ErrorCode::Forbidden,
+        ErrorCode::ValueTooLarge,
+        ErrorCode::BadRequest,
+        ErrorCode::AlreadyExists,
+        ErrorCode::Locked,
+        ErrorCode::Timeout,
+        ErrorCode::NotEnabled,
+        ErrorCode::ChecksumMismatch,
+        ErrorCode::Busy,
+        ErrorCode::ReadOnly,
+        ErrorCode::NotAdmitted,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Internal => "INTERNAL",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::CacheNotFound => "CACHE_NOT_FOUND",
+            ErrorCode::InvalidValue => "INVALID_VALUE",
+            ErrorCode::WrongType => "WRONG_TYPE",
+            ErrorCode::Throttled => "THROTTLED",
+            ErrorCode::PreconditionFailed => "PRECONDITION_FAILED",
+            ErrorCode::QuotaExceeded => "QUOTA_EXCEEDED",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::ValueTooLarge => "VALUE_TOO_LARGE",
+            ErrorCode::BadRequest => "BAD_REQUEST",
+            ErrorCode::AlreadyExists => "ALREADY_EXISTS",
+            ErrorCode::Locked => "LOCKED",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::NotEnabled => "NOT_ENABLED",
+            ErrorCode::ChecksumMismatch => "CHECKSUM_MISMATCH",
+            ErrorCode::Busy => "BUSY",
+            ErrorCode::ReadOnly => "READ_ONLY",
+            ErrorCode::NotAdmitted => "NOT_ADMITTED",
+        }
+    }
+
+    /// Number sent in TCP ERROR frames
+    pub fn number(self) -> u16 {
+        match self {
+            ErrorCode::Internal => 1,
+            ErrorCode::NotFound => 2,
+            ErrorCode::CacheNotFound => 3,
+            ErrorCode::InvalidValue => 4,
+            ErrorCode::WrongType => 5,
+            ErrorCode::Throttled => 6,
+            ErrorCode::PreconditionFailed => 7,
+            ErrorCode::QuotaExceeded => 8,
+            ErrorCode::Unauthorized => 9,
+            ErrorCode::Forbidden => 10,
+            ErrorCode::ValueTooLarge => 11,
+            ErrorCode::BadRequest => 12,
+            ErrorCode::AlreadyExists => 13,
+            ErrorCode::Locked => 14,
+            ErrorCode::Timeout => 15,
+            ErrorCode::NotEnabled => 16,
+            ErrorCode::ChecksumMismatch => 17,
+            ErrorCode::Busy => 18,
+            ErrorCode::ReadOnly => 19,
+            ErrorCode::NotAdmitted => 20,
+        }
+    }
+
+    /// Code for a number from `number`, None for codes this build does not know
+    pub fn from_number(number: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|code| code.number() == number)
+    }
+
+    /// Code for an HTTP status, for errors raised outside the caches such as bad requests
+    pub fn from_http_status(status: u16) -> Self {
+        match status {
+            400 | 405 | 406 | 415 | 422 => ErrorCode::BadRequest,
+            401 => ErrorCode::Unauthorized,
+            403 => ErrorCode::Forbidden,
+            404 => ErrorCode::NotFound,
+            408 | 504 => ErrorCode::Timeout,
+            409 => ErrorCode::AlreadyExists,
+            412 => ErrorCode::PreconditionFailed,
+            413 => ErrorCode::ValueTooLarge,
+            423 => ErrorCode::Locked,
+            429 => ErrorCode::Throttled,
+            501 => ErrorCode::NotEnabled,
+            507 => ErrorCode::NotAdmitted,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 // Keep old alias for backwards compatibility
 pub type CacheError = Error;
 pub type Result<T> = std::result::Result<T, Error>;
@@ -18,3 +182,20 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub struct TtlMs(pub u64);
 
 pub mod config;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_code_numbers_roundtrip() {
+        for code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_number(code.number()), Some(code));
+        }
+        assert_eq!(ErrorCode::from_number(0), None);
+        assert_eq!(
+            Error::CacheNotFound("orders".to_string()).code().as_str(),
+            "CACHE_NOT_FOUND"
+        );
+    }
+}
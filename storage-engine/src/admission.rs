@@ -0,0 +1,150 @@
+use std::hash::{BuildHasher, Hash, RandomState};
+use std::sync::atomic::{AtomicU8, AtomicU64, AtomicUsize, Ordering};
+
+/// Rows of the sketch, each key is counted once per row
+const DEPTH: usize = 4;
+/// Counters saturate like the 4-bit counters of TinyLFU
+const MAX_COUNT: u8 = 15;
+
+/// Count-min sketch of recent key accesses, halved once it has counted ten times
+/// its width so keys that stopped being accessed age out
+pub struct FrequencySketch {
+    counters: Vec<AtomicU8>,
+    width: usize,
+    additions: AtomicUsize,
+    sample_size: usize,
+    hasher: RandomState,
+}
+
+impl FrequencySketch {
+    /// Sketch sized for a cache holding about `capacity` entries
+    pub fn with_capacity(capacity: u64) -> Self {
+        let width = (capacity.clamp(1 << 10, 1 << 20) as usize).next_power_of_two();
+        Self {
+            counters: (0..DEPTH * width).map(|_| AtomicU8::new(0)).collect(),
+            width,
+            additions: AtomicUsize::new(0),
+            sample_size: 10 * width,
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Count an access to `key`
+    pub fn increment<K: Hash>(&self, key: &K) {
+        for index in self.indexes(key) {
+            // Saturated counters are left alone, fetch_update fails without changing them
+            let _ = self.counters[index].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| {
+                (c < MAX_COUNT).then_some(c + 1)
+            });
+        }
+
+        if self.additions.fetch_add(1, Ordering::Relaxed) + 1 >= self.sample_size {
+            self.reset();
+        }
+    }
+
+    /// Estimated accesses to `key` since it was last aged, never an undercount
+    pub fn frequency<K: Hash>(&self, key: &K) -> u8 {
+        self.indexes(key)
+            .map(|index| self.counters[index].load(Ordering::Relaxed))
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Halve every counter; racing increments may be lost, which only ages keys a little sooner
+    fn reset(&self) {
+        self.additions.store(0, Ordering::Relaxed);
+        for counter in &self.counters {
+            let count = counter.load(Ordering::Relaxed);
+            counter.store(count / 2, Ordering::Relaxed);
+        }
+    }
+
+    /// One counter per row, picked by double hashing a single hash of the key
+    fn indexes<K: Hash>(&self, key: &K) -> impl Iterator<Item = usize> {
+        let hash = self.hasher.hash_one(key);
+        let (h1, h2) = (hash as u32 as usize, (hash >> 32) as usize | 1);
+        (0..DEPTH)
+            .map(move |row| row * self.width + h1.wrapping_add(row.wrapping_mul(h2)) % self.width)
+    }
+}
+
+/// Admission in front of a full cache: new keys only get in once they have been
+/// accessed `threshold` times, so one-hit wonders cannot push out hot entries
+pub struct Admission {
+    sketch: FrequencySketch,
+    threshold: u8,
+    rejected: AtomicU64,
+}
+
+impl Admission {
+    pub fn new(capacity: u64, threshold: u8) -> Self {
+        Self {
+            sketch: FrequencySketch::with_capacity(capacity),
+            threshold,
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a read of `key`, hit or miss
+    pub fn record_access<K: Hash>(&self, key: &K) {
+        self.sketch.increment(key);
+    }
+
+    /// Count a write of a new `key` and decide whether it may displace an entry
+    pub fn admit<K: Hash>(&self, key: &K) -> bool {
+        self.sketch.increment(key);
+        let admitted = self.sketch.frequency(key) >= self.threshold;
+        if !admitted {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        admitted
+    }
+
+    /// Writes turned away since the cache was created
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frequency_sketch() {
+        let sketch = FrequencySketch::with_capacity(1024);
+        assert_eq!(sketch.frequency(&"hot"), 0);
+
+        for _ in 0..5 {
+            sketch.increment(&"hot");
+        }
+        sketch.increment(&"cold");
+        assert!(sketch.frequency(&"hot") >= 5);
+        assert!(sketch.frequency(&"cold") >= 1);
+
+        // Counters saturate
+        for _ in 0..20 {
+            sketch.increment(&"hot");
+        }
+        assert_eq!(sketch.frequency(&"hot"), MAX_COUNT);
+
+        // Filling the sample halves every counter
+        for i in 0..sketch.sample_size {
+            sketch.increment(&i);
+        }
+        assert!(sketch.frequency(&"hot") < MAX_COUNT);
+    }
+
+    #[test]
+    fn test_admission() {
+        let admission = Admission::new(1024, 2);
+        assert!(!admission.admit(&"key"));
+        assert_eq!(admission.rejected(), 1);
+
+        // A read makes the next write the key's second access
+        admission.record_access(&"key");
+        assert!(admission.admit(&"key"));
+        assert_eq!(admission.rejected(), 1);
+    }
+}
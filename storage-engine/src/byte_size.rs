@@ -0,0 +1,30 @@
+use bytes::Bytes;
+
+/// Size of a key or value as counted by caches bounded in bytes
+pub trait ByteSize {
+    fn byte_size(&self) -> usize;
+}
+
+impl ByteSize for Bytes {
+    fn byte_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl ByteSize for Vec<u8> {
+    fn byte_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl ByteSize for String {
+    fn byte_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl ByteSize for &str {
+    fn byte_size(&self) -> usize {
+        self.len()
+    }
+}
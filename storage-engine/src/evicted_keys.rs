@@ -0,0 +1,54 @@
+use std::sync::Mutex;
+
+/// Most keys held between two calls to take_evicted, later evictions are not reported
+pub const MAX_EVICTED_KEYS: usize = 65_536;
+
+/// Keys a store evicted since they were last taken, for CacheStore::take_evicted
+pub struct EvictedKeys<K> {
+    keys: Mutex<Vec<K>>,
+}
+
+impl<K> EvictedKeys<K> {
+    pub fn new() -> Self {
+        Self {
+            keys: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn push(&self, key: K) {
+        let mut keys = self.keys.lock().unwrap();
+        if keys.len() < MAX_EVICTED_KEYS {
+            keys.push(key);
+        }
+    }
+
+    pub fn extend(&self, evicted: impl IntoIterator<Item = K>) {
+        let mut keys = self.keys.lock().unwrap();
+        let room = MAX_EVICTED_KEYS.saturating_sub(keys.len());
+        keys.extend(evicted.into_iter().take(room));
+    }
+
+    pub fn take(&self) -> Vec<K> {
+        std::mem::take(&mut *self.keys.lock().unwrap())
+    }
+}
+
+impl<K> Default for EvictedKeys<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_are_bounded_and_taken_once() {
+        let evicted = EvictedKeys::new();
+        evicted.push(1);
+        evicted.extend(2..=MAX_EVICTED_KEYS + 10);
+        assert_eq!(evicted.take().len(), MAX_EVICTED_KEYS);
+        assert!(evicted.take().is_empty());
+    }
+}
@@ -1,44 +1,148 @@
+use crate::admission::Admission;
+use crate::evicted_keys::EvictedKeys;
 use async_trait::async_trait;
+use carbon::domain::EvictionAlgorithm;
 use carbon::domain::response::{DeleteResponse, ExistsResponse, GetResponse, PutResponse};
 use carbon::ports::CacheStore;
-use foyer::{Cache, CacheBuilder};
+use foyer::{Cache, CacheBuilder, Event, EventListener, LfuConfig, LruConfig, SieveConfig};
 use shared::{Error, Result};
+use std::collections::hash_map::DefaultHasher;
 use std::fmt::Debug;
-use std::hash::Hash;
-use std::sync::Arc;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::instrument;
+
+/// Locks shared by keys hashing alike, held by writes so each put knows whether it created
+/// the key
+const KEY_LOCK_STRIPES: usize = 256;
 
 /// Foyer-based in-memory cache implementation
 pub struct FoyerMemoryCache<K, V>
 where
-    K: Debug + Hash + Eq + Send + Sync + 'static,
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
     V: Debug + Send + Sync + Clone + 'static,
 {
     cache: Arc<Cache<K, V>>,
+    policy: EvictionAlgorithm,
+    admission: Option<Admission>,
+    evicted: Arc<EvictedKeys<K>>,
+    key_locks: Vec<Mutex<()>>,
+}
+
+/// Collects the keys foyer evicts, removals asked for by the store are not evictions
+struct EvictionListener<K, V> {
+    evicted: Arc<EvictedKeys<K>>,
+    _values: PhantomData<fn() -> V>,
+}
+
+impl<K, V> EventListener for EvictionListener<K, V>
+where
+    K: foyer::Key + Clone,
+    V: foyer::Value,
+{
+    type Key = K;
+    type Value = V;
+
+    fn on_leave(&self, reason: Event, key: &K, _value: &V) {
+        if matches!(reason, Event::Evict) {
+            self.evicted.push(key.clone());
+        }
+    }
+}
+
+fn eviction_listener<K, V>(
+    evicted: &Arc<EvictedKeys<K>>,
+) -> Arc<dyn EventListener<Key = K, Value = V>>
+where
+    K: foyer::Key + Clone,
+    V: foyer::Value,
+{
+    Arc::new(EvictionListener {
+        evicted: evicted.clone(),
+        _values: PhantomData,
+    })
 }
 
 impl<K, V> FoyerMemoryCache<K, V>
 where
-    K: Debug + Hash + Eq + Send + Sync + 'static,
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
     V: Debug + Send + Sync + Clone + 'static,
 {
     /// Create a new Foyer in-memory cache with the given memory capacity in bytes
     pub fn new(name: String, mem_bytes: usize) -> Self {
-        let cache = CacheBuilder::new(mem_bytes).with_name(name).build();
+        Self::with_policy(name, mem_bytes, EvictionAlgorithm::Unspecified)
+    }
+
+    /// Create a new Foyer in-memory cache evicting with the given algorithm
+    /// Unspecified falls back to TinyLFU (foyer's w-TinyLFU), as do LFU and ARC, which foyer
+    /// does not offer and PolicyCache implements
+    pub fn with_policy(name: String, mem_bytes: usize, policy: EvictionAlgorithm) -> Self {
+        let evicted = Arc::new(EvictedKeys::new());
+        let builder = CacheBuilder::new(mem_bytes)
+            .with_name(name)
+            .with_event_listener(eviction_listener(&evicted));
+        let (cache, policy) = match policy {
+            EvictionAlgorithm::Lru => (
+                builder.with_eviction_config(LruConfig::default()).build(),
+                EvictionAlgorithm::Lru,
+            ),
+            EvictionAlgorithm::Sieve => (
+                builder.with_eviction_config(SieveConfig::default()).build(),
+                EvictionAlgorithm::Sieve,
+            ),
+            EvictionAlgorithm::Unspecified
+            | EvictionAlgorithm::TinyLfu
+            | EvictionAlgorithm::Lfu
+            | EvictionAlgorithm::Arc => (
+                builder.with_eviction_config(LfuConfig::default()).build(),
+                EvictionAlgorithm::TinyLfu,
+            ),
+        };
 
         Self {
             cache: Arc::new(cache),
+            policy,
+            admission: None,
+            evicted,
+            key_locks: key_locks(),
         }
     }
 
+    /// Foyer's insert does not report what it replaced, so puts check for the key and insert
+    /// it under this lock, as do deletes
+    /// Held for no await, an eviction between the check and the insert can still be missed
+    fn lock_key(&self, key: &K) -> MutexGuard<'_, ()> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let stripe = &self.key_locks[(hasher.finish() as usize) % self.key_locks.len()];
+        stripe.lock().unwrap()
+    }
+
+    /// Builder method to turn away new keys accessed fewer than `threshold` times while the
+    /// cache is full, so keys seen once cannot evict frequently used entries
+    pub fn with_admission(mut self, threshold: u8) -> Self {
+        self.admission = Some(Admission::new(self.cache.capacity() as u64, threshold));
+        self
+    }
+
     /// Create a new FoyerMemoryCache with custom configuration
     /// Note: disk_path parameter is accepted but not used yet (Foyer hybrid cache requires more setup)
     pub fn with_config(mem_bytes: usize, _disk_path: Option<String>) -> Self {
         // TODO: Implement hybrid cache with disk persistence
         // For now, just create an in-memory cache
-        let cache = CacheBuilder::new(mem_bytes).build();
+        let evicted = Arc::new(EvictedKeys::new());
+        let cache = CacheBuilder::new(mem_bytes)
+            .with_event_listener(eviction_listener(&evicted))
+            .with_eviction_config(LfuConfig::default())
+            .build();
 
         Self {
             cache: Arc::new(cache),
+            policy: EvictionAlgorithm::TinyLfu,
+            admission: None,
+            evicted,
+            key_locks: key_locks(),
         }
     }
 }
@@ -46,15 +150,47 @@ where
 #[async_trait]
 impl<K, V> CacheStore<K, V> for FoyerMemoryCache<K, V>
 where
-    K: Debug + Hash + Eq + Send + Sync + 'static,
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
     V: Debug + Send + Sync + Clone + 'static,
 {
+    #[instrument(
+        name = "storage.put",
+        level = "debug",
+        skip_all,
+        fields(backend = "foyer")
+    )]
     async fn put(&self, key: K, val: V) -> Result<PutResponse> {
+        let _guard = self.lock_key(&key);
+        let existed = self.cache.contains(&key);
+
+        // Updates and writes with room to spare never displace anything
+        if let Some(admission) = &self.admission
+            && !existed
+            && self.cache.usage() >= self.cache.capacity()
+            && !admission.admit(&key)
+        {
+            return Ok(PutResponse::not_admitted());
+        }
+
         self.cache.insert(key, val);
-        Ok(PutResponse::new(true, "Successfully inserted"))
+        if existed {
+            Ok(PutResponse::new(false, "Successfully updated"))
+        } else {
+            Ok(PutResponse::new(true, "Successfully inserted"))
+        }
     }
 
+    #[instrument(
+        name = "storage.get",
+        level = "debug",
+        skip_all,
+        fields(backend = "foyer")
+    )]
     async fn get(&self, key: &K) -> Result<GetResponse<V>> {
+        if let Some(admission) = &self.admission {
+            admission.record_access(key);
+        }
+
         match self.cache.get(key) {
             Some(entry) => {
                 let value = entry.value();
@@ -64,24 +200,76 @@ where
         }
     }
 
+    #[instrument(
+        name = "storage.delete",
+        level = "debug",
+        skip_all,
+        fields(backend = "foyer")
+    )]
     async fn delete(&self, key: &K) -> Result<DeleteResponse> {
+        let _guard = self.lock_key(key);
         let existed = self.cache.remove(key).is_some();
         Ok(DeleteResponse::new(existed))
     }
 
+    #[instrument(
+        name = "storage.get_and_delete",
+        level = "debug",
+        skip_all,
+        fields(backend = "foyer")
+    )]
+    async fn get_and_delete(&self, key: &K) -> Result<GetResponse<V>> {
+        let _guard = self.lock_key(key);
+        match self.cache.remove(key) {
+            Some(entry) => Ok(GetResponse::new(true, entry.value().clone())),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    #[instrument(
+        name = "storage.exists",
+        level = "debug",
+        skip_all,
+        fields(backend = "foyer")
+    )]
     async fn exists(&self, key: &K) -> Result<ExistsResponse> {
         Ok(ExistsResponse::new(self.cache.contains(key)))
     }
+
+    fn resize(&self, capacity: u64) -> Result<bool> {
+        self.cache
+            .resize(capacity as usize)
+            .map_err(|e| Error::Internal(format!("Failed to resize cache: {}", e)))?;
+        Ok(true)
+    }
+
+    fn eviction_policy(&self) -> Option<EvictionAlgorithm> {
+        Some(self.policy)
+    }
+
+    fn admission_rejected(&self) -> Option<u64> {
+        self.admission.as_ref().map(Admission::rejected)
+    }
+
+    async fn take_evicted(&self) -> Result<Vec<K>> {
+        Ok(self.evicted.take())
+    }
+}
+
+fn key_locks() -> Vec<Mutex<()>> {
+    (0..KEY_LOCK_STRIPES).map(|_| Mutex::new(())).collect()
 }
 
 impl<K, V> Debug for FoyerMemoryCache<K, V>
 where
-    K: Debug + Hash + Eq + Send + Sync + 'static,
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
     V: Debug + Send + Sync + Clone + 'static,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("FoyerMemoryCache")
             .field("cache", &"<foyer::Cache>")
+            .field("policy", &self.policy)
+            .field("admission", &self.admission.is_some())
             .finish()
     }
 }
@@ -107,6 +295,74 @@ mod tests {
         assert_eq!(get_response.message, value);
     }
 
+    #[tokio::test]
+    async fn test_foyer_cache_resize() {
+        let cache = FoyerMemoryCache::new("test".to_string(), 1024 * 1024);
+        cache.put("key", "value").await.unwrap();
+
+        assert!(cache.resize(2 * 1024 * 1024).unwrap());
+        assert!(cache.get(&"key").await.unwrap().found);
+    }
+
+    #[tokio::test]
+    async fn test_foyer_cache_eviction_policy() {
+        let cache: FoyerMemoryCache<&str, &str> = FoyerMemoryCache::new("test".to_string(), 1024);
+        assert_eq!(cache.eviction_policy(), Some(EvictionAlgorithm::TinyLfu));
+
+        let cache = FoyerMemoryCache::with_policy("test".to_string(), 1024, EvictionAlgorithm::Lru);
+        assert_eq!(cache.eviction_policy(), Some(EvictionAlgorithm::Lru));
+        cache.put("key", "value").await.unwrap();
+        assert!(cache.get(&"key").await.unwrap().found);
+
+        let cache: FoyerMemoryCache<&str, &str> =
+            FoyerMemoryCache::with_policy("test".to_string(), 1024, EvictionAlgorithm::Sieve);
+        assert_eq!(cache.eviction_policy(), Some(EvictionAlgorithm::Sieve));
+    }
+
+    #[tokio::test]
+    async fn test_foyer_cache_admission() {
+        let cache = FoyerMemoryCache::new("test".to_string(), 64).with_admission(2);
+
+        // Admitted while there is room
+        let mut filled = 0;
+        while cache.cache.usage() < cache.cache.capacity() {
+            cache.put(format!("hot{}", filled), "value").await.unwrap();
+            filled += 1;
+        }
+        assert_eq!(cache.admission_rejected(), Some(0));
+
+        // A key seen once is turned away from the full cache
+        let cold = "cold".to_string();
+        let response = cache.put(cold.clone(), "value").await.unwrap();
+        assert!(!response.admitted);
+        assert!(!cache.exists(&cold).await.unwrap().exists);
+        assert_eq!(cache.admission_rejected(), Some(1));
+
+        // Asked for again, it has earned a place
+        assert!(cache.get(&cold).await.is_err());
+        assert!(cache.put(cold, "value").await.unwrap().admitted);
+        assert_eq!(cache.admission_rejected(), Some(1));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_foyer_cache_concurrent_puts_create_once() {
+        let cache = Arc::new(FoyerMemoryCache::new("test".to_string(), 1024 * 1024));
+
+        let puts: Vec<_> = (0..16)
+            .map(|i| {
+                let cache = cache.clone();
+                tokio::spawn(async move { cache.put("key", i).await.unwrap().created })
+            })
+            .collect();
+        let mut created = 0;
+        for put in puts {
+            if put.await.unwrap() {
+                created += 1;
+            }
+        }
+        assert_eq!(created, 1);
+    }
+
     #[tokio::test]
     async fn test_foyer_cache_delete() {
         let cache = FoyerMemoryCache::new("test".to_string(), 1024 * 1024);
@@ -127,6 +383,18 @@ mod tests {
         assert!(matches!(result.unwrap_err(), Error::NotFound));
     }
 
+    #[tokio::test]
+    async fn test_foyer_cache_get_and_delete() {
+        let cache = FoyerMemoryCache::new("test".to_string(), 1024);
+        cache.put("key", "value").await.unwrap();
+
+        assert_eq!(cache.get_and_delete(&"key").await.unwrap().message, "value");
+        assert!(matches!(
+            cache.get_and_delete(&"key").await,
+            Err(Error::NotFound)
+        ));
+    }
+
     #[tokio::test]
     async fn test_foyer_cache_get_nonexistent() {
         let cache: FoyerMemoryCache<&str, &str> =
@@ -153,6 +421,9 @@ mod tests {
         // Get the value - should be the new one
         let get_response = cache.get(&key).await.unwrap();
         assert_eq!(get_response.message, "value2");
+
+        // Only the first write created the key
+        assert!(!cache.put(key, "value3").await.unwrap().created);
     }
 
     #[tokio::test]
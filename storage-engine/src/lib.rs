@@ -1,70 +1,203 @@
+mod admission;
+mod byte_size;
+mod evicted_keys;
 mod foyer_cache;
+mod metadata_tracking;
 mod moka_cache;
+mod policy_cache;
+mod redis_cache;
+mod tiered;
+mod ttl_jitter;
 
+pub use byte_size::ByteSize;
 pub use foyer_cache::FoyerMemoryCache;
+pub use metadata_tracking::MetadataTrackingStore;
 pub use moka_cache::MokaCache;
+pub use policy_cache::PolicyCache;
+pub use redis_cache::RedisCacheStore;
+pub use tiered::TieredStore;
 
-use carbon::domain::CacheConfig;
+use carbon::domain::{
+    CacheConfig, CacheEvictionStrategy, CapacityEstimate, CapacityUnit, EvictionAlgorithm,
+};
 use carbon::ports::{CacheStore, StorageFactory};
 use std::sync::Arc;
 use std::{fmt::Debug, hash::Hash};
 
 /// Unified factory for creating cache instances from configuration
-/// Supports Moka, Foyer Memory, and Foyer Hybrid backends
+/// Supports Moka, Foyer Memory, Foyer Hybrid and Redis backends, optionally behind a Moka L1 tier
 pub struct UnifiedStorageFactory;
 
 impl<K, V> StorageFactory<K, V> for UnifiedStorageFactory
 where
-    K: Debug + Hash + Eq + Send + Sync + 'static,
-    V: Debug + Send + Sync + Clone + 'static,
+    K: AsRef<[u8]> + ByteSize + Debug + Hash + Eq + Clone + Send + Sync + 'static,
+    V: AsRef<[u8]> + From<Vec<u8>> + ByteSize + Debug + Send + Sync + Clone + 'static,
 {
     fn create_from_config(&self, config: &CacheConfig) -> Arc<dyn CacheStore<K, V>> {
-        use carbon::domain::CacheEvictionStrategy;
         use std::time::Duration;
 
-        match config.backend {
+        let default_ttl = config.default_ttl_ms.map(Duration::from_millis);
+        let ttl_jitter_pct = config.ttl_jitter_pct.unwrap_or(0);
+        let store: Arc<dyn CacheStore<K, V>> = match config.backend {
             CacheEvictionStrategy::TimeBound => {
                 // Create Moka cache with optional TTL
-                let default_ttl = config.default_ttl_ms.map(Duration::from_millis);
-
-                // For Moka, mem_bytes could be interpreted as max entries
-                // For simplicity, if mem_bytes > 0, treat as bounded with that many entries
-                // Otherwise, unbounded
-                let max_entries = if config.mem_bytes.is_some() {
-                    config.mem_bytes
-                } else {
-                    None
-                };
-
-                Arc::new(MokaCache::new(
-                    config.name.clone(),
-                    max_entries,
-                    default_ttl,
-                ))
+                // mem_bytes counts entries unless the cache weighs them in bytes,
+                // without it the cache is unbounded
+                let cache = match (config.capacity_unit, config.mem_bytes) {
+                    (CapacityUnit::Bytes, Some(max_bytes)) => MokaCache::new_weighted(
+                        config.name.clone(),
+                        max_bytes,
+                        default_ttl,
+                        config.policy,
+                    ),
+                    (_, max_entries) => MokaCache::new_with_policy(
+                        config.name.clone(),
+                        max_entries,
+                        default_ttl,
+                        config.policy,
+                    ),
+                }
+                .with_ttl_jitter(ttl_jitter_pct);
+                match config.max_pinned_bytes {
+                    Some(max_pinned_bytes) => Arc::new(cache.with_pinning(max_pinned_bytes)),
+                    None => Arc::new(cache),
+                }
+            }
+
+            // Foyer has neither LFU nor ARC, size caches evicting with them are held in a
+            // PolicyCache, storage caches are validated not to use them
+            CacheEvictionStrategy::SizeBounded if in_policy_cache(config) => {
+                // Safety: mem_bytes is validated as required for size and storage caches
+                let capacity = config
+                    .mem_bytes
+                    .expect("mem_bytes is required for SizeBounded cache and should be validated");
+                match config.policy {
+                    EvictionAlgorithm::Arc => Arc::new(PolicyCache::arc(capacity)),
+                    _ => Arc::new(PolicyCache::lfu(capacity)),
+                }
             }
 
             CacheEvictionStrategy::SizeBounded => {
                 // Create Foyer in-memory cache
                 // Safety: mem_bytes is validated as required for SizeBounded caches
-                Arc::new(FoyerMemoryCache::new(
+                let cache = FoyerMemoryCache::with_policy(
                     config.name.clone(),
                     config.mem_bytes.expect(
                         "mem_bytes is required for SizeBounded cache and should be validated",
                     ) as usize,
-                ))
+                    config.policy,
+                );
+                match config.admission_threshold {
+                    Some(threshold) => Arc::new(cache.with_admission(threshold)),
+                    None => Arc::new(cache),
+                }
             }
 
             CacheEvictionStrategy::OverflowToDisk => {
                 // TODO: Implement Foyer hybrid (memory + disk)
                 // For now, fallback to memory-only
                 // Safety: mem_bytes is validated as required for OverflowToDisk caches
-                Arc::new(FoyerMemoryCache::new(
+                let cache = FoyerMemoryCache::with_policy(
                     config.name.clone(),
                     config.mem_bytes.expect(
                         "mem_bytes is required for OverflowToDisk cache and should be validated",
                     ) as usize,
-                ))
+                    config.policy,
+                );
+                match config.admission_threshold {
+                    Some(threshold) => Arc::new(cache.with_admission(threshold)),
+                    None => Arc::new(cache),
+                }
+            }
+
+            CacheEvictionStrategy::Redis => {
+                // Safety: remote_url is validated as required for Redis caches
+                Arc::new(
+                    RedisCacheStore::new(
+                        config.name.clone(),
+                        config.remote_url.as_deref().expect(
+                            "remote_url is required for Redis cache and should be validated",
+                        ),
+                        default_ttl,
+                    )
+                    .with_ttl_jitter(ttl_jitter_pct),
+                )
+            }
+        };
+
+        let store: Arc<dyn CacheStore<K, V>> = match config.l1_entries {
+            Some(l1_entries) => Arc::new(TieredStore::new(
+                store,
+                l1_entries,
+                config
+                    .l1_ttl_ms
+                    .map_or(tiered::DEFAULT_L1_TTL, Duration::from_millis),
+            )),
+            None => store,
+        };
+
+        if !config.track_metadata {
+            return store;
+        }
+        // Only the moka and redis backends expire entries
+        let expires_after = match config.backend {
+            CacheEvictionStrategy::TimeBound | CacheEvictionStrategy::Redis => default_ttl,
+            _ => None,
+        };
+        Arc::new(MetadataTrackingStore::new(store, expires_after))
+    }
+}
+
+/// Whether the cache evicts with an algorithm only PolicyCache implements
+fn in_policy_cache(config: &CacheConfig) -> bool {
+    matches!(
+        config.policy,
+        EvictionAlgorithm::Lfu | EvictionAlgorithm::Arc
+    )
+}
+
+/// Approximate per-entry bookkeeping of each backend (hash table slot, eviction and TTL metadata)
+const MOKA_ENTRY_OVERHEAD_BYTES: u64 = 128;
+const FOYER_ENTRY_OVERHEAD_BYTES: u64 = 64;
+/// Keys are held once for the values and twice more to order them
+const POLICY_ENTRY_OVERHEAD_BYTES: u64 = 160;
+
+impl UnifiedStorageFactory {
+    /// Estimate what a cache built from `config` could hold with entries of the given average size
+    /// Counted in entries, memory use grows with entry size; weighed in bytes, only keys and values
+    /// count towards mem_bytes and the backend's overhead comes on top
+    pub fn estimate(
+        &self,
+        config: &CacheConfig,
+        avg_key_bytes: u64,
+        avg_value_bytes: u64,
+    ) -> CapacityEstimate {
+        let entry_overhead_bytes = match config.backend {
+            CacheEvictionStrategy::TimeBound => MOKA_ENTRY_OVERHEAD_BYTES,
+            CacheEvictionStrategy::SizeBounded if in_policy_cache(config) => {
+                POLICY_ENTRY_OVERHEAD_BYTES
             }
+            CacheEvictionStrategy::SizeBounded | CacheEvictionStrategy::OverflowToDisk => {
+                FOYER_ENTRY_OVERHEAD_BYTES
+            }
+            // Entries are held by the Redis server, nothing is kept locally
+            CacheEvictionStrategy::Redis => 0,
+        };
+        let weighed_bytes = avg_key_bytes + avg_value_bytes;
+        let entry_bytes = entry_overhead_bytes + weighed_bytes;
+        let max_entries = match config.capacity_unit {
+            CapacityUnit::Entries => config.mem_bytes,
+            CapacityUnit::Bytes => config
+                .mem_bytes
+                .map(|max_bytes| max_bytes / weighed_bytes.max(1)),
+        };
+
+        CapacityEstimate {
+            capacity_unit: config.capacity_unit,
+            entry_overhead_bytes,
+            entry_bytes,
+            max_entries,
+            max_memory_bytes: max_entries.map(|entries| entries.saturating_mul(entry_bytes)),
         }
     }
 }
@@ -77,11 +210,356 @@ pub struct FoyerStorageFactory;
 #[allow(deprecated)]
 impl<K, V> StorageFactory<K, V> for FoyerStorageFactory
 where
-    K: Debug + Hash + Eq + Send + Sync + 'static,
-    V: Debug + Send + Sync + Clone + 'static,
+    K: ByteSize + Debug + Hash + Eq + Clone + Send + Sync + 'static,
+    V: ByteSize + Debug + Send + Sync + Clone + 'static,
 {
     fn create_from_config(&self, config: &CacheConfig) -> Arc<dyn CacheStore<K, V>> {
         // Delegate to UnifiedStorageFactory
         UnifiedStorageFactory.create_from_config(config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use carbon::CarbonInstance;
+    use carbon::domain::{
+        CacheConfigUpdate, EventKeyMode, EventPolicy, EvictionAlgorithm, MaterializedView,
+        ViewTransform,
+    };
+    use carbon::encoding::Json;
+    use carbon::events::CacheItemEvent;
+    use carbon::planes::control::CacheManager;
+    use carbon::planes::control::operation::AdminOperations;
+    use carbon::planes::data::CacheOperationsService;
+    use carbon::planes::data::operation::CacheOperations;
+    use std::time::Duration;
+    use tokio::sync::broadcast;
+
+    #[test]
+    fn test_estimate() {
+        let config = CacheConfig::with_backend(
+            "orders",
+            CacheEvictionStrategy::SizeBounded,
+            EvictionAlgorithm::Unspecified,
+            Some(1_000),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+
+        let estimate = UnifiedStorageFactory.estimate(&config, 16, 100);
+        assert_eq!(estimate.entry_bytes, FOYER_ENTRY_OVERHEAD_BYTES + 116);
+        assert_eq!(estimate.max_entries, Some(1_000));
+        assert_eq!(
+            estimate.max_memory_bytes,
+            Some(1_000 * (FOYER_ENTRY_OVERHEAD_BYTES + 116))
+        );
+
+        let config = CacheConfig::with_backend(
+            "sessions",
+            CacheEvictionStrategy::TimeBound,
+            EvictionAlgorithm::Unspecified,
+            Some(11_600),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .with_capacity_unit(CapacityUnit::Bytes);
+
+        let estimate = UnifiedStorageFactory.estimate(&config, 16, 100);
+        assert_eq!(estimate.capacity_unit, CapacityUnit::Bytes);
+        assert_eq!(estimate.max_entries, Some(100));
+        assert_eq!(
+            estimate.max_memory_bytes,
+            Some(100 * (MOKA_ENTRY_OVERHEAD_BYTES + 116))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_embedded_instance() {
+        let carbon = CarbonInstance::new(Arc::new(UnifiedStorageFactory));
+        let config = CacheConfig::with_backend(
+            "sessions",
+            CacheEvictionStrategy::TimeBound,
+            EvictionAlgorithm::Unspecified,
+            Some(100),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+
+        assert!(carbon.create_cache(config).await.unwrap().created);
+        carbon.put("sessions", "abc", "payload").await.unwrap();
+        assert_eq!(
+            carbon.get("sessions", "abc").await.unwrap(),
+            Some(Bytes::from("payload"))
+        );
+        assert!(carbon.delete("sessions", "abc").await.unwrap());
+        assert_eq!(carbon.get("sessions", "abc").await.unwrap(), None);
+        assert_eq!(carbon.list_caches().await.unwrap().caches.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_reaper() {
+        let manager = CacheManager::<Vec<u8>, Bytes>::new();
+        let (events, mut receiver) = broadcast::channel(16);
+        let service = CacheOperationsService::with_event_broadcaster(manager.clone(), events);
+        let config = CacheConfig::with_backend(
+            "sessions",
+            CacheEvictionStrategy::TimeBound,
+            EvictionAlgorithm::Unspecified,
+            Some(100),
+            None,
+            None,
+            Some(50),
+            None,
+            None,
+            None,
+        )
+        .with_reap_interval_ms(Some(100));
+        let store = UnifiedStorageFactory.create_from_config(&config);
+        manager.create_cache(config, store).await.unwrap();
+
+        service
+            .put("sessions", b"abc".to_vec(), Bytes::from("payload"))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(service.reap_expired("sessions").await.unwrap(), 1);
+
+        // Skip past the Added event of the put
+        loop {
+            if let CacheItemEvent::Expired(event) = receiver.recv().await.unwrap() {
+                assert_eq!(event.key, b"abc");
+                break;
+            }
+        }
+
+        let info = manager.describe_cache("sessions").await.unwrap().info;
+        assert_eq!(info.reaped_entries, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_clone_copies_entries() {
+        let manager = CacheManager::<Vec<u8>, Bytes>::new();
+        let config = CacheConfig::with_backend(
+            "orders",
+            CacheEvictionStrategy::TimeBound,
+            EvictionAlgorithm::Unspecified,
+            Some(10_000),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        let store = UnifiedStorageFactory.create_from_config(&config);
+        manager.create_cache(config, store).await.unwrap();
+        let writer = CacheOperationsService::new(manager.clone());
+        for i in 0..1_500u32 {
+            writer
+                .put("orders", i.to_be_bytes().to_vec(), Bytes::from("order"))
+                .await
+                .unwrap();
+        }
+
+        let overrides = CacheConfigUpdate {
+            description: Some("orders for testing".to_string()),
+            ..Default::default()
+        };
+        let config = manager
+            .clone_config("orders", "orders-test", overrides)
+            .await
+            .unwrap();
+        assert_eq!(config.mem_bytes, Some(10_000));
+        let store = UnifiedStorageFactory.create_from_config(&config);
+        manager.create_cache(config, store).await.unwrap();
+
+        let (events, mut receiver) = broadcast::channel(16);
+        let service = CacheOperationsService::with_event_broadcaster(manager.clone(), events);
+        let entries = service.snapshot("orders").await.unwrap();
+        let copied = service
+            .copy_entries("orders", "orders-test", entries)
+            .await
+            .unwrap();
+        assert_eq!(copied, 1_500);
+
+        // Progress after the first batch, then once the copy is done
+        let CacheItemEvent::CloneProgress(first) = receiver.recv().await.unwrap() else {
+            panic!("expected clone progress");
+        };
+        assert_eq!(
+            (first.copied, first.total, first.done),
+            (1_000, 1_500, false)
+        );
+        let CacheItemEvent::CloneProgress(last) = receiver.recv().await.unwrap() else {
+            panic!("expected clone progress");
+        };
+        assert_eq!((last.copied, last.done), (1_500, true));
+
+        let key = 7u32.to_be_bytes().to_vec();
+        assert!(service.get("orders-test", &key).await.unwrap().found);
+    }
+
+    #[tokio::test]
+    async fn test_view_needs_an_empty_cache() {
+        let manager = CacheManager::<Vec<u8>, Bytes>::new();
+        for name in ["users", "user-names"] {
+            let config = CacheConfig::with_backend(
+                name,
+                CacheEvictionStrategy::TimeBound,
+                EvictionAlgorithm::Unspecified,
+                Some(100),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+            let store = UnifiedStorageFactory.create_from_config(&config);
+            manager.create_cache(config, store).await.unwrap();
+        }
+        let service = CacheOperationsService::new(manager.clone());
+        service
+            .put("user-names", b"alice".to_vec(), Bytes::from("kept"))
+            .await
+            .unwrap();
+
+        let view = MaterializedView {
+            cache: "user-names".to_string(),
+            source: "users".to_string(),
+            transform: ViewTransform::Projection {
+                fields: [("name".to_string(), "/name".to_string())].into(),
+            },
+        };
+        assert!(matches!(
+            manager.add_view(view).await,
+            Err(shared::Error::PreconditionFailed(_))
+        ));
+        assert!(
+            service
+                .get("user-names", &b"alice".to_vec())
+                .await
+                .unwrap()
+                .found
+        );
+    }
+
+    #[tokio::test]
+    async fn test_typed_cache_operations() {
+        let manager = CacheManager::<String, Json<Vec<u32>>>::new();
+        let (events, mut receiver) = broadcast::channel(16);
+        let service = CacheOperationsService::with_event_broadcaster(manager.clone(), events);
+        let config = CacheConfig::with_backend(
+            "scores",
+            CacheEvictionStrategy::TimeBound,
+            EvictionAlgorithm::Unspecified,
+            Some(100),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        let store = Arc::new(MokaCache::new("scores".to_string(), Some(100), None));
+        manager.create_cache(config, store).await.unwrap();
+
+        let key = "alice".to_string();
+        let put = service
+            .put("scores", key.clone(), Json(vec![1, 2, 3]))
+            .await
+            .unwrap();
+        assert!(put.created);
+
+        let got = service.get("scores", &key).await.unwrap();
+        assert!(got.found);
+        assert_eq!(got.message, Json(vec![1, 2, 3]));
+
+        // Events carry the byte encoding of the typed key and value
+        match receiver.recv().await.unwrap() {
+            CacheItemEvent::Added(event) => {
+                assert_eq!(event.key, b"alice");
+                assert_eq!(event.value, b"[1,2,3]");
+            }
+            other => panic!("expected an Added event, got {:?}", other),
+        }
+
+        assert!(service.delete("scores", &key).await.unwrap().deleted);
+        assert!(matches!(
+            receiver.recv().await.unwrap(),
+            CacheItemEvent::Deleted(event) if event.key == b"alice"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_event_policy_redacts_events() {
+        let manager = CacheManager::<String, String>::new();
+        let (events, mut receiver) = broadcast::channel(16);
+        let service = CacheOperationsService::with_event_broadcaster(manager.clone(), events);
+        let config = CacheConfig::with_backend(
+            "patients",
+            CacheEvictionStrategy::TimeBound,
+            EvictionAlgorithm::Unspecified,
+            Some(100),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .with_events(EventPolicy {
+            key: EventKeyMode::Hash,
+            max_value_bytes: Some(4),
+            ..Default::default()
+        });
+        let store = Arc::new(MokaCache::new("patients".to_string(), Some(100), None));
+        manager.create_cache(config, store).await.unwrap();
+
+        service
+            .put("patients", "alice".to_string(), "diagnosis".to_string())
+            .await
+            .unwrap();
+        match receiver.recv().await.unwrap() {
+            CacheItemEvent::Added(event) => {
+                assert_eq!(event.key.len(), 64);
+                assert_ne!(event.key, b"alice");
+                assert!(event.value.is_empty());
+                assert_eq!(event.value_size, 9);
+            }
+            other => panic!("expected an Added event, got {:?}", other),
+        }
+
+        // Turning events off takes effect on the next write
+        let update = CacheConfigUpdate {
+            events: Some(EventPolicy {
+                enabled: false,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        manager.update_cache("patients", update).await.unwrap();
+        service
+            .delete("patients", &"alice".to_string())
+            .await
+            .unwrap();
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(broadcast::error::TryRecvError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,322 @@
+use crate::ByteSize;
+use async_trait::async_trait;
+use carbon::domain::response::{DeleteResponse, ExistsResponse, GetResponse, PutResponse};
+use carbon::domain::{BulkEntry, EntryMetadata, EvictionAlgorithm};
+use carbon::ports::CacheStore;
+use dashmap::DashMap;
+use shared::{Error, Result};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+struct EntryStats {
+    created_at_ms: u64,
+    last_accessed_ms: u64,
+    expires_at_ms: Option<u64>,
+    size_bytes: u64,
+    access_count: u64,
+}
+
+/// Store wrapper keeping access statistics for every entry of a cache created with track_metadata
+/// Statistics of entries the backend evicts or expires are dropped when it reports them, or the
+/// next time the key is read by backends that do not
+pub struct MetadataTrackingStore<K, V> {
+    inner: Arc<dyn CacheStore<K, V>>,
+    default_ttl: Option<Duration>,
+    entries: DashMap<K, EntryStats>,
+}
+
+impl<K, V> MetadataTrackingStore<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new(inner: Arc<dyn CacheStore<K, V>>, default_ttl: Option<Duration>) -> Self {
+        Self {
+            inner,
+            default_ttl,
+            entries: DashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl<K, V> CacheStore<K, V> for MetadataTrackingStore<K, V>
+where
+    K: ByteSize + Clone + Hash + Eq + Send + Sync + 'static,
+    V: ByteSize + Send + Sync + 'static,
+{
+    async fn exists(&self, key: &K) -> Result<ExistsResponse> {
+        self.inner.exists(key).await
+    }
+
+    async fn put(&self, key: K, val: V) -> Result<PutResponse> {
+        let size_bytes = (key.byte_size() + val.byte_size()) as u64;
+        let result = self.inner.put(key.clone(), val).await?;
+        if !result.admitted {
+            return Ok(result);
+        }
+
+        let now = now_millis();
+        self.entries.insert(
+            key,
+            EntryStats {
+                created_at_ms: now,
+                last_accessed_ms: now,
+                expires_at_ms: expires_at(now, self.default_ttl),
+                size_bytes,
+                access_count: 0,
+            },
+        );
+        Ok(result)
+    }
+
+    async fn put_batch(&self, entries: Vec<BulkEntry<K, V>>) -> Result<Vec<bool>> {
+        let written: Vec<(K, u64, Option<Duration>)> = entries
+            .iter()
+            .map(|entry| {
+                let size_bytes = (entry.key.byte_size() + entry.value.byte_size()) as u64;
+                (
+                    entry.key.clone(),
+                    size_bytes,
+                    entry.ttl.or(self.default_ttl),
+                )
+            })
+            .collect();
+        let admitted = self.inner.put_batch(entries).await?;
+
+        let now = now_millis();
+        for ((key, size_bytes, ttl), admitted) in written.into_iter().zip(&admitted) {
+            if *admitted {
+                self.entries.insert(
+                    key,
+                    EntryStats {
+                        created_at_ms: now,
+                        last_accessed_ms: now,
+                        expires_at_ms: expires_at(now, ttl),
+                        size_bytes,
+                        access_count: 0,
+                    },
+                );
+            }
+        }
+        Ok(admitted)
+    }
+
+    async fn get(&self, key: &K) -> Result<GetResponse<V>> {
+        match self.inner.get(key).await {
+            Ok(result) => {
+                if let Some(mut stats) = self.entries.get_mut(key) {
+                    stats.last_accessed_ms = now_millis();
+                    stats.access_count += 1;
+                }
+                Ok(result)
+            }
+            Err(Error::NotFound) => {
+                self.entries.remove(key);
+                Err(Error::NotFound)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn delete(&self, key: &K) -> Result<DeleteResponse> {
+        let result = self.inner.delete(key).await?;
+        self.entries.remove(key);
+        Ok(result)
+    }
+
+    async fn get_and_delete(&self, key: &K) -> Result<GetResponse<V>> {
+        let result = self.inner.get_and_delete(key).await;
+        self.entries.remove(key);
+        result
+    }
+
+    async fn get_and_expire(&self, key: &K, ttl: Option<Duration>) -> Result<GetResponse<V>> {
+        match self.inner.get_and_expire(key, ttl).await {
+            Ok(result) => {
+                if let Some(mut stats) = self.entries.get_mut(key) {
+                    let now = now_millis();
+                    stats.last_accessed_ms = now;
+                    stats.access_count += 1;
+                    stats.expires_at_ms = expires_at(now, ttl);
+                }
+                Ok(result)
+            }
+            Err(Error::NotFound) => {
+                self.entries.remove(key);
+                Err(Error::NotFound)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn resize(&self, capacity: u64) -> Result<bool> {
+        self.inner.resize(capacity)
+    }
+
+    fn eviction_policy(&self) -> Option<EvictionAlgorithm> {
+        self.inner.eviction_policy()
+    }
+
+    fn admission_rejected(&self) -> Option<u64> {
+        self.inner.admission_rejected()
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.inner.ping().await
+    }
+
+    async fn entries(&self) -> Result<Vec<(K, V)>> {
+        self.inner.entries().await
+    }
+
+    async fn pin(&self, key: &K) -> Result<bool> {
+        self.inner.pin(key).await
+    }
+
+    async fn unpin(&self, key: &K) -> Result<bool> {
+        self.inner.unpin(key).await
+    }
+
+    fn pinned_bytes(&self) -> Option<u64> {
+        self.inner.pinned_bytes()
+    }
+
+    async fn reap_expired(&self) -> Result<Vec<K>> {
+        let reaped = self.inner.reap_expired().await?;
+        for key in &reaped {
+            self.entries.remove(key);
+        }
+        Ok(reaped)
+    }
+
+    async fn take_evicted(&self) -> Result<Vec<K>> {
+        let evicted = self.inner.take_evicted().await?;
+        for key in &evicted {
+            // Written again since, the statistics are the new entry's
+            if !self.inner.exists(key).await?.exists {
+                self.entries.remove(key);
+            }
+        }
+        Ok(evicted)
+    }
+
+    async fn metadata(&self, key: &K) -> Result<Option<EntryMetadata>> {
+        if !self.inner.exists(key).await?.exists {
+            self.entries.remove(key);
+            return Err(Error::NotFound);
+        }
+
+        let stats = self.entries.get(key).ok_or(Error::NotFound)?;
+        let now = now_millis();
+        let ttl_remaining_ms = stats
+            .expires_at_ms
+            .map(|expires_at_ms| expires_at_ms.saturating_sub(now));
+
+        Ok(Some(EntryMetadata {
+            created_at_ms: stats.created_at_ms,
+            last_accessed_ms: stats.last_accessed_ms,
+            ttl_remaining_ms,
+            size_bytes: stats.size_bytes,
+            access_count: stats.access_count,
+        }))
+    }
+}
+
+impl<K, V> Debug for MetadataTrackingStore<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetadataTrackingStore")
+            .field("tracked_entries", &self.entries.len())
+            .field("default_ttl", &self.default_ttl)
+            .finish()
+    }
+}
+
+fn expires_at(now_ms: u64, ttl: Option<Duration>) -> Option<u64> {
+    ttl.map(|ttl| now_ms.saturating_add(ttl.as_millis() as u64))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MokaCache;
+
+    #[tokio::test]
+    async fn test_metadata_tracks_reads() {
+        let inner: Arc<dyn CacheStore<&str, &str>> =
+            Arc::new(MokaCache::new("test".to_string(), Some(100), None));
+        let store = MetadataTrackingStore::new(inner, Some(Duration::from_secs(60)));
+
+        store.put("key", "value").await.unwrap();
+        store.get(&"key").await.unwrap();
+        store.get(&"key").await.unwrap();
+
+        let metadata = store.metadata(&"key").await.unwrap().unwrap();
+        assert_eq!(metadata.access_count, 2);
+        assert_eq!(metadata.size_bytes, 8);
+        assert!(metadata.last_accessed_ms >= metadata.created_at_ms);
+        assert!(metadata.ttl_remaining_ms.unwrap() <= 60_000);
+
+        // Overwriting starts the statistics over
+        store.put("key", "other").await.unwrap();
+        assert_eq!(
+            store.metadata(&"key").await.unwrap().unwrap().access_count,
+            0
+        );
+
+        store.delete(&"key").await.unwrap();
+        assert!(matches!(store.metadata(&"key").await, Err(Error::NotFound)));
+    }
+
+    /// Moka store reporting every key as evicted without dropping any
+    struct ReportsEvictions(MokaCache<&'static str, &'static str>);
+
+    #[async_trait]
+    impl CacheStore<&'static str, &'static str> for ReportsEvictions {
+        async fn exists(&self, key: &&'static str) -> Result<ExistsResponse> {
+            self.0.exists(key).await
+        }
+
+        async fn put(&self, key: &'static str, val: &'static str) -> Result<PutResponse> {
+            self.0.put(key, val).await
+        }
+
+        async fn get(&self, key: &&'static str) -> Result<GetResponse<&'static str>> {
+            self.0.get(key).await
+        }
+
+        async fn delete(&self, key: &&'static str) -> Result<DeleteResponse> {
+            self.0.delete(key).await
+        }
+
+        async fn get_and_delete(&self, key: &&'static str) -> Result<GetResponse<&'static str>> {
+            self.0.get_and_delete(key).await
+        }
+
+        async fn take_evicted(&self) -> Result<Vec<&'static str>> {
+            self.0.delete(&"gone").await?;
+            Ok(vec!["gone", "kept"])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evicted_entries_lose_their_statistics() {
+        let inner = ReportsEvictions(MokaCache::new("test".to_string(), None, None));
+        let store = MetadataTrackingStore::new(Arc::new(inner), None);
+        store.put("gone", "value").await.unwrap();
+        store.put("kept", "value").await.unwrap();
+
+        assert_eq!(store.take_evicted().await.unwrap().len(), 2);
+        assert!(!store.entries.contains_key("gone"));
+        // Still in the store, written again after its eviction
+        assert!(store.entries.contains_key("kept"));
+    }
+}
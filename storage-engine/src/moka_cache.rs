@@ -1,69 +1,305 @@
+use crate::ByteSize;
+use crate::evicted_keys::EvictedKeys;
+use crate::ttl_jitter::jitter;
 use async_trait::async_trait;
 use carbon::domain::response::{DeleteResponse, ExistsResponse, GetResponse, PutResponse};
+use carbon::domain::{BulkEntry, EvictionAlgorithm};
 use carbon::ports::CacheStore;
-use moka::future::Cache;
+use dashmap::DashMap;
+use moka::Expiry;
+use moka::future::{Cache, CacheBuilder};
+use moka::notification::RemovalCause;
+use moka::ops::compute::{CompResult, Op};
+use moka::policy::EvictionPolicy;
 use shared::{Error, Result};
 use std::fmt::Debug;
 use std::hash::Hash;
-use std::time::Duration;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::instrument;
 
 /// Moka-based cache implementation with TTL support
 /// Provides lock-free, concurrent cache with optional size bounds and TTL
 pub struct MokaCache<K, V>
 where
-    K: Debug + Hash + Eq + Send + Sync + 'static,
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
     V: Debug + Clone + Send + Sync + 'static,
 {
     cache: Cache<K, V>,
+    policy: EvictionAlgorithm,
+    ttl_overrides: Arc<DashMap<K, Option<Duration>>>,
+    ttl_jitter_pct: Arc<AtomicU8>,
+    expired_keys: ExpiredKeys<K>,
+    evicted_keys: Arc<EvictedKeys<K>>,
+    /// Set for caches created with max_pinned_bytes
+    pinned: Option<Pinned<K, V>>,
+}
+
+/// Pinned entries, held outside moka so eviction never picks them and kept until unpinned
+/// Changes go through moka's lock on the key, and while an entry is unpinned it is briefly in
+/// both, so moka is read first
+struct Pinned<K, V> {
+    entries: DashMap<K, V>,
+    bytes: AtomicU64,
+    max_bytes: u64,
+    weigh: fn(&K, &V) -> u64,
+}
+
+impl<K: Hash + Eq + Clone, V> Pinned<K, V> {
+    /// Pin `value` under `key`, replacing a pinned value, refused past max_bytes
+    fn store(&self, key: &K, value: V) -> Result<()> {
+        let old = self
+            .entries
+            .get(key)
+            .map_or(0, |entry| (self.weigh)(key, entry.value()));
+        let new = (self.weigh)(key, &value);
+        self.bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                let used = used.saturating_sub(old) + new;
+                (new <= old || used <= self.max_bytes).then_some(used)
+            })
+            .map_err(|used| {
+                Error::QuotaExceeded(format!(
+                    "pinning {} bytes would exceed max_pinned_bytes of {}, {} bytes are pinned",
+                    new - old,
+                    self.max_bytes,
+                    used
+                ))
+            })?;
+        self.entries.insert(key.clone(), value);
+        Ok(())
+    }
+
+    fn remove(&self, key: &K) -> Option<V> {
+        let (key, value) = self.entries.remove(key)?;
+        self.bytes
+            .fetch_sub((self.weigh)(&key, &value), Ordering::Relaxed);
+        Some(value)
+    }
+}
+
+/// Keys moka removed on expiry since the last reap
+/// Stays None until a reaper first asks, so caches without one collect nothing
+type ExpiredKeys<K> = Arc<Mutex<Option<Vec<K>>>>;
+
+/// Expires entries after the cache's default TTL, unless GETEX or a bulk load gave an entry its own
+/// They record the TTL and then write the entry, which consumes the override here
+struct EntryExpiry<K> {
+    default_ttl: Option<Duration>,
+    ttl_overrides: Arc<DashMap<K, Option<Duration>>>,
+    ttl_jitter_pct: Arc<AtomicU8>,
+}
+
+impl<K: Hash + Eq> EntryExpiry<K> {
+    fn ttl(&self, key: &K) -> Option<Duration> {
+        let ttl = match self.ttl_overrides.remove(key) {
+            Some((_, ttl)) => ttl,
+            None => self.default_ttl,
+        };
+        ttl.map(|ttl| jitter(ttl, self.ttl_jitter_pct.load(Ordering::Relaxed)))
+    }
+}
+
+impl<K, V> Expiry<K, V> for EntryExpiry<K>
+where
+    K: Hash + Eq,
+{
+    fn expire_after_create(&self, key: &K, _value: &V, _created_at: Instant) -> Option<Duration> {
+        self.ttl(key)
+    }
+
+    fn expire_after_update(
+        &self,
+        key: &K,
+        _value: &V,
+        _updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        // Any other write starts the default TTL again
+        self.ttl(key)
+    }
 }
 
 /// Factory methods for MokaCache
 impl<K, V> MokaCache<K, V>
 where
-    K: Debug + Hash + Eq + Send + Sync + 'static,
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
     V: Debug + Clone + Send + Sync + 'static,
 {
     /// Create a new unbounded Moka cache with optional default TTL
     pub fn new_unbounded(default_ttl: Option<Duration>) -> Self {
-        let mut builder = Cache::builder();
-
-        if let Some(ttl) = default_ttl {
-            builder = builder.time_to_live(ttl);
-        }
-
-        Self {
-            cache: builder.build(),
-        }
+        Self::from_builder(
+            Cache::builder(),
+            None,
+            default_ttl,
+            EvictionAlgorithm::Unspecified,
+        )
     }
 
     /// Create a new bounded Moka cache with max entries and optional default TTL
     pub fn new_bounded(max_entries: u64, default_ttl: Option<Duration>) -> Self {
-        let mut builder = Cache::builder().max_capacity(max_entries);
-
-        if let Some(ttl) = default_ttl {
-            builder = builder.time_to_live(ttl);
-        }
-
-        Self {
-            cache: builder.build(),
-        }
+        Self::from_builder(
+            Cache::builder(),
+            Some(max_entries),
+            default_ttl,
+            EvictionAlgorithm::Unspecified,
+        )
     }
 
     /// Create a Moka cache from name and optional capacity
     /// Used for compatibility with factory pattern
     pub fn new(name: String, max_entries: Option<u64>, default_ttl: Option<Duration>) -> Self {
-        let mut builder = Cache::builder().name(&name);
+        Self::new_with_policy(
+            name,
+            max_entries,
+            default_ttl,
+            EvictionAlgorithm::Unspecified,
+        )
+    }
 
-        if let Some(capacity) = max_entries {
-            builder = builder.max_capacity(capacity);
-        }
+    /// Create a Moka cache evicting with the given algorithm
+    /// Moka only offers LRU and TinyLFU, anything else falls back to TinyLFU, the closest to LFU
+    /// and ARC
+    pub fn new_with_policy(
+        name: String,
+        max_entries: Option<u64>,
+        default_ttl: Option<Duration>,
+        policy: EvictionAlgorithm,
+    ) -> Self {
+        Self::from_builder(
+            Cache::builder().name(&name),
+            max_entries,
+            default_ttl,
+            policy,
+        )
+    }
 
-        if let Some(ttl) = default_ttl {
-            builder = builder.time_to_live(ttl);
+    fn from_builder(
+        builder: CacheBuilder<K, V, Cache<K, V>>,
+        max_capacity: Option<u64>,
+        default_ttl: Option<Duration>,
+        policy: EvictionAlgorithm,
+    ) -> Self {
+        let (eviction_policy, policy) = match policy {
+            EvictionAlgorithm::Lru => (EvictionPolicy::lru(), EvictionAlgorithm::Lru),
+            EvictionAlgorithm::Unspecified
+            | EvictionAlgorithm::TinyLfu
+            | EvictionAlgorithm::Sieve
+            | EvictionAlgorithm::Lfu
+            | EvictionAlgorithm::Arc => (EvictionPolicy::tiny_lfu(), EvictionAlgorithm::TinyLfu),
+        };
+        let ttl_overrides = Arc::new(DashMap::new());
+        let ttl_jitter_pct = Arc::new(AtomicU8::new(0));
+        let expired_keys: ExpiredKeys<K> = Arc::new(Mutex::new(None));
+        let evicted_keys = Arc::new(EvictedKeys::new());
+        let listener_keys = expired_keys.clone();
+        let listener_evicted = evicted_keys.clone();
+        let mut builder = builder
+            .eviction_policy(eviction_policy)
+            .expire_after(EntryExpiry {
+                default_ttl,
+                ttl_overrides: ttl_overrides.clone(),
+                ttl_jitter_pct: ttl_jitter_pct.clone(),
+            })
+            // Expired keys go to the reaper once there is one, the rest to take_evicted
+            .eviction_listener(
+                move |key: Arc<K>, _value: V, cause: RemovalCause| match cause {
+                    RemovalCause::Expired => match listener_keys.lock().unwrap().as_mut() {
+                        Some(keys) => keys.push(K::clone(&key)),
+                        None => listener_evicted.push(K::clone(&key)),
+                    },
+                    RemovalCause::Size => listener_evicted.push(K::clone(&key)),
+                    RemovalCause::Explicit | RemovalCause::Replaced => {}
+                },
+            );
+
+        if let Some(capacity) = max_capacity {
+            builder = builder.max_capacity(capacity);
         }
 
         Self {
             cache: builder.build(),
+            policy,
+            ttl_overrides,
+            ttl_jitter_pct,
+            expired_keys,
+            evicted_keys,
+            pinned: None,
+        }
+    }
+
+    /// Builder method to shorten every TTL the cache sets by a random share of up to `pct` percent
+    pub fn with_ttl_jitter(self, pct: u8) -> Self {
+        self.ttl_jitter_pct.store(pct, Ordering::Relaxed);
+        self
+    }
+}
+
+impl<K, V> MokaCache<K, V>
+where
+    K: ByteSize + Debug + Hash + Eq + Clone + Send + Sync + 'static,
+    V: ByteSize + Debug + Clone + Send + Sync + 'static,
+{
+    /// Create a Moka cache bounded by the summed size of its keys and values
+    pub fn new_weighted(
+        name: String,
+        max_bytes: u64,
+        default_ttl: Option<Duration>,
+        policy: EvictionAlgorithm,
+    ) -> Self {
+        // Moka weighs entries in u32, larger entries saturate
+        let builder = Cache::builder().name(&name).weigher(|key: &K, value: &V| {
+            u32::try_from(key.byte_size() + value.byte_size()).unwrap_or(u32::MAX)
+        });
+        Self::from_builder(builder, Some(max_bytes), default_ttl, policy)
+    }
+
+    /// Builder method to let up to `max_bytes` of keys and values be pinned, on top of the
+    /// cache's capacity
+    pub fn with_pinning(mut self, max_bytes: u64) -> Self {
+        self.pinned = Some(Pinned {
+            entries: DashMap::new(),
+            bytes: AtomicU64::new(0),
+            max_bytes,
+            weigh: |key: &K, value: &V| (key.byte_size() + value.byte_size()) as u64,
+        });
+        self
+    }
+}
+
+impl<K, V> MokaCache<K, V>
+where
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
+    V: Debug + Clone + Send + Sync + 'static,
+{
+    fn pinned_value(&self, key: &K) -> Option<V> {
+        let pinned = self.pinned.as_ref()?;
+        pinned.entries.get(key).map(|entry| entry.value().clone())
+    }
+
+    /// Remove an entry wherever it lives, returning its value
+    async fn remove(&self, key: &K) -> Option<V> {
+        let Some(pinned) = &self.pinned else {
+            return self.cache.remove(key).await;
+        };
+
+        let mut unpinned = None;
+        let result = self
+            .cache
+            .entry_by_ref(key)
+            .and_compute_with(|entry| {
+                unpinned = pinned.remove(key);
+                std::future::ready(match entry {
+                    Some(_) => Op::Remove,
+                    None => Op::Nop,
+                })
+            })
+            .await;
+
+        match result {
+            CompResult::Removed(entry) => Some(entry.into_value()),
+            _ => unpinned,
         }
     }
 }
@@ -72,41 +308,328 @@ where
 #[async_trait]
 impl<K, V> CacheStore<K, V> for MokaCache<K, V>
 where
-    K: Debug + Hash + Eq + Send + Sync,
+    K: Debug + Hash + Eq + Clone + Send + Sync,
     V: Debug + Clone + Send + Sync,
 {
+    #[instrument(
+        name = "storage.put",
+        level = "debug",
+        skip_all,
+        fields(backend = "moka")
+    )]
     async fn put(&self, key: K, val: V) -> Result<PutResponse> {
-        self.cache.insert(key, val).await;
-        Ok(PutResponse::new(true, "Successfully inserted"))
+        let Some(pinned) = &self.pinned else {
+            // Upsert holds the key, so the write and what it replaced are seen together
+            let entry = self
+                .cache
+                .entry(key)
+                .and_upsert_with(|_| std::future::ready(val))
+                .await;
+
+            return if entry.is_old_value_replaced() {
+                Ok(PutResponse::new(false, "Successfully updated"))
+            } else {
+                Ok(PutResponse::new(true, "Successfully inserted"))
+            };
+        };
+
+        // A pinned key is written in place and stays pinned
+        let mut pinned_write = None;
+        let result = self
+            .cache
+            .entry_by_ref(&key)
+            .and_compute_with(|entry| {
+                let op = match entry {
+                    None if pinned.entries.contains_key(&key) => {
+                        self.ttl_overrides.remove(&key);
+                        pinned_write = Some(pinned.store(&key, val));
+                        Op::Nop
+                    }
+                    _ => Op::Put(val),
+                };
+                std::future::ready(op)
+            })
+            .await;
+
+        match (pinned_write, result) {
+            (Some(written), _) => {
+                written?;
+                Ok(PutResponse::new(false, "Successfully updated"))
+            }
+            (None, CompResult::ReplacedWith(_)) => {
+                Ok(PutResponse::new(false, "Successfully updated"))
+            }
+            (None, _) => Ok(PutResponse::new(true, "Successfully inserted")),
+        }
     }
 
+    #[instrument(
+        name = "storage.get",
+        level = "debug",
+        skip_all,
+        fields(backend = "moka")
+    )]
     async fn get(&self, key: &K) -> Result<GetResponse<V>> {
         match self.cache.get(key).await {
             Some(value) => Ok(GetResponse::new(true, value)),
-            None => Err(Error::NotFound), // Either doesn't exist or TTL expired
+            None => match self.pinned_value(key) {
+                Some(value) => Ok(GetResponse::new(true, value)),
+                None => Err(Error::NotFound), // Either doesn't exist or TTL expired
+            },
         }
     }
 
+    #[instrument(
+        name = "storage.delete",
+        level = "debug",
+        skip_all,
+        fields(backend = "moka")
+    )]
     async fn delete(&self, key: &K) -> Result<DeleteResponse> {
-        let existed = self.cache.remove(key).await.is_some();
+        let existed = self.remove(key).await.is_some();
         Ok(DeleteResponse::new(existed))
     }
 
+    #[instrument(
+        name = "storage.get_and_delete",
+        level = "debug",
+        skip_all,
+        fields(backend = "moka")
+    )]
+    async fn get_and_delete(&self, key: &K) -> Result<GetResponse<V>> {
+        match self.remove(key).await {
+            Some(value) => Ok(GetResponse::new(true, value)),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    #[instrument(
+        name = "storage.put_batch",
+        level = "debug",
+        skip_all,
+        fields(backend = "moka", entries = entries.len())
+    )]
+    async fn put_batch(&self, entries: Vec<BulkEntry<K, V>>) -> Result<Vec<bool>> {
+        for entry in &entries {
+            if let Some(ttl) = entry.ttl {
+                self.ttl_overrides.insert(entry.key.clone(), Some(ttl));
+            }
+        }
+
+        if self.pinned.is_none() {
+            let admitted = vec![true; entries.len()];
+            for entry in entries {
+                self.cache.insert(entry.key, entry.value).await;
+            }
+            return Ok(admitted);
+        }
+
+        // Pinned keys are written in place, those that no longer fit are not admitted
+        let mut admitted = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match self.put(entry.key, entry.value).await {
+                Ok(_) => admitted.push(true),
+                Err(Error::QuotaExceeded(_)) => admitted.push(false),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(admitted)
+    }
+
+    #[instrument(
+        name = "storage.get_and_expire",
+        level = "debug",
+        skip_all,
+        fields(backend = "moka")
+    )]
+    async fn get_and_expire(&self, key: &K, ttl: Option<Duration>) -> Result<GetResponse<V>> {
+        // Rewrite the entry with its own value so the expiry picks up the new TTL,
+        // compute holds the key so no other write lands in between
+        let result = self
+            .cache
+            .entry_by_ref(key)
+            .and_compute_with(|entry| {
+                let op = match entry {
+                    Some(entry) => {
+                        self.ttl_overrides.insert(key.clone(), ttl);
+                        Op::Put(entry.into_value())
+                    }
+                    None => Op::Nop,
+                };
+                std::future::ready(op)
+            })
+            .await;
+
+        match result {
+            CompResult::ReplacedWith(entry) => Ok(GetResponse::new(true, entry.into_value())),
+            _ if self.pinned_value(key).is_some() => Err(Error::InvalidValue(
+                "pinned entries do not expire, unpin the key first".to_string(),
+            )),
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    #[instrument(
+        name = "storage.exists",
+        level = "debug",
+        skip_all,
+        fields(backend = "moka")
+    )]
     async fn exists(&self, key: &K) -> Result<ExistsResponse> {
-        Ok(ExistsResponse::new(self.cache.contains_key(key)))
+        let exists = self.cache.contains_key(key) || self.pinned_value(key).is_some();
+        Ok(ExistsResponse::new(exists))
+    }
+
+    fn eviction_policy(&self) -> Option<EvictionAlgorithm> {
+        Some(self.policy)
+    }
+
+    async fn entries(&self) -> Result<Vec<(K, V)>> {
+        let mut entries: Vec<(K, V)> = self
+            .cache
+            .iter()
+            .map(|(key, value)| (K::clone(&key), value))
+            .collect();
+        if let Some(pinned) = &self.pinned {
+            entries.extend(
+                pinned
+                    .entries
+                    .iter()
+                    .filter(|entry| !self.cache.contains_key(entry.key()))
+                    .map(|entry| (entry.key().clone(), entry.value().clone())),
+            );
+        }
+        Ok(entries)
+    }
+
+    #[instrument(
+        name = "storage.pin",
+        level = "debug",
+        skip_all,
+        fields(backend = "moka")
+    )]
+    async fn pin(&self, key: &K) -> Result<bool> {
+        let Some(pinned) = &self.pinned else {
+            return Err(Error::InvalidValue(
+                "cache does not allow pinning entries, it needs max_pinned_bytes".to_string(),
+            ));
+        };
+
+        // The entry leaves moka only once it is pinned, so readers always find it
+        let mut outcome = Err(Error::NotFound);
+        self.cache
+            .entry_by_ref(key)
+            .and_compute_with(|entry| {
+                let op = match entry {
+                    Some(entry) => {
+                        outcome = pinned.store(key, entry.into_value()).map(|()| true);
+                        match outcome {
+                            Ok(_) => Op::Remove,
+                            Err(_) => Op::Nop,
+                        }
+                    }
+                    None if pinned.entries.contains_key(key) => {
+                        outcome = Ok(false);
+                        Op::Nop
+                    }
+                    None => Op::Nop,
+                };
+                std::future::ready(op)
+            })
+            .await;
+        outcome
+    }
+
+    #[instrument(
+        name = "storage.unpin",
+        level = "debug",
+        skip_all,
+        fields(backend = "moka")
+    )]
+    async fn unpin(&self, key: &K) -> Result<bool> {
+        let Some(pinned) = &self.pinned else {
+            return Err(Error::InvalidValue(
+                "cache does not allow pinning entries, it needs max_pinned_bytes".to_string(),
+            ));
+        };
+
+        // Back into moka with the cache's default TTL, then out of the pinned entries unless
+        // the key was pinned again in between
+        let result = self
+            .cache
+            .entry_by_ref(key)
+            .and_compute_with(|entry| {
+                let op = match (entry, self.pinned_value(key)) {
+                    (None, Some(value)) => Op::Put(value),
+                    _ => Op::Nop,
+                };
+                std::future::ready(op)
+            })
+            .await;
+        if !matches!(result, CompResult::Inserted(_)) {
+            return Ok(false);
+        }
+
+        self.cache
+            .entry_by_ref(key)
+            .and_compute_with(|entry| {
+                if entry.is_some() {
+                    pinned.remove(key);
+                }
+                std::future::ready(Op::Nop)
+            })
+            .await;
+        Ok(true)
+    }
+
+    fn pinned_bytes(&self) -> Option<u64> {
+        let pinned = self.pinned.as_ref()?;
+        Some(pinned.bytes.load(Ordering::Relaxed))
+    }
+
+    #[instrument(
+        name = "storage.reap_expired",
+        level = "debug",
+        skip_all,
+        fields(backend = "moka")
+    )]
+    async fn reap_expired(&self) -> Result<Vec<K>> {
+        {
+            let mut expired_keys = self.expired_keys.lock().unwrap();
+            if expired_keys.is_none() {
+                *expired_keys = Some(Vec::new());
+            }
+        }
+
+        // Housekeeping removes expired entries, passing each to the eviction listener
+        self.cache.run_pending_tasks().await;
+
+        let reaped = self
+            .expired_keys
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default();
+        Ok(reaped)
+    }
+
+    async fn take_evicted(&self) -> Result<Vec<K>> {
+        Ok(self.evicted_keys.take())
     }
 }
 
 /// Debug implementation for MokaCache
 impl<K, V> Debug for MokaCache<K, V>
 where
-    K: Debug + Hash + Eq + Send + Sync,
+    K: Debug + Hash + Eq + Clone + Send + Sync,
     V: Debug + Clone + Send + Sync,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("MokaCache")
             .field("entry_count", &self.cache.entry_count())
             .field("weighted_size", &self.cache.weighted_size())
+            .field("policy", &self.policy)
             .finish()
     }
 }
@@ -131,6 +654,10 @@ mod tests {
         let get_response = cache.get(&key).await.unwrap();
         assert!(get_response.found);
         assert_eq!(get_response.message, value);
+
+        // Writing the key again replaces it
+        let put_response = cache.put(key, value).await.unwrap();
+        assert!(!put_response.created);
     }
 
     #[tokio::test]
@@ -236,4 +763,193 @@ mod tests {
         let entry_count = cache.cache.entry_count();
         assert!(entry_count <= 2, "Cache should have at most 2 entries");
     }
+
+    #[tokio::test]
+    async fn test_moka_cache_eviction_policy() {
+        let cache: MokaCache<&str, &str> =
+            MokaCache::new_with_policy("test".to_string(), Some(10), None, EvictionAlgorithm::Lru);
+        assert_eq!(cache.eviction_policy(), Some(EvictionAlgorithm::Lru));
+
+        // Moka has no SIEVE, it falls back to TinyLFU
+        let cache: MokaCache<&str, &str> = MokaCache::new_with_policy(
+            "test".to_string(),
+            Some(10),
+            None,
+            EvictionAlgorithm::Sieve,
+        );
+        assert_eq!(cache.eviction_policy(), Some(EvictionAlgorithm::TinyLfu));
+    }
+
+    #[tokio::test]
+    async fn test_moka_cache_weighted() {
+        // Room for two 10-byte entries
+        let cache =
+            MokaCache::new_weighted("test".to_string(), 20, None, EvictionAlgorithm::Unspecified);
+
+        cache.put("key1", "value1").await.unwrap();
+        cache.put("key2", "value2").await.unwrap();
+        cache.put("key3", "value3").await.unwrap();
+        cache.cache.run_pending_tasks().await;
+
+        assert!(cache.cache.weighted_size() <= 20);
+        assert!(cache.cache.entry_count() <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_moka_cache_reap_expired() {
+        let cache = MokaCache::new("test".to_string(), None, Some(Duration::from_millis(50)));
+        assert!(cache.reap_expired().await.unwrap().is_empty());
+
+        cache.put("key1", "value1").await.unwrap();
+        cache.put("key2", "value2").await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+
+        let mut reaped = cache.reap_expired().await.unwrap();
+        reaped.sort();
+        assert_eq!(reaped, vec!["key1", "key2"]);
+        assert_eq!(cache.cache.entry_count(), 0);
+
+        // Each key is reported once
+        assert!(cache.reap_expired().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_moka_cache_reports_evictions() {
+        // Without a reaper, expired keys are reported as evicted
+        let cache = MokaCache::new("test".to_string(), None, Some(Duration::from_millis(50)));
+        cache.put("key1", "value1").await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+        cache.cache.run_pending_tasks().await;
+        assert_eq!(cache.take_evicted().await.unwrap(), vec!["key1"]);
+
+        let cache = MokaCache::new("test".to_string(), Some(1), None);
+        cache.put("key1", "value1").await.unwrap();
+        cache.put("key2", "value2").await.unwrap();
+        cache.cache.run_pending_tasks().await;
+        assert_eq!(cache.take_evicted().await.unwrap().len(), 1);
+        assert!(cache.take_evicted().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_moka_cache_get_and_delete() {
+        let cache = MokaCache::new("test".to_string(), None, None);
+        cache.put("key", "value").await.unwrap();
+
+        assert_eq!(cache.get_and_delete(&"key").await.unwrap().message, "value");
+        assert!(matches!(
+            cache.get_and_delete(&"key").await,
+            Err(Error::NotFound)
+        ));
+        assert!(!cache.exists(&"key").await.unwrap().exists);
+    }
+
+    #[tokio::test]
+    async fn test_moka_cache_get_and_expire() {
+        let cache = MokaCache::new("test".to_string(), None, Some(Duration::from_secs(60)));
+        cache.put("key", "value").await.unwrap();
+
+        let response = cache
+            .get_and_expire(&"key", Some(Duration::from_millis(50)))
+            .await
+            .unwrap();
+        assert_eq!(response.message, "value");
+
+        sleep(Duration::from_millis(100)).await;
+        assert!(cache.get(&"key").await.is_err());
+
+        // Without a TTL the entry outlives the cache default
+        let cache = MokaCache::new("test".to_string(), None, Some(Duration::from_millis(50)));
+        cache.put("key", "value").await.unwrap();
+        cache.get_and_expire(&"key", None).await.unwrap();
+
+        sleep(Duration::from_millis(100)).await;
+        assert!(cache.get(&"key").await.unwrap().found);
+
+        assert!(matches!(
+            cache.get_and_expire(&"missing", None).await,
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_moka_cache_put_batch() {
+        let cache = MokaCache::new("test".to_string(), None, Some(Duration::from_secs(60)));
+        let entries = vec![
+            BulkEntry {
+                key: "short",
+                value: "a",
+                ttl: Some(Duration::from_millis(50)),
+            },
+            BulkEntry {
+                key: "long",
+                value: "b",
+                ttl: None,
+            },
+        ];
+        assert_eq!(cache.put_batch(entries).await.unwrap(), vec![true, true]);
+        assert_eq!(cache.get(&"short").await.unwrap().message, "a");
+
+        // Each entry keeps its own TTL, the rest fall back to the cache default
+        sleep(Duration::from_millis(100)).await;
+        assert!(cache.get(&"short").await.is_err());
+        assert_eq!(cache.get(&"long").await.unwrap().message, "b");
+    }
+
+    #[tokio::test]
+    async fn test_moka_cache_pinned_entries() {
+        let cache = MokaCache::new_bounded(2, None).with_pinning(10);
+        cache.put("config", "v1").await.unwrap();
+        assert!(cache.pin(&"config").await.unwrap());
+        assert!(!cache.pin(&"config").await.unwrap());
+        assert!(matches!(cache.pin(&"missing").await, Err(Error::NotFound)));
+
+        // Bulk data evicts itself, never the pinned entry
+        for key in ["a", "b", "c", "d"] {
+            cache.put(key, "bulk").await.unwrap();
+        }
+        cache.cache.run_pending_tasks().await;
+        assert_eq!(cache.get(&"config").await.unwrap().message, "v1");
+
+        // Writes keep the key pinned, as long as pinned entries stay within the cap
+        cache.put("config", "v2").await.unwrap();
+        assert_eq!(cache.pinned_bytes(), Some(8));
+        assert!(matches!(
+            cache.put("config", "too long").await,
+            Err(Error::QuotaExceeded(_))
+        ));
+        assert_eq!(cache.get(&"config").await.unwrap().message, "v2");
+
+        assert!(cache.unpin(&"config").await.unwrap());
+        assert!(!cache.unpin(&"config").await.unwrap());
+        assert_eq!(cache.pinned_bytes(), Some(0));
+        assert_eq!(cache.get(&"config").await.unwrap().message, "v2");
+
+        // Caches without a cap do not pin
+        let cache = MokaCache::new("test".to_string(), None, None);
+        cache.put("config", "v1").await.unwrap();
+        assert!(cache.pin(&"config").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_moka_cache_ttl_jitter() {
+        let cache = MokaCache::new("test".to_string(), None, Some(Duration::from_millis(1_000)))
+            .with_ttl_jitter(50);
+        for key in 0..100u32 {
+            cache.put(key, key).await.unwrap();
+        }
+
+        // Every entry expires within the window, not all at the end of it
+        sleep(Duration::from_millis(900)).await;
+        let mut live = 0;
+        for key in 0..100u32 {
+            if cache.get(&key).await.is_ok() {
+                live += 1;
+            }
+        }
+        assert!(live < 100, "{} entries still live", live);
+        sleep(Duration::from_millis(200)).await;
+        for key in 0..100u32 {
+            assert!(cache.get(&key).await.is_err());
+        }
+    }
 }
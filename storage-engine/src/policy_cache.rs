@@ -0,0 +1,493 @@
+use crate::evicted_keys::EvictedKeys;
+use async_trait::async_trait;
+use carbon::domain::EvictionAlgorithm;
+use carbon::domain::response::{DeleteResponse, ExistsResponse, GetResponse, PutResponse};
+use carbon::ports::CacheStore;
+use shared::{Error, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Mutex;
+use tracing::instrument;
+
+/// In-memory cache bounded by entry count, evicting with LFU or ARC, which neither foyer nor
+/// moka offer
+/// Reads and writes count as uses, `exists` does not
+pub struct PolicyCache<K, V> {
+    state: Mutex<State<K, V>>,
+    policy: EvictionAlgorithm,
+    evicted: EvictedKeys<K>,
+}
+
+struct State<K, V> {
+    values: HashMap<K, V>,
+    capacity: usize,
+    tracker: Tracker<K>,
+}
+
+enum Tracker<K> {
+    Lfu(Lfu<K>),
+    Arc(Adaptive<K>),
+}
+
+impl<K, V> PolicyCache<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Evict the entry used the fewest times, the oldest among equals
+    /// Counts never decay, so keys that were hot once stay until others are used more often
+    pub fn lfu(capacity: u64) -> Self {
+        Self::new(capacity, Tracker::Lfu(Lfu::new()), EvictionAlgorithm::Lfu)
+    }
+
+    /// Adaptive Replacement Cache, splitting the capacity between entries used once and entries
+    /// used again, and remembering as many recently evicted keys to move the split towards
+    /// whichever side evicted keys that were then written again
+    pub fn arc(capacity: u64) -> Self {
+        Self::new(
+            capacity,
+            Tracker::Arc(Adaptive::new()),
+            EvictionAlgorithm::Arc,
+        )
+    }
+
+    fn new(capacity: u64, tracker: Tracker<K>, policy: EvictionAlgorithm) -> Self {
+        Self {
+            state: Mutex::new(State {
+                values: HashMap::new(),
+                capacity: (capacity as usize).max(1),
+                tracker,
+            }),
+            policy,
+            evicted: EvictedKeys::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> Tracker<K> {
+    fn touch(&mut self, key: &K) {
+        match self {
+            Tracker::Lfu(lfu) => lfu.touch(key),
+            Tracker::Arc(arc) => arc.touch(key),
+        }
+    }
+
+    /// Track a new key, returning the keys evicted to make room for it
+    fn insert(&mut self, key: K, capacity: usize) -> Vec<K> {
+        match self {
+            Tracker::Lfu(lfu) => {
+                let evicted = lfu.shrink(capacity - 1);
+                lfu.insert(key);
+                evicted
+            }
+            Tracker::Arc(arc) => arc.insert(key, capacity),
+        }
+    }
+
+    fn remove(&mut self, key: &K) {
+        match self {
+            Tracker::Lfu(lfu) => lfu.remove(key),
+            Tracker::Arc(arc) => arc.remove(key),
+        }
+    }
+
+    /// Evict down to `capacity` entries
+    fn shrink(&mut self, capacity: usize) -> Vec<K> {
+        match self {
+            Tracker::Lfu(lfu) => lfu.shrink(capacity),
+            Tracker::Arc(arc) => arc.shrink(capacity),
+        }
+    }
+}
+
+/// Keys in the order they were last used, least recent first
+struct RecencyList<K> {
+    order: BTreeMap<u64, K>,
+    positions: HashMap<K, u64>,
+    next: u64,
+}
+
+impl<K: Hash + Eq + Clone> RecencyList<K> {
+    fn new() -> Self {
+        Self {
+            order: BTreeMap::new(),
+            positions: HashMap::new(),
+            next: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.positions.contains_key(key)
+    }
+
+    /// List `key` as the most recently used, moving it when it is already listed
+    fn push(&mut self, key: K) {
+        self.remove(&key);
+        self.order.insert(self.next, key.clone());
+        self.positions.insert(key, self.next);
+        self.next += 1;
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        match self.positions.remove(key) {
+            Some(position) => {
+                self.order.remove(&position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn pop_oldest(&mut self) -> Option<K> {
+        let (_, key) = self.order.pop_first()?;
+        self.positions.remove(&key);
+        Some(key)
+    }
+}
+
+/// Use counts of the resident keys, ordered by count and then by last use
+struct Lfu<K> {
+    order: BTreeMap<(u64, u64), K>,
+    uses: HashMap<K, (u64, u64)>,
+    next: u64,
+}
+
+impl<K: Hash + Eq + Clone> Lfu<K> {
+    fn new() -> Self {
+        Self {
+            order: BTreeMap::new(),
+            uses: HashMap::new(),
+            next: 0,
+        }
+    }
+
+    fn touch(&mut self, key: &K) {
+        let Some(slot) = self.uses.get_mut(key) else {
+            return;
+        };
+        if let Some(key) = self.order.remove(slot) {
+            *slot = (slot.0 + 1, self.next);
+            self.order.insert(*slot, key);
+            self.next += 1;
+        }
+    }
+
+    fn insert(&mut self, key: K) {
+        let slot = (1, self.next);
+        self.next += 1;
+        self.order.insert(slot, key.clone());
+        self.uses.insert(key, slot);
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(slot) = self.uses.remove(key) {
+            self.order.remove(&slot);
+        }
+    }
+
+    fn shrink(&mut self, capacity: usize) -> Vec<K> {
+        let mut evicted = Vec::new();
+        while self.uses.len() > capacity {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            self.uses.remove(&key);
+            evicted.push(key);
+        }
+        evicted
+    }
+}
+
+/// ARC as described by Megiddo and Modha: `recent` holds keys used once and `frequent` keys used
+/// again, `recent_ghosts` and `frequent_ghosts` the keys lately evicted from each
+/// `target` is the share of the capacity `recent` aims for
+struct Adaptive<K> {
+    recent: RecencyList<K>,
+    frequent: RecencyList<K>,
+    recent_ghosts: RecencyList<K>,
+    frequent_ghosts: RecencyList<K>,
+    target: usize,
+}
+
+impl<K: Hash + Eq + Clone> Adaptive<K> {
+    fn new() -> Self {
+        Self {
+            recent: RecencyList::new(),
+            frequent: RecencyList::new(),
+            recent_ghosts: RecencyList::new(),
+            frequent_ghosts: RecencyList::new(),
+            target: 0,
+        }
+    }
+
+    fn resident(&self) -> usize {
+        self.recent.len() + self.frequent.len()
+    }
+
+    fn touch(&mut self, key: &K) {
+        if self.recent.remove(key) || self.frequent.contains(key) {
+            self.frequent.push(key.clone());
+        }
+    }
+
+    fn insert(&mut self, key: K, capacity: usize) -> Vec<K> {
+        let mut evicted = Vec::new();
+
+        if self.recent_ghosts.contains(&key) {
+            // Evicted from `recent` too early, give it more room
+            let delta = (self.frequent_ghosts.len() / self.recent_ghosts.len()).max(1);
+            self.target = (self.target + delta).min(capacity);
+            self.recent_ghosts.remove(&key);
+            self.replace(false, capacity, &mut evicted);
+            self.frequent.push(key);
+            return evicted;
+        }
+        if self.frequent_ghosts.contains(&key) {
+            let delta = (self.recent_ghosts.len() / self.frequent_ghosts.len()).max(1);
+            self.target = self.target.saturating_sub(delta);
+            self.frequent_ghosts.remove(&key);
+            self.replace(true, capacity, &mut evicted);
+            self.frequent.push(key);
+            return evicted;
+        }
+
+        if self.recent.len() + self.recent_ghosts.len() >= capacity {
+            if self.recent.len() < capacity {
+                self.recent_ghosts.pop_oldest();
+                self.replace(false, capacity, &mut evicted);
+            } else if let Some(oldest) = self.recent.pop_oldest() {
+                evicted.push(oldest);
+            }
+        } else {
+            let tracked = self.resident() + self.recent_ghosts.len() + self.frequent_ghosts.len();
+            if tracked >= capacity {
+                if tracked >= 2 * capacity {
+                    self.frequent_ghosts.pop_oldest();
+                }
+                self.replace(false, capacity, &mut evicted);
+            }
+        }
+        self.recent.push(key);
+        evicted
+    }
+
+    /// Once the cache is full, evict the least recent entry of `recent` while it is over its
+    /// target, of `frequent` otherwise, remembering the key in the matching ghost list
+    fn replace(&mut self, frequent_ghost_hit: bool, capacity: usize, evicted: &mut Vec<K>) {
+        if self.resident() < capacity {
+            return;
+        }
+
+        let recent_over_target = self.recent.len() > self.target
+            || (frequent_ghost_hit && self.recent.len() == self.target);
+        let from_recent = self.recent.len() > 0 && (recent_over_target || self.frequent.len() == 0);
+        let (list, ghosts) = if from_recent {
+            (&mut self.recent, &mut self.recent_ghosts)
+        } else {
+            (&mut self.frequent, &mut self.frequent_ghosts)
+        };
+        if let Some(key) = list.pop_oldest() {
+            ghosts.push(key.clone());
+            evicted.push(key);
+        }
+    }
+
+    fn remove(&mut self, key: &K) {
+        if !self.recent.remove(key) {
+            self.frequent.remove(key);
+        }
+    }
+
+    fn shrink(&mut self, capacity: usize) -> Vec<K> {
+        let mut evicted = Vec::new();
+        self.target = self.target.min(capacity);
+        while self.resident() > capacity {
+            // Evicts as a full cache would, resident() stays at least capacity
+            self.replace(false, capacity, &mut evicted);
+        }
+        // Ghosts are kept to the capacity for `recent` and twice the capacity overall
+        let recent_ghosts = capacity.saturating_sub(self.recent.len());
+        while self.recent_ghosts.len() > recent_ghosts {
+            self.recent_ghosts.pop_oldest();
+        }
+        let frequent_ghosts =
+            (2 * capacity).saturating_sub(self.resident() + self.recent_ghosts.len());
+        while self.frequent_ghosts.len() > frequent_ghosts {
+            self.frequent_ghosts.pop_oldest();
+        }
+        evicted
+    }
+}
+
+#[async_trait]
+impl<K, V> CacheStore<K, V> for PolicyCache<K, V>
+where
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
+    V: Debug + Clone + Send + Sync + 'static,
+{
+    #[instrument(
+        name = "storage.put",
+        level = "debug",
+        skip_all,
+        fields(backend = "policy")
+    )]
+    async fn put(&self, key: K, val: V) -> Result<PutResponse> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        if let Some(value) = state.values.get_mut(&key) {
+            *value = val;
+            state.tracker.touch(&key);
+            return Ok(PutResponse::new(false, "Successfully updated"));
+        }
+
+        let evicted = state.tracker.insert(key.clone(), state.capacity);
+        for key in &evicted {
+            state.values.remove(key);
+        }
+        self.evicted.extend(evicted);
+        state.values.insert(key, val);
+        Ok(PutResponse::new(true, "Successfully inserted"))
+    }
+
+    #[instrument(
+        name = "storage.get",
+        level = "debug",
+        skip_all,
+        fields(backend = "policy")
+    )]
+    async fn get(&self, key: &K) -> Result<GetResponse<V>> {
+        let mut state = self.state.lock().unwrap();
+        let value = state.values.get(key).cloned().ok_or(Error::NotFound)?;
+        state.tracker.touch(key);
+        Ok(GetResponse::new(true, value))
+    }
+
+    #[instrument(
+        name = "storage.delete",
+        level = "debug",
+        skip_all,
+        fields(backend = "policy")
+    )]
+    async fn delete(&self, key: &K) -> Result<DeleteResponse> {
+        let existed = self.get_and_delete(key).await.is_ok();
+        Ok(DeleteResponse::new(existed))
+    }
+
+    #[instrument(
+        name = "storage.get_and_delete",
+        level = "debug",
+        skip_all,
+        fields(backend = "policy")
+    )]
+    async fn get_and_delete(&self, key: &K) -> Result<GetResponse<V>> {
+        let mut state = self.state.lock().unwrap();
+        let value = state.values.remove(key).ok_or(Error::NotFound)?;
+        state.tracker.remove(key);
+        Ok(GetResponse::new(true, value))
+    }
+
+    #[instrument(
+        name = "storage.exists",
+        level = "debug",
+        skip_all,
+        fields(backend = "policy")
+    )]
+    async fn exists(&self, key: &K) -> Result<ExistsResponse> {
+        let state = self.state.lock().unwrap();
+        Ok(ExistsResponse::new(state.values.contains_key(key)))
+    }
+
+    fn resize(&self, capacity: u64) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        state.capacity = (capacity as usize).max(1);
+        let evicted = state.tracker.shrink(state.capacity);
+        for key in &evicted {
+            state.values.remove(key);
+        }
+        self.evicted.extend(evicted);
+        Ok(true)
+    }
+
+    fn eviction_policy(&self) -> Option<EvictionAlgorithm> {
+        Some(self.policy)
+    }
+
+    async fn take_evicted(&self) -> Result<Vec<K>> {
+        Ok(self.evicted.take())
+    }
+
+    async fn entries(&self) -> Result<Vec<(K, V)>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .values
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+impl<K, V> Debug for PolicyCache<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries = self.state.lock().map(|state| state.values.len()).ok();
+        f.debug_struct("PolicyCache")
+            .field("entries", &entries)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lfu_evicts_least_used() {
+        let cache = PolicyCache::lfu(3);
+        for key in ["hot", "warm", "cold"] {
+            cache.put(key, key).await.unwrap();
+        }
+        for _ in 0..3 {
+            cache.get(&"hot").await.unwrap();
+        }
+        cache.get(&"warm").await.unwrap();
+
+        // The new key takes the place of the one used least
+        cache.put("new", "new").await.unwrap();
+        assert!(cache.get(&"cold").await.is_err());
+        assert!(cache.get(&"hot").await.is_ok());
+        assert!(cache.get(&"warm").await.is_ok());
+        assert_eq!(cache.eviction_policy(), Some(EvictionAlgorithm::Lfu));
+        assert_eq!(cache.take_evicted().await.unwrap(), vec!["cold"]);
+
+        assert!(cache.resize(1).unwrap());
+        assert_eq!(cache.entries().await.unwrap(), vec![("hot", "hot")]);
+        assert_eq!(cache.take_evicted().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_arc_keeps_reused_entries_through_a_scan() {
+        let cache = PolicyCache::arc(4);
+        for key in 0..2u32 {
+            cache.put(key, key).await.unwrap();
+            cache.get(&key).await.unwrap();
+        }
+
+        // Keys used once only displace each other
+        for key in 100..120u32 {
+            cache.put(key, key).await.unwrap();
+        }
+        assert!(cache.get(&0).await.is_ok());
+        assert!(cache.get(&1).await.is_ok());
+        assert_eq!(cache.entries().await.unwrap().len(), 4);
+
+        cache.delete(&0).await.unwrap();
+        assert!(!cache.exists(&0).await.unwrap().exists);
+        assert_eq!(cache.eviction_policy(), Some(EvictionAlgorithm::Arc));
+    }
+}
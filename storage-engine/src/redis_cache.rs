@@ -0,0 +1,276 @@
+use crate::ttl_jitter::jitter;
+use async_trait::async_trait;
+use carbon::domain::response::{DeleteResponse, ExistsResponse, GetResponse, PutResponse};
+use carbon::domain::{BulkEntry, redact_url_credentials};
+use carbon::ports::CacheStore;
+use redis::aio::{ConnectionLike, ConnectionManager};
+use redis::{Client, Cmd, RedisError, Value};
+use shared::{Error, Result};
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tracing::instrument;
+
+/// Store keeping entries in an external Redis or Valkey server
+/// Keys are prefixed with `carbon:{cache name}:` so several caches can share a server,
+/// eviction is left to the server's maxmemory-policy and dropping the cache leaves its keys behind
+pub struct RedisCacheStore<K, V> {
+    name: String,
+    client: std::result::Result<Client, String>,
+    connection: OnceCell<ConnectionManager>,
+    key_prefix: Vec<u8>,
+    default_ttl: Option<Duration>,
+    ttl_jitter_pct: u8,
+    _entries: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> RedisCacheStore<K, V> {
+    /// Connects on first use, a malformed `url` fails every operation rather than the creation
+    pub fn new(name: String, url: &str, default_ttl: Option<Duration>) -> Self {
+        let client = Client::open(url)
+            .map_err(|e| format!("invalid redis url '{}': {}", redact_url_credentials(url), e));
+        Self {
+            key_prefix: format!("carbon:{}:", name).into_bytes(),
+            name,
+            client,
+            connection: OnceCell::new(),
+            default_ttl,
+            ttl_jitter_pct: 0,
+            _entries: PhantomData,
+        }
+    }
+
+    /// Builder method to shorten every TTL the store sets by a random share of up to `pct` percent
+    pub fn with_ttl_jitter(mut self, pct: u8) -> Self {
+        self.ttl_jitter_pct = pct;
+        self
+    }
+
+    /// PX argument of a write with `ttl`
+    fn expiry_millis(&self, ttl: Duration) -> u64 {
+        jitter(ttl, self.ttl_jitter_pct).as_millis().max(1) as u64
+    }
+
+    /// Shared multiplexed connection, reconnected by the manager when it drops
+    async fn connection(&self) -> Result<ConnectionManager> {
+        let client = self
+            .client
+            .as_ref()
+            .map_err(|e| Error::InvalidValue(e.clone()))?;
+        self.connection
+            .get_or_try_init(|| ConnectionManager::new(client.clone()))
+            .await
+            .cloned()
+            .map_err(redis_error)
+    }
+
+    async fn query<T: redis::FromRedisValue>(&self, cmd: &Cmd) -> Result<T> {
+        let mut connection = self.connection().await?;
+        cmd.query_async(&mut connection).await.map_err(redis_error)
+    }
+}
+
+impl<K: AsRef<[u8]>, V> RedisCacheStore<K, V> {
+    fn redis_key(&self, key: &K) -> Vec<u8> {
+        let mut redis_key = self.key_prefix.clone();
+        redis_key.extend_from_slice(key.as_ref());
+        redis_key
+    }
+}
+
+fn redis_error(e: RedisError) -> Error {
+    Error::Internal(format!("redis: {}", e))
+}
+
+fn found<V: From<Vec<u8>>>(value: Option<Vec<u8>>) -> Result<GetResponse<V>> {
+    match value {
+        Some(value) => Ok(GetResponse::new(true, V::from(value))),
+        None => Err(Error::NotFound),
+    }
+}
+
+#[async_trait]
+impl<K, V> CacheStore<K, V> for RedisCacheStore<K, V>
+where
+    K: AsRef<[u8]> + Send + Sync + 'static,
+    V: AsRef<[u8]> + From<Vec<u8>> + Send + Sync + 'static,
+{
+    #[instrument(
+        name = "storage.exists",
+        level = "debug",
+        skip_all,
+        fields(backend = "redis")
+    )]
+    async fn exists(&self, key: &K) -> Result<ExistsResponse> {
+        let count: i64 = self
+            .query(redis::cmd("EXISTS").arg(self.redis_key(key)))
+            .await?;
+        Ok(ExistsResponse::new(count > 0))
+    }
+
+    #[instrument(
+        name = "storage.put",
+        level = "debug",
+        skip_all,
+        fields(backend = "redis")
+    )]
+    async fn put(&self, key: K, val: V) -> Result<PutResponse> {
+        // EXISTS and SET in one MULTI, so the old value never crosses the wire and no other
+        // write slips in between the check and the write
+        let redis_key = self.redis_key(&key);
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .cmd("EXISTS")
+            .arg(&redis_key)
+            .cmd("SET")
+            .arg(&redis_key)
+            .arg(val.as_ref());
+        if let Some(ttl) = self.default_ttl {
+            pipe.arg("PX").arg(self.expiry_millis(ttl));
+        }
+        pipe.ignore();
+
+        let mut connection = self.connection().await?;
+        let (existed,): (i64,) = pipe
+            .query_async(&mut connection)
+            .await
+            .map_err(redis_error)?;
+        match existed {
+            0 => Ok(PutResponse::new(true, "Successfully inserted")),
+            _ => Ok(PutResponse::new(false, "Successfully updated")),
+        }
+    }
+
+    #[instrument(
+        name = "storage.put_batch",
+        level = "debug",
+        skip_all,
+        fields(backend = "redis", entries = entries.len())
+    )]
+    async fn put_batch(&self, entries: Vec<BulkEntry<K, V>>) -> Result<Vec<bool>> {
+        // One pipelined round trip for the whole batch
+        let mut pipe = redis::pipe();
+        for entry in &entries {
+            pipe.cmd("SET")
+                .arg(self.redis_key(&entry.key))
+                .arg(entry.value.as_ref());
+            if let Some(ttl) = entry.ttl.or(self.default_ttl) {
+                pipe.arg("PX").arg(self.expiry_millis(ttl));
+            }
+        }
+
+        // Replies are read one by one, a server at maxmemory with noeviction refuses single
+        // entries with OOM while the rest of the batch goes in
+        let mut connection = self.connection().await?;
+        let replies = connection
+            .req_packed_commands(&pipe, 0, entries.len())
+            .await
+            .map_err(redis_error)?;
+        replies
+            .into_iter()
+            .map(|reply| match reply {
+                Value::ServerError(e) if e.code() == "OOM" => Ok(false),
+                Value::ServerError(e) => Err(redis_error(e.into())),
+                _ => Ok(true),
+            })
+            .collect()
+    }
+
+    #[instrument(
+        name = "storage.get",
+        level = "debug",
+        skip_all,
+        fields(backend = "redis")
+    )]
+    async fn get(&self, key: &K) -> Result<GetResponse<V>> {
+        found(
+            self.query(redis::cmd("GET").arg(self.redis_key(key)))
+                .await?,
+        )
+    }
+
+    #[instrument(
+        name = "storage.delete",
+        level = "debug",
+        skip_all,
+        fields(backend = "redis")
+    )]
+    async fn delete(&self, key: &K) -> Result<DeleteResponse> {
+        let count: i64 = self
+            .query(redis::cmd("DEL").arg(self.redis_key(key)))
+            .await?;
+        Ok(DeleteResponse::new(count > 0))
+    }
+
+    #[instrument(
+        name = "storage.get_and_delete",
+        level = "debug",
+        skip_all,
+        fields(backend = "redis")
+    )]
+    async fn get_and_delete(&self, key: &K) -> Result<GetResponse<V>> {
+        found(
+            self.query(redis::cmd("GETDEL").arg(self.redis_key(key)))
+                .await?,
+        )
+    }
+
+    #[instrument(
+        name = "storage.get_and_expire",
+        level = "debug",
+        skip_all,
+        fields(backend = "redis")
+    )]
+    async fn get_and_expire(&self, key: &K, ttl: Option<Duration>) -> Result<GetResponse<V>> {
+        let mut cmd = redis::cmd("GETEX");
+        cmd.arg(self.redis_key(key));
+        match ttl {
+            Some(ttl) => cmd.arg("PX").arg(self.expiry_millis(ttl)),
+            None => cmd.arg("PERSIST"),
+        };
+        found(self.query(&cmd).await?)
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.query::<String>(&redis::cmd("PING")).await.map(|_| ())
+    }
+}
+
+impl<K, V> Debug for RedisCacheStore<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisCacheStore")
+            .field("name", &self.name)
+            .field("connected", &self.connection.initialized())
+            .field("default_ttl", &self.default_ttl)
+            .field("ttl_jitter_pct", &self.ttl_jitter_pct)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_keys_are_prefixed_with_cache_name() {
+        let store: RedisCacheStore<Vec<u8>, Bytes> =
+            RedisCacheStore::new("orders".to_string(), "redis://localhost:6379", None);
+        assert_eq!(
+            store.redis_key(&b"42".to_vec()),
+            b"carbon:orders:42".to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_url_fails_operations() {
+        let store: RedisCacheStore<Vec<u8>, Bytes> =
+            RedisCacheStore::new("orders".to_string(), "not a url", None);
+        assert!(matches!(
+            store.get(&b"42".to_vec()).await,
+            Err(Error::InvalidValue(_))
+        ));
+        assert!(store.ping().await.is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::MokaCache;
+use async_trait::async_trait;
+use carbon::domain::response::{DeleteResponse, ExistsResponse, GetResponse, PutResponse};
+use carbon::domain::{BulkEntry, EntryMetadata, EvictionAlgorithm};
+use carbon::ports::CacheStore;
+use dashmap::DashMap;
+use shared::Result;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long the L1 tier serves an entry when the cache sets no l1_ttl_ms
+pub const DEFAULT_L1_TTL: Duration = Duration::from_secs(60);
+
+/// Write counters shared by keys hashing alike, a read promotes only if its key saw no write
+const VERSION_STRIPES: usize = 256;
+
+/// Keys with TTLs of their own the store holds before it first sweeps out expired ones
+const MIN_SWEEP_EXPIRING: usize = 1024;
+
+/// Store wrapper keeping the hot entries of a slower backend in an in-process moka tier
+/// Reads served by the backend are promoted into the L1 tier unless a write to the key raced
+/// them, writes go through to the backend before the L1 tier takes them. Changes the backend
+/// sees from elsewhere, such as other processes writing to Redis or the backend evicting, reach
+/// the L1 tier once its TTL passes
+/// Entries with a TTL of their own are only ever served by the backend, so they cannot outlive
+/// it in the L1 tier
+pub struct TieredStore<K, V>
+where
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
+    V: Debug + Clone + Send + Sync + 'static,
+{
+    l1: MokaCache<K, V>,
+    l2: Arc<dyn CacheStore<K, V>>,
+    versions: Vec<AtomicU64>,
+    expiring: DashMap<K, Instant>,
+    sweep_at: AtomicUsize,
+}
+
+impl<K, V> TieredStore<K, V>
+where
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
+    V: Debug + Clone + Send + Sync + 'static,
+{
+    pub fn new(l2: Arc<dyn CacheStore<K, V>>, l1_entries: u64, l1_ttl: Duration) -> Self {
+        Self {
+            l1: MokaCache::new_bounded(l1_entries, Some(l1_ttl)),
+            l2,
+            versions: (0..VERSION_STRIPES).map(|_| AtomicU64::new(0)).collect(),
+            expiring: DashMap::new(),
+            sweep_at: AtomicUsize::new(MIN_SWEEP_EXPIRING),
+        }
+    }
+
+    fn version(&self, key: &K) -> &AtomicU64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.versions[(hasher.finish() as usize) % self.versions.len()]
+    }
+
+    /// Called once the backend has the write, before the L1 tier is brought up to date
+    fn written(&self, key: &K) {
+        self.version(key).fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Drop `key` from the L1 tier after a write to the backend
+    async fn invalidate(&self, key: &K) -> Result<()> {
+        self.written(key);
+        self.l1.delete(key).await.map(|_| ())
+    }
+
+    /// Remember that `key` expires on its own at `ttl`, or forget it when None
+    fn set_own_ttl(&self, key: &K, ttl: Option<Duration>) {
+        match ttl {
+            Some(ttl) => {
+                self.sweep();
+                self.expiring.insert(key.clone(), Instant::now() + ttl);
+            }
+            None => {
+                self.expiring.remove(key);
+            }
+        }
+    }
+
+    fn has_own_ttl(&self, key: &K) -> bool {
+        self.expiring
+            .get(key)
+            .is_some_and(|expires_at| *expires_at > Instant::now())
+    }
+
+    /// Drop passed expiries once the map has doubled since the last sweep
+    fn sweep(&self) {
+        if self.expiring.len() < self.sweep_at.load(Ordering::Relaxed) {
+            return;
+        }
+        let now = Instant::now();
+        self.expiring.retain(|_, expires_at| *expires_at > now);
+        self.sweep_at.store(
+            (self.expiring.len() * 2).max(MIN_SWEEP_EXPIRING),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+#[async_trait]
+impl<K, V> CacheStore<K, V> for TieredStore<K, V>
+where
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
+    V: Debug + Clone + Send + Sync + 'static,
+{
+    async fn exists(&self, key: &K) -> Result<ExistsResponse> {
+        self.l2.exists(key).await
+    }
+
+    async fn put(&self, key: K, val: V) -> Result<PutResponse> {
+        let result = self.l2.put(key.clone(), val.clone()).await?;
+        // A plain write replaces any TTL the entry had of its own
+        self.set_own_ttl(&key, None);
+        if result.admitted {
+            self.written(&key);
+            self.l1.put(key, val).await?;
+        } else {
+            self.invalidate(&key).await?;
+        }
+        Ok(result)
+    }
+
+    async fn put_batch(&self, entries: Vec<BulkEntry<K, V>>) -> Result<Vec<bool>> {
+        // Entries left out of the L1 tier, those with TTLs of their own are kept out of it
+        let keys: Vec<(K, Option<Duration>)> = entries
+            .iter()
+            .map(|entry| (entry.key.clone(), entry.ttl))
+            .collect();
+        let admitted = self.l2.put_batch(entries).await?;
+        for (key, ttl) in &keys {
+            self.set_own_ttl(key, *ttl);
+            self.invalidate(key).await?;
+        }
+        Ok(admitted)
+    }
+
+    async fn get(&self, key: &K) -> Result<GetResponse<V>> {
+        if let Ok(hit) = self.l1.get(key).await {
+            return Ok(hit);
+        }
+        let version = self.version(key).load(Ordering::Acquire);
+        let result = self.l2.get(key).await?;
+        if self.has_own_ttl(key) {
+            return Ok(result);
+        }
+        self.l1.put(key.clone(), result.message.clone()).await?;
+        // A write landing during the read may have been overtaken by the promotion
+        if self.version(key).load(Ordering::Acquire) != version {
+            self.l1.delete(key).await?;
+        }
+        Ok(result)
+    }
+
+    async fn delete(&self, key: &K) -> Result<DeleteResponse> {
+        let result = self.l2.delete(key).await;
+        self.set_own_ttl(key, None);
+        self.invalidate(key).await?;
+        result
+    }
+
+    async fn get_and_delete(&self, key: &K) -> Result<GetResponse<V>> {
+        let result = self.l2.get_and_delete(key).await;
+        self.set_own_ttl(key, None);
+        self.invalidate(key).await?;
+        result
+    }
+
+    async fn get_and_expire(&self, key: &K, ttl: Option<Duration>) -> Result<GetResponse<V>> {
+        let result = self.l2.get_and_expire(key, ttl).await;
+        if result.is_ok() {
+            self.set_own_ttl(key, ttl);
+        }
+        self.invalidate(key).await?;
+        result
+    }
+
+    fn resize(&self, capacity: u64) -> Result<bool> {
+        self.l2.resize(capacity)
+    }
+
+    fn eviction_policy(&self) -> Option<EvictionAlgorithm> {
+        self.l2.eviction_policy()
+    }
+
+    fn admission_rejected(&self) -> Option<u64> {
+        self.l2.admission_rejected()
+    }
+
+    async fn metadata(&self, key: &K) -> Result<Option<EntryMetadata>> {
+        self.l2.metadata(key).await
+    }
+
+    async fn reap_expired(&self) -> Result<Vec<K>> {
+        let reaped = self.l2.reap_expired().await?;
+        for key in &reaped {
+            self.set_own_ttl(key, None);
+            self.invalidate(key).await?;
+        }
+        Ok(reaped)
+    }
+
+    async fn take_evicted(&self) -> Result<Vec<K>> {
+        let evicted = self.l2.take_evicted().await?;
+        for key in &evicted {
+            self.set_own_ttl(key, None);
+            self.invalidate(key).await?;
+        }
+        Ok(evicted)
+    }
+
+    async fn entries(&self) -> Result<Vec<(K, V)>> {
+        self.l2.entries().await
+    }
+
+    async fn pin(&self, key: &K) -> Result<bool> {
+        self.l2.pin(key).await
+    }
+
+    async fn unpin(&self, key: &K) -> Result<bool> {
+        self.l2.unpin(key).await
+    }
+
+    fn pinned_bytes(&self) -> Option<u64> {
+        self.l2.pinned_bytes()
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.l2.ping().await
+    }
+}
+
+impl<K, V> Debug for TieredStore<K, V>
+where
+    K: Debug + Hash + Eq + Clone + Send + Sync + 'static,
+    V: Debug + Clone + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TieredStore")
+            .field("l1", &"<MokaCache>")
+            .field("l2", &"<CacheStore>")
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::Error;
+
+    #[tokio::test]
+    async fn test_promote_on_hit_and_write_through() {
+        let l2: Arc<dyn CacheStore<&str, &str>> =
+            Arc::new(MokaCache::new("l2".to_string(), Some(100), None));
+        let store = TieredStore::new(l2.clone(), 10, DEFAULT_L1_TTL);
+
+        // Entries already in the backend are promoted when read
+        l2.put("key", "value").await.unwrap();
+        assert!(matches!(store.l1.get(&"key").await, Err(Error::NotFound)));
+        assert_eq!(store.get(&"key").await.unwrap().message, "value");
+        assert_eq!(store.l1.get(&"key").await.unwrap().message, "value");
+
+        // Writes reach both tiers
+        assert!(!store.put("key", "other").await.unwrap().created);
+        assert_eq!(l2.get(&"key").await.unwrap().message, "other");
+        assert_eq!(store.l1.get(&"key").await.unwrap().message, "other");
+
+        assert!(store.delete(&"key").await.unwrap().deleted);
+        assert!(matches!(store.l1.get(&"key").await, Err(Error::NotFound)));
+        assert!(matches!(store.get(&"key").await, Err(Error::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_entries_with_own_ttl_stay_in_backend() {
+        let l2: Arc<dyn CacheStore<&str, &str>> =
+            Arc::new(MokaCache::new("l2".to_string(), Some(100), None));
+        let store = TieredStore::new(l2.clone(), 10, DEFAULT_L1_TTL);
+
+        let entry = BulkEntry {
+            key: "key",
+            value: "value",
+            ttl: Some(Duration::from_secs(60)),
+        };
+        assert_eq!(store.put_batch(vec![entry]).await.unwrap(), vec![true]);
+        assert_eq!(store.get(&"key").await.unwrap().message, "value");
+        assert!(matches!(store.l1.get(&"key").await, Err(Error::NotFound)));
+
+        // A plain write drops the TTL, so the entry is promoted again
+        store.put("key", "other").await.unwrap();
+        store.l1.delete(&"key").await.unwrap();
+        assert_eq!(store.get(&"key").await.unwrap().message, "other");
+        assert_eq!(store.l1.get(&"key").await.unwrap().message, "other");
+    }
+}
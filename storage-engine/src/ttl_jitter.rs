@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+/// Shorten `ttl` by a random share of up to `pct` percent, so entries written together with the
+/// same TTL expire over a window instead of in the same instant
+/// The TTL is never lengthened, a configured TTL stays the longest an entry lives
+pub fn jitter(ttl: Duration, pct: u8) -> Duration {
+    if pct == 0 || ttl.is_zero() {
+        return ttl;
+    }
+    let share = rand::random::<f64>() * pct.min(100) as f64 / 100.0;
+    ttl.mul_f64(1.0 - share)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_stays_within_the_window() {
+        let ttl = Duration::from_secs(100);
+        assert_eq!(jitter(ttl, 0), ttl);
+
+        let jittered: Vec<Duration> = (0..1000).map(|_| jitter(ttl, 20)).collect();
+        assert!(
+            jittered
+                .iter()
+                .all(|&t| t <= ttl && t >= Duration::from_secs(80))
+        );
+        // Spread out rather than all the same
+        let min = jittered.iter().min().unwrap();
+        let max = jittered.iter().max().unwrap();
+        assert!(*max - *min > Duration::from_secs(10));
+    }
+}